@@ -0,0 +1,37 @@
+/* C interface to the rt ray tracer. Build with `cargo build --release --features ffi`, then
+ * link target/release/librt.so (librt.dylib, rt.dll) by path: `-lrt` finds the system's POSIX librt */
+#ifndef RT_H
+#define RT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A parsed and validated scene */
+typedef struct RtScene RtScene;
+
+/* Parse a scene file's JSON text (without includes); NULL on errors, see rt_last_error */
+RtScene *rt_scene_from_json(const char *json);
+
+/* Free a scene; NULL is ignored. No render may be using it */
+void rt_scene_free(RtScene *scene);
+
+/* Render as 8-bit RGB, row-major from the top-left pixel, into a buffer of at least
+ * width * height * 3 bytes. threads = 0 uses all cores. Returns 0, or -1 on errors */
+int rt_render_rgb(const RtScene *scene, uint32_t width, uint32_t height, uint32_t samples,
+                  uint32_t threads, uint8_t *buffer, size_t buffer_len);
+
+/* Fraction (0-1) of the scene's current or last render that is done; callable from any thread */
+double rt_render_progress(const RtScene *scene);
+
+/* Message for the last failed call on this thread, or NULL; valid until the next failure */
+const char *rt_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RT_H */
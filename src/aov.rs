@@ -0,0 +1,109 @@
+use std::str::FromStr;
+
+use crate::math::Float;
+use crate::exr::ExrWriter;
+use crate::png::PngWriter;
+use crate::ppm::PpmWriter;
+
+/// Arbitrary output variable: a per-pixel quantity written instead of the shaded image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    Depth, // Distance of the first surface seen, for compositing fog or training depth estimators
+}
+
+impl FromStr for Aov {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "depth" => Ok(Self::Depth),
+            _ => Err(format!("unknown AOV '{}' (expected depth)", s)),
+        }
+    }
+}
+
+/// Depth of the first surface each camera ray hits: along the view direction for perspective cameras,
+/// straight out from the camera otherwise; pixels showing the background are infinitely deep
+pub struct DepthMap {
+    pub width: u32,
+    pub height: u32,
+    depths: Vec<Float>,
+}
+
+impl DepthMap {
+    /// Create a depth map with every pixel showing the background
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            depths: vec![Float::INFINITY; (width * height) as usize],
+        }
+    }
+    
+    pub fn set(&mut self, x: u32, y: u32, depth: Float) {
+        self.depths[(y * self.width + x) as usize] = depth;
+    }
+    
+    pub fn depth(&self, x: u32, y: u32) -> Float {
+        self.depths[(y * self.width + x) as usize]
+    }
+    
+    /// Nearest and furthest depths of any surface in the image, or None if it shows only background
+    pub fn range(&self) -> Option<(Float, Float)> {
+        let mut finite = self.depths.iter().copied().filter(|depth| depth.is_finite());
+        let first = finite.next()?;
+        Some(finite.fold((first, first), |(near, far), depth| (near.min(depth), far.max(depth))))
+    }
+    
+    /// Depths mapped from `near`..`far` to 0..1, clamped (so the background is 1)
+    pub fn normalized(&self, near: Float, far: Float) -> Vec<Float> {
+        let span = (far - near).max(Float::EPSILON);
+        self.depths.iter().map(|depth| ((depth - near) / span).clamp(0.0, 1.0)).collect()
+    }
+    
+    /// 16-bit grayscale PNG of the normalized depths
+    pub fn to_png(&self, near: Float, far: Float) -> PngWriter {
+        let values: Vec<u16> = self.normalized(near, far).iter().map(|value| (value * 65535.0).round() as u16).collect();
+        PngWriter::gray16(self.width, self.height, &values)
+    }
+    
+    /// 8-bit gray PPM of the normalized depths
+    pub fn to_ppm(&self, near: Float, far: Float) -> PpmWriter {
+        let mut writer = PpmWriter::new(self.width, self.height);
+        for value in self.normalized(near, far) {
+            let gray = (value * 255.0).round() as u8;
+            writer.write_pixel(gray, gray, gray);
+        }
+        writer
+    }
+    
+    /// EXR with the depths in its Z channel: metric, or normalized if given a range
+    #[allow(clippy::unnecessary_cast)] // Float is only f64 without the f32 feature
+    pub fn to_exr(&self, range: Option<(Float, Float)>) -> ExrWriter {
+        let values = match range {
+            Some((near, far)) => self.normalized(near, far),
+            None => self.depths.clone(),
+        };
+        ExrWriter::new(self.width, self.height).channel("Z", values.iter().map(|&value| value as f32).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_depth_map() {
+        let mut depths = DepthMap::new(3, 1);
+        depths.set(0, 0, 2.0);
+        depths.set(1, 0, 6.0);
+        assert_eq!(depths.range(), Some((2.0, 6.0)));
+        assert_eq!(depths.normalized(2.0, 6.0), vec![0.0, 1.0, 1.0]);
+        assert_eq!(depths.normalized(4.0, 5.0), vec![0.0, 1.0, 1.0]);
+        assert_eq!(depths.to_ppm(0.0, 8.0).pixels(), &[64, 64, 64, 191, 191, 191, 255, 255, 255]);
+        
+        assert_eq!(DepthMap::new(2, 2).range(), None);
+        assert_eq!("depth".parse(), Ok(Aov::Depth));
+        assert!("normals".parse::<Aov>().is_err());
+    }
+}
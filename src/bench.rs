@@ -0,0 +1,244 @@
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::math::{consts::PI, Float, Vec3};
+use crate::color::Color;
+use crate::camera::Camera;
+use crate::material::Material;
+use crate::shapes::{Sphere, Plane, Cube, Cylinder, Triangle};
+use crate::scene::{Scene, Light, AcceleratorOptions};
+use crate::render::Renderer;
+use crate::stats;
+
+/// Seed for the workload generators, fixed so every run benchmarks the same scenes
+const SEED: u64 = 0x5eed_1234_abcd_0001;
+
+/// A standardized scene for benchmarking
+pub struct Workload {
+    pub name: &'static str,
+    pub description: &'static str,
+    build: fn(Float) -> (Scene, Camera), // Takes the aspect ratio
+}
+
+impl Workload {
+    /// Build the workload's scene and camera
+    pub fn build(&self, aspect_ratio: Float) -> (Scene, Camera) {
+        (self.build)(aspect_ratio)
+    }
+}
+
+/// Timing and counts for one workload, averaged per frame
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkloadResult {
+    pub name: String,
+    pub objects: usize,
+    pub lights: usize,
+    pub frames: u32,
+    pub ms_per_frame: f64,
+    pub ms_fastest: f64,
+    pub rays_per_frame: u64,
+    pub rays_per_sec: f64,
+    pub intersection_tests_per_frame: u64,
+}
+
+/// Full benchmark report, serialized as JSON by `rt bench`
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub version: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+    pub threads: usize,
+    pub accelerator: String,
+    pub workloads: Vec<WorkloadResult>,
+}
+
+/// The standard workloads, in report order
+pub fn workloads() -> Vec<Workload> {
+    vec![
+        Workload { name: "primitives", description: "150 random spheres, cubes and cylinders", build: primitives },
+        Workload { name: "mesh", description: "tessellated spheres, 1200 triangles", build: mesh },
+        Workload { name: "shadows", description: "8 lights over a field of occluders", build: shadows },
+        Workload { name: "spheres", description: "5000 small spheres in a cloud", build: spheres },
+    ]
+}
+
+/// Render a workload `frames` times with the given accelerator and report its timings and ray counts
+/// Counts come from global counters, so nothing else should render at the same time
+pub fn run(workload: &Workload, renderer: &Renderer, accelerator: &AcceleratorOptions, width: u32, height: u32, frames: u32) -> WorkloadResult {
+    let (mut scene, camera) = workload.build(width as Float / height as Float);
+    scene.accelerator = *accelerator;
+    let frames = frames.max(1);
+    
+    stats::take();
+    let mut times = Vec::with_capacity(frames as usize);
+    for _ in 0..frames {
+        let start = Instant::now();
+        renderer.render_framebuffer(&scene, &camera, width, height);
+        times.push(start.elapsed().as_secs_f64());
+    }
+    let counters = stats::take();
+    
+    let seconds = times.iter().sum::<f64>() / frames as f64;
+    let fastest = times.iter().cloned().fold(f64::INFINITY, f64::min);
+    let rays_per_frame = counters.rays / frames as u64;
+    
+    WorkloadResult {
+        name: workload.name.to_string(),
+        objects: scene.objects.len(),
+        lights: scene.lights.len(),
+        frames,
+        ms_per_frame: seconds * 1000.0,
+        ms_fastest: fastest * 1000.0,
+        rays_per_frame,
+        rays_per_sec: rays_per_frame as f64 / seconds,
+        intersection_tests_per_frame: counters.intersection_tests / frames as u64,
+    }
+}
+
+/// Small deterministic generator (xorshift64*) so workloads don't depend on an RNG crate
+struct Rng(u64);
+
+impl Rng {
+    /// Uniform in [0, 1)
+    fn next(&mut self) -> Float {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        ((self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64) as Float
+    }
+    
+    fn range(&mut self, min: Float, max: Float) -> Float {
+        min + (max - min) * self.next()
+    }
+    
+    fn color(&mut self) -> Color {
+        Color::new(self.range(0.2, 0.9), self.range(0.2, 0.9), self.range(0.2, 0.9))
+    }
+}
+
+fn overview_camera(aspect_ratio: Float) -> Camera {
+    Camera::new(Vec3::new(0.0, 6.0, 8.0), Vec3::new(0.0, 0.0, -4.0), Vec3::unit_y(), 50.0, aspect_ratio)
+}
+
+fn primitives(aspect_ratio: Float) -> (Scene, Camera) {
+    let mut rng = Rng(SEED);
+    let mut scene = Scene::new();
+    scene.add_object(Box::new(Plane::horizontal(0.0, Material::gray())));
+    
+    for i in 0..150 {
+        let center = Vec3::new(rng.range(-8.0, 8.0), 0.0, rng.range(-14.0, 2.0));
+        let size = rng.range(0.2, 0.5);
+        let material = Material::with_specular(rng.color(), 0.3, 16.0);
+        match i % 3 {
+            0 => scene.add_object(Box::new(Sphere::new(center + Vec3::new(0.0, size, 0.0), size, material))),
+            1 => scene.add_object(Box::new(Cube::new(
+                center - Vec3::new(size, 0.0, size),
+                center + Vec3::new(size, 2.0 * size, size),
+                material,
+            ))),
+            _ => scene.add_object(Box::new(Cylinder::new(center + Vec3::new(0.0, size, 0.0), size, 2.0 * size, material))),
+        }
+    }
+    
+    scene.add_light(Light::white_light(Vec3::new(5.0, 10.0, 5.0), 0.9));
+    (scene, overview_camera(aspect_ratio))
+}
+
+fn mesh(aspect_ratio: Float) -> (Scene, Camera) {
+    let mut rng = Rng(SEED);
+    let mut scene = Scene::new();
+    scene.add_object(Box::new(Plane::horizontal(0.0, Material::gray())));
+    
+    for &x in &[-3.0, 0.0, 3.0] {
+        let material = Material::with_specular(rng.color(), 0.5, 32.0);
+        add_uv_sphere(&mut scene, Vec3::new(x, 1.2, -4.0), 1.2, 20, 10, material);
+    }
+    
+    scene.add_light(Light::white_light(Vec3::new(5.0, 10.0, 5.0), 0.9));
+    (scene, overview_camera(aspect_ratio))
+}
+
+/// Add a sphere tessellated into `segments` × `rings` quads, each split into two triangles
+fn add_uv_sphere(scene: &mut Scene, center: Vec3, radius: Float, segments: u32, rings: u32, material: Material) {
+    let point = |segment: u32, ring: u32| {
+        let theta = PI * ring as Float / rings as Float;
+        let phi = 2.0 * PI * segment as Float / segments as Float;
+        center + radius * Vec3::new(theta.sin() * phi.cos(), theta.cos(), -theta.sin() * phi.sin())
+    };
+    
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let (a, b) = (point(segment, ring), point(segment + 1, ring));
+            let (c, d) = (point(segment, ring + 1), point(segment + 1, ring + 1));
+            scene.add_object(Box::new(Triangle::new(a, c, d, material.clone())));
+            scene.add_object(Box::new(Triangle::new(a, d, b, material.clone())));
+        }
+    }
+}
+
+fn shadows(aspect_ratio: Float) -> (Scene, Camera) {
+    let mut rng = Rng(SEED);
+    let mut scene = Scene::new();
+    scene.add_object(Box::new(Plane::horizontal(0.0, Material::white())));
+    
+    for _ in 0..24 {
+        let center = Vec3::new(rng.range(-6.0, 6.0), rng.range(1.0, 3.0), rng.range(-10.0, 0.0));
+        scene.add_object(Box::new(Sphere::new(center, rng.range(0.3, 0.7), Material::new(rng.color()))));
+    }
+    
+    for i in 0..8 {
+        let angle = 2.0 * PI * i as Float / 8.0;
+        let position = Vec3::new(8.0 * angle.cos(), 6.0, -5.0 + 8.0 * angle.sin());
+        scene.add_light(Light::new(position, 0.15, rng.color()));
+    }
+    (scene, overview_camera(aspect_ratio))
+}
+
+fn spheres(aspect_ratio: Float) -> (Scene, Camera) {
+    let mut rng = Rng(SEED);
+    let mut scene = Scene::new();
+    scene.add_object(Box::new(Plane::horizontal(0.0, Material::gray())));
+    
+    for _ in 0..5000 {
+        let center = Vec3::new(rng.range(-6.0, 6.0), rng.range(0.5, 4.5), rng.range(-12.0, 0.0));
+        scene.add_object(Box::new(Sphere::new(center, rng.range(0.05, 0.15), Material::new(rng.color()))));
+    }
+    
+    scene.add_light(Light::white_light(Vec3::new(5.0, 10.0, 5.0), 0.9));
+    (scene, overview_camera(aspect_ratio))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_workloads_are_deterministic() {
+        for workload in workloads() {
+            let (scene, camera) = workload.build(1.0);
+            let (again, _) = workload.build(1.0);
+            assert_eq!(scene.objects.len(), again.objects.len());
+            
+            let renderer = Renderer::new();
+            assert_eq!(
+                renderer.render(&scene, &camera, 16, 16).pixels(),
+                renderer.render(&again, &camera, 16, 16).pixels(),
+                "{} differs between builds",
+                workload.name
+            );
+        }
+    }
+    
+    #[test]
+    fn test_run_counts_rays() {
+        let workload = &workloads()[0];
+        let result = run(workload, &Renderer::new(), &AcceleratorOptions::default(), 8, 8, 2);
+        assert_eq!(result.frames, 2);
+        // Other tests may render concurrently, so only a lower bound holds
+        assert!(result.rays_per_frame >= 64);
+        // The BVH skips most objects, so tests per ray are well below the object count
+        assert!(result.intersection_tests_per_frame > 0);
+    }
+}
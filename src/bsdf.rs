@@ -0,0 +1,408 @@
+use crate::math::{consts::PI, Float, Onb, Vec3};
+use crate::material::Material;
+use crate::sampling::{self, luminance, Rng};
+
+/// Scattering at a surface point for the path tracer, derived from a `Material`
+/// Lobes are weighted like the ray tracer blends them: the mirror takes `reflectivity` of the light,
+/// transmission passes straight through, and the rest is Lambertian diffuse plus a Phong highlight
+/// Anisotropic materials trade the Phong lobe for an anisotropic GGX (Trowbridge-Reitz) one of similar width
+/// A clearcoat is a GGX lobe on top of it all, reflecting by Fresnel what the layers beneath then no longer get
+/// Materials with an index of refraction bend transmitted light instead, or reflect it by Fresnel; with a
+/// different index per channel, each channel is traced on its own path (three-band spectral tracing)
+#[derive(Debug, Clone)]
+pub struct Bsdf {
+    diffuse: Vec3,             // Lambertian reflectance
+    glossy: Float,             // Phong lobe weight
+    exponent: Float,           // Phong lobe exponent (shininess)
+    roughness: Option<(Float, Float)>, // GGX widths along and across the tangent, for anisotropic materials
+    tangent: Vec3,             // Direction of the GGX lobe's first width, before rotation
+    rotation: (Float, Float),  // Sine and cosine of the angle the tangent turns about the normal
+    clearcoat: Float,          // Clearcoat weight (0 for none)
+    clearcoat_roughness: Float, // Clearcoat GGX width
+    mirror: Float,             // Perfect mirror reflectance
+    transmission: Vec3,        // Light passing straight through, per channel
+    ior: Vec3,                 // Per-channel index of refraction of the transmission lobe, zero for none
+    outside_ior: Vec3,         // Index of refraction of the medium around the surface (1 for air)
+    front_face: bool,          // Whether the path arrives from outside
+    channel: Option<usize>,    // The only color channel a dispersed path still carries
+    probabilities: [Float; 4], // Chance of sampling the diffuse, glossy, mirror and transmission lobes
+}
+
+/// Direction picked by `Bsdf::sample`
+#[derive(Debug, Clone)]
+pub struct BsdfSample {
+    pub direction: Vec3,
+    pub weight: Vec3,       // BSDF × cos θ / pdf: the factor applied to the path throughput
+    pub pdf: Option<Float>, // Density per steradian, None for the mirror and straight-through lobes
+    pub transmitted: bool,  // Passed through the surface rather than reflected
+    pub channel: Option<usize>, // The only color channel the path carries from here, once dispersion splits it off
+}
+
+impl Bsdf {
+    /// Scattering of `material` at a hit whose texture coordinates increase along `tangent` (zero if the
+    /// shape has none), which anisotropic highlights line up with
+    pub fn new(material: &Material, tangent: Vec3) -> Self {
+        let reflected = 1.0 - material.reflectivity.clamp(0.0, 1.0);
+        let opaque = Vec3::new(1.0, 1.0, 1.0) - material.transmission;
+        let diffuse = Vec3::from(material.albedo) * opaque * reflected;
+        let glossy = material.specular.max(0.0) * luminance(opaque) * reflected;
+        let mirror = material.reflectivity.clamp(0.0, 1.0);
+        let transmission = material.transmission * reflected;
+        
+        let weights = [luminance(diffuse).max(0.0), glossy, mirror, luminance(transmission).max(0.0)];
+        let total: Float = weights.iter().sum();
+        let probabilities = if total > 0.0 { weights.map(|w| w / total) } else { [0.0; 4] };
+        
+        // Phong exponent to GGX width, then stretched along the tangent (positive) or across it (negative)
+        let exponent = material.shininess.max(0.0);
+        let roughness = (material.anisotropy != 0.0).then(|| {
+            let width = (2.0 / (exponent + 2.0)).sqrt();
+            let aspect = (1.0 - 0.9 * material.anisotropy.abs().min(1.0)).sqrt();
+            let (long, short) = (width / aspect, width * aspect);
+            if material.anisotropy > 0.0 { (long, short) } else { (short, long) }
+        });
+        
+        Self {
+            diffuse,
+            glossy,
+            exponent,
+            roughness,
+            tangent,
+            rotation: material.anisotropy_rotation.to_radians().sin_cos(),
+            clearcoat: material.clearcoat.clamp(0.0, 1.0),
+            clearcoat_roughness: material.clearcoat_roughness.max(1e-3),
+            mirror,
+            transmission,
+            ior: material.ior,
+            outside_ior: Vec3::new(1.0, 1.0, 1.0),
+            front_face: true,
+            channel: None,
+            probabilities,
+        }
+    }
+    
+    /// The same scattering for a path reaching the front (or back) of the surface, carrying only
+    /// `channel` if dispersion has split it off, where the medium around the surface has index `outside_ior`
+    pub fn with_path(self, front_face: bool, channel: Option<usize>, outside_ior: Vec3) -> Self {
+        Self { front_face, channel, outside_ior, ..self }
+    }
+    
+    /// BSDF value for light arriving from `wi` and leaving toward `wo` (the smooth lobes only)
+    /// All directions are unit length and `normal` faces `wo`
+    pub fn eval(&self, wo: Vec3, wi: Vec3, normal: Vec3) -> Vec3 {
+        if normal.dot(&wi) <= 0.0 {
+            return Vec3::zero();
+        }
+        let highlight = self.glossy * match self.roughness {
+            Some(roughness) => self.ggx(wo, wi, normal, roughness).0,
+            None => (self.exponent + 2.0) / (2.0 * PI) * self.phong_cos(wo, wi, normal).powf(self.exponent),
+        };
+        let base = self.diffuse / PI + Vec3::new(highlight, highlight, highlight);
+        if self.clearcoat == 0.0 {
+            return base;
+        }
+        let half = (wo + wi).normalize();
+        let coat = self.clearcoat * fresnel(wo.dot(&half)) * self.ggx(wo, wi, normal, self.coat_widths()).0;
+        base * (1.0 - self.coat_reflectance(wo, normal)) + Vec3::new(coat, coat, coat)
+    }
+    
+    /// Density (per steradian) with which `sample` picks `wi` through the smooth lobes
+    pub fn pdf(&self, wo: Vec3, wi: Vec3, normal: Vec3) -> Float {
+        let cos_theta = normal.dot(&wi);
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+        let [diffuse, glossy, _, _] = self.probabilities;
+        let glossy_pdf = match self.roughness {
+            Some(roughness) => self.ggx(wo, wi, normal, roughness).1,
+            None => (self.exponent + 1.0) / (2.0 * PI) * self.phong_cos(wo, wi, normal).powf(self.exponent),
+        };
+        let base = diffuse * cos_theta / PI + glossy * glossy_pdf;
+        if self.clearcoat == 0.0 {
+            return base;
+        }
+        let coat = self.coat_reflectance(wo, normal);
+        coat * self.ggx(wo, wi, normal, self.coat_widths()).1 + (1.0 - coat) * base
+    }
+    
+    /// Pick a direction to continue the path, or None if the surface absorbs it
+    /// The clearcoat is picked as often as it reflects, and the layers beneath share the rest
+    pub fn sample(&self, wo: Vec3, normal: Vec3, rng: &mut Rng) -> Option<BsdfSample> {
+        let [diffuse, glossy, mirror, _] = self.probabilities;
+        let coat = self.coat_reflectance(wo, normal);
+        let u = rng.next_float();
+        if u < coat {
+            let direction = self.sample_ggx(wo, normal, self.coat_widths(), rng);
+            return self.smooth_sample(wo, direction, normal);
+        }
+        let u = (u - coat) / (1.0 - coat);
+        
+        if u < diffuse + glossy {
+            let direction = if u < diffuse {
+                sampling::cosine_hemisphere(normal, rng)
+            } else if let Some(roughness) = self.roughness {
+                self.sample_ggx(wo, normal, roughness, rng)
+            } else {
+                let (v, phi) = (rng.next_float(), 2.0 * PI * rng.next_float());
+                sampling::around(mirrored(wo, normal), v.powf(1.0 / (self.exponent + 1.0)), phi)
+            };
+            self.smooth_sample(wo, direction, normal)
+        } else if u < diffuse + glossy + mirror {
+            let weight = self.mirror / mirror;
+            Some(BsdfSample {
+                direction: mirrored(wo, normal),
+                weight: Vec3::new(weight, weight, weight),
+                pdf: None,
+                transmitted: false,
+                channel: self.channel,
+            })
+        } else if self.probabilities[3] > 0.0 {
+            let weight = self.transmission / self.probabilities[3];
+            if self.ior == Vec3::zero() {
+                return Some(BsdfSample { direction: -wo, weight, pdf: None, transmitted: true, channel: self.channel });
+            }
+            self.sample_refraction(wo, normal, weight, rng)
+        } else {
+            None
+        }
+    }
+    
+    /// Refraction through the surface or Fresnel reflection off it, picked as often as each happens
+    /// A path meeting a dispersive surface for the first time picks one channel to carry on alone
+    fn sample_refraction(&self, wo: Vec3, normal: Vec3, weight: Vec3, rng: &mut Rng) -> Option<BsdfSample> {
+        let varies = |ior: Vec3| ior.x != ior.y || ior.y != ior.z;
+        let dispersive = varies(self.ior) || varies(self.outside_ior);
+        let (channel, weight) = match self.channel {
+            None if dispersive => {
+                let channel = ((rng.next_float() * 3.0) as usize).min(2);
+                let mut single = Vec3::zero();
+                single[channel] = weight[channel] * 3.0;
+                (Some(channel), single)
+            }
+            channel => (channel, weight),
+        };
+        let (inside, outside) = match channel {
+            Some(channel) if dispersive => (self.ior[channel], self.outside_ior[channel]),
+            _ => (self.ior.y, self.outside_ior.y), // The same in every channel
+        };
+        
+        let ratio = if self.front_face { outside / inside } else { inside / outside };
+        let (reflectance, refracted) = refract(wo, normal, ratio);
+        let (direction, transmitted) = match refracted {
+            Some(direction) if rng.next_float() >= reflectance => (direction, true),
+            _ => (mirrored(wo, normal), false),
+        };
+        Some(BsdfSample { direction, weight, pdf: None, transmitted, channel })
+    }
+    
+    /// A direction picked by one of the smooth lobes, weighted by the density of all of them, since any
+    /// could have produced it
+    fn smooth_sample(&self, wo: Vec3, direction: Vec3, normal: Vec3) -> Option<BsdfSample> {
+        let pdf = self.pdf(wo, direction, normal);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let weight = self.eval(wo, direction, normal) * (normal.dot(&direction) / pdf);
+        Some(BsdfSample { direction, weight, pdf: Some(pdf), transmitted: false, channel: self.channel })
+    }
+    
+    /// Direction reflected off a microfacet normal picked with density D(h) cos θh from the GGX lobe
+    /// with widths (`along`, `across`) the tangent: a slope of the unit-width distribution, stretched
+    fn sample_ggx(&self, wo: Vec3, normal: Vec3, (along, across): (Float, Float), rng: &mut Rng) -> Vec3 {
+        let (v, phi) = (rng.next_float(), 2.0 * PI * rng.next_float());
+        let slope = (v / (1.0 - v)).sqrt();
+        let half = self.frame(normal).to_world(Vec3::new(along * slope * phi.cos(), across * slope * phi.sin(), 1.0).normalize());
+        mirrored(wo, half)
+    }
+    
+    /// Fraction of the light leaving toward `wo` that the clearcoat reflects, and the layers beneath don't
+    fn coat_reflectance(&self, wo: Vec3, normal: Vec3) -> Float {
+        self.clearcoat * fresnel(normal.dot(&wo))
+    }
+    
+    fn coat_widths(&self) -> (Float, Float) {
+        (self.clearcoat_roughness, self.clearcoat_roughness)
+    }
+    
+    /// The GGX lobe with widths (`along`, `across`) the tangent: its BRDF for unit weight, and the density with
+    /// which `sample` picks `wi` from it (D(h) cos θh, turned into a density of reflected directions)
+    fn ggx(&self, wo: Vec3, wi: Vec3, normal: Vec3, (along, across): (Float, Float)) -> (Float, Float) {
+        let frame = self.frame(normal);
+        let (wo, wi) = (frame.to_local(wo), frame.to_local(wi));
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let half = (wo + wi).normalize();
+        let stretched = (half.x / along).powi(2) + (half.y / across).powi(2) + half.z * half.z;
+        let distribution = 1.0 / (PI * along * across * stretched * stretched);
+        
+        // Smith masking and shadowing, correlated between the two directions
+        let lambda = |w: Vec3| (-1.0 + (1.0 + ((along * w.x).powi(2) + (across * w.y).powi(2)) / (w.z * w.z)).sqrt()) / 2.0;
+        let geometry = 1.0 / (1.0 + lambda(wo) + lambda(wi));
+        (distribution * geometry / (4.0 * wo.z * wi.z), distribution * half.z / (4.0 * wo.dot(&half)))
+    }
+    
+    /// Tangent frame around `normal`, turned by the material's rotation
+    fn frame(&self, normal: Vec3) -> Onb {
+        let frame = Onb::from_normal_tangent(normal, self.tangent);
+        let (sin, cos) = self.rotation;
+        let u = frame.u * cos + frame.v * sin;
+        Onb { u, v: normal.cross(&u), w: normal }
+    }
+    
+    /// Cosine between `wi` and the mirror direction of `wo`, which centers the Phong lobe
+    fn phong_cos(&self, wo: Vec3, wi: Vec3, normal: Vec3) -> Float {
+        mirrored(wo, normal).dot(&wi).max(0.0)
+    }
+}
+
+/// Schlick's approximation of the Fresnel reflectance of a lacquer (index of refraction 1.5) lit at
+/// `cos_theta` from its normal
+pub fn fresnel(cos_theta: Float) -> Float {
+    0.04 + 0.96 * (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+}
+
+/// Light from `wo` meeting a smooth boundary whose `normal` faces it, with `ratio` the index of refraction
+/// on its side over that beyond: the fraction reflected (Fresnel), and the direction the rest refracts
+/// to, or None if the boundary reflects it all (total internal reflection)
+pub fn refract(wo: Vec3, normal: Vec3, ratio: Float) -> (Float, Option<Vec3>) {
+    let cos_i = normal.dot(&wo).clamp(0.0, 1.0);
+    let sin2_t = ratio * ratio * (1.0 - cos_i * cos_i);
+    if sin2_t >= 1.0 {
+        return (1.0, None);
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let perpendicular = (ratio * cos_i - cos_t) / (ratio * cos_i + cos_t);
+    let parallel = (cos_i - ratio * cos_t) / (cos_i + ratio * cos_t);
+    let reflectance = (perpendicular * perpendicular + parallel * parallel) / 2.0;
+    (reflectance, Some((-wo * ratio + normal * (ratio * cos_i - cos_t)).normalize()))
+}
+
+/// `wo` reflected about the normal
+fn mirrored(wo: Vec3, normal: Vec3) -> Vec3 {
+    (-wo).reflect(&normal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    
+    #[test]
+    fn test_sampling_matches_eval() {
+        // Averaging sampled weights estimates the directional albedo: ∫ f cos dω
+        let material = Material::with_specular(Color::gray(0.5), 0.3, 20.0);
+        let bsdf = Bsdf::new(&material, Vec3::zero());
+        let normal = Vec3::unit_y();
+        let wo = Vec3::new(0.3, 1.0, 0.2).normalize();
+        
+        let mut rng = Rng::new(7);
+        let (mut sampled, mut uniform) = (0.0, 0.0);
+        let count = 100_000;
+        for _ in 0..count {
+            if let Some(sample) = bsdf.sample(wo, normal, &mut rng) {
+                sampled += sample.weight.x / count as Float;
+            }
+            
+            // Reference estimate with uniform hemisphere directions (density 1 / 2π)
+            let wi = sampling::around(normal, rng.next_float(), 2.0 * PI * rng.next_float());
+            uniform += bsdf.eval(wo, wi, normal).x * normal.dot(&wi) * 2.0 * PI / count as Float;
+        }
+        assert!((sampled - uniform).abs() < 0.02, "sampled {} vs uniform {}", sampled, uniform);
+        
+        let mirror = Bsdf::new(&Material::with_reflection(Color::black(), 1.0), Vec3::zero()).sample(wo, normal, &mut rng).unwrap();
+        assert!(mirror.pdf.is_none());
+        assert!((mirror.direction - Vec3::new(-0.3, 1.0, -0.2).normalize()).length() < 1e-6);
+    }
+    
+    #[test]
+    fn test_anisotropic() {
+        let mut material = Material::with_specular(Color::black(), 1.0, 100.0);
+        material.anisotropy = 0.8;
+        let normal = Vec3::unit_y();
+        let wo = Vec3::new(0.0, 1.0, 0.5).normalize();
+        let mirror = Vec3::new(0.0, 1.0, -0.5).normalize();
+        
+        // The highlight spreads further along the tangent than across it, and turns with the rotation
+        let tilted = |material: &Material, tangent: Vec3| {
+            Bsdf::new(material, tangent).eval(wo, (mirror + Vec3::unit_x() * 0.2).normalize(), normal).x
+        };
+        let along = tilted(&material, Vec3::unit_x());
+        assert!(along > 4.0 * tilted(&material, Vec3::unit_z()));
+        material.anisotropy_rotation = 90.0;
+        assert!((tilted(&material, Vec3::unit_z()) - along).abs() < 1e-9 * along);
+        
+        // Sampling the lobe agrees with its density: sampled weights average to the directional albedo
+        let bsdf = Bsdf::new(&material, Vec3::unit_x());
+        let mut rng = Rng::new(11);
+        let (mut sampled, mut uniform) = (0.0, 0.0);
+        let count = 200_000;
+        for _ in 0..count {
+            if let Some(sample) = bsdf.sample(wo, normal, &mut rng) {
+                sampled += sample.weight.x / count as Float;
+            }
+            let wi = sampling::around(normal, rng.next_float(), 2.0 * PI * rng.next_float());
+            uniform += bsdf.eval(wo, wi, normal).x * normal.dot(&wi) * 2.0 * PI / count as Float;
+        }
+        assert!((sampled - uniform).abs() < 0.03 && sampled > 0.5, "sampled {} vs uniform {}", sampled, uniform);
+    }
+    
+    #[test]
+    fn test_clearcoat() {
+        let mut material = Material::new(Color::gray(0.5));
+        material.clearcoat = 1.0;
+        material.clearcoat_roughness = 0.1;
+        let bsdf = Bsdf::new(&material, Vec3::zero());
+        let normal = Vec3::unit_y();
+        
+        // The coat reflects little head-on and much at grazing angles, taking that light from the base
+        assert!((fresnel(1.0) - 0.04).abs() < 1e-9 && fresnel(0.0) == 1.0);
+        let mirror_ratio = |wo: Vec3| bsdf.eval(wo, Vec3::new(-wo.x, wo.y, -wo.z), normal).x / (0.5 / PI);
+        assert!(mirror_ratio(Vec3::new(0.1, 1.0, 0.0).normalize()) < mirror_ratio(Vec3::new(1.0, 0.1, 0.0).normalize()));
+        let beside = |wo: Vec3| bsdf.eval(wo, Vec3::new(0.0, 1.0, 1.0).normalize(), normal).x;
+        assert!(beside(Vec3::new(1.0, 0.1, 0.0).normalize()) < beside(Vec3::unit_y()));
+        
+        // Sampled weights still average to the directional albedo, which the coat can't raise above 1
+        let wo = Vec3::new(1.0, 0.3, 0.0).normalize();
+        let mut rng = Rng::new(5);
+        let (mut sampled, mut uniform) = (0.0, 0.0);
+        let count = 200_000;
+        for _ in 0..count {
+            if let Some(sample) = bsdf.sample(wo, normal, &mut rng) {
+                sampled += sample.weight.x / count as Float;
+            }
+            let wi = sampling::around(normal, rng.next_float(), 2.0 * PI * rng.next_float());
+            uniform += bsdf.eval(wo, wi, normal).x * normal.dot(&wi) * 2.0 * PI / count as Float;
+        }
+        assert!((sampled - uniform).abs() < 0.02 && sampled < 1.0, "sampled {} vs uniform {}", sampled, uniform);
+    }
+    
+    #[test]
+    fn test_refraction() {
+        // Snell's law and Fresnel: 4% reflected head-on into glass, all of it past the critical angle out of it
+        let normal = Vec3::unit_y();
+        let wo = Vec3::new(0.5, (0.75 as Float).sqrt(), 0.0); // 30° from the normal
+        let (reflectance, refracted) = refract(Vec3::unit_y(), normal, 1.0 / 1.5);
+        assert!((reflectance - 0.04).abs() < 1e-6 && refracted == Some(-Vec3::unit_y()));
+        let refracted = refract(wo, normal, 1.0 / 1.5).1.unwrap();
+        assert!((refracted.x - -0.5 / 1.5).abs() < 1e-6 && refracted.y < 0.0);
+        assert_eq!(refract(Vec3::new(1.0, 0.5, 0.0).normalize(), normal, 1.5), (1.0, None));
+        
+        // A dispersive surface sends each channel its own way, and paths already split keep to their channel
+        let bsdf = Bsdf::new(&Material::flint_glass(), Vec3::zero());
+        let mut rng = Rng::new(3);
+        let mut bent = [0.0; 3];
+        for _ in 0..1000 {
+            let sample = bsdf.sample(wo, normal, &mut rng).unwrap();
+            let channel = sample.channel.unwrap();
+            assert_eq!(sample.weight[channel], 3.0);
+            assert_eq!(sample.weight.x + sample.weight.y + sample.weight.z, 3.0);
+            if sample.transmitted {
+                bent[channel] = sample.direction.x;
+            }
+        }
+        assert!(bent[0] < bent[1] && bent[1] < bent[2], "{:?}", bent); // Blue bends most, leaving least far along -x
+        let sample = bsdf.clone().with_path(false, Some(2), Vec3::new(1.0, 1.0, 1.0)).sample(-wo, -normal, &mut rng).unwrap();
+        assert_eq!((sample.channel, sample.weight), (Some(2), Vec3::new(1.0, 1.0, 1.0)));
+    }
+}
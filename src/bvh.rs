@@ -0,0 +1,461 @@
+use std::str::FromStr;
+
+use crate::math::{Aabb, Float, Vec3, Ray, Interval};
+use crate::stats;
+
+/// Deepest tree the traversal stack can hold; deeper nodes are made leaves
+const MAX_DEPTH: usize = 64;
+
+/// Most rays traced together by `Bvh::traverse_packet` (a 4×4 block of pixels)
+pub const MAX_PACKET: usize = 16;
+
+/// How BVH nodes are divided between their two children
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitMethod {
+    Median, // Half the items on each side, along the longest axis: fast to build
+    #[default]
+    Sah,    // Cheapest split by the surface area heuristic: slower to build, faster to trace
+}
+
+impl FromStr for SplitMethod {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "median" => Ok(Self::Median),
+            "sah" => Ok(Self::Sah),
+            _ => Err(format!("unknown BVH split method '{}' (expected median or sah)", s)),
+        }
+    }
+}
+
+/// BVH build parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BvhOptions {
+    pub split: SplitMethod,
+    pub max_leaf_size: usize,  // Larger nodes are always split; SAH may split smaller ones too
+    pub bins: usize,           // Candidate split planes per node for SAH, spaced evenly along the longest axis
+    pub traversal_cost: Float, // Cost of visiting a node, relative to intersecting one item
+}
+
+impl Default for BvhOptions {
+    fn default() -> Self {
+        Self {
+            split: SplitMethod::Sah,
+            max_leaf_size: 4,
+            bins: 12,
+            traversal_cost: 0.125,
+        }
+    }
+}
+
+/// Bounding volume hierarchy over a list of items (shapes or scene objects), referred to by index
+/// Items without bounds (infinite planes) are kept aside and offered to every ray
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    nodes: Vec<Node>,      // Depth-first: a node's first child follows it directly
+    items: Vec<usize>,     // Item indices, grouped so each leaf covers a contiguous range
+    unbounded: Vec<usize>, // Items without bounds
+    traversal_cost: Float, // As built with, for `cost`
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    bounds: Aabb,
+    start: usize,  // First entry of `items` in a leaf
+    count: usize,  // Items in a leaf, 0 for interior nodes
+    second: usize, // Index of an interior node's second child
+}
+
+impl Bvh {
+    /// Build over items with the given bounds (None = unbounded) with the default options
+    pub fn new(bounds: &[Option<Aabb>]) -> Self {
+        Self::with_options(bounds, &BvhOptions::default())
+    }
+    
+    /// Build over items with the given bounds (None = unbounded)
+    pub fn with_options(bounds: &[Option<Aabb>], options: &BvhOptions) -> Self {
+        let (mut items, unbounded): (Vec<usize>, Vec<usize>) = (0..bounds.len()).partition(|&i| bounds[i].is_some());
+        let bounded: Vec<Aabb> = bounds.iter().map(|b| b.unwrap_or_else(Aabb::empty)).collect();
+        
+        let mut builder = Builder { nodes: Vec::new(), bounds: &bounded, options };
+        if !items.is_empty() {
+            builder.build(&mut items, 0, 0);
+        }
+        Self { nodes: builder.nodes, items, unbounded, traversal_cost: options.traversal_cost }
+    }
+    
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+    
+    /// Bytes its nodes and item lists take
+    pub fn memory(&self) -> usize {
+        size_of_val(self.nodes.as_slice()) + size_of_val(self.items.as_slice()) + size_of_val(self.unbounded.as_slice())
+    }
+    
+    /// Expected cost of tracing a ray that hits the root's box, in item intersections, by the surface
+    /// area heuristic: each node is entered with probability proportional to its surface area
+    /// Unbounded items aren't counted; lower is better for the same items
+    pub fn cost(&self) -> Float {
+        let root_area = match self.nodes.first() {
+            Some(root) if root.bounds.surface_area() > 0.0 => root.bounds.surface_area(),
+            _ => return self.items.len() as Float,
+        };
+        self.nodes
+            .iter()
+            .map(|node| {
+                let work = if node.count > 0 { node.count as Float } else { self.traversal_cost };
+                work * node.bounds.surface_area() / root_area
+            })
+            .sum()
+    }
+    
+    /// Items of each leaf, in depth-first order: groups of items close to each other
+    pub fn leaves(&self) -> impl Iterator<Item = &[usize]> {
+        self.nodes.iter().filter(|node| node.count > 0).map(|node| &self.items[node.start..node.start + node.count])
+    }
+    
+    /// Bounds of every item, or None if some are unbounded
+    pub fn bounds(&self) -> Option<Aabb> {
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+        Some(self.nodes.first().map_or_else(Aabb::empty, |root| root.bounds))
+    }
+    
+    /// Offer the ray every item whose bounds it enters before the closest hit so far
+    /// `visit(item, t_max)` intersects an item and returns the distance of a hit closer than t_max;
+    /// nearer nodes are visited first, so distant ones are usually skipped
+    pub fn traverse(&self, ray: &Ray, mut t_max: Float, mut visit: impl FnMut(usize, Float) -> Option<Float>) {
+        for &item in &self.unbounded {
+            if let Some(t) = visit(item, t_max) {
+                t_max = t;
+            }
+        }
+        if self.nodes.is_empty() {
+            return;
+        }
+        
+        let inverse_direction = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+        let mut stack = [(0, 0.0); MAX_DEPTH];
+        let mut depth = 0;
+        let mut visited = 0;
+        if let Some(t) = self.nodes[0].bounds.hit(ray, inverse_direction, Interval::new(0.0, t_max)) {
+            stack[0] = (0, t);
+            depth = 1;
+        }
+        
+        while depth > 0 {
+            depth -= 1;
+            let (index, t_enter) = stack[depth];
+            if t_enter > t_max {
+                continue; // A closer hit was found since this node was pushed
+            }
+            
+            visited += 1;
+            let node = &self.nodes[index];
+            if node.count > 0 {
+                for &item in &self.items[node.start..node.start + node.count] {
+                    if let Some(t) = visit(item, t_max) {
+                        t_max = t;
+                    }
+                }
+                continue;
+            }
+            
+            // Push the farther child first so the nearer one is popped next
+            let children = [index + 1, node.second];
+            let hits = children.map(|child| self.nodes[child].bounds.hit(ray, inverse_direction, Interval::new(0.0, t_max)));
+            let order = match hits {
+                [Some(first), Some(second)] if second < first => [1, 0],
+                _ => [0, 1],
+            };
+            for &i in order.iter().rev() {
+                if let Some(t) = hits[i] {
+                    stack[depth] = (children[i], t);
+                    depth += 1;
+                }
+            }
+        }
+        stats::count_nodes(visited);
+    }
+    
+    /// Offer a packet of coherent rays (such as camera rays through neighboring pixels) the items whose
+    /// bounds any of them enters, walking the tree once for all of them; `t_max` holds each ray's closest
+    /// hit so far and `visit(ray, item, t_max)` works as in `traverse` for one ray of the packet
+    /// Rays that diverge visit nodes the others need, so incoherent rays are better traced one at a time
+    pub fn traverse_packet(&self, rays: &[Ray], t_max: &mut [Float], mut visit: impl FnMut(usize, usize, Float) -> Option<Float>) {
+        assert!(rays.len() <= MAX_PACKET && rays.len() == t_max.len(), "packets hold up to {} rays", MAX_PACKET);
+        for &item in &self.unbounded {
+            for (r, t_max) in t_max.iter_mut().enumerate() {
+                if let Some(t) = visit(r, item, *t_max) {
+                    *t_max = t;
+                }
+            }
+        }
+        if self.nodes.is_empty() {
+            return;
+        }
+        
+        let mut inverse_directions = [Vec3::zero(); MAX_PACKET];
+        for (inverse, ray) in inverse_directions.iter_mut().zip(rays) {
+            *inverse = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+        }
+        let mut stack = [0; MAX_DEPTH]; // Starting with the root
+        let mut depth = 1;
+        let mut visited = 0;
+        
+        while depth > 0 {
+            depth -= 1;
+            let index = stack[depth];
+            let node = &self.nodes[index];
+            
+            // Bit r is set if ray r enters the node before its closest hit
+            let mut active = 0u32;
+            for (r, ray) in rays.iter().enumerate() {
+                if node.bounds.hit(ray, inverse_directions[r], Interval::new(0.0, t_max[r])).is_some() {
+                    active |= 1 << r;
+                }
+            }
+            if active == 0 {
+                continue;
+            }
+            
+            visited += active.count_ones() as u64;
+            if node.count > 0 {
+                for &item in &self.items[node.start..node.start + node.count] {
+                    for r in (0..rays.len()).filter(|r| active & (1 << r) != 0) {
+                        if let Some(t) = visit(r, item, t_max[r]) {
+                            t_max[r] = t;
+                        }
+                    }
+                }
+                continue;
+            }
+            
+            // Children in the order the first active ray reaches their centers, nearer one popped next
+            let ray = &rays[active.trailing_zeros() as usize];
+            let children = [index + 1, node.second];
+            let along = children.map(|child| (self.nodes[child].bounds.centroid() - ray.origin).dot(&ray.direction));
+            let order = if along[1] < along[0] { [0, 1] } else { [1, 0] };
+            for i in order {
+                stack[depth] = children[i];
+                depth += 1;
+            }
+        }
+        stats::count_nodes(visited);
+    }
+}
+
+/// Builds the nodes of a BVH depth-first
+struct Builder<'a> {
+    nodes: Vec<Node>,
+    bounds: &'a [Aabb],
+    options: &'a BvhOptions,
+}
+
+impl Builder<'_> {
+    /// Build the subtree over `items` (which start at `start` in the final item list), returning its node index
+    fn build(&mut self, items: &mut [usize], start: usize, depth: usize) -> usize {
+        let index = self.nodes.len();
+        let node_bounds = items.iter().fold(Aabb::empty(), |total, &item| total.union(&self.bounds[item]));
+        self.nodes.push(Node { bounds: node_bounds, start, count: items.len(), second: 0 });
+        if items.len() <= 1 || depth + 1 >= MAX_DEPTH {
+            return index;
+        }
+        
+        let middle = match self.options.split {
+            SplitMethod::Sah => match self.sah_split(items, &node_bounds) {
+                Some(middle) => middle,
+                None if items.len() > self.options.max_leaf_size => self.median_split(items),
+                None => return index, // A leaf is cheaper than any split
+            },
+            SplitMethod::Median if items.len() > self.options.max_leaf_size => self.median_split(items),
+            SplitMethod::Median => return index,
+        };
+        
+        let (first, second) = items.split_at_mut(middle);
+        self.build(first, start, depth + 1);
+        let second = self.build(second, start + middle, depth + 1);
+        self.nodes[index].count = 0;
+        self.nodes[index].second = second;
+        index
+    }
+    
+    /// Order items so the first half have the smaller centroids along the longest axis; returns the half size
+    fn median_split(&self, items: &mut [usize]) -> usize {
+        let axis = self.centroid_bounds(items).longest_axis();
+        let key = |item: usize| self.bounds[item].centroid()[axis];
+        let middle = items.len() / 2;
+        items.select_nth_unstable_by(middle, |&a, &b| key(a).total_cmp(&key(b)));
+        middle
+    }
+    
+    /// Bin the items by centroid along the longest axis and find the split between bins with the lowest
+    /// SAH cost; if it beats a leaf (or the node is too big for one), order the items by side and return
+    /// the size of the first side
+    fn sah_split(&self, items: &mut [usize], node_bounds: &Aabb) -> Option<usize> {
+        let centroids = self.centroid_bounds(items);
+        let axis = centroids.longest_axis();
+        let (low, extent) = (centroids.min[axis], (centroids.max - centroids.min)[axis]);
+        if extent <= 0.0 {
+            return None; // All centroids coincide, so no plane separates them
+        }
+        
+        let bin_count = self.options.bins.max(2);
+        let bin_of = |item: usize| {
+            let offset = (self.bounds[item].centroid()[axis] - low) / extent;
+            ((offset * bin_count as Float) as usize).min(bin_count - 1)
+        };
+        let mut bins = vec![(0, Aabb::empty()); bin_count];
+        for &item in items.iter() {
+            let bin = &mut bins[bin_of(item)];
+            bin.0 += 1;
+            bin.1 = bin.1.union(&self.bounds[item]);
+        }
+        
+        // Sweep from the right to get each suffix's count and bounds, then from the left for the prefixes
+        let mut suffixes = vec![(0, Aabb::empty()); bin_count];
+        let mut suffix = (0, Aabb::empty());
+        for i in (1..bin_count).rev() {
+            suffix = (suffix.0 + bins[i].0, suffix.1.union(&bins[i].1));
+            suffixes[i] = suffix;
+        }
+        let mut best: Option<(Float, usize)> = None;
+        let mut prefix = (0, Aabb::empty());
+        for split in 1..bin_count {
+            prefix = (prefix.0 + bins[split - 1].0, prefix.1.union(&bins[split - 1].1));
+            let (right_count, right_bounds) = suffixes[split];
+            if prefix.0 == 0 || right_count == 0 {
+                continue;
+            }
+            let cost = prefix.0 as Float * prefix.1.surface_area() + right_count as Float * right_bounds.surface_area();
+            if best.is_none_or(|(best_cost, _)| cost < best_cost) {
+                best = Some((cost, split));
+            }
+        }
+        
+        let (cost, split) = best?;
+        let cost = match node_bounds.surface_area() {
+            area if area > 0.0 => self.options.traversal_cost + cost / area,
+            _ => self.options.traversal_cost,
+        };
+        if items.len() <= self.options.max_leaf_size && cost >= items.len() as Float {
+            return None;
+        }
+        
+        // Move the items left of the split to the front
+        let mut middle = 0;
+        for i in 0..items.len() {
+            if bin_of(items[i]) < split {
+                items.swap(i, middle);
+                middle += 1;
+            }
+        }
+        Some(middle)
+    }
+    
+    fn centroid_bounds(&self, items: &[usize]) -> Aabb {
+        items.iter().fold(Aabb::empty(), |total, &item| total.grow(self.bounds[item].centroid()))
+    }
+}
+
+/// Coordinate along an axis (0 = x, 1 = y, 2 = z)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::Rng;
+    
+    /// Unit boxes scattered through a 20-unit cube
+    fn random_boxes(rng: &mut Rng, count: usize) -> Vec<Aabb> {
+        (0..count).map(|_| {
+            let min = Vec3::new(rng.next_float(), rng.next_float(), rng.next_float()) * 20.0 - Vec3::new(10.0, 10.0, 10.0);
+            Aabb::new(min, min + Vec3::new(1.0, 1.0, 1.0))
+        }).collect()
+    }
+    
+    #[test]
+    fn test_traversal_finds_closest_hit() {
+        // The BVH must find the same closest box as testing all of them, however it was split
+        let mut rng = Rng::new(9);
+        let boxes = random_boxes(&mut rng, 200);
+        let bounds: Vec<Option<Aabb>> = boxes.iter().map(|&b| Some(b)).collect();
+        
+        for split in [SplitMethod::Median, SplitMethod::Sah] {
+            let bvh = Bvh::with_options(&bounds, &BvhOptions { split, ..BvhOptions::default() });
+            assert_eq!(bvh.bounds(), Some(boxes.iter().fold(Aabb::empty(), |total, b| total.union(b))));
+            
+            for _ in 0..500 {
+                let origin = Vec3::new(rng.next_float(), rng.next_float(), rng.next_float()) * 4.0;
+                let direction = Vec3::new(rng.next_float(), rng.next_float(), rng.next_float()) * 2.0 - Vec3::new(1.0, 1.0, 1.0);
+                let ray = Ray::new(origin, direction);
+                let inverse = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+                let expected = boxes.iter().filter_map(|b| b.hit(&ray, inverse, Interval::new(0.0, Float::INFINITY))).reduce(Float::min);
+                
+                let (mut closest, mut visited) = (None, 0);
+                let (_, counters) = stats::count(|| {
+                    bvh.traverse(&ray, Float::INFINITY, |item, t_max| {
+                        visited += 1;
+                        let t = boxes[item].hit(&ray, inverse, Interval::new(0.0, t_max))?;
+                        closest = Some(t);
+                        Some(t)
+                    })
+                });
+                assert_eq!(closest, expected, "{:?}", split);
+                assert!(visited < boxes.len() / 2, "visited {} boxes with {:?}", visited, split);
+                assert!((1..bvh.node_count() as u64 / 2).contains(&counters.node_visits), "{} nodes", counters.node_visits);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_packet_traversal_matches_single_rays() {
+        // Fans of 16 rays from one origin, some spreading wide enough to part ways in the tree
+        let mut rng = Rng::new(10);
+        let boxes = random_boxes(&mut rng, 200);
+        let bvh = Bvh::new(&boxes.iter().map(|&b| Some(b)).collect::<Vec<_>>());
+        let closest = |ray: &Ray, t_max: Float, item: usize| {
+            let inverse = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+            boxes[item].hit(ray, inverse, Interval::new(0.0, t_max))
+        };
+        
+        for spread in [0.01, 0.1, 1.0] {
+            for _ in 0..50 {
+                let origin = Vec3::new(rng.next_float(), rng.next_float(), rng.next_float()) * 4.0;
+                let center = Vec3::new(rng.next_float(), rng.next_float(), rng.next_float()) * 2.0 - Vec3::new(1.0, 1.0, 1.0);
+                let rays: Vec<Ray> = (0..MAX_PACKET)
+                    .map(|i| Ray::new(origin, center + Vec3::new((i % 4) as Float, (i / 4) as Float, 0.0) * spread))
+                    .collect();
+                
+                let mut t_max = [Float::INFINITY; MAX_PACKET];
+                bvh.traverse_packet(&rays, &mut t_max, |r, item, limit| closest(&rays[r], limit, item));
+                for (ray, t) in rays.iter().zip(t_max) {
+                    let mut expected = Float::INFINITY;
+                    bvh.traverse(ray, Float::INFINITY, |item, limit| {
+                        expected = closest(ray, limit, item)?;
+                        Some(expected)
+                    });
+                    assert_eq!(t, expected, "{:?} in a packet spread by {}", ray, spread);
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn test_sah_lowers_cost() {
+        // A dense cluster next to a sparse spread: median splits cut through the cluster's neighborhood
+        let mut rng = Rng::new(4);
+        let mut boxes = random_boxes(&mut rng, 300);
+        boxes.extend(random_boxes(&mut rng, 300).iter().map(|b| Aabb::new(b.min * 0.05, b.min * 0.05 + Vec3::new(0.05, 0.05, 0.05))));
+        let bounds: Vec<Option<Aabb>> = boxes.iter().map(|&b| Some(b)).collect();
+        
+        let median = Bvh::with_options(&bounds, &BvhOptions { split: SplitMethod::Median, ..BvhOptions::default() });
+        let sah = Bvh::new(&bounds);
+        assert!(sah.cost() < median.cost() * 0.9, "SAH {} vs median {}", sah.cost(), median.cost());
+        
+        // Bigger leaves mean fewer nodes
+        let coarse = Bvh::with_options(&bounds, &BvhOptions { max_leaf_size: 16, traversal_cost: 4.0, ..BvhOptions::default() });
+        assert!(coarse.node_count() < sah.node_count());
+    }
+}
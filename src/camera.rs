@@ -1,11 +1,55 @@
-use crate::math::{Vec3, Ray};
+use serde::{Deserialize, Serialize};
+
+use crate::math::{consts::PI, Float, Vec3, Ray, Quaternion};
 
 /// Pinhole camera with adjustable position, target, and field of view
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub origin: Vec3,
     pub lower_left_corner: Vec3,
     pub horizontal: Vec3,
     pub vertical: Vec3,
+    pub projection: Projection,   // How points of the image window (one unit in front of the origin) become directions
+    pub target_distance: Float,   // Distance to the point looked at, where the eyes of a stereo pair converge by default
+    pub near: Float,              // Camera rays start this far ahead (a depth for perspective, else a distance)
+    pub far: Float,               // and find nothing beyond this far
+    pub exposure: f64,            // Stops (EV) the renderer scales the image by, set from `Exposure`; 0 leaves it alone
+}
+
+/// How the camera spreads the directions it sees over the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Projection {
+    #[default]
+    Perspective, // Straight lines stay straight; the field of view must stay below 180°
+    Fisheye,     // Equidistant: the angle off the view direction grows evenly across the image, up to 360°
+    Panorama,    // Equirectangular: 360° across and 180° up and down whatever the field of view, for 2:1 skyboxes and VR viewers
+}
+
+/// Photographic exposure settings: sensor sensitivity (ISO), shutter time in seconds and aperture f-number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exposure {
+    pub iso: f64,
+    pub shutter: f64,
+    pub aperture: f64,
+}
+
+impl Exposure {
+    /// Sunny 16: ISO 100, 1/100 s at f/16, right for scenes lit by the sun
+    pub fn sunny_16() -> Self {
+        Self { iso: 100.0, shutter: 0.01, aperture: 16.0 }
+    }
+    
+    /// Exposure value at ISO 100: log2(N²/t), less a stop for each doubling of the ISO
+    pub fn ev100(&self) -> f64 {
+        (self.aperture * self.aperture / self.shutter * 100.0 / self.iso).log2()
+    }
+    
+    /// Stops that map the brightest luminance the sensor records unclipped, 1.2 · 2^EV100 cd/m²
+    /// (saturation-based sensitivity), to a pixel value of 1
+    pub fn stops(&self) -> f64 {
+        -(1.2 * self.ev100().exp2()).log2()
+    }
 }
 
 impl Camera {
@@ -19,12 +63,21 @@ impl Camera {
         look_from: Vec3,
         look_at: Vec3,
         up: Vec3,
-        fov: f64,
-        aspect_ratio: f64,
+        fov: Float,
+        aspect_ratio: Float,
     ) -> Self {
+        Self::with_projection(look_from, look_at, up, fov, aspect_ratio, Projection::Perspective)
+    }
+    
+    /// Create a camera with the given projection, its vertical field of view `fov` degrees
+    pub fn with_projection(look_from: Vec3, look_at: Vec3, up: Vec3, fov: Float, aspect_ratio: Float, projection: Projection) -> Self {
+        // The window spans the tangents of the angles it sees, or for the other projections the angles themselves
         let theta = fov.to_radians();
-        let half_height = (theta / 2.0).tan();
-        let half_width = aspect_ratio * half_height;
+        let (half_width, half_height) = match projection {
+            Projection::Perspective => (aspect_ratio * (theta / 2.0).tan(), (theta / 2.0).tan()),
+            Projection::Fisheye => (aspect_ratio * theta / 2.0, theta / 2.0),
+            Projection::Panorama => (PI, PI / 2.0),
+        };
         
         let w = (look_from - look_at).normalize();
         let u = up.cross(&w).normalize();
@@ -40,13 +93,81 @@ impl Camera {
             lower_left_corner,
             horizontal,
             vertical,
+            projection,
+            target_distance: (look_at - look_from).length(),
+            near: 0.0,
+            far: Float::INFINITY,
+            exposure: 0.0,
         }
     }
     
+    /// The same camera rolled `degrees` about its view direction, counterclockwise as seen from behind it, so
+    /// the scene tilts clockwise in the image (a Dutch angle); a shifted lens turns with the camera
+    pub fn with_roll(self, degrees: Float) -> Self {
+        let roll = Quaternion::from_axis_angle(self.horizontal.cross(&self.vertical), degrees.to_radians());
+        let center = roll.rotate(self.lower_left_corner + (self.horizontal + self.vertical) * 0.5 - self.origin);
+        let (horizontal, vertical) = (roll.rotate(self.horizontal), roll.rotate(self.vertical));
+        Self { lower_left_corner: self.origin + center - (horizontal + vertical) * 0.5, horizontal, vertical, ..self }
+    }
+    
+    /// One eye of a stereo pair: the camera moved `offset` to the right (negative for the left eye), its image
+    /// shifted so that both eyes' views line up `convergence` ahead (off-axis, so the image planes stay parallel
+    /// rather than toed in, which would skew them against each other)
+    pub fn eye(&self, offset: Float, convergence: Float) -> Self {
+        let right = self.horizontal.normalize() * offset;
+        Self { origin: self.origin + right, lower_left_corner: self.lower_left_corner + right * (1.0 - 1.0 / convergence), ..self.clone() }
+    }
+    
+    /// The same camera with its lens shifted `x` image widths right and `y` image heights up: the view moves
+    /// without turning the camera, so lines parallel to the image plane stay parallel (an architectural shift
+    /// lens keeping a tall building's verticals upright)
+    pub fn with_shift(self, x: Float, y: Float) -> Self {
+        Self { lower_left_corner: self.lower_left_corner + self.horizontal * x + self.vertical * y, ..self }
+    }
+    
+    /// The same camera clipping away everything nearer than `near` and beyond `far`, e.g. a wall in the way
+    /// of a room's interior; perspective clips at planes of constant depth, the other projections at spheres
+    pub fn with_clipping(self, near: Float, far: Float) -> Self {
+        Self { near, far, ..self }
+    }
+    
+    /// How far along its rays the camera sees past where they start, from the near clip to the far
+    pub fn clip_range(&self) -> Float {
+        self.far - self.near
+    }
+    
+    /// The same camera, exposing the image with photographic settings (for scenes in physical light units)
+    pub fn with_exposure(self, exposure: Exposure) -> Self {
+        Self { exposure: exposure.stops(), ..self }
+    }
+    
+    /// Angle in radians one pixel subtends at the center of an image `height` pixels high
+    pub fn pixel_spread(&self, height: u32) -> Float {
+        let distance = (self.lower_left_corner + (self.horizontal + self.vertical) * 0.5 - self.origin).length();
+        self.vertical.length() / distance / height.max(1) as Float
+    }
+    
     /// Get ray for given screen coordinates (u, v in [0, 1])
-    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
+    pub fn get_ray(&self, u: Float, v: Float) -> Ray {
         let direction = self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin;
-        Ray::new(self.origin, direction)
+        if self.projection == Projection::Perspective {
+            return Ray::new(self.origin + direction * self.near, direction); // The window is a unit depth ahead
+        }
+        
+        // Angles right and up of the view direction, read off the window a unit distance away
+        let (right, up) = (self.horizontal.normalize(), self.vertical.normalize());
+        let forward = up.cross(&right);
+        let distance = direction.dot(&forward);
+        let (x, y) = (direction.dot(&right) / distance, direction.dot(&up) / distance);
+        let direction = match self.projection {
+            Projection::Fisheye => {
+                let angle = x.hypot(y);
+                let across = if angle > 0.0 { (right * x + up * y) / angle } else { Vec3::zero() };
+                forward * angle.cos() + across * angle.sin()
+            }
+            _ => (forward * x.cos() + right * x.sin()) * y.cos() + up * y.sin(),
+        };
+        Ray::new(self.origin + direction * self.near, direction)
     }
 }
 
@@ -70,5 +191,70 @@ mod tests {
         
         // Ray should point roughly down -Z
         assert!(ray.direction.z < 0.0);
+        
+        // Clipped, rays start at the near plane, a depth of 2 ahead even toward the corners
+        let clipped = camera.clone().with_clipping(2.0, 10.0);
+        assert_eq!(clipped.get_ray(0.5, 0.5).origin, Vec3::new(0.0, 0.0, -2.0));
+        assert!((clipped.get_ray(0.0, 1.0).origin.z + 2.0).abs() < 1e-6);
+        assert_eq!(clipped.clip_range(), 8.0);
+        
+        // Rolled a quarter turn, the top of the image looks left and the center stays put
+        let rolled = camera.clone().with_roll(90.0);
+        let (center, top) = (rolled.get_ray(0.5, 0.5).direction, rolled.get_ray(0.5, 1.0).direction);
+        assert!((center - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-6);
+        assert!((top - Vec3::new(-1.0, 0.0, -1.0)).length() < 1e-6, "{:?}", top);
+        
+        // Shifted half a frame up, the bottom of the image looks straight ahead, and a roll turns the shift along
+        let shifted = camera.clone().with_shift(0.0, 0.5);
+        assert!((shifted.get_ray(0.5, 0.0).direction - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-6);
+        assert_eq!(shifted.horizontal, camera.horizontal);
+        let bottom = shifted.with_roll(90.0).get_ray(0.5, 0.0).direction;
+        assert!((bottom - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-6, "{:?}", bottom);
+        
+        // A stereo pair's eyes look at the same point at the convergence distance, from either side
+        let (left, right) = (camera.eye(-0.1, 4.0), camera.eye(0.1, 4.0));
+        let (left, right) = (left.get_ray(0.5, 0.5), right.get_ray(0.5, 0.5));
+        assert_eq!((left.origin.x, right.origin.x), (-0.1, 0.1));
+        assert!((left.at(4.0) - right.at(4.0)).length() < 1e-6 && (left.at(4.0).z + 4.0).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn test_projections() {
+        let camera = |fov, projection| Camera::with_projection(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0), Vec3::unit_y(), fov, 2.0, projection);
+        let direction = |camera: &Camera, u, v| camera.get_ray(u, v).direction.normalize();
+        let close = |a: Vec3, b: Vec3| (a - b).length() < 1e-6;
+        
+        // A 180° fisheye sees straight up at the top of the image, and evenly spaced angles in between
+        let fisheye = camera(180.0, Projection::Fisheye);
+        assert!(close(direction(&fisheye, 0.5, 1.0), Vec3::unit_y()));
+        assert!(close(direction(&fisheye, 0.5, 0.75), Vec3::new(0.0, 1.0, -1.0).normalize()));
+        assert!(close(direction(&fisheye, 0.5, 0.5), Vec3::new(0.0, 0.0, -1.0)));
+        assert!((fisheye.pixel_spread(100) - PI / 100.0).abs() < 1e-6);
+        
+        // A panorama wraps all the way around, whatever its field of view: behind at the left edge, right at
+        // three quarters of the way across, and the poles along the top and bottom
+        let panorama = camera(45.0, Projection::Panorama);
+        assert!(close(direction(&panorama, 0.0, 0.5), Vec3::new(0.0, 0.0, 1.0)));
+        assert!(close(direction(&panorama, 0.75, 0.5), Vec3::new(1.0, 0.0, 0.0)));
+        assert!(close(direction(&panorama, 0.3, 0.0), -Vec3::unit_y()));
+    }
+    
+    #[test]
+    fn test_exposure() {
+        let sunny = Exposure::sunny_16();
+        assert!((sunny.ev100() - 14.64).abs() < 0.01);
+        
+        // Doubling the ISO, the shutter time or the aperture area each add a stop
+        let brighter = [
+            Exposure { iso: 200.0, ..sunny },
+            Exposure { shutter: 0.02, ..sunny },
+            Exposure { aperture: 16.0 / 2.0_f64.sqrt(), ..sunny },
+        ];
+        for exposure in brighter {
+            assert!((exposure.stops() - sunny.stops() - 1.0).abs() < 1e-4);
+        }
+        
+        // Sunlit white paper (about 30000 cd/m²) comes out near full brightness
+        assert!((30000.0 * sunny.stops().exp2() - 1.0).abs() < 0.1);
     }
 }
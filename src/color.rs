@@ -0,0 +1,189 @@
+use std::ops::{Add, Sub, Mul, Div};
+
+use crate::math::{Float, Vec3};
+
+/// Linear RGB color (reflectances, light colors, pixel values), kept apart from `Vec3` positions
+/// and directions; convert with `From`/`Into` where color math meets vector math
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Color {
+    pub r: Float,
+    pub g: Float,
+    pub b: Float,
+}
+
+impl Color {
+    pub fn new(r: Float, g: Float, b: Float) -> Self {
+        Self { r, g, b }
+    }
+    
+    pub fn black() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+    
+    pub fn white() -> Self {
+        Self::new(1.0, 1.0, 1.0)
+    }
+    
+    /// Gray with the same value in every channel
+    pub fn gray(value: Float) -> Self {
+        Self::new(value, value, value)
+    }
+    
+    /// Parse "#rrggbb" or "rrggbb" (sRGB-encoded, as in CSS and color pickers) into linear RGB
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok().map(|byte| srgb_to_linear(byte as Float / 255.0));
+        Some(Self::new(channel(0)?, channel(2)?, channel(4)?))
+    }
+    
+    /// Color of a blackbody at `kelvin` (clamped to 1000–40000 K), from Tanner Helland's fit to the
+    /// blackbody tables; 6600 K is white, lower is warmer (candles near 1900, tungsten near 2700), higher cooler
+    pub fn from_kelvin(kelvin: Float) -> Self {
+        let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+        let r = if t <= 66.0 { 255.0 } else { 329.6987 * (t - 60.0).powf(-0.1332048) };
+        let g = if t <= 66.0 { 99.4708 * t.ln() - 161.1196 } else { 288.1222 * (t - 60.0).powf(-0.07551485) };
+        let b = if t >= 66.0 {
+            255.0
+        } else if t <= 19.0 {
+            0.0
+        } else {
+            138.5177 * (t - 10.0).ln() - 305.0448
+        };
+        
+        // The fit gives sRGB-encoded bytes
+        let channel = |value: Float| srgb_to_linear(value.clamp(0.0, 255.0) / 255.0);
+        Self::new(channel(r), channel(g), channel(b))
+    }
+    
+    /// Color from bytes encoded with the sRGB transfer curve (the inverse of `to_srgb8`)
+    pub fn from_srgb8(bytes: [u8; 3]) -> Self {
+        let [r, g, b] = bytes.map(|byte| srgb_to_linear(byte as Float / 255.0));
+        Self::new(r, g, b)
+    }
+    
+    /// Perceived brightness (Rec. 709 weights)
+    pub fn luminance(&self) -> Float {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+    
+    /// Linear interpolation from `self` (t = 0) to `other` (t = 1)
+    pub fn lerp(&self, other: Color, t: Float) -> Self {
+        *self * (1.0 - t) + other * t
+    }
+    
+    /// Bytes of the color encoded with the sRGB transfer curve, clamped to [0, 1]
+    pub fn to_srgb8(&self) -> [u8; 3] {
+        [self.r, self.g, self.b].map(|value| (255.0 * linear_to_srgb(value.clamp(0.0, 1.0)) + 0.5) as u8)
+    }
+}
+
+fn srgb_to_linear(value: Float) -> Float {
+    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(value: Float) -> Float {
+    if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 }
+}
+
+impl From<Vec3> for Color {
+    fn from(v: Vec3) -> Self {
+        Self::new(v.x, v.y, v.z)
+    }
+}
+
+impl From<Color> for Vec3 {
+    fn from(color: Color) -> Self {
+        Vec3::new(color.r, color.g, color.b)
+    }
+}
+
+impl Add for Color {
+    type Output = Color;
+    
+    fn add(self, other: Color) -> Color {
+        Color::new(self.r + other.r, self.g + other.g, self.b + other.b)
+    }
+}
+
+impl Sub for Color {
+    type Output = Color;
+    
+    fn sub(self, other: Color) -> Color {
+        Color::new(self.r - other.r, self.g - other.g, self.b - other.b)
+    }
+}
+
+/// Component-wise product: a color filtered by another (light reflected by a surface)
+impl Mul for Color {
+    type Output = Color;
+    
+    fn mul(self, other: Color) -> Color {
+        Color::new(self.r * other.r, self.g * other.g, self.b * other.b)
+    }
+}
+
+impl Mul<Float> for Color {
+    type Output = Color;
+    
+    fn mul(self, scalar: Float) -> Color {
+        Color::new(self.r * scalar, self.g * scalar, self.b * scalar)
+    }
+}
+
+impl Mul<Color> for Float {
+    type Output = Color;
+    
+    fn mul(self, color: Color) -> Color {
+        color * self
+    }
+}
+
+impl Div<Float> for Color {
+    type Output = Color;
+    
+    fn div(self, scalar: Float) -> Color {
+        Color::new(self.r / scalar, self.g / scalar, self.b / scalar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_color() {
+        let (orange, blue) = (Color::new(1.0, 0.5, 0.0), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(orange * Color::new(0.5, 0.5, 2.0), Color::new(0.5, 0.25, 0.0));
+        assert_eq!(orange.lerp(blue, 0.5), Color::new(0.5, 0.25, 0.5));
+        assert!((Color::white().luminance() - 1.0).abs() < 1e-6);
+        assert_eq!(Vec3::from(orange), Vec3::new(1.0, 0.5, 0.0));
+        assert_eq!(Color::from(Vec3::new(1.0, 0.5, 0.0)), orange);
+    }
+    
+    #[test]
+    fn test_hex_round_trip() {
+        assert_eq!(Color::from_hex("#ffffff"), Some(Color::white()));
+        assert_eq!(Color::from_hex("000000"), Some(Color::black()));
+        for hex in ["#ff8000", "#1a2b3c", "#7f7f7f"] {
+            let bytes = Color::from_hex(hex).unwrap().to_srgb8();
+            assert_eq!(format!("#{:02x}{:02x}{:02x}", bytes[0], bytes[1], bytes[2]), hex);
+        }
+        
+        // Middle gray in sRGB is about a fifth of full brightness in linear light
+        assert!((Color::from_hex("#808080").unwrap().g - 0.216).abs() < 1e-3);
+        assert_eq!(Color::from_hex("#12345"), None);
+        assert_eq!(Color::from_hex("#12345g"), None);
+    }
+    
+    #[test]
+    fn test_from_kelvin() {
+        assert_eq!(Color::from_kelvin(6600.0), Color::white());
+        let (candle, sky) = (Color::from_kelvin(1900.0), Color::from_kelvin(12000.0));
+        assert!(candle.r == 1.0 && candle.g < 0.3 && candle.b < 0.05, "{:?}", candle);
+        assert!(sky.b == 1.0 && sky.r < 0.6, "{:?}", sky);
+        assert_eq!(Color::from_kelvin(100.0), Color::from_kelvin(1000.0));
+    }
+}
@@ -0,0 +1,85 @@
+use std::io;
+#[cfg(feature = "fs")]
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// Config file looked up in the working directory before the user config directory
+pub const LOCAL_FILE: &str = "rt.toml";
+
+/// Errors from reading a config file
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("could not read config file: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid config file: {0}")]
+    Parse(#[from] toml::de::Error),
+}
+
+/// Image file formats the renderer can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Ppm,
+    Png,
+    Exr, // 32-bit float, linear
+}
+
+/// Default render settings from `rt.toml`; command-line flags override every field
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub samples: Option<u32>,        // Antialiasing samples per pixel
+    pub threads: Option<usize>,      // Render threads (0 = all cores)
+    pub gamma: Option<f64>,          // Output gamma (1.0 = linear)
+    pub format: Option<ImageFormat>, // Used for stdout and output names without an extension
+    pub bit_depth: Option<u8>,       // Bits per PNG or PPM sample, 8 or 16
+}
+
+impl Config {
+    /// Parse a config from TOML text
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(text)?)
+    }
+    
+    /// Read and parse a config file
+    #[cfg(feature = "fs")]
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+    
+    /// The config file in effect: `./rt.toml`, else `$XDG_CONFIG_HOME/rt/config.toml` (or `~/.config/rt/config.toml`)
+    #[cfg(feature = "fs")]
+    pub fn find() -> Option<PathBuf> {
+        let local = PathBuf::from(LOCAL_FILE);
+        if local.is_file() {
+            return Some(local);
+        }
+        
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        let user = config_home.join("rt").join("config.toml");
+        user.is_file().then_some(user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_config() {
+        let config = Config::from_toml("width = 1920\nheight = 1080\nsamples = 64\nformat = \"png\"\n").unwrap();
+        assert_eq!(config.width, Some(1920));
+        assert_eq!(config.samples, Some(64));
+        assert_eq!(config.format, Some(ImageFormat::Png));
+        assert_eq!(config.gamma, None);
+        assert_eq!(Config::from_toml("bit_depth = 16").unwrap().bit_depth, Some(16));
+        
+        assert!(Config::from_toml("widht = 1920").is_err());
+        assert!(Config::from_toml("format = \"jpg\"").is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use crate::math::{Float, Vec3};
+use crate::framebuffer::Framebuffer;
+use crate::sampling::luminance;
+
+/// Smallest albedo divided out of a color, so black surfaces don't blow up the filtered irradiance
+const MIN_ALBEDO: Float = 0.01;
+
+/// Per-pixel features of the first surface each camera ray hits (AOVs), guiding the denoiser
+/// Pixels showing the background have a zero normal and albedo
+pub struct Guides {
+    pub width: u32,
+    pub height: u32,
+    normals: Vec<Vec3>,
+    albedos: Vec<Vec3>,
+}
+
+impl Guides {
+    /// Create guides with every pixel showing the background
+    pub fn new(width: u32, height: u32) -> Self {
+        let size = (width * height) as usize;
+        Self {
+            width,
+            height,
+            normals: vec![Vec3::zero(); size],
+            albedos: vec![Vec3::zero(); size],
+        }
+    }
+    
+    /// Set a pixel's normal (facing the camera) and albedo, averaged over its samples
+    pub fn set(&mut self, x: u32, y: u32, normal: Vec3, albedo: Vec3) {
+        let index = (y * self.width + x) as usize;
+        self.normals[index] = normal;
+        self.albedos[index] = albedo;
+    }
+    
+    pub fn normal(&self, x: u32, y: u32) -> Vec3 {
+        self.normals[(y * self.width + x) as usize]
+    }
+    
+    pub fn albedo(&self, x: u32, y: u32) -> Vec3 {
+        self.albedos[(y * self.width + x) as usize]
+    }
+}
+
+/// Edge-preserving image-space denoiser (joint bilateral filter)
+/// Neighboring pixels are averaged only where the guides show the same surface: similar normal and albedo,
+/// and similar brightness after a 3×3 pre-blur (a cheap patch comparison, so single fireflies get spread out
+/// rather than preserved). Colors are divided by the albedo while filtering, keeping surface colors sharp
+#[derive(Debug, Clone)]
+pub struct Denoiser {
+    pub radius: u32,          // Window half-size in pixels
+    pub sigma_spatial: Float, // Falloff with distance in pixels
+    pub sigma_color: Float,   // Falloff with relative brightness difference (0-1)
+    pub sigma_normal: Float,  // Falloff with normal difference
+    pub sigma_albedo: Float,  // Falloff with albedo difference
+}
+
+impl Denoiser {
+    pub fn new() -> Self {
+        Self {
+            radius: 5,
+            sigma_spatial: 3.0,
+            sigma_color: 0.25,
+            sigma_normal: 0.3,
+            sigma_albedo: 0.1,
+        }
+    }
+    
+    /// Filtered copy of a framebuffer; alpha is kept as is
+    pub fn denoise(&self, framebuffer: &Framebuffer, guides: &Guides) -> Framebuffer {
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        let index = |x: u32, y: u32| (y * width + x) as usize;
+        
+        // Irradiance estimates: the noisy part of the image, without surface color
+        let albedos: Vec<Vec3> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                guides.albedo(x, y).max(&Vec3::new(MIN_ALBEDO, MIN_ALBEDO, MIN_ALBEDO))
+            })
+            .collect();
+        let irradiance: Vec<Vec3> = albedos
+            .iter()
+            .enumerate()
+            .map(|(i, albedo)| {
+                let color = Vec3::from(framebuffer.color(i as u32 % width, i as u32 / width));
+                Vec3::new(color.x / albedo.x, color.y / albedo.y, color.z / albedo.z)
+            })
+            .collect();
+        let brightness = box_blur(&irradiance.iter().map(|&c| luminance(c)).collect::<Vec<_>>(), width, height);
+        
+        let falloff = |distance_squared: Float, sigma: Float| (-distance_squared / (2.0 * sigma * sigma)).exp();
+        let mut denoised = Framebuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let center = index(x, y);
+                let (normal, albedo) = (guides.normal(x, y), guides.albedo(x, y));
+                let (mut sum, mut total_weight) = (Vec3::zero(), 0.0);
+                
+                for ny in y.saturating_sub(self.radius)..(y + self.radius + 1).min(height) {
+                    for nx in x.saturating_sub(self.radius)..(x + self.radius + 1).min(width) {
+                        let neighbor = index(nx, ny);
+                        let (dx, dy) = (nx as Float - x as Float, ny as Float - y as Float);
+                        let contrast = (brightness[center] - brightness[neighbor]).abs()
+                            / (brightness[center] + brightness[neighbor]).max(MIN_ALBEDO);
+                        
+                        let weight = falloff(dx * dx + dy * dy, self.sigma_spatial)
+                            * falloff(contrast * contrast, self.sigma_color)
+                            * falloff((guides.normal(nx, ny) - normal).length_squared(), self.sigma_normal)
+                            * falloff((guides.albedo(nx, ny) - albedo).length_squared(), self.sigma_albedo);
+                        sum += irradiance[neighbor] * weight;
+                        total_weight += weight;
+                    }
+                }
+                
+                // The center pixel has weight 1, so the total is never zero
+                let filtered = sum / total_weight;
+                let albedo = albedos[center];
+                let color = filtered * albedo;
+                denoised.set(x, y, color.into(), framebuffer.alpha(x, y));
+            }
+        }
+        denoised
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 3×3 mean of each pixel's neighborhood (clipped at the image border)
+fn box_blur(values: &[Float], width: u32, height: u32) -> Vec<Float> {
+    let mut blurred = Vec::with_capacity(values.len());
+    for y in 0..height {
+        for x in 0..width {
+            let (mut sum, mut count) = (0.0, 0.0);
+            for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                    sum += values[(ny * width + nx) as usize];
+                    count += 1.0;
+                }
+            }
+            blurred.push(sum / count);
+        }
+    }
+    blurred
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::Rng;
+    
+    #[test]
+    fn test_denoise_keeps_edges() {
+        // Left half red, right half blue, both lit by noisy irradiance around 0.5
+        let (width, height) = (32, 16);
+        let mut framebuffer = Framebuffer::new(width, height);
+        let mut guides = Guides::new(width, height);
+        let mut rng = Rng::new(3);
+        for y in 0..height {
+            for x in 0..width {
+                let albedo = if x < width / 2 { Vec3::new(0.8, 0.1, 0.1) } else { Vec3::new(0.1, 0.1, 0.8) };
+                guides.set(x, y, Vec3::unit_z(), albedo);
+                framebuffer.set(x, y, (albedo * rng.next_float()).into(), 1.0);
+            }
+        }
+        
+        let denoised = Denoiser::new().denoise(&framebuffer, &guides);
+        let error = |image: &Framebuffer| {
+            let mut total = 0.0;
+            for y in 0..height {
+                for x in 0..width {
+                    total += (Vec3::from(image.color(x, y)) - guides.albedo(x, y) * 0.5).length_squared();
+                }
+            }
+            total
+        };
+        assert!(error(&denoised) < error(&framebuffer) / 5.0, "{} vs {}", error(&denoised), error(&framebuffer));
+        
+        // Colors don't bleed across the albedo edge
+        let edge = denoised.color(width / 2 - 1, height / 2);
+        assert!(edge.b < edge.r / 4.0, "{:?}", edge);
+        assert_eq!(denoised.alpha(0, 0), 1.0);
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+use crate::image::RgbaImage;
+
+const CHANNELS: [&str; 4] = ["r", "g", "b", "a"];
+
+/// Per-channel and per-pixel differences between two images of the same size
+/// Errors are absolute differences scaled to 0-1
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffReport {
+    pub max_error: [f64; 4],  // Largest error per channel (r, g, b, a)
+    pub mean_error: [f64; 4], // Average error per channel over all pixels
+    pub differing: usize,     // Pixels where any channel's error exceeds the threshold
+    pub pixels: usize,
+    pub threshold: f64,
+}
+
+impl DiffReport {
+    /// Whether every pixel is within the threshold
+    pub fn matches(&self) -> bool {
+        self.differing == 0
+    }
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "channel  max error  mean error")?;
+        for (i, name) in CHANNELS.iter().enumerate() {
+            writeln!(f, "{:<7}  {:>9.6}  {:>10.6}", name, self.max_error[i], self.mean_error[i])?;
+        }
+        write!(
+            f,
+            "{} of {} pixels differ by more than {} ({:.3}%)",
+            self.differing,
+            self.pixels,
+            self.threshold,
+            100.0 * self.differing as f64 / self.pixels.max(1) as f64
+        )
+    }
+}
+
+/// Largest channel error of each pixel, 0-1
+fn pixel_errors<'a>(a: &'a RgbaImage, b: &'a RgbaImage) -> impl Iterator<Item = [f64; 4]> + 'a {
+    a.pixels.iter().zip(&b.pixels).map(|(pa, pb)| {
+        let mut error = [0.0; 4];
+        for c in 0..4 {
+            error[c] = (pa[c] as f64 - pb[c] as f64).abs() / 255.0;
+        }
+        error
+    })
+}
+
+/// Compare two images; panics if their sizes differ
+pub fn compare(a: &RgbaImage, b: &RgbaImage, threshold: f64) -> DiffReport {
+    assert_eq!((a.width, a.height), (b.width, b.height), "images must be the same size");
+    
+    let mut max_error = [0.0f64; 4];
+    let mut total = [0.0; 4];
+    let mut differing = 0;
+    for error in pixel_errors(a, b) {
+        for c in 0..4 {
+            max_error[c] = max_error[c].max(error[c]);
+            total[c] += error[c];
+        }
+        if error.iter().any(|&e| e > threshold) {
+            differing += 1;
+        }
+    }
+    
+    let pixels = a.pixels.len();
+    DiffReport {
+        max_error,
+        mean_error: total.map(|t| t / pixels.max(1) as f64),
+        differing,
+        pixels,
+        threshold,
+    }
+}
+
+/// RGB heatmap of where two images differ: black where they match, through red and yellow to white
+/// for the largest difference, so that even small errors are visible
+pub fn heatmap(a: &RgbaImage, b: &RgbaImage) -> Vec<u8> {
+    let errors: Vec<f64> = pixel_errors(a, b).map(|e| e.iter().cloned().fold(0.0, f64::max)).collect();
+    let largest = errors.iter().cloned().fold(0.0, f64::max);
+    
+    let mut rgb = Vec::with_capacity(errors.len() * 3);
+    for error in errors {
+        let t = if largest > 0.0 { 3.0 * error / largest } else { 0.0 };
+        for channel in 0..3 {
+            rgb.push(((t - channel as f64).clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+    }
+    rgb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn image(pixels: Vec<[u8; 4]>) -> RgbaImage {
+        RgbaImage { width: pixels.len() as u32, height: 1, pixels }
+    }
+    
+    #[test]
+    fn test_compare() {
+        let a = image(vec![[0, 0, 0, 255], [100, 100, 100, 255], [255, 255, 255, 255], [10, 20, 30, 40]]);
+        let b = image(vec![[0, 0, 0, 255], [102, 100, 100, 255], [255, 0, 255, 255], [10, 20, 30, 40]]);
+        
+        let report = compare(&a, &b, 0.01);
+        assert_eq!(report.max_error, [2.0 / 255.0, 1.0, 0.0, 0.0]);
+        assert_eq!(report.mean_error[1], 0.25);
+        assert_eq!(report.differing, 1); // 2/255 is under the threshold
+        assert!(!report.matches());
+        assert!(compare(&a, &a, 0.0).matches());
+        
+        let heatmap = heatmap(&a, &b);
+        assert_eq!(&heatmap[0..3], &[0, 0, 0]);
+        assert_eq!(&heatmap[6..9], &[255, 255, 255]);
+    }
+}
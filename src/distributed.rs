@@ -0,0 +1,532 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::ops::Range;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::Float;
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::render::TILE_SIZE;
+use crate::serve::RenderRequest;
+
+/// What a coordinator tells a worker, one JSON line each
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "order", rename_all = "lowercase")]
+enum Order {
+    Job(Box<RenderRequest>),   // The image to render bands of, sent before its first band
+    Band { rows: Range<u32> }, // Render these rows of tiles of the last job
+    Done,                      // Nothing is left
+}
+
+/// A worker's answer to a band, one JSON line; a band is followed by its pixels, row by row, as
+/// little-endian f64 premultiplied RGBA, so the image comes out exactly as rendered
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "reply", rename_all = "lowercase")]
+enum Reply {
+    Band { width: u32, height: u32 },
+    Error { message: String },
+}
+
+/// Splits an image into bands of tile rows and hands them to workers connecting over TCP as they become free,
+/// putting the image together as the bands come back
+/// A band whose worker fails, disconnects or goes quiet for `timeout` goes to the next free worker; the image is
+/// the same as a render on one machine, whichever workers render which bands
+pub struct Coordinator {
+    pub band_rows: u32,    // Rows of tiles (TILE_SIZE pixels each) in a band
+    pub attempts: u32,     // Workers a band may fail on before the render is given up
+    pub timeout: Duration, // Longest a worker may go without answering before its band counts as failed
+}
+
+impl Coordinator {
+    /// Render the job with workers connecting to the listener, to the assembled image (before exposure and gamma)
+    /// Returns once every band is in, or with an error once a band has failed `attempts` times
+    pub fn render(&self, listener: &TcpListener, job: &RenderRequest) -> Result<Framebuffer, String> {
+        job.check()?;
+        let (width, height) = (job.width, job.height);
+        let rows = height.div_ceil(TILE_SIZE);
+        let bands: Vec<Range<u32>> = (0..rows).step_by(self.band_rows.max(1) as usize).map(|start| start..(start + self.band_rows.max(1)).min(rows)).collect();
+        let image = Mutex::new(Framebuffer::new(width, height));
+        
+        let dispatch = Dispatch { attempts: self.attempts, give_up_all: true, timeout: self.timeout };
+        let describe = |band: usize| format!("tile rows {}..{}", bands[band].start, bands[band].end);
+        let given_up = dispatch.run(listener, bands.len(), &describe, &|| false, &|connection, band| {
+            let rows = bands[band].clone();
+            Ok(connection.render((0, job), rows.clone(), (width, height))?.map(|pixels| {
+                let mut image = image.lock().unwrap();
+                for y in 0..pixels.height {
+                    for x in 0..pixels.width {
+                        image.set(x, rows.start * TILE_SIZE + y, pixels.color(x, y), pixels.alpha(x, y));
+                    }
+                }
+            }))
+        })?;
+        match given_up.into_iter().next() {
+            Some((_, error)) => Err(error),
+            None => Ok(image.into_inner().unwrap()),
+        }
+    }
+}
+
+/// Renders the frames of an animation, each a job of its own, handing whole frames to workers connecting over
+/// TCP as they become free
+/// A frame whose worker fails, disconnects or goes quiet for `timeout` goes to the next free worker; one that has
+/// failed on `attempts` workers is given up while the rest carry on
+pub struct Farm {
+    pub attempts: u32,     // Workers a frame may fail on before it is given up
+    pub timeout: Duration, // Longest a worker may go without answering before its frame counts as failed
+}
+
+impl Farm {
+    /// Render the frames with workers connecting to the listener, passing each image (before exposure and gamma)
+    /// to `done` with its frame's index as it comes in
+    /// Returns the frames not rendered, with why, once the rest are, or as soon as `stop` returns true
+    pub fn render(
+        &self,
+        listener: &TcpListener,
+        frames: &[RenderRequest],
+        stop: impl Fn() -> bool + Sync,
+        done: impl Fn(usize, Framebuffer) + Sync,
+    ) -> Result<Vec<(usize, String)>, String> {
+        for frame in frames {
+            frame.check()?;
+        }
+        let dispatch = Dispatch { attempts: self.attempts, give_up_all: false, timeout: self.timeout };
+        dispatch.run(listener, frames.len(), &|frame| format!("frame {}", frame), &stop, &|connection, frame| {
+            let job = &frames[frame];
+            Ok(connection.render((frame, job), 0..job.height.div_ceil(TILE_SIZE), (job.width, job.height))?.map(|image| done(frame, image)))
+        })
+    }
+}
+
+/// Frames of a farm render that are finished, saved as each one finishes so that a render stopped partway can
+/// pick up where it left off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub options: RenderRequest,             // Render options of every frame, without a scene
+    pub bit_depth: u8,                      // Bits per channel of PNG and PPM frames
+    pub finished: BTreeMap<String, String>, // Output file of each finished frame, by its scene file
+}
+
+impl Manifest {
+    /// An empty manifest for frames rendered with these options and saved at this bit depth
+    pub fn new(options: &RenderRequest, bit_depth: u8) -> Self {
+        Self { options: RenderRequest { scene: None, ..options.clone() }, bit_depth, finished: BTreeMap::new() }
+    }
+    
+    /// Whether frames rendered with these options and saved at this bit depth can be counted as finished (only
+    /// their scenes may differ)
+    pub fn matches(&self, options: &RenderRequest, bit_depth: u8) -> bool {
+        let without_scene = |options: &RenderRequest| {
+            let mut value = serde_json::to_value(options).expect("render requests serialize");
+            value.as_object_mut().map(|fields| fields.remove("scene"));
+            value
+        };
+        self.bit_depth == bit_depth && without_scene(&self.options) == without_scene(options)
+    }
+    
+    /// Whether a frame is finished: listed with this output file, and the file still there
+    #[cfg(feature = "fs")]
+    pub fn is_finished(&self, scene: &str, output: &Path) -> bool {
+        self.finished.get(scene).is_some_and(|listed| Path::new(listed) == output) && output.is_file()
+    }
+    
+    /// Read a manifest, or None if there is no file
+    #[cfg(feature = "fs")]
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).map(Some).map_err(|err| invalid(err.to_string())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+    
+    /// Write the manifest, by way of a temporary file so a crash never leaves half of one
+    #[cfg(feature = "fs")]
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_vec_pretty(self).expect("manifests serialize"))?;
+        std::fs::rename(&temporary, path)
+    }
+}
+
+/// Units of work (bands or frames) still to do, shared by the threads talking to workers
+struct Queue {
+    pending: VecDeque<usize>,
+    failures: HashMap<usize, u32>,  // Failed attempts by unit
+    finished: Vec<bool>,            // Whether each unit is done or given up
+    remaining: usize,               // Units not yet finished, pending or in progress
+    given_up: Vec<(usize, String)>, // Units not done, with why
+    stopped: bool,                  // Set to stop handing out units and cut off the busy workers
+    workers: Vec<TcpStream>,        // Connections, shut down to cut the workers off
+}
+
+/// How units of work are handed to workers
+struct Dispatch {
+    attempts: u32,     // Workers a unit may fail on before it is given up
+    give_up_all: bool, // Stop everything once a unit is given up, rather than carry on with the rest
+    timeout: Duration, // Longest a worker may go without answering before its unit counts as failed
+}
+
+/// Sends a worker a unit of work and handles its result; the outer error is the connection failing, the inner
+/// one the worker's own
+type Work<'a> = dyn Fn(&mut Connection, usize) -> io::Result<Result<(), String>> + Sync + 'a;
+
+impl Dispatch {
+    /// Hand units 0..units to workers connecting to the listener as they become free, a unit whose worker fails
+    /// going to the next free one, until each is done or given up, or `stop` returns true
+    /// Returns the units given up, with why (`describe` names them)
+    fn run(&self, listener: &TcpListener, units: usize, describe: &(dyn Fn(usize) -> String + Sync), stop: &(dyn Fn() -> bool + Sync), work: &Work) -> Result<Vec<(usize, String)>, String> {
+        let queue = Mutex::new(Queue {
+            pending: (0..units).collect(),
+            failures: HashMap::new(),
+            finished: vec![false; units],
+            remaining: units,
+            given_up: Vec::new(),
+            stopped: false,
+            workers: Vec::new(),
+        });
+        let changed = Condvar::new();
+        
+        listener.set_nonblocking(true).map_err(|err| err.to_string())?;
+        std::thread::scope(|scope| {
+            loop {
+                {
+                    let mut queue = queue.lock().unwrap();
+                    if queue.remaining == 0 || queue.stopped {
+                        break;
+                    }
+                    if stop() {
+                        queue.stopped = true;
+                        break;
+                    }
+                }
+                match listener.accept() {
+                    Ok((stream, address)) => {
+                        let _ = stream.set_nonblocking(false); // Blocking like the listener was before
+                        log::info!("worker {} connected", address);
+                        let (queue, changed) = (&queue, &changed);
+                        scope.spawn(move || {
+                            if let Err(err) = self.serve_worker(stream, queue, changed, describe, work) {
+                                log::warn!("worker {}: {}", address, err);
+                            }
+                        });
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => std::thread::sleep(Duration::from_millis(50)),
+                    Err(err) => log::warn!("accepting a worker: {}", err),
+                }
+            }
+            // Wake idle workers to be sent home; if everything was stopped, cut off the busy ones too
+            let queue = queue.lock().unwrap();
+            if queue.stopped {
+                for worker in &queue.workers {
+                    let _ = worker.shutdown(Shutdown::Both);
+                }
+            }
+            changed.notify_all();
+        });
+        
+        let mut queue = queue.into_inner().unwrap();
+        for unit in (0..units).filter(|&unit| !queue.finished[unit]) {
+            queue.given_up.push((unit, format!("{} was stopped before it was done", describe(unit))));
+        }
+        Ok(queue.given_up)
+    }
+    
+    /// Send a worker units until none are left
+    fn serve_worker(&self, stream: TcpStream, queue: &Mutex<Queue>, changed: &Condvar, describe: &(dyn Fn(usize) -> String + Sync), work: &Work) -> io::Result<()> {
+        let mut connection = Connection { reader: BufReader::new(stream.try_clone()?), writer: stream.try_clone()?, job: None };
+        stream.set_read_timeout(Some(self.timeout))?; // Only answers to units are read
+        queue.lock().unwrap().workers.push(stream);
+        
+        loop {
+            // Take the next unit, waiting while others are out in case they fail and come back
+            let unit = {
+                let mut queue = queue.lock().unwrap();
+                loop {
+                    if queue.stopped {
+                        return Ok(());
+                    }
+                    if let Some(unit) = queue.pending.pop_front() {
+                        break Some(unit);
+                    }
+                    if queue.remaining == 0 {
+                        break None;
+                    }
+                    queue = changed.wait(queue).unwrap();
+                }
+            };
+            let Some(unit) = unit else {
+                return connection.writer.write_all(&order_line(&Order::Done));
+            };
+            
+            let result = work(&mut connection, unit).map_err(|err| timed_out(err, self.timeout));
+            let mut queue = queue.lock().unwrap();
+            if queue.stopped {
+                return result.map(|_| ());
+            }
+            let (reason, broken) = match result {
+                Ok(Ok(())) => {
+                    queue.finished[unit] = true;
+                    queue.remaining -= 1;
+                    log::info!("{} done, {} to go", describe(unit), queue.remaining);
+                    changed.notify_all();
+                    continue;
+                }
+                Ok(Err(message)) => (message, None),
+                Err(err) => (err.to_string(), Some(err)),
+            };
+            let failures = queue.failures.entry(unit).or_insert(0);
+            *failures += 1;
+            if *failures >= self.attempts {
+                let error = format!("{} failed on {} workers, last with: {}", describe(unit), failures, reason);
+                log::warn!("{}", error);
+                queue.given_up.push((unit, error));
+                queue.finished[unit] = true;
+                queue.remaining -= 1;
+                queue.stopped |= self.give_up_all;
+            } else {
+                queue.pending.push_front(unit);
+            }
+            changed.notify_all();
+            if let Some(err) = broken {
+                return Err(err);
+            }
+        }
+    }
+}
+
+/// A read timing out as the error saying so
+fn timed_out(err: io::Error, timeout: Duration) -> io::Error {
+    match err.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => io::Error::new(io::ErrorKind::TimedOut, format!("no answer in {:?}", timeout)),
+        _ => err,
+    }
+}
+
+/// A coordinator's connection to a worker, and which job it last sent it
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    job: Option<usize>,
+}
+
+impl Connection {
+    /// Have the worker render rows of tiles of a job, sending it the job first unless it was the last one sent
+    /// (jobs are told apart by index); the outer error is the connection failing, the inner one the worker's own
+    fn render(&mut self, (index, job): (usize, &RenderRequest), rows: Range<u32>, size: (u32, u32)) -> io::Result<Result<Framebuffer, String>> {
+        if self.job != Some(index) {
+            self.writer.write_all(&order_line(&Order::Job(Box::new(job.clone()))))?;
+            self.job = Some(index);
+        }
+        self.writer.write_all(&order_line(&Order::Band { rows: rows.clone() }))?;
+        let expected = (size.0, (rows.end * TILE_SIZE).min(size.1) - rows.start * TILE_SIZE);
+        match read_line::<Reply>(&mut self.reader)? {
+            Reply::Band { width, height } if (width, height) == expected => read_pixels(&mut self.reader, width, height).map(Ok),
+            Reply::Band { width, height } => Err(invalid(format!("band is {}x{}, expected {}x{}", width, height, expected.0, expected.1))),
+            Reply::Error { message } => Ok(Err(message)),
+        }
+    }
+}
+
+/// Renders bands of images for a coordinator or farm, one job after another
+pub struct Worker {
+    pub threads: usize, // Render threads
+}
+
+impl Worker {
+    /// Render what the coordinator on the other end of the stream asks for until it has nothing left
+    /// A job that cannot be built is answered with an error for each of its bands
+    pub fn run(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+        let mut current = None;
+        
+        loop {
+            match read_line::<Order>(&mut reader)? {
+                Order::Job(job) => {
+                    let built = job.check().and_then(|()| job.build()).map(|(scene, camera)| (scene, camera, job.renderer(self.threads)));
+                    if let Err(message) = &built {
+                        log::warn!("job: {}", message);
+                    }
+                    current = Some((job, built));
+                }
+                Order::Band { rows } => match &current {
+                    Some((job, Ok((scene, camera, renderer)))) => {
+                        let band = renderer.render_tile_rows(scene, camera, (job.width, job.height), rows.clone());
+                        log::info!("rendered tile rows {}..{}", rows.start, rows.end);
+                        writer.write_all(&reply_line(&Reply::Band { width: band.width, height: band.height }))?;
+                        writer.write_all(&pixel_bytes(&band))?;
+                    }
+                    Some((_, Err(message))) => writer.write_all(&reply_line(&Reply::Error { message: message.clone() }))?,
+                    None => return Err(invalid("a band before any job".to_string())),
+                },
+                Order::Done => return Ok(()),
+            }
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn order_line(order: &Order) -> Vec<u8> {
+    let mut line = serde_json::to_vec(order).expect("orders serialize");
+    line.push(b'\n');
+    line
+}
+
+fn reply_line(reply: &Reply) -> Vec<u8> {
+    let mut line = serde_json::to_vec(reply).expect("replies serialize");
+    line.push(b'\n');
+    line
+}
+
+/// Read one JSON line; the connection closing first is an UnexpectedEof error
+fn read_line<T: for<'de> Deserialize<'de>>(reader: &mut impl BufRead) -> io::Result<T> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+    }
+    serde_json::from_str(&line).map_err(|err| invalid(err.to_string()))
+}
+
+/// A framebuffer's pixels as little-endian f64 RGBA
+#[allow(clippy::unnecessary_cast)] // Float is only f64 without the f32 feature
+fn pixel_bytes(framebuffer: &Framebuffer) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(framebuffer.width as usize * framebuffer.height as usize * 32);
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            let (color, alpha) = (framebuffer.color(x, y), framebuffer.alpha(x, y));
+            for value in [color.r, color.g, color.b, alpha] {
+                bytes.extend_from_slice(&(value as f64).to_le_bytes());
+            }
+        }
+    }
+    bytes
+}
+
+/// Read a width × height framebuffer written by `pixel_bytes`
+fn read_pixels(reader: &mut impl Read, width: u32, height: u32) -> io::Result<Framebuffer> {
+    let mut bytes = vec![0; width as usize * height as usize * 32];
+    reader.read_exact(&mut bytes)?;
+    let mut framebuffer = Framebuffer::new(width, height);
+    for (i, pixel) in bytes.chunks_exact(32).enumerate() {
+        let value = |channel: usize| f64::from_le_bytes(pixel[8 * channel..8 * channel + 8].try_into().unwrap()) as Float;
+        framebuffer.set(i as u32 % width, i as u32 / width, Color::new(value(0), value(1), value(2)), value(3));
+    }
+    Ok(framebuffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene_file::SceneDesc;
+    
+    #[test]
+    fn test_distributed_render() {
+        let scene = r#"{
+            "camera": { "look_from": [0, 1, 4], "look_at": [0, 0.5, 0] },
+            "lights": [{ "position": [3, 5, 3] }],
+            "objects": [
+                { "type": "plane", "point": [0, 0, 0], "normal": [0, 1, 0], "material": "gray" },
+                { "type": "sphere", "center": [0, 0.7, 0], "radius": 0.7, "material": "red" }
+            ]
+        }"#;
+        let job = RenderRequest { scene: Some(SceneDesc::from_json(scene).unwrap()), width: 40, height: 50, samples: 4, path_trace: true, ..RenderRequest::default() };
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        
+        // A worker that takes a band and vanishes, then one that does the work
+        let flaky = || {
+            let stream = TcpStream::connect(address).unwrap();
+            let mut reader = BufReader::new(stream);
+            assert!(matches!(read_line::<Order>(&mut reader).unwrap(), Order::Job(_)));
+            read_line::<Order>(&mut reader).unwrap()
+        };
+        let image = std::thread::scope(|scope| {
+            let coordinator = scope.spawn(|| Coordinator { band_rows: 2, attempts: 2, timeout: Duration::from_secs(60) }.render(&listener, &job));
+            assert!(matches!(flaky(), Order::Band { rows } if rows == (0..2)));
+            scope.spawn(|| Worker { threads: 2 }.run(TcpStream::connect(address).unwrap()).unwrap());
+            coordinator.join().unwrap().unwrap()
+        });
+        let (scene, camera) = job.build().unwrap();
+        let expected = job.renderer(1).render_framebuffer(&scene, &camera, 40, 50);
+        for (x, y) in (0..50).flat_map(|y| (0..40).map(move |x| (x, y))) {
+            assert_eq!(image.color(x, y), expected.color(x, y), "({}, {})", x, y);
+        }
+        
+        // A band failing on every worker it is given to gives the render up
+        let failing = std::thread::scope(|scope| {
+            let coordinator = scope.spawn(|| Coordinator { band_rows: 4, attempts: 1, timeout: Duration::from_secs(60) }.render(&listener, &job));
+            flaky();
+            coordinator.join().unwrap()
+        });
+        assert!(failing.is_err_and(|error| error.starts_with("tile rows 0..4 failed on 1 workers")));
+        
+        // So does one whose worker takes it and never answers
+        let stalled = std::thread::scope(|scope| {
+            let coordinator = scope.spawn(|| Coordinator { band_rows: 4, attempts: 1, timeout: Duration::from_millis(100) }.render(&listener, &job));
+            let stream = TcpStream::connect(address).unwrap();
+            let mut reader = BufReader::new(&stream);
+            assert!(matches!(read_line::<Order>(&mut reader).unwrap(), Order::Job(_)));
+            assert!(matches!(read_line::<Order>(&mut reader).unwrap(), Order::Band { .. }));
+            coordinator.join().unwrap()
+        });
+        assert!(stalled.is_err_and(|error| error.ends_with("no answer in 100ms")));
+    }
+    
+    #[test]
+    fn test_farm() {
+        // Three frames of a ball moving right; the middle one names a material that does not exist
+        let frame = |x: f64, material: &str| {
+            let scene = format!(r#"{{
+                "camera": {{ "look_from": [0, 0, 4], "look_at": [0, 0, 0] }},
+                "lights": [{{ "position": [0, 5, 5] }}],
+                "objects": [{{ "type": "sphere", "center": [{}, 0, 0], "radius": 0.5, "material": "{}" }}]
+            }}"#, x, material);
+            RenderRequest { scene: Some(SceneDesc::from_json(&scene).unwrap()), width: 24, height: 20, ..RenderRequest::default() }
+        };
+        let frames = [frame(-1.0, "red"), frame(0.0, "missing"), frame(1.0, "red")];
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        
+        // One worker renders every frame it can, one job after another, and stays for the rest
+        let images = Mutex::new(Vec::new());
+        let not_rendered = std::thread::scope(|scope| {
+            let farm = scope.spawn(|| Farm { attempts: 2, timeout: Duration::from_secs(60) }.render(&listener, &frames, || false, |frame, image| images.lock().unwrap().push((frame, image))));
+            scope.spawn(|| Worker { threads: 1 }.run(TcpStream::connect(address).unwrap()).unwrap());
+            farm.join().unwrap().unwrap()
+        });
+        assert_eq!(not_rendered.len(), 1);
+        assert_eq!(not_rendered[0].0, 1);
+        assert!(not_rendered[0].1.starts_with("frame 1 failed on 2 workers"), "{}", not_rendered[0].1);
+        let mut images = images.into_inner().unwrap();
+        images.sort_by_key(|(frame, _)| *frame);
+        assert_eq!(images.iter().map(|(frame, _)| *frame).collect::<Vec<_>>(), vec![0, 2]);
+        let (scene, camera) = frames[2].build().unwrap();
+        let expected = frames[2].renderer(1).render_framebuffer(&scene, &camera, 24, 20);
+        assert!((0..20).all(|y| (0..24).all(|x| images[1].1.color(x, y) == expected.color(x, y))));
+        
+        // With no workers, stopping leaves every frame unrendered
+        let stopped = Farm { attempts: 1, timeout: Duration::from_secs(60) }.render(&listener, &frames, || true, |_, _| unreachable!()).unwrap();
+        assert_eq!(stopped.len(), 3);
+        
+        // A manifest lists frames by scene file, and only counts them for the same options
+        let mut manifest = Manifest::new(&frames[0], 8);
+        manifest.finished.insert("a.json".to_string(), "a.png".to_string());
+        let manifest: Manifest = serde_json::from_str(&serde_json::to_string(&manifest).unwrap()).unwrap();
+        assert!(manifest.options.scene.is_none());
+        assert!(manifest.matches(&frames[2], 8));
+        assert!(!manifest.matches(&RenderRequest { seed: 7, ..frame(0.0, "red") }, 8));
+        assert!(!manifest.matches(&frames[2], 16));
+        #[cfg(feature = "fs")]
+        assert!(!manifest.is_finished("a.json", Path::new("a.png"))); // No such file
+    }
+}
@@ -0,0 +1,168 @@
+use crate::math::{consts::PI, Float, Vec3};
+use crate::hdr::HdrImage;
+use crate::sampling::{luminance, Rng};
+
+/// Equirectangular HDR image lighting the scene from infinitely far away
+/// The top row is straight up (+y) and the image center faces -z before rotation.
+/// Directions are importance-sampled in proportion to the luminance of their pixel, so a small
+/// bright sun is found by light sampling instead of by chance
+pub struct Environment {
+    image: HdrImage,
+    intensity: Float,        // Radiance multiplier
+    rotation: Float,         // Radians about the vertical axis
+    marginal: Vec<Float>,    // Cumulative probability of picking each row
+    conditional: Vec<Float>, // Cumulative probability of each column within its row, row after row
+    weights: Vec<Float>,     // Probability of picking each pixel
+}
+
+impl Environment {
+    /// Environment from an equirectangular image, rotated `rotation` degrees about the vertical axis
+    pub fn new(image: HdrImage, intensity: Float, rotation: Float) -> Self {
+        let (width, height) = (image.width as usize, image.height as usize);
+        
+        // Rows near the poles cover less solid angle
+        let mut weights: Vec<Float> = image
+            .pixels
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| {
+                let theta = ((i / width) as Float + 0.5) / height as Float * PI;
+                luminance(color).max(0.0) * theta.sin()
+            })
+            .collect();
+        let total: Float = weights.iter().sum();
+        if total > 0.0 {
+            weights.iter_mut().for_each(|weight| *weight /= total);
+        }
+        
+        let mut marginal = Vec::with_capacity(height);
+        let mut conditional = Vec::with_capacity(width * height);
+        let mut rows_sum = 0.0;
+        for row in weights.chunks(width.max(1)) {
+            let row_total: Float = row.iter().sum();
+            let mut sum = 0.0;
+            for &weight in row {
+                sum += weight;
+                conditional.push(if row_total > 0.0 { sum / row_total } else { 0.0 });
+            }
+            rows_sum += row_total;
+            marginal.push(rows_sum);
+        }
+        
+        Self { image, intensity, rotation: rotation.to_radians(), marginal, conditional, weights }
+    }
+    
+    /// Environment computed from the radiance in each direction (a procedural sky), taken at the
+    /// centers of a `width` × `height` equirectangular image
+    pub fn from_fn(width: u32, height: u32, intensity: Float, radiance: impl Fn(Vec3) -> Vec3) -> Self {
+        let pixels = (0..width * height)
+            .map(|i| {
+                let (u, v) = (((i % width) as Float + 0.5) / width as Float, ((i / width) as Float + 0.5) / height as Float);
+                let (theta, phi) = (v * PI, (u - 0.5) * 2.0 * PI);
+                radiance(Vec3::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos()))
+            })
+            .collect();
+        Self::new(HdrImage { width, height, pixels }, intensity, 0.0)
+    }
+    
+    /// Radiance arriving from `direction`
+    pub fn radiance(&self, direction: Vec3) -> Vec3 {
+        if self.image.pixels.is_empty() {
+            return Vec3::zero();
+        }
+        let (x, y) = self.pixel_at(direction);
+        self.image.pixel(x, y) * self.intensity
+    }
+    
+    /// Density (per steradian) with which `sample` picks `direction`
+    pub fn pdf(&self, direction: Vec3) -> Float {
+        if self.image.pixels.is_empty() {
+            return 0.0;
+        }
+        let (x, y) = self.pixel_at(direction);
+        let sin_theta = (1.0 - direction.normalize().y.powi(2)).max(0.0).sqrt();
+        self.pixel_pdf(x, y, sin_theta)
+    }
+    
+    /// Pick a direction toward the environment, returning it with its radiance and density,
+    /// or None if the environment is black
+    pub fn sample(&self, rng: &mut Rng) -> Option<(Vec3, Vec3, Float)> {
+        let (width, height) = (self.image.width as usize, self.image.height as usize);
+        if self.marginal.last().is_none_or(|&total| total <= 0.0) {
+            return None;
+        }
+        
+        let (u, v) = (rng.next_float(), rng.next_float());
+        let row = self.marginal.partition_point(|&c| c <= u).min(height - 1);
+        let columns = &self.conditional[row * width..(row + 1) * width];
+        let column = columns.partition_point(|&c| c <= v).min(width - 1);
+        
+        // Uniform within the pixel, in image coordinates
+        let u = (column as Float + rng.next_float()) / width as Float;
+        let v = (row as Float + rng.next_float()) / height as Float;
+        let (theta, phi) = (v * PI, (u - 0.5) * 2.0 * PI + self.rotation);
+        let direction = Vec3::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos());
+        
+        let pdf = self.pixel_pdf(column as u32, row as u32, theta.sin());
+        let radiance = self.image.pixel(column as u32, row as u32) * self.intensity;
+        (pdf > 0.0).then_some((direction, radiance, pdf))
+    }
+    
+    /// Pixel seen in `direction`
+    fn pixel_at(&self, direction: Vec3) -> (u32, u32) {
+        let direction = direction.normalize();
+        let phi = direction.x.atan2(-direction.z) - self.rotation;
+        let u = (phi / (2.0 * PI) + 0.5).rem_euclid(1.0);
+        let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+        let x = ((u * self.image.width as Float) as u32).min(self.image.width - 1);
+        let y = ((v * self.image.height as Float) as u32).min(self.image.height - 1);
+        (x, y)
+    }
+    
+    /// Solid-angle density of sampling within pixel (x, y) at polar sine `sin_theta`
+    /// Each pixel spans 2π/width × π/height in (φ, θ), and dω = sin θ dθ dφ
+    fn pixel_pdf(&self, x: u32, y: u32, sin_theta: Float) -> Float {
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let (width, height) = (self.image.width as Float, self.image.height as Float);
+        let weight = self.weights[(y * self.image.width + x) as usize];
+        weight * width * height / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_importance_sampling() {
+        // Dim sky with one very bright "sun" pixel above the horizon
+        let (width, height) = (32, 16);
+        let mut pixels = vec![Vec3::new(0.5, 0.5, 0.5); width * height];
+        pixels[4 * width + 10] = Vec3::new(5000.0, 5000.0, 5000.0);
+        let environment = Environment::new(HdrImage { width: 32, height: 16, pixels }, 1.0, 30.0);
+        
+        // Reference irradiance on an upward-facing surface, summed pixel by pixel
+        let mut reference = 0.0;
+        for y in 0..height {
+            for x in 0..width {
+                let theta = (y as Float + 0.5) / height as Float * PI;
+                let solid_angle = 2.0 * PI * PI / (width * height) as Float * theta.sin();
+                reference += environment.image.pixel(x as u32, y as u32).x * theta.cos().max(0.0) * solid_angle;
+            }
+        }
+        
+        // Importance-sampled estimate, checking that densities agree with `pdf`
+        let mut rng = Rng::new(5);
+        let count = 20_000;
+        let mut estimate = 0.0;
+        for _ in 0..count {
+            let (direction, radiance, pdf) = environment.sample(&mut rng).unwrap();
+            assert!((environment.pdf(direction) - pdf).abs() <= 1e-3 * pdf, "{} vs {}", environment.pdf(direction), pdf);
+            assert_eq!(environment.radiance(direction), radiance);
+            estimate += radiance.x * direction.y.max(0.0) / pdf / count as Float;
+        }
+        assert!((estimate - reference).abs() < 0.02 * reference, "{} vs {}", estimate, reference);
+    }
+}
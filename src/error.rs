@@ -0,0 +1,95 @@
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::ConfigError;
+use crate::image::ImageError;
+use crate::scene_file::SceneError;
+use crate::validate::Diagnostic;
+
+/// Errors surfaced to the user by the `rt` command
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{}: {source}", path.display())]
+    Scene { path: PathBuf, source: SceneError },
+    
+    #[error("{}: {} (run `rt validate` for all diagnostics)", path.display(), summarize(errors))]
+    InvalidScene { path: PathBuf, errors: Vec<Diagnostic> },
+    
+    #[error("could not write {target}: {source}")]
+    Io { target: String, source: io::Error },
+    
+    #[error("could not watch {} for changes: {source}", path.display())]
+    Watch { path: PathBuf, source: io::Error },
+    
+    #[error("could not serve on {address}: {source}")]
+    Serve { address: String, source: io::Error },
+    
+    #[error("distributed render failed: {0}")]
+    Distributed(String),
+    
+    #[error("invalid argument {name}: {reason}")]
+    InvalidArgument { name: &'static str, reason: String },
+    
+    #[error("unsupported output format '{0}' (use .png, .exr or .ppm)")]
+    UnsupportedFormat(String),
+    
+    #[error("{}: {source}", path.display())]
+    Config { path: PathBuf, source: ConfigError },
+    
+    #[error("{}: {source}", path.display())]
+    Image { path: PathBuf, source: ImageError },
+    
+    #[error("images differ in size: {}x{} and {}x{}", a.0, a.1, b.0, b.1)]
+    SizeMismatch { a: (u32, u32), b: (u32, u32) },
+}
+
+impl Error {
+    /// Process exit status: 2 for bad command lines or config files (as clap uses), 3 for unreadable
+    /// scenes or images, 4 when `rt diff` finds different images, 1 for I/O failures
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::InvalidArgument { .. } | Error::UnsupportedFormat(_) | Error::Config { .. } => 2,
+            Error::Scene { .. } | Error::InvalidScene { .. } | Error::Image { .. } => 3,
+            Error::SizeMismatch { .. } => 4,
+            Error::Io { .. } | Error::Watch { .. } | Error::Serve { .. } | Error::Distributed(_) => 1,
+        }
+    }
+}
+
+/// First validation error, plus how many more there are
+fn summarize(errors: &[Diagnostic]) -> String {
+    match errors {
+        [] => "invalid scene".to_string(),
+        [only] => format!("{}: {}", only.field, only.message),
+        [first, rest @ ..] => format!("{}: {} (and {} more errors)", first.field, first.message, rest.len()),
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::Severity;
+    
+    #[test]
+    fn test_error_messages() {
+        let diagnostic = |field: &str| Diagnostic {
+            severity: Severity::Error,
+            field: field.to_string(),
+            message: "must be positive, got 0".to_string(),
+        };
+        let err = Error::InvalidScene {
+            path: PathBuf::from("scene.json"),
+            errors: vec![diagnostic("objects[0].radius"), diagnostic("objects[1].radius")],
+        };
+        assert_eq!(
+            err.to_string(),
+            "scene.json: objects[0].radius: must be positive, got 0 (and 1 more errors) (run `rt validate` for all diagnostics)"
+        );
+        assert_eq!(err.exit_code(), 3);
+        assert_eq!(Error::UnsupportedFormat("jpg".to_string()).exit_code(), 2);
+        let watch = Error::Watch { path: PathBuf::from("scene.json"), source: io::Error::other("no inotify") };
+        assert_eq!((watch.to_string().as_str(), watch.exit_code()), ("could not watch scene.json for changes: no inotify", 1));
+    }
+}
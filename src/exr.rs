@@ -0,0 +1,106 @@
+/// Bytes every OpenEXR file starts with, followed by the version (2, single-part scanline)
+pub const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+
+/// Minimal OpenEXR encoder (32-bit float channels, one scanline per block, uncompressed)
+/// Values are stored as they are, so depths stay metric and colors keep their full range
+pub struct ExrWriter {
+    width: u32,
+    height: u32,
+    channels: Vec<(String, Vec<f32>)>, // Name ("R", "G", "B", "A", "Z") and row-major values
+}
+
+impl ExrWriter {
+    /// Create an image with no channels yet
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            channels: Vec::new(),
+        }
+    }
+    
+    /// Add a channel of row-major values from the top-left pixel
+    pub fn channel(mut self, name: &str, values: Vec<f32>) -> Self {
+        assert_eq!(values.len(), (self.width * self.height) as usize, "channel size mismatch");
+        self.channels.push((name.to_string(), values));
+        self
+    }
+    
+    /// Encode as EXR file bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Readers expect the channels in alphabetical order, in the header and in each scanline
+        let mut channels: Vec<&(String, Vec<f32>)> = self.channels.iter().collect();
+        channels.sort_by(|a, b| a.0.cmp(&b.0));
+        
+        let mut exr = MAGIC.to_vec();
+        exr.extend_from_slice(&2u32.to_le_bytes());
+        
+        let mut list = Vec::new();
+        for (name, _) in &channels {
+            list.extend_from_slice(name.as_bytes());
+            list.push(0);
+            list.extend_from_slice(&2i32.to_le_bytes()); // FLOAT
+            list.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved
+            list.extend_from_slice(&1i32.to_le_bytes()); // x and y sampling
+            list.extend_from_slice(&1i32.to_le_bytes());
+        }
+        list.push(0);
+        let window: Vec<u8> = [0, 0, self.width as i32 - 1, self.height as i32 - 1].iter().flat_map(|v| v.to_le_bytes()).collect();
+        write_attribute(&mut exr, "channels", "chlist", &list);
+        write_attribute(&mut exr, "compression", "compression", &[0]); // NO_COMPRESSION
+        write_attribute(&mut exr, "dataWindow", "box2i", &window);
+        write_attribute(&mut exr, "displayWindow", "box2i", &window);
+        write_attribute(&mut exr, "lineOrder", "lineOrder", &[0]); // INCREASING_Y
+        write_attribute(&mut exr, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+        write_attribute(&mut exr, "screenWindowCenter", "v2f", &[0; 8]);
+        write_attribute(&mut exr, "screenWindowWidth", "float", &1f32.to_le_bytes());
+        exr.push(0);
+        
+        // Offset table, then each scanline: its y, its byte count and each channel's row in turn
+        let block_size = channels.len() * self.width as usize * 4;
+        let table_end = exr.len() + self.height as usize * 8;
+        for y in 0..self.height as usize {
+            exr.extend_from_slice(&((table_end + y * (block_size + 8)) as u64).to_le_bytes());
+        }
+        for y in 0..self.height as usize {
+            exr.extend_from_slice(&(y as i32).to_le_bytes());
+            exr.extend_from_slice(&(block_size as i32).to_le_bytes());
+            for (_, values) in &channels {
+                let row = &values[y * self.width as usize..(y + 1) * self.width as usize];
+                exr.extend(row.iter().flat_map(|value| value.to_le_bytes()));
+            }
+        }
+        exr
+    }
+}
+
+/// Append a header attribute: its name, type name, size and value
+fn write_attribute(out: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    for text in [name, kind] {
+        out.extend_from_slice(text.as_bytes());
+        out.push(0);
+    }
+    out.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_exr_structure() {
+        let exr = ExrWriter::new(2, 3).channel("Z", vec![1.0, 2.0, 3.0, 4.0, 5.0, f32::INFINITY]).channel("A", vec![0.5; 6]).to_bytes();
+        assert!(exr.starts_with(&[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]));
+        
+        // Channels are listed A before Z, and the last scanline (y = 2) holds A's row then Z's
+        let header = String::from_utf8_lossy(&exr);
+        assert!(header.find("A\0").unwrap() < header.find("Z\0").unwrap());
+        let table_end = exr.len() - 3 * 24;
+        let last = u64::from_le_bytes(exr[table_end - 8..table_end].try_into().unwrap()) as usize;
+        assert_eq!(last, exr.len() - 24);
+        assert_eq!(&exr[last..last + 8], &[2, 0, 0, 0, 16, 0, 0, 0]);
+        let values: Vec<f32> = exr[last + 8..].chunks(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect();
+        assert_eq!(values, vec![0.5, 0.5, 5.0, f32::INFINITY]);
+    }
+}
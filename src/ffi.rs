@@ -0,0 +1,163 @@
+//! C interface for embedding the renderer; the declarations are in `include/rt.h`
+//!
+//! Functions return 0 (or a non-null pointer) on success. On failure `rt_last_error` describes
+//! what went wrong on the calling thread.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::sync::Arc;
+
+use crate::render::{Progress, Renderer};
+use crate::scene_file::{SceneDesc, DEFAULT_FOV};
+use crate::validate;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: String) {
+    let message = CString::new(message).unwrap_or_else(|_| c"error message contained a NUL byte".to_owned());
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+/// A parsed and validated scene, owned by the caller until `rt_scene_free`
+pub struct RtScene {
+    desc: SceneDesc,
+    progress: Arc<Progress>,
+}
+
+/// Parse a scene from JSON text (includes can't be used); returns NULL on errors
+///
+/// # Safety
+/// `json` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_from_json(json: *const c_char) -> *mut RtScene {
+    if json.is_null() {
+        set_error("scene JSON is NULL".to_string());
+        return std::ptr::null_mut();
+    }
+    let Ok(json) = CStr::from_ptr(json).to_str() else {
+        set_error("scene JSON is not valid UTF-8".to_string());
+        return std::ptr::null_mut();
+    };
+    
+    let desc = match SceneDesc::from_json(json) {
+        Ok(desc) if !desc.include.is_empty() => {
+            set_error("includes can't be read here; flatten the scene with `rt export` first".to_string());
+            return std::ptr::null_mut();
+        }
+        Ok(desc) => desc,
+        Err(err) => {
+            set_error(err.to_string());
+            return std::ptr::null_mut();
+        }
+    };
+    if let Some(error) = validate::validate(&desc).into_iter().find(|d| d.severity == validate::Severity::Error) {
+        set_error(error.to_string());
+        return std::ptr::null_mut();
+    }
+    
+    Box::into_raw(Box::new(RtScene { desc, progress: Arc::default() }))
+}
+
+/// Free a scene from `rt_scene_from_json`; NULL is ignored
+///
+/// # Safety
+/// `scene` must come from `rt_scene_from_json`, must not be freed twice, and no render may be using it.
+#[no_mangle]
+pub unsafe extern "C" fn rt_scene_free(scene: *mut RtScene) {
+    if !scene.is_null() {
+        drop(Box::from_raw(scene));
+    }
+}
+
+/// Render into `buffer` as 8-bit RGB, row-major from the top-left pixel
+/// `buffer_len` must be at least `width * height * 3`; `threads` 0 uses all cores
+///
+/// # Safety
+/// `scene` must be a live scene and `buffer` must point to `buffer_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rt_render_rgb(
+    scene: *const RtScene,
+    width: u32,
+    height: u32,
+    samples: u32,
+    threads: u32,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> c_int {
+    let Some(scene) = scene.as_ref() else {
+        set_error("scene is NULL".to_string());
+        return -1;
+    };
+    if width == 0 || height == 0 || samples == 0 {
+        set_error("width, height and samples must be non-zero".to_string());
+        return -1;
+    }
+    let needed = width as usize * height as usize * 3;
+    if buffer.is_null() || buffer_len < needed {
+        set_error(format!("buffer holds {} bytes, {}x{} RGB needs {}", buffer_len, width, height, needed));
+        return -1;
+    }
+    
+    let (scene_objects, camera) = match scene.desc.build(1.0, DEFAULT_FOV, width as f64 / height as f64) {
+        Ok(built) => built,
+        Err(err) => {
+            set_error(err.to_string());
+            return -1;
+        }
+    };
+    let mut renderer = Renderer::new();
+    renderer.samples = samples;
+    renderer.threads = match threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n as usize,
+    };
+    renderer.progress = Some(scene.progress.clone());
+    
+    let rgb = renderer.render_framebuffer(&scene_objects, &camera, width, height).to_rgb8();
+    std::slice::from_raw_parts_mut(buffer, needed).copy_from_slice(&rgb);
+    0
+}
+
+/// Fraction of the scene's current (or last) render that is done, 0-1; safe to call from another thread
+///
+/// # Safety
+/// `scene` must be a live scene.
+#[no_mangle]
+pub unsafe extern "C" fn rt_render_progress(scene: *const RtScene) -> f64 {
+    scene.as_ref().map_or(0.0, |scene| scene.progress.fraction())
+}
+
+/// Message for the last failed call on this thread, or NULL; valid until the next failing call
+#[no_mangle]
+pub extern "C" fn rt_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ref().map_or(std::ptr::null(), |message| message.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_render_through_ffi() {
+        let json = c"{ \"camera\": { \"look_from\": [0, 0, 0], \"look_at\": [0, 0, -1] }, \"background\": [0, 0, 1] }";
+        unsafe {
+            let scene = rt_scene_from_json(json.as_ptr());
+            assert!(!scene.is_null());
+            assert_eq!(rt_render_progress(scene), 0.0);
+            
+            let mut buffer = vec![0u8; 4 * 3 * 3];
+            assert_eq!(rt_render_rgb(scene, 4, 3, 1, 1, buffer.as_mut_ptr(), buffer.len()), 0);
+            assert_eq!(&buffer[0..3], &[0, 0, 255]);
+            assert_eq!(rt_render_progress(scene), 1.0);
+            
+            assert_eq!(rt_render_rgb(scene, 8, 8, 1, 1, buffer.as_mut_ptr(), buffer.len()), -1);
+            assert!(CStr::from_ptr(rt_last_error()).to_str().unwrap().contains("needs 192"));
+            rt_scene_free(scene);
+            
+            assert!(rt_scene_from_json(c"{ \"objects\": [] }".as_ptr()).is_null());
+            assert!(CStr::from_ptr(rt_last_error()).to_str().unwrap().contains("camera"));
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use std::str::FromStr;
+
+use crate::math::Float;
+use crate::color::Color;
+use crate::ppm::PpmWriter;
+use crate::exr::ExrWriter;
+
+/// Floating-point RGBA image produced by the renderer
+/// Colors are stored premultiplied by alpha, so opaque renders are unaffected by the alpha channel
+pub struct Framebuffer {
+    pub width: u32,
+    pub height: u32,
+    colors: Vec<Color>,
+    alphas: Vec<Float>,
+}
+
+impl Framebuffer {
+    /// Create a black, fully transparent framebuffer
+    pub fn new(width: u32, height: u32) -> Self {
+        let size = width as usize * height as usize;
+        Self {
+            width,
+            height,
+            colors: vec![Color::black(); size],
+            alphas: vec![0.0; size],
+        }
+    }
+    
+    /// Set a pixel from a premultiplied color and its coverage (alpha)
+    pub fn set(&mut self, x: u32, y: u32, color: Color, alpha: Float) {
+        let index = self.index(x, y);
+        self.colors[index] = color;
+        self.alphas[index] = alpha;
+    }
+    
+    /// Premultiplied color at a pixel
+    pub fn color(&self, x: u32, y: u32) -> Color {
+        self.colors[self.index(x, y)]
+    }
+    
+    /// Alpha at a pixel (0.0 = transparent, 1.0 = opaque)
+    pub fn alpha(&self, x: u32, y: u32) -> Float {
+        self.alphas[self.index(x, y)]
+    }
+    
+    /// Position of a pixel in the row-major buffers
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+    
+    /// Scale every pixel by 2^ev (photographic exposure stops), before conversion to 8-bit
+    /// Unlike light brightness this acts on the final image, so it behaves the same in every scene
+    pub fn apply_exposure(&mut self, ev: f64) {
+        let scale = 2.0_f64.powf(ev) as Float;
+        for color in &mut self.colors {
+            *color = *color * scale;
+        }
+    }
+    
+    /// Gamma-encode every pixel (value^(1/gamma)); 1.0 leaves the image linear, 2.2 suits most displays
+    pub fn apply_gamma(&mut self, gamma: f64) {
+        if gamma == 1.0 {
+            return;
+        }
+        let gamma = gamma as Float;
+        
+        // Encode the straight color, not the premultiplied one, so edges keep their coverage
+        let encode = |value: Float| value.max(0.0).powf(1.0 / gamma);
+        for (color, &alpha) in self.colors.iter_mut().zip(&self.alphas) {
+            if alpha > 0.0 {
+                let straight = *color / alpha;
+                *color = Color::new(encode(straight.r), encode(straight.g), encode(straight.b)) * alpha;
+            }
+        }
+    }
+    
+    /// 8-bit RGB bytes, row-major from the top-left pixel (transparent areas come out black)
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.colors.len() * 3);
+        for color in &self.colors {
+            bytes.extend_from_slice(&[to_byte(color.r), to_byte(color.g), to_byte(color.b)]);
+        }
+        bytes
+    }
+    
+    /// 8-bit RGBA bytes with straight (non-premultiplied) alpha, as image editors expect
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.colors.len() * 4);
+        for (color, &alpha) in self.colors.iter().zip(&self.alphas) {
+            let straight = if alpha > 0.0 { *color / alpha } else { Color::black() };
+            bytes.extend_from_slice(&[
+                to_byte(straight.r),
+                to_byte(straight.g),
+                to_byte(straight.b),
+                to_byte(alpha),
+            ]);
+        }
+        bytes
+    }
+    
+    /// 16-bit RGB values, row-major from the top-left pixel, for gradients too smooth for 8 bits
+    pub fn to_rgb16(&self) -> Vec<u16> {
+        self.colors.iter().flat_map(|color| [to_u16(color.r), to_u16(color.g), to_u16(color.b)]).collect()
+    }
+    
+    /// 16-bit RGBA values with straight alpha
+    pub fn to_rgba16(&self) -> Vec<u16> {
+        let mut values = Vec::with_capacity(self.colors.len() * 4);
+        for (color, &alpha) in self.colors.iter().zip(&self.alphas) {
+            let straight = if alpha > 0.0 { *color / alpha } else { Color::black() };
+            values.extend_from_slice(&[to_u16(straight.r), to_u16(straight.g), to_u16(straight.b), to_u16(alpha)]);
+        }
+        values
+    }
+    
+    /// Convert to an EXR writer of the full-range float colors (premultiplied, as compositors expect), with or
+    /// without alpha
+    #[allow(clippy::unnecessary_cast)] // Float is only f64 without the f32 feature
+    pub fn to_exr(&self, with_alpha: bool) -> ExrWriter {
+        let channel = |value: fn(&Color) -> Float| self.colors.iter().map(|color| value(color) as f32).collect();
+        let writer = ExrWriter::new(self.width, self.height)
+            .channel("R", channel(|color| color.r))
+            .channel("G", channel(|color| color.g))
+            .channel("B", channel(|color| color.b));
+        if with_alpha {
+            return writer.channel("A", self.alphas.iter().map(|&alpha| alpha as f32).collect());
+        }
+        writer
+    }
+    
+    /// Convert to a PPM writer (alpha is dropped)
+    pub fn to_ppm(&self) -> PpmWriter {
+        let mut writer = PpmWriter::new(self.width, self.height);
+        for rgb in self.to_rgb8().chunks(3) {
+            writer.write_pixel(rgb[0], rgb[1], rgb[2]);
+        }
+        writer
+    }
+}
+
+/// How the two images of a stereo pair are put into one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoLayout {
+    SideBySide, // Left eye's image beside the right's, twice as wide, for cross-eyed viewing or VR headsets
+    Anaglyph,   // Red from the left eye and green and blue from the right, for red/cyan glasses
+}
+
+impl StereoLayout {
+    /// One image from the left and right eyes' images, which are the same size
+    pub fn combine(self, left: &Framebuffer, right: &Framebuffer) -> Framebuffer {
+        assert_eq!((left.width, left.height), (right.width, right.height), "stereo images differ in size");
+        match self {
+            StereoLayout::SideBySide => {
+                let mut combined = Framebuffer::new(left.width * 2, left.height);
+                for y in 0..left.height {
+                    for x in 0..left.width {
+                        combined.set(x, y, left.color(x, y), left.alpha(x, y));
+                        combined.set(x + left.width, y, right.color(x, y), right.alpha(x, y));
+                    }
+                }
+                combined
+            }
+            StereoLayout::Anaglyph => {
+                let mut combined = Framebuffer::new(left.width, left.height);
+                for y in 0..left.height {
+                    for x in 0..left.width {
+                        let (red, cyan) = (left.color(x, y), right.color(x, y));
+                        combined.set(x, y, Color::new(red.r, cyan.g, cyan.b), left.alpha(x, y).max(right.alpha(x, y)));
+                    }
+                }
+                combined
+            }
+        }
+    }
+}
+
+impl FromStr for StereoLayout {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "side-by-side" => Ok(Self::SideBySide),
+            "anaglyph" => Ok(Self::Anaglyph),
+            _ => Err(format!("unknown stereo layout '{}' (expected side-by-side or anaglyph)", s)),
+        }
+    }
+}
+
+/// How edges found by `Renderer::render_edges` are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wireframe {
+    Overlay, // Orange lines over the shaded image
+    Only,    // Black lines on white, without shading anything
+}
+
+impl Wireframe {
+    /// Line color over the shaded image, bright enough to stand out from most scenes
+    const OVERLAY_COLOR: Color = Color { r: 1.0, g: 0.55, b: 0.0 };
+    
+    /// Draw lines from each pixel's edge coverage (row by row) onto the framebuffer; Only replaces what it held
+    pub fn draw(self, framebuffer: &mut Framebuffer, edges: &[Float]) {
+        assert_eq!(edges.len(), framebuffer.colors.len(), "edge coverage size mismatch");
+        let (line, background) = match self {
+            Wireframe::Overlay => (Self::OVERLAY_COLOR, None),
+            Wireframe::Only => (Color::black(), Some(Color::white())),
+        };
+        for (index, &coverage) in edges.iter().enumerate() {
+            let (color, alpha) = match background {
+                Some(background) => (background, 1.0),
+                None => (framebuffer.colors[index], framebuffer.alphas[index]),
+            };
+            framebuffer.colors[index] = color * (1.0 - coverage) + line * coverage;
+            framebuffer.alphas[index] = alpha * (1.0 - coverage) + coverage;
+        }
+    }
+}
+
+impl FromStr for Wireframe {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "overlay" => Ok(Self::Overlay),
+            "only" => Ok(Self::Only),
+            _ => Err(format!("unknown wireframe mode '{}' (expected overlay or only)", s)),
+        }
+    }
+}
+
+/// Convert a [0, 1] channel value to a byte, clamping out-of-range values
+fn to_byte(value: Float) -> u8 {
+    (255.0 * value.clamp(0.0, 1.0)) as u8
+}
+
+/// Convert a [0, 1] channel value to a 16-bit sample, clamping out-of-range values
+fn to_u16(value: Float) -> u16 {
+    (65535.0 * value.clamp(0.0, 1.0)) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_framebuffer_alpha() {
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.set(0, 0, Color::new(1.0, 0.0, 0.0), 1.0);
+        framebuffer.set(1, 0, Color::new(0.0, 0.25, 0.0), 0.5); // premultiplied half-covered green
+        
+        assert_eq!(framebuffer.to_rgb8(), vec![255, 0, 0, 0, 63, 0]);
+        assert_eq!(framebuffer.to_rgba8(), vec![255, 0, 0, 255, 0, 127, 0, 127]);
+        assert_eq!(framebuffer.to_rgb16(), vec![65535, 0, 0, 0, 16383, 0]);
+        assert_eq!(framebuffer.to_rgba16(), vec![65535, 0, 0, 65535, 0, 32767, 0, 32767]);
+        assert_eq!(framebuffer.alpha(1, 0), 0.5);
+    }
+    
+    #[test]
+    fn test_exposure() {
+        let mut framebuffer = Framebuffer::new(1, 1);
+        framebuffer.set(0, 0, Color::new(0.25, 0.5, 2.0), 1.0);
+        
+        framebuffer.apply_exposure(1.0);
+        assert_eq!(framebuffer.color(0, 0), Color::new(0.5, 1.0, 4.0));
+        
+        framebuffer.apply_exposure(-2.0);
+        assert_eq!(framebuffer.color(0, 0), Color::new(0.125, 0.25, 1.0));
+        assert_eq!(framebuffer.alpha(0, 0), 1.0);
+        
+        framebuffer.apply_gamma(2.0);
+        assert_eq!(framebuffer.color(0, 0), Color::new((0.125 as Float).sqrt(), 0.5, 1.0));
+    }
+    
+    #[test]
+    fn test_stereo_layouts() {
+        let (mut left, mut right) = (Framebuffer::new(1, 1), Framebuffer::new(1, 1));
+        left.set(0, 0, Color::new(0.8, 0.6, 0.4), 1.0);
+        right.set(0, 0, Color::new(0.2, 0.3, 0.5), 1.0);
+        
+        let side_by_side = StereoLayout::SideBySide.combine(&left, &right);
+        assert_eq!((side_by_side.width, side_by_side.height), (2, 1));
+        assert_eq!((side_by_side.color(0, 0), side_by_side.color(1, 0)), (left.color(0, 0), right.color(0, 0)));
+        assert_eq!(StereoLayout::Anaglyph.combine(&left, &right).color(0, 0), Color::new(0.8, 0.3, 0.5));
+        assert_eq!("anaglyph".parse(), Ok(StereoLayout::Anaglyph));
+    }
+    
+    #[test]
+    fn test_wireframe() {
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.set(0, 0, Color::new(0.0, 0.0, 1.0), 1.0);
+        
+        // A full line replaces the pixel; half a line over the transparent background half covers it
+        Wireframe::Overlay.draw(&mut framebuffer, &[1.0, 0.5]);
+        assert_eq!(framebuffer.color(0, 0), Color::new(1.0, 0.55, 0.0));
+        assert_eq!((framebuffer.color(1, 0), framebuffer.alpha(1, 0)), (Color::new(0.5, 0.275, 0.0), 0.5));
+        
+        Wireframe::Only.draw(&mut framebuffer, &[0.0, 0.25]);
+        assert_eq!((framebuffer.color(0, 0), framebuffer.color(1, 0)), (Color::white(), Color::new(0.75, 0.75, 0.75)));
+        assert_eq!(framebuffer.alpha(1, 0), 1.0);
+        assert!("lines".parse::<Wireframe>().is_err());
+    }
+}
@@ -0,0 +1,781 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::color::Color;
+use crate::image::RgbaImage;
+use crate::material::Material;
+use crate::math::{Float, Vec3, Quaternion, Mat4, Aabb};
+use crate::texture::Texture;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GltfError {
+    #[error("could not read glTF file: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid glTF JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid glTF file: {0}")]
+    Invalid(String),
+}
+
+/// Scene from a glTF 2.0 file (`.gltf` JSON or `.glb` binary): its meshes in their own space, the materials
+/// they use mapped to ours, and where the nodes of its default scene place meshes, cameras and lights
+#[derive(Debug, Clone)]
+pub struct GltfScene {
+    pub meshes: Vec<Vec<GltfPrimitive>>, // Each mesh's primitives
+    pub materials: Vec<Material>,
+    pub instances: Vec<(usize, Mat4)>,   // Mesh index and world transform of each node with a mesh
+    pub cameras: Vec<GltfCamera>,
+    pub lights: Vec<GltfLight>,
+}
+
+/// Triangles of one mesh primitive, sharing their vertices
+#[derive(Debug, Clone)]
+pub struct GltfPrimitive {
+    pub positions: Vec<Vec3>,
+    pub normals: Option<Vec<Vec3>>,
+    pub uvs: Option<Vec<(Float, Float)>>, // Flipped to v up, as our textures read them
+    pub colors: Option<Vec<Color>>,       // Linear vertex colors, multiplying the base color
+    pub triangles: Vec<[usize; 3]>,       // Counter-clockwise seen from the front
+    pub material: Option<usize>,          // Index into `materials`, the default material when unset
+}
+
+/// Perspective camera placed by a node: it looks down the transform's -z axis with +y up
+#[derive(Debug, Clone)]
+pub struct GltfCamera {
+    pub transform: Mat4,
+    pub fov: Float, // Vertical field of view in degrees
+}
+
+/// Point or spot light from the `KHR_lights_punctual` extension (spot cones are dropped)
+#[derive(Debug, Clone)]
+pub struct GltfLight {
+    pub position: Vec3,
+    pub color: Color,
+    pub candela: Float, // Luminous intensity
+}
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+// The parts of the glTF JSON schema that are read, with the spec's defaults
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Document {
+    #[serde(default)]
+    scene: Option<usize>,
+    #[serde(default)]
+    scenes: Vec<SceneDef>,
+    #[serde(default)]
+    nodes: Vec<NodeDef>,
+    #[serde(default)]
+    meshes: Vec<MeshDef>,
+    #[serde(default)]
+    accessors: Vec<AccessorDef>,
+    #[serde(default)]
+    buffer_views: Vec<BufferViewDef>,
+    #[serde(default)]
+    buffers: Vec<BufferDef>,
+    #[serde(default)]
+    materials: Vec<MaterialDef>,
+    #[serde(default)]
+    textures: Vec<TextureDef>,
+    #[serde(default)]
+    images: Vec<ImageDef>,
+    #[serde(default)]
+    cameras: Vec<CameraDef>,
+    #[serde(default)]
+    extensions: DocumentExtensions,
+}
+
+#[derive(Deserialize)]
+struct SceneDef {
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct NodeDef {
+    #[serde(default)]
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    camera: Option<usize>,
+    matrix: Option<[f64; 16]>, // Column-major
+    translation: Option<[f64; 3]>,
+    rotation: Option<[f64; 4]>, // x, y, z, w
+    scale: Option<[f64; 3]>,
+    #[serde(default)]
+    extensions: NodeExtensions,
+}
+
+#[derive(Deserialize)]
+struct MeshDef {
+    primitives: Vec<PrimitiveDef>,
+}
+
+#[derive(Deserialize)]
+struct PrimitiveDef {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    #[serde(default = "default_mode")]
+    mode: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccessorDef {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferViewDef {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct BufferDef {
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaterialDef {
+    name: Option<String>,
+    #[serde(default)]
+    pbr_metallic_roughness: PbrDef,
+    emissive_texture: Option<TextureRef>,
+    #[serde(default)]
+    emissive_factor: [f64; 3],
+    #[serde(default = "default_alpha_mode")]
+    alpha_mode: String,
+    #[serde(default = "default_alpha_cutoff")]
+    alpha_cutoff: f64,
+    #[serde(default)]
+    double_sided: bool,
+    #[serde(default)]
+    extensions: MaterialExtensions,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PbrDef {
+    #[serde(default = "default_base_color")]
+    base_color_factor: [f64; 4],
+    base_color_texture: Option<TextureRef>,
+    #[serde(default = "default_factor")]
+    metallic_factor: f64,
+    #[serde(default = "default_factor")]
+    roughness_factor: f64,
+}
+
+impl Default for PbrDef {
+    fn default() -> Self {
+        Self { base_color_factor: default_base_color(), base_color_texture: None, metallic_factor: 1.0, roughness_factor: 1.0 }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextureRef {
+    index: usize,
+    #[serde(default)]
+    tex_coord: usize,
+}
+
+#[derive(Deserialize, Default)]
+struct MaterialExtensions {
+    #[serde(rename = "KHR_materials_transmission")]
+    transmission: Option<TransmissionDef>,
+    #[serde(rename = "KHR_materials_ior")]
+    ior: Option<IorDef>,
+    #[serde(rename = "KHR_materials_emissive_strength")]
+    emissive_strength: Option<EmissiveStrengthDef>,
+    #[serde(rename = "KHR_materials_clearcoat")]
+    clearcoat: Option<ClearcoatDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransmissionDef {
+    #[serde(default)]
+    transmission_factor: f64,
+}
+
+#[derive(Deserialize)]
+struct IorDef {
+    #[serde(default = "default_ior")]
+    ior: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmissiveStrengthDef {
+    #[serde(default = "default_factor")]
+    emissive_strength: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClearcoatDef {
+    #[serde(default)]
+    clearcoat_factor: f64,
+    #[serde(default)]
+    clearcoat_roughness_factor: f64,
+}
+
+#[derive(Deserialize)]
+struct TextureDef {
+    source: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageDef {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct CameraDef {
+    perspective: Option<PerspectiveDef>,
+}
+
+#[derive(Deserialize)]
+struct PerspectiveDef {
+    yfov: f64, // Radians
+}
+
+#[derive(Deserialize, Default)]
+struct DocumentExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    lights: Option<LightsDef>,
+}
+
+#[derive(Deserialize)]
+struct LightsDef {
+    lights: Vec<LightDef>,
+}
+
+#[derive(Deserialize)]
+struct LightDef {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default = "default_light_color")]
+    color: [f64; 3],
+    #[serde(default = "default_factor")]
+    intensity: f64,
+}
+
+#[derive(Deserialize, Default)]
+struct NodeExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    light: Option<NodeLightDef>,
+}
+
+#[derive(Deserialize)]
+struct NodeLightDef {
+    light: usize,
+}
+
+fn default_mode() -> u32 {
+    4 // Triangles
+}
+
+fn default_alpha_mode() -> String {
+    "OPAQUE".to_string()
+}
+
+fn default_alpha_cutoff() -> f64 {
+    0.5
+}
+
+fn default_base_color() -> [f64; 4] {
+    [1.0; 4]
+}
+
+fn default_factor() -> f64 {
+    1.0
+}
+
+fn default_ior() -> f64 {
+    1.5
+}
+
+fn default_light_color() -> [f64; 3] {
+    [1.0; 3]
+}
+
+fn invalid(reason: impl Into<String>) -> GltfError {
+    GltfError::Invalid(reason.into())
+}
+
+/// Bytes of a base64 data URI (`data:<type>;base64,<data>`), None if the URI isn't one
+fn decode_data_uri(uri: &str) -> Option<Result<Vec<u8>, GltfError>> {
+    let data = uri.strip_prefix("data:")?;
+    let Some((_, encoded)) = data.split_once(";base64,") else {
+        return Some(Err(invalid("data URIs must be base64")));
+    };
+    Some(decode_base64(encoded).ok_or_else(|| invalid("bad base64 in a data URI")))
+}
+
+/// Standard base64, padding optional
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in text.bytes().filter(|&c| c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        bits = bits << 6 | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// Split a GLB container into its JSON and its binary chunk
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), GltfError> {
+    let word = |at: usize| bytes.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    if word(4) != Some(2) {
+        return Err(invalid("only version 2 GLB files are supported"));
+    }
+    let length = (word(8).unwrap_or(0) as usize).min(bytes.len());
+    let (mut at, mut json, mut bin) = (12, None, None);
+    while let (Some(chunk_length), Some(kind)) = (word(at), word(at + 4)) {
+        let chunk = bytes.get(at + 8..at + 8 + chunk_length as usize).ok_or_else(|| invalid("truncated GLB chunk"))?;
+        match kind {
+            CHUNK_JSON if json.is_none() => json = Some(chunk),
+            CHUNK_BIN if bin.is_none() => bin = Some(chunk),
+            _ => {} // Unknown chunks are skipped
+        }
+        at += 8 + chunk_length as usize;
+        if at >= length {
+            break;
+        }
+    }
+    Ok((json.ok_or_else(|| invalid("GLB file without a JSON chunk"))?, bin))
+}
+
+/// Reads accessors' elements out of the loaded buffers
+struct Accessors<'a> {
+    document: &'a Document,
+    buffers: &'a [Vec<u8>],
+}
+
+impl Accessors<'_> {
+    /// Bytes of a buffer view
+    fn view(&self, index: usize) -> Result<&[u8], GltfError> {
+        let view = self.document.buffer_views.get(index).ok_or_else(|| invalid(format!("no buffer view {}", index)))?;
+        let buffer = self.buffers.get(view.buffer).ok_or_else(|| invalid(format!("no buffer {}", view.buffer)))?;
+        buffer
+            .get(view.byte_offset..view.byte_offset + view.byte_length)
+            .ok_or_else(|| invalid(format!("buffer view {} runs past its buffer", index)))
+    }
+    
+    /// The accessor's elements, each `width` components converted to numbers (normalized integers to 0..1
+    /// or -1..1), flattened into one list
+    fn read(&self, index: usize, width: usize) -> Result<Vec<f64>, GltfError> {
+        let accessor = self.document.accessors.get(index).ok_or_else(|| invalid(format!("no accessor {}", index)))?;
+        let expected = match width {
+            1 => "SCALAR",
+            2 => "VEC2",
+            3 => "VEC3",
+            _ => "VEC4",
+        };
+        if accessor.kind != expected {
+            return Err(invalid(format!("accessor {} is {}, expected {}", index, accessor.kind, expected)));
+        }
+        if accessor.sparse.is_some() {
+            return Err(invalid("sparse accessors aren't supported"));
+        }
+        let size = match accessor.component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            other => return Err(invalid(format!("unknown component type {}", other))),
+        };
+        let Some(view_index) = accessor.buffer_view else {
+            return Ok(vec![0.0; accessor.count * width]); // All zeros, per the spec
+        };
+        let view = self.view(view_index)?;
+        let stride = self.document.buffer_views[view_index].byte_stride.unwrap_or(size * width);
+        let needed = if accessor.count == 0 { 0 } else { accessor.byte_offset + stride * (accessor.count - 1) + size * width };
+        if needed > view.len() {
+            return Err(invalid(format!("accessor {} runs past its buffer view", index)));
+        }
+        
+        let mut values = Vec::with_capacity(accessor.count * width);
+        for element in 0..accessor.count {
+            for component in 0..width {
+                let at = accessor.byte_offset + stride * element + size * component;
+                let b = &view[at..at + size];
+                let (value, max) = match accessor.component_type {
+                    5120 => (b[0] as i8 as f64, 127.0),
+                    5121 => (b[0] as f64, 255.0),
+                    5122 => (i16::from_le_bytes([b[0], b[1]]) as f64, 32767.0),
+                    5123 => (u16::from_le_bytes([b[0], b[1]]) as f64, 65535.0),
+                    5125 => (u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64, u32::MAX as f64),
+                    _ => (f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64, 1.0),
+                };
+                values.push(if accessor.normalized { (value / max).max(-1.0) } else { value });
+            }
+        }
+        Ok(values)
+    }
+    
+    fn read_vec3(&self, index: usize) -> Result<Vec<Vec3>, GltfError> {
+        Ok(self.read(index, 3)?.chunks(3).map(|v| Vec3::new(v[0] as Float, v[1] as Float, v[2] as Float)).collect())
+    }
+    
+    /// Colors stored as RGB or RGBA, the alpha dropped
+    fn read_colors(&self, index: usize) -> Result<Vec<Color>, GltfError> {
+        let width = if self.document.accessors.get(index).is_some_and(|accessor| accessor.kind == "VEC4") { 4 } else { 3 };
+        Ok(self.read(index, width)?.chunks(width).map(|c| Color::new(c[0] as Float, c[1] as Float, c[2] as Float)).collect())
+    }
+}
+
+impl GltfScene {
+    /// Read a `.gltf` or `.glb` file, with the buffers and images it refers to by relative path
+    #[cfg(feature = "fs")]
+    pub fn load(path: &Path) -> Result<Self, GltfError> {
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        Self::parse_with(&std::fs::read(path)?, |uri| std::fs::read(base_dir.join(uri)))
+    }
+    
+    /// Parse a self-contained glTF file: a GLB, or JSON whose buffers and images are data URIs
+    pub fn parse(bytes: &[u8]) -> Result<Self, GltfError> {
+        Self::parse_with(bytes, |uri| Err(io::Error::new(io::ErrorKind::Unsupported, format!("can't read '{}' without file access", uri))))
+    }
+    
+    /// Parse a glTF file, reading the files it refers to with `read_file`
+    fn parse_with(bytes: &[u8], read_file: impl Fn(&str) -> io::Result<Vec<u8>>) -> Result<Self, GltfError> {
+        let (json, bin) = if bytes.starts_with(GLB_MAGIC) { split_glb(bytes)? } else { (bytes, None) };
+        let document: Document = serde_json::from_slice(json)?;
+        let read_uri = |uri: &str| decode_data_uri(uri).unwrap_or_else(|| Ok(read_file(uri)?));
+        
+        // A buffer without a URI is the GLB's binary chunk
+        let buffers = document
+            .buffers
+            .iter()
+            .map(|buffer| match &buffer.uri {
+                Some(uri) => read_uri(uri),
+                None => bin.map(<[u8]>::to_vec).ok_or_else(|| invalid("buffer without a URI outside a GLB file")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let accessors = Accessors { document: &document, buffers: &buffers };
+        
+        let meshes = document
+            .meshes
+            .iter()
+            .map(|mesh| mesh.primitives.iter().filter_map(|primitive| read_primitive(&accessors, primitive).transpose()).collect())
+            .collect::<Result<Vec<_>, _>>()?;
+        
+        // Images that can't be decoded (JPEG, say) leave their materials untextured rather than failing the import
+        let images: Vec<Option<RgbaImage>> = document
+            .images
+            .iter()
+            .enumerate()
+            .map(|(index, image)| {
+                let bytes = match (&image.uri, image.buffer_view) {
+                    (Some(uri), _) => read_uri(uri)?,
+                    (None, Some(view)) => accessors.view(view)?.to_vec(),
+                    (None, None) => return Err(invalid(format!("image {} has neither a URI nor a buffer view", index))),
+                };
+                Ok(RgbaImage::decode(&bytes).map_err(|err| log::warn!("glTF image {} skipped: {}", index, err)).ok())
+            })
+            .collect::<Result<_, GltfError>>()?;
+        let image = |texture: &Option<TextureRef>| {
+            let texture = texture.as_ref()?;
+            if texture.tex_coord != 0 {
+                log::warn!("glTF texture {} uses texture coordinates {}; reading set 0", texture.index, texture.tex_coord);
+            }
+            images.get(document.textures.get(texture.index)?.source?)?.as_ref()
+        };
+        let materials: Vec<Material> = document.materials.iter().map(|material| convert_material(material, image)).collect();
+        if meshes.iter().flatten().any(|primitive: &GltfPrimitive| primitive.material.is_some_and(|index| index >= materials.len())) {
+            return Err(invalid("primitive refers to a missing material"));
+        }
+        
+        let mut scene = GltfScene { meshes, materials, instances: Vec::new(), cameras: Vec::new(), lights: Vec::new() };
+        scene.place_nodes(&document)?;
+        Ok(scene)
+    }
+    
+    /// Walk the default scene's node hierarchy, placing its meshes, cameras and lights
+    fn place_nodes(&mut self, document: &Document) -> Result<(), GltfError> {
+        // Without scenes, every node nobody has as a child is a root
+        let roots: Vec<usize> = match document.scenes.get(document.scene.unwrap_or(0)) {
+            Some(scene) => scene.nodes.clone(),
+            None => {
+                let children: Vec<usize> = document.nodes.iter().flat_map(|node| node.children.iter().copied()).collect();
+                (0..document.nodes.len()).filter(|index| !children.contains(index)).collect()
+            }
+        };
+        let lights = document.extensions.lights.as_ref().map_or(&[][..], |lights| &lights.lights[..]);
+        let mut stack: Vec<(usize, Mat4)> = roots.into_iter().rev().map(|root| (root, Mat4::identity())).collect();
+        let mut visited = vec![false; document.nodes.len()];
+        
+        while let Some((index, parent)) = stack.pop() {
+            let node = document.nodes.get(index).ok_or_else(|| invalid(format!("no node {}", index)))?;
+            if std::mem::replace(&mut visited[index], true) {
+                return Err(invalid(format!("node {} appears twice in the hierarchy", index)));
+            }
+            let transform = parent * node_matrix(node);
+            if let Some(mesh) = node.mesh {
+                if mesh >= self.meshes.len() {
+                    return Err(invalid(format!("node {} refers to missing mesh {}", index, mesh)));
+                }
+                self.instances.push((mesh, transform));
+            }
+            match node.camera.map(|camera| document.cameras.get(camera).map(|camera| &camera.perspective)) {
+                Some(Some(Some(perspective))) => self.cameras.push(GltfCamera { transform, fov: perspective.yfov.to_degrees() as Float }),
+                Some(Some(None)) => log::warn!("glTF node {} has an orthographic camera, which is skipped", index),
+                Some(None) => return Err(invalid(format!("node {} refers to a missing camera", index))),
+                None => {}
+            }
+            if let Some(light) = &node.extensions.light {
+                let light = lights.get(light.light).ok_or_else(|| invalid(format!("node {} refers to a missing light", index)))?;
+                if light.kind == "directional" {
+                    log::warn!("glTF node {} has a directional light, which is skipped", index);
+                } else {
+                    let [r, g, b] = light.color.map(|channel| channel as Float);
+                    let position = transform.transform_point(Vec3::zero());
+                    self.lights.push(GltfLight { position, color: Color::new(r, g, b), candela: light.intensity as Float });
+                }
+            }
+            stack.extend(node.children.iter().rev().map(|&child| (child, transform)));
+        }
+        Ok(())
+    }
+    
+    /// Box around every placed mesh, None if nothing is placed
+    pub fn bounds(&self) -> Option<Aabb> {
+        let points = self.instances.iter().flat_map(|(mesh, transform)| {
+            self.meshes[*mesh].iter().flat_map(|primitive| primitive.positions.iter().map(|&point| transform.transform_point(point)))
+        });
+        points.fold(None, |bounds: Option<Aabb>, point| Some(bounds.unwrap_or(Aabb::new(point, point)).grow(point)))
+    }
+}
+
+/// A node's local transform, from its matrix or from translation, rotation and scale
+fn node_matrix(node: &NodeDef) -> Mat4 {
+    if let Some(m) = node.matrix {
+        let m = m.map(|value| value as Float);
+        return Mat4 { rows: std::array::from_fn(|row| std::array::from_fn(|column| m[column * 4 + row])) };
+    }
+    let vector = |v: [f64; 3]| Vec3::new(v[0] as Float, v[1] as Float, v[2] as Float);
+    let [x, y, z, w] = node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]).map(|value| value as Float);
+    Mat4::from_translation(vector(node.translation.unwrap_or([0.0; 3])))
+        * Mat4::from_rotation(Quaternion::new(w, x, y, z).normalize())
+        * Mat4::from_scale(vector(node.scale.unwrap_or([1.0; 3])))
+}
+
+/// A primitive's triangles, None for points and lines, which have no surface
+fn read_primitive(accessors: &Accessors, primitive: &PrimitiveDef) -> Result<Option<GltfPrimitive>, GltfError> {
+    let attribute = |name: &str| primitive.attributes.get(name).copied();
+    let position = attribute("POSITION").ok_or_else(|| invalid("primitive without positions"))?;
+    if primitive.mode < 4 {
+        log::warn!("glTF primitive of mode {} (points or lines) skipped", primitive.mode);
+        return Ok(None);
+    }
+    let positions = accessors.read_vec3(position)?;
+    let normals = attribute("NORMAL").map(|normals| accessors.read_vec3(normals)).transpose()?;
+    let uvs = attribute("TEXCOORD_0")
+        .map(|uvs| accessors.read(uvs, 2).map(|uvs| uvs.chunks(2).map(|uv| (uv[0] as Float, 1.0 - uv[1] as Float)).collect::<Vec<_>>()))
+        .transpose()?;
+    let colors = attribute("COLOR_0").map(|colors| accessors.read_colors(colors)).transpose()?;
+    let lengths = [normals.as_ref().map(Vec::len), uvs.as_ref().map(Vec::len), colors.as_ref().map(Vec::len)];
+    if lengths.into_iter().flatten().any(|length| length != positions.len()) {
+        return Err(invalid("primitive attributes differ in length"));
+    }
+    
+    // Without indices the vertices are taken in order
+    let indices: Vec<usize> = match primitive.indices {
+        Some(indices) => accessors.read(indices, 1)?.into_iter().map(|index| index as usize).collect(),
+        None => (0..positions.len()).collect(),
+    };
+    if indices.iter().any(|&index| index >= positions.len()) {
+        return Err(invalid("primitive indices past its vertices"));
+    }
+    let triangles: Vec<[usize; 3]> = match primitive.mode {
+        4 => indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect(),
+        // Every other triangle of a strip runs the other way round
+        5 => indices.windows(3).enumerate().map(|(i, t)| if i % 2 == 0 { [t[0], t[1], t[2]] } else { [t[1], t[0], t[2]] }).collect(),
+        6 => indices.windows(2).skip(1).map(|t| [indices[0], t[0], t[1]]).collect(),
+        other => return Err(invalid(format!("unknown primitive mode {}", other))),
+    };
+    Ok(Some(GltfPrimitive { positions, normals, uvs, colors, triangles, material: primitive.material }))
+}
+
+/// Our closest match to a metallic-roughness material
+/// Roughness sets the highlight's spread (the Blinn-Phong exponent matching a GGX lobe of that roughness) and,
+/// with metalness, how strong it is; smooth metals become mirrors tinted by the base color
+fn convert_material<'a>(desc: &MaterialDef, image: impl Fn(&Option<TextureRef>) -> Option<&'a RgbaImage>) -> Material {
+    let pbr = &desc.pbr_metallic_roughness;
+    let [r, g, b, alpha] = pbr.base_color_factor.map(|channel| channel as Float);
+    let metallic = pbr.metallic_factor.clamp(0.0, 1.0) as Float;
+    let roughness = pbr.roughness_factor.clamp(0.05, 1.0) as Float;
+    let smoothness = 1.0 - roughness;
+    let mut material = Material {
+        specular: (0.25 + 0.65 * metallic) * smoothness,
+        shininess: (2.0 / roughness.powi(4) - 2.0).clamp(1.0, 1000.0),
+        reflectivity: metallic * smoothness * smoothness,
+        two_sided: desc.double_sided,
+        name: desc.name.as_deref().map(Arc::from),
+        ..Material::new(Color::new(r, g, b))
+    };
+    
+    let base_image = image(&pbr.base_color_texture);
+    if let Some(base_image) = base_image {
+        material.texture = Some(Arc::new(Texture::from_image(base_image, true)));
+    }
+    match desc.alpha_mode.as_str() {
+        "MASK" | "BLEND" if base_image.is_some() => {
+            // Blending has no counterpart here; cutting out at half opacity keeps leaves and decals in shape
+            material.opacity = base_image.map(|image| Arc::new(Texture::opacity_from_image(image)));
+            material.alpha_cutoff = if desc.alpha_mode == "MASK" { desc.alpha_cutoff as Float } else { 0.5 };
+        }
+        "BLEND" if alpha < 1.0 => material.transmission = Vec3::new(1.0, 1.0, 1.0) * (1.0 - alpha),
+        _ => {}
+    }
+    
+    let strength = desc.extensions.emissive_strength.as_ref().map_or(1.0, |strength| strength.emissive_strength);
+    let [r, g, b] = desc.emissive_factor.map(|channel| (channel * strength) as Float);
+    material.emission = Color::new(r, g, b);
+    if let Some(emissive_image) = image(&desc.emissive_texture) {
+        material.emission_texture = Some(Arc::new(Texture::from_image(emissive_image, true)));
+    }
+    
+    if let Some(transmission) = desc.extensions.transmission.as_ref().filter(|transmission| transmission.transmission_factor > 0.0) {
+        let ior = desc.extensions.ior.as_ref().map_or(default_ior(), |ior| ior.ior) as Float;
+        material.transmission = Vec3::new(1.0, 1.0, 1.0) * transmission.transmission_factor as Float;
+        material.ior = Vec3::new(ior, ior, ior);
+        material.two_sided = true; // Light has to leave through the back of the surface
+    }
+    if let Some(clearcoat) = &desc.extensions.clearcoat {
+        material.clearcoat = clearcoat.clearcoat_factor as Float;
+        material.clearcoat_roughness = clearcoat.clearcoat_roughness_factor as Float;
+    }
+    material
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// A GLB holding one triangle placed twice: by a parent node moved up, and by its child turned and doubled
+    fn triangle_glb() -> Vec<u8> {
+        let mut bin = Vec::new();
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            bin.extend_from_slice(&value.to_le_bytes());
+        }
+        for index in [0u16, 1, 2, 0] {
+            bin.extend_from_slice(&index.to_le_bytes()); // Padded to 4 bytes
+        }
+        bin.extend_from_slice(&[255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 0]); // Normalized RGBA bytes
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [
+                { "mesh": 0, "translation": [0, 2, 0], "children": [1] },
+                { "mesh": 0, "rotation": [0, 0.7071068, 0, 0.7071068], "scale": [2, 2, 2] }
+            ],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0, "COLOR_0": 2 }, "indices": 1, "material": 0 }] }],
+            "materials": [{ "pbrMetallicRoughness": { "baseColorFactor": [1, 0.5, 0, 1], "metallicFactor": 0 }, "doubleSided": true }],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+                { "bufferView": 0, "byteOffset": 36, "componentType": 5123, "count": 3, "type": "SCALAR" },
+                { "bufferView": 0, "byteOffset": 44, "componentType": 5121, "normalized": true, "count": 3, "type": "VEC4" }
+            ],
+            "bufferViews": [{ "buffer": 0, "byteLength": 56 }],
+            "buffers": [{ "byteLength": 56 }]
+        }"#;
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        
+        let mut glb = Vec::new();
+        glb.extend_from_slice(GLB_MAGIC);
+        for word in [2, (12 + 8 + json.len() + 8 + bin.len()) as u32, json.len() as u32, CHUNK_JSON] {
+            glb.extend_from_slice(&word.to_le_bytes());
+        }
+        glb.extend_from_slice(&json);
+        for word in [bin.len() as u32, CHUNK_BIN] {
+            glb.extend_from_slice(&word.to_le_bytes());
+        }
+        glb.extend_from_slice(&bin);
+        glb
+    }
+    
+    #[test]
+    fn test_parse_glb() {
+        let scene = GltfScene::parse(&triangle_glb()).unwrap();
+        let primitive = &scene.meshes[0][0];
+        assert_eq!(primitive.positions[1], Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!((primitive.triangles.clone(), primitive.material), (vec![[0, 1, 2]], Some(0)));
+        assert_eq!(primitive.colors.as_ref().unwrap()[1], Color::new(0.0, 1.0, 0.0));
+        assert_eq!(scene.materials[0].albedo, Color::new(1.0, 0.5, 0.0));
+        
+        // The child turns x to -z, doubles it, then rides up with its parent
+        assert_eq!(scene.instances.len(), 2);
+        let corner = scene.instances[1].1.transform_point(Vec3::new(1.0, 0.0, 0.0));
+        assert!((corner - Vec3::new(0.0, 2.0, -2.0)).length() < 1e-5);
+        let bounds = scene.bounds().unwrap();
+        assert!((bounds.min - Vec3::new(0.0, 2.0, -2.0)).length() < 1e-5 && (bounds.max - Vec3::new(1.0, 4.0, 0.0)).length() < 1e-5);
+    }
+    
+    #[test]
+    fn test_data_uris_and_errors() {
+        assert_eq!(decode_base64("AAECAw==").unwrap(), vec![0, 1, 2, 3]);
+        assert_eq!(decode_base64("aGk").unwrap(), b"hi");
+        assert!(decode_base64("a*b").is_none());
+        
+        // One triangle's positions, embedded in the JSON
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 } }] }],
+            "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }],
+            "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+            "buffers": [{ "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA", "byteLength": 36 }]
+        }"#;
+        let scene = GltfScene::parse(json.as_bytes()).unwrap();
+        assert_eq!(scene.meshes[0][0].triangles, vec![[0, 1, 2]]);
+        assert_eq!(scene.meshes[0][0].positions[2], Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(scene.instances.len(), 1); // The only node is a root without a scene list
+        
+        let external = json.replace("data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA", "triangle.bin");
+        assert!(matches!(GltfScene::parse(external.as_bytes()), Err(GltfError::Io(_))));
+        let short = json.replace(r#""count": 3"#, r#""count": 4"#);
+        assert!(matches!(GltfScene::parse(short.as_bytes()), Err(GltfError::Invalid(_))));
+    }
+}
@@ -0,0 +1,163 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use crate::math::{Float, Vec3};
+use crate::image::{self, ImageError};
+
+/// Floating-point RGB image, as read from Radiance `.hdr` files (linear, unbounded values)
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Vec3>, // Row-major from the top-left pixel
+}
+
+impl HdrImage {
+    /// Pixel at (x, y)
+    pub fn pixel(&self, x: u32, y: u32) -> Vec3 {
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+    
+    /// Decode a Radiance RGBE image, flat or with the usual per-channel run-length encoding
+    /// Only the standard top-to-bottom, left-to-right orientation (`-Y h +X w`) is supported
+    pub fn decode(bytes: &[u8]) -> Result<Self, ImageError> {
+        let invalid = |reason: &str| ImageError::Invalid { format: "HDR", reason: reason.to_string() };
+        let unsupported = |reason: String| ImageError::Unsupported { format: "HDR", reason };
+        
+        if !bytes.starts_with(b"#?RADIANCE") && !bytes.starts_with(b"#?RGBE") {
+            return Err(invalid("missing #?RADIANCE signature"));
+        }
+        
+        // Header lines up to an empty line, then the resolution line
+        let mut lines = bytes.split(|&b| b == b'\n');
+        let mut pos = 0;
+        let mut next_line = || -> Result<String, ImageError> {
+            let line = lines.next().ok_or_else(|| invalid("truncated header"))?;
+            pos += line.len() + 1;
+            Ok(String::from_utf8_lossy(line).trim().to_string())
+        };
+        loop {
+            let line = next_line()?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(unsupported(format!("pixel format {}", format)));
+                }
+            }
+        }
+        let resolution = next_line()?;
+        let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (height.parse::<u32>(), width.parse::<u32>()),
+            _ => return Err(unsupported(format!("orientation '{}'", resolution))),
+        };
+        let (height, width) = (height.map_err(|_| invalid("bad height"))?, width.map_err(|_| invalid("bad width"))?);
+        
+        let mut data = &bytes[pos.min(bytes.len())..];
+        let count = image::pixel_count(width, height).ok_or_else(|| invalid("image too large"))?;
+        // Run-length encoding packs at most 127 pixels of each channel into two bytes
+        if count.max(width as usize) > data.len() * 16 {
+            return Err(invalid("truncated pixel data"));
+        }
+        let mut pixels = Vec::with_capacity(count);
+        let mut scanline = vec![[0u8; 4]; width as usize];
+        for _ in 0..height {
+            data = read_scanline(data, &mut scanline).ok_or_else(|| invalid("truncated pixel data"))?;
+            pixels.extend(scanline.iter().map(|&rgbe| from_rgbe(rgbe)));
+        }
+        
+        Ok(Self { width, height, pixels })
+    }
+    
+    /// Read and decode a `.hdr` file
+    #[cfg(feature = "fs")]
+    pub fn load(path: &Path) -> Result<Self, ImageError> {
+        Self::decode(&std::fs::read(path)?)
+    }
+}
+
+/// Decode one scanline into `scanline`, returning the remaining data (None if it runs out)
+fn read_scanline<'a>(data: &'a [u8], scanline: &mut [[u8; 4]]) -> Option<&'a [u8]> {
+    let width = scanline.len();
+    let run_length_encoded = (8..0x8000).contains(&width)
+        && data.len() >= 4
+        && data[0] == 2
+        && data[1] == 2
+        && usize::from(data[2]) << 8 | usize::from(data[3]) == width;
+    
+    if !run_length_encoded {
+        let bytes = data.get(..width * 4)?;
+        for (pixel, rgbe) in scanline.iter_mut().zip(bytes.chunks(4)) {
+            pixel.copy_from_slice(rgbe);
+        }
+        return Some(&data[width * 4..]);
+    }
+    
+    // Each channel in turn: runs (count > 128, one repeated byte) and literal spans
+    let mut data = &data[4..];
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let (&count, rest) = data.split_first()?;
+            if count > 128 {
+                let count = usize::from(count - 128);
+                let &value = rest.first()?;
+                scanline.get_mut(x..x + count)?.iter_mut().for_each(|pixel| pixel[channel] = value);
+                data = &rest[1..];
+                x += count;
+            } else {
+                let count = usize::from(count);
+                let values = rest.get(..count)?;
+                for (pixel, &value) in scanline.get_mut(x..x + count)?.iter_mut().zip(values) {
+                    pixel[channel] = value;
+                }
+                data = &rest[count..];
+                x += count.max(1); // A zero-length span would never advance
+            }
+        }
+    }
+    Some(data)
+}
+
+/// Shared-exponent pixel to linear RGB
+fn from_rgbe([r, g, b, e]: [u8; 4]) -> Vec3 {
+    if e == 0 {
+        return Vec3::zero();
+    }
+    let scale = (2.0 as Float).powi(i32::from(e) - 136);
+    Vec3::new(Float::from(r) * scale, Float::from(g) * scale, Float::from(b) * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_decode() {
+        // 2×1 flat image: 0.5 gray and (2, 1, 0)
+        let mut flat = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        flat.extend_from_slice(&[128, 128, 128, 128, 128, 64, 0, 130]);
+        let image = HdrImage::decode(&flat).unwrap();
+        assert_eq!(image.pixels, vec![Vec3::new(0.5, 0.5, 0.5), Vec3::new(2.0, 1.0, 0.0)]);
+        
+        // 8×1 run-length encoded: a run for red, literals for green, runs for blue and the exponent
+        let mut rle = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+        rle.extend_from_slice(&[2, 2, 0, 8, 136, 128, 8, 0, 1, 2, 3, 4, 5, 6, 7, 136, 0, 136, 129]);
+        let image = HdrImage::decode(&rle).unwrap();
+        assert_eq!(image.pixel(3, 0), Vec3::new(1.0, 3.0 / 128.0, 0.0));
+        
+        assert!(matches!(HdrImage::decode(&rle[..rle.len() - 1]), Err(ImageError::Invalid { .. })));
+        let reason = |bytes: &[u8]| match HdrImage::decode(bytes) {
+            Err(ImageError::Invalid { reason, .. }) => reason,
+            other => panic!("expected an invalid image, got {:?}", other),
+        };
+        assert_eq!(reason(b"#?RADIANCE\n\n-Y 65536 +X 65536\n"), "image too large");
+        assert_eq!(reason(b"#?RADIANCE\n\n-Y 0 +X 4294967295\n"), "truncated pixel data");
+        assert_eq!(reason(b"#?RADIANCE\n\n-Y 1000 +X 1000\n\x02\x02\x03\xe8"), "truncated pixel data");
+        assert!(matches!(
+            HdrImage::decode(b"#?RADIANCE\n\n+Y 1 +X 8\n"),
+            Err(ImageError::Unsupported { .. })
+        ));
+    }
+}
@@ -0,0 +1,181 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::io;
+
+use crate::math::{Float, Vec3};
+
+#[derive(Debug, thiserror::Error)]
+pub enum IesError {
+    #[error("could not read IES file: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid IES file: {0}")]
+    Invalid(String),
+    #[error("unsupported IES file: {0}")]
+    Unsupported(String),
+}
+
+/// Measured angular intensity distribution of a light fixture, from an IES LM-63 photometric file
+/// The fixture points straight down: vertical angles run from -y (0°) to +y (180°), and horizontal
+/// angles turn about the y axis from +x (0°) toward -z (90°), counterclockwise seen from above
+#[derive(Debug, Clone, PartialEq)]
+pub struct IesProfile {
+    vertical: Vec<Float>,     // Degrees, increasing
+    horizontal: Vec<Float>,   // Degrees, increasing
+    candela: Vec<Vec<Float>>, // A row of vertical samples per horizontal angle, relative to the peak
+    pub peak: Float,          // Brightest intensity in the file, in candela
+}
+
+impl IesProfile {
+    /// Parse the text of an IES file (LM-63-1995 or -2002, type C photometry)
+    pub fn parse(text: &str) -> Result<Self, IesError> {
+        let invalid = |reason: String| IesError::Invalid(reason);
+        
+        // Keyword lines up to TILT, then numbers split across lines at will
+        let mut lines = text.lines();
+        let tilt = loop {
+            let line = lines.next().ok_or_else(|| invalid("missing TILT line".to_string()))?;
+            if let Some(tilt) = line.trim().strip_prefix("TILT=") {
+                break tilt.trim();
+            }
+        };
+        let mut numbers = lines
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty())
+            .map(|token| token.parse::<Float>().map_err(|_| invalid(format!("bad number '{}'", token))));
+        let mut next = || numbers.next().unwrap_or_else(|| Err(invalid("truncated data".to_string())));
+        let mut take = |count: usize| (0..count).map(|_| next()).collect::<Result<Vec<Float>, IesError>>();
+        
+        match tilt {
+            "NONE" => {}
+            "INCLUDE" => {
+                // Lamp geometry, then angle and multiplier pairs for tilted lamps, which don't apply here
+                let header = take(2)?;
+                take(2 * header[1] as usize)?;
+            }
+            other => return Err(IesError::Unsupported(format!("TILT={} (tilt data in a separate file)", other))),
+        }
+        
+        // Lamp count, lumens per lamp, multiplier, angle counts, photometric type, units, size (3),
+        // ballast factor, ballast-lamp factor, input watts
+        let header = take(13)?;
+        let (multiplier, vertical_count, horizontal_count) = (header[2], header[3] as usize, header[4] as usize);
+        if header[5] != 1.0 {
+            return Err(IesError::Unsupported(format!("photometric type {} (only type C is supported)", header[5])));
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(invalid("no angles".to_string()));
+        }
+        
+        let vertical = take(vertical_count)?;
+        let horizontal = take(horizontal_count)?;
+        if !vertical.is_sorted() || !horizontal.is_sorted() {
+            return Err(invalid("angles must increase".to_string()));
+        }
+        let scale = multiplier * header[10] * header[11];
+        let candela = (0..horizontal_count).map(|_| take(vertical_count)).collect::<Result<Vec<_>, _>>()?;
+        let peak = candela.iter().flatten().fold(0.0, |peak: Float, &value| peak.max(value * scale));
+        if peak <= 0.0 {
+            return Err(invalid("no light in any direction".to_string()));
+        }
+        let candela = candela.into_iter().map(|row| row.into_iter().map(|value| value * scale / peak).collect()).collect();
+        
+        Ok(Self { vertical, horizontal, candela, peak })
+    }
+    
+    /// Read and parse an IES file (headers need not be UTF-8)
+    #[cfg(feature = "fs")]
+    pub fn load(path: &Path) -> Result<Self, IesError> {
+        Self::parse(&String::from_utf8_lossy(&std::fs::read(path)?))
+    }
+    
+    /// Intensity toward `direction` (from the light), relative to the peak: 1 in the brightest direction
+    /// and 0 outside the measured vertical range; between samples it is interpolated bilinearly
+    pub fn relative(&self, direction: Vec3) -> Float {
+        let direction = direction.normalize();
+        let vertical = (-direction.y).clamp(-1.0, 1.0).acos().to_degrees();
+        let (first, last) = (self.vertical[0], self.vertical[self.vertical.len() - 1]);
+        if vertical < first - 1e-3 || vertical > last + 1e-3 {
+            return 0.0;
+        }
+        
+        // Files measure only as much of the circle as the fixture's symmetry needs
+        let mut horizontal = (-direction.z).atan2(direction.x).to_degrees().rem_euclid(360.0);
+        let widest = self.horizontal[self.horizontal.len() - 1];
+        if widest <= 90.0 {
+            horizontal %= 180.0;
+            if horizontal > 90.0 {
+                horizontal = 180.0 - horizontal;
+            }
+        } else if widest <= 180.0 && horizontal > 180.0 {
+            horizontal = 360.0 - horizontal;
+        }
+        
+        let (v0, v1, tv) = locate(&self.vertical, vertical);
+        let (h0, h1, th) = locate(&self.horizontal, horizontal);
+        let row = |h: usize| self.candela[h][v0] * (1.0 - tv) + self.candela[h][v1] * tv;
+        row(h0) * (1.0 - th) + row(h1) * th
+    }
+}
+
+/// Samples on either side of `angle` in increasing `angles`, and how far it lies from the first to the
+/// second; angles beyond either end take the end sample
+fn locate(angles: &[Float], angle: Float) -> (usize, usize, Float) {
+    let upper = angles.partition_point(|&a| a < angle).min(angles.len() - 1);
+    if upper == 0 {
+        return (0, 0, 0.0);
+    }
+    let lower = upper - 1;
+    let t = ((angle - angles[lower]) / (angles[upper] - angles[lower])).clamp(0.0, 1.0);
+    (lower, upper, t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // A downlight: brightest straight down, half as bright at 45°, dark sideways and above
+    const DOWNLIGHT: &str = "IESNA:LM-63-2002
+[TEST] downlight
+[MANUFAC] rt
+TILT=NONE
+1 1000 2.0 3 1 1 2 0.1 0.1 0
+1.0 1.0 15
+0 45 90
+0
+500, 250, 0
+";
+
+    #[test]
+    fn test_parse_and_sample() {
+        let profile = IesProfile::parse(DOWNLIGHT).unwrap();
+        assert_eq!(profile.peak, 1000.0);
+        let at = |degrees: Float| {
+            let radians = degrees.to_radians();
+            profile.relative(Vec3::new(radians.sin(), -radians.cos(), 0.0))
+        };
+        assert!((at(0.0) - 1.0).abs() < 1e-6);
+        assert!((at(45.0) - 0.5).abs() < 1e-6);
+        assert!((at(22.5) - 0.75).abs() < 1e-6);
+        assert_eq!(at(135.0), 0.0);
+        
+        // One horizontal angle: the same all the way around
+        assert!((profile.relative(Vec3::new(0.0, -1.0, 1.0)) - 0.5).abs() < 1e-6);
+        
+        assert!(matches!(IesProfile::parse(&DOWNLIGHT.replace("3 1 1 2", "3 1 3 2")), Err(IesError::Unsupported(_))));
+        assert!(matches!(IesProfile::parse(&DOWNLIGHT.replace("500, 250, 0", "500")), Err(IesError::Invalid(_))));
+        assert!(matches!(IesProfile::parse("IESNA:LM-63-2002\n1 2 3"), Err(IesError::Invalid(_))));
+    }
+    
+    #[test]
+    fn test_quadrant_symmetry() {
+        // Measured from 0° to 90° only: bright along x, dim along z, mirrored into the other quadrants
+        let text = DOWNLIGHT.replace("3 1 1 2", "2 3 1 2").replace("0 45 90\n0\n500, 250, 0", "0 90\n0 45 90\n1 1\n1 1\n0.5 0.5");
+        let profile = IesProfile::parse(&text).unwrap();
+        let toward = |x: Float, z: Float| profile.relative(Vec3::new(x, -1.0, z));
+        assert!((toward(1.0, 0.0) - 1.0).abs() < 1e-6);
+        assert!((toward(-1.0, 0.0) - 1.0).abs() < 1e-6);
+        assert!((toward(0.0, 1.0) - 0.5).abs() < 1e-6);
+        assert!((toward(1.0, -1.0) - toward(-1.0, 1.0)).abs() < 1e-6);
+        assert!((toward(1.0, -1.0) - 1.0).abs() < 1e-6);
+    }
+}
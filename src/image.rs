@@ -0,0 +1,93 @@
+use std::io;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use crate::{png, ppm};
+
+/// Most pixels a decoded image may have, 16384 × 16384
+pub const MAX_PIXELS: usize = 1 << 28;
+
+/// Errors from reading an image file
+#[derive(Debug, thiserror::Error)]
+pub enum ImageError {
+    #[error("could not read image: {0}")]
+    Io(#[from] io::Error),
+    #[error("unrecognized image format (expected PNG or PPM)")]
+    UnknownFormat,
+    #[error("invalid {format} image: {reason}")]
+    Invalid { format: &'static str, reason: String },
+    #[error("unsupported {format} image: {reason}")]
+    Unsupported { format: &'static str, reason: String },
+}
+
+/// 8-bit RGBA image with straight alpha, as read from PNG or PPM files
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>, // Row-major from the top-left pixel
+}
+
+impl RgbaImage {
+    /// Pixel at (x, y)
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+    
+    /// Decode PNG or PPM (P3/P6) bytes, recognized by their signature
+    pub fn decode(bytes: &[u8]) -> Result<Self, ImageError> {
+        if bytes.starts_with(&png::SIGNATURE) {
+            png::decode(bytes)
+        } else if bytes.starts_with(b"P3") || bytes.starts_with(b"P6") {
+            ppm::decode(bytes)
+        } else {
+            Err(ImageError::UnknownFormat)
+        }
+    }
+    
+    /// Read and decode an image file
+    #[cfg(feature = "fs")]
+    pub fn load(path: &Path) -> Result<Self, ImageError> {
+        Self::decode(&std::fs::read(path)?)
+    }
+}
+
+/// Pixels in an image of this size, or None if there are more than `MAX_PIXELS`
+pub(crate) fn pixel_count(width: u32, height: u32) -> Option<usize> {
+    (width as usize).checked_mul(height as usize).filter(|&count| count <= MAX_PIXELS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::PngWriter;
+    use crate::ppm::PpmWriter;
+    
+    #[test]
+    fn test_round_trips() {
+        let mut ppm = PpmWriter::new(2, 1);
+        ppm.write_pixel(255, 0, 10);
+        ppm.write_pixel(1, 2, 3);
+        let image = RgbaImage::decode(ppm.to_string().as_bytes()).unwrap();
+        assert_eq!(image.pixels, vec![[255, 0, 10, 255], [1, 2, 3, 255]]);
+        
+        let png = PngWriter::rgba(2, 1, vec![255, 0, 10, 128, 1, 2, 3, 0]).to_bytes();
+        let image = RgbaImage::decode(&png).unwrap();
+        assert_eq!(image.pixels, vec![[255, 0, 10, 128], [1, 2, 3, 0]]);
+        
+        assert!(matches!(RgbaImage::decode(b"GIF89a"), Err(ImageError::UnknownFormat)));
+    }
+    
+    #[test]
+    fn test_bad_sizes() {
+        let reason = |bytes: &[u8]| match RgbaImage::decode(bytes) {
+            Err(ImageError::Invalid { reason, .. }) => reason,
+            other => panic!("expected an invalid image, got {:?}", other),
+        };
+        assert_eq!(reason(b"P6 2 1 255"), "not enough pixel data");
+        assert_eq!(reason(b"P6 2 1 255\n\x01\x02\x03"), "not enough pixel data");
+        assert_eq!(reason(b"P6 4294967295 4294967295 255\n"), "image too large");
+        assert_eq!(reason(b"P3 65536 65536 255\n0 0 0"), "image too large");
+        assert_eq!(reason(b"P3 1000 1000 255\n0 0 0"), "expected a number");
+    }
+}
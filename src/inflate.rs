@@ -0,0 +1,287 @@
+//! Decompression of zlib streams (RFC 1950) holding DEFLATE data (RFC 1951), as found in PNG files
+
+/// Maximum Huffman code length in DEFLATE
+const MAX_BITS: usize = 15;
+
+/// Base lengths and extra bits for length symbols 257-285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+/// Base distances and extra bits for distance symbols 0-29
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Order in which code length code lengths are stored in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Reads bits least-significant first, as DEFLATE packs them
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,  // Next byte to load
+    buffer: u32, // Loaded bits not yet consumed
+    count: u32,  // Number of valid bits in `buffer`
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0, buffer: 0, count: 0 }
+    }
+    
+    fn bits(&mut self, n: u32) -> Result<u32, &'static str> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or("unexpected end of compressed data")?;
+            self.buffer |= (byte as u32) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1u64 << n) - 1) as u32;
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+    
+    /// Drop the rest of the current byte (before stored blocks)
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+    
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], &'static str> {
+        let bytes = self.data.get(self.pos..self.pos + n).ok_or("unexpected end of stored block")?;
+        self.pos += n;
+        Ok(bytes)
+    }
+}
+
+/// Canonical Huffman code, decoded one bit at a time
+struct Huffman {
+    counts: [u16; MAX_BITS + 1], // Number of codes of each length
+    symbols: Vec<u16>,           // Symbols ordered by code
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, &'static str> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        
+        // Reject over-subscribed codes (incomplete ones are allowed, e.g. a single distance code)
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err("over-subscribed Huffman code");
+            }
+        }
+        
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        
+        Ok(Self { counts, symbols })
+    }
+    
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, &'static str> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid Huffman code")
+    }
+}
+
+/// Decompress a zlib stream, checking its header and Adler-32 checksum
+pub fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    if data.len() < 6 {
+        return Err("zlib stream too short");
+    }
+    let (cmf, flg) = (data[0], data[1]);
+    if cmf & 0x0f != 8 || !(cmf as u16 * 256 + flg as u16).is_multiple_of(31) {
+        return Err("invalid zlib header");
+    }
+    if flg & 0x20 != 0 {
+        return Err("zlib preset dictionaries are not supported");
+    }
+    
+    let output = inflate(&data[2..])?;
+    let expected = u32::from_be_bytes(data[data.len() - 4..].try_into().unwrap());
+    if adler32(&output) != expected {
+        return Err("zlib checksum mismatch");
+    }
+    Ok(output)
+}
+
+/// Decompress raw DEFLATE data
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let mut reader = BitReader::new(data);
+    let mut output = Vec::new();
+    
+    loop {
+        let is_final = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let header = reader.bytes(4)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                let nlen = u16::from_le_bytes([header[2], header[3]]);
+                if len != !nlen {
+                    return Err("stored block length mismatch");
+                }
+                output.extend_from_slice(reader.bytes(len as usize)?);
+            }
+            1 => {
+                let (literals, distances) = fixed_codes()?;
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            _ => return Err("invalid block type"),
+        }
+        
+        if is_final {
+            return Ok(output);
+        }
+    }
+}
+
+fn fixed_codes() -> Result<(Huffman, Huffman), &'static str> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), &'static str> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+    
+    // Literal/length and distance code lengths share one run-length coded sequence
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (value, repeat) = match code_length_code.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or("repeat with no previous length")?, 3 + reader.bits(2)?),
+            17 => (0, 3 + reader.bits(3)?),
+            18 => (0, 11 + reader.bits(7)?),
+            _ => return Err("invalid code length symbol"),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err("code lengths overrun");
+    }
+    if lengths[256] == 0 {
+        return Err("missing end-of-block code");
+    }
+    
+    Ok((Huffman::new(&lengths[..literal_count])?, Huffman::new(&lengths[literal_count..])?))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), &'static str> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let length = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+                
+                let index = distances.decode(reader)? as usize;
+                if index >= 30 {
+                    return Err("invalid distance symbol");
+                }
+                let distance = DIST_BASE[index] as usize + reader.bits(DIST_EXTRA[index] as u32)? as usize;
+                if distance > output.len() {
+                    return Err("distance too far back");
+                }
+                
+                // Copy byte by byte: the match may overlap the bytes it produces
+                let start = output.len() - distance;
+                for i in 0..length {
+                    output.push(output[start + i]);
+                }
+            }
+            _ => return Err("invalid literal/length symbol"),
+        }
+    }
+}
+
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    adler32_update(1, data)
+}
+
+/// Carry an Adler-32 checksum of earlier data on over more (1 is the checksum of nothing)
+pub(crate) fn adler32_update(adler: u32, data: &[u8]) -> u32 {
+    let (mut a, mut b) = (adler & 0xffff, adler >> 16);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_fixed_and_dynamic_blocks() {
+        // zlib.compress(b"hello hello hello"), a fixed Huffman block
+        let fixed = [0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00, 0x3a, 0x2e, 0x06, 0x7d];
+        assert_eq!(zlib_decompress(&fixed).unwrap(), b"hello hello hello");
+        
+        // zlib.compress(data, 9) for the bytes below, a dynamic Huffman block
+        let dynamic = [
+            0x78, 0xda, 0x3d, 0xcc, 0xc1, 0x11, 0x00, 0x30, 0x08, 0x02, 0xc1, 0x56, 0xd2, 0x1a, 0x18, 0xfa, 0x6f, 0x21,
+            0x82, 0x13, 0xfd, 0xb9, 0xe3, 0x29, 0x64, 0x04, 0xb0, 0x47, 0x55, 0xe7, 0x66, 0x0b, 0x9a, 0x58, 0x32, 0x7d,
+            0xa4, 0xb1, 0xaf, 0xb4, 0x9d, 0x43, 0x31, 0xe1, 0x22, 0xe7, 0x59, 0x42, 0xe1, 0x01, 0x56, 0x73, 0x25, 0x0e,
+        ];
+        let expected: Vec<u8> = (0..100).map(|i| if i % 7 == 0 { b'e' } else { b"aaaaaaaaaabbbbbcc d"[i % 19] }).collect();
+        assert_eq!(zlib_decompress(&dynamic).unwrap(), expected);
+    }
+    
+    #[test]
+    fn test_rejects_corrupt_stream() {
+        assert!(zlib_decompress(&[0x78, 0x9c, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]).is_err());
+        assert!(zlib_decompress(&[0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01]).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use crate::math::{Aabb, Float, Vec3, Ray, Interval};
+use crate::stats;
+
+/// Cost of stepping through a node, relative to `INTERSECTION_COST`
+const TRAVERSAL_COST: Float = 1.0;
+
+/// Cost of intersecting one item
+const INTERSECTION_COST: Float = 80.0;
+
+/// Fraction of the cost saved when a split leaves one side empty, since rays cross empty space for free
+const EMPTY_BONUS: Float = 0.5;
+
+/// Deepest tree the traversal stack can hold
+const MAX_DEPTH: usize = 64;
+
+/// Splits that cost more than a leaf tolerated on one path before giving up on it
+const MAX_BAD_REFINES: u32 = 3;
+
+/// kd-tree over a list of items referred to by index: space is cut by axis-aligned planes chosen by the
+/// surface area heuristic, and each leaf lists the items overlapping its cell, so an item straddling a
+/// plane is listed on both sides. Items without bounds are kept aside and offered to every ray
+#[derive(Debug, Clone)]
+pub struct KdTree {
+    nodes: Vec<Node>,      // Depth-first: a node's below child follows it directly
+    items: Vec<usize>,     // Leaf item lists
+    unbounded: Vec<usize>, // Items without bounds
+    bounds: Aabb,          // Of all bounded items
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Node {
+    Leaf { start: usize, count: usize },
+    Interior { axis: usize, split: Float, above: usize }, // `above` is the index of the child past the plane
+}
+
+impl KdTree {
+    /// Build over items with the given bounds (None = unbounded)
+    pub fn new(bounds: &[Option<Aabb>]) -> Self {
+        let (items, unbounded): (Vec<usize>, Vec<usize>) = (0..bounds.len()).partition(|&i| bounds[i].is_some());
+        let bounded: Vec<Aabb> = bounds.iter().map(|b| b.unwrap_or_else(Aabb::empty)).collect();
+        let total = items.iter().fold(Aabb::empty(), |total, &item| total.union(&bounded[item]));
+        
+        let mut tree = Self { nodes: Vec::new(), items: Vec::new(), unbounded, bounds: total };
+        if !items.is_empty() {
+            let max_depth = (8.0 + 1.3 * (items.len() as Float).log2()).round() as usize;
+            tree.build(&bounded, total, items, max_depth.min(MAX_DEPTH - 1), 0);
+        }
+        tree
+    }
+    
+    /// Bounds of every item, or None if some are unbounded
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.unbounded.is_empty().then_some(self.bounds)
+    }
+    
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+    
+    /// Bytes its nodes and item lists take
+    pub fn memory(&self) -> usize {
+        size_of_val(self.nodes.as_slice()) + size_of_val(self.items.as_slice()) + size_of_val(self.unbounded.as_slice())
+    }
+    
+    /// Item entries over all leaves; more than the item count when items straddle split planes
+    pub fn reference_count(&self) -> usize {
+        self.items.len()
+    }
+    
+    /// Offer the ray the items in each cell it passes through, front to back, stopping once a hit is
+    /// closer than the next cell; `visit` works as in `Bvh::traverse` and may see an item more than once
+    pub fn traverse(&self, ray: &Ray, mut t_max: Float, mut visit: impl FnMut(usize, Float) -> Option<Float>) {
+        for &item in &self.unbounded {
+            if let Some(t) = visit(item, t_max) {
+                t_max = t;
+            }
+        }
+        if self.nodes.is_empty() {
+            return;
+        }
+        
+        let inverse_direction = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+        let (mut t_enter, mut t_exit) = match self.bounds.hit_range(ray, inverse_direction, Interval::new(0.0, t_max)) {
+            Some(inside) => (inside.min, inside.max),
+            None => return,
+        };
+        let mut stack = [(0, 0.0, 0.0); MAX_DEPTH];
+        let mut depth = 0;
+        let mut index = 0;
+        let mut visited = 0;
+        
+        loop {
+            if t_enter > t_max {
+                break; // Cells are visited front to back, so nothing further on can be closer
+            }
+            visited += 1;
+            match self.nodes[index] {
+                Node::Interior { axis, split, above } => {
+                    let (origin, inverse) = (ray.origin[axis], inverse_direction[axis]);
+                    let t_plane = (split - origin) * inverse;
+                    let below_first = origin < split || (origin == split && inverse <= 0.0);
+                    let (near, far) = if below_first { (index + 1, above) } else { (above, index + 1) };
+                    
+                    if t_plane.is_nan() {
+                        // The ray runs within the plane, and may touch items on either side
+                        stack[depth] = (far, t_enter, t_exit);
+                        depth += 1;
+                        index = near;
+                    } else if t_plane > t_exit || t_plane <= 0.0 {
+                        index = near; // The ray leaves the cell before reaching the plane
+                    } else if t_plane < t_enter {
+                        index = far; // The ray crosses the plane before entering the cell
+                    } else {
+                        stack[depth] = (far, t_plane, t_exit);
+                        depth += 1;
+                        index = near;
+                        t_exit = t_plane;
+                    }
+                }
+                Node::Leaf { start, count } => {
+                    for &item in &self.items[start..start + count] {
+                        if let Some(t) = visit(item, t_max) {
+                            t_max = t;
+                        }
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                    (index, t_enter, t_exit) = stack[depth];
+                }
+            }
+        }
+        stats::count_nodes(visited);
+    }
+    
+    /// Build the subtree for a cell holding `items`
+    fn build(&mut self, bounds: &[Aabb], cell: Aabb, items: Vec<usize>, depth_left: usize, bad_refines: u32) {
+        let leaf_cost = INTERSECTION_COST * items.len() as Float;
+        let split = match (items.len() > 1 && depth_left > 0).then(|| best_split(bounds, &cell, &items)).flatten() {
+            Some((cost, axis, split)) => {
+                let bad_refines = if cost > leaf_cost { bad_refines + 1 } else { bad_refines };
+                let hopeless = cost > 4.0 * leaf_cost && items.len() < 16;
+                (!hopeless && bad_refines < MAX_BAD_REFINES).then_some((axis, split, bad_refines))
+            }
+            None => None,
+        };
+        let (axis, split, bad_refines) = match split {
+            Some(split) => split,
+            None => {
+                self.nodes.push(Node::Leaf { start: self.items.len(), count: items.len() });
+                self.items.extend(items);
+                return;
+            }
+        };
+        
+        // Items overlapping both sides (or lying flat in the plane) go to both
+        let below: Vec<usize> = items
+            .iter()
+            .copied()
+            .filter(|&item| bounds[item].min[axis] < split || bounds[item].max[axis] <= split)
+            .collect();
+        let above: Vec<usize> = items
+            .into_iter()
+            .filter(|&item| bounds[item].max[axis] > split || bounds[item].min[axis] >= split)
+            .collect();
+        let (mut below_cell, mut above_cell) = (cell, cell);
+        below_cell.max[axis] = split;
+        above_cell.min[axis] = split;
+        
+        let index = self.nodes.len();
+        self.nodes.push(Node::Leaf { start: 0, count: 0 }); // Replaced once the children are built
+        self.build(bounds, below_cell, below, depth_left - 1, bad_refines);
+        let above_index = self.nodes.len();
+        self.build(bounds, above_cell, above, depth_left - 1, bad_refines);
+        self.nodes[index] = Node::Interior { axis, split, above: above_index };
+    }
+}
+
+/// Cheapest plane through the cell at an item's bound, as (SAH cost, axis, position)
+fn best_split(bounds: &[Aabb], cell: &Aabb, items: &[usize]) -> Option<(Float, usize, Float)> {
+    let size = cell.max - cell.min;
+    let total_area = cell.surface_area();
+    if total_area <= 0.0 {
+        return None;
+    }
+    
+    let mut best: Option<(Float, usize, Float)> = None;
+    for axis in 0..3 {
+        // Bound edges in order along the axis, starts before ends at the same position
+        let mut edges: Vec<(Float, bool)> = items
+            .iter()
+            .flat_map(|&item| [(bounds[item].min[axis], false), (bounds[item].max[axis], true)])
+            .collect();
+        edges.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        
+        let (low, high) = (cell.min[axis], cell.max[axis]);
+        let (other1, other2) = (size[(axis + 1) % 3], size[(axis + 2) % 3]);
+        let (mut below, mut above) = (0, items.len());
+        for &(position, is_end) in &edges {
+            if is_end {
+                above -= 1;
+            }
+            if position > low && position < high {
+                let area = |length: Float| 2.0 * (other1 * other2 + length * (other1 + other2));
+                let (below_share, above_share) = (area(position - low) / total_area, area(high - position) / total_area);
+                let bonus = if below == 0 || above == 0 { EMPTY_BONUS } else { 0.0 };
+                let cost = TRAVERSAL_COST
+                    + INTERSECTION_COST * (1.0 - bonus) * (below_share * below as Float + above_share * above as Float);
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, position));
+                }
+            }
+            if !is_end {
+                below += 1;
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::Rng;
+    
+    #[test]
+    fn test_traversal_finds_closest_hit() {
+        // Random boxes of mixed sizes, so many straddle split planes; the closest hit must match brute force
+        let mut rng = Rng::new(12);
+        let mut random = |scale: Float| Vec3::new(rng.next_float(), rng.next_float(), rng.next_float()) * scale;
+        let boxes: Vec<Aabb> = (0..300).map(|_| {
+            let min = random(20.0) - Vec3::new(10.0, 10.0, 10.0);
+            Aabb::new(min, min + random(3.0))
+        }).collect();
+        let mut bounds: Vec<Option<Aabb>> = boxes.iter().map(|&b| Some(b)).collect();
+        bounds.push(None); // An unbounded item that never hits
+        let tree = KdTree::new(&bounds);
+        assert!(tree.reference_count() > boxes.len());
+        
+        for _ in 0..500 {
+            let ray = Ray::new(random(4.0), random(2.0) - Vec3::new(1.0, 1.0, 1.0));
+            let inverse = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+            let expected = boxes.iter().filter_map(|b| b.hit(&ray, inverse, Interval::new(0.0, Float::INFINITY))).reduce(Float::min);
+            
+            let (mut closest, mut visited) = (None, 0);
+            tree.traverse(&ray, Float::INFINITY, |item, t_max| {
+                visited += 1;
+                let t = boxes.get(item)?.hit(&ray, inverse, Interval::new(0.0, t_max))?;
+                closest = Some(t);
+                Some(t)
+            });
+            assert_eq!(closest, expected);
+            assert!(visited < boxes.len() / 2, "visited {} boxes", visited);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+pub mod config;
+pub mod error;
+pub mod math;
+pub mod color;
+pub mod ppm;
+pub mod png;
+pub mod exr;
+pub mod inflate;
+pub mod image;
+pub mod hdr;
+pub mod texture;
+pub mod ies;
+pub mod vox;
+pub mod scan;
+pub mod gltf;
+pub mod framebuffer;
+pub mod camera;
+pub mod material;
+pub mod shapes;
+pub mod bvh;
+pub mod kdtree;
+pub mod scene;
+pub mod environment;
+pub mod sky;
+pub mod render;
+pub mod denoise;
+pub mod aov;
+pub mod bsdf;
+pub mod sampling;
+pub mod scenes;
+pub mod scene_file;
+pub mod validate;
+pub mod selftest;
+pub mod stats;
+pub mod bench;
+pub mod diff;
+pub mod serve;
+pub mod distributed;
+pub mod wasm;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "simd")]
+pub mod simd;
+
+pub use error::{Error, Result};
@@ -1,230 +1,1502 @@
-use clap::Parser;
-use std::io;
-
-mod math;
-mod ppm;
-mod camera;
-mod material;
-mod shapes;
-mod scene;
-mod render;
-
-use math::Vec3;
-use camera::Camera;
-use material::Material;
-use shapes::{Sphere, Plane, Cube, Cylinder};
-use scene::{Scene, Light};
-use render::Renderer;
-use ppm::PpmWriter;
+use clap::{ArgAction, Parser, Subcommand};
+use log::LevelFilter;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use rt::math::{Float, Vec3, Aabb};
+use rt::render::{DebugView, Renderer, TileOrder};
+use rt::denoise::Denoiser;
+use rt::stats::Stopwatch;
+use rt::sampling::LightSampling;
+use rt::png::{PngStream, PngWriter};
+use rt::material::Material;
+use rt::scene_file::{SceneDesc, DEFAULT_FOV};
+use rt::validate::{Diagnostic, Severity};
+use rt::scene::{Scene, AcceleratorKind, AcceleratorOptions};
+use rt::bvh::{BvhOptions, SplitMethod};
+use rt::camera::{Camera, Projection};
+use rt::bench::{self, BenchReport, Workload};
+use rt::config::{Config, ImageFormat};
+use rt::image::RgbaImage;
+use rt::ppm::{PpmStream, PpmWriter};
+use rt::exr::ExrWriter;
+use rt::aov::{Aov, DepthMap};
+use rt::framebuffer::{Framebuffer, StereoLayout, Wireframe};
+use rt::serve::{RenderRequest, Server};
+use rt::distributed::{Coordinator, Farm, Manifest, Worker};
+use rt::{diff, scenes, selftest, validate, Error, Result};
 
 #[derive(Parser)]
 #[command(name = "rt")]
-#[command(about = "A CPU ray tracer that outputs PPM images")]
-struct Args {
-    #[arg(long, default_value_t = 800)]
-    width: u32,
+#[command(about = "A CPU ray tracer that outputs PPM and PNG images")]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     
-    #[arg(long, default_value_t = 600)]
-    height: u32,
+    /// Without a subcommand, `rt [OPTIONS]` renders just like `rt render [OPTIONS]`
+    #[command(flatten)]
+    render: RenderArgs,
+    
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a built-in scene or a scene file (the default when no subcommand is given)
+    Render(Box<RenderArgs>),
+    /// Check a scene file for mistakes (degenerate shapes, bad references, NaNs) without rendering
+    Validate {
+        /// Scene file to check
+        path: PathBuf,
+    },
+    /// Print object and light counts, bounds, triangles and memory of a scene file, and each object's
+    Info {
+        #[command(flatten)]
+        scene: SceneFileArgs,
+        
+        /// List only the objects with this name
+        #[arg(long, value_name = "NAME")]
+        object: Option<String>,
+    },
+    /// Write a scene file as one self-contained JSON file, with includes, --set and material overrides applied
+    Export {
+        #[command(flatten)]
+        scene: SceneFileArgs,
+        
+        /// Output file (stdout if not specified)
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Time standardized workloads and print the results as JSON
+    Bench(BenchArgs),
+    /// Compare two PNG or PPM images; exits with status 4 if they differ beyond the threshold
+    Diff(DiffArgs),
+    /// Run numerical and rendering sanity checks and report pass/fail
+    Selftest,
+    /// Print every ray, hit, shadow ray and bounce behind one pixel, rendered with the same options as `rt render`
+    Trace(Box<TraceArgs>),
+    /// Render scenes POSTed over HTTP, streaming progress and the finished PNG back
+    Serve(ServeArgs),
+    /// Render a scene file on workers connecting over TCP, handing out bands of tiles and saving the assembled image
+    Coordinator(Box<CoordinatorArgs>),
+    /// Render the frames of an animation, one scene file each, on local worker processes and workers connecting
+    /// over TCP; a manifest of finished frames lets an interrupted farm resume where it stopped
+    Farm(Box<FarmArgs>),
+    /// Render bands of tiles for a coordinator or farm until it has none left
+    Worker(WorkerArgs),
+}
+
+#[derive(clap::Args)]
+struct RenderArgs {
+    /// Image width in pixels [default: 800]
+    #[arg(long)]
+    width: Option<u32>,
+    
+    /// Image height in pixels [default: 600]
+    #[arg(long)]
+    height: Option<u32>,
     
     #[arg(long, default_value_t = 1)]
     scene: u32,
     
+    /// Render a JSON scene file instead of a built-in scene
+    #[arg(long)]
+    scene_file: Option<PathBuf>,
+    
+    /// Replace a scene file material with a library preset or another material (repeatable)
+    #[arg(long = "override-material", value_name = "NAME=PRESET", value_parser = parse_key_value)]
+    override_material: Vec<(String, String)>,
+    
+    /// Set a scene file parameter before rendering, e.g. camera.fov=35 or lights[0].intensity=2 (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    set: Vec<(String, String)>,
+    
+    /// Render from a named camera in the scene file's cameras table instead of its default camera
+    #[arg(long, value_name = "NAME")]
+    camera: Option<String>,
+    
+    /// Render every object in matte gray clay
+    #[arg(long)]
+    clay: bool,
+    
     #[arg(long, default_value_t = 1.0)]
     brightness: f64,
     
-    #[arg(long, default_value_t = 45.0)]
+    /// Exposure adjustment in stops (EV) applied to the rendered image
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    exposure: f64,
+    
+    #[arg(long, default_value_t = DEFAULT_FOV)]
     fov: f64,
     
+    /// Roll the camera this many degrees counterclockwise about its view direction, on top of the scene's own roll
+    #[arg(long, value_name = "DEGREES", default_value_t = 0.0, allow_negative_numbers = true)]
+    roll: f64,
+    
+    /// Render a stereo pair and put it together as side-by-side (twice as wide) or anaglyph (red/cyan)
+    #[arg(long, value_name = "LAYOUT")]
+    stereo: Option<StereoLayout>,
+    
+    /// Distance between the stereo eyes, in scene units (meters for eyes 6.5 cm apart)
+    #[arg(long, value_name = "DISTANCE", default_value_t = 0.065, requires = "stereo")]
+    interocular: f64,
+    
+    /// Distance ahead at which the eyes' views line up, appearing at the screen [default: the camera's look_at]
+    #[arg(long, value_name = "DISTANCE", requires = "stereo")]
+    convergence: Option<f64>,
+    
+    /// Output file, PNG, EXR or PPM by extension (stdout if not specified)
     #[arg(long)]
     output: Option<String>,
     
-    #[arg(long)]
+    /// Bits per channel of PNG and PPM output, 8 or 16 (for smooth gradients without banding) [default: 8]
+    #[arg(long, value_name = "BITS", conflicts_with = "aov")]
+    bit_depth: Option<u8>,
+    
+    /// Write the image to the output a band of rows at a time as they are rendered, holding only the band in
+    /// memory; PNG or binary (P6) PPM
+    #[arg(long, conflicts_with_all = ["stereo", "aov", "denoise", "wireframe", "time_limit", "watch"])]
+    stream: bool,
+    
+    /// Render again at preview resolution each time the scene file is saved, overwriting the output, until interrupted
+    #[arg(long, requires_all = ["scene_file", "output"])]
+    watch: bool,
+    
+    /// Fraction of the width and height that --watch previews render at
+    #[arg(long, value_name = "FRACTION", default_value_t = 0.5, requires = "watch")]
+    preview_scale: f64,
+    
+    /// Write a per-pixel quantity instead of the shaded image: depth
+    #[arg(long, value_name = "AOV", conflicts_with = "stereo")]
+    aov: Option<Aov>,
+    
+    /// Depth written as 0 in a depth AOV [default: the nearest surface]
+    #[arg(long, value_name = "DISTANCE", requires = "aov")]
+    depth_near: Option<f64>,
+    
+    /// Depth written as 1 (full white) in a depth AOV [default: the furthest surface]
+    #[arg(long, value_name = "DISTANCE", requires = "aov")]
+    depth_far: Option<f64>,
+    
+    /// Show an intermediate quantity instead of shading: normals, uv, depth, bvh-heat (objects tested per ray)
+    /// or shadow-count (lights each point is shadowed from)
+    #[arg(long, value_name = "VIEW", conflicts_with = "aov")]
+    debug_view: Option<DebugView>,
+    
+    /// Show the bvh-heat and bvh-nodes counts linearly in gray, COUNT as white, instead of on a log color ramp
+    #[arg(long, value_name = "COUNT", requires = "debug_view")]
+    heat_max: Option<f64>,
+    
+    /// Draw object outlines, creases and mesh triangle edges: overlay (over the shaded image) or only (black on white)
+    #[arg(long, value_name = "MODE", conflicts_with = "aov")]
+    wireframe: Option<Wireframe>,
+    
+    /// Antialiasing samples per pixel [default: 1]
+    #[arg(long, visible_alias = "samples")]
     aa: Option<u32>,
     
     #[arg(long)]
     reflect: bool,
     
+    /// Render on all CPU cores (same as --threads 0)
     #[arg(long)]
     mt: bool,
+    
+    /// Render threads, 0 for all CPU cores [default: 1]
+    #[arg(long)]
+    threads: Option<usize>,
+    
+    /// Output gamma, 1.0 for linear and 2.2 for typical displays [default: 1.0]
+    #[arg(long)]
+    gamma: Option<f64>,
+    
+    /// Config file with default settings [default: ./rt.toml, else ~/.config/rt/config.toml]
+    #[arg(long, conflicts_with = "no_config")]
+    config: Option<PathBuf>,
+    
+    /// Ignore config files
+    #[arg(long)]
+    no_config: bool,
+    
+    /// Give background pixels alpha 0 (written to PNG and EXR output)
+    #[arg(long)]
+    transparent: bool,
+    
+    /// Lights evaluated per shading point, picked at random and weighted [default: every light]
+    #[arg(long, value_name = "N")]
+    light_samples: Option<u32>,
+    
+    /// How --light-samples picks lights: power (brighter lights more often) or uniform
+    #[arg(long, value_name = "STRATEGY", default_value = "power", requires = "light_samples")]
+    light_sampling: LightSampling,
+    
+    /// Path trace: global illumination and soft shadows from sphere lights (use with --aa 64 or more)
+    #[arg(long)]
+    path_trace: bool,
+    
+    /// Path trace without connecting path vertices to the lights, relying on BSDF sampling alone
+    /// (much noisier and point lights go dark; for comparison and debugging)
+    #[arg(long, requires = "path_trace")]
+    no_nee: bool,
+    
+    /// Least probability with which Russian roulette lets a dim path continue after 3 bounces (1 disables it)
+    #[arg(long, value_name = "P", default_value_t = 0.05)]
+    roulette_min: f64,
+    
+    /// Limit each indirect path contribution to this value per channel, removing fireflies (--path-trace)
+    #[arg(long, value_name = "MAX")]
+    clamp: Option<f64>,
+    
+    /// Leave out samples brighter than their pixel's mean + SIGMAS standard deviations
+    #[arg(long, value_name = "SIGMAS")]
+    reject_outliers: Option<f64>,
+    
+    /// Smooth out sampling noise, guided by the normals and albedos of the visible surfaces
+    #[arg(long)]
+    denoise: bool,
+    
+    /// Order in which image tiles are rendered: scanline, spiral (center first), hilbert or random
+    #[arg(long, value_name = "ORDER", default_value = "scanline")]
+    tile_order: TileOrder,
+    
+    /// Trace only one pixel in N (2: a checkerboard, 4: one per 2×2 block) and interpolate the rest, for quick drafts
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    interleave: u32,
+    
+    /// Stop taking samples after this many seconds and write the average of those taken so far
+    #[arg(long, value_name = "SECONDS")]
+    time_limit: Option<f64>,
+    
+    /// Ignore surfaces further than this along any ray, bounding the cost of tracing huge scenes
+    #[arg(long, value_name = "DISTANCE")]
+    max_distance: Option<f64>,
+    
+    /// Seed for the random numbers of path tracing and light sampling; other seeds give other noise
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    seed: u64,
+    
+    /// Trace camera rays as packets of N×N pixels sharing one accelerator walk (1, 2 or 4; 1 disables packets)
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    packet_size: u32,
+    
+    /// Spatial index over the objects: bvh, kdtree, or none (test every object, for debugging)
+    #[arg(long, value_name = "KIND", default_value = "bvh")]
+    accel: AcceleratorKind,
+    
+    /// How BVH nodes are split: sah (surface area heuristic, faster rendering) or median (faster building)
+    #[arg(long, value_name = "METHOD", default_value = "sah")]
+    bvh_split: SplitMethod,
+    
+    /// Most objects in a BVH leaf that is never split
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    bvh_leaf_size: usize,
+    
+    /// Candidate split planes per BVH node with --bvh-split sah
+    #[arg(long, value_name = "N", default_value_t = 12)]
+    bvh_bins: usize,
+}
+
+/// A scene file plus the edits applied to it before use
+#[derive(clap::Args)]
+struct SceneFileArgs {
+    /// Scene file (JSON)
+    path: PathBuf,
+    
+    /// Replace a material with a library preset or another material (repeatable)
+    #[arg(long = "override-material", value_name = "NAME=PRESET", value_parser = parse_key_value)]
+    override_material: Vec<(String, String)>,
+    
+    /// Set a scene parameter, e.g. camera.fov=35 (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    set: Vec<(String, String)>,
+}
+
+#[derive(clap::Args)]
+struct DiffArgs {
+    /// First image (PNG or PPM)
+    a: PathBuf,
+    
+    /// Second image, the same size as the first
+    b: PathBuf,
+    
+    /// Largest per-channel error (0-1) for pixels to still count as equal
+    #[arg(long, default_value_t = 0.01)]
+    threshold: f64,
+    
+    /// Write an image highlighting where the inputs differ (.png or .ppm)
+    #[arg(long, value_name = "FILE")]
+    heatmap: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct TraceArgs {
+    /// Pixel to trace, counted from the top left
+    #[arg(long, value_name = "X,Y", value_parser = parse_pixel)]
+    pixel: (u32, u32),
+    
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(clap::Args)]
+struct ServeArgs {
+    /// Port to listen on
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+    
+    /// Address to listen on; 0.0.0.0 accepts renders from other machines
+    #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1")]
+    bind: String,
+    
+    /// Render threads for each request, 0 for all CPU cores
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+    
+    /// Directory scenes may read textures, meshes and other files from, by relative paths; without it scenes
+    /// that read files are refused
+    #[arg(long, value_name = "DIR")]
+    assets: Option<PathBuf>,
+    
+    /// Renders to run at once; further requests are refused until one finishes
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    max_renders: usize,
+}
+
+#[derive(clap::Args)]
+struct CoordinatorArgs {
+    #[command(flatten)]
+    scene: SceneFileArgs,
+    
+    /// Output file, PNG or EXR if it ends in .png or .exr, otherwise PPM
+    #[arg(long)]
+    output: String,
+    
+    /// Address workers connect to
+    #[arg(long, value_name = "ADDRESS", default_value = "0.0.0.0:7878")]
+    listen: String,
+    
+    /// Rows of 16-pixel tiles in each band handed to a worker
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    band_rows: u32,
+    
+    /// Workers a band may fail on before the render is given up
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    attempts: u32,
+    
+    /// Seconds a worker may take over a band before it counts as failed
+    #[arg(long, value_name = "SECS", default_value_t = 600)]
+    timeout: u64,
+    
+    #[command(flatten)]
+    job: JobArgs,
+}
+
+#[derive(clap::Args)]
+struct FarmArgs {
+    /// Scene files, one per frame; each frame is saved under its scene file's name
+    #[arg(required = true)]
+    frames: Vec<PathBuf>,
+    
+    /// Replace a material with a library preset or another material in every frame (repeatable)
+    #[arg(long = "override-material", value_name = "NAME=PRESET", value_parser = parse_key_value)]
+    override_material: Vec<(String, String)>,
+    
+    /// Set a scene parameter in every frame, e.g. camera.fov=35 (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    set: Vec<(String, String)>,
+    
+    /// Directory the frames are saved to
+    #[arg(long, value_name = "DIR")]
+    output_dir: PathBuf,
+    
+    /// Image format of the frames
+    #[arg(long, default_value = "png", value_parser = ["png", "exr", "ppm"])]
+    format: String,
+    
+    /// Record of finished frames, skipped when the farm is run again [default: OUTPUT_DIR/manifest.json]
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+    
+    /// Local worker processes to start, each rendering one frame at a time
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
+    
+    /// Render threads for each local worker, 0 to share the CPU cores between them
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+    
+    /// Also take workers from other machines, connecting to this address
+    #[arg(long, value_name = "ADDRESS")]
+    listen: Option<String>,
+    
+    /// Workers a frame may fail on before it is given up
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    attempts: u32,
+    
+    /// Seconds a worker may take over a frame before it counts as failed
+    #[arg(long, value_name = "SECS", default_value_t = 3600)]
+    timeout: u64,
+    
+    #[command(flatten)]
+    job: JobArgs,
+}
+
+/// Render options of a job handed to workers, and how its image is saved
+#[derive(clap::Args)]
+struct JobArgs {
+    #[arg(long, default_value_t = 800)]
+    width: u32,
+    
+    #[arg(long, default_value_t = 600)]
+    height: u32,
+    
+    /// Antialiasing samples per pixel
+    #[arg(long, visible_alias = "samples", default_value_t = 1)]
+    aa: u32,
+    
+    /// Enable mirror reflections
+    #[arg(long)]
+    reflect: bool,
+    
+    /// Path trace instead of ray tracing
+    #[arg(long)]
+    path_trace: bool,
+    
+    /// Transparent background (PNG and EXR)
+    #[arg(long)]
+    transparent: bool,
+    
+    /// Seed for the random numbers
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    
+    /// Exposure adjustment in stops (EV)
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    exposure: f64,
+    
+    /// Gamma encoding of the output
+    #[arg(long, default_value_t = 1.0)]
+    gamma: f64,
+    
+    /// Bits per channel of PNG and PPM output, 8 or 16
+    #[arg(long, value_name = "BITS", default_value_t = 8)]
+    bit_depth: u8,
+}
+
+impl JobArgs {
+    fn request(&self, scene: Option<SceneDesc>) -> RenderRequest {
+        RenderRequest {
+            scene,
+            width: self.width,
+            height: self.height,
+            samples: self.aa,
+            reflect: self.reflect,
+            path_trace: self.path_trace,
+            transparent: self.transparent,
+            seed: self.seed,
+            exposure: self.exposure,
+            gamma: self.gamma,
+        }
+    }
+}
+
+#[derive(clap::Args)]
+struct WorkerArgs {
+    /// Coordinator address to connect to, HOST:PORT
+    #[arg(long, value_name = "ADDRESS")]
+    connect: String,
+    
+    /// Render threads, 0 for all CPU cores
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+}
+
+#[derive(clap::Args)]
+struct BenchArgs {
+    #[arg(long, default_value_t = 320)]
+    width: u32,
+    
+    #[arg(long, default_value_t = 240)]
+    height: u32,
+    
+    /// Frames rendered per workload
+    #[arg(long, default_value_t = 3)]
+    iterations: u32,
+    
+    /// Antialiasing samples per pixel
+    #[arg(long, default_value_t = 1)]
+    samples: u32,
+    
+    /// Render threads, 0 for all CPU cores
+    #[arg(long, default_value_t = 1)]
+    threads: usize,
+    
+    /// Camera ray packet size, as for render
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    packet_size: u32,
+    
+    /// Spatial index to benchmark: bvh, kdtree or none
+    #[arg(long, value_name = "KIND", default_value = "bvh")]
+    accel: AcceleratorKind,
+    
+    /// Only run these workloads (primitives, mesh, shadows, spheres; repeatable)
+    #[arg(long = "workload", value_name = "NAME")]
+    workloads: Vec<String>,
+    
+    /// Write the JSON report to a file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
 }
 
-fn main() -> io::Result<()> {
-    let args = Args::parse();
-    
-    // Create scene based on scene number
-    let mut scene = Scene::new();
-    let camera;
-    
-    match args.scene {
-        1 => {
-            // Scene 1: Bright green sphere, no plane, no shadows, blue background
-            scene.background_color = Vec3::new(0.5, 0.7, 1.0); // Light blue background
-            
-            scene.add_object(Box::new(Sphere::new(
-                Vec3::new(0.0, 0.0, -3.0),
-                1.2,
-                Material::green(),
-            )));
-            
-            // Bright lighting for maximum brightness
-            scene.add_light(Light::white_light(
-                Vec3::new(0.0, 0.0, 1.0), // Light from front to avoid shadows
-                args.brightness * 2.0,     // Extra bright
-            ));
-            
-            camera = Camera::new(
-                Vec3::new(0.0, 0.0, 0.0),   // look_from
-                Vec3::new(0.0, 0.0, -1.0),  // look_at
-                Vec3::unit_y(),             // up
-                args.fov,                   // fov
-                args.width as f64 / args.height as f64, // aspect_ratio
-            );
-        }
-        2 => {
-            // Scene 2: Red cube on gray plane with shadows, dimmer than Scene 1
-            scene.background_color = Vec3::new(0.5, 0.7, 1.0); // Same blue background
-            
-            scene.add_object(Box::new(Plane::horizontal(-1.5, Material::gray())));
-            scene.add_object(Box::new(Cube::new(
-                Vec3::new(-0.5, -1.5, -3.7), // min corner - smaller cube
-                Vec3::new(0.5, -0.5, -2.7),  // max corner - smaller cube
-                Material::red(),
-            )));
-            
-            // Dimmer lighting with shadows
-            scene.add_light(Light::white_light(
-                Vec3::new(2.0, 3.0, -1.0),
-                args.brightness * 0.6, // Dimmer than scene 1
-            ));
-            
-            camera = Camera::new(
-                Vec3::new(0.0, 0.5, 0.0),
-                Vec3::new(0.0, -0.5, -3.0),
-                Vec3::unit_y(),
-                args.fov,
-                args.width as f64 / args.height as f64,
-            );
-        }
-        3 => {
-            // Scene 3: All primitives (green sphere, blue cylinder, red cube) on gray plane
-            scene.background_color = Vec3::new(0.5, 0.7, 1.0); // Same blue background
-            
-            scene.add_object(Box::new(Plane::horizontal(-1.5, Material::gray())));
-            
-            // Green sphere (left)
-            scene.add_object(Box::new(Sphere::new(
-                Vec3::new(-2.5, -0.7, -4.0),
-                0.8,
-                Material::green(),
-            )));
-            
-            // Blue cylinder (center)
-            scene.add_object(Box::new(Cylinder::new(
-                Vec3::new(0.0, -1.5, -4.5),
-                0.6,
-                2.5,  // Increased height from 1.8 to 2.5
-                Material::blue(),
-            )));
-            
-            // Red cube (right)
-            scene.add_object(Box::new(Cube::new(
-                Vec3::new(1.8, -1.5, -3.7),
-                Vec3::new(2.8, -0.5, -2.7),
-                Material::red(),
-            )));
-            
-            scene.add_light(Light::white_light(
-                Vec3::new(2.0, 4.0, -1.0),
-                args.brightness * 0.8,
-            ));
-            
-            camera = Camera::new(
-                Vec3::new(0.0, 1.5, 1.0),  // Move camera back and up
-                Vec3::new(0.0, -0.5, -4.0),
-                Vec3::unit_y(),
-                args.fov.max(60.0),  // Ensure minimum 60° FOV for wider view
-                args.width as f64 / args.height as f64,
-            );
-        }
-        4 => {
-            // Scene 4: Same objects as Scene 3 but from different camera angle
-            scene.background_color = Vec3::new(0.5, 0.7, 1.0); // Same blue background
-            
-            scene.add_object(Box::new(Plane::horizontal(-1.5, Material::gray())));
-            
-            // Green sphere (left)
-            scene.add_object(Box::new(Sphere::new(
-                Vec3::new(-2.5, -0.7, -4.0),
-                0.8,
-                Material::green(),
-            )));
-            
-            // Blue cylinder (center)
-            scene.add_object(Box::new(Cylinder::new(
-                Vec3::new(0.0, -1.5, -4.5),
-                0.6,
-                2.5,  // Increased height from 1.8 to 2.5
-                Material::blue(),
-            )));
-            
-            // Red cube (right)
-            scene.add_object(Box::new(Cube::new(
-                Vec3::new(1.8, -1.5, -3.7),
-                Vec3::new(2.8, -0.5, -2.7),
-                Material::red(),
-            )));
-            
-            scene.add_light(Light::white_light(
-                Vec3::new(2.0, 4.0, -1.0),
-                args.brightness * 0.8,
-            ));
-            
-            // Different camera position - from the side and lower
-            camera = Camera::new(
-                Vec3::new(-4.0, 0.5, -1.0),  // Further back side view for full visibility
-                Vec3::new(0.0, -0.5, -4.0),  // Same target
-                Vec3::unit_y(),
-                args.fov.max(65.0),  // Wider FOV for side view
-                args.width as f64 / args.height as f64,
-            );
-        }
-        _ => {
-            // Default to scene 1
-            scene.add_object(Box::new(Sphere::new(
-                Vec3::new(0.0, 0.0, -3.0),
-                1.0,
-                Material::red(),
-            )));
-            
-            scene.add_light(Light::white_light(
-                Vec3::new(2.0, 2.0, 0.0),
-                args.brightness,
-            ));
-            
-            camera = Camera::new(
-                Vec3::new(0.0, 0.0, 0.0),
-                Vec3::new(0.0, 0.0, -1.0),
-                Vec3::unit_y(),
-                args.fov,
-                args.width as f64 / args.height as f64,
-            );
-        }
-    }
-    
-    // Render the scene
-    let renderer = Renderer::new();
-    let writer = renderer.render(&scene, &camera, args.width, args.height);
-    
-    // Output to stdout or file
-    match args.output {
-        Some(filename) => {
-            std::fs::write(filename, writer.to_string())?;
+#[derive(clap::Args)]
+struct LogArgs {
+    /// More log output on stderr: -v for info, -vv for debug, -vvv for trace
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+    
+    /// Log level (off, error, warn, info, debug, trace); overrides -v
+    #[arg(long, value_name = "LEVEL", global = true)]
+    log_level: Option<LevelFilter>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    init_logging(&cli.log);
+    
+    let result = match &cli.command {
+        Some(Command::Render(args)) => render(args).map(|()| ExitCode::SUCCESS),
+        Some(Command::Validate { path }) => run_validate(path),
+        Some(Command::Info { scene, object }) => run_info(scene, object.as_deref()).map(|()| ExitCode::SUCCESS),
+        Some(Command::Export { scene, output }) => run_export(scene, output.as_deref()).map(|()| ExitCode::SUCCESS),
+        Some(Command::Bench(args)) => run_bench(args).map(|()| ExitCode::SUCCESS),
+        Some(Command::Diff(args)) => run_diff(args),
+        Some(Command::Selftest) => Ok(run_selftest()),
+        Some(Command::Trace(args)) => run_trace(args).map(|()| ExitCode::SUCCESS),
+        Some(Command::Serve(args)) => run_serve(args).map(|()| ExitCode::SUCCESS),
+        Some(Command::Coordinator(args)) => run_coordinator(args).map(|()| ExitCode::SUCCESS),
+        Some(Command::Farm(args)) => run_farm(args).map(|()| ExitCode::SUCCESS),
+        Some(Command::Worker(args)) => run_worker(args).map(|()| ExitCode::SUCCESS),
+        None => render(&cli.render).map(|()| ExitCode::SUCCESS),
+    };
+    
+    result.unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        ExitCode::from(err.exit_code())
+    })
+}
+
+/// Log to stderr at the level chosen by --log-level or -v (warnings only by default)
+/// RUST_LOG, if set, takes precedence, e.g. RUST_LOG=rt::render=debug
+fn init_logging(args: &LogArgs) {
+    let level = args.log_level.unwrap_or(match args.verbose {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    });
+    
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .format(|buf, record| writeln!(buf, "{}: {}", record.level().as_str().to_lowercase(), record.args()))
+        .init();
+}
+
+/// Render settings after applying config file defaults and command-line overrides
+struct Settings {
+    width: u32,
+    height: u32,
+    samples: u32,
+    threads: usize,
+    gamma: f64,
+    format: ImageFormat,
+    bit_depth: u8, // Of PNG and PPM samples
+}
+
+/// Render the selected scene and write the image
+fn render(args: &RenderArgs) -> Result<()> {
+    let settings = resolve_settings(args)?;
+    if args.watch {
+        return watch(args, settings);
+    }
+    render_with(args, &settings)
+}
+
+/// Render the scene file at preview resolution, then again each time it changes, until interrupted
+/// Errors in the scene file are reported and the previous image kept, so a half-finished edit doesn't end the loop
+fn watch(args: &RenderArgs, settings: Settings) -> Result<()> {
+    let path = args.scene_file.as_deref().expect("--watch requires --scene-file");
+    let preview = |size: u32| ((size as f64 * args.preview_scale).round() as u32).max(1);
+    let settings = Settings { width: preview(settings.width), height: preview(settings.height), ..settings };
+    
+    // Editors often save by writing a new file and renaming it over the old one, which a watch on the file
+    // itself would lose track of, so the directory is watched instead
+    let watch_error = |source| Error::Watch { path: path.to_path_buf(), source: io::Error::other::<notify::Error>(source) };
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+    let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    watcher.watch(directory, RecursiveMode::NonRecursive).map_err(watch_error)?;
+    
+    loop {
+        let start = Stopwatch::start();
+        match render_with(args, &settings) {
+            Ok(()) => eprintln!("rendered {}x{} in {:.2?}; watching {}", settings.width, settings.height, start.elapsed(), path.display()),
+            Err(err) => eprintln!("error: {}", err),
+        }
+        loop {
+            match events.recv() {
+                Ok(Ok(event)) if saves(&event, path) => break,
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => log::warn!("watching {}: {}", path.display(), err),
+                Err(mpsc::RecvError) => return Ok(()), // The watcher has stopped
+            }
+        }
+        // A save can take several events; render once they settle
+        while events.recv_timeout(Duration::from_millis(100)).is_ok() {}
+    }
+}
+
+/// Whether a watch event wrote the file at `path`, directly or by renaming another file over it
+fn saves(event: &notify::Event, path: &Path) -> bool {
+    let written = match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => &event.paths[event.paths.len().saturating_sub(1)..], // [from, to]
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => &[][..], // Moved away, e.g. to a backup
+        EventKind::Create(_) | EventKind::Modify(_) => &event.paths[..],
+        _ => &[][..],
+    };
+    written.iter().any(|changed| changed.file_name() == path.file_name())
+}
+
+/// Render the selected scene with resolved settings and write the image
+fn render_with(args: &RenderArgs, settings: &Settings) -> Result<()> {
+    let (width, height) = (settings.width, settings.height);
+    let (scene, camera) = load_scene(args, settings)?;
+    let renderer = build_renderer(args, settings);
+    if args.stream {
+        return render_streaming(args, settings, (&scene, &camera), &renderer);
+    }
+    if args.aov == Some(Aov::Depth) {
+        let depths = renderer.render_depth(&scene, &camera, width, height);
+        return write_output(args.output.as_deref(), &encode_depth(&depths, args, settings.format));
+    }
+    let render_view = |camera: &Camera| {
+        if args.wireframe == Some(Wireframe::Only) {
+            let mut framebuffer = Framebuffer::new(width, height);
+            Wireframe::Only.draw(&mut framebuffer, &renderer.render_edges(&scene, camera, width, height));
+            return framebuffer;
         }
+        let mut framebuffer = renderer.render_framebuffer(&scene, camera, width, height);
+        if args.denoise {
+            let start = Stopwatch::start();
+            let guides = renderer.render_guides(&scene, camera, width, height);
+            framebuffer = Denoiser::new().denoise(&framebuffer, &guides);
+            log::info!("denoised in {:.2?}", start.elapsed());
+        }
+        framebuffer.apply_exposure(args.exposure); // Before the lines, which keep their color
+        if let Some(wireframe) = args.wireframe {
+            wireframe.draw(&mut framebuffer, &renderer.render_edges(&scene, camera, width, height));
+        }
+        framebuffer
+    };
+    let mut framebuffer = match args.stereo {
+        Some(layout) => {
+            let convergence = args.convergence.map_or(camera.target_distance, |distance| distance as Float);
+            let offset = args.interocular as Float / 2.0;
+            layout.combine(&render_view(&camera.eye(-offset, convergence)), &render_view(&camera.eye(offset, convergence)))
+        }
+        None => render_view(&camera),
+    };
+    framebuffer.apply_gamma(settings.gamma);
+    write_output(args.output.as_deref(), &encode_image(&framebuffer, settings.format, args.transparent, settings.bit_depth))
+}
+
+/// Render straight to the output a band at a time, each band finished and encoded as soon as it is done
+fn render_streaming(args: &RenderArgs, settings: &Settings, (scene, camera): (&Scene, &Camera), renderer: &Renderer) -> Result<()> {
+    let (width, height) = (settings.width, settings.height);
+    let target = args.output.as_deref().unwrap_or("stdout");
+    let io_error = |source| Error::Io { target: target.to_string(), source };
+    let writer: Box<dyn Write> = match &args.output {
+        Some(filename) => Box::new(std::fs::File::create(filename).map_err(io_error)?),
+        None => Box::new(io::stdout()),
+    };
+    let bit_depth = settings.bit_depth;
+    let finish = |mut band: Framebuffer| {
+        band.apply_exposure(args.exposure);
+        band.apply_gamma(settings.gamma);
+        band
+    };
+    
+    match settings.format {
+        ImageFormat::Png => {
+            let mut png = PngStream::new(writer, width, height, if args.transparent { 4 } else { 3 }, bit_depth).map_err(io_error)?;
+            renderer
+                .render_bands(scene, camera, (width, height), |band| {
+                    let band = finish(band);
+                    png.write_rows(&match (args.transparent, bit_depth) {
+                        (true, 16) => big_endian(&band.to_rgba16()),
+                        (false, 16) => big_endian(&band.to_rgb16()),
+                        (true, _) => band.to_rgba8(),
+                        (false, _) => band.to_rgb8(),
+                    })
+                })
+                .map_err(io_error)?;
+            png.finish().map_err(io_error)?;
+        }
+        ImageFormat::Ppm => {
+            let mut ppm = PpmStream::new(writer, width, height, bit_depth).map_err(io_error)?;
+            renderer
+                .render_bands(scene, camera, (width, height), |band| {
+                    let band = finish(band);
+                    ppm.write_rows(&if bit_depth == 16 { big_endian(&band.to_rgb16()) } else { band.to_rgb8() })
+                })
+                .map_err(io_error)?;
+            ppm.finish().map_err(io_error)?;
+        }
+        ImageFormat::Exr => unreachable!("checked with the settings"),
+    }
+    Ok(())
+}
+
+/// Encode a finished image, with its alpha channel for transparent renders to PNG or EXR
+/// 16-bit PPM images are binary (P6); 8-bit ones stay ASCII (P3)
+fn encode_image(framebuffer: &Framebuffer, format: ImageFormat, transparent: bool, bit_depth: u8) -> Vec<u8> {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    match format {
+        ImageFormat::Png if bit_depth == 16 && transparent => PngWriter::rgba16(width, height, &framebuffer.to_rgba16()).to_bytes(),
+        ImageFormat::Png if bit_depth == 16 => PngWriter::rgb16(width, height, &framebuffer.to_rgb16()).to_bytes(),
+        ImageFormat::Png if transparent => PngWriter::rgba(width, height, framebuffer.to_rgba8()).to_bytes(),
+        ImageFormat::Png => PngWriter::rgb(width, height, framebuffer.to_rgb8()).to_bytes(),
+        ImageFormat::Ppm if bit_depth == 16 => {
+            let mut ppm = PpmStream::new(Vec::new(), width, height, 16).expect("writing to memory");
+            ppm.write_rows(&big_endian(&framebuffer.to_rgb16())).expect("writing to memory");
+            ppm.finish().expect("writing to memory")
+        }
+        ImageFormat::Ppm => framebuffer.to_ppm().to_string().into_bytes(),
+        ImageFormat::Exr => framebuffer.to_exr(transparent).to_bytes(),
+    }
+}
+
+/// 16-bit samples as bytes, high byte first, as PNG and PPM store them
+fn big_endian(values: &[u16]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_be_bytes()).collect()
+}
+
+/// Print the events behind each sample of one pixel, as the render arguments would render it
+fn run_trace(args: &TraceArgs) -> Result<()> {
+    let settings = resolve_settings(&args.render)?;
+    let (x, y) = args.pixel;
+    if x >= settings.width || y >= settings.height {
+        let reason = format!("({}, {}) is outside the {}x{} image", x, y, settings.width, settings.height);
+        return Err(Error::InvalidArgument { name: "--pixel", reason });
+    }
+    let (scene, camera) = load_scene(&args.render, &settings)?;
+    let renderer = build_renderer(&args.render, &settings);
+    
+    let traces = renderer.trace_pixel(&scene, &camera, (settings.width, settings.height), (x, y));
+    println!("pixel ({}, {}) of {}x{}, {} samples", x, y, settings.width, settings.height, traces.len());
+    for trace in &traces {
+        print!("{}", trace);
+    }
+    Ok(())
+}
+
+/// Create the scene from the scene file, or based on scene number
+fn load_scene(args: &RenderArgs, settings: &Settings) -> Result<(Scene, Camera)> {
+    let (width, height) = (settings.width, settings.height);
+    let aspect_ratio = width as f64 / height as f64;
+    let (scene, camera) = match &args.scene_file {
+        Some(path) => load_scene_file(path, args, aspect_ratio)?,
         None => {
-            print!("{}", writer.to_string());
+            if !args.override_material.is_empty() || !args.set.is_empty() || args.camera.is_some() {
+                log::warn!("--override-material, --set and --camera only apply to --scene-file scenes");
+            }
+            log::info!("built-in scene {}", args.scene);
+            let (mut scene, camera) = scenes::build(args.scene, args.brightness, args.fov, aspect_ratio);
+            scene.accelerator = accelerator_options(args);
+            (scene, camera)
         }
+    };
+    let camera = if args.roll != 0.0 { camera.with_roll(args.roll as Float) } else { camera };
+    if camera.projection == Projection::Panorama && width != 2 * height {
+        log::warn!("panoramas are twice as wide as they are high; {}x{} stretches this one", width, height);
+    }
+    log::info!(
+        "scene: {} objects, {} lights, {} media",
+        scene.objects.len(),
+        scene.lights.len(),
+        scene.media.len()
+    );
+    
+    Ok((scene, camera))
+}
+
+/// Set up a renderer with the render arguments' options
+fn build_renderer(args: &RenderArgs, settings: &Settings) -> Renderer {
+    let mut renderer = Renderer::new();
+    renderer.reflections = args.reflect;
+    renderer.transparent_background = args.transparent;
+    renderer.samples = settings.samples;
+    renderer.threads = settings.threads;
+    renderer.light_samples = args.light_samples;
+    renderer.light_sampling = args.light_sampling;
+    renderer.path_tracing = args.path_trace;
+    renderer.next_event_estimation = !args.no_nee;
+    renderer.roulette_min_probability = args.roulette_min as Float;
+    renderer.clamp = args.clamp.map(|max| max as Float);
+    renderer.outlier_rejection = args.reject_outliers.map(|sigmas| sigmas as Float);
+    renderer.packet_size = args.packet_size;
+    renderer.seed = args.seed;
+    renderer.tile_order = args.tile_order;
+    renderer.interleave = args.interleave;
+    renderer.time_limit = args.time_limit.map(Duration::from_secs_f64);
+    renderer.max_distance = args.max_distance.map_or(Float::INFINITY, |distance| distance as Float);
+    renderer.debug_view = args.debug_view;
+    renderer.heat_max = args.heat_max.map(|max| max as Float);
+    if args.clay {
+        renderer.material_override = Some(Material::clay());
     }
+    renderer
+}
+
+/// Encode a depth map, normalized from --depth-near to --depth-far (the image's nearest and furthest surfaces
+/// by default) for PNG and PPM; EXR keeps the depths themselves unless either is given
+fn encode_depth(depths: &DepthMap, args: &RenderArgs, format: ImageFormat) -> Vec<u8> {
+    let (nearest, furthest) = depths.range().unwrap_or((0.0, 1.0));
+    let (near, far) = (args.depth_near.map_or(nearest, |near| near as Float), args.depth_far.map_or(furthest, |far| far as Float));
+    log::info!("depth {:.3} to {:.3} mapped to 0 to 1", near, far);
+    match format {
+        ImageFormat::Png => depths.to_png(near, far).to_bytes(),
+        ImageFormat::Ppm => depths.to_ppm(near, far).to_string().into_bytes(),
+        ImageFormat::Exr => {
+            let normalized = args.depth_near.is_some() || args.depth_far.is_some();
+            depths.to_exr(normalized.then_some((near, far))).to_bytes()
+        }
+    }
+}
+
+/// Write an encoded image to the output file, or stdout if there is none
+fn write_output(output: Option<&str>, bytes: &[u8]) -> Result<()> {
+    match output {
+        Some(filename) => std::fs::write(filename, bytes).map_err(|source| Error::Io { target: filename.to_string(), source }),
+        None => io::stdout().write_all(bytes).map_err(|source| Error::Io { target: "stdout".to_string(), source }),
+    }
+}
+
+/// Combine command-line flags, the config file and built-in defaults (in that order of priority)
+fn resolve_settings(args: &RenderArgs) -> Result<Settings> {
+    let config_path = if args.no_config { None } else { args.config.clone().or_else(Config::find) };
+    let config = match &config_path {
+        Some(path) => {
+            log::info!("using config {}", path.display());
+            Config::load(path).map_err(|source| Error::Config { path: path.clone(), source })?
+        }
+        None => Config::default(),
+    };
+    
+    let threads = if args.mt { Some(0) } else { args.threads };
+    let threads = match threads.or(config.threads).unwrap_or(1) {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    
+    let settings = Settings {
+        width: args.width.or(config.width).unwrap_or(800),
+        height: args.height.or(config.height).unwrap_or(600),
+        samples: args.aa.or(config.samples).unwrap_or(1),
+        threads,
+        gamma: args.gamma.or(config.gamma).unwrap_or(1.0),
+        format: output_format(args.output.as_deref(), config.format)?,
+        bit_depth: args.bit_depth.or(config.bit_depth).unwrap_or(8),
+    };
+    check_settings(args, &settings)?;
+    Ok(settings)
+}
+
+/// Check a bit depth is one PNG and PPM output can have, and, if the format it is for is given, one that format takes
+fn check_bit_depth(bit_depth: u8, format: Option<ImageFormat>) -> Result<()> {
+    let invalid = |reason| Err(Error::InvalidArgument { name: "--bit-depth", reason });
+    if ![8, 16].contains(&bit_depth) {
+        return invalid(format!("must be 8 or 16, got {}", bit_depth));
+    }
+    if bit_depth == 16 && format == Some(ImageFormat::Exr) {
+        return invalid("EXR output is always 32-bit float".to_string());
+    }
+    Ok(())
+}
+
+/// Pick the image format from the output file extension, else the config (PPM by default)
+fn output_format(output: Option<&str>, default: Option<ImageFormat>) -> Result<ImageFormat> {
+    let extension = output
+        .and_then(|name| Path::new(name).extension())
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    
+    match extension.as_deref() {
+        None => Ok(default.unwrap_or(ImageFormat::Ppm)),
+        Some("ppm") => Ok(ImageFormat::Ppm),
+        Some("png") => Ok(ImageFormat::Png),
+        Some("exr") => Ok(ImageFormat::Exr),
+        Some(other) => Err(Error::UnsupportedFormat(other.to_string())),
+    }
+}
+
+/// Reject settings that would otherwise render NaNs or an empty image
+fn check_settings(args: &RenderArgs, settings: &Settings) -> Result<()> {
+    let invalid = |name, reason: String| Err(Error::InvalidArgument { name, reason });
     
+    if settings.width == 0 || settings.height == 0 {
+        return invalid("--width/--height", format!("image size must be non-zero, got {}x{}", settings.width, settings.height));
+    }
+    if settings.samples == 0 {
+        return invalid("--aa", "need at least one sample per pixel".to_string());
+    }
+    if let Some(max) = args.clamp.filter(|max| !(max.is_finite() && *max > 0.0)) {
+        return invalid("--clamp", format!("must be a positive number, got {}", max));
+    }
+    if let Some(sigmas) = args.reject_outliers.filter(|sigmas| !(sigmas.is_finite() && *sigmas >= 0.0)) {
+        return invalid("--reject-outliers", format!("must be a non-negative number, got {}", sigmas));
+    }
+    if !(args.roulette_min > 0.0 && args.roulette_min <= 1.0) {
+        return invalid("--roulette-min", format!("must be in (0, 1], got {}", args.roulette_min));
+    }
+    if let Some(seconds) = args.time_limit.filter(|seconds| !(seconds.is_finite() && *seconds >= 0.0)) {
+        return invalid("--time-limit", format!("must be a non-negative number of seconds, got {}", seconds));
+    }
+    if args.time_limit.is_some() && args.reject_outliers.is_some() {
+        return invalid("--reject-outliers", "can't be combined with --time-limit, which keeps only running sums".to_string());
+    }
+    if let Some(distance) = args.max_distance.filter(|distance| !(distance.is_finite() && *distance > 0.0)) {
+        return invalid("--max-distance", format!("must be a positive number, got {}", distance));
+    }
+    if let Some(max) = args.heat_max.filter(|max| !(max.is_finite() && *max > 0.0)) {
+        return invalid("--heat-max", format!("must be a positive count, got {}", max));
+    }
+    for (name, distance) in [("--depth-near", args.depth_near), ("--depth-far", args.depth_far)] {
+        if let Some(distance) = distance.filter(|distance| !(distance.is_finite() && *distance >= 0.0)) {
+            return invalid(name, format!("must be a non-negative number, got {}", distance));
+        }
+    }
+    if let (Some(near), Some(far)) = (args.depth_near, args.depth_far) {
+        if far <= near {
+            return invalid("--depth-far", format!("must be beyond --depth-near ({}), got {}", near, far));
+        }
+    }
+    if ![1, 2, 4].contains(&args.interleave) {
+        return invalid("--interleave", format!("must be 1, 2 or 4, got {}", args.interleave));
+    }
+    if args.stream && args.interleave > 1 {
+        return invalid("--stream", "can't be combined with --interleave, which fills in pixels across the whole image".to_string());
+    }
+    // A config file's bit depth is left out of EXR renders rather than refused
+    check_bit_depth(settings.bit_depth, args.bit_depth.is_some().then_some(settings.format))?;
+    if args.stream && settings.format == ImageFormat::Exr {
+        return invalid("--stream", "writes PNG or PPM, not EXR".to_string());
+    }
+    if ![1, 2, 4].contains(&args.packet_size) {
+        return invalid("--packet-size", format!("must be 1, 2 or 4, got {}", args.packet_size));
+    }
+    if args.bvh_leaf_size == 0 {
+        return invalid("--bvh-leaf-size", "leaves need room for at least one object".to_string());
+    }
+    if args.bvh_bins < 2 {
+        return invalid("--bvh-bins", format!("need at least 2 bins to split between, got {}", args.bvh_bins));
+    }
+    if args.light_samples == Some(0) {
+        return invalid("--light-samples", "need at least one light per shading point".to_string());
+    }
+    if !(settings.gamma.is_finite() && settings.gamma > 0.0) {
+        return invalid("--gamma", format!("must be a positive number, got {}", settings.gamma));
+    }
+    if !(args.fov > 0.0 && args.fov < 180.0) {
+        return invalid("--fov", format!("must be between 0 and 180 degrees, got {}", args.fov));
+    }
+    if !args.brightness.is_finite() || args.brightness < 0.0 {
+        return invalid("--brightness", format!("must be a non-negative number, got {}", args.brightness));
+    }
+    if !args.exposure.is_finite() {
+        return invalid("--exposure", format!("must be a finite number, got {}", args.exposure));
+    }
+    if !args.roll.is_finite() {
+        return invalid("--roll", format!("must be a finite number, got {}", args.roll));
+    }
+    if !(args.interocular.is_finite() && args.interocular >= 0.0) {
+        return invalid("--interocular", format!("must be a non-negative number, got {}", args.interocular));
+    }
+    if let Some(convergence) = args.convergence.filter(|distance| !(distance.is_finite() && *distance > 0.0)) {
+        return invalid("--convergence", format!("must be a positive distance, got {}", convergence));
+    }
+    if !(args.preview_scale > 0.0 && args.preview_scale <= 1.0) {
+        return invalid("--preview-scale", format!("must be in (0, 1], got {}", args.preview_scale));
+    }
     Ok(())
 }
+
+/// Parse a KEY=VALUE pair (material overrides and scene parameters)
+fn parse_key_value(value: &str) -> std::result::Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() && !value.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", value)),
+    }
+}
+
+/// Parse a pixel position given as X,Y
+fn parse_pixel(value: &str) -> std::result::Result<(u32, u32), String> {
+    let parse = |text: &str| text.trim().parse::<u32>().ok();
+    match value.split_once(',') {
+        Some((x, y)) => parse(x).zip(parse(y)).ok_or_else(|| format!("expected X,Y in whole pixels, got '{}'", value)),
+        None => Err(format!("expected X,Y, got '{}'", value)),
+    }
+}
+
+/// Read a scene file and apply --set parameters and material overrides
+fn read_scene_file(path: &Path, set: &[(String, String)], overrides: &[(String, String)]) -> Result<SceneDesc> {
+    let scene_error = |source| Error::Scene { path: path.to_path_buf(), source };
+    
+    let mut desc = SceneDesc::from_file_with(path, set).map_err(scene_error)?;
+    for (name, preset) in overrides {
+        desc.override_material(name, preset).map_err(scene_error)?;
+    }
+    Ok(desc)
+}
+
+/// Load a scene file with the render arguments' edits, then check and build it
+fn load_scene_file(path: &Path, args: &RenderArgs, aspect_ratio: f64) -> Result<(Scene, Camera)> {
+    let mut desc = read_scene_file(path, &args.set, &args.override_material)?;
+    if let Some(name) = &args.camera {
+        desc.select_camera(name).map_err(|source| Error::Scene { path: path.to_path_buf(), source })?;
+    }
+    log::info!(
+        "loaded {}: {} objects, {} lights, {} materials",
+        path.display(),
+        desc.objects.len(),
+        desc.lights.len(),
+        desc.materials.len()
+    );
+    
+    let (errors, warnings): (Vec<Diagnostic>, Vec<Diagnostic>) = validate::validate(&desc)
+        .into_iter()
+        .partition(|d| d.severity == Severity::Error);
+    for warning in &warnings {
+        log::warn!("{}: {}: {}", path.display(), warning.field, warning.message);
+    }
+    if !errors.is_empty() {
+        return Err(Error::InvalidScene { path: path.to_path_buf(), errors });
+    }
+    
+    desc.build_with(args.brightness, args.fov, aspect_ratio, &accelerator_options(args))
+        .map_err(|source| Error::Scene { path: path.to_path_buf(), source })
+}
+
+fn accelerator_options(args: &RenderArgs) -> AcceleratorOptions {
+    let bvh = BvhOptions { split: args.bvh_split, max_leaf_size: args.bvh_leaf_size, bins: args.bvh_bins, ..BvhOptions::default() };
+    AcceleratorOptions { kind: args.accel, bvh }
+}
+
+/// Print scene file diagnostics; the exit status is non-zero if there are errors
+fn run_validate(path: &Path) -> Result<ExitCode> {
+    let desc = SceneDesc::from_file(path).map_err(|source| Error::Scene { path: path.to_path_buf(), source })?;
+    
+    let diagnostics = validate::validate(&desc);
+    for diagnostic in &diagnostics {
+        eprintln!("{}: {}", path.display(), diagnostic);
+    }
+    
+    if validate::has_errors(&diagnostics) {
+        return Ok(ExitCode::from(3));
+    }
+    println!("{}: ok ({} warnings)", path.display(), diagnostics.len());
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print a summary of a scene file: counts by object type, lights, cameras, materials, shared geometry, bounds, triangles
+/// and memory, then the type, name, triangles, memory, bounds and materials of each object (or those named `object`)
+fn run_info(args: &SceneFileArgs, object: Option<&str>) -> Result<()> {
+    let desc = read_scene_file(&args.path, &args.set, &args.override_material)?;
+    let scene_error = |source| Error::Scene { path: args.path.clone(), source };
+    let listed = match object {
+        Some(name) => desc.objects_named(name).map_err(scene_error)?,
+        None => (0..desc.objects.len()).collect(),
+    };
+    
+    // Shared geometry is charged to the first object placing it
+    let shapes = desc.build_objects(&AcceleratorOptions::default()).map_err(scene_error)?;
+    let mut shared = HashSet::new();
+    let memory: Vec<usize> = shapes.iter().map(|shape| shape.memory(&mut shared)).collect();
+    let triangles: usize = shapes.iter().map(|shape| shape.triangle_count()).sum();
+    
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for object in &desc.objects {
+        *counts.entry(object.shape.type_name()).or_default() += 1;
+    }
+    let breakdown: Vec<String> = counts.iter().map(|(name, count)| format!("{} {}", count, name)).collect();
+    
+    println!("{}", args.path.display());
+    println!("  objects:   {} ({})", desc.objects.len(), breakdown.join(", "));
+    println!("  lights:    {}", desc.lights.len());
+    if !desc.cameras.is_empty() {
+        println!("  cameras:   default, {}", Vec::from_iter(desc.cameras.keys().map(String::as_str)).join(", "));
+    }
+    println!("  materials: {} defined", desc.materials.len());
+    if !desc.geometry.is_empty() {
+        let shapes: usize = desc.geometry.values().map(Vec::len).sum();
+        println!("  geometry:  {} shared ({} shapes)", desc.geometry.len(), shapes);
+    }
+    let bounded: Vec<Aabb> = shapes.iter().filter_map(|shape| shape.bounds()).collect();
+    match bounded.iter().copied().reduce(|a, b| a.union(&b)) {
+        Some(bounds) => println!("  bounds:    {}", format_bounds(bounds)),
+        None => println!("  bounds:    none (no bounded objects)"),
+    }
+    if bounded.len() < shapes.len() {
+        println!("             ({} unbounded object(s) not included)", shapes.len() - bounded.len());
+    }
+    println!("  triangles: {}", triangles);
+    println!("  memory:    ~{} in objects", format_bytes(memory.iter().sum()));
+    
+    let names: Vec<&str> = desc.objects.iter().map(|object| object.name.as_deref().unwrap_or("-")).collect();
+    let materials: Vec<String> = desc.objects.iter().map(|object| Vec::from_iter(desc.material_names(object)).join(", ")).collect();
+    let width = |column: &dyn Fn(usize) -> usize, header: &str| listed.iter().map(|&i| column(i)).max().unwrap_or(0).max(header.len());
+    let index_width = width(&|i| i.to_string().len(), "#");
+    let type_width = width(&|i| desc.objects[i].shape.type_name().len(), "type");
+    let name_width = width(&|i| names[i].len(), "name");
+    let material_width = width(&|i| materials[i].len(), "materials");
+    println!();
+    println!(
+        "  {:>index_width$}  {:type_width$}  {:name_width$}  {:>9}  {:>9}  {:material_width$}  bounds",
+        "#", "type", "name", "triangles", "memory", "materials"
+    );
+    for &i in &listed {
+        let bounds = shapes[i].bounds().map_or_else(|| "unbounded".to_string(), format_bounds);
+        println!(
+            "  {:>index_width$}  {:type_width$}  {:name_width$}  {:>9}  {:>9}  {:material_width$}  {}",
+            i,
+            desc.objects[i].shape.type_name(),
+            names[i],
+            shapes[i].triangle_count(),
+            format_bytes(memory[i]),
+            materials[i],
+            bounds,
+        );
+    }
+    Ok(())
+}
+
+/// A box's corners to a thousandth, for reading rather than reloading
+fn format_bounds(bounds: Aabb) -> String {
+    let point = |p: Vec3| [p.x, p.y, p.z].map(|x| (x * 1000.0).round() / 1000.0 + 0.0); // Adding zero turns -0 into 0
+    format!("{:?} to {:?}", point(bounds.min), point(bounds.max))
+}
+
+/// A byte count in B, KB or MB
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1048576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+    }
+}
+
+/// Write the resolved scene description as JSON
+fn run_export(args: &SceneFileArgs, output: Option<&Path>) -> Result<()> {
+    let json = read_scene_file(&args.path, &args.set, &args.override_material)?.to_json() + "\n";
+    match output {
+        Some(path) => std::fs::write(path, json).map_err(|source| Error::Io { target: path.display().to_string(), source }),
+        None => io::stdout().write_all(json.as_bytes()).map_err(|source| Error::Io { target: "stdout".to_string(), source }),
+    }
+}
+
+/// Listen for render requests until interrupted
+fn run_serve(args: &ServeArgs) -> Result<()> {
+    let address = format!("{}:{}", args.bind, args.port);
+    let serve_error = |source| Error::Serve { address: address.clone(), source };
+    let listener = TcpListener::bind(&address).map_err(serve_error)?;
+    let threads = match args.threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    eprintln!("listening on http://{}", listener.local_addr().map_err(serve_error)?);
+    let mut server = Server::new(threads);
+    server.assets = args.assets.clone();
+    server.max_renders = args.max_renders.max(1);
+    server.serve(listener).map_err(serve_error)
+}
+
+/// Hand out bands of the scene to workers as they connect, then save the image they render
+fn run_coordinator(args: &CoordinatorArgs) -> Result<()> {
+    let format = output_format(Some(&args.output), None)?;
+    check_bit_depth(args.job.bit_depth, Some(format))?;
+    let job = args.job.request(Some(read_scene_file(&args.scene.path, &args.scene.set, &args.scene.override_material)?));
+    let invalid = |reason| Error::InvalidArgument { name: "coordinator", reason };
+    job.check().map_err(invalid)?;
+    job.build().map_err(invalid)?; // Catch scene errors here rather than on every worker
+    
+    let listener = TcpListener::bind(&args.listen).map_err(|source| Error::Serve { address: args.listen.clone(), source })?;
+    eprintln!("waiting for workers on {}", args.listen);
+    let start = Stopwatch::start();
+    let timeout = Duration::from_secs(args.timeout.max(1));
+    let coordinator = Coordinator { band_rows: args.band_rows.max(1), attempts: args.attempts.max(1), timeout };
+    let mut framebuffer = coordinator.render(&listener, &job).map_err(Error::Distributed)?;
+    log::info!("rendered {}x{} in {:.2?}", job.width, job.height, start.elapsed());
+    job.finish(&mut framebuffer);
+    write_output(Some(&args.output), &encode_image(&framebuffer, format, job.transparent, args.job.bit_depth))
+}
+
+/// Render the frames not yet in the manifest on local worker processes and any workers connecting, saving
+/// each frame and adding it to the manifest as it comes in
+fn run_farm(args: &FarmArgs) -> Result<()> {
+    let invalid = |reason| Error::InvalidArgument { name: "farm", reason };
+    if args.jobs == 0 && args.listen.is_none() {
+        return Err(invalid("no workers: use --jobs, --listen or both".to_string()));
+    }
+    
+    // Each frame's output is named after its scene file
+    let mut outputs = HashSet::new();
+    let mut frames = Vec::new();
+    for path in &args.frames {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let output = args.output_dir.join(format!("{}.{}", stem, args.format));
+        if !outputs.insert(output.clone()) {
+            return Err(invalid(format!("two frames would be saved to {}", output.display())));
+        }
+        frames.push((path, output));
+    }
+    let options = args.job.request(None);
+    check_bit_depth(args.job.bit_depth, Some(output_format(Some(&format!("frame.{}", args.format)), None)?))?;
+    
+    // Frames the manifest lists as finished, with the same options, are skipped
+    let manifest_path = args.manifest.clone().unwrap_or_else(|| args.output_dir.join("manifest.json"));
+    let manifest_error = |source| Error::Io { target: manifest_path.display().to_string(), source };
+    let manifest = match Manifest::load(&manifest_path).map_err(manifest_error)? {
+        Some(manifest) if !manifest.matches(&options, args.job.bit_depth) => {
+            return Err(invalid(format!("{} is for other render options; remove it to render every frame again", manifest_path.display())));
+        }
+        Some(manifest) => manifest,
+        None => Manifest::new(&options, args.job.bit_depth),
+    };
+    frames.retain(|(path, output)| !manifest.is_finished(&path.display().to_string(), output));
+    if frames.len() < args.frames.len() {
+        eprintln!("{} of {} frames already finished", args.frames.len() - frames.len(), args.frames.len());
+    }
+    if frames.is_empty() {
+        return Ok(());
+    }
+    let jobs = frames
+        .iter()
+        .map(|(path, _)| Ok(args.job.request(Some(read_scene_file(path, &args.set, &args.override_material)?))))
+        .collect::<Result<Vec<_>>>()?;
+    std::fs::create_dir_all(&args.output_dir).map_err(|source| Error::Io { target: args.output_dir.display().to_string(), source })?;
+    
+    let listen = args.listen.as_deref().unwrap_or("127.0.0.1:0");
+    let serve_error = |source| Error::Serve { address: listen.to_string(), source };
+    let listener = TcpListener::bind(listen).map_err(serve_error)?;
+    let mut address = listener.local_addr().map_err(serve_error)?;
+    if address.ip().is_unspecified() {
+        address.set_ip([127, 0, 0, 1].into());
+    }
+    if args.listen.is_some() {
+        eprintln!("waiting for workers on {}", listen);
+    }
+    let threads = match args.threads {
+        0 => (std::thread::available_parallelism().map_or(1, |n| n.get()) / args.jobs.max(1)).max(1),
+        n => n,
+    };
+    let exe = std::env::current_exe().map_err(|source| Error::Io { target: "rt executable".to_string(), source })?;
+    let workers: Vec<_> = (0..args.jobs)
+        .map(|_| std::process::Command::new(&exe).args(["worker", "--connect", &address.to_string(), "--threads", &threads.to_string()]).spawn())
+        .collect::<io::Result<_>>()
+        .map_err(|source| Error::Io { target: exe.display().to_string(), source })?;
+    let workers = std::sync::Mutex::new(workers);
+    
+    // Without other machines to wait for, stop once every local worker has exited
+    let stop = || args.listen.is_none() && workers.lock().unwrap().iter_mut().all(|worker| !matches!(worker.try_wait(), Ok(None)));
+    let manifest = std::sync::Mutex::new(manifest);
+    let failed = std::sync::Mutex::new(Vec::new());
+    let save = |frame: usize, mut framebuffer: Framebuffer| {
+        let (path, output) = &frames[frame];
+        jobs[frame].finish(&mut framebuffer);
+        let saved = output_format(output.to_str(), None).and_then(|format| {
+            let bytes = encode_image(&framebuffer, format, jobs[frame].transparent, args.job.bit_depth);
+            std::fs::write(output, bytes).map_err(|source| Error::Io { target: output.display().to_string(), source })
+        });
+        if let Err(err) = saved {
+            failed.lock().unwrap().push(format!("{}: {}", path.display(), err));
+            return;
+        }
+        let mut manifest = manifest.lock().unwrap();
+        manifest.finished.insert(path.display().to_string(), output.display().to_string());
+        if let Err(err) = manifest.save(&manifest_path) {
+            log::warn!("{}: {}", manifest_path.display(), err);
+        }
+        eprintln!("saved {} ({} of {} frames)", output.display(), manifest.finished.len(), args.frames.len());
+    };
+    let start = Stopwatch::start();
+    let farm = Farm { attempts: args.attempts.max(1), timeout: Duration::from_secs(args.timeout.max(1)) };
+    let not_rendered = farm.render(&listener, &jobs, stop, save).map_err(invalid)?;
+    log::info!("rendered {} frames in {:.2?}", jobs.len() - not_rendered.len(), start.elapsed());
+    for mut worker in workers.into_inner().unwrap() {
+        let _ = worker.kill(); // Workers still running when the farm stops are cut off mid-frame
+        let _ = worker.wait();
+    }
+    
+    let mut failed = failed.into_inner().unwrap();
+    failed.extend(not_rendered.into_iter().map(|(frame, error)| format!("{}: {}", frames[frame].0.display(), error)));
+    for failure in &failed {
+        eprintln!("{}", failure);
+    }
+    match failed.len() {
+        0 => Ok(()),
+        n => Err(Error::Distributed(format!("{} of {} frames not finished; run again to retry them", n, frames.len()))),
+    }
+}
+
+/// Render bands for a coordinator or farm until it has no more
+fn run_worker(args: &WorkerArgs) -> Result<()> {
+    let stream = TcpStream::connect(&args.connect).map_err(|source| Error::Serve { address: args.connect.clone(), source })?;
+    let threads = match args.threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    Worker { threads }.run(stream).map_err(|err| Error::Distributed(err.to_string()))
+}
+
+/// Run the benchmark workloads and write a JSON report
+fn run_bench(args: &BenchArgs) -> Result<()> {
+    let workloads: Vec<Workload> = bench::workloads()
+        .into_iter()
+        .filter(|workload| args.workloads.is_empty() || args.workloads.iter().any(|name| name == workload.name))
+        .collect();
+    if let Some(unknown) = args.workloads.iter().find(|name| !workloads.iter().any(|w| w.name == name.as_str())) {
+        return Err(Error::InvalidArgument {
+            name: "--workload",
+            reason: format!("unknown workload '{}' (expected primitives, mesh, shadows or spheres)", unknown),
+        });
+    }
+    if args.width == 0 || args.height == 0 || args.samples == 0 {
+        return Err(Error::InvalidArgument { name: "--width/--height/--samples", reason: "must be non-zero".to_string() });
+    }
+    
+    if ![1, 2, 4].contains(&args.packet_size) {
+        return Err(Error::InvalidArgument { name: "--packet-size", reason: format!("must be 1, 2 or 4, got {}", args.packet_size) });
+    }
+    
+    let mut renderer = Renderer::new();
+    renderer.samples = args.samples;
+    renderer.packet_size = args.packet_size;
+    renderer.threads = match args.threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    
+    let mut report = BenchReport {
+        version: env!("CARGO_PKG_VERSION"),
+        width: args.width,
+        height: args.height,
+        samples: renderer.samples,
+        threads: renderer.threads,
+        accelerator: args.accel.to_string(),
+        workloads: Vec::new(),
+    };
+    let accelerator = AcceleratorOptions { kind: args.accel, ..AcceleratorOptions::default() };
+    for workload in &workloads {
+        log::info!("bench {}: {}", workload.name, workload.description);
+        report.workloads.push(bench::run(workload, &renderer, &accelerator, args.width, args.height, args.iterations));
+    }
+    
+    let json = serde_json::to_string_pretty(&report).expect("bench reports always serialize") + "\n";
+    match &args.output {
+        Some(path) => std::fs::write(path, json).map_err(|source| Error::Io { target: path.display().to_string(), source }),
+        None => io::stdout().write_all(json.as_bytes()).map_err(|source| Error::Io { target: "stdout".to_string(), source }),
+    }
+}
+
+/// Print how two images differ, optionally writing a heatmap of the differences
+fn run_diff(args: &DiffArgs) -> Result<ExitCode> {
+    if !(0.0..=1.0).contains(&args.threshold) {
+        return Err(Error::InvalidArgument { name: "--threshold", reason: format!("must be between 0 and 1, got {}", args.threshold) });
+    }
+    let heatmap_format = args.heatmap.as_deref().map(|path| output_format(Some(path), None)).transpose()?;
+    
+    let load = |path: &PathBuf| RgbaImage::load(path).map_err(|source| Error::Image { path: path.clone(), source });
+    let (a, b) = (load(&args.a)?, load(&args.b)?);
+    if (a.width, a.height) != (b.width, b.height) {
+        return Err(Error::SizeMismatch { a: (a.width, a.height), b: (b.width, b.height) });
+    }
+    
+    let report = diff::compare(&a, &b, args.threshold);
+    println!("{}", report);
+    
+    if let (Some(path), Some(format)) = (&args.heatmap, heatmap_format) {
+        let rgb = diff::heatmap(&a, &b);
+        let bytes = match format {
+            ImageFormat::Png => PngWriter::rgb(a.width, a.height, rgb).to_bytes(),
+            ImageFormat::Exr => {
+                let channel = |offset: usize| rgb.iter().skip(offset).step_by(3).map(|&byte| byte as f32 / 255.0).collect();
+                ExrWriter::new(a.width, a.height).channel("R", channel(0)).channel("G", channel(1)).channel("B", channel(2)).to_bytes()
+            }
+            ImageFormat::Ppm => {
+                let mut ppm = PpmWriter::new(a.width, a.height);
+                for pixel in rgb.chunks(3) {
+                    ppm.write_pixel(pixel[0], pixel[1], pixel[2]);
+                }
+                ppm.to_string().into_bytes()
+            }
+        };
+        std::fs::write(path, bytes).map_err(|source| Error::Io { target: path.clone(), source })?;
+    }
+    
+    Ok(if report.matches() { ExitCode::SUCCESS } else { ExitCode::from(4) })
+}
+
+/// Print self-test results; the exit status is non-zero if any check failed
+fn run_selftest() -> ExitCode {
+    let results = selftest::run();
+    let failed = results.iter().filter(|r| !r.passed).count();
+    
+    for result in &results {
+        let status = if result.passed { "PASS" } else { "FAIL" };
+        match &result.detail {
+            Some(detail) => println!("{}  {} ({})", status, result.name, detail),
+            None => println!("{}  {}", status, result.name),
+        }
+    }
+    
+    println!();
+    println!("{} passed, {} failed", results.len() - failed, failed);
+    if failed == 0 { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, RemoveKind};
+    
+    fn event(kind: EventKind, paths: &[&str]) -> notify::Event {
+        paths.iter().fold(notify::Event::new(kind), |event, path| event.add_path(PathBuf::from(path)))
+    }
+    
+    #[test]
+    fn test_watch_saves() {
+        let scene = Path::new("scenes/room.json");
+        assert!(saves(&event(EventKind::Modify(ModifyKind::Data(DataChange::Any)), &["scenes/room.json"]), scene));
+        assert!(saves(&event(EventKind::Create(CreateKind::File), &["scenes/room.json"]), scene));
+        
+        // Editors that save through a temporary file and rename it over the scene
+        let rename = EventKind::Modify(ModifyKind::Name(RenameMode::Both));
+        assert!(saves(&event(rename, &["scenes/.room.json.swp", "scenes/room.json"]), scene));
+        assert!(saves(&event(EventKind::Modify(ModifyKind::Name(RenameMode::To)), &["scenes/room.json"]), scene));
+        assert!(!saves(&event(rename, &["scenes/room.json", "scenes/room.json~"]), scene));
+        assert!(!saves(&event(EventKind::Modify(ModifyKind::Name(RenameMode::From)), &["scenes/room.json"]), scene));
+        assert!(!saves(&event(EventKind::Remove(RemoveKind::File), &["scenes/room.json"]), scene));
+        
+        // Other files in the watched directory
+        assert!(!saves(&event(EventKind::Modify(ModifyKind::Data(DataChange::Any)), &["scenes/hall.json"]), scene));
+        assert!(!saves(&event(rename, &["scenes/room.json.tmp", "scenes/hall.json"]), scene));
+    }
+}
@@ -1,68 +1,285 @@
-use crate::math::Vec3;
+use std::sync::Arc;
+
+use crate::math::{Float, Vec3};
+use crate::color::Color;
+use crate::texture::Texture;
 
 /// Material properties for shading
 #[derive(Debug, Clone)]
 pub struct Material {
-    pub albedo: Vec3,      // Base color (diffuse reflectance)
-    pub specular: f64,     // Specular reflection coefficient
-    pub shininess: f64,    // Phong shininess exponent
-    pub reflectivity: f64, // Mirror reflection coefficient (0.0 = no reflection, 1.0 = perfect mirror)
+    pub albedo: Color,     // Base color (diffuse reflectance)
+    pub specular: Float,     // Specular reflection coefficient
+    pub shininess: Float,    // Phong shininess exponent
+    pub reflectivity: Float, // Mirror reflection coefficient (0.0 = no reflection, 1.0 = perfect mirror)
+    pub mean_free_path: Vec3, // Per-channel subsurface scattering distance (zero = opaque)
+    pub transmission: Vec3,   // Per-channel fraction of light passing through the surface (zero = opaque)
+    pub shadow_catcher: bool, // Shows the background, only darkened where shadows fall (for compositing)
+    pub two_sided: bool,      // Seen from behind too; rays pass through the back of single-sided surfaces
+    pub texture: Option<Arc<Texture>>, // Image multiplying the albedo, looked up by the hit's texture coordinates
+    pub opacity: Option<Arc<Texture>>, // Cutout map: the surface is missing where its alpha is below `alpha_cutoff`
+    pub alpha_cutoff: Float,           // Opacity at which cutout maps switch from holes to surface
+    pub emission: Color,                        // Light the surface gives off itself (radiance), black for most
+    pub emission_texture: Option<Arc<Texture>>, // Image multiplying the emission (screens, signs)
+    pub bump: Option<Arc<Texture>>,             // Height map whose slopes tilt the shading normal
+    pub bump_height: Float,                     // World-space height of white above black in the bump map
+    pub anisotropy: Float,          // Highlight stretch along the tangent (positive) or across it (negative), -1 to 1
+    pub anisotropy_rotation: Float, // Degrees the direction of stretch turns about the normal from the tangent
+    pub clearcoat: Float,           // Weight of a glossy lacquer layer over the rest (0 for none, up to 1)
+    pub clearcoat_roughness: Float, // Width of the lacquer's highlight (GGX roughness), small for a polished coat
+    pub ior: Vec3, // Per-channel index of refraction bending transmitted light (zero passes it straight through)
+    pub absorption: Color,         // Tint light inside a refractive material takes on after 1/`absorption_density` units
+    pub absorption_density: Float, // How fast light inside takes on the absorption tint, per unit distance (0 = clear)
+    pub name: Option<Arc<str>>,    // Name it was defined under in a scene file or model, by which instances replace it
 }
 
 impl Material {
     /// Create a new material with diffuse properties
-    pub fn new(albedo: Vec3) -> Self {
+    pub fn new(albedo: Color) -> Self {
         Self {
             albedo,
             specular: 0.0,
             shininess: 1.0,
             reflectivity: 0.0,
+            mean_free_path: Vec3::zero(),
+            transmission: Vec3::zero(),
+            shadow_catcher: false,
+            two_sided: true,
+            texture: None,
+            opacity: None,
+            alpha_cutoff: 0.5,
+            emission: Color::black(),
+            emission_texture: None,
+            bump: None,
+            bump_height: 0.02,
+            anisotropy: 0.0,
+            anisotropy_rotation: 0.0,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.03,
+            ior: Vec3::zero(),
+            absorption: Color::white(),
+            absorption_density: 0.0,
+            name: None,
         }
     }
     
     /// Create a material with specular highlights (Phong shading)
-    pub fn with_specular(albedo: Vec3, specular: f64, shininess: f64) -> Self {
+    pub fn with_specular(albedo: Color, specular: Float, shininess: Float) -> Self {
         Self {
-            albedo,
             specular,
             shininess,
-            reflectivity: 0.0,
+            ..Self::new(albedo)
         }
     }
     
     /// Create a reflective material (mirror-like)
-    pub fn with_reflection(albedo: Vec3, reflectivity: f64) -> Self {
+    pub fn with_reflection(albedo: Color, reflectivity: Float) -> Self {
         Self {
-            albedo,
-            specular: 0.0,
-            shininess: 1.0,
             reflectivity,
+            ..Self::new(albedo)
         }
     }
     
+    /// Create a translucent material (wax, skin, marble) that lets light bleed through
+    /// - mean_free_path: how far light travels inside per color channel, in world units
+    pub fn translucent(albedo: Color, mean_free_path: Vec3) -> Self {
+        Self {
+            mean_free_path,
+            ..Self::new(albedo)
+        }
+    }
+    
+    /// Create a transparent material that filters light passing through it (tinted glass, colored film)
+    /// - transmission: fraction of each color channel let through, also used to tint shadows
+    pub fn transparent(albedo: Color, transmission: Vec3) -> Self {
+        Self {
+            transmission,
+            ..Self::new(albedo)
+        }
+    }
+    
+    /// Create clear glass or liquid that bends the light passing through it by index of refraction `ior`
+    /// (1.33 for water, about 1.5 for glass), reflecting more of it at grazing angles
+    pub fn dielectric(transmission: Vec3, ior: Float) -> Self {
+        Self {
+            transmission,
+            ior: Vec3::new(ior, ior, ior),
+            ..Self::new(Color::black())
+        }
+    }
+    
+    /// Create a glowing material that gives off `emission` (radiance) on top of reflecting light like `albedo`
+    /// Path tracing lights the scene with it wherever paths happen to hit it; ray tracing only shows the glow
+    pub fn emissive(albedo: Color, emission: Color) -> Self {
+        Self {
+            emission,
+            ..Self::new(albedo)
+        }
+    }
+    
+    /// Create a shadow catcher for compositing renders onto photos
+    /// The surface shows whatever is behind it, darkened only where objects shadow it
+    pub fn shadow_catcher() -> Self {
+        Self {
+            shadow_catcher: true,
+            ..Self::new(Color::white())
+        }
+    }
+    
+    /// The same material, but only seen from in front: rays (shadow rays too) pass through its back
+    /// Use it for open meshes whose insides shouldn't show, or walls that block light from one side only
+    pub fn single_sided(self) -> Self {
+        Self { two_sided: false, ..self }
+    }
+    
+    /// Whether any color channel scatters below the surface
+    pub fn is_translucent(&self) -> bool {
+        self.mean_free_path.x > 0.0 || self.mean_free_path.y > 0.0 || self.mean_free_path.z > 0.0
+    }
+    
+    /// Whether light passing through the surface bends
+    pub fn is_refractive(&self) -> bool {
+        self.ior != Vec3::zero()
+    }
+    
+    /// Whether the index of refraction differs between color channels, splitting white light into colors
+    pub fn is_dispersive(&self) -> bool {
+        self.is_refractive() && (self.ior.x != self.ior.y || self.ior.y != self.ior.z)
+    }
+    
+    /// Whether the surface gives off light of its own
+    pub fn is_emissive(&self) -> bool {
+        self.emission != Color::black()
+    }
+    
+    /// Whether any color channel passes through the surface
+    pub fn is_transparent(&self) -> bool {
+        self.transmission.x > 0.0 || self.transmission.y > 0.0 || self.transmission.z > 0.0
+    }
+    
     /// Predefined materials
     pub fn red() -> Self {
-        Self::new(Vec3::new(0.8, 0.2, 0.2))
+        Self::new(Color::new(0.8, 0.2, 0.2))
     }
     
     pub fn green() -> Self {
-        Self::new(Vec3::new(0.2, 0.8, 0.2))
+        Self::new(Color::new(0.2, 0.8, 0.2))
     }
     
     pub fn blue() -> Self {
-        Self::new(Vec3::new(0.2, 0.2, 0.8))
+        Self::new(Color::new(0.2, 0.2, 0.8))
     }
     
     pub fn white() -> Self {
-        Self::new(Vec3::new(0.8, 0.8, 0.8))
+        Self::new(Color::new(0.8, 0.8, 0.8))
     }
     
     pub fn gray() -> Self {
-        Self::new(Vec3::new(0.5, 0.5, 0.5))
+        Self::new(Color::new(0.5, 0.5, 0.5))
     }
     
     pub fn mirror() -> Self {
-        Self::with_reflection(Vec3::new(0.9, 0.9, 0.9), 0.9)
+        Self::with_reflection(Color::new(0.9, 0.9, 0.9), 0.9)
+    }
+    
+    pub fn wax() -> Self {
+        Self::translucent(Color::new(0.9, 0.85, 0.7), Vec3::new(0.8, 0.5, 0.3))
+    }
+    
+    pub fn glass() -> Self {
+        Self {
+            reflectivity: 0.1,
+            transmission: Vec3::new(0.9, 0.9, 0.9),
+            ..Self::new(Color::new(0.05, 0.05, 0.05))
+        }
+    }
+    
+    /// Dense glass that spreads colors apart (red bends least, blue most), for prisms and gemstones
+    pub fn flint_glass() -> Self {
+        Self {
+            ior: Vec3::new(1.61, 1.62, 1.65),
+            ..Self::dielectric(Vec3::new(1.0, 1.0, 1.0), 1.62)
+        }
+    }
+    
+    /// Green gemstone, deeper in color where light travels further through it
+    pub fn emerald() -> Self {
+        Self {
+            absorption: Color::new(0.2, 0.75, 0.35),
+            absorption_density: 2.0,
+            ..Self::dielectric(Vec3::new(1.0, 1.0, 1.0), 1.58)
+        }
+    }
+    
+    pub fn gold() -> Self {
+        Self {
+            specular: 0.8,
+            shininess: 64.0,
+            reflectivity: 0.6,
+            ..Self::new(Color::new(1.0, 0.78, 0.34))
+        }
+    }
+    
+    pub fn chrome() -> Self {
+        Self {
+            specular: 1.0,
+            shininess: 128.0,
+            reflectivity: 0.8,
+            ..Self::new(Color::new(0.55, 0.55, 0.55))
+        }
+    }
+    
+    /// Brushed metal: highlights drawn out along the surface's tangent (path tracing only)
+    pub fn brushed_aluminum() -> Self {
+        Self {
+            specular: 0.9,
+            shininess: 200.0,
+            anisotropy: 0.8,
+            ..Self::new(Color::new(0.25, 0.25, 0.26))
+        }
+    }
+    
+    /// Glossy red paint under a polished clearcoat
+    pub fn car_paint() -> Self {
+        Self {
+            specular: 0.2,
+            shininess: 16.0,
+            clearcoat: 1.0,
+            ..Self::new(Color::new(0.5, 0.03, 0.03))
+        }
+    }
+    
+    /// Matte light gray used for clay (look-development) renders
+    pub fn clay() -> Self {
+        Self::new(Color::new(0.7, 0.7, 0.7))
+    }
+    
+    pub fn rubber() -> Self {
+        Self::with_specular(Color::new(0.05, 0.05, 0.05), 0.1, 8.0)
+    }
+    
+    /// Look up a built-in material by name (as used in scene files)
+    pub fn from_library(name: &str) -> Option<Self> {
+        let material = match name {
+            "red" => Self::red(),
+            "green" => Self::green(),
+            "blue" => Self::blue(),
+            "white" => Self::white(),
+            "gray" => Self::gray(),
+            "mirror" => Self::mirror(),
+            "wax" => Self::wax(),
+            "glass" => Self::glass(),
+            "flint_glass" => Self::flint_glass(),
+            "emerald" => Self::emerald(),
+            "gold" => Self::gold(),
+            "chrome" => Self::chrome(),
+            "rubber" => Self::rubber(),
+            "brushed_aluminum" => Self::brushed_aluminum(),
+            "car_paint" => Self::car_paint(),
+            "clay" => Self::clay(),
+            "shadow_catcher" => Self::shadow_catcher(),
+            _ => return None,
+        };
+        Some(material)
     }
 }
 
@@ -73,10 +290,40 @@ mod tests {
     #[test]
     fn test_material_creation() {
         let mat = Material::red();
-        assert_eq!(mat.albedo, Vec3::new(0.8, 0.2, 0.2));
+        assert_eq!(mat.albedo, Color::new(0.8, 0.2, 0.2));
         assert_eq!(mat.reflectivity, 0.0);
         
         let mirror = Material::mirror();
         assert_eq!(mirror.reflectivity, 0.9);
     }
+
+    #[test]
+    fn test_translucent_material() {
+        assert!(Material::wax().is_translucent());
+        assert!(!Material::mirror().is_translucent());
+    }
+
+    #[test]
+    fn test_transparent_material() {
+        assert!(Material::transparent(Color::black(), Vec3::new(0.0, 0.5, 0.0)).is_transparent());
+        assert!(!Material::wax().is_transparent());
+    }
+
+    #[test]
+    fn test_shadow_catcher_material() {
+        assert!(Material::shadow_catcher().shadow_catcher);
+        assert!(!Material::mirror().shadow_catcher);
+    }
+
+    #[test]
+    fn test_material_library() {
+        assert_eq!(Material::from_library("gold").unwrap().reflectivity, 0.6);
+        assert!(Material::from_library("unobtainium").is_none());
+    }
+
+    #[test]
+    fn test_single_sided_material() {
+        assert!(Material::red().two_sided);
+        assert!(!Material::red().single_sided().two_sided);
+    }
 }
@@ -1,16 +1,29 @@
-use std::ops::{Add, Sub, Mul, Div, Neg};
+use std::ops::{Add, AddAssign, Sub, SubAssign, Mul, MulAssign, Div, DivAssign, Neg, Index, IndexMut};
+
+/// Scalar type of all vector, ray and color math: `f64`, or `f32` with the `f32` feature
+/// (half the memory for framebuffers and meshes, at the cost of precision)
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+#[cfg(feature = "f32")]
+pub type Float = f32;
+
+/// Mathematical constants (PI, ...) for `Float`
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
 
 /// 3D vector for positions, directions, and colors
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Vec3 {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
 }
 
 impl Vec3 {
     /// Create a new Vec3
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub fn new(x: Float, y: Float, z: Float) -> Self {
         Self { x, y, z }
     }
     
@@ -35,7 +48,7 @@ impl Vec3 {
     }
     
     /// Dot product
-    pub fn dot(&self, other: &Vec3) -> f64 {
+    pub fn dot(&self, other: &Vec3) -> Float {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
     
@@ -49,12 +62,12 @@ impl Vec3 {
     }
     
     /// Length (magnitude) of the vector
-    pub fn length(&self) -> f64 {
+    pub fn length(&self) -> Float {
         (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
     }
     
     /// Squared length (avoids sqrt for performance)
-    pub fn length_squared(&self) -> f64 {
+    pub fn length_squared(&self) -> Float {
         self.x * self.x + self.y * self.y + self.z * self.z
     }
     
@@ -72,6 +85,40 @@ impl Vec3 {
     pub fn reflect(&self, normal: &Vec3) -> Vec3 {
         *self - *normal * 2.0 * self.dot(normal)
     }
+    
+    /// Component-wise minimum
+    pub fn min(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+    }
+    
+    /// Component-wise maximum
+    pub fn max(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+    }
+    
+    /// Each component clamped to [min, max]
+    pub fn clamp(&self, min: Float, max: Float) -> Vec3 {
+        Vec3::new(self.x.clamp(min, max), self.y.clamp(min, max), self.z.clamp(min, max))
+    }
+    
+    /// Largest of the three components (the brightest channel of a color)
+    pub fn max_component(&self) -> Float {
+        self.x.max(self.y).max(self.z)
+    }
+    
+    /// Linear interpolation from `self` (t = 0) to `other` (t = 1)
+    pub fn lerp(&self, other: &Vec3, t: Float) -> Vec3 {
+        *self * (1.0 - t) + *other * t
+    }
+    
+    pub fn abs(&self) -> Vec3 {
+        Vec3::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+    
+    /// Whether every component is within 1e-8 of zero (degenerate directions, black colors)
+    pub fn near_zero(&self) -> bool {
+        self.x.abs() < 1e-8 && self.y.abs() < 1e-8 && self.z.abs() < 1e-8
+    }
 }
 
 // Operator implementations
@@ -91,15 +138,24 @@ impl Sub for Vec3 {
     }
 }
 
-impl Mul<f64> for Vec3 {
+impl Mul<Float> for Vec3 {
     type Output = Vec3;
     
-    fn mul(self, scalar: f64) -> Vec3 {
+    fn mul(self, scalar: Float) -> Vec3 {
         Vec3::new(self.x * scalar, self.y * scalar, self.z * scalar)
     }
 }
 
-impl Mul<Vec3> for f64 {
+/// Component-wise product, e.g. a color filtered by a per-channel transmission
+impl Mul for Vec3 {
+    type Output = Vec3;
+    
+    fn mul(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x * other.x, self.y * other.y, self.z * other.z)
+    }
+}
+
+impl Mul<Vec3> for Float {
     type Output = Vec3;
     
     fn mul(self, vec: Vec3) -> Vec3 {
@@ -107,10 +163,10 @@ impl Mul<Vec3> for f64 {
     }
 }
 
-impl Div<f64> for Vec3 {
+impl Div<Float> for Vec3 {
     type Output = Vec3;
     
-    fn div(self, scalar: f64) -> Vec3 {
+    fn div(self, scalar: Float) -> Vec3 {
         Vec3::new(self.x / scalar, self.y / scalar, self.z / scalar)
     }
 }
@@ -123,6 +179,61 @@ impl Neg for Vec3 {
     }
 }
 
+impl AddAssign for Vec3 {
+    fn add_assign(&mut self, other: Vec3) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Vec3 {
+    fn sub_assign(&mut self, other: Vec3) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign<Float> for Vec3 {
+    fn mul_assign(&mut self, scalar: Float) {
+        *self = *self * scalar;
+    }
+}
+
+impl MulAssign for Vec3 {
+    fn mul_assign(&mut self, other: Vec3) {
+        *self = *self * other;
+    }
+}
+
+impl DivAssign<Float> for Vec3 {
+    fn div_assign(&mut self, scalar: Float) {
+        *self = *self / scalar;
+    }
+}
+
+/// Component by axis: 0 = x, 1 = y, 2 = z
+impl Index<usize> for Vec3 {
+    type Output = Float;
+    
+    fn index(&self, axis: usize) -> &Float {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 axis {} out of range", axis),
+        }
+    }
+}
+
+impl IndexMut<usize> for Vec3 {
+    fn index_mut(&mut self, axis: usize) -> &mut Float {
+        match axis {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("Vec3 axis {} out of range", axis),
+        }
+    }
+}
+
 /// Ray with origin and direction
 #[derive(Debug, Clone)]
 pub struct Ray {
@@ -137,11 +248,542 @@ impl Ray {
     }
     
     /// Get point along ray at parameter t
-    pub fn at(&self, t: f64) -> Vec3 {
+    pub fn at(&self, t: Float) -> Vec3 {
         self.origin + self.direction * t
     }
 }
 
+/// Range of distances along a ray, from min to max
+/// Intersection routines take the range a hit must fall in, rather than each comparing against its own epsilon
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub min: Float,
+    pub max: Float,
+}
+
+impl Interval {
+    /// Every distance in front of a ray's origin: `hits` with no limit
+    pub const FORWARD: Self = Self { min: 0.0, max: Float::INFINITY };
+    
+    pub fn new(min: Float, max: Float) -> Self {
+        Self { min, max }
+    }
+    
+    /// Distances a ray can hit something at: in front of its origin and before t_max
+    /// Rays leaving a surface start just off it (`HitInfo::offset_origin`) rather than skipping hits near their origin
+    pub fn hits(t_max: Float) -> Self {
+        Self::new(0.0, t_max)
+    }
+    
+    /// True if no distance lies in the range (min > max, or either is NaN)
+    pub fn is_empty(&self) -> bool {
+        self.min.partial_cmp(&self.max).is_none_or(|order| order.is_gt())
+    }
+    
+    /// min <= t <= max
+    pub fn contains(&self, t: Float) -> bool {
+        self.min <= t && t <= self.max
+    }
+    
+    /// min < t < max: hits exactly at either end don't count
+    pub fn surrounds(&self, t: Float) -> bool {
+        self.min < t && t < self.max
+    }
+    
+    /// Nearest distance in the range (unlike `Float::clamp`, doesn't panic when it's empty)
+    pub fn clamp(&self, t: Float) -> Float {
+        t.max(self.min).min(self.max)
+    }
+}
+
+/// Unit quaternion w + xi + yj + zk representing a rotation
+/// Unlike Euler angles, rotations compose and interpolate smoothly without gimbal lock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: Float,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Quaternion {
+    /// Create a quaternion from its components
+    pub fn new(w: Float, x: Float, y: Float, z: Float) -> Self {
+        Self { w, x, y, z }
+    }
+    
+    /// The rotation that leaves everything where it is
+    pub fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 0.0)
+    }
+    
+    /// Rotation by `angle` radians about `axis` (counterclockwise looking down the axis)
+    pub fn from_axis_angle(axis: Vec3, angle: Float) -> Self {
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        let axis = axis.normalize() * sin;
+        Self::new(cos, axis.x, axis.y, axis.z)
+    }
+    
+    /// Rotation about X, then Y, then Z by the angles (radians) in `angles`
+    pub fn from_euler(angles: Vec3) -> Self {
+        Self::from_axis_angle(Vec3::unit_z(), angles.z)
+            * Self::from_axis_angle(Vec3::unit_y(), angles.y)
+            * Self::from_axis_angle(Vec3::unit_x(), angles.x)
+    }
+    
+    /// The rotation a rotation matrix (orthonormal, determinant 1) performs
+    pub fn from_matrix(matrix: &Mat3) -> Self {
+        // From the largest of the four components, which the trace or a diagonal entry gives most accurately
+        let [[m00, m01, m02], [m10, m11, m12], [m20, m21, m22]] = matrix.rows;
+        let trace = m00 + m11 + m22;
+        let quaternion = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self::new(s / 4.0, (m21 - m12) / s, (m02 - m20) / s, (m10 - m01) / s)
+        } else if m00 > m11 && m00 > m22 {
+            let s = (1.0 + m00 - m11 - m22).sqrt() * 2.0;
+            Self::new((m21 - m12) / s, s / 4.0, (m01 + m10) / s, (m02 + m20) / s)
+        } else if m11 > m22 {
+            let s = (1.0 + m11 - m00 - m22).sqrt() * 2.0;
+            Self::new((m02 - m20) / s, (m01 + m10) / s, s / 4.0, (m12 + m21) / s)
+        } else {
+            let s = (1.0 + m22 - m00 - m11).sqrt() * 2.0;
+            Self::new((m10 - m01) / s, (m02 + m20) / s, (m12 + m21) / s, s / 4.0)
+        };
+        quaternion.normalize()
+    }
+    
+    /// The opposite rotation
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.w, -self.x, -self.y, -self.z)
+    }
+    
+    pub fn dot(&self, other: &Quaternion) -> Float {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+    
+    /// Scale to unit length, undoing rounding drift after many products
+    pub fn normalize(&self) -> Self {
+        let len = self.dot(self).sqrt();
+        Self::new(self.w / len, self.x / len, self.y / len, self.z / len)
+    }
+    
+    /// Rotate a vector
+    pub fn rotate(&self, v: Vec3) -> Vec3 {
+        // v + 2w(u × v) + 2u × (u × v), with u the vector part
+        let u = Vec3::new(self.x, self.y, self.z);
+        let t = u.cross(&v) * 2.0;
+        v + t * self.w + u.cross(&t)
+    }
+    
+    /// Spherical linear interpolation: turns at a constant rate from `self` (t = 0) to `other` (t = 1)
+    /// along the shorter way round
+    pub fn slerp(&self, other: &Quaternion, t: Float) -> Self {
+        let mut cos = self.dot(other);
+        let mut other = *other;
+        if cos < 0.0 {
+            // q and -q are the same rotation; the one nearer `self` is the short way
+            cos = -cos;
+            other = Self::new(-other.w, -other.x, -other.y, -other.z);
+        }
+        
+        // Nearly equal rotations: interpolate linearly to avoid dividing by sin(θ) ≈ 0
+        let (a, b) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        Self::new(
+            a * self.w + b * other.w,
+            a * self.x + b * other.x,
+            a * self.y + b * other.y,
+            a * self.z + b * other.z,
+        )
+        .normalize()
+    }
+}
+
+/// Hamilton product: `a * b` rotates by `b`, then by `a`
+impl Mul for Quaternion {
+    type Output = Quaternion;
+    
+    fn mul(self, other: Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        )
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// Orthonormal basis (u, v, w) with w along a given normal, for turning directions sampled
+/// around the z axis (local space) into world space and back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// Basis around a unit-length normal; a zero or non-finite normal gives the world axes
+    pub fn from_normal(normal: Vec3) -> Self {
+        if !(normal.length_squared() > 0.0 && normal.length_squared().is_finite()) {
+            return Self { u: Vec3::unit_x(), v: Vec3::unit_y(), w: Vec3::unit_z() };
+        }
+        // Duff et al., "Building an Orthonormal Basis, Revisited": no branches on the axis, no degenerate cases
+        let sign = if normal.z >= 0.0 { 1.0 } else { -1.0 };
+        let a = -1.0 / (sign + normal.z);
+        let b = normal.x * normal.y * a;
+        Self {
+            u: Vec3::new(1.0 + sign * normal.x * normal.x * a, sign * b, -sign * normal.x),
+            v: Vec3::new(b, sign + normal.y * normal.y * a, -normal.y),
+            w: normal,
+        }
+    }
+    
+    /// Basis around a unit-length normal with u along `tangent` (made perpendicular to the normal), or
+    /// `from_normal`'s if the tangent is zero or along the normal
+    pub fn from_normal_tangent(normal: Vec3, tangent: Vec3) -> Self {
+        let u = tangent - normal * normal.dot(&tangent);
+        if u.length_squared() < 1e-12 {
+            return Self::from_normal(normal);
+        }
+        let u = u.normalize();
+        Self { u, v: normal.cross(&u), w: normal }
+    }
+    
+    /// World-space direction of local coordinates (x along u, y along v, z along the normal)
+    pub fn to_world(&self, local: Vec3) -> Vec3 {
+        self.u * local.x + self.v * local.y + self.w * local.z
+    }
+    
+    /// Local coordinates of a world-space direction
+    pub fn to_local(&self, world: Vec3) -> Vec3 {
+        Vec3::new(world.dot(&self.u), world.dot(&self.v), world.dot(&self.w))
+    }
+}
+
+/// 3×3 matrix of rows, for linear maps such as the normal matrix of a transform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat3 {
+    pub rows: [[Float; 3]; 3],
+}
+
+impl Mat3 {
+    pub fn identity() -> Self {
+        Self { rows: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]] }
+    }
+    
+    /// Scale along each axis
+    pub fn from_scale(scale: Vec3) -> Self {
+        Self { rows: [[scale.x, 0.0, 0.0], [0.0, scale.y, 0.0], [0.0, 0.0, scale.z]] }
+    }
+    
+    /// The rotation of a unit quaternion
+    pub fn from_rotation(rotation: Quaternion) -> Self {
+        // Columns are the rotated axes
+        let [x, y, z] = [Vec3::unit_x(), Vec3::unit_y(), Vec3::unit_z()].map(|axis| rotation.rotate(axis));
+        Self { rows: [[x.x, y.x, z.x], [x.y, y.y, z.y], [x.z, y.z, z.z]] }
+    }
+    
+    pub fn transpose(&self) -> Self {
+        Self { rows: transpose(self.rows) }
+    }
+    
+    pub fn determinant(&self) -> Float {
+        let [a, b, c] = self.rows.map(|row| Vec3::new(row[0], row[1], row[2]));
+        a.dot(&b.cross(&c))
+    }
+    
+    /// Inverse matrix, or None if it is singular
+    pub fn inverse(&self) -> Option<Self> {
+        invert(self.rows).map(|rows| Self { rows })
+    }
+}
+
+impl Mul for Mat3 {
+    type Output = Mat3;
+    
+    fn mul(self, other: Mat3) -> Mat3 {
+        Mat3 { rows: multiply(&self.rows, &other.rows) }
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+    
+    fn mul(self, v: Vec3) -> Vec3 {
+        let [x, y, z] = self.rows.map(|row| row[0] * v.x + row[1] * v.y + row[2] * v.z);
+        Vec3::new(x, y, z)
+    }
+}
+
+/// 4×4 matrix of rows for affine and projective transforms, acting on column vectors
+/// (`a * b` applies `b` first)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub rows: [[Float; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn identity() -> Self {
+        Self::from_linear(Mat3::identity(), Vec3::zero())
+    }
+    
+    /// The affine map x ↦ linear · x + translation
+    pub fn from_linear(linear: Mat3, translation: Vec3) -> Self {
+        let [a, b, c] = linear.rows;
+        Self {
+            rows: [
+                [a[0], a[1], a[2], translation.x],
+                [b[0], b[1], b[2], translation.y],
+                [c[0], c[1], c[2], translation.z],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+    
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self::from_linear(Mat3::identity(), translation)
+    }
+    
+    pub fn from_scale(scale: Vec3) -> Self {
+        Self::from_linear(Mat3::from_scale(scale), Vec3::zero())
+    }
+    
+    pub fn from_rotation(rotation: Quaternion) -> Self {
+        Self::from_linear(Mat3::from_rotation(rotation), Vec3::zero())
+    }
+    
+    /// View matrix from world to camera space for a camera at `eye` looking toward `target`,
+    /// with the camera looking down its -z axis and `up` roughly along +y
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        // Same basis as `Camera::new`
+        let w = (eye - target).normalize();
+        let u = up.cross(&w).normalize();
+        let v = w.cross(&u);
+        let rows = Mat3 { rows: [[u.x, u.y, u.z], [v.x, v.y, v.z], [w.x, w.y, w.z]] };
+        Self::from_linear(rows, -(rows * eye))
+    }
+    
+    /// Projection from camera space to clip space for a vertical field of view in degrees
+    /// Points between the `near` and `far` planes end up with depths from -1 to 1 after the perspective divide
+    pub fn perspective(fov: Float, aspect_ratio: Float, near: Float, far: Float) -> Self {
+        let f = 1.0 / (fov.to_radians() / 2.0).tan();
+        Self {
+            rows: [
+                [f / aspect_ratio, 0.0, 0.0, 0.0],
+                [0.0, f, 0.0, 0.0],
+                [0.0, 0.0, (far + near) / (near - far), 2.0 * far * near / (near - far)],
+                [0.0, 0.0, -1.0, 0.0],
+            ],
+        }
+    }
+    
+    pub fn transpose(&self) -> Self {
+        Self { rows: transpose(self.rows) }
+    }
+    
+    /// Inverse matrix, or None if it is singular
+    pub fn inverse(&self) -> Option<Self> {
+        invert(self.rows).map(|rows| Self { rows })
+    }
+    
+    /// The upper-left 3×3 block: the linear part of an affine map
+    pub fn linear(&self) -> Mat3 {
+        let [a, b, c, _] = self.rows;
+        Mat3 { rows: [[a[0], a[1], a[2]], [b[0], b[1], b[2]], [c[0], c[1], c[2]]] }
+    }
+    
+    /// Matrix that takes normals to the transformed surface: the inverse transpose of the linear part
+    /// Normals come out perpendicular to the surface even under non-uniform scale, but not unit length
+    pub fn normal_matrix(&self) -> Option<Mat3> {
+        self.linear().inverse().map(|inverse| inverse.transpose())
+    }
+    
+    /// Transform a point, dividing by w for projections
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let [x, y, z, w] = self.rows.map(|row| row[0] * p.x + row[1] * p.y + row[2] * p.z + row[3]);
+        if w == 1.0 { Vec3::new(x, y, z) } else { Vec3::new(x, y, z) / w }
+    }
+    
+    /// Transform a direction or offset, which translation doesn't move
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        self.linear() * v
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+    
+    fn mul(self, other: Mat4) -> Mat4 {
+        Mat4 { rows: multiply(&self.rows, &other.rows) }
+    }
+}
+
+fn transpose<const N: usize>(rows: [[Float; N]; N]) -> [[Float; N]; N] {
+    std::array::from_fn(|i| std::array::from_fn(|j| rows[j][i]))
+}
+
+fn multiply<const N: usize>(a: &[[Float; N]; N], b: &[[Float; N]; N]) -> [[Float; N]; N] {
+    std::array::from_fn(|i| std::array::from_fn(|j| (0..N).map(|k| a[i][k] * b[k][j]).sum()))
+}
+
+/// Gauss-Jordan elimination with partial pivoting
+fn invert<const N: usize>(mut rows: [[Float; N]; N]) -> Option<[[Float; N]; N]> {
+    let mut inverse: [[Float; N]; N] = std::array::from_fn(|i| std::array::from_fn(|j| if i == j { 1.0 } else { 0.0 }));
+    for column in 0..N {
+        // The largest remaining entry in the column keeps rounding errors small
+        let pivot = (column..N).max_by(|&a, &b| rows[a][column].abs().total_cmp(&rows[b][column].abs()))?;
+        if rows[pivot][column] == 0.0 {
+            return None;
+        }
+        rows.swap(column, pivot);
+        inverse.swap(column, pivot);
+        
+        let scale = 1.0 / rows[column][column];
+        for j in 0..N {
+            rows[column][j] *= scale;
+            inverse[column][j] *= scale;
+        }
+        for i in (0..N).filter(|&i| i != column) {
+            let factor = rows[i][column];
+            for j in 0..N {
+                rows[i][j] -= factor * rows[column][j];
+                inverse[i][j] -= factor * inverse[column][j];
+            }
+        }
+    }
+    Some(inverse)
+}
+
+/// Axis-aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+    
+    /// Box containing nothing, the starting point for `union`
+    pub fn empty() -> Self {
+        Self::new(Vec3::new(Float::INFINITY, Float::INFINITY, Float::INFINITY), -Vec3::new(Float::INFINITY, Float::INFINITY, Float::INFINITY))
+    }
+    
+    /// Smallest box containing both boxes
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            Vec3::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y), self.min.z.min(other.min.z)),
+            Vec3::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y), self.max.z.max(other.max.z)),
+        )
+    }
+    
+    /// Smallest box containing this box and a point
+    pub fn grow(&self, point: Vec3) -> Aabb {
+        self.union(&Aabb::new(point, point))
+    }
+    
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+    
+    /// Bounds of the box once transformed by an affine matrix (of all eight transformed corners)
+    pub fn transformed(&self, matrix: &Mat4) -> Aabb {
+        (0..8).fold(Aabb::empty(), |bounds, corner| {
+            let pick = |bit: usize, min: Float, max: Float| if corner & bit == 0 { min } else { max };
+            let point = Vec3::new(pick(1, self.min.x, self.max.x), pick(2, self.min.y, self.max.y), pick(4, self.min.z, self.max.z));
+            bounds.grow(matrix.transform_point(point))
+        })
+    }
+    
+    /// Distance at which the ray enters the box (range.min if it starts inside), if that is in the range
+    /// `inverse_direction` is 1 / ray.direction per component, computed once per ray
+    pub fn hit(&self, ray: &Ray, inverse_direction: Vec3, range: Interval) -> Option<Float> {
+        self.hit_range(ray, inverse_direction, range).map(|inside| inside.min)
+    }
+    
+    /// Distances at which the ray enters and leaves the box, clipped to the range
+    pub fn hit_range(&self, ray: &Ray, inverse_direction: Vec3, range: Interval) -> Option<Interval> {
+        let (near, far) = self.slabs(ray, inverse_direction);
+        let inside = Interval::new(near.max(range.min), far.min(range.max));
+        (!inside.is_empty()).then_some(inside)
+    }
+    
+    /// Distances at which the ray's line enters and leaves the box, unclipped (near > far for a miss)
+    /// A ray parallel to a slab is inside it for its whole length if it lies within or on the slab, and never otherwise
+    pub fn slabs(&self, ray: &Ray, inverse_direction: Vec3) -> (Float, Float) {
+        let slab = |origin: Float, inverse: Float, min: Float, max: Float| {
+            let (t1, t2) = ((min - origin) * inverse, (max - origin) * inverse);
+            if t1.is_nan() || t2.is_nan() {
+                return (Float::NEG_INFINITY, Float::INFINITY); // Parallel to the slab and lying on its face
+            }
+            (t1.min(t2), t1.max(t2))
+        };
+        let (x_near, x_far) = slab(ray.origin.x, inverse_direction.x, self.min.x, self.max.x);
+        let (y_near, y_far) = slab(ray.origin.y, inverse_direction.y, self.min.y, self.max.y);
+        let (z_near, z_far) = slab(ray.origin.z, inverse_direction.z, self.min.z, self.max.z);
+        (x_near.max(y_near).max(z_near), x_far.min(y_far).min(z_far))
+    }
+    
+    /// The same distances as `slabs`, each with the outward normal of the face the ray's line enters or
+    /// leaves through; the line then runs along the slab it enters last and the one it leaves first
+    pub fn slab_faces(&self, ray: &Ray, inverse_direction: Vec3) -> ((Float, Vec3), (Float, Vec3)) {
+        let mut near = (Float::NEG_INFINITY, Vec3::zero());
+        let mut far = (Float::INFINITY, Vec3::zero());
+        for (axis, unit) in [Vec3::unit_x(), Vec3::unit_y(), Vec3::unit_z()].into_iter().enumerate() {
+            let inverse = inverse_direction[axis];
+            let (t1, t2) = ((self.min[axis] - ray.origin[axis]) * inverse, (self.max[axis] - ray.origin[axis]) * inverse);
+            if t1.is_nan() || t2.is_nan() {
+                continue; // Parallel to the slab and lying on its face
+            }
+            
+            // Heading toward +axis, the line crosses the min face first
+            let (entry, exit) = if t1 <= t2 { ((t1, -unit), (t2, unit)) } else { ((t2, unit), (t1, -unit)) };
+            if entry.0 > near.0 {
+                near = entry;
+            }
+            if exit.0 < far.0 {
+                far = exit;
+            }
+        }
+        (near, far)
+    }
+    
+    /// Total area of the six faces (0 for an empty box), proportional to the chance a random ray hits it
+    pub fn surface_area(&self) -> Float {
+        let size = self.max - self.min;
+        if size.x < 0.0 || size.y < 0.0 || size.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+    
+    /// Index of the longest axis (0 = x, 1 = y, 2 = z)
+    pub fn longest_axis(&self) -> usize {
+        let size = self.max - self.min;
+        if size.x >= size.y && size.x >= size.z {
+            0
+        } else if size.y >= size.z {
+            1
+        } else {
+            2
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -158,6 +800,22 @@ mod tests {
         
         let cross = v1.cross(&v2);
         assert_eq!(cross, Vec3::new(-3.0, 6.0, -3.0));
+        
+        assert_eq!(v1 * v2, Vec3::new(4.0, 10.0, 18.0));
+        assert_eq!(cross.min(&v1), Vec3::new(-3.0, 2.0, -3.0));
+        assert_eq!(cross.max(&v1), Vec3::new(1.0, 6.0, 3.0));
+        assert_eq!(cross.clamp(0.0, 5.0), Vec3::new(0.0, 5.0, 0.0));
+        assert_eq!(cross.abs().max_component(), 6.0);
+        assert_eq!(v1.lerp(&v2, 0.5), Vec3::new(2.5, 3.5, 4.5));
+        assert!(Vec3::new(1e-9, 0.0, -1e-9).near_zero() && !v1.near_zero());
+        
+        let mut v = v1;
+        v += v2;
+        v *= 2.0;
+        v -= v1;
+        v[2] /= 3.0;
+        assert_eq!(v, Vec3::new(9.0, 12.0, 5.0));
+        assert_eq!((v[0], v[1], v[2]), (9.0, 12.0, 5.0));
     }
     
     #[test]
@@ -168,6 +826,102 @@ mod tests {
         assert_eq!(normalized, Vec3::new(0.6, 0.8, 0.0));
     }
     
+    #[test]
+    fn test_quaternion() {
+        let close = |a: Vec3, b: Vec3| (a - b).length() < 1e-5;
+        let quarter = Quaternion::from_axis_angle(Vec3::unit_y(), consts::FRAC_PI_2);
+        assert!(close(quarter.rotate(Vec3::unit_x()), -Vec3::unit_z()));
+        assert!(close(quarter.conjugate().rotate(quarter.rotate(Vec3::new(1.0, 2.0, 3.0))), Vec3::new(1.0, 2.0, 3.0)));
+        
+        // X first, then Z: x stays put, then turns into y
+        let euler = Quaternion::from_euler(Vec3::new(consts::FRAC_PI_2, 0.0, consts::FRAC_PI_2));
+        assert!(close(euler.rotate(Vec3::unit_x()), Vec3::unit_y()));
+        
+        // Halfway from nothing to a quarter turn is an eighth turn
+        let half = Quaternion::identity().slerp(&quarter, 0.5);
+        let eighth = Quaternion::from_axis_angle(Vec3::unit_y(), consts::FRAC_PI_4);
+        assert!(close(half.rotate(Vec3::unit_x()), eighth.rotate(Vec3::unit_x())));
+        
+        // Back from matrices, including a half turn (zero trace path)
+        for rotation in [euler, Quaternion::from_axis_angle(Vec3::new(1.0, 1.0, 0.0), consts::PI)] {
+            let recovered = Quaternion::from_matrix(&Mat3::from_rotation(rotation));
+            assert!(close(recovered.rotate(Vec3::new(1.0, 2.0, 3.0)), rotation.rotate(Vec3::new(1.0, 2.0, 3.0))));
+        }
+    }
+    
+    #[test]
+    fn test_onb() {
+        // Including the normals where the construction flips sign, and degenerate ones
+        let normals = [Vec3::unit_z(), -Vec3::unit_z(), Vec3::new(0.0, -0.6, -0.8), Vec3::new(1.0, 2.0, -3.0).normalize(), Vec3::zero(), Vec3::new(Float::NAN, 0.0, 1.0)];
+        for normal in normals {
+            let onb = Onb::from_normal(normal);
+            for (a, b) in [(onb.u, onb.v), (onb.v, onb.w), (onb.w, onb.u)] {
+                assert!((a.length() - 1.0).abs() < 1e-5 && a.dot(&b).abs() < 1e-5, "{:?}", onb);
+            }
+            assert!((onb.u.cross(&onb.v) - onb.w).length() < 1e-5, "left-handed {:?}", onb);
+            
+            let local = Vec3::new(0.3, -0.4, 0.5);
+            assert!((onb.to_local(onb.to_world(local)) - local).length() < 1e-5);
+        }
+        assert_eq!(Onb::from_normal(Vec3::zero()).w, Vec3::unit_z());
+    }
+    
+    #[test]
+    fn test_matrices() {
+        let close = |a: Vec3, b: Vec3| (a - b).length() < 1e-4;
+        let rotation = Quaternion::from_euler(Vec3::new(0.3, -1.2, 0.7));
+        let m = Mat4::from_translation(Vec3::new(1.0, -2.0, 3.0)) * Mat4::from_rotation(rotation) * Mat4::from_scale(Vec3::new(2.0, 0.5, 3.0));
+        let p = Vec3::new(0.4, 1.5, -2.0);
+        assert!(close(m.inverse().unwrap().transform_point(m.transform_point(p)), p));
+        assert!(close(Mat3::from_rotation(rotation) * p, rotation.rotate(p)));
+        assert_eq!(Mat3::from_scale(Vec3::new(1.0, 0.0, 1.0)).inverse(), None);
+        
+        // Normals stay perpendicular to transformed tangents
+        let (tangent, normal) = (Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        assert!((m.normal_matrix().unwrap() * normal).dot(&m.transform_vector(tangent)).abs() < 1e-4);
+        
+        // The target lands straight ahead of the camera, and the near and far planes at depths -1 and 1
+        let view = Mat4::look_at(Vec3::new(0.0, 1.0, 5.0), Vec3::new(0.0, 1.0, -5.0), Vec3::unit_y());
+        assert!(close(view.transform_point(Vec3::new(0.0, 1.0, -5.0)), Vec3::new(0.0, 0.0, -10.0)));
+        let projection = Mat4::perspective(90.0, 2.0, 1.0, 10.0);
+        assert!(close(projection.transform_point(Vec3::new(2.0, 1.0, -1.0)), Vec3::new(1.0, 1.0, -1.0)));
+        assert!(close(projection.transform_point(Vec3::new(0.0, 0.0, -10.0)), Vec3::new(0.0, 0.0, 1.0)));
+    }
+    
+    #[test]
+    fn test_aabb() {
+        let bounds = Aabb::new(Vec3::zero(), Vec3::new(2.0, 1.0, 1.0)).union(&Aabb::empty().grow(Vec3::new(-1.0, 0.5, 0.5)));
+        assert_eq!(bounds, Aabb::new(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(2.0, 1.0, 1.0)));
+        assert_eq!(bounds.centroid(), Vec3::new(0.5, 0.5, 0.5));
+        assert_eq!(bounds.surface_area(), 14.0);
+        assert_eq!(bounds.longest_axis(), 0);
+        assert_eq!(Aabb::empty().surface_area(), 0.0);
+        
+        // In through the -x face and out through the +x face, clipped to t_max
+        let ray = Ray::new(Vec3::new(-3.0, 0.5, 0.5), Vec3::unit_x());
+        let inverse = Vec3::new(1.0, Float::INFINITY, Float::INFINITY);
+        assert_eq!(bounds.slabs(&ray, inverse), (2.0, 5.0));
+        assert_eq!(bounds.hit_range(&ray, inverse, Interval::new(0.0, 4.0)), Some(Interval::new(2.0, 4.0)));
+        assert_eq!(bounds.slab_faces(&ray, inverse), ((2.0, -Vec3::unit_x()), (5.0, Vec3::unit_x())));
+        
+        // Parallel to a face and lying on it still counts, just outside it doesn't
+        let along_face = Ray::new(Vec3::new(-3.0, 1.0, 0.5), Vec3::unit_x());
+        assert_eq!(bounds.hit(&along_face, inverse, Interval::new(0.0, Float::INFINITY)), Some(2.0));
+        let above = Ray::new(Vec3::new(-3.0, 1.5, 0.5), Vec3::unit_x());
+        assert_eq!(bounds.hit(&above, inverse, Interval::new(0.0, Float::INFINITY)), None);
+    }
+    
+    #[test]
+    fn test_interval() {
+        let range = Interval::hits(10.0);
+        assert!(!range.surrounds(-1.0) && !range.surrounds(0.0) && range.surrounds(5.0) && !range.surrounds(10.0));
+        assert!(range.contains(0.0) && range.contains(10.0) && !range.contains(10.5));
+        assert_eq!(range.clamp(-1.0), 0.0);
+        assert_eq!(range.clamp(20.0), 10.0);
+        assert!(Interval::new(1.0, 0.0).is_empty() && !Interval::new(1.0, 1.0).is_empty());
+        assert!(Interval::new(0.0, Float::NAN).is_empty());
+    }
+    
     #[test]
     fn test_ray() {
         let ray = Ray::new(Vec3::zero(), Vec3::unit_x());
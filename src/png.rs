@@ -0,0 +1,422 @@
+use std::io::{self, Write};
+
+use crate::image::{self, ImageError, RgbaImage};
+use crate::inflate::{adler32, adler32_update, zlib_decompress};
+
+/// Bytes every PNG file starts with
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Minimal PNG encoder (8- or 16-bit RGB or RGBA, or 16-bit grayscale; uncompressed deflate blocks)
+pub struct PngWriter {
+    width: u32,
+    height: u32,
+    channels: u8,  // 1 = gray, 3 = RGB, 4 = RGBA
+    bit_depth: u8, // 8 or 16 (big-endian samples)
+    pixels: Vec<u8>,
+}
+
+/// Largest payload of a stored (uncompressed) deflate block
+const MAX_STORED_BLOCK: usize = 65535;
+
+impl PngWriter {
+    /// Create an RGB image from row-major pixel bytes
+    pub fn rgb(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        Self::with_channels(width, height, 3, 8, pixels)
+    }
+    
+    /// Create an RGBA image from row-major pixel bytes (straight alpha)
+    pub fn rgba(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        Self::with_channels(width, height, 4, 8, pixels)
+    }
+    
+    /// Create a 16-bit RGB image from row-major values
+    pub fn rgb16(width: u32, height: u32, values: &[u16]) -> Self {
+        Self::with_channels(width, height, 3, 16, values.iter().flat_map(|value| value.to_be_bytes()).collect())
+    }
+    
+    /// Create a 16-bit RGBA image from row-major values (straight alpha)
+    pub fn rgba16(width: u32, height: u32, values: &[u16]) -> Self {
+        Self::with_channels(width, height, 4, 16, values.iter().flat_map(|value| value.to_be_bytes()).collect())
+    }
+    
+    /// Create a 16-bit grayscale image from row-major values, e.g. a depth map
+    pub fn gray16(width: u32, height: u32, values: &[u16]) -> Self {
+        Self::with_channels(width, height, 1, 16, values.iter().flat_map(|value| value.to_be_bytes()).collect())
+    }
+    
+    fn with_channels(width: u32, height: u32, channels: u8, bit_depth: u8, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), width as usize * height as usize * channels as usize * bit_depth as usize / 8, "pixel buffer size mismatch");
+        Self {
+            width,
+            height,
+            channels,
+            bit_depth,
+            pixels,
+        }
+    }
+    
+    /// Encode as PNG file bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut png = SIGNATURE.to_vec();
+        
+        // IHDR: dimensions, bit depth, color type 0 (gray), 2 (RGB) or 6 (RGBA)
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        let color_type = match self.channels {
+            1 => 0,
+            4 => 6,
+            _ => 2,
+        };
+        header.extend_from_slice(&[self.bit_depth, color_type, 0, 0, 0]);
+        write_chunk(&mut png, b"IHDR", &header);
+        
+        // Each scanline is prefixed with filter type 0 (none)
+        let row_bytes = self.width as usize * self.channels as usize * self.bit_depth as usize / 8;
+        let mut raw = Vec::with_capacity((row_bytes + 1) * self.height as usize);
+        for row in self.pixels.chunks(row_bytes.max(1)).take(self.height as usize) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+        
+        write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+/// PNG encoder that writes rows as they come, for images too large to hold whole (8- or 16-bit RGB or RGBA)
+/// Each batch of rows goes out at once as an IDAT chunk of stored deflate blocks, so a file cut short still
+/// holds every row written before
+pub struct PngStream<W: Write> {
+    writer: W,
+    row_bytes: usize,
+    rows_left: u32,
+    adler: u32, // Checksum of the rows so far, with their filter bytes
+}
+
+impl<W: Write> PngStream<W> {
+    /// Write the signature and header of an image with 3 (RGB) or 4 (RGBA) channels of 8 or 16 bits; rows of
+    /// 16-bit samples are written as big-endian bytes
+    pub fn new(mut writer: W, width: u32, height: u32, channels: u8, bit_depth: u8) -> io::Result<Self> {
+        let mut png = SIGNATURE.to_vec();
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&height.to_be_bytes());
+        header.extend_from_slice(&[bit_depth, if channels == 4 { 6 } else { 2 }, 0, 0, 0]);
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &[0x78, 0x01]); // The zlib header; the stream runs on through the rows' chunks
+        writer.write_all(&png)?;
+        Ok(Self { writer, row_bytes: width as usize * channels as usize * bit_depth as usize / 8, rows_left: height, adler: 1 })
+    }
+    
+    /// Write the next whole rows of pixel bytes down the image and flush them
+    pub fn write_rows(&mut self, pixels: &[u8]) -> io::Result<()> {
+        let rows = pixels.len() / self.row_bytes.max(1);
+        if !pixels.len().is_multiple_of(self.row_bytes.max(1)) || rows > self.rows_left as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not whole rows of the image"));
+        }
+        let mut raw = Vec::with_capacity(pixels.len() + rows);
+        for row in pixels.chunks(self.row_bytes.max(1)) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+        
+        // The last rows end the zlib stream, with its checksum
+        let mut data = Vec::with_capacity(raw.len() + 16);
+        self.rows_left -= rows as u32;
+        self.adler = adler32_update(self.adler, &raw);
+        stored_blocks(&mut data, &raw, self.rows_left == 0);
+        if self.rows_left == 0 {
+            data.extend_from_slice(&self.adler.to_be_bytes());
+        }
+        let mut chunk = Vec::with_capacity(data.len() + 12);
+        write_chunk(&mut chunk, b"IDAT", &data);
+        self.writer.write_all(&chunk)?;
+        self.writer.flush()
+    }
+    
+    /// End the file once every row is written, returning the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.rows_left > 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} rows of the image not written", self.rows_left)));
+        }
+        let mut end = Vec::with_capacity(12);
+        write_chunk(&mut end, b"IEND", &[]);
+        self.writer.write_all(&end)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Append a length-prefixed, CRC-terminated chunk
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wrap data in a zlib stream made of stored deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01]; // deflate, 32K window, no preset dictionary
+    stored_blocks(&mut out, data, true);
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Append data as stored deflate blocks, the last of them marked final if `last` is set
+fn stored_blocks(out: &mut Vec<u8>, data: &[u8], last: bool) {
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    
+    if blocks.peek().is_none() && last {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]); // single empty final block
+    }
+    
+    while let Some(block) = blocks.next() {
+        let is_final = last && blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(is_final as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+}
+
+/// Decode a non-interlaced PNG of any color type and bit depth to 8-bit RGBA
+/// 16-bit channels keep their high byte; low bit depths are scaled up to 0-255
+pub fn decode(bytes: &[u8]) -> Result<RgbaImage, ImageError> {
+    let invalid = |reason: &str| ImageError::Invalid { format: "PNG", reason: reason.to_string() };
+    let unsupported = |reason: String| ImageError::Unsupported { format: "PNG", reason };
+    
+    if !bytes.starts_with(&SIGNATURE) {
+        return Err(invalid("missing PNG signature"));
+    }
+    
+    // Collect the chunks we need, checking each CRC
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = Vec::new();
+    let mut pos = SIGNATURE.len();
+    loop {
+        let length = bytes.get(pos..pos + 4).ok_or_else(|| invalid("truncated chunk"))?;
+        let length = u32::from_be_bytes(length.try_into().unwrap()) as usize;
+        let chunk = bytes.get(pos + 4..pos + 8 + length).ok_or_else(|| invalid("truncated chunk"))?;
+        let crc = bytes.get(pos + 8 + length..pos + 12 + length).ok_or_else(|| invalid("truncated chunk"))?;
+        if crc32(chunk) != u32::from_be_bytes(crc.try_into().unwrap()) {
+            return Err(invalid("chunk CRC mismatch"));
+        }
+        pos += 12 + length;
+        
+        let (kind, data) = chunk.split_at(4);
+        match kind {
+            b"IHDR" if data.len() == 13 => header = Some(data),
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = data,
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {} // Ancillary chunks (gamma, text, ...) are ignored
+        }
+    }
+    
+    let header = header.ok_or_else(|| invalid("missing IHDR chunk"))?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap());
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    let (depth, color_type, interlace) = (header[8] as usize, header[9], header[12]);
+    if interlace != 0 {
+        return Err(unsupported("interlaced images".to_string()));
+    }
+    let channels = match (color_type, depth) {
+        (0, 1 | 2 | 4 | 8 | 16) | (3, 1 | 2 | 4 | 8) => 1,
+        (2, 8 | 16) => 3,
+        (4, 8 | 16) => 2,
+        (6, 8 | 16) => 4,
+        _ => return Err(unsupported(format!("color type {} with bit depth {}", color_type, depth))),
+    };
+    
+    let count = image::pixel_count(width, height).ok_or_else(|| invalid("image too large"))?;
+    
+    let raw = zlib_decompress(&compressed).map_err(invalid)?;
+    let stride = (width as usize * channels * depth).div_ceil(8);
+    let bytes_per_pixel = (channels * depth).div_ceil(8);
+    if raw.len() < (stride + 1) * height as usize {
+        return Err(invalid("not enough image data"));
+    }
+    
+    // Undo the per-row filters, each predicting from the row above and/or the pixel to the left
+    let mut rows = vec![0u8; stride * height as usize];
+    for y in 0..height as usize {
+        let filter = raw[y * (stride + 1)];
+        let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        let (done, rest) = rows.split_at_mut(y * stride);
+        let above = if y > 0 { &done[(y - 1) * stride..] } else { &[][..] };
+        let row = &mut rest[..stride];
+        
+        for i in 0..stride {
+            let a = if i >= bytes_per_pixel { row[i - bytes_per_pixel] } else { 0 };
+            let b = above.get(i).copied().unwrap_or(0);
+            let c = if i >= bytes_per_pixel { above.get(i - bytes_per_pixel).copied().unwrap_or(0) } else { 0 };
+            let prediction = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(invalid("unknown row filter")),
+            };
+            row[i] = line[i].wrapping_add(prediction);
+        }
+    }
+    
+    // Read samples (any depth) and expand them to RGBA
+    let sample = |row: &[u8], index: usize| -> u16 {
+        match depth {
+            16 => row[index * 2] as u16, // High byte
+            8 => row[index] as u16,
+            _ => {
+                let bit = index * depth;
+                ((row[bit / 8] >> (8 - depth - bit % 8)) & ((1 << depth) - 1) as u8) as u16
+            }
+        }
+    };
+    let scale = |value: u16| if depth < 8 { (value * 255 / ((1 << depth) - 1)) as u8 } else { value as u8 };
+    
+    let mut pixels = Vec::with_capacity(count);
+    for row in rows.chunks(stride.max(1)).take(height as usize) {
+        for x in 0..width as usize {
+            let pixel = match color_type {
+                0 => {
+                    let v = scale(sample(row, x));
+                    [v, v, v, 255]
+                }
+                2 => [sample(row, x * 3) as u8, sample(row, x * 3 + 1) as u8, sample(row, x * 3 + 2) as u8, 255],
+                3 => {
+                    let index = sample(row, x) as usize;
+                    let rgb = palette.get(index * 3..index * 3 + 3).ok_or_else(|| invalid("palette index out of range"))?;
+                    [rgb[0], rgb[1], rgb[2], transparency.get(index).copied().unwrap_or(255)]
+                }
+                4 => {
+                    let v = sample(row, x * 2) as u8;
+                    [v, v, v, sample(row, x * 2 + 1) as u8]
+                }
+                _ => [
+                    sample(row, x * 4) as u8,
+                    sample(row, x * 4 + 1) as u8,
+                    sample(row, x * 4 + 2) as u8,
+                    sample(row, x * 4 + 3) as u8,
+                ],
+            };
+            pixels.push(pixel);
+        }
+    }
+    
+    Ok(RgbaImage { width, height, pixels })
+}
+
+/// Paeth predictor: whichever of left, above or upper-left is closest to left + above - upper-left
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+    
+    #[test]
+    fn test_png_structure() {
+        let png = PngWriter::rgba(2, 1, vec![255, 0, 0, 255, 0, 0, 255, 0]).to_bytes();
+        assert!(png.starts_with(&[0x89, b'P', b'N', b'G']));
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(png[25], 6); // RGBA color type
+        assert!(png.ends_with(&[b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+        
+        // 16-bit gray reads back through the decoder, which keeps the high bytes
+        let png = PngWriter::gray16(2, 1, &[0x1234, 0xff00]).to_bytes();
+        assert_eq!((png[24], png[25]), (16, 0));
+        assert_eq!(decode(&png).unwrap().pixel(1, 0), [255, 255, 255, 255]);
+        assert_eq!(decode(&png).unwrap().pixel(0, 0), [0x12, 0x12, 0x12, 255]);
+    }
+    
+    #[test]
+    fn test_png_stream() {
+        // Three rows written in two batches decode like the whole image written at once
+        let pixels: Vec<u8> = (0..2 * 3 * 3).map(|i| i as u8 * 10).collect();
+        let mut stream = PngStream::new(Vec::new(), 2, 3, 3, 8).unwrap();
+        stream.write_rows(&pixels[..6]).unwrap();
+        assert!(stream.writer.windows(6).any(|bytes| bytes == &pixels[..6])); // On its way out already
+        stream.write_rows(&pixels[6..]).unwrap();
+        assert!(stream.write_rows(&pixels[..6]).is_err()); // Past the bottom
+        let png = stream.finish().unwrap();
+        assert_eq!(decode(&png).unwrap().pixels, decode(&PngWriter::rgb(2, 3, pixels.clone()).to_bytes()).unwrap().pixels);
+        
+        let mut short = PngStream::new(Vec::new(), 2, 3, 4, 8).unwrap();
+        short.write_rows(&[0; 8]).unwrap();
+        assert!(short.finish().is_err());
+        
+        // 16-bit rows take two bytes a sample, as the whole-image writer writes them
+        let values = [0x1234, 0xff00, 0x00ff, 0x8000, 0x0001, 0xffff];
+        let bytes: Vec<u8> = values.iter().flat_map(|value: &u16| value.to_be_bytes()).collect();
+        let mut stream = PngStream::new(Vec::new(), 2, 1, 3, 16).unwrap();
+        stream.write_rows(&bytes).unwrap();
+        let png = stream.finish().unwrap();
+        assert_eq!(png[24], 16);
+        assert_eq!(decode(&png).unwrap().pixels, decode(&PngWriter::rgb16(2, 1, &values).to_bytes()).unwrap().pixels);
+        assert_eq!(decode(&png).unwrap().pixel(0, 0), [0x12, 0xff, 0x00, 255]);
+    }
+    
+    #[test]
+    fn test_decode_filters() {
+        // 3x2 RGB written by zlib with a Sub-filtered row and a Paeth-filtered row
+        let png = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+            0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x02, 0x08, 0x02, 0x00, 0x00, 0x00, 0x12, 0x16, 0xf1,
+            0x4d, 0x00, 0x00, 0x00, 0x14, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xe4, 0x12, 0x91, 0x83,
+            0x00, 0x16, 0x56, 0x30, 0x68, 0x12, 0x59, 0x06, 0x00, 0x11, 0x09, 0x02, 0x50, 0x1e, 0xd8, 0xc7,
+            0xa9, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+        ];
+        let image = decode(&png).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.pixel(1, 0), [40, 50, 60, 255]);
+        assert_eq!(image.pixel(2, 1), [200, 100, 0, 255]);
+        
+        let mut corrupt = png;
+        corrupt[50] ^= 0xff;
+        assert!(decode(&corrupt).is_err());
+        
+        // A header claiming 65536x65537 pixels is refused before anything is allocated for them
+        let mut huge = png;
+        huge[16..24].copy_from_slice(&[0, 1, 0, 0, 0, 1, 0, 1]);
+        let crc = crc32(&huge[12..29]);
+        huge[29..33].copy_from_slice(&crc.to_be_bytes());
+        assert!(matches!(decode(&huge), Err(ImageError::Invalid { reason, .. }) if reason == "image too large"));
+    }
+}
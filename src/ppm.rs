@@ -1,3 +1,8 @@
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::image::{self, ImageError, RgbaImage};
+
 /// PPM P3 format writer for ASCII image output
 pub struct PpmWriter {
     width: u32,
@@ -22,26 +27,126 @@ impl PpmWriter {
         self.pixels.push(b);
     }
     
-    /// Convert to PPM P3 format string
-    pub fn to_string(&self) -> String {
-        let mut result = String::new();
-        
+    /// Raw RGB bytes written so far, row-major from the top-left pixel
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+/// Formats as a PPM P3 string
+impl fmt::Display for PpmWriter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // PPM P3 header
-        result.push_str("P3\n");
-        result.push_str(&format!("{} {}\n", self.width, self.height));
-        result.push_str("255\n");
+        writeln!(f, "P3")?;
+        writeln!(f, "{} {}", self.width, self.height)?;
+        writeln!(f, "255")?;
         
         // Write pixels - one per line as "R G B"
         for chunk in self.pixels.chunks(3) {
             if chunk.len() == 3 {
-                result.push_str(&format!("{} {} {}\n", chunk[0], chunk[1], chunk[2]));
+                writeln!(f, "{} {} {}", chunk[0], chunk[1], chunk[2])?;
             }
         }
         
-        result
+        Ok(())
+    }
+}
+
+/// Binary PPM (P6) writer that writes rows as they come, for images too large to hold whole, with 8- or 16-bit
+/// samples (maximum value 255 or 65535); a file cut short still holds every row written before
+pub struct PpmStream<W: Write> {
+    writer: W,
+    row_bytes: usize,
+    rows_left: u32,
+}
+
+impl<W: Write> PpmStream<W> {
+    /// Write the header of a width × height image of 8 or 16 bits per sample
+    pub fn new(mut writer: W, width: u32, height: u32, bit_depth: u8) -> io::Result<Self> {
+        let max_value = if bit_depth == 16 { 65535 } else { 255 };
+        write!(writer, "P6\n{} {}\n{}\n", width, height, max_value)?;
+        Ok(Self { writer, row_bytes: width as usize * 3 * bit_depth as usize / 8, rows_left: height })
+    }
+    
+    /// Write the next whole rows of RGB samples down the image and flush them; 16-bit samples are big-endian
+    pub fn write_rows(&mut self, rgb: &[u8]) -> io::Result<()> {
+        let rows = rgb.len() / self.row_bytes.max(1);
+        if !rgb.len().is_multiple_of(self.row_bytes.max(1)) || rows > self.rows_left as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not whole rows of the image"));
+        }
+        self.rows_left -= rows as u32;
+        self.writer.write_all(rgb)?;
+        self.writer.flush()
+    }
+    
+    /// End the image once every row is written, returning the writer
+    pub fn finish(self) -> io::Result<W> {
+        if self.rows_left > 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} rows of the image not written", self.rows_left)));
+        }
+        Ok(self.writer)
     }
 }
 
+/// Decode a PPM image, either ASCII (P3) or binary (P6), with any maximum value
+pub fn decode(bytes: &[u8]) -> Result<RgbaImage, ImageError> {
+    let invalid = |reason: &str| ImageError::Invalid { format: "PPM", reason: reason.to_string() };
+    let binary = bytes.starts_with(b"P6");
+    
+    // Header fields are whitespace-separated and may be interleaved with # comments
+    let mut pos = 2;
+    let next_number = |pos: &mut usize| -> Result<u32, ImageError> {
+        loop {
+            match bytes.get(*pos) {
+                Some(b'#') => {
+                    while bytes.get(*pos).is_some_and(|&b| b != b'\n') {
+                        *pos += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => *pos += 1,
+                _ => break,
+            }
+        }
+        let start = *pos;
+        while bytes.get(*pos).is_some_and(|b| b.is_ascii_digit()) {
+            *pos += 1;
+        }
+        std::str::from_utf8(&bytes[start..*pos])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(|| invalid("expected a number"))
+    };
+    
+    let width = next_number(&mut pos)?;
+    let height = next_number(&mut pos)?;
+    let max_value = next_number(&mut pos)?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid("maximum value must be between 1 and 65535"));
+    }
+    let scale = |value: u32| (value.min(max_value) * 255 / max_value) as u8;
+    
+    let count = image::pixel_count(width, height).ok_or_else(|| invalid("image too large"))? * 3;
+    let mut samples = Vec::with_capacity(count.min(bytes.len())); // Each sample takes at least a byte
+    if binary {
+        // A single whitespace byte separates the header from the samples
+        let data = bytes.get(pos + 1..).ok_or_else(|| invalid("not enough pixel data"))?;
+        let width = if max_value > 255 { 2 } else { 1 };
+        if data.len() < count * width {
+            return Err(invalid("not enough pixel data"));
+        }
+        for chunk in data.chunks(width).take(count) {
+            samples.push(scale(chunk.iter().fold(0, |value, &b| value << 8 | b as u32)));
+        }
+    } else {
+        for _ in 0..count {
+            samples.push(scale(next_number(&mut pos)?));
+        }
+    }
+    
+    let pixels = samples.chunks(3).map(|rgb| [rgb[0], rgb[1], rgb[2], 255]).collect();
+    Ok(RgbaImage { width, height, pixels })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,5 +165,24 @@ mod tests {
         assert!(output.contains("0 255 0\n"));
         assert!(output.contains("0 0 255\n"));
         assert!(output.contains("255 255 255\n"));
+        
+        // Streamed a row at a time, the same pixels come out in binary
+        let mut stream = PpmStream::new(Vec::new(), 2, 2, 8).unwrap();
+        stream.write_rows(&writer.pixels()[..6]).unwrap();
+        assert!(stream.finish().is_err());
+        let mut stream = PpmStream::new(Vec::new(), 2, 2, 8).unwrap();
+        for row in writer.pixels().chunks(6) {
+            stream.write_rows(row).unwrap();
+        }
+        let ppm = stream.finish().unwrap();
+        assert!(ppm.starts_with(b"P6\n2 2\n255\n"));
+        assert_eq!(decode(&ppm).unwrap().pixels, decode(output.as_bytes()).unwrap().pixels);
+        
+        // 16-bit samples take two bytes each, high byte first
+        let mut stream = PpmStream::new(Vec::new(), 1, 1, 16).unwrap();
+        stream.write_rows(&[0xff, 0xff, 0x80, 0x00, 0x00, 0x01]).unwrap();
+        let ppm = stream.finish().unwrap();
+        assert!(ppm.starts_with(b"P6\n1 1\n65535\n"));
+        assert_eq!(decode(&ppm).unwrap().pixel(0, 0), [255, 127, 0, 255]);
     }
 }
@@ -0,0 +1,220 @@
+use std::path::PathBuf;
+
+use numpy::{PyArray1, PyArray3, PyArrayMethods};
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::render::Renderer;
+use crate::scene_file::{ObjectDesc, SceneDesc, SceneError, ShapeDesc, CameraDesc, LightDesc, MaterialRef, DEFAULT_FOV};
+use crate::validate;
+
+fn scene_error(err: SceneError) -> PyErr {
+    match err {
+        SceneError::Io(err) => PyOSError::new_err(err.to_string()),
+        err => PyValueError::new_err(err.to_string()),
+    }
+}
+
+/// A scene description, built up from Python or loaded from a scene file
+#[pyclass(name = "Scene")]
+#[derive(Clone)]
+pub struct PyScene {
+    desc: SceneDesc,
+}
+
+impl PyScene {
+    fn add_object(&mut self, shape: ShapeDesc, material: &str) {
+        self.desc.objects.push(ObjectDesc {
+            name: None,
+            shape,
+            material: Some(MaterialRef::Named(material.to_string())),
+            cast_shadows: None,
+            visible_to_camera: None,
+            visible_in_reflections: None,
+            priority: None,
+        });
+    }
+    
+    /// Validate, build and render to 8-bit RGB bytes
+    fn render_rgb(&self, width: u32, height: u32, samples: u32, threads: usize) -> Result<Vec<u8>, String> {
+        if width == 0 || height == 0 || samples == 0 {
+            return Err("width, height and samples must be non-zero".to_string());
+        }
+        if !self.desc.include.is_empty() {
+            return Err(SceneError::IncludesUnavailable.to_string());
+        }
+        if let Some(error) = validate::validate(&self.desc).into_iter().find(|d| d.severity == validate::Severity::Error) {
+            return Err(error.to_string());
+        }
+        
+        let (scene, camera) = self
+            .desc
+            .build(1.0, DEFAULT_FOV, width as f64 / height as f64)
+            .map_err(|err| err.to_string())?;
+        let mut renderer = Renderer::new();
+        renderer.samples = samples;
+        renderer.threads = match threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        Ok(renderer.render_framebuffer(&scene, &camera, width, height).to_rgb8())
+    }
+}
+
+#[pymethods]
+impl PyScene {
+    /// An empty scene; it needs a camera before it can be rendered
+    #[new]
+    fn new() -> Self {
+        Self { desc: SceneDesc::from_json("{}").expect("an empty scene parses") }
+    }
+    
+    /// Parse scene file JSON text (includes can't be resolved; use `load` for those)
+    #[staticmethod]
+    fn from_json(text: &str) -> PyResult<Self> {
+        SceneDesc::from_json(text).map(|desc| Self { desc }).map_err(scene_error)
+    }
+    
+    /// Read a scene file, resolving its includes
+    #[staticmethod]
+    fn load(path: PathBuf) -> PyResult<Self> {
+        SceneDesc::from_file(&path).map(|desc| Self { desc }).map_err(scene_error)
+    }
+    
+    /// Set a parameter like the `--set` flag, e.g. `scene.set("lights[0].intensity", "2")`
+    fn set(&mut self, key: &str, value: &str) -> PyResult<()> {
+        let mut document = serde_json::to_value(&self.desc).expect("scene descriptions always serialize");
+        crate::scene_file::set_parameter(&mut document, key, value).map_err(scene_error)?;
+        self.desc = serde_json::from_value(document).map_err(|err| scene_error(err.into()))?;
+        Ok(())
+    }
+    
+    #[pyo3(signature = (look_from, look_at, up = [0.0, 1.0, 0.0], fov = None, roll = None, shift = None))]
+    fn set_camera(&mut self, look_from: [f64; 3], look_at: [f64; 3], up: [f64; 3], fov: Option<f64>, roll: Option<f64>, shift: Option<[f64; 2]>) {
+        self.desc.camera = Some(CameraDesc { look_from, look_at, up, fov, projection: None, roll, shift, near: None, far: None, iso: None, shutter: None, aperture: None });
+    }
+    
+    /// Render from a camera in the scene's cameras table from now on
+    fn select_camera(&mut self, name: &str) -> PyResult<()> {
+        self.desc.select_camera(name).map_err(scene_error)
+    }
+    
+    #[pyo3(signature = (position, intensity = 1.0, color = [1.0, 1.0, 1.0], radius = None, temperature = None, cast_shadows = true))]
+    fn add_light(&mut self, position: [f64; 3], intensity: f64, color: [f64; 3], radius: Option<f64>, temperature: Option<f64>, cast_shadows: bool) {
+        let cast_shadows = (!cast_shadows).then_some(false); // Left out of the JSON unless set
+        self.desc.lights.push(LightDesc { position, intensity, color, radius, lumens: None, watts: None, temperature, ies: None, cast_shadows, illuminates: Vec::new(), ignores: Vec::new() });
+    }
+    
+    #[pyo3(signature = (center, radius, material = "white"))]
+    fn add_sphere(&mut self, center: [f64; 3], radius: f64, material: &str) {
+        self.add_object(ShapeDesc::Sphere { center, radius }, material);
+    }
+    
+    #[pyo3(signature = (min, max, material = "white"))]
+    fn add_cube(&mut self, min: [f64; 3], max: [f64; 3], material: &str) {
+        self.add_object(ShapeDesc::Cube { min, max }, material);
+    }
+    
+    #[pyo3(signature = (point, normal, material = "white"))]
+    fn add_plane(&mut self, point: [f64; 3], normal: [f64; 3], material: &str) {
+        self.add_object(ShapeDesc::Plane { point, normal }, material);
+    }
+    
+    #[pyo3(signature = (center, radius, height, material = "white"))]
+    fn add_cylinder(&mut self, center: [f64; 3], radius: f64, height: f64, material: &str) {
+        self.add_object(ShapeDesc::Cylinder { center, radius, height }, material);
+    }
+    
+    #[pyo3(signature = (start, end, radius, material = "white"))]
+    fn add_capsule(&mut self, start: [f64; 3], end: [f64; 3], radius: f64, material: &str) {
+        self.add_object(ShapeDesc::Capsule { start, end, radius }, material);
+    }
+    
+    /// `basis` is "catmull_rom" (through every point) or "bezier" (3n + 1 points guiding cubic spans)
+    #[pyo3(signature = (points, radius, tip_radius = None, basis = "catmull_rom", material = "white"))]
+    fn add_curve(&mut self, points: Vec<[f64; 3]>, radius: f64, tip_radius: Option<f64>, basis: &str, material: &str) -> PyResult<()> {
+        let basis = serde_json::from_value(basis.into()).map_err(|err| scene_error(err.into()))?;
+        self.add_object(ShapeDesc::Curve { points, radius, tip_radius, basis }, material);
+        Ok(())
+    }
+    
+    #[pyo3(signature = (path, min, max, material = "white"))]
+    fn add_heightfield(&mut self, path: PathBuf, min: [f64; 3], max: [f64; 3], material: &str) {
+        self.add_object(ShapeDesc::Heightfield { path, min, max }, material);
+    }
+    
+    #[pyo3(signature = (path, min, voxel_size, material = "white"))]
+    fn add_voxels(&mut self, path: PathBuf, min: [f64; 3], voxel_size: f64, material: &str) {
+        self.add_object(ShapeDesc::Voxels { path, min, voxel_size }, material);
+    }
+    
+    /// `splat` is "disk" (facing each ray) or "sphere"
+    #[pyo3(signature = (path, radius, splat = "disk", translate = [0.0; 3], scale = 1.0, material = "white"))]
+    fn add_points(&mut self, path: PathBuf, radius: f64, splat: &str, translate: [f64; 3], scale: f64, material: &str) -> PyResult<()> {
+        let splat = serde_json::from_value(splat.into()).map_err(|err| scene_error(err.into()))?;
+        self.add_object(ShapeDesc::Points { path, radius, splat, translate, scale }, material);
+        Ok(())
+    }
+    
+    /// `shading` is "flat" or "smooth", or None to go by whether the file has vertex normals
+    #[pyo3(signature = (path, translate = [0.0; 3], scale = 1.0, shading = None, subdivision = None, material = "white"))]
+    fn add_mesh(&mut self, path: PathBuf, translate: [f64; 3], scale: f64, shading: Option<&str>, subdivision: Option<u32>, material: &str) -> PyResult<()> {
+        let shading = shading.map(|shading| serde_json::from_value(shading.into())).transpose().map_err(|err| scene_error(err.into()))?;
+        self.add_object(ShapeDesc::Mesh { path, translate, scale, shading, subdivision }, material);
+        Ok(())
+    }
+    
+    /// `material` is used only by primitives the file gives no material
+    #[pyo3(signature = (path, translate = [0.0; 3], scale = 1.0, material = "white"))]
+    fn add_gltf(&mut self, path: PathBuf, translate: [f64; 3], scale: f64, material: &str) {
+        self.add_object(ShapeDesc::Gltf { path, translate, scale }, material);
+    }
+    
+    /// The scene as scene file JSON
+    fn to_json(&self) -> String {
+        self.desc.to_json()
+    }
+    
+    /// Render to a (height, width, 3) uint8 array; `threads=0` uses all cores
+    /// The GIL is released while rendering, so other Python threads keep running
+    #[pyo3(signature = (width, height, samples = 1, threads = 0))]
+    fn render<'py>(
+        &self,
+        py: Python<'py>,
+        width: u32,
+        height: u32,
+        samples: u32,
+        threads: usize,
+    ) -> PyResult<Bound<'py, PyArray3<u8>>> {
+        let rgb = py.detach(|| self.render_rgb(width, height, samples, threads)).map_err(PyValueError::new_err)?;
+        PyArray1::from_vec(py, rgb).reshape([height as usize, width as usize, 3])
+    }
+}
+
+/// Python module `rt`
+#[pymodule]
+#[pyo3(name = "rt")]
+fn python_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyScene>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_build_and_render() {
+        let mut scene = PyScene::new();
+        assert!(scene.render_rgb(4, 3, 1, 1).unwrap_err().contains("camera"));
+        
+        scene.set_camera([0.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0], None, None, None);
+        scene.add_light([0.0, 5.0, 0.0], 1.0, [1.0, 1.0, 1.0], None, None, true);
+        scene.add_sphere([0.0, 0.0, -3.0], 1.0, "red");
+        scene.set("background", "[0, 0, 1]").unwrap();
+        
+        let rgb = scene.render_rgb(4, 3, 1, 1).unwrap();
+        assert_eq!(rgb.len(), 4 * 3 * 3);
+        assert_eq!(&rgb[0..3], &[0, 0, 255]);
+        assert!(PyScene::from_json(&scene.to_json()).is_ok());
+    }
+}
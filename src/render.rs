@@ -1,100 +1,1297 @@
-use crate::math::{Vec3, Ray};
+use crate::math::{Float, Vec3, Ray};
 use crate::camera::Camera;
-use crate::scene::Scene;
+use crate::scene::{Scene, Light, RayKind};
+use crate::shapes::HitInfo;
 use crate::ppm::PpmWriter;
+use crate::framebuffer::Framebuffer;
+use crate::denoise::Guides;
+use crate::aov::DepthMap;
+use crate::material::Material;
+use crate::bsdf::{fresnel, refract};
+use crate::stats::{self, Stopwatch};
+use crate::sampling::{luminance, LightSampler, LightSampling, Rng};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::ops::Range;
+use std::str::FromStr;
+use std::time::Duration;
+
+mod debug;
+mod edges;
+mod interleave;
+mod nesting;
+mod path;
+mod trace;
+
+use nesting::Interior;
+pub use debug::DebugView;
+pub use trace::{SampleTrace, TraceEvent};
+
+/// Width and height of a tile: the unit of work handed to render threads, timed at debug level
+pub const TILE_SIZE: u32 = 16;
+
+/// Most camera rays per pixel for the denoiser guides; more only refine antialiased edges
+const GUIDE_SAMPLES: u32 = 16;
+
+/// Pixels finished by the current render, readable from other threads while it runs
+#[derive(Debug, Default)]
+pub struct Progress {
+    pixels_done: AtomicU64,
+    pixels: AtomicU64, // Counting each pixel once per pass with a time limit
+}
+
+impl Progress {
+    /// Fraction of the image rendered so far, 0-1 (0 before the first render starts)
+    pub fn fraction(&self) -> f64 {
+        match self.pixels.load(Ordering::Relaxed) {
+            0 => 0.0,
+            pixels => self.pixels_done.load(Ordering::Relaxed) as f64 / pixels as f64,
+        }
+    }
+}
+
+/// Order in which tiles are handed to render threads; the image is the same in any order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileOrder {
+    #[default]
+    Scanline, // Rows of tiles from the top
+    Spiral,   // Outward from the center, where the subject usually is
+    Hilbert,  // Along a Hilbert curve, so consecutive tiles are neighbors and share cached geometry
+    Random,   // Shuffled with the render's seed
+}
+
+impl FromStr for TileOrder {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scanline" => Ok(Self::Scanline),
+            "spiral" => Ok(Self::Spiral),
+            "hilbert" => Ok(Self::Hilbert),
+            "random" => Ok(Self::Random),
+            _ => Err(format!("unknown tile order '{}' (expected scanline, spiral, hilbert or random)", s)),
+        }
+    }
+}
+
+/// Block of up to TILE_SIZE × TILE_SIZE pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Tile {
+    id: u32, // Position in scanline order, which seeds the tile's random numbers
+    left: u32,
+    top: u32,
+    right: u32,  // Exclusive
+    bottom: u32, // Exclusive
+}
+
+impl Tile {
+    /// Image position of the tile's i-th pixel, counting along its rows
+    fn pixel(&self, i: usize) -> (u32, u32) {
+        let width = self.right - self.left;
+        (self.left + i as u32 % width, self.top + i as u32 / width)
+    }
+    
+    fn contains(&self, (x, y): (u32, u32)) -> bool {
+        (self.left..self.right).contains(&x) && (self.top..self.bottom).contains(&y)
+    }
+    
+    /// Which random stream of the tile's a pixel's sample draws from, out of `samples` per pixel
+    fn sample_index(&self, (x, y): (u32, u32), sample: usize, samples: usize) -> u64 {
+        ((y - self.top) * (self.right - self.left) + x - self.left) as u64 * samples as u64 + sample as u64
+    }
+}
 
 /// Ray tracer renderer
 pub struct Renderer {
     pub max_depth: u32,
-    pub epsilon: f64,
+    pub volume_steps: u32, // Ray-marching steps through each participating medium
+    pub reflections: bool, // Trace mirror reflections for materials with reflectivity > 0
+    pub transparent_background: bool, // Camera rays that miss everything get alpha 0
+    pub material_override: Option<Material>, // Replaces every object's material (e.g. clay renders)
+    pub samples: u32,   // Antialiasing samples per pixel
+    pub threads: usize, // Worker threads used by render_framebuffer
+    pub progress: Option<Arc<Progress>>, // Updated after each tile
+    pub light_samples: Option<u32>,      // Lights sampled per shading point (None = every light)
+    pub light_sampling: LightSampling,   // How those lights are picked
+    pub path_tracing: bool, // Trace random-walk paths (global illumination, soft shadows) instead of Whitted rays
+    pub next_event_estimation: bool, // Connect each path vertex to the lights; without it only BSDF sampling finds them
+    pub roulette_min_probability: Float, // Russian roulette: least chance a dim path survives each bounce (1 = never stop early)
+    pub clamp: Option<Float>, // Largest channel value of any indirect contribution in path tracing (firefly clamp)
+    pub outlier_rejection: Option<Float>, // Drop samples brighter than their pixel's mean + this many standard deviations
+    pub packet_size: u32, // Camera rays are traced as packets of packet_size × packet_size pixels (1 = one at a time)
+    pub seed: u64,        // Global seed for the random numbers; the same seed renders the same image
+    pub time_limit: Option<Duration>, // Stop taking further samples after this long (always taking one); disables outlier rejection
+    pub tile_order: TileOrder,        // Order in which tiles are rendered
+    pub interleave: u32,              // Render one pixel in this many (1, 2 or 4) and interpolate the rest, for drafts
+    pub max_distance: Float,          // Rays ignore surfaces further along them than this, bounding traversal in huge scenes
+    pub debug_view: Option<DebugView>, // Show an intermediate quantity per pixel instead of shading
+    pub heat_max: Option<Float>,       // Heat views show count / heat_max in gray instead of a log-scaled color ramp
+}
+
+/// What every tile of a render shares
+struct Frame<'a> {
+    scene: &'a Scene,
+    camera: &'a Camera,
+    lights: LightSampler,
+    size: (u32, u32),             // Width and height in pixels
+    offsets: Vec<(Float, Float)>, // Sub-pixel sample positions
+    rows: Range<u32>,             // Rows of tiles to render, counted from the top
+}
+
+/// Buffers a render worker reuses from one tile to the next
+#[derive(Default)]
+struct Scratch {
+    packet: Vec<(u32, u32)>,          // Pixels of the current packet
+    rays: Vec<Ray>,                   // Their camera rays for one sample
+    samples: Vec<Vec<(Vec3, Float)>>, // Each pixel's samples so far
+}
+
+/// Per-sample state carried through a trace
+struct SampleContext<'a> {
+    rng: Rng,
+    lights: &'a LightSampler,
+    spread: Float,    // Angle a pixel subtends, by which each ray's footprint widens along the path
+    travelled: Float, // Length of the path from the camera to the current ray's origin
+    interior: Interior, // Refractive objects the current ray is inside of
 }
 
 impl Renderer {
     pub fn new() -> Self {
         Self {
             max_depth: 10,
-            epsilon: 1e-4,
+            volume_steps: 32,
+            reflections: false,
+            transparent_background: false,
+            material_override: None,
+            samples: 1,
+            threads: 1,
+            progress: None,
+            light_samples: None,
+            light_sampling: LightSampling::default(),
+            path_tracing: false,
+            next_event_estimation: true,
+            roulette_min_probability: 0.05,
+            clamp: None,
+            outlier_rejection: None,
+            packet_size: 4,
+            seed: 0,
+            time_limit: None,
+            tile_order: TileOrder::default(),
+            interleave: 1,
+            max_distance: Float::INFINITY,
+            debug_view: None,
+            heat_max: None,
         }
     }
     
     /// Render a scene to a PPM writer
     pub fn render(&self, scene: &Scene, camera: &Camera, width: u32, height: u32) -> PpmWriter {
-        let mut writer = PpmWriter::new(width, height);
+        self.render_framebuffer(scene, camera, width, height).to_ppm()
+    }
+    
+    /// Render a scene to a floating-point RGBA framebuffer
+    /// Tiles are shared out between `threads` workers; the result doesn't depend on the thread count or tile order
+    /// With a `time_limit`, samples are taken one pass over the image at a time, stopping when time runs out
+    /// With an `interleave` above 1, only some pixels are traced and the others are interpolated from them
+    pub fn render_framebuffer(&self, scene: &Scene, camera: &Camera, width: u32, height: u32) -> Framebuffer {
+        let start = Stopwatch::start();
+        let frame = Frame {
+            scene,
+            camera,
+            lights: LightSampler::new(&scene.lights, self.light_samples, self.light_sampling),
+            size: (width, height),
+            offsets: sample_offsets(self.samples),
+            rows: 0..height.div_ceil(TILE_SIZE),
+        };
+        let samples = frame.offsets.len();
+        if let Some(progress) = &self.progress {
+            let passes = if self.time_limit.is_some() { samples as u64 } else { 1 };
+            progress.pixels_done.store(0, Ordering::Relaxed);
+            progress.pixels.store(width as u64 * height as u64 * passes, Ordering::Relaxed);
+        }
+        
+        let mut framebuffer = Framebuffer::new(width, height);
+        let mut taken = samples;
+        match self.time_limit {
+            None => {
+                for (tile, pixels) in self.render_tiles(&frame, 0..samples, None) {
+                    for (i, (color, alpha)) in pixels.into_iter().enumerate() {
+                        let (x, y) = tile.pixel(i);
+                        framebuffer.set(x, y, color.into(), alpha);
+                    }
+                }
+            }
+            Some(limit) => {
+                // Sums of each pixel's samples and their count; every pixel gets at least the first sample
+                let mut sums = vec![(Vec3::zero(), 0.0, 0); width as usize * height as usize];
+                for sample in 0..samples {
+                    if sample > 0 && start.elapsed() >= limit {
+                        log::info!("time limit of {:.2?} reached after {} of {} samples/pixel", limit, sample, samples);
+                        taken = sample;
+                        break;
+                    }
+                    let deadline = (sample > 0).then_some((&start, limit));
+                    for (tile, pixels) in self.render_tiles(&frame, sample..sample + 1, deadline) {
+                        for (i, (color, alpha)) in pixels.into_iter().enumerate() {
+                            let (x, y) = tile.pixel(i);
+                            let sum = &mut sums[y as usize * width as usize + x as usize];
+                            *sum = (sum.0 + color, sum.1 + alpha, sum.2 + 1);
+                        }
+                    }
+                }
+                for (i, (color, alpha, count)) in sums.into_iter().enumerate() {
+                    framebuffer.set(i as u32 % width, i as u32 / width, (color / count as Float).into(), alpha / count as Float);
+                }
+            }
+        }
+        if self.interleave > 1 {
+            interleave::fill(&mut framebuffer, self.interleave);
+        }
+        if camera.exposure != 0.0 {
+            framebuffer.apply_exposure(camera.exposure);
+        }
+        
+        log::info!(
+            "rendered {}x{} at {} samples/pixel on {} threads in {:.2?}",
+            width,
+            height,
+            taken,
+            self.threads.max(1),
+            start.elapsed()
+        );
+        framebuffer
+    }
+    
+    /// Render a band of a width × height image: the tiles in `rows` (of TILE_SIZE pixels each, from the top), as
+    /// wide as the image and down to its bottom at most, exactly as `render_framebuffer` renders them
+    /// Machines rendering different bands of the same image can so put it together; the time limit and interleave
+    /// are ignored, as they depend on the whole image
+    pub fn render_tile_rows(&self, scene: &Scene, camera: &Camera, (width, height): (u32, u32), rows: Range<u32>) -> Framebuffer {
+        let frame = Frame {
+            scene,
+            camera,
+            lights: LightSampler::new(&scene.lights, self.light_samples, self.light_sampling),
+            size: (width, height),
+            offsets: sample_offsets(self.samples),
+            rows: rows.clone(),
+        };
+        let top = (rows.start * TILE_SIZE).min(height);
+        let mut band = Framebuffer::new(width, (rows.end * TILE_SIZE).clamp(top, height) - top);
+        for (tile, pixels) in self.render_tiles(&frame, 0..frame.offsets.len(), None) {
+            for (i, (color, alpha)) in pixels.into_iter().enumerate() {
+                let (x, y) = tile.pixel(i);
+                band.set(x, y - top, color.into(), alpha);
+            }
+        }
+        if camera.exposure != 0.0 {
+            band.apply_exposure(camera.exposure);
+        }
+        band
+    }
+    
+    /// Render a width × height image a band of whole tile rows at a time from the top, passing each band to `band`
+    /// as it is done, so only one band is held at a time; together they make the image `render_framebuffer`
+    /// renders, less the time limit and interleave
+    /// Bands hold enough tiles to keep every thread busy; the first error from `band` stops the render
+    pub fn render_bands<E>(&self, scene: &Scene, camera: &Camera, (width, height): (u32, u32), mut band: impl FnMut(Framebuffer) -> Result<(), E>) -> Result<(), E> {
+        let start = Stopwatch::start();
+        if let Some(progress) = &self.progress {
+            progress.pixels_done.store(0, Ordering::Relaxed);
+            progress.pixels.store(width as u64 * height as u64, Ordering::Relaxed);
+        }
+        let rows = height.div_ceil(TILE_SIZE);
+        let band_rows = (4 * self.threads.max(1) as u32).div_ceil(width.div_ceil(TILE_SIZE)).max(1);
+        for top in (0..rows).step_by(band_rows as usize) {
+            band(self.render_tile_rows(scene, camera, (width, height), top..(top + band_rows).min(rows)))?;
+        }
+        log::info!("rendered {}x{} in bands of {} pixel rows on {} threads in {:.2?}", width, height, band_rows * TILE_SIZE, self.threads.max(1), start.elapsed());
+        Ok(())
+    }
+    
+    /// Render every tile in the frame's rows with the given range of samples per pixel, as (tile, pixels) in any order
+    /// Once `deadline` (a start time and a limit) has passed, workers take no further tiles
+    fn render_tiles(&self, frame: &Frame, samples: Range<usize>, deadline: Option<(&Stopwatch, Duration)>) -> Vec<(Tile, Vec<(Vec3, Float)>)> {
+        let mut tiles = self.tiles(frame.size);
+        tiles.retain(|tile| frame.rows.contains(&(tile.top / TILE_SIZE)));
+        let next_tile = AtomicUsize::new(0);
+        let next = || {
+            let expired = deadline.is_some_and(|(start, limit)| start.elapsed() >= limit);
+            if expired { None } else { tiles.get(next_tile.fetch_add(1, Ordering::Relaxed)).copied() }
+        };
+        let workers = self.threads.clamp(1, tiles.len().max(1));
+        
+        // A single worker renders on the calling thread (wasm32 can't spawn threads)
+        if workers == 1 {
+            let mut scratch = Scratch::default();
+            return std::iter::from_fn(next).map(|tile| (tile, self.render_tile(frame, tile, samples.clone(), &mut scratch))).collect();
+        }
+        std::thread::scope(|s| {
+            let workers: Vec<_> = (0..workers)
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut done = Vec::new();
+                        let mut scratch = Scratch::default();
+                        while let Some(tile) = next() {
+                            done.push((tile, self.render_tile(frame, tile, samples.clone(), &mut scratch)));
+                        }
+                        done
+                    })
+                })
+                .collect();
+            workers.into_iter().flat_map(|worker| worker.join().expect("render thread panicked")).collect()
+        })
+    }
+    
+    /// The image's tiles in `tile_order`
+    fn tiles(&self, (width, height): (u32, u32)) -> Vec<Tile> {
+        let (columns, rows) = (width.div_ceil(TILE_SIZE), height.div_ceil(TILE_SIZE));
+        let mut tiles: Vec<Tile> = (0..columns * rows)
+            .map(|id| {
+                let (left, top) = (id % columns * TILE_SIZE, id / columns * TILE_SIZE);
+                Tile { id, left, top, right: (left + TILE_SIZE).min(width), bottom: (top + TILE_SIZE).min(height) }
+            })
+            .collect();
+        
+        match self.tile_order {
+            TileOrder::Scanline => {}
+            TileOrder::Spiral => {
+                // Rings of tiles around the center (in half-tile units), each walked by angle
+                let key = |tile: &Tile| {
+                    let (column, row) = (tile.id % columns, tile.id / columns);
+                    let (dx, dy) = ((2 * column + 1) as i64 - columns as i64, (2 * row + 1) as i64 - rows as i64);
+                    (dx.abs().max(dy.abs()), (dy as f64).atan2(dx as f64))
+                };
+                tiles.sort_by(|a, b| {
+                    let (a, b) = (key(a), key(b));
+                    a.0.cmp(&b.0).then(a.1.total_cmp(&b.1))
+                });
+            }
+            TileOrder::Hilbert => {
+                let n = columns.max(rows).next_power_of_two();
+                tiles.sort_by_key(|tile| hilbert_index(n, tile.id % columns, tile.id / columns));
+            }
+            TileOrder::Random => {
+                let mut rng = Rng::new(self.seed ^ 0x7113_0d3e_5eed_0001);
+                for i in (1..tiles.len()).rev() {
+                    let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+                    tiles.swap(i, j);
+                }
+            }
+        }
+        tiles
+    }
+    
+    /// Render one tile, averaging the given range of samples of each pixel
+    /// Random numbers come from the tile's own stream, so any worker renders it the same way
+    fn render_tile(&self, frame: &Frame, area: Tile, sample_range: Range<usize>, scratch: &mut Scratch) -> Vec<(Vec3, Float)> {
+        let (scene, camera, offsets) = (frame.scene, frame.camera, &frame.offsets);
+        let start = Stopwatch::start();
+        let tile_width = area.right - area.left;
+        let size = self.packet_size.max(1);
+        let stream = Rng::for_tile(self.seed, area.id);
+        let spread = camera.pixel_spread(frame.size.1);
+        let t_max = camera.clip_range().min(self.max_distance);
+        let mut pixels = vec![(Vec3::zero(), 0.0); (tile_width * (area.bottom - area.top)) as usize];
+        let Scratch { packet, rays, samples } = scratch;
+        samples.resize_with((size * size) as usize, Vec::new);
+        
+        // Each sample's camera rays through a packet of pixels are intersected together, then traced on their own
+        for packet_top in (area.top..area.bottom).step_by(size as usize) {
+            for packet_left in (area.left..area.right).step_by(size as usize) {
+                packet.clear();
+                for y in packet_top..(packet_top + size).min(area.bottom) {
+                    let row = (packet_left..(packet_left + size).min(area.right)).map(|x| (x, y));
+                    packet.extend(row.filter(|&pixel| interleave::renders(self.interleave, pixel)));
+                }
+                samples.iter_mut().for_each(Vec::clear);
+                
+                for (sample, &offset) in offsets.iter().enumerate().take(sample_range.end).skip(sample_range.start) {
+                    rays.clear();
+                    rays.extend(packet.iter().map(|&pixel| pixel_ray(camera, pixel, offset, frame.size)));
+                    for (i, (ray, hit)) in rays.iter().zip(scene.intersect_packet(rays, RayKind::Camera, t_max)).enumerate() {
+                        let mut context = SampleContext {
+                            rng: stream.split(area.sample_index(packet[i], sample, offsets.len())),
+                            lights: &frame.lights,
+                            spread,
+                            travelled: 0.0,
+                            interior: Interior::default(),
+                        };
+                        samples[i].push(if let Some(view) = self.debug_view {
+                            self.debug_sample(view, ray, hit, scene, camera, &mut context)
+                        } else if self.path_tracing {
+                            self.trace_path(ray, hit, scene, &mut context)
+                        } else {
+                            self.trace_hit(ray, hit, scene, 0, RayKind::Camera, &mut context)
+                        });
+                    }
+                }
+                for (i, &(x, y)) in packet.iter().enumerate() {
+                    pixels[((y - area.top) * tile_width + x - area.left) as usize] = average_samples(&samples[i], self.outlier_rejection);
+                }
+            }
+        }
+        
+        stats::flush();
+        if let Some(progress) = &self.progress {
+            progress.pixels_done.fetch_add(pixels.len() as u64, Ordering::Relaxed);
+        }
+        log::debug!("tile {} ({}, {}) rendered in {:.1?}", area.id, area.left, area.top, start.elapsed());
+        pixels
+    }
+    
+    /// Normals and albedos of the first surface seen through each pixel, to guide `Denoiser`
+    /// Uses the same sample positions as the render, up to GUIDE_SAMPLES of them
+    pub fn render_guides(&self, scene: &Scene, camera: &Camera, width: u32, height: u32) -> Guides {
+        let offsets = sample_offsets(self.samples.min(GUIDE_SAMPLES));
+        let count = offsets.len() as Float;
+        let spread = camera.pixel_spread(height);
+        let t_max = camera.clip_range().min(self.max_distance);
+        let mut guides = Guides::new(width, height);
         
         for y in 0..height {
             for x in 0..width {
-                let u = x as f64 / width as f64;
-                let v = (height - 1 - y) as f64 / height as f64; // Flip Y coordinate
-                
-                let ray = camera.get_ray(u, v);
-                let color = self.trace_ray(&ray, scene, 0);
-                
-                // Convert color to RGB bytes
-                let r = (255.0 * color.x.min(1.0).max(0.0)) as u8;
-                let g = (255.0 * color.y.min(1.0).max(0.0)) as u8;
-                let b = (255.0 * color.z.min(1.0).max(0.0)) as u8;
-                
-                writer.write_pixel(r, g, b);
+                let (mut normal, mut albedo) = (Vec3::zero(), Vec3::zero());
+                for &offset in &offsets {
+                    let ray = pixel_ray(camera, (x, y), offset, (width, height));
+                    if let Some(mut hit) = scene.intersect_within(&ray, RayKind::Camera, t_max) {
+                        hit.apply_textures(ray.direction, spread * (hit.point - ray.origin).length());
+                        let material = self.material_override.as_ref().unwrap_or(&hit.material);
+                        normal += hit.normal;
+                        albedo += Vec3::from(material.albedo);
+                    }
+                }
+                guides.set(x, y, normal / count, albedo / count);
             }
         }
+        guides
+    }
+    
+    /// Depth of the first surface seen through each pixel (see `DepthMap`), the nearest at any of the render's
+    /// sample positions, so edges take the foreground's depth rather than a blend that no surface has
+    pub fn render_depth(&self, scene: &Scene, camera: &Camera, width: u32, height: u32) -> DepthMap {
+        let offsets = sample_offsets(self.samples);
+        let t_max = camera.clip_range().min(self.max_distance);
+        let mut depths = DepthMap::new(width, height);
         
-        writer
+        for y in 0..height {
+            for x in 0..width {
+                let nearest = offsets
+                    .iter()
+                    .filter_map(|&offset| scene.intersect_within(&pixel_ray(camera, (x, y), offset, (width, height)), RayKind::Camera, t_max))
+                    .fold(Float::INFINITY, |nearest, hit| nearest.min(hit.t));
+                depths.set(x, y, camera.near + nearest); // Camera rays start at the near clip
+            }
+        }
+        depths
     }
     
-    /// Trace a ray through the scene
-    fn trace_ray(&self, ray: &Ray, scene: &Scene, depth: u32) -> Vec3 {
+    /// Trace a ray through the scene, returning its color (premultiplied) and alpha
+    /// - kind: camera or reflection ray, selecting which objects it can see
+    fn trace_ray(&self, ray: &Ray, scene: &Scene, depth: u32, kind: RayKind, context: &mut SampleContext) -> (Vec3, Float) {
         if depth >= self.max_depth {
-            return Vec3::zero();
+            trace::record(|| TraceEvent::End("max depth reached, black"));
+            return (Vec3::zero(), 1.0);
         }
         
-        if let Some(hit) = scene.intersect(ray) {
-            // Lambertian shading with hard shadows
-            let mut color = Vec3::zero();
+        let hit = scene.intersect_within(ray, kind, self.max_distance);
+        let (ray, hit, transmittance) = self.skip_false_hits(ray.clone(), hit, scene, kind, context);
+        let (color, alpha) = self.trace_hit(&ray, hit, scene, depth, kind, context);
+        (color * transmittance, alpha)
+    }
+    
+    /// The rest of `trace_ray` once the ray's closest hit is known
+    fn trace_hit(&self, ray: &Ray, mut hit: Option<HitInfo>, scene: &Scene, depth: u32, kind: RayKind, context: &mut SampleContext) -> (Vec3, Float) {
+        if depth >= self.max_depth {
+            return (Vec3::zero(), 1.0);
+        }
+        let travelled = context.travelled;
+        trace::record(|| TraceEvent::Ray { depth, kind, origin: ray.origin, direction: ray.direction });
+        trace::record(|| hit.as_ref().map_or(TraceEvent::Miss, TraceEvent::hit));
+        if let Some(hit) = &mut hit {
+            self.prepare_hit(ray, hit, context);
+        }
+        
+        let (color, alpha) = match &hit {
+            Some(hit) if hit.material.shadow_catcher => self.shade_shadow_catcher(ray, scene, hit, depth, kind, context),
+            Some(hit) => self.shade(ray, scene, hit, depth, kind, context),
+            // Only directly visible background can be transparent, reflections still show it
+            None if self.transparent_background && kind == RayKind::Camera => (Vec3::zero(), 0.0),
+            None => (scene.background(ray.direction), 1.0),
+        };
+        context.travelled = travelled;
+        
+        let (color, alpha) = if scene.media.is_empty() {
+            (color, alpha)
+        } else {
+            let t_max = hit.map_or(Float::INFINITY, |hit| hit.t);
+            self.apply_media(ray, scene, t_max, color, alpha, context)
+        };
+        trace::record(|| TraceEvent::Shaded { depth, color, alpha });
+        (color, alpha)
+    }
+    
+    /// Ready a hit for shading and advance the path to it: the override material replaces the hit's own,
+    /// or else its textures are filtered for the footprint the ray's cone has widened to
+    fn prepare_hit(&self, ray: &Ray, hit: &mut HitInfo, context: &mut SampleContext) {
+        context.travelled += (hit.point - ray.origin).length();
+        match &self.material_override {
+            Some(material) => hit.material = material.clone(),
+            None => hit.apply_textures(ray.direction, context.spread * context.travelled),
+        }
+    }
+    
+    /// Shade a surface hit: Lambertian lighting with shadows, plus transparency and reflection
+    fn shade(&self, ray: &Ray, scene: &Scene, hit: &HitInfo, depth: u32, kind: RayKind, context: &mut SampleContext) -> (Vec3, Float) {
+        // Lambertian shading with hard shadows
+        let mut color = Vec3::zero();
+        
+        context.lights.pick(&scene.lights, &mut context.rng, |light, weight, _| {
+            if !light.links.illuminates(hit.object) {
+                trace::record(|| TraceEvent::Unlit { light: trace::light_index(scene, light), reason: "not linked to the object" });
+                return;
+            }
+            let light_dir = (light.position - hit.point).normalize();
+            let light_distance = (light.position - hit.point).length();
+            
+            if hit.material.is_translucent() {
+                color += self.shade_translucent(scene, hit, light, light_dir, light_distance) * weight;
+                return;
+            }
             
-            for light in &scene.lights {
-                let light_dir = (light.position - hit.point).normalize();
-                let light_distance = (light.position - hit.point).length();
-                let light_intensity = hit.normal.dot(&light_dir).max(0.0);
+            let light_intensity = hit.normal.dot(&light_dir).max(0.0);
+            
+            // Only add light contribution if surface faces the light
+            if light_intensity > 0.0 {
+                // Cast shadow ray to check for occlusion
+                let visibility = self.visibility_of(scene, light, hit.offset_origin(light_dir), light_dir, light_distance);
+                trace::record(|| TraceEvent::Shadow { light: trace::light_index(scene, light), visibility });
                 
-                // Only add light contribution if surface faces the light
-                if light_intensity > 0.0 {
-                    // Cast shadow ray to check for occlusion
-                    let shadow_ray_origin = hit.point + hit.normal * self.epsilon; // Bias to avoid self-intersection
-                    let shadow_ray = Ray::new(shadow_ray_origin, light_dir);
-                    
-                    let mut in_shadow = false;
-                    
-                    // Check if shadow ray hits any object before reaching the light
-                    if let Some(shadow_hit) = scene.intersect(&shadow_ray) {
-                        // If we hit something closer than the light, we're in shadow
-                        if shadow_hit.t < light_distance - self.epsilon {
-                            in_shadow = true;
-                        }
-                    }
-                    
-                    // Only add light contribution if not in shadow
-                    if !in_shadow {
-                        let light_contribution = Vec3::new(
-                            hit.material.albedo.x * light.color.x,
-                            hit.material.albedo.y * light.color.y,
-                            hit.material.albedo.z * light.color.z,
-                        ) * light.intensity * light_intensity;
-                        color = color + light_contribution;
-                    }
+                // Only add light contribution if not in shadow
+                if visibility != Vec3::zero() {
+                    let light_contribution = Vec3::from(hit.material.albedo * light.color) * visibility * light.intensity_at(hit.point) * light_intensity;
+                    color += light_contribution * weight;
+                }
+            } else {
+                trace::record(|| TraceEvent::Unlit { light: trace::light_index(scene, light), reason: "behind the surface" });
+            }
+        });
+        
+        // Add small ambient light to prevent completely black shadows
+        color += Vec3::from(hit.material.albedo) * 0.1 + Vec3::from(hit.material.emission);
+        let mut alpha = 1.0;
+        
+        if hit.material.is_transparent() {
+            // See through the surface, bent if it refracts: blend with whatever lies behind it
+            let (behind, behind_alpha) = if hit.material.is_refractive() {
+                self.trace_refraction(ray, scene, hit, depth, kind, context)
+            } else {
+                trace::record(|| TraceEvent::Bounce("transparent, seeing through"));
+                let behind_ray = Ray::new(hit.offset_origin(ray.direction), ray.direction);
+                self.trace_ray(&behind_ray, scene, depth + 1, kind, context)
+            };
+            let transmission = hit.material.transmission;
+            color = color * (Vec3::new(1.0, 1.0, 1.0) - transmission) + behind * transmission;
+            
+            // Coverage drops by how much of the (possibly transparent) background shows through
+            let average_transmission = (transmission.x + transmission.y + transmission.z) / 3.0;
+            alpha = 1.0 - average_transmission * (1.0 - behind_alpha);
+        }
+        
+        if self.reflections && hit.material.reflectivity > 0.0 {
+            // Mirror reflection blended by reflectivity
+            trace::record(|| TraceEvent::Bounce("reflection"));
+            let reflected_dir = ray.direction.normalize().reflect(&hit.normal);
+            let reflected_ray = Ray::new(hit.offset_origin(reflected_dir), reflected_dir);
+            let (reflected, _) = self.trace_ray(&reflected_ray, scene, depth + 1, RayKind::Reflection, context);
+            let reflectivity = hit.material.reflectivity;
+            color = color * (1.0 - reflectivity) + reflected * reflectivity;
+            alpha = alpha * (1.0 - reflectivity) + reflectivity;
+        }
+        
+        if self.reflections && hit.material.clearcoat > 0.0 {
+            // Clearcoat on top of it all: a sharp reflection, strongest at grazing angles
+            let direction = ray.direction.normalize();
+            let coat = hit.material.clearcoat.min(1.0) * fresnel(-direction.dot(&hit.normal));
+            trace::record(|| TraceEvent::Bounce("clearcoat reflection"));
+            let reflected_dir = direction.reflect(&hit.normal);
+            let reflected_ray = Ray::new(hit.offset_origin(reflected_dir), reflected_dir);
+            let (reflected, _) = self.trace_ray(&reflected_ray, scene, depth + 1, RayKind::Reflection, context);
+            color = color * (1.0 - coat) + reflected * coat;
+            alpha = alpha * (1.0 - coat) + coat;
+        }
+        
+        (color, alpha)
+    }
+    
+    /// Light a refractive surface passes on from beyond it, bent by its index of refraction, plus what it reflects
+    /// by Fresnel; a dispersive surface bends each color channel by its own index, one ray per channel
+    fn trace_refraction(&self, ray: &Ray, scene: &Scene, hit: &HitInfo, depth: u32, kind: RayKind, context: &mut SampleContext) -> (Vec3, Float) {
+        let direction = ray.direction.normalize();
+        let mirrored = direction.reflect(&hit.normal);
+        trace::record(|| TraceEvent::Bounce("Fresnel reflection off a refractive surface"));
+        let (reflected, _) = self.trace_ray(&Ray::new(hit.offset_origin(mirrored), mirrored), scene, depth + 1, RayKind::Reflection, context);
+        
+        // Bent by the indices inside and beyond, the latter from the medium around the object
+        let (inside, outside) = (hit.material.ior, context.interior.outside_ior(hit.object));
+        let bands = if hit.material.is_dispersive() || outside.x != outside.y || outside.y != outside.z {
+            vec![(0, Vec3::unit_x()), (1, Vec3::unit_y()), (2, Vec3::unit_z())]
+        } else {
+            vec![(1, Vec3::new(1.0, 1.0, 1.0))]
+        };
+        let (mut color, mut alpha) = (Vec3::zero(), 0.0);
+        for (channel, band) in bands {
+            let ratio = if hit.front_face { outside[channel] / inside[channel] } else { inside[channel] / outside[channel] };
+            let (reflectance, refracted) = refract(-direction, hit.normal, ratio);
+            let (through, through_alpha) = match refracted {
+                Some(refracted) => {
+                    trace::record(|| TraceEvent::Bounce("refraction"));
+                    let interior = context.interior.clone();
+                    context.interior.cross(hit, scene.objects[hit.object].flags.priority);
+                    let through = self.trace_ray(&Ray::new(hit.offset_origin(refracted), refracted), scene, depth + 1, kind, context);
+                    context.interior = interior;
+                    through
+                }
+                None => {
+                    trace::record(|| TraceEvent::End("total internal reflection, nothing refracted"));
+                    (Vec3::zero(), 0.0)
                 }
+            };
+            color += (through * (1.0 - reflectance) + reflected * reflectance) * band;
+            alpha += (through_alpha * (1.0 - reflectance) + reflectance) * (band.x + band.y + band.z) / 3.0;
+        }
+        (color, alpha)
+    }
+    
+    /// Shadow catcher: shows whatever lies behind the surface, darkened by the shadows it receives
+    /// and overlaid with reflections of other objects
+    /// With a transparent background this leaves alpha only where shadows and reflections land
+    fn shade_shadow_catcher(&self, ray: &Ray, scene: &Scene, hit: &HitInfo, depth: u32, kind: RayKind, context: &mut SampleContext) -> (Vec3, Float) {
+        let mut received = Vec3::zero();
+        let mut unoccluded = 0.0;
+        
+        context.lights.pick(&scene.lights, &mut context.rng, |light, weight, _| {
+            if !light.links.illuminates(hit.object) {
+                return;
+            }
+            let light_dir = (light.position - hit.point).normalize();
+            let light_distance = (light.position - hit.point).length();
+            let light_intensity = hit.normal.dot(&light_dir).max(0.0) * light.intensity_at(hit.point) * weight;
+            
+            if light_intensity > 0.0 {
+                let visibility = self.visibility_of(scene, light, hit.offset_origin(light_dir), light_dir, light_distance);
+                trace::record(|| TraceEvent::Shadow { light: trace::light_index(scene, light), visibility });
+                received += visibility * light_intensity;
+                unoccluded += light_intensity;
+            }
+        });
+        
+        // Fraction of the light that would have arrived with nothing in the way
+        let lit_fraction = if unoccluded > 0.0 {
+            received / unoccluded
+        } else {
+            Vec3::new(1.0, 1.0, 1.0)
+        };
+        
+        trace::record(|| TraceEvent::Bounce("shadow catcher, seeing through"));
+        let behind_ray = Ray::new(hit.offset_origin(ray.direction), ray.direction);
+        let (behind, behind_alpha) = self.trace_ray(&behind_ray, scene, depth + 1, kind, context);
+        let mut color = behind * lit_fraction;
+        
+        // The shadow acts as a black layer whose opacity is the fraction of light blocked
+        let shadow_opacity = 1.0 - (lit_fraction.x + lit_fraction.y + lit_fraction.z) / 3.0;
+        let mut alpha = shadow_opacity + behind_alpha * (1.0 - shadow_opacity);
+        
+        if self.reflections && hit.material.reflectivity > 0.0 {
+            // Only reflections of actual objects show up on the catcher
+            let reflected_dir = ray.direction.normalize().reflect(&hit.normal);
+            let reflected_ray = Ray::new(hit.offset_origin(reflected_dir), reflected_dir);
+            if scene.intersect_within(&reflected_ray, RayKind::Reflection, self.max_distance).is_some() {
+                trace::record(|| TraceEvent::Bounce("shadow catcher reflection"));
+                let (reflected, _) = self.trace_ray(&reflected_ray, scene, depth + 1, RayKind::Reflection, context);
+                let reflectivity = hit.material.reflectivity;
+                color = color * (1.0 - reflectivity) + reflected * reflectivity;
+                alpha = alpha * (1.0 - reflectivity) + reflectivity;
+            }
+        }
+        
+        (color, alpha)
+    }
+    
+    /// Per-channel fraction of a light reaching `origin`: zero if an opaque surface blocks it,
+    /// otherwise filtered by every transparent surface crossed and by participating media
+    fn light_visibility(&self, scene: &Scene, origin: Vec3, light_dir: Vec3, light_distance: Float) -> Vec3 {
+        let mut visibility = Vec3::new(1.0, 1.0, 1.0);
+        let mut shadow_ray = Ray::new(origin, light_dir);
+        let mut remaining = light_distance.min(self.max_distance);
+        
+        // Check if shadow ray hits any object before reaching the light (or the furthest rays see)
+        while let Some(shadow_hit) = scene.intersect_within(&shadow_ray, RayKind::Shadow, remaining) {
+            // Hits beyond the light don't cast shadows
+            if shadow_hit.t >= remaining {
+                break;
+            }
+            
+            // Opaque occluders block the light, transparent ones tint it and let the ray continue
+            let material = self.material_override.as_ref().unwrap_or(&shadow_hit.material);
+            if !material.is_transparent() {
+                return Vec3::zero();
             }
             
-            // Add small ambient light to prevent completely black shadows
-            let ambient = Vec3::new(
-                hit.material.albedo.x * 0.1,
-                hit.material.albedo.y * 0.1,
-                hit.material.albedo.z * 0.1,
-            );
-            color = color + ambient;
+            visibility *= material.transmission;
             
-            color
+            let origin = shadow_hit.offset_origin(light_dir);
+            remaining -= (origin - shadow_ray.origin).dot(&light_dir);
+            shadow_ray = Ray::new(origin, light_dir);
+        }
+        
+        visibility * scene.media_transmittance(&Ray::new(origin, light_dir), light_distance)
+    }
+    
+    /// `light_visibility` toward a light, without tracing shadow rays if it casts no shadows (media still dim it)
+    fn visibility_of(&self, scene: &Scene, light: &Light, origin: Vec3, light_dir: Vec3, light_distance: Float) -> Vec3 {
+        if light.casts_shadows {
+            return self.light_visibility(scene, origin, light_dir, light_distance);
+        }
+        let transmittance = scene.media_transmittance(&Ray::new(origin, light_dir), light_distance);
+        Vec3::new(transmittance, transmittance, transmittance)
+    }
+    
+    /// Subsurface scattering approximation for translucent materials
+    /// Wrap lighting softens the terminator per channel, and back-lit points receive light
+    /// transmitted through the object's interior, attenuated by thickness / mean free path
+    fn shade_translucent(&self, scene: &Scene, hit: &HitInfo, light: &Light, light_dir: Vec3, light_distance: Float) -> Vec3 {
+        let mfp = hit.material.mean_free_path;
+        let cos_theta = hit.normal.dot(&light_dir);
+        
+        // Longer mean free paths wrap light further around the terminator
+        let wrap = |mfp: Float| mfp.max(0.0) / (1.0 + mfp.max(0.0));
+        let wrapped = |mfp: Float| ((cos_theta + wrap(mfp)) / (1.0 + wrap(mfp))).max(0.0);
+        let mut diffuse = Vec3::new(wrapped(mfp.x), wrapped(mfp.y), wrapped(mfp.z));
+        
+        let visibility = if cos_theta > 0.0 {
+            self.visibility_of(scene, light, hit.offset_origin(light_dir), light_dir, light_distance)
         } else {
-            scene.background_color
+            // Light arrives from behind: march through the object to where it exits toward the light
+            let inner_ray = Ray::new(hit.offset_origin(light_dir), light_dir);
+            match scene.intersect(&inner_ray) {
+                Some(exit) if exit.t < light_distance => {
+                    let thickness = (exit.point - hit.point).length();
+                    let transmitted = |mfp: Float| if mfp > 0.0 { (-thickness / mfp).exp() } else { 0.0 };
+                    diffuse += Vec3::new(transmitted(mfp.x), transmitted(mfp.y), transmitted(mfp.z)) * -cos_theta;
+                    
+                    self.visibility_of(scene, light, exit.offset_origin(light_dir), light_dir, light_distance - thickness)
+                }
+                _ => self.visibility_of(scene, light, hit.offset_origin(light_dir), light_dir, light_distance),
+            }
+        };
+        
+        Vec3::from(hit.material.albedo * light.color) * diffuse * visibility * light.intensity_at(hit.point)
+    }
+    
+    /// Attenuate `color` by the media along the ray and add single-scattered light from each light source
+    /// Scattered light is opaque, so alpha rises as the background fades out
+    fn apply_media(&self, ray: &Ray, scene: &Scene, t_max: Float, color: Vec3, alpha: Float, context: &mut SampleContext) -> (Vec3, Float) {
+        let ray_length = ray.direction.length();
+        let view_dir = ray.direction / ray_length;
+        let mut result = color;
+        let mut result_alpha = alpha;
+        
+        for medium in &scene.media {
+            let (t_enter, t_exit) = match medium.segment(ray, t_max) {
+                Some(segment) => segment,
+                None => continue,
+            };
+            
+            // Ray-march the segment, treating in-scattered light as constant within each step
+            let step = (t_exit - t_enter) / self.volume_steps as Float;
+            let step_transmittance = (-medium.density * step * ray_length).exp();
+            let mut transmittance = 1.0;
+            let mut scattered = Vec3::zero();
+            
+            for i in 0..self.volume_steps {
+                let point = ray.at(t_enter + (i as Float + 0.5) * step);
+                
+                // Same small ambient term as surfaces so shadowed fog isn't pitch black
+                let mut in_light = Vec3::new(0.1, 0.1, 0.1);
+                context.lights.pick(&scene.lights, &mut context.rng, |light, weight, _| {
+                    let light_dir = (light.position - point).normalize();
+                    let light_distance = (light.position - point).length();
+                    let visibility = self.visibility_of(scene, light, point, light_dir, light_distance);
+                    let phase = medium.phase.evaluate(view_dir.dot(&light_dir));
+                    in_light += Vec3::from(light.color) * visibility * light.intensity_at(point) * phase * weight;
+                });
+                
+                scattered += medium.albedo * in_light * transmittance * (1.0 - step_transmittance);
+                transmittance *= step_transmittance;
+            }
+            
+            result = result * transmittance + scattered;
+            result_alpha = result_alpha * transmittance + (1.0 - transmittance);
+        }
+        
+        (result, result_alpha)
+    }
+}
+
+/// Average a pixel's samples (premultiplied colors average correctly together with alpha)
+/// With `outlier_sigmas`, samples whose luminance lies further than that many standard deviations
+/// above the mean are left out, trading a little energy for the removal of isolated fireflies
+fn average_samples(samples: &[(Vec3, Float)], outlier_sigmas: Option<Float>) -> (Vec3, Float) {
+    let count = samples.len() as Float;
+    let limit = outlier_sigmas.map_or(Float::INFINITY, |sigmas| {
+        let mean = samples.iter().map(|(color, _)| luminance(*color)).sum::<Float>() / count;
+        let variance = samples.iter().map(|(color, _)| (luminance(*color) - mean).powi(2)).sum::<Float>() / count;
+        mean + sigmas * variance.sqrt()
+    });
+    
+    let (mut color, mut alpha, mut kept) = (Vec3::zero(), 0.0, 0.0);
+    for &(sample_color, sample_alpha) in samples.iter().filter(|(color, _)| luminance(*color) <= limit) {
+        color += sample_color;
+        alpha += sample_alpha;
+        kept += 1.0;
+    }
+    (color / kept, alpha / kept)
+}
+
+/// Distance of cell (x, y) along the Hilbert curve that fills an n × n grid (n a power of two)
+fn hilbert_index(n: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut index = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let (rx, ry) = (((x & s) > 0) as u32, ((y & s) > 0) as u32);
+        index += s as u64 * s as u64 * ((3 * rx) ^ ry) as u64;
+        // Rotate the quadrant so the curve inside it starts and ends where the next level expects
+        if ry == 0 {
+            if rx == 1 {
+                (x, y) = (n - 1 - x, n - 1 - y);
+            }
+            (x, y) = (y, x);
+        }
+        s /= 2;
+    }
+    index
+}
+
+/// Camera ray through a sub-pixel position of pixel (x, y), counted from the top-left
+fn pixel_ray(camera: &Camera, (x, y): (u32, u32), (dx, dy): (Float, Float), (width, height): (u32, u32)) -> Ray {
+    let u = (x as Float + dx) / width as Float;
+    let v = ((height - 1 - y) as Float + dy) / height as Float; // Flip Y coordinate
+    camera.get_ray(u, v)
+}
+
+/// Sub-pixel sample positions in [0, 1)², on a stratified grid
+/// A single sample stays at the pixel corner, as the renderer has always used
+fn sample_offsets(samples: u32) -> Vec<(Float, Float)> {
+    let samples = samples.max(1);
+    if samples == 1 {
+        return vec![(0.0, 0.0)];
+    }
+    
+    let columns = (samples as Float).sqrt().ceil() as u32;
+    let rows = samples.div_ceil(columns);
+    (0..samples)
+        .map(|i| ((i % columns) as Float + 0.5) / columns as Float)
+        .zip((0..samples).map(|i| ((i / columns) as Float + 0.5) / rows as Float))
+        .collect()
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::scene::LightLinks;
+    use crate::scenes;
+    
+    /// RGB bytes of the center pixel of a 3×3 render
+    fn center_pixel(scene: &Scene, camera: &Camera) -> [u8; 3] {
+        let image = Renderer::new().render(scene, camera, 3, 3);
+        [image.pixels()[12], image.pixels()[13], image.pixels()[14]]
+    }
+    
+    #[test]
+    fn test_translucent_back_lighting() {
+        // A small sphere lit from straight behind: wax glows on the side facing the camera, where matte of the same
+        // albedo only shows the ambient light
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 4.0), Vec3::zero(), Vec3::unit_y(), 10.0, 1.0);
+        let brightness = |material: Material| {
+            let mut scene = Scene::new();
+            scene.add_object(Box::new(crate::shapes::Sphere::new(Vec3::zero(), 0.3, material)));
+            scene.add_light(Light::white_light(Vec3::new(0.0, 0.0, -5.0), 1.0));
+            center_pixel(&scene, &camera).iter().map(|&channel| channel as u32).sum::<u32>()
+        };
+        let (wax, matte) = (brightness(Material::wax()), brightness(Material { mean_free_path: Vec3::zero(), ..Material::wax() }));
+        assert!(wax > 2 * matte, "{} vs {}", wax, matte);
+    }
+    
+    #[test]
+    fn test_colored_shadows() {
+        // A light shining through a green pane onto a white floor casts a green shadow rather than a black one
+        let camera = Camera::new(Vec3::new(0.0, 1.0, 2.0), Vec3::zero(), Vec3::unit_y(), 20.0, 1.0);
+        let mut scene = Scene::new();
+        scene.add_object(Box::new(crate::shapes::Plane::horizontal(0.0, Material::white())));
+        let pane = Material::transparent(Material::white().albedo, Vec3::new(0.1, 0.8, 0.1));
+        scene.add_object(Box::new(crate::shapes::Cube::new(Vec3::new(-1.0, 2.0, -1.0), Vec3::new(1.0, 2.1, 1.0), pane)));
+        scene.add_light(Light::white_light(Vec3::new(0.0, 5.0, 0.0), 1.0));
+        
+        let [red, green, blue] = center_pixel(&scene, &camera);
+        assert!(green > 100 && red < 50 && blue < 50, "{:?}", [red, green, blue]);
+    }
+    
+    #[test]
+    fn test_sample_offsets() {
+        assert_eq!(sample_offsets(0), vec![(0.0, 0.0)]);
+        assert_eq!(sample_offsets(4), vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]);
+        assert_eq!(sample_offsets(3).len(), 3);
+    }
+    
+    #[test]
+    fn test_outlier_rejection() {
+        let mut samples = vec![(Vec3::new(0.5, 0.5, 0.5), 1.0); 15];
+        samples.push((Vec3::new(100.0, 100.0, 100.0), 1.0));
+        
+        let (plain, _) = average_samples(&samples, None);
+        assert!(plain.x > 6.0);
+        let (filtered, alpha) = average_samples(&samples, Some(3.0));
+        assert_eq!((filtered, alpha), (Vec3::new(0.5, 0.5, 0.5), 1.0));
+        
+        // Identical samples are never outliers
+        assert_eq!(average_samples(&samples[..4], Some(0.0)).0, Vec3::new(0.5, 0.5, 0.5));
+    }
+    
+    #[test]
+    fn test_threads_match_single_thread() {
+        let (scene, camera) = scenes::build(3, 1.0, 45.0, 4.0 / 3.0);
+        let mut renderer = Renderer::new();
+        renderer.samples = 2;
+        let single = renderer.render(&scene, &camera, 40, 30);
+        
+        renderer.threads = 3;
+        assert_eq!(renderer.render(&scene, &camera, 40, 30).pixels(), single.pixels());
+    }
+    
+    #[test]
+    fn test_seed_sets_noise() {
+        // Path tracing is noisy: another seed changes the image, but threads never do
+        let (scene, camera) = scenes::build(3, 1.0, 45.0, 4.0 / 3.0);
+        let mut renderer = Renderer::new();
+        renderer.path_tracing = true;
+        let first = renderer.render(&scene, &camera, 40, 30);
+        
+        renderer.seed = 7;
+        let second = renderer.render(&scene, &camera, 40, 30);
+        assert_ne!(second.pixels(), first.pixels());
+        renderer.threads = 3;
+        assert_eq!(renderer.render(&scene, &camera, 40, 30).pixels(), second.pixels());
+    }
+    
+    #[test]
+    fn test_tile_orders() {
+        // Every order covers each tile once and renders the same image; Hilbert steps only between neighbors
+        let (scene, camera) = scenes::build(3, 1.0, 45.0, 4.0 / 3.0);
+        let mut renderer = Renderer::new();
+        renderer.path_tracing = true;
+        let expected = renderer.render(&scene, &camera, 70, 50);
+        
+        for order in ["scanline", "spiral", "hilbert", "random"] {
+            renderer.tile_order = order.parse().unwrap();
+            let mut ids: Vec<u32> = renderer.tiles((64, 64)).iter().map(|tile| tile.id).collect();
+            if order == "hilbert" {
+                for pair in ids.windows(2) {
+                    let (a, b) = ((pair[0] % 4, pair[0] / 4), (pair[1] % 4, pair[1] / 4));
+                    assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1, "{:?}", ids);
+                }
+            }
+            if order == "spiral" {
+                assert!([5, 6, 9, 10].contains(&ids[0]), "{:?}", ids); // A center tile
+            }
+            ids.sort();
+            assert_eq!(ids, (0..16).collect::<Vec<_>>());
+            assert_eq!(renderer.render(&scene, &camera, 70, 50).pixels(), expected.pixels(), "{}", order);
         }
+        
+        // Bands of tile rows rendered apart fit together into the same image
+        let full = renderer.render_framebuffer(&scene, &camera, 70, 50);
+        let upper = renderer.render_tile_rows(&scene, &camera, (70, 50), 0..3);
+        let lower = renderer.render_tile_rows(&scene, &camera, (70, 50), 3..10);
+        assert_eq!((upper.height, lower.height), (48, 2));
+        for (x, y) in (0..50).flat_map(|y| (0..70).map(move |x| (x, y))) {
+            let band = if y < 48 { upper.color(x, y) } else { lower.color(x, y - 48) };
+            assert_eq!(band, full.color(x, y), "({}, {})", x, y);
+        }
+        let mut rows = Vec::new();
+        renderer.render_bands(&scene, &camera, (70, 50), |band| {
+            rows.extend(band.to_rgb8());
+            Ok::<_, ()>(())
+        }).unwrap();
+        assert_eq!(rows, full.to_rgb8());
+    }
+    
+    #[test]
+    fn test_interleave() {
+        // Traced pixels match the full render exactly; the interpolated ones come close
+        let (scene, camera) = scenes::build(3, 1.0, 45.0, 4.0 / 3.0);
+        let mut renderer = Renderer::new();
+        let full = renderer.render_framebuffer(&scene, &camera, 41, 30);
+        
+        for interleave in [2, 4] {
+            renderer.interleave = interleave;
+            let draft = renderer.render_framebuffer(&scene, &camera, 41, 30);
+            let mut error = 0.0;
+            for (x, y) in (0..30).flat_map(|y| (0..41).map(move |x| (x, y))) {
+                if interleave::renders(interleave, (x, y)) {
+                    assert_eq!(draft.color(x, y), full.color(x, y));
+                }
+                error += Vec3::from(draft.color(x, y) - full.color(x, y)).length();
+            }
+            assert!(error / (41.0 * 30.0) < 0.05, "{} with interleave {}", error / (41.0 * 30.0), interleave);
+        }
+    }
+    
+    #[test]
+    fn test_time_limit() {
+        let (scene, camera) = scenes::build(3, 1.0, 45.0, 4.0 / 3.0);
+        let mut renderer = Renderer::new();
+        renderer.path_tracing = true;
+        renderer.samples = 4;
+        let full = renderer.render(&scene, &camera, 40, 30);
+        
+        // Time to spare: the same sums in the same order
+        renderer.time_limit = Some(Duration::from_secs(3600));
+        assert_eq!(renderer.render(&scene, &camera, 40, 30).pixels(), full.pixels());
+        
+        // No time at all: one sample per pixel, noisier but just as bright on average
+        renderer.time_limit = Some(Duration::ZERO);
+        let rushed = renderer.render(&scene, &camera, 40, 30);
+        assert_ne!(rushed.pixels(), full.pixels());
+        let mean = |image: &PpmWriter| image.pixels().iter().map(|&v| v as f64).sum::<f64>() / image.pixels().len() as f64;
+        assert!((mean(&rushed) / mean(&full) - 1.0).abs() < 0.05, "{} vs {}", mean(&rushed), mean(&full));
+    }
+    
+    #[test]
+    fn test_packets_match_single_rays() {
+        // 41 columns leave partial tiles at the right edge
+        let (scene, camera) = scenes::build(3, 1.0, 45.0, 4.0 / 3.0);
+        let mut renderer = Renderer::new();
+        for path_tracing in [false, true] {
+            renderer.path_tracing = path_tracing;
+            renderer.packet_size = 1;
+            let single = renderer.render(&scene, &camera, 41, 30);
+            for size in [2, 4] {
+                renderer.packet_size = size;
+                assert_eq!(renderer.render(&scene, &camera, 41, 30).pixels(), single.pixels(), "packets of {0}x{0}", size);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_light_sampling_converges() {
+        let mut scene = Scene::new();
+        scene.add_object(Box::new(crate::shapes::Plane::horizontal(0.0, Material::gray())));
+        for i in 0..6 {
+            let x = i as Float - 2.5;
+            scene.add_light(Light::new(Vec3::new(x, 1.0 + 0.3 * i as Float, -2.0), 0.1 * (i + 1) as Float, Color::new(1.0, 0.8, 0.6)));
+        }
+        let camera = Camera::new(Vec3::new(0.0, 2.0, 1.0), Vec3::new(0.0, 0.0, -2.0), Vec3::unit_y(), 60.0, 1.0);
+        let mean = |renderer: &Renderer| {
+            let framebuffer = renderer.render_framebuffer(&scene, &camera, 8, 8);
+            let sum = (0..64).fold(Vec3::zero(), |sum, i| sum + Vec3::from(framebuffer.color(i % 8, i / 8)));
+            sum / 64.0
+        };
+        
+        let mut renderer = Renderer::new();
+        renderer.samples = 256;
+        let exact = mean(&renderer);
+        for strategy in [LightSampling::Power, LightSampling::Uniform] {
+            renderer.light_samples = Some(1);
+            renderer.light_sampling = strategy;
+            let sampled = mean(&renderer);
+            assert!((sampled - exact).length() < 0.02 * exact.length(), "{:?}: {:?} vs {:?}", strategy, sampled, exact);
+        }
+    }
+    
+    #[test]
+    fn test_next_event_estimation() {
+        // A diffuse floor under a sphere light: both estimators converge to the same image,
+        // but connecting to the light directly is far less noisy than waiting for paths to hit it
+        let camera = Camera::new(Vec3::new(0.0, 1.0, 2.0), Vec3::new(0.0, 0.0, 0.0), Vec3::unit_y(), 40.0, 1.0);
+        let mut scene = Scene::new();
+        scene.background_color = Vec3::zero();
+        scene.add_object(Box::new(crate::shapes::Plane::horizontal(0.0, Material::white())));
+        scene.add_light(Light::sphere(Vec3::new(0.0, 4.0, 2.5), 1.0, 1.0, Color::white()));
+        
+        let render = |next_event_estimation: bool| {
+            let mut renderer = Renderer::new();
+            renderer.path_tracing = true;
+            renderer.next_event_estimation = next_event_estimation;
+            renderer.samples = 64;
+            renderer.render_framebuffer(&scene, &camera, 16, 16)
+        };
+        let (connected, unconnected) = (render(true), render(false));
+        
+        let pixels = |framebuffer: &Framebuffer| -> Vec<Float> { (0..256).map(|i| framebuffer.color(i % 16, i / 16).r).collect() };
+        let mean = |values: &[Float]| values.iter().sum::<Float>() / values.len() as Float;
+        let noise = |values: &[Float]| mean(&values.windows(2).map(|pair| (pair[1] - pair[0]).powi(2)).collect::<Vec<_>>());
+        let (connected, unconnected) = (pixels(&connected), pixels(&unconnected));
+        
+        assert!((mean(&connected) - mean(&unconnected)).abs() < 0.1 * mean(&connected), "{} vs {}", mean(&connected), mean(&unconnected));
+        assert!(noise(&connected) * 10.0 < noise(&unconnected), "{} vs {}", noise(&connected), noise(&unconnected));
+    }
+    
+    #[test]
+    fn test_lights_without_shadows() {
+        // A black ceiling between the light and the floor: the light only reaches the floor if it casts no shadows
+        let camera = Camera::new(Vec3::new(0.0, 1.0, 2.0), Vec3::new(0.0, 0.0, 0.0), Vec3::unit_y(), 40.0, 1.0);
+        let render = |ceiling: bool, light: Light, path_tracing: bool| {
+            let mut scene = Scene::new();
+            scene.background_color = Vec3::zero();
+            scene.add_object(Box::new(crate::shapes::Plane::horizontal(0.0, Material::white())));
+            if ceiling {
+                scene.add_object(Box::new(crate::shapes::Plane::horizontal(3.0, Material::new(Color::black()))));
+            }
+            scene.add_light(light);
+            let mut renderer = Renderer::new();
+            renderer.path_tracing = path_tracing;
+            renderer.samples = if path_tracing { 64 } else { 1 };
+            let framebuffer = renderer.render_framebuffer(&scene, &camera, 16, 16);
+            (0..256).map(|i| framebuffer.color(i % 16, i / 16).r).sum::<Float>() / 256.0
+        };
+        
+        let point = Light::white_light(Vec3::new(0.0, 4.0, 0.0), 1.0);
+        let ambient = render(true, point.clone(), false);
+        assert!((ambient - 0.08).abs() < 1e-6, "{}", ambient);
+        assert_eq!(render(true, point.clone().without_shadows(), false), render(false, point, false));
+        
+        let sphere = Light::sphere(Vec3::new(0.0, 4.0, 0.0), 0.5, 1.0, Color::white());
+        let (lit, open) = (render(true, sphere.clone().without_shadows(), true), render(false, sphere.clone(), true));
+        assert!((lit - open).abs() < 0.05 * open, "{} vs {}", lit, open);
+        assert!(render(true, sphere, true) < 0.01 * open);
+    }
+    
+    #[test]
+    fn test_light_linking() {
+        // A floor left out of the light's links looks the same as with no light at all
+        let camera = Camera::new(Vec3::new(0.0, 1.0, 2.0), Vec3::new(0.0, 0.0, 0.0), Vec3::unit_y(), 40.0, 1.0);
+        let render = |light: Option<Light>, path_tracing: bool| {
+            let mut scene = Scene::new();
+            scene.background_color = Vec3::zero();
+            scene.add_object(Box::new(crate::shapes::Plane::horizontal(0.0, Material::white())));
+            scene.lights.extend(light);
+            let mut renderer = Renderer::new();
+            renderer.path_tracing = path_tracing;
+            renderer.samples = if path_tracing { 16 } else { 1 };
+            let framebuffer = renderer.render_framebuffer(&scene, &camera, 8, 8);
+            (0..64).map(|i| framebuffer.color(i % 8, i / 8).r).sum::<Float>() / 64.0
+        };
+        
+        for (radius, path_tracing) in [(0.0, false), (0.5, true)] {
+            let light = Light::sphere(Vec3::new(0.0, 4.0, 0.0), radius, 1.0, Color::white());
+            let unlinked = Light { links: LightLinks::Except(vec![0]), ..light.clone() };
+            assert_eq!(render(Some(unlinked), path_tracing), render(None, path_tracing));
+            assert!(render(Some(light), path_tracing) > render(None, path_tracing) + 0.1);
+        }
+    }
+    
+    #[test]
+    fn test_emission() {
+        // With no lights at all, a glowing ceiling shows its own color, and lights the floor when path traced
+        let mut scene = Scene::new();
+        scene.background_color = Vec3::zero();
+        scene.add_object(Box::new(crate::shapes::Plane::horizontal(0.0, Material::white())));
+        let glow = Material::emissive(Color::black(), Color::new(2.0, 1.0, 0.0));
+        scene.add_object(Box::new(crate::shapes::Plane::new(Vec3::new(0.0, 2.0, 0.0), -Vec3::unit_y(), glow)));
+        
+        let render = |look_at: Vec3, path_tracing: bool| {
+            let camera = Camera::new(Vec3::new(0.0, 1.0, 0.0), look_at, Vec3::unit_z(), 40.0, 1.0);
+            let mut renderer = Renderer::new();
+            renderer.path_tracing = path_tracing;
+            renderer.samples = if path_tracing { 16 } else { 1 };
+            let framebuffer = renderer.render_framebuffer(&scene, &camera, 4, 4);
+            framebuffer.color(2, 2)
+        };
+        for path_tracing in [false, true] {
+            assert_eq!(render(Vec3::new(0.0, 2.0, 0.0), path_tracing), Color::new(2.0, 1.0, 0.0));
+        }
+        let floor = render(Vec3::new(0.0, 0.0, 0.0), true);
+        assert!(floor.r > 0.5 && floor.g > 0.2 && floor.b == 0.0, "{:?}", floor);
+        assert!(render(Vec3::new(0.0, 0.0, 0.0), false).r < 0.1); // Only ambient light in the ray tracer
+    }
+    
+    #[test]
+    fn test_clipping() {
+        // A glowing red ball in front of a blue one, on black
+        let mut scene = Scene::new();
+        scene.background_color = Vec3::zero();
+        for (z, color) in [(-3.0, Color::new(1.0, 0.0, 0.0)), (-6.0, Color::new(0.0, 0.0, 1.0))] {
+            scene.add_object(Box::new(crate::shapes::Sphere::new(Vec3::new(0.0, 0.0, z), 1.0, Material::emissive(Color::black(), color))));
+        }
+        let camera = Camera::new(Vec3::zero(), -Vec3::unit_z(), Vec3::unit_y(), 20.0, 1.0);
+        let render = |camera: &Camera, max_distance: Float| {
+            let mut renderer = Renderer::new();
+            renderer.max_distance = max_distance;
+            renderer.render_framebuffer(&scene, camera, 4, 4).color(2, 2)
+        };
+        
+        assert_eq!(render(&camera, Float::INFINITY), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(render(&camera.clone().with_clipping(4.5, Float::INFINITY), Float::INFINITY), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(render(&camera.clone().with_clipping(4.5, 4.9), Float::INFINITY), Color::black());
+        assert_eq!(render(&camera, 1.5), Color::black());
+        
+        // The depth pass measures from the camera, not from the near clip its rays start at (the pixel looks a
+        // little below the balls' centers, so it sees them a little behind their fronts at 2 and 5)
+        let depth = |camera: &Camera| Renderer::new().render_depth(&scene, camera, 4, 4).depth(2, 2);
+        assert!((2.0..2.1).contains(&depth(&camera)), "{}", depth(&camera));
+        assert!((5.0..5.2).contains(&depth(&camera.clone().with_clipping(4.5, Float::INFINITY))));
+        assert_eq!(depth(&camera.clone().with_clipping(4.5, 4.9)), Float::INFINITY);
+    }
+    
+    #[test]
+    fn test_russian_roulette_is_unbiased() {
+        // Bright walls under a sky bounce light many times; ending dim paths early must not darken the image
+        let mut scene = Scene::new();
+        scene.background_color = Vec3::new(1.0, 1.0, 1.0);
+        scene.add_object(Box::new(crate::shapes::Plane::horizontal(0.0, Material::white())));
+        scene.add_object(Box::new(crate::shapes::Sphere::new(Vec3::new(0.0, 1.0, -3.0), 1.0, Material::white())));
+        let camera = Camera::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, 0.5, -3.0), Vec3::unit_y(), 60.0, 1.0);
+        
+        let render = |min_probability: Float| {
+            let mut renderer = Renderer::new();
+            renderer.path_tracing = true;
+            renderer.roulette_min_probability = min_probability;
+            renderer.samples = 64;
+            let framebuffer = renderer.render_framebuffer(&scene, &camera, 8, 8);
+            (0..64).map(|i| framebuffer.color(i % 8, i / 8).r).sum::<Float>() / 64.0
+        };
+        let (full, roulette) = (render(1.0), render(0.05));
+        assert!((full - roulette).abs() < 0.02 * full, "{} vs {}", full, roulette);
+    }
+    
+    #[test]
+    fn test_small_sphere_light_matches_point_light() {
+        // Light and BSDF sampling of the sphere together (MIS) must add up to the point light's irradiance
+        let camera = Camera::new(Vec3::new(0.0, 1.5, 2.0), Vec3::new(0.0, 0.0, -1.0), Vec3::unit_y(), 60.0, 1.0);
+        let render = |light: Light| {
+            let mut scene = Scene::new();
+            scene.background_color = Vec3::zero();
+            scene.add_object(Box::new(crate::shapes::Plane::horizontal(0.0, Material::with_specular(Color::gray(0.6), 0.4, 8.0))));
+            scene.add_light(light);
+            
+            let mut renderer = Renderer::new();
+            renderer.path_tracing = true;
+            renderer.samples = 64;
+            let framebuffer = renderer.render_framebuffer(&scene, &camera, 8, 8);
+            (0..64).fold(Vec3::zero(), |sum, i| sum + Vec3::from(framebuffer.color(i % 8, i / 8))) / 64.0
+        };
+        
+        let position = Vec3::new(0.5, 2.5, 2.5); // Behind the camera, out of view
+        let point = render(Light::white_light(position, 1.0));
+        let sphere = render(Light::sphere(position, 0.1, 1.0, Color::white()));
+        assert!(point.x > 0.1);
+        assert!((point - sphere).length() < 0.03 * point.length(), "point {:?} vs sphere {:?}", point, sphere);
     }
 }
@@ -0,0 +1,140 @@
+use std::str::FromStr;
+
+use crate::math::{Float, Vec3, Ray};
+use crate::camera::Camera;
+use crate::scene::{Scene, RayKind};
+use crate::shapes::HitInfo;
+use crate::stats;
+use super::{Renderer, SampleContext};
+
+/// Count at which the heat views' color ramp saturates (on a log scale, so 16 is halfway)
+const HEAT_MAX: Float = 256.0;
+
+/// What a debug view shows instead of the shaded image, from each camera ray's first hit (the background is black)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    Normals,     // Shading normal, x, y and z mapped from -1..1 to red, green and blue
+    Uv,          // Texture coordinates, u in red and v in green, repeating every unit
+    Depth,       // White at the camera, mid gray at the point looked at, fading to black far away
+    BvhHeat,     // Objects tested by the ray, from blue (one) through green to red (HEAT_MAX or more)
+    BvhNodes,    // Accelerator nodes the ray entered, on the same scale
+    ShadowCount, // Lights the point is in shadow from, from black (none) through the heat colors to red (all)
+}
+
+impl FromStr for DebugView {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normals" => Ok(Self::Normals),
+            "uv" => Ok(Self::Uv),
+            "depth" => Ok(Self::Depth),
+            "bvh-heat" => Ok(Self::BvhHeat),
+            "bvh-nodes" => Ok(Self::BvhNodes),
+            "shadow-count" => Ok(Self::ShadowCount),
+            _ => Err(format!("unknown debug view '{}' (expected normals, uv, depth, bvh-heat, bvh-nodes or shadow-count)", s)),
+        }
+    }
+}
+
+impl Renderer {
+    /// Color of a camera ray in a debug view, given its closest hit; nothing is shaded or traced further
+    /// (the heat views cast the ray again to count its work)
+    pub(super) fn debug_sample(&self, view: DebugView, ray: &Ray, hit: Option<HitInfo>, scene: &Scene, camera: &Camera, context: &mut SampleContext) -> (Vec3, Float) {
+        if matches!(view, DebugView::BvhHeat | DebugView::BvhNodes) {
+            let (_, counters) = stats::count(|| scene.intersect_within(ray, RayKind::Camera, camera.clip_range().min(self.max_distance)));
+            let count = if view == DebugView::BvhHeat { counters.intersection_tests } else { counters.node_visits } as Float;
+            return match self.heat_max {
+                Some(max) => (Vec3::new(1.0, 1.0, 1.0) * (count / max), 1.0),
+                None if count == 0.0 => (Vec3::zero(), 1.0),
+                None => (heat_color((count.ln() / HEAT_MAX.ln()).min(1.0)), 1.0),
+            };
+        }
+        let Some(mut hit) = hit else {
+            return (Vec3::zero(), 1.0);
+        };
+        self.prepare_hit(ray, &mut hit, context); // Bump maps bend the normals shown
+        
+        let color = match view {
+            DebugView::Normals => (hit.normal + Vec3::new(1.0, 1.0, 1.0)) * 0.5,
+            DebugView::Uv => Vec3::new(hit.uv.0.rem_euclid(1.0), hit.uv.1.rem_euclid(1.0), 0.0),
+            DebugView::Depth => {
+                let depth = camera.near + hit.t;
+                Vec3::new(1.0, 1.0, 1.0) * (camera.target_distance / (camera.target_distance + depth))
+            }
+            DebugView::ShadowCount => {
+                let lights: Vec<_> = scene.lights.iter().enumerate().filter(|(_, light)| light.casts_shadows && light.links.illuminates(hit.object)).collect();
+                let shadowed = lights
+                    .iter()
+                    .filter(|(_, light)| {
+                        let light_dir = (light.position - hit.point).normalize();
+                        let light_distance = (light.position - hit.point).length();
+                        hit.normal.dot(&light_dir) > 0.0 && self.light_visibility(scene, hit.offset_origin(light_dir), light_dir, light_distance) == Vec3::zero()
+                    })
+                    .count();
+                match shadowed {
+                    0 => Vec3::zero(),
+                    n => heat_color(n as Float / lights.len() as Float),
+                }
+            }
+            DebugView::BvhHeat | DebugView::BvhNodes => unreachable!("handled above"),
+        };
+        (color, 1.0)
+    }
+}
+
+/// Blue through cyan, green and yellow to red as `heat` goes from 0 to 1
+fn heat_color(heat: Float) -> Vec3 {
+    let heat = heat.clamp(0.0, 1.0) * 4.0;
+    match heat {
+        h if h < 1.0 => Vec3::new(0.0, h, 1.0),
+        h if h < 2.0 => Vec3::new(0.0, 1.0, 2.0 - h),
+        h if h < 3.0 => Vec3::new(h - 2.0, 1.0, 0.0),
+        h => Vec3::new(1.0, 4.0 - h, 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::shapes::{Plane, Sphere};
+    use crate::scene::Light;
+    use crate::color::Color;
+    
+    #[test]
+    fn test_debug_views() {
+        // A floor lit from straight above, with a ball out of view shading the middle of it
+        let mut scene = Scene::new();
+        scene.add_object(Box::new(Plane::horizontal(0.0, Material::white())));
+        scene.add_object(Box::new(Sphere::new(Vec3::new(0.0, 5.0, 0.0), 1.0, Material::white())));
+        scene.add_light(Light::new(Vec3::new(0.0, 10.0, 0.0), 1.0, Color::white()));
+        let camera = Camera::new(Vec3::new(0.0, 1.0, 4.0), Vec3::zero(), Vec3::unit_y(), 60.0, 1.0);
+        let view = |view: DebugView, (x, y): (u32, u32)| {
+            let mut renderer = Renderer::new();
+            renderer.debug_view = Some(view);
+            renderer.render_framebuffer(&scene, &camera, 8, 8).color(x, y)
+        };
+        
+        // Pixel (4, 3) looks at the point looked at, the origin, in the ball's shadow; the top row sees only sky
+        assert_eq!(view(DebugView::Normals, (4, 3)), Color::new(0.5, 1.0, 0.5));
+        let gray = view(DebugView::Depth, (4, 3));
+        assert!((gray.r - 0.5).abs() < 1e-6 && gray.r == gray.b, "{:?}", gray);
+        assert_eq!(view(DebugView::Depth, (4, 0)), Color::black());
+        assert_eq!(view(DebugView::ShadowCount, (4, 3)), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(view(DebugView::ShadowCount, (0, 7)), Color::black());
+        let heat = view(DebugView::BvhHeat, (4, 3)); // The floor is tested, and the ball only by SIMD builds
+        assert!(heat.r == 0.0 && heat.g <= 0.5 && heat.b == 1.0, "{:?}", heat);
+        
+        // With a heat_max of 1 the gray level is the count itself
+        let mut renderer = Renderer::new();
+        renderer.debug_view = Some(DebugView::BvhHeat);
+        renderer.heat_max = Some(1.0);
+        let tests = renderer.render_framebuffer(&scene, &camera, 8, 8).color(4, 3);
+        assert!((tests.r == 1.0 || tests.r == 2.0) && tests.r == tests.b, "{:?}", tests);
+        
+        assert_eq!(heat_color(0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(heat_color(0.5), Vec3::new(0.0, 1.0, 0.0));
+        assert!("wireframe".parse::<DebugView>().is_err());
+    }
+}
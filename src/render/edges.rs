@@ -0,0 +1,98 @@
+use crate::math::{Float, Vec3};
+use crate::camera::Camera;
+use crate::scene::{Scene, RayKind};
+use super::{Renderer, pixel_ray};
+
+/// Surfaces meeting at a sharper angle than this (its cosine) are split by an edge, as at a cube's corners;
+/// curved surfaces turn far less than this from one pixel to the next
+const CREASE_COS: Float = 0.9; // About 25°
+
+/// What a camera ray lands on, as far as edges are concerned
+#[derive(Clone, Copy)]
+struct Surface {
+    object: usize,
+    primitive: usize,
+    normal: Vec3, // Geometric normal
+}
+
+/// Whether an edge runs between what two neighboring rays see: an object's outline against another or the
+/// background, a border between two of its triangles (or voxels), or a crease
+fn is_edge(a: Option<Surface>, b: Option<Surface>) -> bool {
+    match (a, b) {
+        (None, None) => false,
+        (Some(a), Some(b)) => a.object != b.object || a.primitive != b.primitive || a.normal.dot(&b.normal) < CREASE_COS,
+        _ => true,
+    }
+}
+
+impl Renderer {
+    /// Fraction of each pixel (row by row) covered by edges one pixel wide: outlines, creases and the borders
+    /// of each mesh triangle, found by comparing what camera rays a pixel apart see
+    /// Each pixel takes a grid of at least 2×2 rays, more with more samples, so the lines are antialiased
+    pub fn render_edges(&self, scene: &Scene, camera: &Camera, width: u32, height: u32) -> Vec<Float> {
+        let grid = ((self.samples as Float).sqrt().ceil() as u32).max(2);
+        let t_max = camera.clip_range().min(self.max_distance);
+        let offset = |i: u32| ((i % grid) as Float + 0.5) / grid as Float;
+        let row = |y: u32| -> Vec<Option<Surface>> {
+            if y >= height * grid {
+                return Vec::new(); // Below the image
+            }
+            (0..width * grid)
+                .map(|x| {
+                    let ray = pixel_ray(camera, (x / grid, y / grid), (offset(x), offset(y)), (width, height));
+                    let hit = scene.intersect_within(&ray, RayKind::Camera, t_max)?;
+                    Some(Surface { object: hit.object, primitive: hit.primitive, normal: hit.geometric_normal })
+                })
+                .collect()
+        };
+        
+        // A ray is on an edge if what it sees differs from the ray one pixel right or below; only the rows
+        // of two pixels are kept at a time
+        let mut coverage = vec![0.0; (width * height) as usize];
+        let mut rows: Vec<Vec<Option<Surface>>> = (0..2 * grid).map(row).collect();
+        for y in 0..height {
+            for (i, surfaces) in rows.iter().take(grid as usize).enumerate() {
+                let below = &rows[i + grid as usize];
+                for (x, &here) in surfaces.iter().enumerate() {
+                    let neighbors = [surfaces.get(x + grid as usize), below.get(x)];
+                    if neighbors.into_iter().flatten().any(|&other| is_edge(here, other)) {
+                        coverage[(y * width) as usize + x / grid as usize] += 1.0 / (grid * grid) as Float;
+                    }
+                }
+            }
+            rows.drain(..grid as usize);
+            rows.extend(((y + 2) * grid..(y + 3) * grid).map(row));
+        }
+        coverage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::shapes::{Cube, Sphere};
+    
+    #[test]
+    fn test_render_edges() {
+        // A cube seen corner on, beside a sphere, over the background
+        let mut scene = Scene::new();
+        scene.add_object(Box::new(Cube::new(Vec3::new(-2.0, -0.5, -0.5), Vec3::new(-1.0, 0.5, 0.5), Material::white())));
+        scene.add_object(Box::new(Sphere::new(Vec3::new(1.5, 0.0, 0.0), 0.8, Material::white())));
+        let camera = Camera::new(Vec3::new(-1.5, 2.0, 5.0), Vec3::new(0.0, 0.0, 0.0), Vec3::unit_y(), 45.0, 2.0);
+        let (width, height) = (64, 32);
+        let edges = Renderer::new().render_edges(&scene, &camera, width, height);
+        let at = |x: u32, y: u32| edges[(y * width + x) as usize];
+        
+        // Nothing in the corners, nothing inside the sphere, but its outline, and lines across the cube
+        assert_eq!(at(0, 0), 0.0);
+        assert_eq!(at(width - 1, height - 1), 0.0);
+        let sphere_row: Vec<Float> = (width / 2..width).map(|x| at(x, height / 2)).collect();
+        let first = sphere_row.iter().position(|&c| c > 0.0).unwrap();
+        let last = sphere_row.iter().rposition(|&c| c > 0.0).unwrap();
+        assert!(last > first + 8 && sphere_row[first + 3..last - 2].iter().all(|&c| c == 0.0), "{:?}", sphere_row);
+        let cube_column: Vec<Float> = (0..height).map(|y| at(width * 5 / 16, y)).collect();
+        assert!(cube_column.iter().filter(|&&c| c > 0.0).count() >= 3, "{:?}", cube_column);
+        assert!(edges.iter().all(|&c| (0.0..=1.0).contains(&c)));
+    }
+}
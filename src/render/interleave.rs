@@ -0,0 +1,46 @@
+use crate::framebuffer::Framebuffer;
+use crate::color::Color;
+use crate::math::Float;
+
+/// Neighbors across an edge, then across a corner
+const EDGES: [(i64, i64); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const CORNERS: [(i64, i64); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+/// Whether pixel (x, y) is rendered when only one pixel in `interleave` is
+/// 2 renders a checkerboard, 4 the top-left pixel of each 2×2 block, anything else every pixel
+pub(super) fn renders(interleave: u32, (x, y): (u32, u32)) -> bool {
+    match interleave {
+        2 => (x + y) % 2 == 0,
+        4 => x % 2 == 0 && y % 2 == 0,
+        _ => true,
+    }
+}
+
+/// Fill in the pixels `renders` leaves out with the average of their nearest rendered neighbors,
+/// those across an edge if there are any and otherwise those across a corner
+/// Only rendered pixels are read, so the framebuffer can be filled in place
+pub(super) fn fill(framebuffer: &mut Framebuffer, interleave: u32) {
+    let (width, height) = (framebuffer.width as i64, framebuffer.height as i64);
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            if renders(interleave, (x, y)) {
+                continue;
+            }
+            for neighbors in [EDGES, CORNERS] {
+                let (mut color, mut alpha, mut count) = (Color::black(), 0.0, 0);
+                for (dx, dy) in neighbors {
+                    let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                    if (0..width).contains(&nx) && (0..height).contains(&ny) && renders(interleave, (nx as u32, ny as u32)) {
+                        color = color + framebuffer.color(nx as u32, ny as u32);
+                        alpha += framebuffer.alpha(nx as u32, ny as u32);
+                        count += 1;
+                    }
+                }
+                if count > 0 {
+                    framebuffer.set(x, y, color / count as Float, alpha / count as Float);
+                    break;
+                }
+            }
+        }
+    }
+}
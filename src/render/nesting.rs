@@ -0,0 +1,125 @@
+use crate::math::{Float, Vec3, Ray};
+use crate::scene::{Scene, RayKind};
+use crate::shapes::HitInfo;
+use super::{Renderer, SampleContext};
+
+/// A refractive object a ray has entered
+#[derive(Debug, Clone, Copy)]
+struct Medium {
+    object: usize,    // Scene object index
+    priority: u32,    // The object's nesting priority
+    ior: Vec3,        // Per-channel index of refraction inside it
+    absorption: Vec3, // Fraction of each channel left after `1 / density` inside it
+    density: Float,   // Rate of absorption, zero for clear media
+}
+
+/// Refractive objects a ray is inside of, for nesting them (water in a glass, bubbles in ice)
+/// Overlapping objects share their overlap with the one of highest priority (the latest entered among equals):
+/// the surfaces of the others don't exist in there, and the indices on either side of each real boundary
+/// are those of the object it bounds and of the medium beyond it
+#[derive(Debug, Clone, Default)]
+pub(super) struct Interior {
+    media: Vec<Medium>, // In the order entered
+}
+
+impl Interior {
+    /// Whether a ray really crosses the surface of `object` here, or it lies inside a medium of higher priority
+    pub(super) fn crosses(&self, object: usize, priority: u32) -> bool {
+        self.enclosing(object).is_none_or(|medium| medium.priority <= priority)
+    }
+    
+    /// Index of refraction beyond the surface of `object` from its inside: that of the medium enclosing it, or
+    /// of the air
+    pub(super) fn outside_ior(&self, object: usize) -> Vec3 {
+        self.enclosing(object).map_or(Vec3::new(1.0, 1.0, 1.0), |medium| medium.ior)
+    }
+    
+    /// Record passing through the surface a ray hit: into its object from the front, out of it from the back
+    pub(super) fn cross(&mut self, hit: &HitInfo, priority: u32) {
+        self.media.retain(|medium| medium.object != hit.object);
+        if hit.front_face {
+            let (absorption, density) = (Vec3::from(hit.material.absorption), hit.material.absorption_density);
+            self.media.push(Medium { object: hit.object, priority, ior: hit.material.ior, absorption, density });
+        }
+    }
+    
+    /// Fraction of each channel left after travelling `distance` through the medium the ray is in (Beer–Lambert)
+    pub(super) fn transmittance(&self, distance: Float) -> Vec3 {
+        match self.media.iter().max_by_key(|medium| medium.priority) {
+            Some(medium) if medium.density > 0.0 => {
+                let exponent = medium.density * distance;
+                Vec3::new(medium.absorption.x.powf(exponent), medium.absorption.y.powf(exponent), medium.absorption.z.powf(exponent))
+            }
+            _ => Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+    
+    /// The medium of highest priority apart from `object`, the latest entered among equals
+    fn enclosing(&self, object: usize) -> Option<&Medium> {
+        self.media.iter().filter(|medium| medium.object != object).max_by_key(|medium| medium.priority)
+    }
+}
+
+impl Renderer {
+    /// Carry `ray` on through the refractive surfaces it doesn't really cross (see `Interior`), up to the first
+    /// hit it does, returning the ray from the last surface passed, that hit, and the fraction of light the
+    /// media passed through on the way let through
+    pub(super) fn skip_false_hits(&self, mut ray: Ray, mut hit: Option<HitInfo>, scene: &Scene, kind: RayKind, context: &mut SampleContext) -> (Ray, Option<HitInfo>, Vec3) {
+        let mut transmittance = Vec3::new(1.0, 1.0, 1.0);
+        while let Some(false_hit) = hit.take_if(|hit| {
+            hit.material.is_refractive() && !context.interior.crosses(hit.object, scene.objects[hit.object].flags.priority)
+        }) {
+            transmittance *= context.interior.transmittance(false_hit.t * ray.direction.length());
+            context.interior.cross(&false_hit, scene.objects[false_hit.object].flags.priority);
+            context.travelled += false_hit.t * ray.direction.length();
+            ray = Ray::new(false_hit.offset_origin(ray.direction), ray.direction);
+            hit = scene.intersect_within(&ray, kind, self.max_distance);
+        }
+        let distance = hit.as_ref().map_or(Float::INFINITY, |hit| hit.t * ray.direction.length());
+        (ray, hit, transmittance * context.interior.transmittance(distance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::color::Color;
+    
+    #[test]
+    fn test_priorities() {
+        // Water (priority 1) poured into a glass (priority 2): the glass's inside wall bounds the water
+        let hit = |object: usize, front_face: bool, material: &Material| {
+            let ray = Ray::new(Vec3::zero(), Vec3::unit_z());
+            let normal = if front_face { -Vec3::unit_z() } else { Vec3::unit_z() };
+            HitInfo { object, ..HitInfo::new(&ray, 1.0, normal, material.clone()) }
+        };
+        let (glass, water) = (Material::dielectric(Vec3::new(1.0, 1.0, 1.0), 1.5), Material::dielectric(Vec3::new(1.0, 1.0, 1.0), 1.33));
+        let mut interior = Interior::default();
+        assert!(interior.crosses(0, 2));
+        interior.cross(&hit(0, true, &glass), 2);
+        
+        // The water's surface doesn't exist inside the glass wall, but leaving the wall enters it
+        assert!(!interior.crosses(1, 1));
+        interior.cross(&hit(1, true, &water), 1);
+        assert!(interior.crosses(0, 2));
+        assert_eq!(interior.outside_ior(0), Vec3::new(1.33, 1.33, 1.33));
+        interior.cross(&hit(0, false, &glass), 2);
+        assert!(interior.crosses(1, 1));
+        assert_eq!(interior.outside_ior(1), Vec3::new(1.0, 1.0, 1.0));
+    }
+    
+    #[test]
+    fn test_absorption() {
+        let ray = Ray::new(Vec3::zero(), Vec3::unit_z());
+        let material = Material { absorption: Color::new(0.5, 1.0, 0.0), absorption_density: 2.0, ..Material::dielectric(Vec3::new(1.0, 1.0, 1.0), 1.5) };
+        let mut interior = Interior::default();
+        assert_eq!(interior.transmittance(10.0), Vec3::new(1.0, 1.0, 1.0));
+        
+        // The tint is reached after half a unit, and deepens with distance
+        interior.cross(&HitInfo::new(&ray, 1.0, -Vec3::unit_z(), material), 0);
+        assert_eq!(interior.transmittance(0.5), Vec3::new(0.5, 1.0, 0.0));
+        assert_eq!(interior.transmittance(1.0), Vec3::new(0.25, 1.0, 0.0));
+        assert_eq!(interior.transmittance(0.0), Vec3::new(1.0, 1.0, 1.0));
+    }
+}
@@ -0,0 +1,216 @@
+use crate::math::{consts::PI, Float, Vec3, Ray};
+use crate::scene::{Scene, Light, RayKind};
+use crate::shapes::HitInfo;
+use crate::bsdf::Bsdf;
+use crate::sampling;
+use super::trace::{self, TraceEvent};
+use super::{Renderer, SampleContext};
+
+/// Bounces every path takes before Russian roulette may end it
+const ROULETTE_DEPTH: u32 = 3;
+
+impl Renderer {
+    /// Trace a camera ray as a random walk through the scene (`path_tracing` mode),
+    /// returning its color (premultiplied) and alpha
+    /// Direct light is estimated at every bounce both by sampling the lights (next-event estimation) and by
+    /// the BSDF-sampled continuation hitting a sphere light, and the two are combined by multiple importance
+    /// sampling; with `next_event_estimation` off only the second remains, and point lights go dark
+    /// `camera_hit` is the camera ray's closest hit, found beforehand along with its neighbors'
+    pub(super) fn trace_path(&self, camera_ray: &Ray, mut camera_hit: Option<HitInfo>, scene: &Scene, context: &mut SampleContext) -> (Vec3, Float) {
+        let mut ray = camera_ray.clone();
+        let mut kind = RayKind::Camera; // Straight-through transmission keeps camera visibility, bounces count as reflections
+        let mut radiance = Vec3::zero();
+        let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+        let mut bsdf_pdf = None; // Density of the BSDF sample that produced `ray`; None after the camera or a mirror
+        let mut scattered_by = None; // Object `ray` left, whose light links decide which lights it may reach
+        let mut channel = None; // The only color channel left after dispersion, for later refractions to keep to
+        
+        for depth in 0..self.max_depth {
+            let mut hit = if depth == 0 { camera_hit.take() } else { scene.intersect_within(&ray, kind, self.max_distance) };
+            let transmittance;
+            (ray, hit, transmittance) = self.skip_false_hits(ray, hit, scene, kind, context);
+            throughput *= transmittance;
+            trace::record(|| TraceEvent::Ray { depth, kind, origin: ray.origin, direction: ray.direction });
+            trace::record(|| hit.as_ref().map_or(TraceEvent::Miss, TraceEvent::hit));
+            if let Some(hit) = &mut hit {
+                self.prepare_hit(&ray, hit, context);
+            }
+            
+            // A sphere light in front of the closest surface ends the path
+            let t_max = hit.as_ref().map_or(Float::INFINITY, |hit| hit.t);
+            if let Some((index, _)) = scene.intersect_light(&ray, t_max) {
+                let light = &scene.lights[index];
+                trace::record(|| TraceEvent::LightHit { light: index });
+                let weight = match bsdf_pdf {
+                    _ if scattered_by.is_some_and(|object| !light.links.illuminates(object)) => 0.0,
+                    Some(_) if self.next_event_estimation && !light.casts_shadows => 0.0, // Light sampling finds it in full
+                    Some(pdf) if self.next_event_estimation => {
+                        let light_pdf = context.lights.rate(index) * sphere_light_pdf(light, ray.origin);
+                        pdf / (pdf + light_pdf)
+                    }
+                    _ => 1.0,
+                };
+                let emitted = throughput * light.radiance_toward(ray.origin) * weight;
+                return (radiance + self.clamp_indirect(emitted, depth), 1.0);
+            }
+            
+            let hit = match hit {
+                Some(hit) => hit,
+                None if self.transparent_background && kind == RayKind::Camera => return (radiance, 0.0),
+                None => {
+                    // An environment map was also reached by light sampling at the previous bounce
+                    let weight = match (bsdf_pdf, &scene.environment) {
+                        (Some(pdf), Some(environment)) if self.next_event_estimation => {
+                            pdf / (pdf + environment.pdf(ray.direction))
+                        }
+                        _ => 1.0,
+                    };
+                    let background = throughput * scene.background(ray.direction) * weight;
+                    return (radiance + self.clamp_indirect(background, depth), 1.0);
+                }
+            };
+            
+            // Glowing surfaces are only found by paths hitting them, so their light is taken in full
+            if hit.material.is_emissive() {
+                let emitted = throughput * Vec3::from(hit.material.emission);
+                radiance += self.clamp_indirect(emitted, depth);
+            }
+            
+            if hit.material.shadow_catcher {
+                // Composited as in the ray tracer; nothing scatters further
+                let (color, alpha) = self.shade_shadow_catcher(&ray, scene, &hit, depth, kind, context);
+                let alpha = if kind == RayKind::Camera { alpha } else { 1.0 };
+                return (radiance + throughput * color, alpha);
+            }
+            
+            // Shapes report normals facing the ray, so this shades the side it arrived from
+            let wo = -ray.direction.normalize();
+            let normal = hit.normal;
+            let outside_ior = context.interior.outside_ior(hit.object);
+            let bsdf = Bsdf::new(&hit.material, hit.tangent).with_path(hit.front_face, channel, outside_ior);
+            if self.next_event_estimation {
+                let direct = throughput * self.direct_light(scene, &hit, wo, &bsdf, context);
+                radiance += self.clamp_indirect(direct, depth);
+            }
+            
+            let sample = match bsdf.sample(wo, normal, &mut context.rng) {
+                Some(sample) => sample,
+                None => {
+                    trace::record(|| TraceEvent::End("absorbed, the BSDF sampled no direction"));
+                    break;
+                }
+            };
+            throughput *= sample.weight;
+            bsdf_pdf = sample.pdf;
+            channel = sample.channel;
+            
+            // Russian roulette: dim paths continue only by chance, and survivors make up for the others
+            if depth + 1 >= ROULETTE_DEPTH {
+                let survival = throughput.max_component().clamp(self.roulette_min_probability, 1.0);
+                if context.rng.next_float() >= survival {
+                    trace::record(|| TraceEvent::End("stopped by Russian roulette"));
+                    break;
+                }
+                throughput /= survival;
+            }
+            ray = Ray::new(hit.offset_origin(sample.direction), sample.direction);
+            scattered_by = Some(hit.object);
+            if sample.transmitted && hit.material.is_refractive() {
+                context.interior.cross(&hit, scene.objects[hit.object].flags.priority);
+            }
+            if !sample.transmitted {
+                kind = RayKind::Reflection;
+            }
+            trace::record(|| TraceEvent::Bounce(if sample.transmitted { "BSDF sample transmitted" } else { "BSDF sample reflected" }));
+            if depth + 1 == self.max_depth {
+                trace::record(|| TraceEvent::End("max depth reached"));
+            }
+        }
+        
+        (radiance, 1.0)
+    }
+    
+    /// Scale down a contribution found `depth` bounces after the camera so no channel exceeds `clamp`
+    /// Light seen directly or lighting the first hit is left alone: clamping only the rare bright
+    /// indirect paths (caustics through glass and mirrors) removes fireflies while biasing the image least
+    fn clamp_indirect(&self, contribution: Vec3, depth: u32) -> Vec3 {
+        match self.clamp {
+            Some(max) if depth > 0 => {
+                let peak = contribution.max_component();
+                if peak > max { contribution * (max / peak) } else { contribution }
+            }
+            _ => contribution,
+        }
+    }
+    
+    /// Light arriving at a surface point from the lights and the environment map, scattered toward `wo`
+    /// Sphere lights are sampled within the cone they subtend and the environment by its luminance, both
+    /// MIS-weighted against BSDF sampling; point lights can only be reached this way
+    /// Lights unlinked from the object hit are skipped
+    fn direct_light(&self, scene: &Scene, hit: &HitInfo, wo: Vec3, bsdf: &Bsdf, context: &mut SampleContext) -> Vec3 {
+        let (origin, normal) = (hit.offset_origin(hit.normal), hit.normal);
+        let mut total = Vec3::zero();
+        
+        context.lights.pick(&scene.lights, &mut context.rng, |light, weight, rng| {
+            if !light.links.illuminates(hit.object) {
+                return;
+            }
+            let to_light = light.position - origin;
+            let (direction, distance, incoming, light_pdf) = if light.radius > 0.0 {
+                let sin2_max = light.radius * light.radius / to_light.length_squared();
+                if sin2_max >= 1.0 {
+                    return; // Inside the light
+                }
+                let direction = sampling::uniform_cone(to_light.normalize(), sin2_max, rng);
+                
+                // Distance to the near side of the sphere along the sampled direction
+                let along = to_light.dot(&direction);
+                let offset_squared = to_light.length_squared() - along * along;
+                let distance = along - (light.radius * light.radius - offset_squared).max(0.0).sqrt();
+                
+                let pdf = sampling::cone_pdf(sin2_max);
+                (direction, distance, light.radiance_toward(origin) / pdf, Some(pdf / weight))
+            } else {
+                // Irradiance π·intensity makes a Lambertian surface as bright as in the ray tracer
+                (to_light.normalize(), to_light.length(), Vec3::from(light.color) * (PI * light.intensity_at(origin)), None)
+            };
+            
+            let cos_theta = normal.dot(&direction);
+            let f = bsdf.eval(wo, direction, normal);
+            if cos_theta <= 0.0 || f == Vec3::zero() {
+                return;
+            }
+            
+            let visibility = self.visibility_of(scene, light, origin, direction, distance);
+            trace::record(|| TraceEvent::Shadow { light: trace::light_index(scene, light), visibility });
+            let mis = match light_pdf {
+                // BSDF samples can't reach a light through the objects its light passes through, so it is found only here
+                Some(light_pdf) if light.casts_shadows => light_pdf / (light_pdf + bsdf.pdf(wo, direction, normal)),
+                _ => 1.0,
+            };
+            total += f * incoming * visibility * (cos_theta * weight * mis);
+        });
+        
+        if let Some((direction, incoming, pdf)) = scene.environment.as_ref().and_then(|env| env.sample(&mut context.rng)) {
+            let cos_theta = normal.dot(&direction);
+            let f = bsdf.eval(wo, direction, normal);
+            if cos_theta > 0.0 && f != Vec3::zero() {
+                let visibility = self.light_visibility(scene, origin, direction, Float::INFINITY);
+                let mis = pdf / (pdf + bsdf.pdf(wo, direction, normal));
+                total += f * incoming * visibility * (cos_theta * mis / pdf);
+            }
+        }
+        
+        total
+    }
+}
+
+/// Density (per steradian) with which `direct_light` samples a direction toward a sphere light from `origin`
+fn sphere_light_pdf(light: &Light, origin: Vec3) -> Float {
+    let sin2_max = light.radius * light.radius / (light.position - origin).length_squared();
+    if sin2_max < 1.0 {
+        sampling::cone_pdf(sin2_max)
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,194 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use crate::math::{Float, Vec3};
+use crate::camera::Camera;
+use crate::scene::{Scene, Light, RayKind};
+use crate::shapes::HitInfo;
+use crate::sampling::{LightSampler, Rng};
+use super::{Renderer, SampleContext, Interior, pixel_ray, sample_offsets};
+
+thread_local! {
+    // Events of the sample being traced by `Renderer::trace_pixel` on this thread; None while rendering normally
+    static EVENTS: RefCell<Option<Vec<TraceEvent>>> = const { RefCell::new(None) };
+}
+
+/// One step in tracing a sample, for working out why a pixel looks the way it does
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent {
+    Ray { depth: u32, kind: RayKind, origin: Vec3, direction: Vec3 },
+    Hit { object: usize, t: Float, point: Vec3, normal: Vec3, front_face: bool },
+    Miss,
+    LightHit { light: usize },                    // A path reached a sphere light
+    Shadow { light: usize, visibility: Vec3 },    // Shadow ray result: zero if blocked, else the light let through
+    Unlit { light: usize, reason: &'static str }, // A light skipped without a shadow ray
+    Bounce(&'static str),                         // Why another ray is traced from the last hit
+    End(&'static str),                            // Why no further ray is traced
+    Shaded { depth: u32, color: Vec3, alpha: Float }, // Color a ray returns, after everything it led to
+}
+
+impl TraceEvent {
+    pub fn hit(hit: &HitInfo) -> Self {
+        Self::Hit { object: hit.object, t: hit.t, point: hit.point, normal: hit.normal, front_face: hit.front_face }
+    }
+}
+
+/// A pixel sample's color with the events that produced it
+#[derive(Debug, Clone)]
+pub struct SampleTrace {
+    pub offset: (Float, Float), // Position within the pixel
+    pub color: Vec3,
+    pub alpha: Float,
+    pub events: Vec<TraceEvent>,
+}
+
+/// Record an event if this thread is tracing a pixel; `event` is only called then
+pub(super) fn record(event: impl FnOnce() -> TraceEvent) {
+    EVENTS.with(|events| {
+        if let Some(events) = events.borrow_mut().as_mut() {
+            events.push(event());
+        }
+    });
+}
+
+/// Index of a scene light, for naming it in events
+pub(super) fn light_index(scene: &Scene, light: &Light) -> usize {
+    scene.lights.iter().position(|other| std::ptr::eq(other, light)).unwrap_or(usize::MAX)
+}
+
+impl Renderer {
+    /// Trace each sample of one pixel as `render_framebuffer` does, with the same random numbers, recording
+    /// the rays cast, surfaces hit, shadow rays and bounce decisions along the way
+    pub fn trace_pixel(&self, scene: &Scene, camera: &Camera, (width, height): (u32, u32), (x, y): (u32, u32)) -> Vec<SampleTrace> {
+        let lights = LightSampler::new(&scene.lights, self.light_samples, self.light_sampling);
+        let offsets = sample_offsets(self.samples);
+        let tile = self.tiles((width, height)).into_iter().find(|tile| tile.contains((x, y))).expect("pixel outside the image");
+        let t_max = camera.clip_range().min(self.max_distance);
+        
+        offsets
+            .iter()
+            .enumerate()
+            .map(|(sample, &offset)| {
+                let mut context = SampleContext {
+                    rng: Rng::for_tile(self.seed, tile.id).split(tile.sample_index((x, y), sample, offsets.len())),
+                    lights: &lights,
+                    spread: camera.pixel_spread(height),
+                    travelled: 0.0,
+                    interior: Interior::default(),
+                };
+                let ray = pixel_ray(camera, (x, y), offset, (width, height));
+                EVENTS.with(|events| *events.borrow_mut() = Some(Vec::new()));
+                let hit = scene.intersect_within(&ray, RayKind::Camera, t_max);
+                let (color, alpha) = if self.path_tracing {
+                    self.trace_path(&ray, hit, scene, &mut context)
+                } else {
+                    self.trace_hit(&ray, hit, scene, 0, RayKind::Camera, &mut context)
+                };
+                let events = EVENTS.with(|events| events.borrow_mut().take()).unwrap_or_default();
+                SampleTrace { offset, color, alpha, events }
+            })
+            .collect()
+    }
+}
+
+/// A vector to three decimals
+struct Show(Vec3);
+
+impl fmt::Display for Show {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({:.3}, {:.3}, {:.3})", self.0.x, self.0.y, self.0.z)
+    }
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ray { depth, kind, origin, direction } => {
+                let kind = match kind {
+                    RayKind::Camera if *depth == 0 => "camera ray",
+                    RayKind::Camera => "continuing ray",
+                    RayKind::Shadow => "shadow ray",
+                    RayKind::Reflection => "reflected ray",
+                };
+                write!(f, "{} (depth {}) from {} toward {}", kind, depth, Show(*origin), Show(*direction))
+            }
+            Self::Hit { object, t, point, normal, front_face } => {
+                let side = if *front_face { "outside" } else { "inside" };
+                write!(f, "hit object {} at t = {:.4}, point {}, normal {}, from {}", object, t, Show(*point), Show(*normal), side)
+            }
+            Self::Miss => write!(f, "missed everything: background"),
+            Self::LightHit { light } => write!(f, "reached light {}", light),
+            Self::Shadow { light, visibility } if *visibility == Vec3::zero() => write!(f, "light {}: in shadow", light),
+            Self::Shadow { light, visibility } => write!(f, "light {}: visible, letting through {}", light, Show(*visibility)),
+            Self::Unlit { light, reason } => write!(f, "light {}: {}", light, reason),
+            Self::Bounce(reason) => write!(f, "bounce: {}", reason),
+            Self::End(reason) => write!(f, "end: {}", reason),
+            Self::Shaded { depth, color, alpha } => write!(f, "depth {} returns color {} alpha {:.3}", depth, Show(*color), alpha),
+        }
+    }
+}
+
+impl fmt::Display for SampleTrace {
+    /// The sample's result, then its events indented by the depth of the ray they belong to
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "sample at ({:.3}, {:.3}): color {} alpha {:.3}", self.offset.0, self.offset.1, Show(self.color), self.alpha)?;
+        let mut depth = 0;
+        for event in &self.events {
+            let indent = match *event {
+                TraceEvent::Ray { depth: ray_depth, .. } => {
+                    depth = ray_depth;
+                    ray_depth
+                }
+                // A ray's result closes it, and what follows belongs to the ray that cast it
+                TraceEvent::Shaded { depth: ray_depth, .. } => {
+                    depth = ray_depth.saturating_sub(1);
+                    ray_depth
+                }
+                _ => depth,
+            };
+            writeln!(f, "{:width$}{}", "", event, width = 2 + 2 * indent as usize)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::shapes::{Plane, Sphere};
+    use crate::color::Color;
+    
+    #[test]
+    fn test_trace_pixel() {
+        // A mirror ball over a floor, lit from above, seen dead center
+        let mut scene = Scene::new();
+        scene.add_object(Box::new(Plane::horizontal(0.0, Material::white())));
+        let mut mirror = Material::white();
+        mirror.reflectivity = 0.5;
+        scene.add_object(Box::new(Sphere::new(Vec3::new(0.0, 1.0, 0.0), 0.5, mirror)));
+        scene.add_light(Light::new(Vec3::new(0.0, 10.0, 0.0), 1.0, Color::white()));
+        let camera = Camera::new(Vec3::new(0.0, 1.0, 4.0), Vec3::new(0.0, 1.0, 0.0), Vec3::unit_y(), 60.0, 1.0);
+        let mut renderer = Renderer::new();
+        renderer.reflections = true;
+        
+        let traces = renderer.trace_pixel(&scene, &camera, (8, 8), (4, 3));
+        assert_eq!(traces.len(), 1);
+        let trace = &traces[0];
+        let framebuffer = renderer.render_framebuffer(&scene, &camera, 8, 8);
+        assert_eq!(Color::from(trace.color), framebuffer.color(4, 3));
+        
+        // The ball is hit straight on; its reflection goes back out past the camera, to the background
+        assert!(matches!(trace.events[0], TraceEvent::Ray { depth: 0, kind: RayKind::Camera, .. }));
+        assert!(matches!(trace.events[1], TraceEvent::Hit { object: 1, front_face: true, .. }));
+        assert!(trace.events.contains(&TraceEvent::Bounce("reflection")));
+        assert!(trace.events.contains(&TraceEvent::Miss));
+        assert!(matches!(trace.events.last(), Some(TraceEvent::Shaded { depth: 0, .. })));
+        let log = trace.to_string();
+        assert!(log.contains("\n    reflected ray (depth 1)"), "{}", log);
+        
+        // Nothing is recorded outside trace_pixel
+        renderer.render_framebuffer(&scene, &camera, 8, 8);
+        assert_eq!(EVENTS.with(|events| events.borrow().clone()), None);
+    }
+}
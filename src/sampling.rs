@@ -0,0 +1,191 @@
+use std::str::FromStr;
+
+use crate::math::{consts::PI, Float, Onb, Vec3};
+use crate::scene::Light;
+
+/// Small, fast pseudo-random generator (SplitMix64)
+/// Renders draw one stream per tile from a global seed and split it per pixel sample, so their
+/// random numbers don't depend on the thread count or the order in which samples are traced
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+    
+    /// Stream for one tile of a render with the given global seed
+    pub fn for_tile(seed: u64, tile: u32) -> Self {
+        let mut rng = Self::new(seed ^ (tile as u64).wrapping_mul(0xd1b5_4a32_d192_ed03));
+        rng.next_u64(); // Decorrelate neighboring tiles
+        rng
+    }
+    
+    /// Independent generator for the `index`-th sample of this stream, seeded from the stream's
+    /// `index`-th number without advancing it, so samples can be drawn in any order
+    pub fn split(&self, index: u64) -> Self {
+        let mut at = Self(self.0.wrapping_add(index.wrapping_mul(0x9e37_79b9_7f4a_7c15)));
+        Self::new(at.next_u64())
+    }
+    
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+    
+    /// Uniform number in [0, 1)
+    pub fn next_float(&mut self) -> Float {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) as Float
+    }
+}
+
+/// How shading points pick lights when only some of them are sampled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LightSampling {
+    Uniform, // Every light equally likely
+    #[default]
+    Power, // In proportion to intensity × luminance, so dim lights cost little
+}
+
+impl FromStr for LightSampling {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Self::Uniform),
+            "power" => Ok(Self::Power),
+            _ => Err(format!("unknown light sampling '{}' (expected uniform or power)", s)),
+        }
+    }
+}
+
+/// Picks which lights a shading point evaluates, with the weights that keep the result unbiased
+#[derive(Debug, Clone)]
+pub struct LightSampler {
+    samples: Option<u32>, // Lights picked per shading point (None = every light, unweighted)
+    cdf: Vec<Float>,      // Cumulative selection probabilities, one per light
+}
+
+impl LightSampler {
+    /// Sampler over `lights`; `samples` of None evaluates every light, as without light sampling
+    pub fn new(lights: &[Light], samples: Option<u32>, strategy: LightSampling) -> Self {
+        let power = |light: &Light| light.intensity.max(0.0) * light.color.luminance().max(0.0);
+        let total: Float = lights.iter().map(power).sum();
+        
+        // Lights with no power at all still get picked uniformly rather than never
+        let weights: Vec<Float> = match strategy {
+            LightSampling::Power if total > 0.0 => lights.iter().map(|light| power(light) / total).collect(),
+            _ => vec![1.0 / lights.len() as Float; lights.len()],
+        };
+        let cdf = weights
+            .iter()
+            .scan(0.0, |sum, weight| {
+                *sum += weight;
+                Some(*sum)
+            })
+            .collect();
+        
+        Self { samples: samples.map(|n| n.max(1)), cdf }
+    }
+    
+    /// Probability of picking light `index` in a single draw
+    pub fn probability(&self, index: usize) -> Float {
+        self.cdf[index] - if index == 0 { 0.0 } else { self.cdf[index - 1] }
+    }
+    
+    /// Expected number of times light `index` is picked per shading point (1 when every light is evaluated)
+    pub fn rate(&self, index: usize) -> Float {
+        match self.samples {
+            Some(samples) => samples as Float * self.probability(index),
+            None => 1.0,
+        }
+    }
+    
+    /// Call `visit` with each light to evaluate and the factor to scale its contribution by
+    /// (the inverse of its rate); the generator is handed on for sampling points on the light
+    pub fn pick<'a>(&self, lights: &'a [Light], rng: &mut Rng, mut visit: impl FnMut(&'a Light, Float, &mut Rng)) {
+        let samples = match self.samples {
+            Some(samples) if !lights.is_empty() => samples,
+            _ => {
+                lights.iter().for_each(|light| visit(light, 1.0, rng));
+                return;
+            }
+        };
+        
+        let total = self.cdf[self.cdf.len() - 1];
+        for _ in 0..samples {
+            let u = rng.next_float() * total;
+            let index = self.cdf.partition_point(|&c| c <= u).min(lights.len() - 1);
+            visit(&lights[index], 1.0 / self.rate(index), rng);
+        }
+    }
+}
+
+/// Perceived brightness of a linear RGB color (Rec. 709 weights)
+pub fn luminance(color: Vec3) -> Float {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+/// Direction at polar angle acos(cos_theta) and azimuth phi around `axis` (unit length)
+pub fn around(axis: Vec3, cos_theta: Float, phi: Float) -> Vec3 {
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    Onb::from_normal(axis).to_world(Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta))
+}
+
+/// Cosine-weighted direction in the hemisphere around `normal`; its density is cos θ / π
+pub fn cosine_hemisphere(normal: Vec3, rng: &mut Rng) -> Vec3 {
+    let (u, v) = (rng.next_float(), rng.next_float());
+    around(normal, (1.0 - u).sqrt(), 2.0 * PI * v)
+}
+
+/// Uniform direction within a cone around `axis` whose half-angle has squared sine `sin2_max`
+/// (r²/d² for a sphere of radius r at distance d); its density is `cone_pdf(sin2_max)`
+pub fn uniform_cone(axis: Vec3, sin2_max: Float, rng: &mut Rng) -> Vec3 {
+    let (u, v) = (rng.next_float(), rng.next_float());
+    around(axis, 1.0 - u * cone_height(sin2_max), 2.0 * PI * v)
+}
+
+/// Density (per steradian) of `uniform_cone`
+pub fn cone_pdf(sin2_max: Float) -> Float {
+    1.0 / (2.0 * PI * cone_height(sin2_max))
+}
+
+/// 1 - cos θmax, computed without cancellation so tiny cones (distant lights) keep their precision
+fn cone_height(sin2_max: Float) -> Float {
+    sin2_max / (1.0 + (1.0 - sin2_max).max(0.0).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    
+    #[test]
+    fn test_power_sampling() {
+        let lights = [
+            Light::white_light(Vec3::zero(), 3.0),
+            Light::white_light(Vec3::zero(), 1.0),
+            Light::new(Vec3::zero(), 4.0, Color::black()), // Black: never picked by power
+        ];
+        let sampler = LightSampler::new(&lights, Some(2), LightSampling::Power);
+        assert!((sampler.probability(0) - 0.75).abs() < 1e-6);
+        assert_eq!(sampler.probability(2), 0.0);
+        
+        // Weights undo the selection probability, so on average every light counts once
+        let mut rng = Rng::new(1);
+        let mut counted = [0.0; 3];
+        for _ in 0..10_000 {
+            sampler.pick(&lights, &mut rng, |light, weight, _| {
+                let index = lights.iter().position(|l| std::ptr::eq(l, light)).unwrap();
+                counted[index] += weight / 10_000.0;
+            });
+        }
+        assert!((counted[0] - 1.0).abs() < 0.05 && (counted[1] - 1.0).abs() < 0.05, "{:?}", counted);
+        
+        let uniform = LightSampler::new(&lights, Some(1), LightSampling::Uniform);
+        assert!((uniform.probability(2) - 1.0 / 3.0).abs() < 1e-6);
+    }
+}
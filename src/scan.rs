@@ -0,0 +1,441 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::collections::HashMap;
+use std::io;
+
+use crate::math::{Float, Vec3};
+use crate::color::Color;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScanError {
+    #[error("could not read scan: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid PLY file: {0}")]
+    InvalidPly(String),
+    #[error("invalid XYZ file: line {line}: {reason}")]
+    InvalidXyz { line: usize, reason: String },
+    #[error("invalid STL file: {0}")]
+    InvalidStl(String),
+    #[error("no faces to make a mesh of")]
+    NoFaces,
+}
+
+/// Scanned points from a PLY or XYZ file, with their normals and colors if the file has them,
+/// and the triangles joining them into a mesh if it has faces; or the triangles of an STL model
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Scan {
+    pub positions: Vec<Vec3>,
+    pub normals: Option<Vec<Vec3>>, // One per position, as written (not necessarily unit length)
+    pub colors: Option<Vec<Color>>, // One per position
+    pub faces: Vec<[usize; 3]>,     // Indices of each triangle's corners, counter-clockwise seen from the front
+}
+
+/// Scalar types of PLY properties, under their old and new names
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlyType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => PlyType::I8,
+            "uchar" | "uint8" => PlyType::U8,
+            "short" | "int16" => PlyType::I16,
+            "ushort" | "uint16" => PlyType::U16,
+            "int" | "int32" => PlyType::I32,
+            "uint" | "uint32" => PlyType::U32,
+            "float" | "float32" => PlyType::F32,
+            "double" | "float64" => PlyType::F64,
+            _ => return None,
+        })
+    }
+    
+    fn size(self) -> usize {
+        match self {
+            PlyType::I8 | PlyType::U8 => 1,
+            PlyType::I16 | PlyType::U16 => 2,
+            PlyType::I32 | PlyType::U32 | PlyType::F32 => 4,
+            PlyType::F64 => 8,
+        }
+    }
+}
+
+/// A property of a PLY element: one value, or a list of them after a count
+#[derive(Debug, Clone)]
+struct PlyProperty {
+    name: String,
+    count: Option<PlyType>, // Type of the count, for lists
+    value: PlyType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlyFormat {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+/// Reads the values of a PLY file's body one at a time
+struct PlyReader<'a> {
+    format: PlyFormat,
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl PlyReader<'_> {
+    fn read(&mut self, kind: PlyType) -> Result<f64, ScanError> {
+        let truncated = || ScanError::InvalidPly("truncated body".to_string());
+        if self.format == PlyFormat::Ascii {
+            // Values are separated by any whitespace, lines carrying no meaning
+            let start = self.at + self.bytes[self.at..].iter().position(|b| !b.is_ascii_whitespace()).ok_or_else(truncated)?;
+            let end = start + self.bytes[start..].iter().position(|b| b.is_ascii_whitespace()).unwrap_or(self.bytes.len() - start);
+            self.at = end;
+            let token = std::str::from_utf8(&self.bytes[start..end]).unwrap_or("");
+            return token.parse().map_err(|_| ScanError::InvalidPly(format!("'{}' is not a number", token)));
+        }
+        
+        let mut word = [0; 8];
+        let size = kind.size();
+        word[..size].copy_from_slice(self.bytes.get(self.at..self.at + size).ok_or_else(truncated)?);
+        self.at += size;
+        if self.format == PlyFormat::BigEndian {
+            word[..size].reverse();
+        }
+        Ok(match kind {
+            PlyType::I8 => word[0] as i8 as f64,
+            PlyType::U8 => word[0] as f64,
+            PlyType::I16 => i16::from_le_bytes([word[0], word[1]]) as f64,
+            PlyType::U16 => u16::from_le_bytes([word[0], word[1]]) as f64,
+            PlyType::I32 => i32::from_le_bytes([word[0], word[1], word[2], word[3]]) as f64,
+            PlyType::U32 => u32::from_le_bytes([word[0], word[1], word[2], word[3]]) as f64,
+            PlyType::F32 => f32::from_le_bytes([word[0], word[1], word[2], word[3]]) as f64,
+            PlyType::F64 => f64::from_le_bytes(word),
+        })
+    }
+}
+
+/// Color from channel values that run up to 255 for integer properties and up to 1 for floating point ones
+fn channel_color(channels: [f64; 3], kind: PlyType) -> Color {
+    let full = if matches!(kind, PlyType::F32 | PlyType::F64) { 1.0 } else { 255.0 };
+    Color::from_srgb8(channels.map(|channel| (channel / full * 255.0).round().clamp(0.0, 255.0) as u8))
+}
+
+impl Scan {
+    /// Parse a PLY file (ASCII or binary, either byte order), keeping its vertices' x, y and z, their nx, ny
+    /// and nz and red, green and blue if present, and its faces' vertex indices; other elements and
+    /// properties are skipped
+    pub fn parse_ply(bytes: &[u8]) -> Result<Self, ScanError> {
+        let invalid = |reason: String| ScanError::InvalidPly(reason);
+        let header_end = bytes
+            .windows(11)
+            .position(|window| window == b"end_header\n" || window == b"end_header\r")
+            .ok_or_else(|| invalid("no end_header".to_string()))?;
+        let header = String::from_utf8_lossy(&bytes[..header_end]);
+        let mut lines = header.lines().map(str::trim);
+        if lines.next() != Some("ply") {
+            return Err(invalid("missing ply magic".to_string()));
+        }
+        
+        let mut format = None;
+        let mut elements: Vec<(String, usize, Vec<PlyProperty>)> = Vec::new();
+        for line in lines {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let kind = |name: &str| PlyType::parse(name).ok_or_else(|| invalid(format!("unknown type '{}'", name)));
+            match words.as_slice() {
+                ["format", name, _] => {
+                    format = Some(match *name {
+                        "ascii" => PlyFormat::Ascii,
+                        "binary_little_endian" => PlyFormat::LittleEndian,
+                        "binary_big_endian" => PlyFormat::BigEndian,
+                        _ => return Err(invalid(format!("unknown format '{}'", name))),
+                    })
+                }
+                ["element", name, count] => {
+                    let count = count.parse().map_err(|_| invalid(format!("bad count for element '{}'", name)))?;
+                    elements.push((name.to_string(), count, Vec::new()));
+                }
+                ["property", "list", count, value, name] => {
+                    let element = elements.last_mut().ok_or_else(|| invalid("property before any element".to_string()))?;
+                    element.2.push(PlyProperty { name: name.to_string(), count: Some(kind(count)?), value: kind(value)? });
+                }
+                ["property", value, name] => {
+                    let element = elements.last_mut().ok_or_else(|| invalid("property before any element".to_string()))?;
+                    element.2.push(PlyProperty { name: name.to_string(), count: None, value: kind(value)? });
+                }
+                ["comment", ..] | ["obj_info", ..] | [] => {}
+                _ => return Err(invalid(format!("unexpected header line '{}'", line))),
+            }
+        }
+        let format = format.ok_or_else(|| invalid("no format line".to_string()))?;
+        
+        // The body starts after the newline ending end_header (\r\n for some ASCII writers)
+        let mut at = header_end + 11;
+        if bytes.get(at - 1) == Some(&b'\r') && bytes.get(at) == Some(&b'\n') {
+            at += 1;
+        }
+        let mut reader = PlyReader { format, bytes, at };
+        let mut scan = Scan::default();
+        for (name, count, properties) in &elements {
+            let find = |wanted: &str| properties.iter().position(|property| property.count.is_none() && property.name == wanted);
+            let position = [find("x"), find("y"), find("z")];
+            let normal = [find("nx"), find("ny"), find("nz")];
+            let color = [find("red"), find("green"), find("blue")];
+            let is_vertex = name == "vertex";
+            if is_vertex && position.iter().any(Option::is_none) {
+                return Err(invalid("vertices without x, y and z".to_string()));
+            }
+            let has_normal = is_vertex && normal.iter().all(Option::is_some);
+            let has_color = is_vertex && color.iter().all(Option::is_some);
+            let indices = (name == "face").then(|| {
+                properties.iter().position(|property| property.count.is_some() && matches!(property.name.as_str(), "vertex_indices" | "vertex_index"))
+            }).flatten();
+            
+            let mut values = vec![0.0; properties.len()];
+            let mut polygon = Vec::new();
+            for _ in 0..*count {
+                for (index, (value, property)) in values.iter_mut().zip(properties).enumerate() {
+                    match property.count {
+                        Some(count) => {
+                            let keep = indices == Some(index);
+                            polygon.clear();
+                            for _ in 0..reader.read(count)? as usize {
+                                let value = reader.read(property.value)?;
+                                if keep {
+                                    polygon.push(value as usize);
+                                }
+                            }
+                        }
+                        None => *value = reader.read(property.value)?,
+                    }
+                }
+                if is_vertex {
+                    let [x, y, z] = position.map(|index| values[index.unwrap_or(0)] as Float);
+                    scan.positions.push(Vec3::new(x, y, z));
+                    if has_normal {
+                        let [x, y, z] = normal.map(|index| values[index.unwrap_or(0)] as Float);
+                        scan.normals.get_or_insert_with(Vec::new).push(Vec3::new(x, y, z));
+                    }
+                    if has_color {
+                        let channels = color.map(|index| values[index.unwrap_or(0)]);
+                        let kind = properties[color[0].unwrap_or(0)].value;
+                        scan.colors.get_or_insert_with(Vec::new).push(channel_color(channels, kind));
+                    }
+                }
+                
+                // Polygons are split into fans of triangles around their first corner
+                if indices.is_some() {
+                    for corner in 2..polygon.len() {
+                        scan.faces.push([polygon[0], polygon[corner - 1], polygon[corner]]);
+                    }
+                }
+            }
+        }
+        if let Some(face) = scan.faces.iter().find(|face| face.iter().any(|&index| index >= scan.positions.len())) {
+            return Err(invalid(format!("face {:?} refers past the {} vertices", face, scan.positions.len())));
+        }
+        Ok(scan)
+    }
+    
+    /// Parse an XYZ point list: a point per line as x y z, optionally followed by red, green and blue
+    /// (from 0 to 255, or to 1 if no channel in the file exceeds 1) and anything else, which is ignored
+    /// Blank lines and lines starting with # or // are skipped
+    pub fn parse_xyz(text: &str) -> Result<Self, ScanError> {
+        let mut positions = Vec::new();
+        let mut channels: Vec<[f64; 3]> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+                continue;
+            }
+            let invalid = |reason: String| ScanError::InvalidXyz { line: index + 1, reason };
+            let values = line
+                .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+                .filter(|word| !word.is_empty())
+                .map(|word| word.parse::<f64>().map_err(|_| invalid(format!("'{}' is not a number", word))))
+                .collect::<Result<Vec<_>, _>>()?;
+            match values.len() {
+                0..=2 => return Err(invalid(format!("expected x y z, got {} values", values.len()))),
+                3..=5 if !channels.is_empty() => return Err(invalid("point without a color among colored ones".to_string())),
+                3..=5 => {}
+                _ if channels.len() < positions.len() => return Err(invalid("colored point among uncolored ones".to_string())),
+                _ => channels.push([values[3], values[4], values[5]]),
+            }
+            positions.push(Vec3::new(values[0] as Float, values[1] as Float, values[2] as Float));
+        }
+        
+        let kind = if channels.iter().flatten().any(|&channel| channel > 1.0) { PlyType::U8 } else { PlyType::F64 };
+        let colors = (!channels.is_empty()).then(|| channels.into_iter().map(|channels| channel_color(channels, kind)).collect());
+        Ok(Self { positions, colors, ..Self::default() })
+    }
+    
+    /// Parse an STL model, binary or ASCII: its facets become triangles, and corners at exactly the same
+    /// position become one shared vertex
+    /// STL stores no vertex normals, so the mesh is shaded flat; each facet's own normal is only used to turn
+    /// its corners counter-clockwise, for files that list them the other way
+    pub fn parse_stl(bytes: &[u8]) -> Result<Self, ScanError> {
+        // Binary files are an 80-byte header, a facet count and 50 bytes per facet, and may start with "solid" too
+        let binary_count = bytes.get(80..84).map(|count| u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize);
+        let facets: Vec<Facet> = match binary_count {
+            Some(count) if bytes.len() == 84 + 50 * count => bytes[84..]
+                .chunks_exact(50)
+                .map(|facet| {
+                    let value = |index: usize| f32::from_le_bytes([facet[4 * index], facet[4 * index + 1], facet[4 * index + 2], facet[4 * index + 3]]);
+                    ([value(0), value(1), value(2)], (1..4).map(|corner| [0, 1, 2].map(|axis| value(3 * corner + axis))).collect())
+                })
+                .collect(),
+            _ => parse_stl_ascii(&String::from_utf8_lossy(bytes))?,
+        };
+        
+        let mut scan = Scan::default();
+        let mut shared = HashMap::new(); // Vertex index of each position, by its bits
+        for (normal, corners) in facets {
+            let mut indices: Vec<usize> = corners
+                .iter()
+                .map(|&corner| {
+                    *shared.entry(corner.map(f32::to_bits)).or_insert_with(|| {
+                        scan.positions.push(Vec3::new(Float::from(corner[0]), Float::from(corner[1]), Float::from(corner[2])));
+                        scan.positions.len() - 1
+                    })
+                })
+                .collect();
+            let [a, b, c] = [0, 1, 2].map(|corner| scan.positions[indices[corner]]);
+            let stored = Vec3::new(Float::from(normal[0]), Float::from(normal[1]), Float::from(normal[2]));
+            if (b - a).cross(&(c - a)).dot(&stored) < 0.0 {
+                indices.reverse();
+            }
+            for corner in 2..indices.len() {
+                scan.faces.push([indices[0], indices[corner - 1], indices[corner]]);
+            }
+        }
+        Ok(scan)
+    }
+    
+    /// Read and parse a scan, as XYZ if its extension is `.xyz` (or `.txt`), as STL if it is `.stl`,
+    /// and as PLY otherwise
+    #[cfg(feature = "fs")]
+    pub fn load(path: &Path) -> Result<Self, ScanError> {
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_ascii_lowercase();
+        let bytes = std::fs::read(path)?;
+        match extension.as_str() {
+            "xyz" | "txt" => Self::parse_xyz(&String::from_utf8_lossy(&bytes)),
+            "stl" => Self::parse_stl(&bytes),
+            _ => Self::parse_ply(&bytes),
+        }
+    }
+}
+
+/// Stored normal and corners of an STL facet
+type Facet = ([f32; 3], Vec<[f32; 3]>);
+
+/// Facets of an ASCII STL file: `solid`, then per facet `facet normal` and three numbers,
+/// `outer loop`, `vertex` and three numbers per corner, `endloop` and `endfacet`, and finally `endsolid`
+fn parse_stl_ascii(text: &str) -> Result<Vec<Facet>, ScanError> {
+    let mut words = text.split_whitespace();
+    if words.next() != Some("solid") {
+        return Err(ScanError::InvalidStl("neither binary nor ASCII (which starts with 'solid')".to_string()));
+    }
+    let triple = |words: &mut std::str::SplitWhitespace, what: &str| -> Result<[f32; 3], ScanError> {
+        let mut number = || words.next().and_then(|word| word.parse().ok()).ok_or_else(|| ScanError::InvalidStl(format!("{} without three numbers", what)));
+        Ok([number()?, number()?, number()?])
+    };
+    
+    // Only the words that carry numbers or end a facet or the solid matter; the rest (and the solid's name) are skipped
+    let mut facets = Vec::new();
+    let (mut normal, mut corners) = ([0.0; 3], Vec::new());
+    while let Some(word) = words.next() {
+        match word {
+            "normal" => normal = triple(&mut words, "normal")?,
+            "vertex" => corners.push(triple(&mut words, "vertex")?),
+            "endfacet" if corners.len() < 3 => {
+                return Err(ScanError::InvalidStl(format!("facet {} has fewer than three corners", facets.len() + 1)));
+            }
+            "endfacet" => facets.push((normal, std::mem::take(&mut corners))),
+            "endsolid" => return Ok(facets),
+            _ => {}
+        }
+    }
+    Err(ScanError::InvalidStl("no endsolid (truncated, or binary with the wrong facet count)".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_ply() {
+        let ascii = b"ply\nformat ascii 1.0\ncomment made by hand\nelement vertex 2\nproperty float x\nproperty float y\n\
+            property float z\nproperty uchar red\nproperty uchar green\nproperty uchar blue\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n0 1 2 255 0 0\n3 4 5 0 0 255\n3 0 1 1\n";
+        let scan = Scan::parse_ply(ascii).unwrap();
+        assert_eq!(scan.positions, vec![Vec3::new(0.0, 1.0, 2.0), Vec3::new(3.0, 4.0, 5.0)]);
+        assert_eq!(scan.colors.unwrap()[1], Color::from_srgb8([0, 0, 255]));
+        
+        // The same vertices, binary and big-endian, with a double among the floats and no colors
+        let mut binary = b"ply\nformat binary_big_endian 1.0\nelement vertex 2\nproperty float x\nproperty double y\n\
+            property float z\nend_header\n".to_vec();
+        for [x, y, z] in [[0.0f32, 1.0, 2.0], [3.0, 4.0, 5.0]] {
+            binary.extend([x.to_be_bytes().as_slice(), &(y as f64).to_be_bytes(), &z.to_be_bytes()].concat());
+        }
+        let scan = Scan::parse_ply(&binary).unwrap();
+        assert_eq!(scan.positions[1], Vec3::new(3.0, 4.0, 5.0));
+        assert!(scan.colors.is_none());
+        assert!(matches!(Scan::parse_ply(&binary[..binary.len() - 1]), Err(ScanError::InvalidPly(_))));
+        
+        // A quad with normals, split into two triangles, its face carrying a property the parser skips
+        let mut quad = b"ply\nformat binary_little_endian 1.0\nelement vertex 4\nproperty float x\nproperty float y\n\
+            property float z\nproperty float nx\nproperty float ny\nproperty float nz\nelement face 1\n\
+            property uchar flags\nproperty list uchar uint vertex_indices\nend_header\n".to_vec();
+        for [x, z] in [[0.0f32, 0.0], [1.0, 0.0], [1.0, -1.0], [0.0, -1.0]] {
+            quad.extend([x, 0.0, z, 0.0, 1.0, 0.0].iter().flat_map(|value| value.to_le_bytes()));
+        }
+        quad.extend([7, 4]);
+        quad.extend([0u32, 1, 2, 3].iter().flat_map(|index| index.to_le_bytes()));
+        let scan = Scan::parse_ply(&quad).unwrap();
+        assert_eq!(scan.faces, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(scan.normals.unwrap()[3], Vec3::unit_y());
+        let last = quad.len() - 4;
+        quad[last] = 4;
+        assert!(matches!(Scan::parse_ply(&quad), Err(ScanError::InvalidPly(_))));
+    }
+    
+    #[test]
+    fn test_parse_stl() {
+        // A square facing up as two facets sharing an edge, the second listed clockwise
+        let ascii = "solid square\n\
+            facet normal 0 1 0\n outer loop\n vertex 0 0 0\n vertex 1 0 0\n vertex 1 0 -1\n endloop\nendfacet\n\
+            facet normal 0 1 0\n outer loop\n vertex 0 0 0\n vertex 0 0 -1\n vertex 1 0 -1\n endloop\nendfacet\n\
+            endsolid square\n";
+        let scan = Scan::parse_stl(ascii.as_bytes()).unwrap();
+        assert_eq!(scan.positions.len(), 4);
+        assert_eq!(scan.faces, vec![[0, 1, 2], [2, 3, 0]]);
+        assert!(scan.normals.is_none());
+        
+        // The same facets in binary, behind a header that starts with "solid" as some exporters write
+        let mut binary = b"solid".to_vec();
+        binary.resize(80, b' ');
+        binary.extend(2u32.to_le_bytes());
+        for corners in [[[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, -1.0]], [[0.0, 0.0, 0.0], [0.0, 0.0, -1.0], [1.0, 0.0, -1.0]]] {
+            binary.extend([0.0f32, 1.0, 0.0].iter().chain(corners.iter().flatten()).flat_map(|value| value.to_le_bytes()));
+            binary.extend([0, 0]);
+        }
+        assert_eq!(Scan::parse_stl(&binary).unwrap(), scan);
+        assert!(matches!(Scan::parse_stl(&binary[..binary.len() - 1]), Err(ScanError::InvalidStl(_))));
+    }
+    
+    #[test]
+    fn test_parse_xyz() {
+        let scan = Scan::parse_xyz("# scan\n0 1 2 1 0 0\n3,4,5,0,0,0.5\n").unwrap();
+        assert_eq!(scan.positions[1], Vec3::new(3.0, 4.0, 5.0));
+        assert_eq!(scan.colors.unwrap()[0], Color::from_srgb8([255, 0, 0]));
+        assert!(Scan::parse_xyz("0 1 2\n3 4 5 255 0 0\n").is_err());
+        assert!(matches!(Scan::parse_xyz("0 1\n"), Err(ScanError::InvalidXyz { line: 1, .. })));
+    }
+}
@@ -1,30 +1,385 @@
-use crate::math::{Vec3, Ray};
-use crate::material::Material;
-use crate::shapes::{HitInfo, Intersectable};
+use crate::math::{consts::PI, Aabb, Float, Vec3, Ray, Interval};
+use crate::color::Color;
+use crate::shapes::{HitInfo, Intersectable, ConstantMedium};
+#[cfg(not(feature = "simd"))]
+use crate::shapes::SphereList;
+use crate::environment::Environment;
+use crate::ies::IesProfile;
+use crate::stats;
+use crate::bvh::{Bvh, BvhOptions};
+#[cfg(not(feature = "simd"))]
+use crate::bvh::SplitMethod;
+use crate::kdtree::KdTree;
+#[cfg(feature = "simd")]
+use crate::simd::PackedScene;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+/// Fewest spheres a scene needs before they are gathered into `SphereList`s
+#[cfg(not(feature = "simd"))]
+const SPHERE_LIST_MIN_SPHERES: usize = 64;
+
+/// Most spheres in each `SphereList`, which become the leaves of the top level
+#[cfg(not(feature = "simd"))]
+const SPHERE_LIST_SIZE: usize = 16;
+
+/// Spatial index over a list of items (shapes or scene objects) referred to by index
+pub trait Accelerator: Send + Sync {
+    /// Offer the ray the items it may hit before t_max: `visit(item, t_max)` intersects an item and
+    /// returns the distance of a hit closer than t_max, which then becomes the new limit
+    fn traverse(&self, ray: &Ray, t_max: Float, visit: &mut dyn FnMut(usize, Float) -> Option<Float>);
+    
+    /// Like `traverse` for a packet of rays, with `visit(ray, item, t_max)` and each ray's limit in `t_max`
+    /// Accelerators that can't share work between rays trace them one at a time
+    fn traverse_packet(&self, rays: &[Ray], t_max: &mut [Float], visit: &mut dyn FnMut(usize, usize, Float) -> Option<Float>) {
+        for (r, ray) in rays.iter().enumerate() {
+            self.traverse(ray, t_max[r], &mut |item, limit| {
+                let t = visit(r, item, limit)?;
+                t_max[r] = t;
+                Some(t)
+            });
+        }
+    }
+    
+    /// Bounds of every item, or None if some are unbounded
+    fn bounds(&self) -> Option<Aabb>;
+    
+    /// One-line summary for logs
+    fn describe(&self) -> String;
+    
+    /// Rough bytes the accelerator takes
+    fn memory(&self) -> usize {
+        size_of_val(self)
+    }
+}
+
+impl Accelerator for Bvh {
+    fn traverse(&self, ray: &Ray, t_max: Float, visit: &mut dyn FnMut(usize, Float) -> Option<Float>) {
+        Bvh::traverse(self, ray, t_max, visit);
+    }
+    
+    fn traverse_packet(&self, rays: &[Ray], t_max: &mut [Float], visit: &mut dyn FnMut(usize, usize, Float) -> Option<Float>) {
+        Bvh::traverse_packet(self, rays, t_max, visit);
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        Bvh::bounds(self)
+    }
+    
+    fn describe(&self) -> String {
+        format!("BVH with {} nodes, cost {:.2}", self.node_count(), self.cost())
+    }
+    
+    fn memory(&self) -> usize {
+        size_of_val(self) + Bvh::memory(self)
+    }
+}
+
+impl Accelerator for KdTree {
+    fn traverse(&self, ray: &Ray, t_max: Float, visit: &mut dyn FnMut(usize, Float) -> Option<Float>) {
+        KdTree::traverse(self, ray, t_max, visit);
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        KdTree::bounds(self)
+    }
+    
+    fn describe(&self) -> String {
+        format!("kd-tree with {} nodes, {} item references", self.node_count(), self.reference_count())
+    }
+    
+    fn memory(&self) -> usize {
+        size_of_val(self) + KdTree::memory(self)
+    }
+}
+
+/// Every item offered to every ray, in order: slow, but useful for checking the other accelerators
+struct LinearScan {
+    count: usize,
+    bounds: Option<Aabb>,
+}
+
+impl Accelerator for LinearScan {
+    fn traverse(&self, _ray: &Ray, mut t_max: Float, visit: &mut dyn FnMut(usize, Float) -> Option<Float>) {
+        for item in 0..self.count {
+            if let Some(t) = visit(item, t_max) {
+                t_max = t;
+            }
+        }
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+    
+    fn describe(&self) -> String {
+        "linear scan".to_string()
+    }
+}
+
+/// Which spatial index scenes and groups are built with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AcceleratorKind {
+    #[default]
+    Bvh,
+    KdTree,
+    None, // Linear scan
+}
+
+impl FromStr for AcceleratorKind {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bvh" => Ok(Self::Bvh),
+            "kdtree" => Ok(Self::KdTree),
+            "none" => Ok(Self::None),
+            _ => Err(format!("unknown accelerator '{}' (expected bvh, kdtree or none)", s)),
+        }
+    }
+}
+
+impl fmt::Display for AcceleratorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Bvh => "bvh",
+            Self::KdTree => "kdtree",
+            Self::None => "none",
+        })
+    }
+}
+
+/// Accelerator choice and build parameters
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AcceleratorOptions {
+    pub kind: AcceleratorKind,
+    pub bvh: BvhOptions, // Used when `kind` is Bvh
+}
+
+impl AcceleratorOptions {
+    /// Build over items with the given bounds (None = unbounded)
+    pub fn build(&self, bounds: &[Option<Aabb>]) -> Box<dyn Accelerator> {
+        match self.kind {
+            AcceleratorKind::Bvh => Box::new(Bvh::with_options(bounds, &self.bvh)),
+            AcceleratorKind::KdTree => Box::new(KdTree::new(bounds)),
+            AcceleratorKind::None => {
+                let total = bounds.iter().try_fold(Aabb::empty(), |total, b| b.map(|b| total.union(&b)));
+                Box::new(LinearScan { count: bounds.len(), bounds: total })
+            }
+        }
+    }
+}
 
 /// Light source for illumination
 #[derive(Debug, Clone)]
 pub struct Light {
     pub position: Vec3,
-    pub intensity: f64,
-    pub color: Vec3,
+    pub intensity: Float,
+    pub color: Color,
+    pub radius: Float,         // Sphere light radius; 0 is a point light (only the path tracer uses the size)
+    pub casts_shadows: bool,   // Whether objects block the light; without shadows no shadow rays are traced toward it
+    pub links: LightLinks,     // Which objects the light illuminates
+    pub falloff: bool,         // Whether the intensity falls off with the squared distance (physical units)
+    pub profile: Option<Arc<IesProfile>>, // Measured distribution scaling the intensity by direction
 }
 
+/// Luminous efficacy of light at the eye's peak sensitivity (555 nm), converting watts to lumens
+pub const LUMENS_PER_WATT: Float = 683.0;
+
 impl Light {
-    pub fn new(position: Vec3, intensity: f64, color: Vec3) -> Self {
-        Self { position, intensity, color }
+    pub fn new(position: Vec3, intensity: Float, color: Color) -> Self {
+        Self { position, intensity, color, radius: 0.0, casts_shadows: true, links: LightLinks::All, falloff: false, profile: None }
+    }
+    
+    pub fn white_light(position: Vec3, intensity: Float) -> Self {
+        Self::new(position, intensity, Color::white())
+    }
+    
+    /// Light with the color of a blackbody at `kelvin` (see `Color::from_kelvin`)
+    pub fn from_kelvin(position: Vec3, intensity: Float, kelvin: Float) -> Self {
+        Self::new(position, intensity, Color::from_kelvin(kelvin))
     }
     
-    pub fn white_light(position: Vec3, intensity: f64) -> Self {
-        Self::new(position, intensity, Vec3::new(1.0, 1.0, 1.0))
+    /// Light emitting `lumens` in all directions, dimming with the squared distance as a real light does
+    /// Radiance then comes out in cd/m² (for a white light), for the camera's `Exposure` to map to pixel values
+    pub fn from_lumens(position: Vec3, lumens: Float, color: Color) -> Self {
+        // Luminous intensity Φ/4π, divided by π as shading multiplies albedo by intensity without the 1/π
+        Self { falloff: true, ..Self::new(position, lumens / (4.0 * PI * PI), color) }
+    }
+    
+    /// Light emitting `watts` of radiant power, counted at `LUMENS_PER_WATT`
+    pub fn from_watts(position: Vec3, watts: Float, color: Color) -> Self {
+        Self::from_lumens(position, watts * LUMENS_PER_WATT, color)
+    }
+    
+    /// Spherical light: soft shadows and visible highlights in the path tracer, a point light otherwise
+    pub fn sphere(position: Vec3, radius: Float, intensity: Float, color: Color) -> Self {
+        Self { radius, ..Self::new(position, intensity, color) }
+    }
+    
+    /// The same light, shining through objects: a fill light that costs no shadow rays
+    pub fn without_shadows(self) -> Self {
+        Self { casts_shadows: false, ..self }
+    }
+    
+    /// Intensity arriving at `point`: the same everywhere, or falling off with the squared distance,
+    /// and shaped by the light's IES profile if it has one
+    pub fn intensity_at(&self, point: Vec3) -> Float {
+        let to_point = point - self.position;
+        let intensity = if self.falloff { self.intensity / to_point.length_squared() } else { self.intensity };
+        match &self.profile {
+            Some(profile) => intensity * profile.relative(to_point),
+            None => intensity,
+        }
+    }
+    
+    /// Radiance leaving a sphere light toward `point`, scaled with the squared distance so that
+    /// the light delivers the same irradiance as a point light of equal intensity at any range
+    pub fn radiance_toward(&self, point: Vec3) -> Vec3 {
+        let distance_squared = (self.position - point).length_squared();
+        Vec3::from(self.color) * (self.intensity_at(point) * distance_squared / (self.radius * self.radius))
+    }
+}
+
+/// Objects a light illuminates, by index into `Scene::objects` (light linking)
+/// Objects left out are shaded as if the light weren't there, but may still cast its shadows
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum LightLinks {
+    #[default]
+    All,
+    Only(Vec<usize>),
+    Except(Vec<usize>),
+}
+
+impl LightLinks {
+    pub fn illuminates(&self, object: usize) -> bool {
+        match self {
+            LightLinks::All => true,
+            LightLinks::Only(objects) => objects.contains(&object),
+            LightLinks::Except(objects) => !objects.contains(&object),
+        }
+    }
+}
+
+/// Kind of ray being traced, used to apply per-object visibility flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RayKind {
+    Camera,
+    Shadow,
+    Reflection,
+}
+
+/// Per-object visibility controls and nesting priority honored by the renderer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectFlags {
+    pub cast_shadows: bool,           // Blocks shadow rays
+    pub visible_to_camera: bool,      // Seen by camera rays (and rays continuing through transparent surfaces)
+    pub visible_in_reflections: bool, // Seen by mirror reflection rays
+    pub priority: u32,                // Where refractive objects overlap, the highest priority fills the overlap
+}
+
+impl ObjectFlags {
+    /// Whether an object with these flags can be hit by the given kind of ray
+    pub fn visible_to(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.visible_to_camera,
+            RayKind::Shadow => self.cast_shadows,
+            RayKind::Reflection => self.visible_in_reflections,
+        }
+    }
+}
+
+impl Default for ObjectFlags {
+    fn default() -> Self {
+        Self {
+            cast_shadows: true,
+            visible_to_camera: true,
+            visible_in_reflections: true,
+            priority: 0,
+        }
+    }
+}
+
+/// Shape placed in a scene along with its visibility flags
+pub struct SceneObject {
+    pub shape: Box<dyn Intersectable>,
+    pub flags: ObjectFlags,
+}
+
+/// What the top-level accelerator indexes: a scene object, or nearby spheres intersected together
+/// Only spheres visible to every kind of ray are gathered, so the lists never need visibility checks
+#[cfg(not(feature = "simd"))]
+enum Entry {
+    Object(usize),
+    Spheres { list: SphereList, objects: Vec<usize> }, // Object index of each sphere in the list
+}
+
+/// Accelerator over a scene's objects, with many spheres gathered into lists
+#[cfg(not(feature = "simd"))]
+struct TopLevel {
+    accelerator: Box<dyn Accelerator>,
+    entries: Vec<Entry>,
+}
+
+#[cfg(not(feature = "simd"))]
+impl TopLevel {
+    fn new(objects: &[SceneObject], options: &AcceleratorOptions) -> Self {
+        let spheres: Vec<(usize, (Vec3, Float))> = objects
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| object.flags == ObjectFlags::default())
+            .filter_map(|(index, object)| Some((index, object.shape.sphere()?)))
+            .collect();
+        let mut entries = Vec::new();
+        let mut gathered = vec![false; objects.len()];
+        
+        if spheres.len() >= SPHERE_LIST_MIN_SPHERES {
+            // A median-split BVH's leaves are groups of up to SPHERE_LIST_SIZE spheres near each other
+            let bounds: Vec<Option<Aabb>> = spheres.iter().map(|(index, _)| objects[*index].shape.bounds()).collect();
+            let clusters = Bvh::with_options(
+                &bounds,
+                &BvhOptions { split: SplitMethod::Median, max_leaf_size: SPHERE_LIST_SIZE, ..BvhOptions::default() },
+            );
+            for leaf in clusters.leaves() {
+                let list = SphereList::new(&leaf.iter().map(|&i| spheres[i].1).collect::<Vec<_>>());
+                let indices: Vec<usize> = leaf.iter().map(|&i| spheres[i].0).collect();
+                indices.iter().for_each(|&index| gathered[index] = true);
+                entries.push(Entry::Spheres { list, objects: indices });
+            }
+        }
+        entries.extend((0..objects.len()).filter(|&index| !gathered[index]).map(Entry::Object));
+        
+        let bounds: Vec<Option<Aabb>> = entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::Object(index) => objects[*index].shape.bounds(),
+                Entry::Spheres { list, .. } => Some(list.bounds()),
+            })
+            .collect();
+        let accelerator = options.build(&bounds);
+        log::debug!(
+            "Top level over {} objects ({} sphere lists): {}",
+            objects.len(),
+            entries.iter().filter(|entry| matches!(entry, Entry::Spheres { .. })).count(),
+            accelerator.describe()
+        );
+        Self { accelerator, entries }
     }
 }
 
 /// Scene containing objects and lights
 pub struct Scene {
-    pub objects: Vec<Box<dyn Intersectable>>,
+    pub objects: Vec<SceneObject>, // Add objects with add_object so the accelerator (or SIMD packing) is rebuilt
     pub lights: Vec<Light>,
+    pub media: Vec<ConstantMedium>,
     pub background_color: Vec3,
+    pub environment: Option<Environment>, // Replaces the background color and lights the path tracer
+    pub accelerator: AcceleratorOptions,  // How the top-level accelerator is built (on the first intersection query)
+    #[cfg(not(feature = "simd"))]
+    top_level: OnceLock<TopLevel>,         // Accelerator over the objects, built on the first intersection query
+    #[cfg(feature = "simd")]
+    packed: OnceLock<PackedScene>,         // Spheres and boxes in SIMD lanes (and an accelerator over the rest), built on the first query
 }
 
 impl Scene {
@@ -32,32 +387,294 @@ impl Scene {
         Self {
             objects: Vec::new(),
             lights: Vec::new(),
+            media: Vec::new(),
             background_color: Vec3::new(0.2, 0.3, 0.5), // Sky blue background
+            environment: None,
+            accelerator: AcceleratorOptions::default(),
+            #[cfg(not(feature = "simd"))]
+            top_level: OnceLock::new(),
+            #[cfg(feature = "simd")]
+            packed: OnceLock::new(),
         }
     }
     
     pub fn add_object(&mut self, object: Box<dyn Intersectable>) {
-        self.objects.push(object);
+        self.add_object_with_flags(object, ObjectFlags::default());
+    }
+    
+    pub fn add_object_with_flags(&mut self, object: Box<dyn Intersectable>, flags: ObjectFlags) {
+        self.objects.push(SceneObject { shape: object, flags });
+        #[cfg(not(feature = "simd"))]
+        {
+            self.top_level = OnceLock::new();
+        }
+        #[cfg(feature = "simd")]
+        {
+            self.packed = OnceLock::new();
+        }
     }
     
     pub fn add_light(&mut self, light: Light) {
         self.lights.push(light);
     }
     
-    /// Find closest intersection with any object in the scene
+    pub fn add_medium(&mut self, medium: ConstantMedium) {
+        self.media.push(medium);
+    }
+    
+    /// Radiance of the background seen in `direction`: the environment map, else the background color
+    pub fn background(&self, direction: Vec3) -> Vec3 {
+        match &self.environment {
+            Some(environment) => environment.radiance(direction),
+            None => self.background_color,
+        }
+    }
+    
+    /// Find closest intersection with any object in the scene, ignoring visibility flags
     pub fn intersect(&self, ray: &Ray) -> Option<HitInfo> {
+        self.closest_hit(ray, Float::INFINITY, |_| true)
+    }
+    
+    /// Find closest intersection with objects visible to the given kind of ray
+    pub fn intersect_for(&self, ray: &Ray, kind: RayKind) -> Option<HitInfo> {
+        self.intersect_within(ray, kind, Float::INFINITY)
+    }
+    
+    /// `intersect_for`, ignoring anything at t_max or beyond (which the accelerator then doesn't visit)
+    pub fn intersect_within(&self, ray: &Ray, kind: RayKind, t_max: Float) -> Option<HitInfo> {
+        self.closest_hit(ray, t_max, |object| object.flags.visible_to(kind))
+    }
+    
+    /// Objects whose bounds the ray misses, or that lie beyond the closest hit so far, are skipped by the
+    /// accelerator (instances then traverse their geometry's own: a two-level hierarchy)
+    #[cfg(not(feature = "simd"))]
+    fn closest_hit(&self, ray: &Ray, t_max: Float, include: impl Fn(&SceneObject) -> bool) -> Option<HitInfo> {
         let mut closest_hit = None;
-        let mut closest_t = f64::INFINITY;
+        let mut tests = 0;
         
-        for object in &self.objects {
-            if let Some(hit) = object.intersect(ray) {
-                if hit.t < closest_t {
-                    closest_t = hit.t;
-                    closest_hit = Some(hit);
-                }
+        let top_level = self.top_level();
+        top_level.accelerator.traverse(ray, t_max, &mut |entry, t_max| {
+            let hit = self.hit_entry(&top_level.entries[entry], ray, t_max, &include, &mut tests)?;
+            let t = hit.t;
+            closest_hit = Some(hit);
+            Some(t)
+        });
+        
+        stats::count_ray(tests);
+        closest_hit
+    }
+    
+    /// Hit with an object (or the first of a list of spheres) closer than t_max, counting the objects tested
+    #[cfg(not(feature = "simd"))]
+    fn hit_entry(&self, entry: &Entry, ray: &Ray, t_max: Float, include: &impl Fn(&SceneObject) -> bool, tests: &mut u64) -> Option<HitInfo> {
+        let index = match entry {
+            Entry::Object(index) => *index,
+            Entry::Spheres { list, objects } => {
+                *tests += list.len() as u64;
+                let (i, _) = list.closest(ray, Interval::hits(t_max))?;
+                let hit = self.objects[objects[i]].shape.intersect(ray, Interval::hits(t_max))?; // For its normal and material
+                return Some(HitInfo { object: objects[i], ..hit });
             }
+        };
+        let object = &self.objects[index];
+        if !include(object) {
+            return None;
         }
+        *tests += 1;
+        let hit = object.shape.intersect_sided(ray, Interval::hits(t_max))?;
+        Some(HitInfo { object: index, ..hit })
+    }
+    
+    #[cfg(not(feature = "simd"))]
+    fn top_level(&self) -> &TopLevel {
+        self.top_level.get_or_init(|| TopLevel::new(&self.objects, &self.accelerator))
+    }
+    
+    /// Like the scalar version, but spheres and boxes are tested four at a time first, and only
+    /// fully intersected (for the hit's normal and material) when they are the closest so far;
+    /// the other objects are then found through an accelerator of their own
+    #[cfg(feature = "simd")]
+    fn closest_hit(&self, ray: &Ray, t_max: Float, include: impl Fn(&SceneObject) -> bool) -> Option<HitInfo> {
+        let mut closest_hit = None;
+        let mut closest_t = t_max;
+        let mut tests = 0;
         
+        let packed = self.packed.get_or_init(|| PackedScene::new(&self.objects, &self.accelerator));
+        packed.distances(ray, |index, distance| {
+            let object = &self.objects[index];
+            if !include(object) {
+                return;
+            }
+            tests += 1;
+            if distance >= closest_t {
+                return;
+            }
+            if let Some(hit) = object.shape.intersect_sided(ray, Interval::hits(closest_t)) {
+                closest_t = hit.t;
+                closest_hit = Some(HitInfo { object: index, ..hit });
+            }
+        });
+        packed.traverse_others(ray, closest_t, |index, t_max| {
+            let object = &self.objects[index];
+            if !include(object) {
+                return None;
+            }
+            tests += 1;
+            let hit = object.shape.intersect_sided(ray, Interval::hits(t_max))?;
+            let t = hit.t;
+            closest_hit = Some(HitInfo { object: index, ..hit });
+            Some(t)
+        });
+        
+        stats::count_ray(tests);
         closest_hit
     }
+    
+    /// Closest hits of a packet of coherent camera rays, found by walking the accelerator once for the
+    /// whole packet where it supports that (the BVH does); the same hits as `intersect_within` on each ray
+    #[cfg(not(feature = "simd"))]
+    pub fn intersect_packet(&self, rays: &[Ray], kind: RayKind, t_max: Float) -> Vec<Option<HitInfo>> {
+        if rays.len() == 1 {
+            return vec![self.intersect_within(&rays[0], kind, t_max)];
+        }
+        let mut hits: Vec<Option<HitInfo>> = rays.iter().map(|_| None).collect();
+        let mut t_max = vec![t_max; rays.len()];
+        let mut tests = vec![0; rays.len()];
+        
+        let top_level = self.top_level();
+        let include = |object: &SceneObject| object.flags.visible_to(kind);
+        top_level.accelerator.traverse_packet(rays, &mut t_max, &mut |r, entry, limit| {
+            let hit = self.hit_entry(&top_level.entries[entry], &rays[r], limit, &include, &mut tests[r])?;
+            let t = hit.t;
+            hits[r] = Some(hit);
+            Some(t)
+        });
+        
+        tests.into_iter().for_each(stats::count_ray);
+        hits
+    }
+    
+    /// Closest hits of a packet of rays: SIMD builds test them one at a time against the packed shapes
+    #[cfg(feature = "simd")]
+    pub fn intersect_packet(&self, rays: &[Ray], kind: RayKind, t_max: Float) -> Vec<Option<HitInfo>> {
+        rays.iter().map(|ray| self.intersect_within(ray, kind, t_max)).collect()
+    }
+    
+    /// Closest sphere light the ray hits before t_max, as (light index, t)
+    pub fn intersect_light(&self, ray: &Ray, t_max: Float) -> Option<(usize, Float)> {
+        let mut closest = None;
+        let mut range = Interval::hits(t_max);
+        
+        for (index, light) in self.lights.iter().enumerate().filter(|(_, light)| light.radius > 0.0) {
+            let oc = ray.origin - light.position;
+            let a = ray.direction.length_squared();
+            let half_b = oc.dot(&ray.direction);
+            let c = oc.length_squared() - light.radius * light.radius;
+            let discriminant = half_b * half_b - a * c;
+            if discriminant < 0.0 {
+                continue;
+            }
+            
+            let t = (-half_b - discriminant.sqrt()) / a;
+            if range.surrounds(t) {
+                range.max = t;
+                closest = Some((index, t));
+            }
+        }
+        closest
+    }
+    
+    /// Fraction of light surviving all participating media along the ray up to t_max
+    pub fn media_transmittance(&self, ray: &Ray, t_max: Float) -> Float {
+        self.media
+            .iter()
+            .map(|medium| medium.transmittance(ray, t_max))
+            .product()
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::shapes::{Sphere, Plane, Triangle};
+    
+    #[test]
+    fn test_object_flags_filter_rays() {
+        let mut scene = Scene::new();
+        let blocker = ObjectFlags {
+            visible_to_camera: false,
+            ..ObjectFlags::default()
+        };
+        scene.add_object_with_flags(
+            Box::new(Sphere::new(Vec3::new(0.0, 0.0, -3.0), 1.0, Material::red())),
+            blocker,
+        );
+        
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        assert!(scene.intersect(&ray).is_some());
+        assert!(scene.intersect_for(&ray, RayKind::Camera).is_none());
+        assert!(scene.intersect_for(&ray, RayKind::Shadow).is_some());
+        assert!(scene.intersect_for(&ray, RayKind::Reflection).is_some());
+    }
+    
+    #[test]
+    fn test_single_sided_surfaces() {
+        // A single-sided wall facing +z in front of a sphere: seen from behind it is passed through
+        let mut scene = Scene::new();
+        scene.add_object(Box::new(Plane::new(Vec3::new(0.0, 0.0, -2.0), Vec3::unit_z(), Material::gray().single_sided())));
+        scene.add_object(Box::new(Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, Material::red())));
+        
+        let toward = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        assert!(scene.intersect(&toward).is_some_and(|hit| hit.t == 2.0 && hit.front_face));
+        let back = Ray::new(Vec3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(scene.intersect(&back).is_none());
+        
+        // Inside a single-sided sphere only the wall shows
+        scene.add_object(Box::new(Sphere::new(Vec3::zero(), 10.0, Material::blue().single_sided())));
+        assert!(scene.intersect(&toward).is_some_and(|hit| hit.t == 2.0));
+        assert!(scene.intersect(&Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, 1.0))).is_none());
+    }
+    
+    #[test]
+    fn test_accelerators_agree() {
+        // Spheres (enough to be gathered into lists) and triangles along a line, behind an unbounded plane;
+        // every accelerator must find the same hits as testing each object in turn
+        let build = |kind: AcceleratorKind| {
+            let mut scene = Scene::new();
+            scene.accelerator.kind = kind;
+            scene.add_object(Box::new(Plane::horizontal(-2.0, Material::gray())));
+            for i in 0..80 {
+                let x = i as Float * 0.25 - 10.0;
+                scene.add_object(Box::new(Sphere::new(Vec3::new(x, 0.0, -6.0 - (i % 3) as Float), 0.3, Material::red())));
+                scene.add_object(Box::new(Triangle::new(
+                    Vec3::new(x, 1.0, -5.0),
+                    Vec3::new(x + 0.2, 1.0, -5.0),
+                    Vec3::new(x, 1.5, -5.5),
+                    Material::blue(),
+                )));
+            }
+            scene
+        };
+        let scenes = [build(AcceleratorKind::None), build(AcceleratorKind::Bvh), build(AcceleratorKind::KdTree)];
+        
+        for i in 0..400 {
+            let (u, v) = ((i % 20) as Float / 10.0 - 1.0, (i / 20) as Float / 10.0 - 1.0);
+            let ray = Ray::new(Vec3::new(0.0, 0.5, 0.0), Vec3::new(2.0 * u, v, -1.0));
+            let expected = scenes[0]
+                .objects
+                .iter()
+                .filter_map(|object| object.shape.intersect(&ray, Interval::FORWARD).map(|hit| hit.t))
+                .reduce(Float::min);
+            for scene in &scenes {
+                assert_eq!(scene.intersect(&ray).map(|hit| hit.t), expected, "{:?} with {:?}", scene.accelerator.kind, ray);
+            }
+        }
+    }
 }
@@ -0,0 +1,2133 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::math::{Float, Vec3, Quaternion, Aabb};
+use crate::color::Color;
+use crate::camera::{Camera, Exposure, Projection};
+use crate::material::Material;
+use crate::shapes::{Sphere, Plane, Cube, Cylinder, Capsule, Curve, CurveBasis, curve, Heightfield, VoxelGrid, PointCloud, Splat, Mesh, Shading, Group, Instance, Intersectable, Transform};
+use crate::scene::{Scene, Light, ObjectFlags, LightLinks, AcceleratorOptions};
+use crate::environment::Environment;
+use crate::sky::Sky;
+use crate::ies::IesProfile;
+use crate::texture::{Texture, Wrap, Filter};
+use crate::sampling::Rng;
+use crate::validate::Diagnostic;
+#[cfg(feature = "fs")]
+use crate::ies::IesError;
+#[cfg(feature = "fs")]
+use crate::vox::{VoxModel, VoxError};
+#[cfg(feature = "fs")]
+use crate::scan::{Scan, ScanError};
+#[cfg(feature = "fs")]
+use crate::shapes::mesh;
+#[cfg(feature = "fs")]
+use crate::gltf::{GltfScene, GltfError};
+#[cfg(feature = "fs")]
+use crate::hdr::HdrImage;
+use crate::image::RgbaImage;
+#[cfg(feature = "fs")]
+use crate::image::ImageError;
+
+/// How deep `base` chains between materials may go before we assume a cycle
+const MAX_MATERIAL_DEPTH: usize = 16;
+
+/// How deeply scene files may include each other before we assume a cycle
+#[cfg(feature = "fs")]
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Field of view for cameras that don't set one, as on the command line
+pub const DEFAULT_FOV: f64 = 45.0;
+
+/// Errors from loading or building a scene file
+#[derive(Debug, thiserror::Error)]
+pub enum SceneError {
+    #[error("could not read scene file: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid scene file: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("unknown material '{0}' (not in the materials table or built-in library)")]
+    UnknownMaterial(String),
+    #[error("material '{0}' has a cyclic 'base' chain")]
+    MaterialCycle(String),
+    #[error("cannot set '{key}': {reason}")]
+    InvalidParameter { key: String, reason: String },
+    #[error("includes can't be read without file access; flatten the scene with `rt export` first")]
+    IncludesUnavailable,
+    #[error("{0}")]
+    Invalid(Diagnostic), // An error `validate` found
+    #[cfg(feature = "fs")]
+    #[error("could not load environment map '{}': {source}", path.display())]
+    Environment { path: PathBuf, source: ImageError },
+    #[error("environment maps can't be read without file access")]
+    EnvironmentUnavailable,
+    #[cfg(feature = "fs")]
+    #[error("could not load IES profile '{}': {source}", path.display())]
+    Ies { path: PathBuf, source: IesError },
+    #[error("IES profiles can't be read without file access")]
+    IesUnavailable,
+    #[cfg(feature = "fs")]
+    #[error("could not load texture '{}': {source}", path.display())]
+    Texture { path: PathBuf, source: ImageError },
+    #[error("textures can't be read without file access")]
+    TextureUnavailable,
+    #[cfg(feature = "fs")]
+    #[error("could not load height field '{}': {source}", path.display())]
+    Heightfield { path: PathBuf, source: ImageError },
+    #[error("height fields can't be read without file access")]
+    HeightfieldUnavailable,
+    #[cfg(feature = "fs")]
+    #[error("could not load voxels '{}': {source}", path.display())]
+    Voxels { path: PathBuf, source: VoxError },
+    #[error("voxel files can't be read without file access")]
+    VoxelsUnavailable,
+    #[cfg(feature = "fs")]
+    #[error("could not load points '{}': {source}", path.display())]
+    Points { path: PathBuf, source: ScanError },
+    #[error("point clouds can't be read without file access")]
+    PointsUnavailable,
+    #[cfg(feature = "fs")]
+    #[error("could not load mesh '{}': {source}", path.display())]
+    Mesh { path: PathBuf, source: ScanError },
+    #[error("meshes can't be read without file access")]
+    MeshUnavailable,
+    #[cfg(feature = "fs")]
+    #[error("could not load glTF '{}': {source}", path.display())]
+    Gltf { path: PathBuf, source: GltfError },
+    #[error("glTF files can't be read without file access")]
+    GltfUnavailable,
+    #[error("texture scale must be positive, got {0}")]
+    InvalidTextureScale(f64),
+    #[error("scene has both a sky and an environment map; keep one")]
+    SkyAndEnvironment,
+    #[error("scene has no camera (only included files may omit it)")]
+    MissingCamera,
+    #[error("unknown geometry '{0}' (not in the geometry table)")]
+    UnknownGeometry(String),
+    #[error("geometry '{0}' contains an instance; instances can't be nested")]
+    NestedInstance(String),
+    #[error("unknown camera '{0}' (not in the cameras table)")]
+    UnknownCamera(String),
+    #[error("unknown object '{0}' (no top-level object has that name)")]
+    UnknownObject(String),
+    #[error("include scale must be positive, got {0}")]
+    InvalidScale(f64),
+    #[error("include of '{}' is nested too deeply (cyclic include?)", .0.display())]
+    IncludeCycle(PathBuf),
+    #[error("in included file '{}': {source}", path.display())]
+    Include { path: PathBuf, source: Box<SceneError> },
+}
+
+/// Top-level scene file (JSON)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDesc {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<IncludeDesc>, // Other scene files merged into this one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera: Option<CameraDesc>, // Required except in included files, where it is ignored
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub cameras: BTreeMap<String, CameraDesc>, // Named alternatives to `camera`, also ignored in included files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<[f64; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<EnvironmentDesc>, // Replaces the background
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sky: Option<SkyDesc>, // Replaces the background and adds the sun, in physical units
+    #[serde(default)]
+    pub materials: BTreeMap<String, MaterialDesc>, // Named materials referenced by objects
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub geometry: BTreeMap<String, Vec<ObjectDesc>>, // Named shape lists placed by `instance` objects
+    #[serde(default)]
+    pub lights: Vec<LightDesc>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDesc>,
+}
+
+/// Another scene file whose materials, geometry, lights and objects are merged into this one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IncludeDesc {
+    pub path: PathBuf, // Relative to the including file
+    #[serde(default)]
+    pub translate: [f64; 3],
+    #[serde(default = "default_scale")]
+    pub scale: f64, // Uniform, applied before translation
+}
+
+/// Random variation of each copy in an array, up to the given amount either way
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JitterDesc {
+    #[serde(default)]
+    pub translate: [f64; 3],
+    #[serde(default)]
+    pub rotate: [f64; 3], // Degrees about x, then y, then z
+    #[serde(default)]
+    pub scale: f64, // Fraction of the copy's size, below 1
+}
+
+/// Daylight sky and sun, placed by elevation and azimuth or by latitude, day and hour
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkyDesc {
+    #[serde(default = "default_turbidity")]
+    pub turbidity: f64, // Haze, from 2 (clear) to 10
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elevation: Option<f64>, // Sun degrees above the horizon (45 when unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub azimuth: Option<f64>, // Sun degrees from north (-z) toward east (+x) (180, due south, when unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>, // Degrees north (45 when unset); with `day` and `hour` places the sun instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day: Option<f64>, // Day of the year (172, the June solstice, when unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hour: Option<f64>, // Local solar time, 12 at noon; setting it switches to latitude, day and hour
+    #[serde(default = "default_intensity")]
+    pub intensity: f64,
+}
+
+/// Equirectangular Radiance `.hdr` image surrounding the scene
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentDesc {
+    pub path: PathBuf, // Relative to the scene file
+    #[serde(default = "default_intensity")]
+    pub intensity: f64,
+    #[serde(default)]
+    pub rotation: f64, // Degrees about the vertical axis
+}
+
+/// PNG or PPM image on a material, multiplying its albedo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextureDesc {
+    pub path: PathBuf, // Relative to the scene file
+    #[serde(default = "default_scale")]
+    pub scale: f64, // Units of texture coordinates one copy of the image covers (world units on planes)
+    #[serde(default)]
+    pub wrap: Wrap,
+    #[serde(default)]
+    pub filter: Filter,
+}
+
+/// Index of refraction of a material: one for all channels, or one per channel to split white light
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IorDesc {
+    Single(f64),
+    PerChannel([f64; 3]),
+}
+
+impl IorDesc {
+    fn to_vec3(self) -> Vec3 {
+        match self {
+            IorDesc::Single(ior) => vec3([ior; 3]),
+            IorDesc::PerChannel(ior) => vec3(ior),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDesc {
+    pub look_from: [f64; 3],
+    pub look_at: [f64; 3],
+    #[serde(default = "default_up")]
+    pub up: [f64; 3],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fov: Option<f64>, // Falls back to the --fov flag
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projection: Option<Projection>, // Perspective when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roll: Option<f64>, // Degrees about the view direction, counterclockwise as seen from behind the camera
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shift: Option<[f64; 2]>, // Lens shift right and up, in image widths and heights
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub near: Option<f64>, // Clip distance: nothing nearer is seen (depth for perspective cameras)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub far: Option<f64>, // Nothing further is seen
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iso: Option<f64>, // Photographic exposure, when any of these is set; the rest default to sunny 16
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shutter: Option<f64>, // Seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aperture: Option<f64>, // f-number
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightDesc {
+    pub position: [f64; 3],
+    #[serde(default = "default_intensity")]
+    pub intensity: f64,
+    #[serde(default = "default_light_color")]
+    pub color: [f64; 3],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f64>, // Sphere light radius (point light when unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lumens: Option<f64>, // Physical luminous power, replacing `intensity` and falling off with distance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watts: Option<f64>, // Physical radiant power, likewise (683 lm/W)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>, // Color temperature in kelvin, tinting `color`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ies: Option<PathBuf>, // IES photometric file shaping the light, relative to the scene file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cast_shadows: Option<bool>, // Objects block the light unless this is false
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub illuminates: Vec<String>, // Names of the only objects it lights (every object when empty)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignores: Vec<String>, // Names of objects it leaves unlit
+}
+
+/// Material definition; unset fields come from `base` (a table or library material) or diffuse defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaterialDesc {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub albedo: Option<[f64; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub specular: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shininess: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reflectivity: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_free_path: Option<[f64; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transmission: Option<[f64; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shadow_catcher: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub two_sided: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub texture: Option<TextureDesc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<TextureDesc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alpha_cutoff: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emission: Option<[f64; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emission_texture: Option<TextureDesc>, // Emission defaults to white with one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bump: Option<TextureDesc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bump_height: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anisotropy: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anisotropy_rotation: Option<f64>, // Degrees
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clearcoat: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clearcoat_roughness: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ior: Option<IorDesc>, // Refracting transmitted light, or with a different index per channel dispersing it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub absorption: Option<[f64; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub absorption_density: Option<f64>,
+}
+
+impl From<&Material> for MaterialDesc {
+    #[allow(clippy::unnecessary_cast)] // Float is only f64 without the f32 feature
+    fn from(material: &Material) -> Self {
+        let array = |v: Vec3| [v.x as f64, v.y as f64, v.z as f64];
+        Self {
+            base: None,
+            albedo: Some(array(material.albedo.into())),
+            specular: Some(material.specular as f64),
+            shininess: Some(material.shininess as f64),
+            reflectivity: Some(material.reflectivity as f64),
+            mean_free_path: Some(array(material.mean_free_path)),
+            transmission: Some(array(material.transmission)),
+            shadow_catcher: Some(material.shadow_catcher),
+            two_sided: Some(material.two_sided),
+            texture: None, // Loaded images don't remember their files
+            opacity: None,
+            alpha_cutoff: Some(material.alpha_cutoff as f64),
+            emission: Some(array(material.emission.into())),
+            emission_texture: None,
+            bump: None,
+            bump_height: Some(material.bump_height as f64),
+            anisotropy: Some(material.anisotropy as f64),
+            anisotropy_rotation: Some(material.anisotropy_rotation as f64),
+            clearcoat: Some(material.clearcoat as f64),
+            clearcoat_roughness: Some(material.clearcoat_roughness as f64),
+            ior: Some(if material.is_dispersive() {
+                IorDesc::PerChannel(array(material.ior))
+            } else {
+                IorDesc::Single(material.ior.x as f64)
+            }),
+            absorption: Some(array(material.absorption.into())),
+            absorption_density: Some(material.absorption_density as f64),
+        }
+    }
+}
+
+/// Object material: either a name or an inline definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MaterialRef {
+    Named(String),
+    Inline(Box<MaterialDesc>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ShapeDesc {
+    Sphere { center: [f64; 3], radius: f64 },
+    Cube { min: [f64; 3], max: [f64; 3] },
+    Plane { point: [f64; 3], normal: [f64; 3] },
+    Cylinder { center: [f64; 3], radius: f64, height: f64 },
+    Capsule { start: [f64; 3], end: [f64; 3], radius: f64 },
+    /// Tube of `radius` swept along a smooth curve through `points` (or, for Bézier curves, guided by them),
+    /// tapering linearly to `tip_radius` at its last point
+    Curve {
+        points: Vec<[f64; 3]>,
+        radius: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tip_radius: Option<f64>, // The same as `radius` when unset
+        #[serde(default)]
+        basis: CurveBasis,
+    },
+    /// Terrain from a grayscale image (path relative to the scene file) filling the box from `min` to `max`,
+    /// black on its bottom and white on its top, the image's top edge along its -z side
+    Heightfield { path: PathBuf, min: [f64; 3], max: [f64; 3] },
+    /// MagicaVoxel model (path relative to the scene file), its voxels `voxel_size` across from the corner at `min`,
+    /// turned so the model's z axis points up; each palette color is the object's material with that albedo
+    Voxels { path: PathBuf, min: [f64; 3], voxel_size: f64 },
+    /// Point cloud from a PLY or XYZ scan (path relative to the scene file), a splat of `radius` drawn at each
+    /// point; the points are scaled by `scale` and then moved by `translate`, and keep their colors if they have any
+    Points {
+        path: PathBuf,
+        radius: f64,
+        #[serde(default)]
+        splat: Splat,
+        #[serde(default)]
+        translate: [f64; 3],
+        #[serde(default = "default_scale")]
+        scale: f64,
+    },
+    /// Triangle mesh from a PLY or STL file (path relative to the scene file, STL if it ends in `.stl`), smoothly
+    /// shaded if it has vertex normals unless `shading` says otherwise (smooth shading computes missing ones);
+    /// its vertices are scaled by `scale` and then moved by `translate`
+    /// `subdivision` levels of Loop subdivision smooth it on loading, each splitting every triangle in four; the
+    /// file's normals no longer fit then, so it is shaded smooth with computed ones unless `shading` is flat
+    Mesh {
+        path: PathBuf,
+        #[serde(default)]
+        translate: [f64; 3],
+        #[serde(default = "default_scale")]
+        scale: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        shading: Option<Shading>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        subdivision: Option<u32>,
+    },
+    /// Every mesh a glTF 2.0 file (`.gltf` or `.glb`, relative to the scene file) places, in the file's materials
+    /// (the object's material for primitives without one); scaled by `scale` and then moved by `translate`
+    Gltf {
+        path: PathBuf,
+        #[serde(default)]
+        translate: [f64; 3],
+        #[serde(default = "default_scale")]
+        scale: f64,
+    },
+    /// Geometry from the `geometry` table, scaled, rotated (degrees about x, then y, then z) and then moved;
+    /// `overrides` draw its materials of the given names (table, library or glTF ones) in others, and `tint`
+    /// multiplies the albedo of all of them
+    Instance {
+        geometry: String,
+        #[serde(default)]
+        translate: [f64; 3],
+        #[serde(default)]
+        rotate: [f64; 3],
+        #[serde(default = "default_scale3")]
+        scale: [f64; 3],
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        overrides: BTreeMap<String, MaterialRef>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tint: Option<[f64; 3]>,
+    },
+    /// Objects placed as one, scaled, rotated (degrees about x, then y, then z) and then moved together;
+    /// groups nest, and children without a material take the group's
+    Group {
+        children: Vec<ObjectDesc>,
+        #[serde(default)]
+        translate: [f64; 3],
+        #[serde(default)]
+        rotate: [f64; 3],
+        #[serde(default = "default_scale3")]
+        scale: [f64; 3],
+    },
+    /// `count` copies of `object` along x, y and z, `spacing` apart, the first scaled by `scale` and moved by
+    /// `translate`; each turns by `turn` degrees more than the one before it, and `jitter` varies each at random
+    /// With `materials`, each copy is drawn in one of them picked at random (by `seed`, like the jitter)
+    Array {
+        object: Box<ObjectDesc>,
+        count: [u32; 3],
+        #[serde(default)]
+        spacing: [f64; 3],
+        #[serde(default)]
+        translate: [f64; 3],
+        #[serde(default = "default_scale")]
+        scale: f64,
+        #[serde(default)]
+        turn: [f64; 3],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        jitter: Option<JitterDesc>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        materials: Vec<MaterialRef>,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectDesc {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>, // For light linking; objects may share a name to be linked together
+    #[serde(flatten)]
+    pub shape: ShapeDesc,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<MaterialRef>, // White when unset; instances then keep their geometry's materials
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cast_shadows: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visible_to_camera: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visible_in_reflections: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>, // Overlapping refractive objects leave the overlap to the highest priority
+}
+
+impl LightDesc {
+    /// White point light of unit intensity at `position`, with every option unset
+    pub fn at(position: [f64; 3]) -> Self {
+        Self {
+            position,
+            intensity: default_intensity(),
+            color: default_light_color(),
+            radius: None,
+            lumens: None,
+            watts: None,
+            temperature: None,
+            ies: None,
+            cast_shadows: None,
+            illuminates: Vec::new(),
+            ignores: Vec::new(),
+        }
+    }
+}
+
+impl SkyDesc {
+    /// Sun elevation and azimuth in degrees
+    pub fn sun_angles(&self) -> (Float, Float) {
+        match self.hour {
+            Some(hour) => {
+                let (latitude, day) = (self.latitude.unwrap_or(45.0), self.day.unwrap_or(172.0));
+                Sky::sun_position(latitude as Float, day as Float, hour as Float)
+            }
+            None => (self.elevation.unwrap_or(45.0) as Float, self.azimuth.unwrap_or(180.0) as Float),
+        }
+    }
+    
+    /// The sky, its environment and its sun; `brightness` scales them like the lights
+    fn build(&self, brightness: f64) -> (Environment, Light) {
+        let (elevation, azimuth) = self.sun_angles();
+        let sky = Sky::new(elevation, azimuth, self.turbidity as Float);
+        let intensity = (self.intensity * brightness) as Float;
+        (sky.environment(intensity), sky.sun(intensity))
+    }
+}
+
+impl CameraDesc {
+    /// Photographic exposure settings, if any are given
+    pub fn exposure(&self) -> Option<Exposure> {
+        if self.iso.is_none() && self.shutter.is_none() && self.aperture.is_none() {
+            return None;
+        }
+        let defaults = Exposure::sunny_16();
+        Some(Exposure {
+            iso: self.iso.unwrap_or(defaults.iso),
+            shutter: self.shutter.unwrap_or(defaults.shutter),
+            aperture: self.aperture.unwrap_or(defaults.aperture),
+        })
+    }
+}
+
+impl ShapeDesc {
+    /// Axis-aligned bounds as (min, max) corners, or None for unbounded planes, for voxels, points and meshes
+    /// (whose extent is in their file) and for instances, groups and arrays (whose bounds depend on what they place, see `SceneDesc::bounds`)
+    #[allow(clippy::unnecessary_cast)] // Float is only f64 without the f32 feature
+    pub fn bounds(&self) -> Option<([f64; 3], [f64; 3])> {
+        match *self {
+            ShapeDesc::Sphere { center: c, radius: r } => Some(([c[0] - r, c[1] - r, c[2] - r], [c[0] + r, c[1] + r, c[2] + r])),
+            ShapeDesc::Cube { min, max } | ShapeDesc::Heightfield { min, max, .. } => Some((min, max)),
+            ShapeDesc::Plane { .. } | ShapeDesc::Instance { .. } | ShapeDesc::Group { .. } | ShapeDesc::Array { .. } => None,
+            ShapeDesc::Voxels { .. } | ShapeDesc::Points { .. } | ShapeDesc::Mesh { .. } | ShapeDesc::Gltf { .. } => None,
+            ShapeDesc::Cylinder { center: c, radius: r, height } => {
+                let half = height / 2.0;
+                Some(([c[0] - r, c[1] - half, c[2] - r], [c[0] + r, c[1] + half, c[2] + r]))
+            }
+            ShapeDesc::Capsule { start, end, radius: r } => {
+                Some((std::array::from_fn(|i| start[i].min(end[i]) - r), std::array::from_fn(|i| start[i].max(end[i]) + r)))
+            }
+            ShapeDesc::Curve { ref points, radius, tip_radius, basis } => {
+                if !basis.accepts(points.len()) {
+                    return None;
+                }
+                let r = radius.max(tip_radius.unwrap_or(radius));
+                let points: Vec<Vec3> = points.iter().map(|&point| vec3(point)).collect();
+                let (min, max) = curve::tessellate(&points, basis).iter().fold(
+                    ([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]),
+                    |(min, max), point| (std::array::from_fn(|i| min[i].min(point[i] as f64 - r)), std::array::from_fn(|i| max[i].max(point[i] as f64 + r))),
+                );
+                Some((min, max))
+            }
+        }
+    }
+    
+    /// Lowercase type name, as written in scene files
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ShapeDesc::Sphere { .. } => "sphere",
+            ShapeDesc::Cube { .. } => "cube",
+            ShapeDesc::Plane { .. } => "plane",
+            ShapeDesc::Cylinder { .. } => "cylinder",
+            ShapeDesc::Capsule { .. } => "capsule",
+            ShapeDesc::Curve { .. } => "curve",
+            ShapeDesc::Heightfield { .. } => "heightfield",
+            ShapeDesc::Voxels { .. } => "voxels",
+            ShapeDesc::Points { .. } => "points",
+            ShapeDesc::Mesh { .. } => "mesh",
+            ShapeDesc::Gltf { .. } => "gltf",
+            ShapeDesc::Instance { .. } => "instance",
+            ShapeDesc::Group { .. } => "group",
+            ShapeDesc::Array { .. } => "array",
+        }
+    }
+    
+    /// Placement of an instance's geometry or a group's children (None for other shapes)
+    pub fn instance_transform(&self) -> Option<Transform> {
+        match *self {
+            ShapeDesc::Instance { translate, rotate, scale, .. } | ShapeDesc::Group { translate, rotate, scale, .. } => Some(Transform {
+                translation: vec3(translate),
+                rotation: Quaternion::from_euler(vec3(rotate.map(f64::to_radians))),
+                scale: vec3(scale),
+            }),
+            _ => None,
+        }
+    }
+    
+    /// Placement of each copy of an array's object, with a random number in [0, 1) to pick its material
+    /// (empty for other shapes); copies run along x first, then y, then z
+    #[allow(clippy::unnecessary_cast)] // Float is only f64 without the f32 feature
+    pub fn array_copies(&self) -> Vec<(Transform, Float)> {
+        let ShapeDesc::Array { count, spacing, translate, scale, turn, ref jitter, seed, .. } = *self else {
+            return Vec::new();
+        };
+        let jitter = jitter.clone().unwrap_or_default();
+        let stream = Rng::new(seed);
+        let mut copies = Vec::new();
+        for z in 0..count[2] {
+            for y in 0..count[1] {
+                for x in 0..count[0] {
+                    // As many numbers are drawn for every copy, so changing the jitter keeps the material picks
+                    let steps = copies.len() as f64;
+                    let mut rng = stream.split(copies.len() as u64);
+                    let mut vary = |amount: f64| (rng.next_float() as f64 * 2.0 - 1.0) * amount;
+                    let offset = [x, y, z].map(|step| step as f64);
+                    let translation: [f64; 3] = std::array::from_fn(|axis| translate[axis] + offset[axis] * spacing[axis] + vary(jitter.translate[axis]));
+                    let angles: [f64; 3] = std::array::from_fn(|axis| (turn[axis] * steps + vary(jitter.rotate[axis])).to_radians());
+                    let size = scale * (1.0 + vary(jitter.scale));
+                    let transform = Transform {
+                        translation: vec3(translation),
+                        rotation: Quaternion::from_euler(vec3(angles)),
+                        scale: vec3([size; 3]),
+                    };
+                    copies.push((transform, rng.next_float()));
+                }
+            }
+        }
+        copies
+    }
+}
+
+impl ObjectDesc {
+    /// The object's own material and those its shape refers to: the picks of arrays and the overrides of instances
+    pub fn materials(&self) -> Vec<&MaterialRef> {
+        let mut materials: Vec<&MaterialRef> = self.material.iter().collect();
+        match &self.shape {
+            ShapeDesc::Array { materials: picks, .. } => materials.extend(picks),
+            ShapeDesc::Instance { overrides, .. } => materials.extend(overrides.values()),
+            _ => {}
+        }
+        materials
+    }
+}
+
+impl EnvironmentDesc {
+    /// Read the image; `brightness` scales it like the lights
+    #[cfg(feature = "fs")]
+    fn load(&self, brightness: f64) -> Result<Environment, SceneError> {
+        let image = HdrImage::load(&self.path).map_err(|source| SceneError::Environment { path: self.path.clone(), source })?;
+        log::debug!("environment {}: {}x{}", self.path.display(), image.width, image.height);
+        Ok(Environment::new(image, (self.intensity * brightness) as Float, self.rotation as Float))
+    }
+    
+    #[cfg(not(feature = "fs"))]
+    fn load(&self, _brightness: f64) -> Result<Environment, SceneError> {
+        Err(SceneError::EnvironmentUnavailable)
+    }
+}
+
+/// Read an IES profile, shared by every light that uses it
+#[cfg(feature = "fs")]
+fn load_profile(path: &Path) -> Result<Arc<IesProfile>, SceneError> {
+    let profile = IesProfile::load(path).map_err(|source| SceneError::Ies { path: path.to_path_buf(), source })?;
+    log::debug!("IES profile {}: peak {} cd", path.display(), profile.peak);
+    Ok(Arc::new(profile))
+}
+
+#[cfg(not(feature = "fs"))]
+fn load_profile(_path: &Path) -> Result<Arc<IesProfile>, SceneError> {
+    Err(SceneError::IesUnavailable)
+}
+
+/// Read a height field's image, its brightness giving the heights
+#[cfg(feature = "fs")]
+fn load_heightfield(path: &Path, min: Vec3, max: Vec3, material: Material) -> Result<Heightfield, SceneError> {
+    let image = RgbaImage::load(path).map_err(|source| SceneError::Heightfield { path: path.to_path_buf(), source })?;
+    log::debug!("height field {}: {}x{}", path.display(), image.width, image.height);
+    let heights = image.pixels.iter().map(|&[r, g, b, _]| (r as Float + g as Float + b as Float) / 765.0).collect();
+    Ok(Heightfield::new(image.width as usize, image.height as usize, heights, min, max, material))
+}
+
+#[cfg(not(feature = "fs"))]
+fn load_heightfield(_path: &Path, _min: Vec3, _max: Vec3, _material: Material) -> Result<Heightfield, SceneError> {
+    Err(SceneError::HeightfieldUnavailable)
+}
+
+/// Read a voxel model, its palette colors turning `material` into one material per color
+#[cfg(feature = "fs")]
+fn load_voxels(path: &Path, min: Vec3, voxel_size: Float, material: &Material) -> Result<VoxelGrid, SceneError> {
+    let model = VoxModel::load(path).map_err(|source| SceneError::Voxels { path: path.to_path_buf(), source })?;
+    log::debug!("voxels {}: {} in {:?}", path.display(), model.voxels.len(), model.size);
+    Ok(VoxelGrid::from_vox(&model, min, voxel_size, material))
+}
+
+#[cfg(not(feature = "fs"))]
+fn load_voxels(_path: &Path, _min: Vec3, _voxel_size: Float, _material: &Material) -> Result<VoxelGrid, SceneError> {
+    Err(SceneError::VoxelsUnavailable)
+}
+
+/// Read a scan's points, scaled by `scale` and then moved by `translate`
+#[cfg(feature = "fs")]
+fn load_points(path: &Path, translate: Vec3, scale: Float, radius: Float, splat: Splat, material: Material) -> Result<PointCloud, SceneError> {
+    let scan = Scan::load(path).map_err(|source| SceneError::Points { path: path.to_path_buf(), source })?;
+    log::debug!("points {}: {}{}", path.display(), scan.positions.len(), if scan.colors.is_some() { " colored" } else { "" });
+    let positions = scan.positions.into_iter().map(|point| point * scale + translate).collect();
+    Ok(PointCloud::new(positions, scan.colors, radius, splat, material))
+}
+
+#[cfg(not(feature = "fs"))]
+fn load_points(_path: &Path, _translate: Vec3, _scale: Float, _radius: Float, _splat: Splat, _material: Material) -> Result<PointCloud, SceneError> {
+    Err(SceneError::PointsUnavailable)
+}
+
+/// Read a mesh file's triangles, scaled by `scale` and then moved by `translate`, subdivide them `levels` times,
+/// and shade them as `shading` asks or else smooth if the file has vertex normals or they were subdivided
+/// Vertex colors in the file tint the material's albedo
+#[cfg(feature = "fs")]
+fn load_mesh(path: &Path, translate: Vec3, scale: Float, shading: Option<Shading>, levels: u32, material: Material) -> Result<Mesh, SceneError> {
+    let error = |source| SceneError::Mesh { path: path.to_path_buf(), source };
+    let scan = Scan::load(path).map_err(error)?;
+    if scan.faces.is_empty() {
+        return Err(error(ScanError::NoFaces));
+    }
+    let mut positions: Vec<Vec3> = scan.positions.into_iter().map(|point| point * scale + translate).collect();
+    let mut triangles = scan.faces;
+    let mut colors: Option<Vec<Vec3>> = scan.colors.map(|colors| colors.into_iter().map(Vec3::from).collect());
+    for _ in 0..levels {
+        // The colors are smoothed over the same triangles, ending up at the same new vertices
+        if let Some(values) = &mut colors {
+            *values = mesh::subdivide(values, &triangles).0;
+        }
+        (positions, triangles) = mesh::subdivide(&positions, &triangles);
+    }
+    log::debug!("mesh {}: {} triangles, {} vertices{}", path.display(), triangles.len(), positions.len(), if colors.is_some() { ", colored" } else { "" });
+    let (shading, normals) = if levels > 0 { (shading.or(Some(Shading::Smooth)), None) } else { (shading, scan.normals) };
+    let mut mesh = Mesh::new(positions, triangles, material);
+    if let Some(colors) = colors {
+        mesh = mesh.with_colors(colors.into_iter().map(Color::from).collect());
+    }
+    Ok(match (shading, normals) {
+        (Some(Shading::Flat), _) | (None, None) => mesh,
+        (_, Some(normals)) => mesh.with_normals(normals),
+        (Some(Shading::Smooth), None) => mesh.with_smooth_normals(),
+    })
+}
+
+#[cfg(not(feature = "fs"))]
+fn load_mesh(_path: &Path, _translate: Vec3, _scale: Float, _shading: Option<Shading>, _levels: u32, _material: Material) -> Result<Mesh, SceneError> {
+    Err(SceneError::MeshUnavailable)
+}
+
+#[cfg(feature = "fs")]
+fn read_gltf(path: &Path) -> Result<GltfScene, SceneError> {
+    GltfScene::load(path).map_err(|source| SceneError::Gltf { path: path.to_path_buf(), source })
+}
+
+/// Read a glTF file's placed meshes, scaled by `scale` and then moved by `translate`: each mesh is built
+/// once and shared by the instances its nodes make of it
+#[cfg(feature = "fs")]
+fn load_gltf(path: &Path, translate: Vec3, scale: Float, material: &Material) -> Result<Group, SceneError> {
+    let gltf = read_gltf(path)?;
+    log::debug!("glTF {}: {} meshes placed {} times, {} materials", path.display(), gltf.meshes.len(), gltf.instances.len(), gltf.materials.len());
+    let meshes: Vec<Arc<dyn Intersectable>> = gltf
+        .meshes
+        .into_iter()
+        .map(|primitives| {
+            let parts = primitives
+                .into_iter()
+                .filter(|primitive| !primitive.triangles.is_empty())
+                .map(|primitive| {
+                    let material = primitive.material.map_or(material, |index| &gltf.materials[index]).clone();
+                    let mut mesh = Mesh::new(primitive.positions, primitive.triangles, material);
+                    if let Some(normals) = primitive.normals {
+                        mesh = mesh.with_normals(normals);
+                    }
+                    if let Some(uvs) = primitive.uvs {
+                        mesh = mesh.with_uvs(uvs);
+                    }
+                    if let Some(colors) = primitive.colors {
+                        mesh = mesh.with_colors(colors);
+                    }
+                    Box::new(mesh) as Box<dyn Intersectable>
+                })
+                .collect();
+            Arc::new(Group::new(parts)) as Arc<dyn Intersectable>
+        })
+        .collect();
+    
+    let placement = crate::math::Mat4::from_translation(translate) * crate::math::Mat4::from_scale(Vec3::new(scale, scale, scale));
+    let instances = gltf
+        .instances
+        .iter()
+        .map(|&(mesh, transform)| Box::new(Instance::new(meshes[mesh].clone(), Transform::from_matrix(&(placement * transform)))) as Box<dyn Intersectable>)
+        .collect();
+    Ok(Group::new(instances))
+}
+
+#[cfg(not(feature = "fs"))]
+fn load_gltf(_path: &Path, _translate: Vec3, _scale: Float, _material: &Material) -> Result<Group, SceneError> {
+    Err(SceneError::GltfUnavailable)
+}
+
+/// Whether a path names a glTF file rather than a scene file
+#[cfg(feature = "fs")]
+fn is_gltf(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("gltf") || extension.eq_ignore_ascii_case("glb"))
+}
+
+impl TextureDesc {
+    /// Read the image and make it a texture with `convert`
+    #[cfg(feature = "fs")]
+    fn load(&self, convert: impl Fn(&RgbaImage) -> Texture) -> Result<Arc<Texture>, SceneError> {
+        if !(self.scale > 0.0 && self.scale.is_finite()) {
+            return Err(SceneError::InvalidTextureScale(self.scale));
+        }
+        let image = RgbaImage::load(&self.path).map_err(|source| SceneError::Texture { path: self.path.clone(), source })?;
+        log::debug!("texture {}: {}x{}", self.path.display(), image.width, image.height);
+        Ok(Arc::new(convert(&image).with_modes(self.wrap, self.filter).with_scale(self.scale as Float)))
+    }
+    
+    #[cfg(not(feature = "fs"))]
+    fn load(&self, _convert: impl Fn(&RgbaImage) -> Texture) -> Result<Arc<Texture>, SceneError> {
+        Err(SceneError::TextureUnavailable)
+    }
+}
+
+fn default_turbidity() -> f64 {
+    3.0
+}
+
+fn default_up() -> [f64; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+fn default_scale3() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+fn default_light_color() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_material() -> MaterialRef {
+    MaterialRef::Named("white".to_string())
+}
+
+fn vec3(v: [f64; 3]) -> Vec3 {
+    Vec3::new(v[0] as Float, v[1] as Float, v[2] as Float)
+}
+
+fn color(c: [f64; 3]) -> Color {
+    Color::new(c[0] as Float, c[1] as Float, c[2] as Float)
+}
+
+/// Load a scene file and build it
+/// - brightness: light intensity multiplier
+/// - fov: field of view used when the file's camera doesn't set one
+/// - aspect_ratio: width / height
+#[cfg(feature = "fs")]
+pub fn load(path: &Path, brightness: f64, fov: f64, aspect_ratio: f64) -> Result<(Scene, Camera), SceneError> {
+    SceneDesc::from_file(path)?.build(brightness, fov, aspect_ratio)
+}
+
+/// One step of a parameter path: an object field or an array index
+enum PathSegment<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+/// Split a path like `lights[0].intensity` into segments
+fn parse_path(key: &str) -> Option<Vec<PathSegment<'_>>> {
+    let mut segments = Vec::new();
+    for part in key.split('.') {
+        let (field, mut indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if field.is_empty() {
+            return None;
+        }
+        segments.push(PathSegment::Field(field));
+        
+        while !indices.is_empty() {
+            let close = indices.find(']')?;
+            if !indices.starts_with('[') {
+                return None;
+            }
+            segments.push(PathSegment::Index(indices[1..close].parse().ok()?));
+            indices = &indices[close + 1..];
+        }
+    }
+    Some(segments)
+}
+
+/// Set a parameter in a parsed scene document, e.g. `camera.fov` = `35` or `lights[0].color` = `[1, 0.5, 0]`
+/// Values are read as JSON, falling back to a plain string (so `material=gold` works unquoted)
+/// Missing object fields are created; array indices must already exist
+pub fn set_parameter(document: &mut Value, key: &str, value: &str) -> Result<(), SceneError> {
+    let invalid = |reason: String| SceneError::InvalidParameter { key: key.to_string(), reason };
+    let segments = parse_path(key).ok_or_else(|| invalid("expected a path like camera.fov or lights[0].intensity".to_string()))?;
+    
+    let mut target = document;
+    for segment in segments {
+        target = match segment {
+            PathSegment::Field(field) => match target {
+                Value::Object(map) => map.entry(field).or_insert(Value::Null),
+                _ => return Err(invalid(format!("'{}' is not inside an object", field))),
+            },
+            PathSegment::Index(index) => match target {
+                Value::Array(items) => {
+                    let len = items.len();
+                    items.get_mut(index).ok_or_else(|| invalid(format!("index {} out of range (length {})", index, len)))?
+                }
+                _ => return Err(invalid(format!("[{}] is not inside an array", index))),
+            },
+        };
+        
+        // Fields created along the way become objects so deeper segments can be added
+        if target.is_null() {
+            *target = Value::Object(Default::default());
+        }
+    }
+    
+    *target = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok(())
+}
+
+impl SceneDesc {
+    /// Parse a scene description from JSON text
+    pub fn from_json(text: &str) -> Result<Self, SceneError> {
+        Ok(serde_json::from_str(text)?)
+    }
+    
+    /// Read and parse a scene description from a file
+    #[cfg(feature = "fs")]
+    pub fn from_file(path: &Path) -> Result<Self, SceneError> {
+        Self::from_file_with(path, &[])
+    }
+    
+    /// Read a scene file and apply `key=value` parameter overrides (see `set_parameter`) before parsing it
+    /// Includes are resolved after the overrides, so `include[0].translate` can be set too
+    /// A glTF file (`.gltf` or `.glb`) is read as the scene `from_gltf` describes
+    #[cfg(feature = "fs")]
+    pub fn from_file_with(path: &Path, parameters: &[(String, String)]) -> Result<Self, SceneError> {
+        let mut desc: Self = if is_gltf(path) {
+            let desc = Self::from_gltf(path)?;
+            if parameters.is_empty() { desc } else { Self::with_parameters(serde_json::to_value(desc)?, parameters)? }
+        } else {
+            let text = std::fs::read_to_string(path)?;
+            if parameters.is_empty() {
+                serde_json::from_str(&text)? // Parsing the text directly keeps line numbers in errors
+            } else {
+                Self::with_parameters(serde_json::from_str(&text)?, parameters)?
+            }
+        };
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        desc.resolve_paths(base_dir);
+        desc.resolve_includes(base_dir, 0)?;
+        Ok(desc)
+    }
+    
+    #[cfg(feature = "fs")]
+    fn with_parameters(mut document: Value, parameters: &[(String, String)]) -> Result<Self, SceneError> {
+        for (key, value) in parameters {
+            set_parameter(&mut document, key, value)?;
+        }
+        Ok(serde_json::from_value(document)?)
+    }
+    
+    /// Describe a glTF file as a scene of its own: a `gltf` object with its meshes, its point and spot lights,
+    /// and the camera of its first camera node, or one framing the whole model if it has none
+    /// Its lights are given in lumens, so the camera gets the exposure of a dim interior; without any, a light
+    /// of the usual unitless kind shines from above and behind the camera
+    #[cfg(feature = "fs")]
+    #[allow(clippy::unnecessary_cast)] // Float is only f64 without the f32 feature
+    pub fn from_gltf(path: &Path) -> Result<Self, SceneError> {
+        let gltf = read_gltf(path)?;
+        let mut desc = Self::gltf_part(path, &gltf);
+        let array = |v: Vec3| [v.x as f64, v.y as f64, v.z as f64];
+        let bounds = gltf.bounds().unwrap_or(Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0)));
+        let radius = ((bounds.max - bounds.min).length() / 2.0).max(1e-3);
+        let mut camera = match gltf.cameras.first() {
+            Some(camera) => {
+                let eye = camera.transform.transform_point(Vec3::zero());
+                CameraDesc {
+                    look_from: array(eye),
+                    look_at: array(eye + camera.transform.transform_vector(-Vec3::unit_z())),
+                    up: array(camera.transform.transform_vector(Vec3::unit_y())),
+                    fov: Some(camera.fov as f64),
+                    projection: None,
+                    roll: None,
+                    shift: None,
+                    near: None,
+                    far: None,
+                    iso: None,
+                    shutter: None,
+                    aperture: None,
+                }
+            }
+            None => {
+                // Far enough back for a 45° field of view to take in the bounding sphere, looking a little down
+                let distance = radius / (22.5 as Float).to_radians().sin();
+                let eye = bounds.centroid() + Vec3::new(0.0, 0.4, 1.0).normalize() * distance;
+                CameraDesc { look_from: array(eye), look_at: array(bounds.centroid()), up: default_up(), fov: Some(45.0), projection: None, roll: None, shift: None, near: None, far: None, iso: None, shutter: None, aperture: None }
+            }
+        };
+        if desc.lights.is_empty() {
+            let eye = vec3(camera.look_from);
+            desc.lights.push(LightDesc { position: array(eye + Vec3::new(-1.0, 2.0, 1.0) * radius), ..LightDesc::at([0.0; 3]) });
+        } else {
+            (camera.iso, camera.shutter, camera.aperture) = (Some(100.0), Some(0.5), Some(4.0)); // EV 5
+        }
+        desc.camera = Some(camera);
+        Ok(desc)
+    }
+    
+    /// A glTF file as an included part: one object placing its meshes, named after the file, and its lights
+    #[cfg(feature = "fs")]
+    #[allow(clippy::unnecessary_cast)] // Float is only f64 without the f32 feature
+    fn gltf_part(path: &Path, gltf: &GltfScene) -> Self {
+        let lights = gltf.lights.iter().map(|light| LightDesc {
+            color: [light.color.r as f64, light.color.g as f64, light.color.b as f64],
+            lumens: Some(4.0 * std::f64::consts::PI * light.candela as f64),
+            ..LightDesc::at([light.position.x as f64, light.position.y as f64, light.position.z as f64])
+        });
+        let object = ObjectDesc {
+            name: path.file_stem().map(|stem| stem.to_string_lossy().into_owned()),
+            shape: ShapeDesc::Gltf { path: path.file_name().map(PathBuf::from).unwrap_or_default(), translate: [0.0; 3], scale: 1.0 },
+            material: None,
+            cast_shadows: None,
+            visible_to_camera: None,
+            visible_in_reflections: None,
+            priority: None,
+        };
+        Self {
+            include: Vec::new(),
+            camera: None,
+            cameras: BTreeMap::new(),
+            background: None,
+            environment: None,
+            sky: None,
+            materials: BTreeMap::new(),
+            geometry: BTreeMap::new(),
+            lights: lights.collect(),
+            objects: vec![object],
+        }
+    }
+    
+    /// Make the file paths in this description, written relative to its file, relative to the working directory
+    #[cfg(feature = "fs")]
+    fn resolve_paths(&mut self, base_dir: &Path) {
+        self.for_each_path_mut(&mut |path| *path = base_dir.join(&*path));
+    }
+    
+    /// Every file path the scene reads (environment, IES profiles, textures and loaded shapes), but not its includes
+    pub fn for_each_path_mut(&mut self, visit: &mut dyn FnMut(&mut PathBuf)) {
+        fn textures(material: &mut MaterialDesc, visit: &mut dyn FnMut(&mut PathBuf)) {
+            let textures = [&mut material.texture, &mut material.opacity, &mut material.emission_texture, &mut material.bump];
+            for texture in textures.into_iter().flatten() {
+                visit(&mut texture.path);
+            }
+        }
+        if let Some(environment) = &mut self.environment {
+            visit(&mut environment.path);
+        }
+        for light in &mut self.lights {
+            if let Some(ies) = &mut light.ies {
+                visit(ies);
+            }
+        }
+        for material in self.materials.values_mut() {
+            textures(material, visit);
+        }
+        self.for_each_object_mut(&mut |object| {
+            if let ShapeDesc::Heightfield { path, .. }
+            | ShapeDesc::Voxels { path, .. }
+            | ShapeDesc::Points { path, .. }
+            | ShapeDesc::Mesh { path, .. }
+            | ShapeDesc::Gltf { path, .. } = &mut object.shape
+            {
+                visit(path);
+            }
+            let mut materials: Vec<&mut MaterialRef> = object.material.iter_mut().collect();
+            match &mut object.shape {
+                ShapeDesc::Array { materials: picks, .. } => materials.extend(picks),
+                ShapeDesc::Instance { overrides, .. } => materials.extend(overrides.values_mut()),
+                _ => {}
+            }
+            for material in materials {
+                if let MaterialRef::Inline(desc) = material {
+                    textures(desc, visit);
+                }
+            }
+        });
+    }
+    
+    /// Every object, top-level or in the geometry table, and the children of groups (and the objects of arrays)
+    /// after their group
+    pub fn all_objects(&self) -> Vec<&ObjectDesc> {
+        fn gather<'a>(objects: &'a [ObjectDesc], all: &mut Vec<&'a ObjectDesc>) {
+            for object in objects {
+                all.push(object);
+                match &object.shape {
+                    ShapeDesc::Group { children, .. } => gather(children, all),
+                    ShapeDesc::Array { object, .. } => gather(std::slice::from_ref(object), all),
+                    _ => {}
+                }
+            }
+        }
+        let mut all = Vec::new();
+        for objects in std::iter::once(&self.objects).chain(self.geometry.values()) {
+            gather(objects, &mut all);
+        }
+        all
+    }
+    
+    /// Names of the table materials an object is drawn in, its children's and its instanced geometry's included
+    /// (unless the instance replaces them); inline materials have no name and are left out
+    pub fn material_names<'a>(&'a self, object: &'a ObjectDesc) -> BTreeSet<&'a str> {
+        let mut names: BTreeSet<&str> = object
+            .materials()
+            .into_iter()
+            .filter_map(|material| match material {
+                MaterialRef::Named(name) => Some(name.as_str()),
+                MaterialRef::Inline(_) => None,
+            })
+            .collect();
+        match &object.shape {
+            ShapeDesc::Group { children, .. } => names.extend(children.iter().flat_map(|child| self.material_names(child))),
+            ShapeDesc::Array { object, materials, .. } if materials.is_empty() => names.extend(self.material_names(object)),
+            ShapeDesc::Instance { geometry, overrides, .. } if object.material.is_none() => {
+                let parts = self.geometry.get(geometry).into_iter().flatten();
+                names.extend(parts.flat_map(|part| self.material_names(part)).filter(|name| !overrides.contains_key(*name)));
+            }
+            _ => {}
+        }
+        names
+    }
+    
+    /// Visit every object as `all_objects` lists them, to change it
+    fn for_each_object_mut(&mut self, visit: &mut impl FnMut(&mut ObjectDesc)) {
+        fn walk(objects: &mut [ObjectDesc], visit: &mut impl FnMut(&mut ObjectDesc)) {
+            for object in objects {
+                visit(object);
+                match &mut object.shape {
+                    ShapeDesc::Group { children, .. } => walk(children, visit),
+                    ShapeDesc::Array { object, .. } => walk(std::slice::from_mut(object), visit),
+                    _ => {}
+                }
+            }
+        }
+        for objects in std::iter::once(&mut self.objects).chain(self.geometry.values_mut()) {
+            walk(objects, visit);
+        }
+    }
+    
+    /// Merge included files (recursively) into this description, leaving `include` empty
+    /// Materials and geometry already defined here win over included ones; included lights and objects are appended
+    #[cfg(feature = "fs")]
+    pub fn resolve_includes(&mut self, base_dir: &Path, depth: usize) -> Result<(), SceneError> {
+        for include in std::mem::take(&mut self.include) {
+            let path = base_dir.join(&include.path);
+            if depth >= MAX_INCLUDE_DEPTH {
+                return Err(SceneError::IncludeCycle(path));
+            }
+            if include.scale <= 0.0 {
+                return Err(SceneError::InvalidScale(include.scale));
+            }
+            
+            let mut part = Self::read_part(&path, depth)
+                .map_err(|source| SceneError::Include { path: path.clone(), source: Box::new(source) })?;
+            part.transform(&include);
+            log::debug!(
+                "included {}: {} objects, {} lights, {} materials",
+                path.display(),
+                part.objects.len(),
+                part.lights.len(),
+                part.materials.len()
+            );
+            
+            for (name, material) in part.materials {
+                self.materials.entry(name).or_insert(material);
+            }
+            for (name, geometry) in part.geometry {
+                self.geometry.entry(name).or_insert(geometry);
+            }
+            self.lights.extend(part.lights);
+            self.objects.extend(part.objects);
+        }
+        Ok(())
+    }
+    
+    #[cfg(feature = "fs")]
+    fn read_part(path: &Path, depth: usize) -> Result<Self, SceneError> {
+        let mut part = if is_gltf(path) { Self::gltf_part(path, &read_gltf(path)?) } else { Self::from_json(&std::fs::read_to_string(path)?)? };
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        part.resolve_paths(base_dir);
+        part.resolve_includes(base_dir, depth + 1)?;
+        Ok(part)
+    }
+    
+    /// Scale and move lights and objects (materials and geometry are unaffected, instances and groups carry the
+    /// transform to what they place); the scale is known to be positive
+    #[cfg(feature = "fs")]
+    fn transform(&mut self, include: &IncludeDesc) {
+        let scale = include.scale;
+        let apply = |point: [f64; 3]| [0, 1, 2].map(|axis| point[axis] * scale + include.translate[axis]);
+        
+        for light in &mut self.lights {
+            light.position = apply(light.position);
+            light.radius = light.radius.map(|radius| radius * scale);
+        }
+        
+        for object in &mut self.objects {
+            match &mut object.shape {
+                ShapeDesc::Sphere { center, radius } => {
+                    *center = apply(*center);
+                    *radius *= scale;
+                }
+                ShapeDesc::Cube { min, max } | ShapeDesc::Heightfield { min, max, .. } => {
+                    *min = apply(*min);
+                    *max = apply(*max);
+                }
+                ShapeDesc::Plane { point, .. } => *point = apply(*point),
+                ShapeDesc::Voxels { min, voxel_size, .. } => {
+                    *min = apply(*min);
+                    *voxel_size *= scale;
+                }
+                ShapeDesc::Points { radius, translate, scale: points_scale, .. } => {
+                    *translate = apply(*translate);
+                    *radius *= scale;
+                    *points_scale *= scale;
+                }
+                ShapeDesc::Mesh { translate, scale: mesh_scale, .. } | ShapeDesc::Gltf { translate, scale: mesh_scale, .. } => {
+                    *translate = apply(*translate);
+                    *mesh_scale *= scale;
+                }
+                ShapeDesc::Cylinder { center, radius, height } => {
+                    *center = apply(*center);
+                    *radius *= scale;
+                    *height *= scale;
+                }
+                ShapeDesc::Capsule { start, end, radius } => {
+                    *start = apply(*start);
+                    *end = apply(*end);
+                    *radius *= scale;
+                }
+                ShapeDesc::Curve { points, radius, tip_radius, .. } => {
+                    points.iter_mut().for_each(|point| *point = apply(*point));
+                    *radius *= scale;
+                    *tip_radius = tip_radius.map(|r| r * scale);
+                }
+                ShapeDesc::Instance { translate, scale: instance_scale, .. } | ShapeDesc::Group { translate, scale: instance_scale, .. } => {
+                    *translate = apply(*translate);
+                    *instance_scale = instance_scale.map(|s| s * scale);
+                }
+                ShapeDesc::Array { translate, spacing, scale: array_scale, jitter, .. } => {
+                    *translate = apply(*translate);
+                    *spacing = spacing.map(|s| s * scale);
+                    *array_scale *= scale;
+                    if let Some(jitter) = jitter {
+                        jitter.translate = jitter.translate.map(|s| s * scale);
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Bounds of all bounded objects as (min, max) corners, or None if there are none
+    pub fn bounds(&self) -> Option<([f64; 3], [f64; 3])> {
+        Self::objects_bounds(&self.objects, |name| {
+            self.geometry.get(name).and_then(|objects| Self::objects_bounds(objects, |_| None))
+        })
+    }
+    
+    /// Bounds of a list of objects, where `geometry_bounds` gives the bounds of named geometry for instances
+    #[allow(clippy::unnecessary_cast)] // Float is only f64 without the f32 feature
+    fn objects_bounds(
+        objects: &[ObjectDesc],
+        geometry_bounds: impl Fn(&str) -> Option<([f64; 3], [f64; 3])> + Copy,
+    ) -> Option<([f64; 3], [f64; 3])> {
+        let array = |v: Vec3| [v.x as f64, v.y as f64, v.z as f64];
+        objects
+            .iter()
+            .filter_map(|object| {
+                let ((min, max), placements) = match &object.shape {
+                    ShapeDesc::Instance { geometry, .. } => (geometry_bounds(geometry)?, vec![object.shape.instance_transform()?]),
+                    ShapeDesc::Group { children, .. } => (Self::objects_bounds(children, geometry_bounds)?, vec![object.shape.instance_transform()?]),
+                    ShapeDesc::Array { object: copied, .. } => {
+                        let copies = object.shape.array_copies().into_iter().map(|(transform, _)| transform).collect();
+                        (Self::objects_bounds(std::slice::from_ref(copied), geometry_bounds)?, copies)
+                    }
+                    shape => return shape.bounds(),
+                };
+                let placed = Aabb::new(vec3(min), vec3(max));
+                let bounds = placements.iter().map(|transform| placed.transformed(&transform.matrix())).reduce(|a, b| a.union(&b))?;
+                Some((array(bounds.min), array(bounds.max)))
+            })
+            .reduce(|(a_min, a_max), (b_min, b_max)| {
+                (
+                    [a_min[0].min(b_min[0]), a_min[1].min(b_min[1]), a_min[2].min(b_min[2])],
+                    [a_max[0].max(b_max[0]), a_max[1].max(b_max[1]), a_max[2].max(b_max[2])],
+                )
+            })
+    }
+    
+    /// Serialize back to JSON (includes already resolved, so the result is self-contained)
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("scene descriptions always serialize")
+    }
+    
+    /// Build the renderable scene and camera (same parameters as `scenes::build`)
+    pub fn build(&self, brightness: f64, fov: f64, aspect_ratio: f64) -> Result<(Scene, Camera), SceneError> {
+        self.build_with(brightness, fov, aspect_ratio, &AcceleratorOptions::default())
+    }
+    
+    /// Like `build`, with the options for the scene's accelerator and those of its shared geometry
+    pub fn build_with(&self, brightness: f64, fov: f64, aspect_ratio: f64, accelerator: &AcceleratorOptions) -> Result<(Scene, Camera), SceneError> {
+        let mut scene = Scene::new();
+        scene.accelerator = *accelerator;
+        
+        if let Some(background) = self.background {
+            scene.background_color = vec3(background);
+        }
+        
+        for light in &self.lights {
+            let position = vec3(light.position);
+            let mut tint = color(light.color);
+            if let Some(kelvin) = light.temperature {
+                tint = tint * Color::from_kelvin(kelvin as Float);
+            }
+            let mut built = match (light.lumens, light.watts) {
+                (Some(lumens), _) => Light::from_lumens(position, (lumens * brightness) as Float, tint),
+                (None, Some(watts)) => Light::from_watts(position, (watts * brightness) as Float, tint),
+                (None, None) => Light::new(position, (light.intensity * brightness) as Float, tint),
+            };
+            built.radius = light.radius.unwrap_or(0.0) as Float;
+            built.casts_shadows = light.cast_shadows.unwrap_or(true);
+            built.links = self.light_links(light)?;
+            if let Some(path) = &light.ies {
+                built.profile = Some(load_profile(path)?);
+            }
+            scene.add_light(built);
+        }
+        
+        // The sun follows the file's lights, which keep their indices
+        match (&self.environment, &self.sky) {
+            (Some(_), Some(_)) => return Err(SceneError::SkyAndEnvironment),
+            (Some(environment), None) => scene.environment = Some(environment.load(brightness)?),
+            (None, Some(sky)) => {
+                let (environment, sun) = sky.build(brightness);
+                scene.environment = Some(environment);
+                scene.add_light(sun);
+            }
+            (None, None) => {}
+        }
+        
+        for (object, shape) in self.objects.iter().zip(self.build_objects(accelerator)?) {
+            let defaults = ObjectFlags::default();
+            let flags = ObjectFlags {
+                cast_shadows: object.cast_shadows.unwrap_or(defaults.cast_shadows),
+                visible_to_camera: object.visible_to_camera.unwrap_or(defaults.visible_to_camera),
+                visible_in_reflections: object.visible_in_reflections.unwrap_or(defaults.visible_in_reflections),
+                priority: object.priority.unwrap_or(defaults.priority),
+            };
+            scene.add_object_with_flags(shape, flags);
+        }
+        
+        let camera_desc = self.camera.as_ref().ok_or(SceneError::MissingCamera)?;
+        let mut camera = Camera::with_projection(
+            vec3(camera_desc.look_from),
+            vec3(camera_desc.look_at),
+            vec3(camera_desc.up),
+            camera_desc.fov.unwrap_or(fov) as Float,
+            aspect_ratio as Float,
+            camera_desc.projection.unwrap_or_default(),
+        );
+        if let Some(roll) = camera_desc.roll {
+            camera = camera.with_roll(roll as Float);
+        }
+        if let Some([x, y]) = camera_desc.shift {
+            camera = camera.with_shift(x as Float, y as Float);
+        }
+        if camera_desc.near.is_some() || camera_desc.far.is_some() {
+            camera = camera.with_clipping(camera_desc.near.unwrap_or(0.0) as Float, camera_desc.far.map_or(Float::INFINITY, |far| far as Float));
+        }
+        // A sky is in physical units, so the camera exposes for daylight unless told otherwise
+        if let Some(exposure) = camera_desc.exposure().or(self.sky.is_some().then(Exposure::sunny_16)) {
+            camera = camera.with_exposure(exposure);
+        }
+        
+        Ok((scene, camera))
+    }
+    
+    /// Indices of the top-level objects named `name` (those it becomes in the built scene)
+    pub fn objects_named(&self, name: &str) -> Result<Vec<usize>, SceneError> {
+        let indices: Vec<usize> = self.objects.iter().enumerate()
+            .filter(|(_, object)| object.name.as_deref() == Some(name))
+            .map(|(index, _)| index)
+            .collect();
+        if indices.is_empty() {
+            return Err(SceneError::UnknownObject(name.to_string()));
+        }
+        Ok(indices)
+    }
+    
+    /// Objects a light illuminates: those named in `illuminates` (or all of them) less those in `ignores`
+    fn light_links(&self, light: &LightDesc) -> Result<LightLinks, SceneError> {
+        let mut ignored = Vec::new();
+        for name in &light.ignores {
+            ignored.extend(self.objects_named(name)?);
+        }
+        if light.illuminates.is_empty() {
+            return Ok(if ignored.is_empty() { LightLinks::All } else { LightLinks::Except(ignored) });
+        }
+        let mut only = Vec::new();
+        for name in &light.illuminates {
+            only.extend(self.objects_named(name)?);
+        }
+        only.retain(|index| !ignored.contains(index));
+        Ok(LightLinks::Only(only))
+    }
+    
+    /// Build an object, in `inherited` (its group's material) if it has no material of its own: a plain shape,
+    /// a group of its children, an array of copies, or an instance of shared geometry, built into `geometry` when
+    /// first instanced
+    /// Inside the geometry `within` names, instances aren't allowed
+    /// Build the file's objects, in order, without the camera, lights and environment a scene needs
+    pub fn build_objects(&self, accelerator: &AcceleratorOptions) -> Result<Vec<Box<dyn Intersectable>>, SceneError> {
+        // Each geometry is built once, when first instanced, and shared by all its instances
+        let mut geometry = BTreeMap::new();
+        self.objects.iter().map(|object| self.build_object(object, None, &mut geometry, accelerator, None)).collect()
+    }
+    
+    fn build_object(
+        &self,
+        object: &ObjectDesc,
+        inherited: Option<&MaterialRef>,
+        geometry: &mut BTreeMap<String, Arc<dyn Intersectable>>,
+        accelerator: &AcceleratorOptions,
+        within: Option<&str>,
+    ) -> Result<Box<dyn Intersectable>, SceneError> {
+        let material = object.material.as_ref().or(inherited);
+        match (&object.shape, object.shape.instance_transform()) {
+            (ShapeDesc::Instance { geometry: name, overrides, tint, .. }, Some(transform)) => {
+                if let Some(outer) = within {
+                    return Err(SceneError::NestedInstance(outer.to_string()));
+                }
+                let shared = match geometry.get(name) {
+                    Some(shared) => shared.clone(),
+                    None => {
+                        let shared: Arc<dyn Intersectable> = Arc::new(self.build_geometry(name, geometry, accelerator)?);
+                        geometry.insert(name.clone(), shared.clone());
+                        shared
+                    }
+                };
+                let mut instance = match material {
+                    Some(material) => Instance::with_material(shared, transform, self.resolve_material(material)?),
+                    None => Instance::new(shared, transform),
+                };
+                if !overrides.is_empty() {
+                    let overrides = overrides
+                        .iter()
+                        .map(|(name, material)| Ok((name.clone(), self.resolve_material(material)?)))
+                        .collect::<Result<Vec<_>, SceneError>>()?;
+                    instance = instance.with_overrides(overrides);
+                }
+                if let Some(tint) = *tint {
+                    instance = instance.with_tint(color(tint));
+                }
+                Ok(Box::new(instance))
+            }
+            (ShapeDesc::Group { children, .. }, Some(transform)) => {
+                let shapes = children
+                    .iter()
+                    .map(|child| self.build_object(child, material, geometry, accelerator, within))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let group = Group::with_options(shapes, accelerator);
+                Ok(if transform.is_identity() { Box::new(group) } else { Box::new(Instance::new(Arc::new(group), transform)) })
+            }
+            // The object is built once and every copy instances it
+            (ShapeDesc::Array { object: copied, materials, .. }, _) => {
+                let shared: Arc<dyn Intersectable> = Arc::from(self.build_object(copied, material, geometry, accelerator, within)?);
+                let materials = materials.iter().map(|material| self.resolve_material(material)).collect::<Result<Vec<_>, _>>()?;
+                let copies = object
+                    .shape
+                    .array_copies()
+                    .into_iter()
+                    .map(|(transform, pick)| -> Box<dyn Intersectable> {
+                        if materials.is_empty() {
+                            return Box::new(Instance::new(shared.clone(), transform));
+                        }
+                        let index = ((pick * materials.len() as Float) as usize).min(materials.len() - 1);
+                        Box::new(Instance::with_material(shared.clone(), transform, materials[index].clone()))
+                    })
+                    .collect();
+                Ok(Box::new(Group::with_options(copies, accelerator)))
+            }
+            _ => self.build_shape(object, material),
+        }
+    }
+    
+    /// Build a plain shape (not an instance, group or array), in `material` or else white
+    fn build_shape(&self, object: &ObjectDesc, material: Option<&MaterialRef>) -> Result<Box<dyn Intersectable>, SceneError> {
+        let material = self.resolve_material(material.unwrap_or(&default_material()))?;
+        Ok(match object.shape {
+            ShapeDesc::Sphere { center, radius } => Box::new(Sphere::new(vec3(center), radius as Float, material)),
+            ShapeDesc::Cube { min, max } => Box::new(Cube::new(vec3(min), vec3(max), material)),
+            ShapeDesc::Plane { point, normal } => Box::new(Plane::new(vec3(point), vec3(normal), material)),
+            ShapeDesc::Cylinder { center, radius, height } => {
+                Box::new(Cylinder::new(vec3(center), radius as Float, height as Float, material))
+            }
+            ShapeDesc::Capsule { start, end, radius } => Box::new(Capsule::new(vec3(start), vec3(end), radius as Float, material)),
+            ShapeDesc::Curve { ref points, radius, tip_radius, basis } => {
+                let points: Vec<Vec3> = points.iter().map(|&point| vec3(point)).collect();
+                Box::new(Curve::new(&points, basis, radius as Float, tip_radius.unwrap_or(radius) as Float, material))
+            }
+            ShapeDesc::Heightfield { ref path, min, max } => Box::new(load_heightfield(path, vec3(min), vec3(max), material)?),
+            ShapeDesc::Voxels { ref path, min, voxel_size } => Box::new(load_voxels(path, vec3(min), voxel_size as Float, &material)?),
+            ShapeDesc::Points { ref path, radius, splat, translate, scale } => {
+                Box::new(load_points(path, vec3(translate), scale as Float, radius as Float, splat, material)?)
+            }
+            ShapeDesc::Mesh { ref path, translate, scale, shading, subdivision } => {
+                Box::new(load_mesh(path, vec3(translate), scale as Float, shading, subdivision.unwrap_or(0), material)?)
+            }
+            ShapeDesc::Gltf { ref path, translate, scale } => Box::new(load_gltf(path, vec3(translate), scale as Float, &material)?),
+            ShapeDesc::Instance { .. } | ShapeDesc::Group { .. } | ShapeDesc::Array { .. } => {
+                unreachable!("instances, groups and arrays are built by `build_object`")
+            }
+        })
+    }
+    
+    /// Build the shapes of a geometry table entry into one group with its own accelerator
+    fn build_geometry(
+        &self,
+        name: &str,
+        geometry: &mut BTreeMap<String, Arc<dyn Intersectable>>,
+        accelerator: &AcceleratorOptions,
+    ) -> Result<Group, SceneError> {
+        let objects = self.geometry.get(name).ok_or_else(|| SceneError::UnknownGeometry(name.to_string()))?;
+        let shapes = objects
+            .iter()
+            .map(|object| self.build_object(object, None, geometry, accelerator, Some(name)))
+            .collect::<Result<Vec<_>, _>>()?;
+        log::debug!("geometry {}: {} shapes", name, shapes.len());
+        Ok(Group::with_options(shapes, accelerator))
+    }
+    
+    /// Render from the camera called `name` in the cameras table instead of the default one
+    pub fn select_camera(&mut self, name: &str) -> Result<(), SceneError> {
+        let camera = self.cameras.get(name).ok_or_else(|| SceneError::UnknownCamera(name.to_string()))?;
+        self.camera = Some(camera.clone());
+        Ok(())
+    }
+    
+    /// Replace the material called `name` (a table entry or a library name used by objects) with `preset`
+    pub fn override_material(&mut self, name: &str, preset: &str) -> Result<(), SceneError> {
+        let in_use = self.materials.contains_key(name)
+            || self.all_objects().into_iter().flat_map(ObjectDesc::materials).any(|material| matches!(material, MaterialRef::Named(n) if n == name));
+        if !in_use {
+            return Err(SceneError::UnknownMaterial(name.to_string()));
+        }
+        
+        // Resolve before inserting so a preset that refers to `name` still sees the original
+        let replacement = MaterialDesc::from(&self.named_material(preset, 0)?);
+        self.materials.insert(name.to_string(), replacement);
+        Ok(())
+    }
+    
+    /// Resolve an object's material reference against the materials table and built-in library
+    pub fn resolve_material(&self, reference: &MaterialRef) -> Result<Material, SceneError> {
+        match reference {
+            MaterialRef::Named(name) => self.named_material(name, 0),
+            MaterialRef::Inline(desc) => self.material_from_desc(None, desc, 0),
+        }
+    }
+    
+    /// Table materials shadow library materials of the same name; either keeps the name, for instances to replace it by
+    fn named_material(&self, name: &str, depth: usize) -> Result<Material, SceneError> {
+        if depth > MAX_MATERIAL_DEPTH {
+            return Err(SceneError::MaterialCycle(name.to_string()));
+        }
+        
+        match self.materials.get(name) {
+            Some(desc) => self.material_from_desc(Some(name), desc, depth + 1),
+            None => Material::from_library(name)
+                .map(|material| Material { name: Some(Arc::from(name)), ..material })
+                .ok_or_else(|| SceneError::UnknownMaterial(name.to_string())),
+        }
+    }
+    
+    fn material_from_desc(&self, name: Option<&str>, desc: &MaterialDesc, depth: usize) -> Result<Material, SceneError> {
+        let mut material = match desc.base.as_deref() {
+            // A table entry based on its own name overrides the library material
+            Some(base) if Some(base) == name => {
+                Material::from_library(base).ok_or_else(|| SceneError::UnknownMaterial(base.to_string()))?
+            }
+            Some(base) => self.named_material(base, depth)?,
+            None => Material::white(),
+        };
+        
+        if let Some(albedo) = desc.albedo {
+            material.albedo = color(albedo);
+        }
+        if let Some(specular) = desc.specular {
+            material.specular = specular as Float;
+        }
+        if let Some(shininess) = desc.shininess {
+            material.shininess = shininess as Float;
+        }
+        if let Some(reflectivity) = desc.reflectivity {
+            material.reflectivity = reflectivity as Float;
+        }
+        if let Some(mean_free_path) = desc.mean_free_path {
+            material.mean_free_path = vec3(mean_free_path);
+        }
+        if let Some(transmission) = desc.transmission {
+            material.transmission = vec3(transmission);
+        }
+        if let Some(shadow_catcher) = desc.shadow_catcher {
+            material.shadow_catcher = shadow_catcher;
+        }
+        if let Some(two_sided) = desc.two_sided {
+            material.two_sided = two_sided;
+        }
+        if let Some(texture) = &desc.texture {
+            material.texture = Some(texture.load(|image| Texture::from_image(image, true))?);
+        }
+        if let Some(opacity) = &desc.opacity {
+            material.opacity = Some(opacity.load(Texture::opacity_from_image)?);
+        }
+        if let Some(alpha_cutoff) = desc.alpha_cutoff {
+            material.alpha_cutoff = alpha_cutoff as Float;
+        }
+        if let Some(emission_texture) = &desc.emission_texture {
+            material.emission_texture = Some(emission_texture.load(|image| Texture::from_image(image, true))?);
+            if !material.is_emissive() {
+                material.emission = Color::white();
+            }
+        }
+        if let Some(emission) = desc.emission {
+            material.emission = color(emission);
+        }
+        if let Some(bump) = &desc.bump {
+            material.bump = Some(bump.load(|image| Texture::from_image(image, false))?);
+        }
+        if let Some(bump_height) = desc.bump_height {
+            material.bump_height = bump_height as Float;
+        }
+        if let Some(anisotropy) = desc.anisotropy {
+            material.anisotropy = anisotropy as Float;
+        }
+        if let Some(anisotropy_rotation) = desc.anisotropy_rotation {
+            material.anisotropy_rotation = anisotropy_rotation as Float;
+        }
+        if let Some(clearcoat) = desc.clearcoat {
+            material.clearcoat = clearcoat as Float;
+        }
+        if let Some(clearcoat_roughness) = desc.clearcoat_roughness {
+            material.clearcoat_roughness = clearcoat_roughness as Float;
+        }
+        if let Some(ior) = desc.ior {
+            material.ior = ior.to_vec3();
+        }
+        if let Some(absorption) = desc.absorption {
+            material.absorption = color(absorption);
+        }
+        if let Some(absorption_density) = desc.absorption_density {
+            material.absorption_density = absorption_density as Float;
+        }
+        material.name = name.map(Arc::from); // Inline materials are nameless, whatever they are based on
+        
+        Ok(material)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{consts::PI, Ray};
+    
+    const SCENE: &str = r#"{
+        "camera": { "look_from": [0, 0, 0], "look_at": [0, 0, -1] },
+        "materials": {
+            "floor": { "albedo": [0.3, 0.3, 0.3] },
+            "dull_gold": { "base": "gold", "reflectivity": 0.2 },
+            "gold": { "base": "gold", "albedo": [0.9, 0.7, 0.3] }
+        },
+        "lights": [{ "position": [0, 5, 0], "intensity": 2.0 }],
+        "objects": [
+            { "type": "plane", "point": [0, -1, 0], "normal": [0, 1, 0], "material": "floor" },
+            { "type": "sphere", "center": [0, 0, -3], "radius": 1, "material": "dull_gold" },
+            { "type": "cube", "min": [-1, -1, -5], "max": [1, 1, -4], "material": { "albedo": [1, 0, 0] } },
+            { "type": "cylinder", "center": [2, 0, -4], "radius": 0.5, "height": 2, "material": "chrome" }
+        ]
+    }"#;
+    
+    #[test]
+    fn test_named_materials() {
+        let desc = SceneDesc::from_json(SCENE).unwrap();
+        
+        let floor = desc.resolve_material(&MaterialRef::Named("floor".to_string())).unwrap();
+        assert_eq!(floor.albedo, Color::new(0.3, 0.3, 0.3));
+        
+        // Overrides keep the base material's other properties
+        let dull_gold = desc.resolve_material(&MaterialRef::Named("dull_gold".to_string())).unwrap();
+        assert_eq!(dull_gold.reflectivity, 0.2);
+        assert_eq!(dull_gold.albedo, Color::new(0.9, 0.7, 0.3));
+        
+        let (scene, _) = desc.build(1.0, 45.0, 1.0).unwrap();
+        assert_eq!(scene.objects.len(), 4);
+        assert_eq!(scene.lights[0].intensity, 2.0);
+    }
+    
+    #[test]
+    fn test_unknown_material() {
+        let text = SCENE.replace("\"chrome\"", "\"unobtainium\"");
+        let desc = SceneDesc::from_json(&text).unwrap();
+        assert!(matches!(desc.build(1.0, 45.0, 1.0), Err(SceneError::UnknownMaterial(name)) if name == "unobtainium"));
+    }
+    
+    #[test]
+    fn test_material_override() {
+        let mut desc = SceneDesc::from_json(SCENE).unwrap();
+        desc.override_material("floor", "mirror").unwrap();
+        desc.override_material("chrome", "floor").unwrap();
+        
+        let named = |desc: &SceneDesc, name: &str| desc.resolve_material(&MaterialRef::Named(name.to_string())).unwrap();
+        assert_eq!(named(&desc, "floor").reflectivity, 0.9);
+        assert_eq!(named(&desc, "chrome").reflectivity, 0.9);
+        assert!(desc.override_material("unused", "gold").is_err());
+        assert!(desc.override_material("floor", "unobtainium").is_err());
+    }
+    
+    #[test]
+    fn test_select_camera() {
+        let text = SCENE.replacen(r#""materials""#, r#""cameras": { "top": { "look_from": [0, 10, -3], "look_at": [0, 0, -3], "up": [0, 0, -1], "fov": 30 } },
+        "materials""#, 1);
+        let mut desc = SceneDesc::from_json(&text).unwrap();
+        assert!(matches!(desc.select_camera("hero"), Err(SceneError::UnknownCamera(name)) if name == "hero"));
+        assert_eq!(desc.camera.as_ref().unwrap().look_from, [0.0, 0.0, 0.0]);
+        
+        desc.select_camera("top").unwrap();
+        let (_, camera) = desc.build(1.0, 45.0, 1.0).unwrap();
+        assert_eq!(camera.origin, Vec3::new(0.0, 10.0, -3.0));
+        assert!(desc.to_json().contains(r#""top""#));
+    }
+    
+    #[test]
+    fn test_set_parameter() {
+        let mut document: Value = serde_json::from_str(SCENE).unwrap();
+        set_parameter(&mut document, "camera.fov", "35").unwrap();
+        set_parameter(&mut document, "camera.roll", "90").unwrap();
+        set_parameter(&mut document, "lights[0].color", "[1, 0.5, 0]").unwrap();
+        set_parameter(&mut document, "objects[1].material", "mirror").unwrap();
+        set_parameter(&mut document, "materials.floor.albedo", "[0.1, 0.1, 0.1]").unwrap();
+        
+        let desc: SceneDesc = serde_json::from_value(document.clone()).unwrap();
+        assert_eq!(desc.camera.as_ref().unwrap().fov, Some(35.0));
+        assert_eq!(desc.lights[0].color, [1.0, 0.5, 0.0]);
+        assert_eq!(desc.resolve_material(desc.objects[1].material.as_ref().unwrap()).unwrap().reflectivity, 0.9);
+        assert_eq!(desc.materials["floor"].albedo, Some([0.1, 0.1, 0.1]));
+        
+        // Rolled a quarter turn, up in the image is left in the scene
+        let (_, camera) = desc.build(1.0, 45.0, 1.0).unwrap();
+        assert!(camera.vertical.x < 0.0 && camera.vertical.y.abs() < 1e-6, "{:?}", camera.vertical);
+        
+        assert!(set_parameter(&mut document, "lights[3].intensity", "2").is_err());
+        assert!(set_parameter(&mut document, "camera.fov[0]", "2").is_err());
+        assert!(set_parameter(&mut document, "lights[x]", "2").is_err());
+    }
+    
+    #[test]
+    fn test_light_linking() {
+        let text = SCENE
+            .replace(r#"{ "type": "plane""#, r#"{ "name": "floor", "type": "plane""#)
+            .replace(r#"{ "type": "sphere""#, r#"{ "name": "props", "type": "sphere""#)
+            .replace(r#"{ "type": "cube""#, r#"{ "name": "props", "type": "cube""#);
+        let linked = |links: &str| SceneDesc::from_json(&text.replace("\"intensity\": 2.0", &format!("\"intensity\": 2.0, {}", links))).unwrap();
+        let links = |links: &str| linked(links).build(1.0, 45.0, 1.0).map(|(scene, _)| scene.lights[0].links.clone());
+        assert_eq!(links(r#""ignores": ["floor"]"#).unwrap(), LightLinks::Except(vec![0]));
+        assert_eq!(links(r#""illuminates": ["props", "floor"], "ignores": ["floor"]"#).unwrap(), LightLinks::Only(vec![1, 2]));
+        assert!(matches!(links(r#""ignores": ["ceiling"]"#), Err(SceneError::UnknownObject(name)) if name == "ceiling"));
+        
+        // Hits report which object they landed on, for the renderer to check against the links
+        let (scene, _) = linked(r#""illuminates": ["props"]"#).build(1.0, 45.0, 1.0).unwrap();
+        let hit = |origin: Vec3| scene.intersect(&Ray::new(origin, Vec3::new(0.0, -1.0, 0.0))).unwrap().object;
+        assert_eq!((hit(Vec3::new(5.0, 0.0, 0.0)), hit(Vec3::new(0.0, 5.0, -4.5))), (0, 2));
+        assert!(!scene.lights[0].links.illuminates(0) && scene.lights[0].links.illuminates(2));
+    }
+    
+    #[test]
+    fn test_physical_units() {
+        let text = SCENE
+            .replace(r#""look_at": [0, 0, -1] }"#, r#""look_at": [0, 0, -1], "iso": 400, "shutter": 0.05 }"#)
+            .replace(r#""intensity": 2.0 }"#, r#""lumens": 800 }, { "position": [0, 5, 0], "watts": 2 }"#);
+        let (scene, camera) = SceneDesc::from_json(&text).unwrap().build(1.0, 45.0, 1.0).unwrap();
+        assert_eq!(camera.exposure, Exposure { iso: 400.0, shutter: 0.05, aperture: 16.0 }.stops());
+        
+        // An 800 lm bulb: 800 / 4π cd, or about 15.9 lux a meter below it and a quarter of that at two meters
+        let bulb = &scene.lights[0];
+        let lux = |distance: Float| PI * bulb.intensity_at(Vec3::new(0.0, 5.0 - distance, 0.0));
+        assert!((lux(2.0) - 800.0 / (4.0 * PI) / 4.0).abs() < 1e-3, "{}", lux(2.0));
+        assert!((lux(1.0) - 4.0 * lux(2.0)).abs() < 1e-3);
+        assert!((scene.lights[1].intensity - 2.0 * 683.0 / 800.0 * bulb.intensity).abs() < 1e-3);
+        
+        // Lights without physical units keep their intensity at any distance
+        let (scene, camera) = SceneDesc::from_json(SCENE).unwrap().build(1.0, 45.0, 1.0).unwrap();
+        assert_eq!(scene.lights[0].intensity_at(Vec3::new(0.0, -100.0, 0.0)), 2.0);
+        assert_eq!(camera.exposure, 0.0);
+    }
+    
+    #[test]
+    fn test_sky() {
+        let text = SCENE.replace(r#""materials""#, r#""sky": { "elevation": 30, "azimuth": 90 }, "materials""#);
+        let (scene, camera) = SceneDesc::from_json(&text).unwrap().build(1.0, 45.0, 1.0).unwrap();
+        assert!(scene.environment.is_some());
+        assert_eq!(camera.exposure, Exposure::sunny_16().stops());
+        
+        // The sun comes after the file's lights, in the east and 30° up
+        assert_eq!(scene.lights.len(), 2);
+        let sun = scene.lights[1].position.normalize();
+        assert!((sun.y - 0.5).abs() < 1e-4 && sun.x > 0.8, "{:?}", sun);
+        
+        let hourly = SceneDesc::from_json(&text.replace(r#""elevation": 30, "azimuth": 90"#, r#""latitude": 0, "day": 80, "hour": 8"#)).unwrap();
+        assert!((hourly.sky.as_ref().unwrap().sun_angles().0 - 30.0).abs() < 1.0);
+        
+        let both = text.replace(r#""sky""#, r#""environment": { "path": "sky.hdr" }, "sky""#);
+        assert!(matches!(SceneDesc::from_json(&both).unwrap().build(1.0, 45.0, 1.0), Err(SceneError::SkyAndEnvironment)));
+    }
+    
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_includes() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/gallery.json");
+        let desc = SceneDesc::from_file(&path).unwrap();
+        assert!(desc.include.is_empty());
+        assert_eq!(desc.objects.len(), 10); // floor + 3 pedestals of 3 objects
+        
+        // The including file's "statue" wins over the part's own definition
+        assert_eq!(desc.resolve_material(desc.objects[3].material.as_ref().unwrap()).unwrap().reflectivity, 0.8);
+        
+        // Second pedestal is scaled by 1.5 and moved
+        match desc.objects[6].shape {
+            ShapeDesc::Sphere { center, radius } => {
+                assert_eq!(center, [0.0, 0.75, -6.0]);
+                assert!((radius - 0.6).abs() < 1e-9);
+            }
+            _ => panic!("expected the pedestal's sphere"),
+        }
+    }
+    
+    #[test]
+    fn test_instances() {
+        let desc = SceneDesc::from_json(r#"{
+            "camera": { "look_from": [0, 0, 0], "look_at": [0, 0, -1] },
+            "geometry": {
+                "tree": [
+                    { "type": "cylinder", "center": [0, 0.5, 0], "radius": 0.1, "height": 1, "material": "clay" },
+                    { "type": "sphere", "center": [0, 1.5, 0], "radius": 0.5, "material": "green" }
+                ]
+            },
+            "objects": [
+                { "type": "instance", "geometry": "tree", "translate": [0, 0, -5] },
+                { "type": "instance", "geometry": "tree", "translate": [3, 0, -5], "rotate": [0, 90, 0], "scale": [2, 2, 2], "material": "red" },
+                { "type": "instance", "geometry": "tree", "translate": [-3, 0, -5], "overrides": { "green": "blue" }, "tint": [1, 0.5, 0.5] }
+            ]
+        }"#).unwrap();
+        let (min, max) = desc.bounds().unwrap();
+        let expected = [-3.5, 0.0, -6.0, 4.0, 4.0, -4.0];
+        assert!(min.iter().chain(&max).zip(expected).all(|(a, b)| (a - b).abs() < 1e-4), "{:?} {:?}", min, max);
+        
+        let (scene, _) = desc.build(1.0, 45.0, 1.0).unwrap();
+        let hit = |x: Float, y: Float| scene.intersect(&Ray::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, -1.0)));
+        let crown = hit(0.0, 1.5).unwrap();
+        assert!((crown.t - 4.5).abs() < 1e-4);
+        assert_eq!(crown.material.albedo, Material::green().albedo);
+        
+        // Twice the size, and drawn in the override material
+        let big_crown = hit(3.0, 3.0).unwrap();
+        assert!((big_crown.t - 4.0).abs() < 1e-4);
+        assert_eq!(big_crown.material.albedo, Material::red().albedo);
+        assert!(hit(1.5, 1.5).is_none());
+        
+        // Only the crown's material replaced, and both parts tinted
+        let tint = Color::new(1.0, 0.5, 0.5);
+        assert_eq!(hit(-3.0, 1.5).unwrap().material.albedo, Material::blue().albedo * tint);
+        assert_eq!(hit(-3.0, 0.5).unwrap().material.albedo, Material::clay().albedo * tint);
+        
+        // The materials each instance is drawn in, by name
+        let names: Vec<Vec<&str>> = desc.objects.iter().map(|object| Vec::from_iter(desc.material_names(object))).collect();
+        assert_eq!(names, [vec!["clay", "green"], vec!["red"], vec!["blue", "clay"]]);
+        
+        let missing = SceneDesc::from_json(&desc.to_json().replace("\"geometry\": \"tree\"", "\"geometry\": \"oak\"")).unwrap();
+        assert!(matches!(missing.build(1.0, 45.0, 1.0), Err(SceneError::UnknownGeometry(name)) if name == "oak"));
+    }
+    
+    #[test]
+    fn test_groups() {
+        // A snowman turned a quarter right and moved back, its head a group of its own tipped forward
+        let desc = SceneDesc::from_json(r#"{
+            "camera": { "look_from": [0, 0, 0], "look_at": [0, 0, -1] },
+            "objects": [
+                { "type": "group", "translate": [0, 0, -5], "rotate": [0, 90, 0], "material": "white", "children": [
+                    { "type": "sphere", "center": [0, 0.5, 0], "radius": 0.5 },
+                    { "type": "group", "translate": [0, 1.2, 0], "rotate": [30, 0, 0], "children": [
+                        { "type": "sphere", "center": [0, 0, 0], "radius": 0.3 },
+                        { "type": "capsule", "start": [0, 0, 0.25], "end": [0, 0, 0.5], "radius": 0.05, "material": "red" }
+                    ] }
+                ] }
+            ]
+        }"#).unwrap();
+        // The nose follows both turns: to the right, and down a little
+        let tip = Vec3::new(0.5 * (30.0 as Float).to_radians().cos(), 1.2 - 0.5 * (30.0 as Float).to_radians().sin(), -5.0);
+        let (min, max) = desc.bounds().unwrap();
+        assert!(min[1].abs() < 1e-6 && (min[2] + 5.5).abs() < 1e-6, "{:?} {:?}", min, max);
+        let (min, max) = (vec3(min), vec3(max));
+        assert!((0..3).all(|axis| min[axis] < tip[axis] && tip[axis] <= max[axis]));
+        
+        let (scene, _) = desc.build(1.0, 45.0, 1.0).unwrap();
+        assert_eq!(scene.objects.len(), 1);
+        let body = scene.intersect(&Ray::new(Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0))).unwrap();
+        assert!((body.t - 4.5).abs() < 1e-4 && body.material.albedo == Material::white().albedo);
+        let nose = scene.intersect(&Ray::new(tip + Vec3::new(1.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0))).unwrap();
+        assert!((nose.t - 0.95).abs() < 1e-3 && nose.material.albedo == Material::red().albedo);
+    }
+    
+    #[test]
+    fn test_arrays() {
+        // A fence of three posts, in two rows, each post turned a little more than the last
+        let desc = SceneDesc::from_json(r#"{
+            "camera": { "look_from": [0, 0, 0], "look_at": [0, 0, -1] },
+            "objects": [
+                { "type": "array", "count": [3, 1, 2], "spacing": [2, 0, -3], "translate": [-2, 0, -5], "turn": [0, 45, 0],
+                  "materials": ["red", "green"], "seed": 3,
+                  "object": { "type": "cube", "min": [-0.5, 0, -0.5], "max": [0.5, 1, 0.5] } }
+            ]
+        }"#).unwrap();
+        let copies = desc.objects[0].shape.array_copies();
+        assert_eq!(copies.len(), 6);
+        assert_eq!(copies[4].0.translation, Vec3::new(0.0, 0.0, -8.0));
+        let (min, max) = desc.bounds().unwrap();
+        let half = 0.5f64.sqrt(); // The half-width of a post turned 45 degrees
+        let expected = [-2.0 - half, 0.0, -8.0 - half, 2.0 + half, 1.0, -5.0 + half];
+        assert!(min.iter().chain(&max).zip(expected).all(|(a, b)| (a - b).abs() < 1e-4), "{:?} {:?}", min, max);
+        
+        // Each post is drawn in one of the materials; the second, turned 45 degrees, shows an edge
+        let (scene, _) = desc.build(1.0, 45.0, 1.0).unwrap();
+        assert_eq!(scene.objects.len(), 1);
+        let hit = |x: Float| scene.intersect(&Ray::new(Vec3::new(x, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0))).unwrap();
+        assert!((hit(-2.0).t - 4.5).abs() < 1e-4 && (hit(0.0).t - (5.0 - Float::sqrt(0.5))).abs() < 1e-4);
+        assert!([-2.0, 0.0, 2.0].iter().all(|&x| [Material::red().albedo, Material::green().albedo].contains(&hit(x).material.albedo)));
+        
+        // Jitter is the same from run to run, and leaves the material picks as they were
+        let jittered = desc.to_json().replace("\"seed\": 3", "\"seed\": 3, \"jitter\": { \"translate\": [0.2, 0, 0.2], \"scale\": 0.3 }");
+        let jittered = SceneDesc::from_json(&jittered).unwrap().objects[0].shape.array_copies();
+        assert_eq!(jittered.iter().map(|copy| copy.1).collect::<Vec<_>>(), copies.iter().map(|copy| copy.1).collect::<Vec<_>>());
+        assert!(jittered.iter().zip(&copies).all(|(a, b)| a.0.translation != b.0.translation && (a.0.translation - b.0.translation).abs().max_component() <= 0.2));
+    }
+    
+    #[test]
+    fn test_bounds_and_export() {
+        let desc = SceneDesc::from_json(SCENE).unwrap();
+        assert_eq!(desc.bounds(), Some(([-1.0, -1.0, -5.0], [2.5, 1.0, -2.0])));
+        
+        // Exported JSON parses back to the same scene
+        let exported = SceneDesc::from_json(&desc.to_json()).unwrap();
+        assert_eq!(exported.objects.len(), desc.objects.len());
+        assert_eq!(exported.materials["dull_gold"].reflectivity, Some(0.2));
+        assert!(!desc.to_json().contains("null"));
+    }
+    
+    #[test]
+    fn test_example_scene_loads() {
+        let desc = SceneDesc::from_json(include_str!("../scenes/showcase.json")).unwrap();
+        assert!(desc.build(1.0, 45.0, 4.0 / 3.0).is_ok());
+        #[cfg(feature = "fs")]
+        {
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/outdoor.json"))).unwrap();
+            let (scene, _) = desc.build(1.0, 45.0, 4.0 / 3.0).unwrap();
+            assert!(scene.environment.is_some());
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/lobby.json"))).unwrap();
+            let (scene, _) = desc.build(1.0, 45.0, 4.0 / 3.0).unwrap();
+            let downlight = &scene.lights[0];
+            assert!(downlight.profile.is_some());
+            assert!(downlight.intensity_at(Vec3::new(-1.5, 0.0, -3.0)) > downlight.intensity_at(Vec3::new(1.5, 0.0, -3.0)));
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/checkerboard.json"))).unwrap();
+            let floor = desc.resolve_material(&MaterialRef::Named("checker".to_string())).unwrap();
+            assert_eq!(floor.texture.map(|texture| (texture.levels(), texture.scale)), Some((7, 2.0)));
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/trellis.json"))).unwrap();
+            let lattice = desc.resolve_material(&MaterialRef::Named("lattice".to_string())).unwrap();
+            assert!(lattice.opacity.is_some() && lattice.texture.is_none());
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/screen.json"))).unwrap();
+            let screen = desc.resolve_material(&MaterialRef::Named("screen".to_string())).unwrap();
+            assert!(screen.emission_texture.is_some() && screen.emission == Color::gray(3.0));
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/bricks.json"))).unwrap();
+            let brick = desc.resolve_material(&MaterialRef::Named("brick".to_string())).unwrap();
+            assert!(brick.bump.is_some() && brick.bump_height == 0.03);
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/brushed.json"))).unwrap();
+            let turned = desc.resolve_material(&MaterialRef::Named("turned".to_string())).unwrap();
+            assert!(turned.anisotropy == 0.8 && turned.anisotropy_rotation == 90.0);
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/lacquer.json"))).unwrap();
+            let wood = desc.resolve_material(&MaterialRef::Named("lacquered_wood".to_string())).unwrap();
+            assert!(wood.texture.is_some() && wood.clearcoat == 0.8 && wood.clearcoat_roughness == 0.05);
+            
+            assert!(SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/prism.json"))).is_ok());
+            let ior = |json: &str| SceneDesc::from_json(json).unwrap().materials["glass"].ior.map(IorDesc::to_vec3);
+            assert_eq!(ior(r#"{"materials": {"glass": {"ior": 1.5}}}"#), Some(Vec3::new(1.5, 1.5, 1.5)));
+            assert_eq!(ior(r#"{"materials": {"glass": {"ior": [1.5, 1.6, 1.7]}}}"#), Some(Vec3::new(1.5, 1.6, 1.7)));
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/tumbler.json"))).unwrap();
+            let priorities: Vec<_> = desc.objects.iter().filter_map(|object| object.priority).collect();
+            assert_eq!(priorities, [1, 2, 3]);
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/gems.json"))).unwrap();
+            let ruby = desc.resolve_material(&MaterialRef::Named("ruby".to_string())).unwrap();
+            assert!(ruby.absorption == Color::new(0.9, 0.1, 0.2) && ruby.absorption_density == 1.5);
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/pills.json"))).unwrap();
+            assert_eq!(desc.objects[1].shape.type_name(), "capsule");
+            let (min, max) = desc.objects[1].shape.bounds().unwrap();
+            assert!((min[0] + 1.6).abs() < 1e-9 && (min[1] + 0.5).abs() < 1e-9 && (max[2] - 0.3).abs() < 1e-9);
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/wires.json"))).unwrap();
+            assert!(matches!(desc.objects[5].shape, ShapeDesc::Curve { basis: CurveBasis::Bezier, .. }));
+            let (min, max) = desc.objects[3].shape.bounds().unwrap();
+            assert!((min[1] - 0.635).abs() < 1e-6 && (max[1] - 0.965).abs() < 1e-6); // Sagging to its middle point
+            assert!(desc.build(1.0, 45.0, 1.0).is_ok());
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/valley.json"))).unwrap();
+            assert!(matches!(&desc.objects[0].shape, ShapeDesc::Heightfield { path, .. } if path.ends_with("scenes/hills.png")));
+            assert!(desc.build(1.0, 45.0, 1.0).is_ok());
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/voxels.json"))).unwrap();
+            assert_eq!(desc.objects[1].shape.type_name(), "voxels");
+            assert!(desc.build(1.0, 45.0, 1.0).is_ok());
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/scan.json"))).unwrap();
+            assert!(matches!(&desc.objects[2].shape, ShapeDesc::Points { path, splat: Splat::Sphere, .. } if path.ends_with("scenes/knot.ply")));
+            assert!(desc.build(1.0, 45.0, 1.0).is_ok());
+            
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/meshes.json"))).unwrap();
+            assert_eq!(desc.objects[1].shape.type_name(), "mesh");
+            assert!(desc.build(1.0, 45.0, 1.0).is_ok());
+            assert!(SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/nuts.json"))).unwrap().build(1.0, 45.0, 1.0).is_ok());
+            let smoothing = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/smoothing.json"))).unwrap();
+            assert!(matches!(smoothing.objects[2].shape, ShapeDesc::Mesh { shading: Some(Shading::Smooth), .. }));
+            assert!(matches!(smoothing.objects[3].shape, ShapeDesc::Mesh { subdivision: Some(2), .. }));
+            assert!(smoothing.build(1.0, 45.0, 1.0).is_ok());
+            let painted = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/painted.json"))).unwrap();
+            assert!(painted.build(1.0, 45.0, 1.0).is_ok());
+            let snowmen = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/snowmen.json"))).unwrap();
+            assert!(matches!(&snowmen.objects[1].shape, ShapeDesc::Group { children, .. } if children.len() == 7));
+            assert!(snowmen.build(1.0, 45.0, 1.0).is_ok());
+            let colonnade = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/colonnade.json"))).unwrap();
+            assert_eq!(colonnade.objects[3].shape.array_copies().len(), 84);
+            assert!(colonnade.build(1.0, 45.0, 1.0).is_ok());
+            
+            let parking = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/parking.json"))).unwrap();
+            assert!(matches!(&parking.objects[1].shape, ShapeDesc::Instance { overrides, .. } if overrides.len() == 1));
+            assert!(parking.build(1.0, 45.0, 1.0).is_ok());
+            
+            let facade = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/facade.json"))).unwrap();
+            assert_eq!(facade.camera.as_ref().unwrap().shift, Some([0.0, 0.3]));
+            assert!(facade.build(1.0, 45.0, 1.0).is_ok());
+            
+            let panorama = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/panorama.json"))).unwrap();
+            assert_eq!(panorama.build(1.0, 45.0, 2.0).unwrap().1.projection, Projection::Panorama);
+            let mut faceless = desc.clone(); // The point cloud has no faces to make a mesh of
+            faceless.objects[1].shape = ShapeDesc::Mesh { path: Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes/knot.ply"), translate: [0.0; 3], scale: 1.0, shading: None, subdivision: None };
+            assert!(matches!(faceless.build(1.0, 45.0, 1.0), Err(SceneError::Mesh { source: ScanError::NoFaces, .. })));
+            
+            // A glTF file as an object, and as a whole scene with its own camera and light
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/tabletop.json"))).unwrap();
+            assert_eq!(desc.objects[1].shape.type_name(), "gltf");
+            assert!(desc.build(1.0, 45.0, 1.0).is_ok());
+            let desc = SceneDesc::from_file(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/tabletop.gltf"))).unwrap();
+            let camera = desc.camera.as_ref().unwrap();
+            assert!(camera.look_from.iter().zip([0.0, 1.9, 3.4]).all(|(a, b)| (a - b).abs() < 1e-6));
+            assert_eq!(camera.fov.map(f64::round), Some(40.0));
+            assert!(desc.lights[0].lumens.is_some() && camera.iso.is_some());
+            assert!(desc.build(1.0, 45.0, 1.0).is_ok());
+        }
+        assert!(matches!(SceneDesc::from_json("{}").unwrap().build(1.0, 45.0, 1.0), Err(SceneError::MissingCamera)));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::math::{Float, Vec3};
+use crate::camera::Camera;
+use crate::material::Material;
+use crate::shapes::{Sphere, Plane, Cube, Cylinder};
+use crate::scene::{Scene, Light};
+
+/// Build one of the built-in demo scenes together with its camera
+/// - number: scene number (1-4, anything else falls back to a plain red sphere)
+/// - brightness: light intensity multiplier
+/// - fov: requested vertical field of view in degrees
+/// - aspect_ratio: width / height
+pub fn build(number: u32, brightness: f64, fov: f64, aspect_ratio: f64) -> (Scene, Camera) {
+    let (brightness, fov, aspect_ratio) = (brightness as Float, fov as Float, aspect_ratio as Float);
+    let mut scene = Scene::new();
+    let camera;
+    
+    match number {
+        1 => {
+            // Scene 1: Bright green sphere, no plane, no shadows, blue background
+            scene.background_color = Vec3::new(0.5, 0.7, 1.0); // Light blue background
+            
+            scene.add_object(Box::new(Sphere::new(
+                Vec3::new(0.0, 0.0, -3.0),
+                1.2,
+                Material::green(),
+            )));
+            
+            // Bright lighting for maximum brightness
+            scene.add_light(Light::white_light(
+                Vec3::new(0.0, 0.0, 1.0), // Light from front to avoid shadows
+                brightness * 2.0,          // Extra bright
+            ));
+            
+            camera = Camera::new(
+                Vec3::new(0.0, 0.0, 0.0),   // look_from
+                Vec3::new(0.0, 0.0, -1.0),  // look_at
+                Vec3::unit_y(),             // up
+                fov,                        // fov
+                aspect_ratio,               // aspect_ratio
+            );
+        }
+        2 => {
+            // Scene 2: Red cube on gray plane with shadows, dimmer than Scene 1
+            scene.background_color = Vec3::new(0.5, 0.7, 1.0); // Same blue background
+            
+            scene.add_object(Box::new(Plane::horizontal(-1.5, Material::gray())));
+            scene.add_object(Box::new(Cube::new(
+                Vec3::new(-0.5, -1.5, -3.7), // min corner - smaller cube
+                Vec3::new(0.5, -0.5, -2.7),  // max corner - smaller cube
+                Material::red(),
+            )));
+            
+            // Dimmer lighting with shadows
+            scene.add_light(Light::white_light(
+                Vec3::new(2.0, 3.0, -1.0),
+                brightness * 0.6, // Dimmer than scene 1
+            ));
+            
+            camera = Camera::new(
+                Vec3::new(0.0, 0.5, 0.0),
+                Vec3::new(0.0, -0.5, -3.0),
+                Vec3::unit_y(),
+                fov,
+                aspect_ratio,
+            );
+        }
+        3 | 4 => {
+            // Scenes 3 and 4: All primitives (green sphere, blue cylinder, red cube) on gray plane
+            scene.background_color = Vec3::new(0.5, 0.7, 1.0); // Same blue background
+            
+            scene.add_object(Box::new(Plane::horizontal(-1.5, Material::gray())));
+            
+            // Green sphere (left)
+            scene.add_object(Box::new(Sphere::new(
+                Vec3::new(-2.5, -0.7, -4.0),
+                0.8,
+                Material::green(),
+            )));
+            
+            // Blue cylinder (center)
+            scene.add_object(Box::new(Cylinder::new(
+                Vec3::new(0.0, -1.5, -4.5),
+                0.6,
+                2.5,  // Increased height from 1.8 to 2.5
+                Material::blue(),
+            )));
+            
+            // Red cube (right)
+            scene.add_object(Box::new(Cube::new(
+                Vec3::new(1.8, -1.5, -3.7),
+                Vec3::new(2.8, -0.5, -2.7),
+                Material::red(),
+            )));
+            
+            scene.add_light(Light::white_light(
+                Vec3::new(2.0, 4.0, -1.0),
+                brightness * 0.8,
+            ));
+            
+            camera = if number == 3 {
+                Camera::new(
+                    Vec3::new(0.0, 1.5, 1.0),  // Move camera back and up
+                    Vec3::new(0.0, -0.5, -4.0),
+                    Vec3::unit_y(),
+                    fov.max(60.0),  // Ensure minimum 60° FOV for wider view
+                    aspect_ratio,
+                )
+            } else {
+                // Scene 4: different camera position - from the side and lower
+                Camera::new(
+                    Vec3::new(-4.0, 0.5, -1.0),  // Further back side view for full visibility
+                    Vec3::new(0.0, -0.5, -4.0),  // Same target
+                    Vec3::unit_y(),
+                    fov.max(65.0),  // Wider FOV for side view
+                    aspect_ratio,
+                )
+            };
+        }
+        _ => {
+            // Default to scene 1
+            scene.add_object(Box::new(Sphere::new(
+                Vec3::new(0.0, 0.0, -3.0),
+                1.0,
+                Material::red(),
+            )));
+            
+            scene.add_light(Light::white_light(
+                Vec3::new(2.0, 2.0, 0.0),
+                brightness,
+            ));
+            
+            camera = Camera::new(
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, -1.0),
+                Vec3::unit_y(),
+                fov,
+                aspect_ratio,
+            );
+        }
+    }
+    
+    (scene, camera)
+}
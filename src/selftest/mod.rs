@@ -0,0 +1,202 @@
+use crate::math::{Float, Vec3, Ray, Interval};
+use crate::material::Material;
+use crate::shapes::{Sphere, Plane, Cube, Cylinder, Capsule, Intersectable};
+use crate::render::Renderer;
+use crate::scenes;
+
+/// Resolution used for the reference renders
+const REF_WIDTH: u32 = 32;
+const REF_HEIGHT: u32 = 24;
+
+/// Maximum per-channel difference before a pixel counts as mismatched
+const PIXEL_TOLERANCE: u8 = 2;
+
+/// Fraction of pixels allowed to mismatch (edge pixels may flip on other platforms)
+const MAX_MISMATCH_FRACTION: f64 = 0.01;
+
+/// Allowed error in the exact-math checks, wider for single precision
+#[cfg(not(feature = "f32"))]
+const TOLERANCE: Float = 1e-9;
+#[cfg(feature = "f32")]
+const TOLERANCE: Float = 1e-5;
+
+/// Reference renders of scenes 1-4 at 32×24, brightness 1.0, fov 45°
+const REFERENCES: [(u32, &str); 4] = [
+    (1, include_str!("refs/scene1.ppm")),
+    (2, include_str!("refs/scene2.ppm")),
+    (3, include_str!("refs/scene3.ppm")),
+    (4, include_str!("refs/scene4.ppm")),
+];
+
+/// Outcome of a single self-test check
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+    pub detail: Option<String>,
+}
+
+impl CheckResult {
+    fn pass(name: &str) -> Self {
+        Self { name: name.to_string(), passed: true, detail: None }
+    }
+    
+    fn fail(name: &str, detail: String) -> Self {
+        Self { name: name.to_string(), passed: false, detail: Some(detail) }
+    }
+    
+    fn check(name: &str, passed: bool, detail: impl FnOnce() -> String) -> Self {
+        if passed {
+            Self::pass(name)
+        } else {
+            Self::fail(name, detail())
+        }
+    }
+}
+
+/// Run every self-test check and return the individual results
+pub fn run() -> Vec<CheckResult> {
+    let mut results = Vec::new();
+    results.extend(math_checks());
+    results.extend(intersection_checks());
+    results.extend(render_checks());
+    results.push(thread_determinism_check());
+    results
+}
+
+fn approx_eq(a: Float, b: Float) -> bool {
+    (a - b).abs() < TOLERANCE
+}
+
+fn math_checks() -> Vec<CheckResult> {
+    let a = Vec3::new(1.0, 2.0, 3.0);
+    let b = Vec3::new(-4.0, 0.5, 2.0);
+    
+    let n = Vec3::new(3.0, -7.0, 11.0).normalize();
+    let cross = a.cross(&b);
+    let reflected = Vec3::new(1.0, -1.0, 0.0).reflect(&Vec3::unit_y());
+    let sqrt2 = (2.0 as Float).sqrt();
+    
+    vec![
+        CheckResult::check("math: normalize yields unit length", approx_eq(n.length(), 1.0), || {
+            format!("length = {}", n.length())
+        }),
+        CheckResult::check(
+            "math: cross product is orthogonal to inputs",
+            approx_eq(cross.dot(&a), 0.0) && approx_eq(cross.dot(&b), 0.0),
+            || format!("dots = {}, {}", cross.dot(&a), cross.dot(&b)),
+        ),
+        CheckResult::check(
+            "math: reflect mirrors about the normal",
+            reflected == Vec3::new(1.0, 1.0, 0.0),
+            || format!("got {:?}", reflected),
+        ),
+        CheckResult::check("math: sqrt is correctly rounded", (sqrt2 * sqrt2 - 2.0).abs() <= 4.0 * Float::EPSILON, || {
+            format!("sqrt(2)^2 - 2 = {:e}", sqrt2 * sqrt2 - 2.0)
+        }),
+        CheckResult::check(
+            "math: tan(fov/2) matches reference",
+            approx_eq((45.0 as Float).to_radians().tan(), 1.0),
+            || format!("tan(45°) = {}", (45.0 as Float).to_radians().tan()),
+        ),
+    ]
+}
+
+fn intersection_checks() -> Vec<CheckResult> {
+    let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    let shapes: [(&str, Box<dyn Intersectable>, Float); 5] = [
+        ("sphere", Box::new(Sphere::new(Vec3::zero(), 1.0, Material::red())), 4.0),
+        ("cube", Box::new(Cube::unit(Material::red())), 4.5),
+        ("cylinder", Box::new(Cylinder::new(Vec3::zero(), 1.0, 2.0, Material::red())), 4.0),
+        ("capsule", Box::new(Capsule::new(-Vec3::unit_y(), Vec3::unit_y(), 1.0, Material::red())), 4.0),
+        ("plane", Box::new(Plane::new(Vec3::zero(), Vec3::unit_z(), Material::red())), 5.0),
+    ];
+    
+    shapes
+        .iter()
+        .map(|(name, shape, expected_t)| {
+            let check_name = format!("intersect: {} hit distance", name);
+            match shape.intersect(&ray, Interval::FORWARD) {
+                Some(hit) => CheckResult::check(&check_name, approx_eq(hit.t, *expected_t), || {
+                    format!("expected t = {}, got {}", expected_t, hit.t)
+                }),
+                None => CheckResult::fail(&check_name, "ray missed".to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Render a built-in scene at reference resolution and return its RGB bytes
+fn render_reference_scene(number: u32) -> Vec<u8> {
+    let (scene, camera) = scenes::build(number, 1.0, 45.0, REF_WIDTH as f64 / REF_HEIGHT as f64);
+    let renderer = Renderer::new();
+    renderer.render(&scene, &camera, REF_WIDTH, REF_HEIGHT).pixels().to_vec()
+}
+
+/// Parse the pixel data of an embedded P3 reference image
+fn parse_reference(text: &str) -> Vec<u8> {
+    text.split_whitespace()
+        .skip(4) // "P3", width, height, maxval
+        .filter_map(|value| value.parse().ok())
+        .collect()
+}
+
+fn render_checks() -> Vec<CheckResult> {
+    REFERENCES
+        .iter()
+        .map(|(number, reference)| {
+            let name = format!("render: scene {} matches reference", number);
+            let expected = parse_reference(reference);
+            let actual = render_reference_scene(*number);
+            
+            if expected.len() != actual.len() {
+                return CheckResult::fail(
+                    &name,
+                    format!("expected {} channels, got {}", expected.len(), actual.len()),
+                );
+            }
+            
+            let mismatched = expected
+                .chunks(3)
+                .zip(actual.chunks(3))
+                .filter(|(e, a)| e.iter().zip(a.iter()).any(|(e, a)| e.abs_diff(*a) > PIXEL_TOLERANCE))
+                .count();
+            let total = expected.len() / 3;
+            
+            CheckResult::check(&name, mismatched as f64 <= total as f64 * MAX_MISMATCH_FRACTION, || {
+                format!("{} of {} pixels differ", mismatched, total)
+            })
+        })
+        .collect()
+}
+
+fn thread_determinism_check() -> CheckResult {
+    let name = "render: identical output across threads";
+    let expected = render_reference_scene(3);
+    
+    let outputs: Vec<Option<Vec<u8>>> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..4).map(|_| s.spawn(|| render_reference_scene(3))).collect();
+        handles.into_iter().map(|h| h.join().ok()).collect()
+    });
+    
+    for (i, output) in outputs.iter().enumerate() {
+        match output {
+            Some(pixels) if *pixels == expected => {}
+            Some(_) => return CheckResult::fail(name, format!("thread {} produced different pixels", i)),
+            None => return CheckResult::fail(name, format!("thread {} panicked", i)),
+        }
+    }
+    
+    CheckResult::pass(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_selftest_passes() {
+        let failures: Vec<_> = run().into_iter().filter(|r| !r.passed).collect();
+        assert!(failures.is_empty(), "failed checks: {:?}", failures);
+    }
+}
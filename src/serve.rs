@@ -0,0 +1,437 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::png::PngWriter;
+use crate::render::{Progress, Renderer};
+use crate::scene::Scene;
+use crate::scene_file::{SceneDesc, SceneError, DEFAULT_FOV};
+use crate::stats::Stopwatch;
+use crate::validate::{self, Severity};
+
+/// Largest request line and headers accepted
+const MAX_HEADER: u64 = 8192;
+
+/// Largest request body accepted, scene and options together
+const MAX_BODY: usize = 64 << 20;
+
+/// Most samples a request may ask for over the whole image, e.g. 4K at 512 per pixel
+const MAX_PIXEL_SAMPLES: u64 = 1 << 32;
+
+/// How long a client may take to send each read of its request
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Largest image width or height a request may ask for
+const MAX_SIZE: u32 = 16384;
+
+/// How often a streamed render reports its progress
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A render asked for over HTTP (or handed to distributed workers): a scene in the scene file format, with the
+/// command line's main options
+/// Scenes with `include`s must be flattened with `rt export` first; meshes and textures they name are read
+/// relative to the working directory of whoever renders them, or to the assets directory of a `Server`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderRequest {
+    pub scene: Option<SceneDesc>, // Required
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+    pub reflect: bool,
+    pub path_trace: bool,
+    pub transparent: bool,
+    pub seed: u64,
+    pub exposure: f64, // Stops (EV)
+    pub gamma: f64,
+}
+
+impl Default for RenderRequest {
+    fn default() -> Self {
+        Self {
+            scene: None,
+            width: 800,
+            height: 600,
+            samples: 1,
+            reflect: false,
+            path_trace: false,
+            transparent: false,
+            seed: 0,
+            exposure: 0.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl RenderRequest {
+    /// Check the options, naming the one at fault; only building the scene can fail after this
+    pub fn check(&self) -> Result<(), String> {
+        let (width, height) = (self.width, self.height);
+        match &self.scene {
+            None => Err("invalid request: missing field `scene`".to_string()),
+            Some(desc) if !desc.include.is_empty() => Err(SceneError::IncludesUnavailable.to_string()),
+            _ if width == 0 || height == 0 || width > MAX_SIZE || height > MAX_SIZE => {
+                Err(format!("image size must be 1 to {} pixels each way, got {}x{}", MAX_SIZE, width, height))
+            }
+            _ if self.samples == 0 => Err("need at least one sample per pixel".to_string()),
+            _ if !(self.exposure.is_finite() && self.gamma.is_finite() && self.gamma > 0.0) => {
+                Err(format!("exposure must be finite and gamma positive, got {} and {}", self.exposure, self.gamma))
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    /// Build the scene and its camera
+    pub fn build(&self) -> Result<(Scene, Camera), String> {
+        let desc = self.scene.as_ref().ok_or("invalid request: missing field `scene`")?;
+        desc.build(1.0, DEFAULT_FOV, self.width as f64 / self.height as f64).map_err(|err| err.to_string())
+    }
+    
+    /// A renderer with the request's options, on this many threads
+    pub fn renderer(&self, threads: usize) -> Renderer {
+        let mut renderer = Renderer::new();
+        renderer.samples = self.samples;
+        renderer.threads = threads;
+        renderer.reflections = self.reflect;
+        renderer.path_tracing = self.path_trace;
+        renderer.transparent_background = self.transparent;
+        renderer.seed = self.seed;
+        renderer
+    }
+    
+    /// Apply the exposure and gamma to a finished render
+    pub fn finish(&self, framebuffer: &mut Framebuffer) {
+        framebuffer.apply_exposure(self.exposure);
+        framebuffer.apply_gamma(self.gamma);
+    }
+}
+
+/// One line of a streamed render's response, as JSON tagged by `event`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum RenderEvent {
+    Progress { fraction: f64 },
+    Done { width: u32, height: u32, seconds: f64, png: String }, // The image, base64-encoded
+    Error { message: String },
+}
+
+/// Serves renders over HTTP/1.1, one thread per connection and one request per connection
+///
+/// - `POST /render` streams newline-delimited JSON `RenderEvent`s: progress, then the PNG or an error
+/// - `POST /render.png` answers with just the PNG, for scripts
+/// - `GET /health` answers `ok`
+///
+/// Scenes may only read files under `assets`, by relative paths; without it, scenes that read files are refused.
+#[derive(Debug, Clone)]
+pub struct Server {
+    pub threads: usize,           // Render threads per request
+    pub assets: Option<PathBuf>,  // Directory the scenes' textures, meshes and other files are read from
+    pub max_renders: usize,       // Renders at once; more are answered 503 Service Unavailable
+    renders: Arc<AtomicUsize>,    // Renders running now
+}
+
+impl Server {
+    /// A server rendering on this many threads per request, reading no files and running up to four renders at once
+    pub fn new(threads: usize) -> Self {
+        Self { threads, assets: None, max_renders: 4, renders: Arc::default() }
+    }
+    
+    /// Answer connections on the listener until it fails
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = self.clone();
+            std::thread::spawn(move || {
+                let peer = stream.peer_addr().map_or("unknown".to_string(), |addr| addr.to_string());
+                if let Err(err) = server.handle(stream) {
+                    log::warn!("{}: {}", peer, err);
+                }
+            });
+        }
+        Ok(())
+    }
+    
+    /// Read one request from the stream and answer it
+    pub fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut stream = stream;
+        let (method, path, body) = match read_request(&mut reader) {
+            Ok(request) => request,
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => return respond(&mut stream, "400 Bad Request", "text/plain", err.to_string().as_bytes()),
+            Err(err) => return Err(err),
+        };
+        log::info!("{} {}", method, path);
+        
+        let slot = match (method.as_str(), path.as_str()) {
+            ("POST", "/render" | "/render.png") => match self.take_slot() {
+                Some(slot) => Some(slot),
+                None => return respond(&mut stream, "503 Service Unavailable", "text/plain", b"too many renders running, try again later\n"),
+            },
+            _ => None,
+        };
+        let response = match (method.as_str(), path.as_str()) {
+            ("GET", "/health") => respond(&mut stream, "200 OK", "text/plain", b"ok\n"),
+            ("POST", "/render") => {
+                let request = match self.parse_request(&body) {
+                    Ok(request) => request,
+                    Err(message) => return respond(&mut stream, "400 Bad Request", "application/json", &event_line(&RenderEvent::Error { message })),
+                };
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n")?;
+                let event = self.render(&request, |fraction| {
+                    let _ = write_chunk(&mut stream, &event_line(&RenderEvent::Progress { fraction })); // A client gone away only misses events
+                });
+                write_chunk(&mut stream, &event_line(&event))?;
+                write_chunk(&mut stream, &[])
+            }
+            ("POST", "/render.png") => {
+                let png = self.parse_request(&body).and_then(|request| self.render_png(&request, |_| {}).map(|(png, _)| png));
+                match png {
+                    Ok(png) => respond(&mut stream, "200 OK", "image/png", &png),
+                    Err(message) => respond(&mut stream, "400 Bad Request", "text/plain", format!("{}\n", message).as_bytes()),
+                }
+            }
+            (_, "/health" | "/render" | "/render.png") => respond(&mut stream, "405 Method Not Allowed", "text/plain", b"method not allowed\n"),
+            _ => respond(&mut stream, "404 Not Found", "text/plain", b"not found\n"),
+        };
+        drop(slot);
+        response
+    }
+    
+    /// Count a render as running until the slot is dropped, or None if `max_renders` are running already
+    fn take_slot(&self) -> Option<Slot> {
+        let max = self.max_renders;
+        self.renders.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |running| (running < max).then_some(running + 1)).ok()?;
+        Some(Slot(self.renders.clone()))
+    }
+    
+    /// Parse and check a request body, refusing scenes that are invalid, too costly, or read files outside `assets`
+    fn parse_request(&self, body: &[u8]) -> Result<RenderRequest, String> {
+        let mut request: RenderRequest = serde_json::from_slice(body).map_err(|err| format!("invalid request: {}", err))?;
+        request.check()?;
+        let pixel_samples = request.width as u64 * request.height as u64 * request.samples as u64;
+        if pixel_samples > MAX_PIXEL_SAMPLES {
+            return Err(format!("{} samples over the image is more than the {} a request may ask for", pixel_samples, MAX_PIXEL_SAMPLES));
+        }
+        let desc = request.scene.as_mut().expect("checked requests have a scene");
+        if let Some(error) = validate::validate(desc).into_iter().find(|d| d.severity == Severity::Error) {
+            return Err(error.to_string());
+        }
+        
+        let mut refused = None;
+        desc.for_each_path_mut(&mut |path| match &self.assets {
+            _ if refused.is_some() => {}
+            Some(assets) if path.components().all(|c| matches!(c, Component::Normal(_))) => *path = assets.join(&*path),
+            Some(_) => refused = Some(format!("file paths must be relative and inside the assets directory, got '{}'", path.display())),
+            None => refused = Some(format!("this server reads no files, but the scene names '{}'", path.display())),
+        });
+        refused.map_or(Ok(request), Err)
+    }
+    
+    /// Render a request, calling `progress` with the fraction done as it goes, to the event ending the stream
+    pub fn render(&self, request: &RenderRequest, progress: impl FnMut(f64)) -> RenderEvent {
+        let start = Stopwatch::start();
+        match self.render_png(request, progress) {
+            Ok((png, (width, height))) => RenderEvent::Done { width, height, seconds: start.elapsed().as_secs_f64(), png: base64(&png) },
+            Err(message) => RenderEvent::Error { message },
+        }
+    }
+    
+    /// Render a request to PNG bytes and the image size
+    fn render_png(&self, request: &RenderRequest, mut progress: impl FnMut(f64)) -> Result<(Vec<u8>, (u32, u32)), String> {
+        let (width, height) = (request.width, request.height);
+        let (scene, camera) = request.build()?;
+        let mut renderer = request.renderer(self.threads);
+        let tracker = Arc::new(Progress::default());
+        renderer.progress = Some(tracker.clone());
+        
+        // Report progress from this thread while another renders, checking often so small renders return promptly
+        let mut framebuffer = std::thread::scope(|scope| {
+            let render = scope.spawn(|| renderer.render_framebuffer(&scene, &camera, width, height));
+            let (mut reported, mut since) = (None, Stopwatch::start());
+            while !render.is_finished() {
+                std::thread::sleep(Duration::from_millis(10));
+                let fraction = tracker.fraction();
+                if since.elapsed() >= PROGRESS_INTERVAL && reported != Some(fraction) {
+                    progress(fraction);
+                    (reported, since) = (Some(fraction), Stopwatch::start());
+                }
+            }
+            render.join().expect("render thread panicked")
+        });
+        request.finish(&mut framebuffer);
+        let png = match request.transparent {
+            true => PngWriter::rgba(width, height, framebuffer.to_rgba8()),
+            false => PngWriter::rgb(width, height, framebuffer.to_rgb8()),
+        };
+        Ok((png.to_bytes(), (width, height)))
+    }
+}
+
+/// A running render, counted off when dropped
+struct Slot(Arc<AtomicUsize>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Read a request line, headers and body; malformed requests are InvalidData errors
+fn read_request(reader: &mut impl BufRead) -> io::Result<(String, String, Vec<u8>)> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut head = reader.take(MAX_HEADER);
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path), Some(_version)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(invalid("malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+    
+    let mut length = 0;
+    loop {
+        line.clear();
+        if head.read_line(&mut line)? == 0 {
+            return Err(invalid(if head.limit() == 0 { "request headers too large" } else { "connection closed in the headers" }));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().map_err(|_| invalid("bad Content-Length"))?;
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err(invalid("request body too large"));
+    }
+    let mut body = vec![0; length];
+    head.into_inner().read_exact(&mut body)?;
+    Ok((method, path, body))
+}
+
+/// Write a whole response and close the connection
+fn respond(stream: &mut impl Write, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, content_type, body.len())?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// Write one chunk of a chunked response; an empty chunk ends it
+fn write_chunk(stream: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    write!(stream, "{:x}\r\n", bytes.len())?;
+    stream.write_all(bytes)?;
+    stream.write_all(b"\r\n")?;
+    stream.flush()
+}
+
+/// An event as one line of JSON
+fn event_line(event: &RenderEvent) -> Vec<u8> {
+    let mut line = serde_json::to_vec(event).expect("events serialize");
+    line.push(b'\n');
+    line
+}
+
+/// Standard base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| bits | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[((bits >> (18 - 6 * i)) & 63) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Send a request to a server answering one connection, returning the whole response
+    fn exchange(request: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || Server::new(1).handle(listener.accept().unwrap().0));
+        let mut client = TcpStream::connect(address).unwrap();
+        client.write_all(request.as_bytes()).unwrap();
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).unwrap();
+        server.join().unwrap().unwrap();
+        String::from_utf8_lossy(&response).into_owned() // PNGs come out garbled, but their headers don't
+    }
+    
+    #[test]
+    fn test_render_over_http() {
+        let body = r#"{
+            "scene": {
+                "camera": { "look_from": [0, 0, 0], "look_at": [0, 0, -1] },
+                "lights": [{ "position": [0, 5, 0] }],
+                "objects": [{ "type": "sphere", "center": [0, 0, -3], "radius": 1, "material": "red" }]
+            },
+            "width": 8, "height": 6
+        }"#;
+        let post = |path: &str, body: &str| exchange(&format!("POST {} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", path, body.len(), body));
+        
+        // The stream ends with the finished image, as the last line before the closing chunk
+        let response = post("/render", body);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n") && response.contains("Transfer-Encoding: chunked"), "{}", response);
+        let done = response.lines().rev().find(|line| line.starts_with('{')).unwrap();
+        let event: serde_json::Value = serde_json::from_str(done).unwrap();
+        assert_eq!((event["event"].as_str(), event["width"].as_u64()), (Some("done"), Some(8)));
+        assert!(event["png"].as_str().unwrap().starts_with("iVBORw0KGgo")); // The PNG signature
+        assert!(response.ends_with("0\r\n\r\n"));
+        
+        let mut server = Server::new(1);
+        server.max_renders = 0;
+        assert!(server.parse_request(body.as_bytes()).is_ok() && server.take_slot().is_none());
+        
+        assert!(post("/render.png", body).contains("Content-Type: image/png\r\nContent-Length: "));
+        let bad = post("/render", r#"{ "scene": {}, "width": 0 }"#);
+        assert!(bad.starts_with("HTTP/1.1 400") && bad.contains(r#""event":"error""#), "{}", bad);
+        assert!(post("/render", r#"{ "scene": {}, "colour": 1 }"#).contains("unknown field `colour`"));
+        assert!(exchange("GET /render HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 405"));
+        assert!(exchange("GET /health HTTP/1.1\r\n\r\n").ends_with("ok\n"));
+        
+        let huge = r#"{ "scene": {}, "width": 16384, "height": 16384, "samples": 64 }"#;
+        assert!(post("/render.png", huge).contains("more than the 4294967296 a request may ask for"));
+        let invalid = r#"{ "scene": { "camera": { "look_from": [0, 0, 0], "look_at": [0, 0, -1] }, "objects": [{ "type": "sphere", "center": [0, 0, -3], "radius": -1 }] } }"#;
+        assert!(post("/render.png", invalid).contains("objects[0].radius"));
+        let header = format!("GET /health HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "a".repeat(10000));
+        assert_eq!(read_request(&mut header.as_bytes()).unwrap_err().to_string(), "request headers too large");
+        
+        assert_eq!(base64(b"rt"), "cnQ=");
+        assert_eq!(base64(b"tile"), "dGlsZQ==");
+    }
+    
+    #[test]
+    fn test_scene_files_stay_in_assets() {
+        let with_mesh = |path: &str| format!(r#"{{ "scene": {{ "camera": {{ "look_from": [0, 0, 0], "look_at": [0, 0, -1] }}, "objects": [{{ "type": "mesh", "path": "{}" }}] }} }}"#, path);
+        let server = Server::new(1);
+        let err = server.parse_request(with_mesh("bunny.obj").as_bytes()).unwrap_err();
+        assert!(err.contains("reads no files"), "{}", err);
+        
+        let mut server = Server::new(1);
+        server.assets = Some(PathBuf::from("assets"));
+        let request = server.parse_request(with_mesh("models/bunny.obj").as_bytes()).unwrap();
+        let shape = &request.scene.unwrap().objects[0].shape;
+        assert!(matches!(shape, crate::scene_file::ShapeDesc::Mesh { path, .. } if path == &PathBuf::from("assets/models/bunny.obj")));
+        for path in ["../secret.obj", "models/../../secret.obj", "/etc/passwd"] {
+            assert!(server.parse_request(with_mesh(path).as_bytes()).unwrap_err().contains("inside the assets directory"), "{}", path);
+        }
+    }
+}
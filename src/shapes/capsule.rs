@@ -0,0 +1,128 @@
+use crate::math::{consts::PI, Float, Vec3, Ray, Interval, Aabb, Onb};
+use crate::material::Material;
+use super::{HitInfo, Intersectable};
+
+/// Capsule primitive: every point within `radius` of the segment from `start` to `end`,
+/// a cylinder of any orientation rounded off by half spheres
+#[derive(Debug, Clone)]
+pub struct Capsule {
+    pub start: Vec3,   // Center of one end
+    pub end: Vec3,     // Center of the other end (the same as `start` makes a sphere)
+    pub radius: Float, // Radius of the body and the rounded ends
+    pub material: Material,
+}
+
+impl Capsule {
+    /// Create a new capsule
+    pub fn new(start: Vec3, end: Vec3, radius: Float, material: Material) -> Self {
+        Self {
+            start,
+            end,
+            radius,
+            material,
+        }
+    }
+}
+
+/// Roots of a·t² + b·t + c = 0, smallest first
+fn solve_quadratic(a: Float, b: Float, c: Float) -> Option<[Float; 2]> {
+    let discriminant = b * b - 4.0 * a * c;
+    if a == 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let sqrt_discriminant = discriminant.sqrt();
+    Some([(-b - sqrt_discriminant) / (2.0 * a), (-b + sqrt_discriminant) / (2.0 * a)])
+}
+
+/// Closest hit within `range` on the capsule of `radius` around the segment from `start` to `end`, as the hit
+/// distance and the point on the segment nearest the hit (the normal points away from it)
+pub(super) fn intersect_capsule(start: Vec3, end: Vec3, radius: Float, ray: &Ray, range: Interval) -> Option<(Float, Vec3)> {
+    let segment = end - start;
+    let length = segment.length();
+    let axis = segment.normalize(); // Zero for a sphere
+    let r2 = radius * radius;
+    let mut closest: Option<(Float, Vec3)> = None;
+    let mut consider = |t: Float, nearest: Vec3| {
+        if range.surrounds(t) && closest.is_none_or(|(closest_t, _)| t < closest_t) {
+            closest = Some((t, nearest));
+        }
+    };
+    
+    // The body: an infinite cylinder around the axis (the ray's parts across it), cut to the segment
+    let across = |v: Vec3| v - axis * v.dot(&axis);
+    let (direction_across, origin_across) = (across(ray.direction), across(ray.origin - start));
+    if let Some(roots) = solve_quadratic(
+        direction_across.length_squared(),
+        2.0 * direction_across.dot(&origin_across),
+        origin_across.length_squared() - r2,
+    ) {
+        for t in roots {
+            let along = (ray.at(t) - start).dot(&axis);
+            if (0.0..=length).contains(&along) {
+                consider(t, start + axis * along);
+            }
+        }
+    }
+    
+    // The ends: spheres around the endpoints, each only on its own side of the body
+    for (center, outward) in [(start, -axis), (end, axis)] {
+        let oc = ray.origin - center;
+        if let Some(roots) = solve_quadratic(ray.direction.length_squared(), 2.0 * oc.dot(&ray.direction), oc.length_squared() - r2) {
+            for t in roots {
+                if (ray.at(t) - center).dot(&outward) >= 0.0 {
+                    consider(t, center);
+                }
+            }
+        }
+    }
+    closest
+}
+
+impl Intersectable for Capsule {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
+        let (t, nearest) = intersect_capsule(self.start, self.end, self.radius, ray, range)?;
+        let point = ray.at(t);
+        let normal = (point - nearest).normalize();
+        
+        // Around the axis and along it from one tip to the other, like a cylinder's wall
+        let length = (self.end - self.start).length();
+        let axis = (self.end - self.start).normalize();
+        let frame = Onb::from_normal(axis);
+        let offset = point - self.start;
+        let radial = (offset - axis * offset.dot(&axis)).normalize();
+        let u = radial.dot(&frame.v).atan2(radial.dot(&frame.u)) / (2.0 * PI) + 0.5;
+        let v = (offset.dot(&axis) + self.radius) / (length + 2.0 * self.radius);
+        let uv_scale = (2.0 * PI * self.radius * (length + 2.0 * self.radius)).sqrt();
+        let axes = (axis.cross(&radial), (axis - normal * normal.dot(&axis)).normalize()); // The latter is zero at the tips
+        Some(HitInfo::new(ray, t, normal, self.material.clone()).with_uv((u, v), uv_scale, axes))
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.start.min(&self.end) - extent, self.start.max(&self.end) + extent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_capsule_intersection() {
+        // Lying along x: the body is hit from above, and the rounded end from beyond it
+        let capsule = Capsule::new(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.5, Material::red());
+        let hit = capsule.intersect(&Ray::new(Vec3::new(0.5, 5.0, 0.0), -Vec3::unit_y()), Interval::FORWARD).unwrap();
+        assert!((hit.t - 4.5).abs() < 1e-6);
+        assert!((hit.normal - Vec3::unit_y()).length() < 1e-6);
+        
+        let ray = Ray::new(Vec3::new(5.0, 0.3, 0.0), -Vec3::unit_x());
+        let hit = capsule.intersect(&ray, Interval::FORWARD).unwrap();
+        assert!((hit.point.x - 1.4).abs() < 1e-6);
+        assert!((hit.normal - Vec3::new(0.8, 0.6, 0.0)).length() < 1e-6);
+        
+        // From inside, the far end is the way out
+        let hit = capsule.intersect(&Ray::new(Vec3::zero(), -Vec3::unit_x()), Interval::FORWARD).unwrap();
+        assert!((hit.t - 1.5).abs() < 1e-6 && !hit.front_face);
+        assert!(capsule.intersect(&Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::unit_x()), Interval::FORWARD).is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::math::{Vec3, Ray};
+use crate::math::{Vec3, Ray, Interval, Aabb};
 use crate::material::Material;
 use super::{HitInfo, Intersectable, Transform};
 
@@ -33,80 +33,54 @@ impl Cube {
 }
 
 impl Intersectable for Cube {
-    fn intersect(&self, ray: &Ray) -> Option<HitInfo> {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
         // Slab method for AABB intersection
-        let mut t_min = f64::NEG_INFINITY;
-        let mut t_max = f64::INFINITY;
-        let mut normal = Vec3::zero();
-        let mut hit_min_face = true; // Track if we hit the min face or max face
-        
-        // Check intersection with each pair of parallel planes (X, Y, Z slabs)
-        for i in 0..3 {
-            let (axis_origin, axis_direction, axis_min, axis_max) = match i {
-                0 => (ray.origin.x, ray.direction.x, self.min.x, self.max.x),
-                1 => (ray.origin.y, ray.direction.y, self.min.y, self.max.y),
-                _ => (ray.origin.z, ray.direction.z, self.min.z, self.max.z),
-            };
-            
-            if axis_direction.abs() < 1e-6 {
-                // Ray is parallel to the slab
-                if axis_origin < axis_min || axis_origin > axis_max {
-                    return None; // Ray misses the slab entirely
-                }
-            } else {
-                let t1 = (axis_min - axis_origin) / axis_direction;
-                let t2 = (axis_max - axis_origin) / axis_direction;
-                
-                let (t_near, t_far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
-                
-                if t_near > t_min {
-                    t_min = t_near;
-                    hit_min_face = t1 < t2; // True if we hit the min face
-                    
-                    // Set normal based on which face and axis we hit
-                    normal = match i {
-                        0 => if hit_min_face { Vec3::new(-1.0, 0.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) },
-                        1 => if hit_min_face { Vec3::new(0.0, -1.0, 0.0) } else { Vec3::new(0.0, 1.0, 0.0) },
-                        _ => if hit_min_face { Vec3::new(0.0, 0.0, -1.0) } else { Vec3::new(0.0, 0.0, 1.0) },
-                    };
-                }
-                
-                if t_far < t_max {
-                    t_max = t_far;
-                }
-                
-                // Early exit if slabs don't overlap
-                if t_min > t_max {
-                    return None;
-                }
-            }
+        let bounds = Aabb::new(self.min, self.max);
+        let inverse_direction = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+        let ((t_min, entry_normal), (t_max, exit_normal)) = bounds.slab_faces(ray, inverse_direction);
+        if t_min > t_max {
+            return None; // Slabs don't overlap
         }
         
-        // Choose the closest positive intersection
-        let t = if t_min > 1e-4 {
-            t_min
-        } else if t_max > 1e-4 {
-            t_max
-            // If we're using t_max, we need to recalculate the normal
-            // This happens when the ray starts inside the box
+        // Choose the closest intersection in range, with the normal of the face it lies on
+        let (t, normal) = if range.surrounds(t_min) {
+            (t_min, entry_normal)
+        } else if range.surrounds(t_max) {
+            (t_max, exit_normal) // The ray starts inside the box, and leaves through this face from behind
         } else {
-            return None; // Both intersections behind ray origin
+            return None; // Both intersections behind ray origin or too far
         };
         
-        let hit_point = ray.at(t);
-        
-        Some(HitInfo {
-            t,
-            point: hit_point,
-            normal,
-            material: self.material.clone(),
-        })
+        // Each face is mapped corner to corner, from its side along x or z and its bottom along y or z
+        let size = self.max - self.min;
+        let local = ray.at(t) - self.min;
+        let (u, v, (width, height), axes) = if normal.x != 0.0 {
+            (local.z / size.z, local.y / size.y, (size.z, size.y), (Vec3::unit_z(), Vec3::unit_y()))
+        } else if normal.y != 0.0 {
+            (local.x / size.x, local.z / size.z, (size.x, size.z), (Vec3::unit_x(), Vec3::unit_z()))
+        } else {
+            (local.x / size.x, local.y / size.y, (size.x, size.y), (Vec3::unit_x(), Vec3::unit_y()))
+        };
+        Some(HitInfo::new(ray, t, normal, self.material.clone()).with_uv((u, v), (width * height).sqrt(), axes))
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+    
+    #[cfg(feature = "simd")]
+    fn primitive(&self) -> Option<crate::simd::Primitive> {
+        Some(crate::simd::Primitive::Box { min: self.min, max: self.max })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
+    use crate::math::Float;
+    use crate::color::Color;
+    use crate::texture::{Texture, Wrap, Filter};
     
     #[test]
     fn test_cube_intersection() {
@@ -114,11 +88,44 @@ mod tests {
         
         // Ray pointing at cube center
         let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
-        let hit = cube.intersect(&ray);
+        let hit = cube.intersect(&ray, Interval::FORWARD);
         
         assert!(hit.is_some());
         let hit = hit.unwrap();
         assert!((hit.t - 0.5).abs() < 1e-10);
         assert_eq!(hit.point, Vec3::new(0.0, 0.0, 0.5));
     }
+    
+    #[test]
+    fn test_ray_from_inside() {
+        let cube = Cube::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 2.0, 1.0), Material::blue());
+        
+        // Each ray leaves through the face it heads for, whose normal is flipped to point back inside
+        let origin = Vec3::new(0.2, 0.5, -0.3);
+        let rays = [
+            (Vec3::new(1.0, 0.1, 0.0), -Vec3::unit_x(), 0.8),
+            (Vec3::new(0.0, 1.0, 0.2), -Vec3::unit_y(), 1.5),
+            (Vec3::new(0.1, -0.2, -1.0), Vec3::unit_z(), 0.7),
+        ];
+        for (direction, normal, t) in rays {
+            let hit = cube.intersect(&Ray::new(origin, direction), Interval::FORWARD).unwrap();
+            assert!(!hit.front_face);
+            assert_eq!(hit.normal, normal, "{:?}", direction);
+            assert!((hit.t - t).abs() < 1e-10);
+        }
+    }
+    
+    #[test]
+    fn test_cutout() {
+        // A thin panel whose cutout map is opaque on the left half of each face and a hole on the right
+        let opacity = Texture::new(2, 1, vec![Color::white(); 2], vec![1.0, 0.0]).with_modes(Wrap::Clamp, Filter::Nearest);
+        let mut material = Material::blue();
+        material.opacity = Some(Arc::new(opacity));
+        let panel = Cube::new(Vec3::new(-1.0, -1.0, -0.01), Vec3::new(1.0, 1.0, 0.01), material);
+        
+        let ray = |x: Float| Ray::new(Vec3::new(x, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(panel.intersect_sided(&ray(-0.5), Interval::FORWARD).is_some());
+        assert!(panel.intersect(&ray(0.5), Interval::FORWARD).is_some_and(|hit| hit.is_cut_out()));
+        assert!(panel.intersect_sided(&ray(0.5), Interval::FORWARD).is_none());
+    }
 }
@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::math::{consts::PI, Float, Vec3, Ray, Interval, Aabb, Onb};
+use crate::material::Material;
+use super::capsule::intersect_capsule;
+use super::{HitInfo, Intersectable};
+
+/// How a curve's control points shape it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CurveBasis {
+    #[default]
+    CatmullRom, // Passes through every point, each span shaped by the points on either side
+    Bezier,     // Cubic spans of four points sharing their ends, passing through every third point only
+}
+
+impl CurveBasis {
+    /// Whether a curve of this basis can be made from `count` points
+    pub fn accepts(self, count: usize) -> bool {
+        match self {
+            CurveBasis::CatmullRom => count >= 2,
+            CurveBasis::Bezier => count >= 4 && count % 3 == 1,
+        }
+    }
+}
+
+/// Tube swept along a smooth curve, `radius` thick at its start and tapering linearly to `tip_radius`:
+/// wires, cables, grass blades and strands of hair
+/// Rays meet it as a chain of `SEGMENTS_PER_SPAN` short capsules per span, fine enough that their
+/// joins don't show, with normals from the nearest point on the chain's axis
+#[derive(Debug, Clone)]
+pub struct Curve {
+    pub radius: Float,
+    pub tip_radius: Float,
+    pub material: Material,
+    points: Vec<Vec3>, // The curve sampled along its length, segment ends
+    bounds: Aabb,
+}
+
+/// Straight segments each span of a curve is divided into
+pub const SEGMENTS_PER_SPAN: usize = 8;
+
+impl Curve {
+    /// Create a curve through (or guided by) `points`: at least 2 for Catmull-Rom, and 3n + 1 for Bézier
+    pub fn new(points: &[Vec3], basis: CurveBasis, radius: Float, tip_radius: Float, material: Material) -> Self {
+        let points = tessellate(points, basis);
+        let thickest = radius.max(tip_radius);
+        let extent = Vec3::new(thickest, thickest, thickest);
+        let bounds = points.iter().fold(Aabb::empty(), |bounds, &point| bounds.grow(point - extent).grow(point + extent));
+        Self { radius, tip_radius, material, points, bounds }
+    }
+    
+    /// Thickness a fraction `along` of the way from start to tip
+    fn radius_at(&self, along: Float) -> Float {
+        self.radius + (self.tip_radius - self.radius) * along
+    }
+}
+
+/// Points along a curve, `SEGMENTS_PER_SPAN` segments per span, from the first point to the last
+pub fn tessellate(points: &[Vec3], basis: CurveBasis) -> Vec<Vec3> {
+    assert!(basis.accepts(points.len()), "a {:?} curve can't be made from {} points", basis, points.len());
+    let spans: Vec<[Vec3; 4]> = match basis {
+        // The end spans lean on their endpoint repeated
+        CurveBasis::CatmullRom => (0..points.len() - 1)
+            .map(|i| [points[i.saturating_sub(1)], points[i], points[i + 1], points[(i + 2).min(points.len() - 1)]])
+            .collect(),
+        CurveBasis::Bezier => points.windows(4).step_by(3).map(|span| [span[0], span[1], span[2], span[3]]).collect(),
+    };
+    let mut samples = vec![points[0]];
+    for [p0, p1, p2, p3] in spans {
+        for step in 1..=SEGMENTS_PER_SPAN {
+            let t = step as Float / SEGMENTS_PER_SPAN as Float;
+            let (t2, t3) = (t * t, t * t * t);
+            samples.push(match basis {
+                CurveBasis::CatmullRom => {
+                    (p1 * 2.0 + (p2 - p0) * t + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2 + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+                }
+                CurveBasis::Bezier => {
+                    let s = 1.0 - t;
+                    p0 * (s * s * s) + p1 * (3.0 * s * s * t) + p2 * (3.0 * s * t2) + p3 * t3
+                }
+            });
+        }
+    }
+    samples
+}
+
+impl Intersectable for Curve {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
+        let inverse_direction = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+        self.bounds.hit(ray, inverse_direction, range)?;
+        
+        // Each segment as thick as the curve at its middle; a hit narrows the search for closer ones
+        let segments = (self.points.len() - 1) as Float;
+        let mut range = range;
+        let mut closest = None;
+        for (index, ends) in self.points.windows(2).enumerate() {
+            let radius = self.radius_at((index as Float + 0.5) / segments);
+            if let Some((t, nearest)) = intersect_capsule(ends[0], ends[1], radius, ray, range) {
+                closest = Some((t, nearest, index));
+                range.max = t;
+            }
+        }
+        let (t, nearest, index) = closest?;
+        let point = ray.at(t);
+        let normal = (point - nearest).normalize();
+        
+        // Along the curve from start to tip, and around it
+        let (start, end) = (self.points[index], self.points[index + 1]);
+        let axis = (end - start).normalize();
+        let along = (nearest - start).length() / (end - start).length().max(Float::MIN_POSITIVE);
+        let frame = Onb::from_normal(axis);
+        let radial = (normal - axis * normal.dot(&axis)).normalize();
+        let uv = ((index as Float + along) / segments, radial.dot(&frame.v).atan2(radial.dot(&frame.u)) / (2.0 * PI) + 0.5);
+        let uv_scale = (2.0 * PI * self.radius * segments * (end - start).length()).sqrt();
+        Some(HitInfo::new(ray, t, normal, self.material.clone()).with_uv(uv, uv_scale, (axis, axis.cross(&radial))))
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+    
+    fn memory(&self, _shared: &mut HashSet<usize>) -> usize {
+        size_of_val(self) + size_of_val(self.points.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_curve() {
+        // Catmull-Rom passes through its points; the Bézier through its ends, pulled toward the others
+        let points = [Vec3::zero(), Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(3.0, 1.0, 0.0)];
+        let through = tessellate(&points, CurveBasis::CatmullRom);
+        assert_eq!(through.len(), 3 * SEGMENTS_PER_SPAN + 1);
+        assert!((through[SEGMENTS_PER_SPAN] - points[1]).length() < 1e-6);
+        let pulled = tessellate(&points, CurveBasis::Bezier);
+        assert!((pulled[SEGMENTS_PER_SPAN / 2] - Vec3::new(1.5, 0.5, 0.0)).length() < 1e-6);
+        
+        // A straight wire tapering from 0.2 to 0, hit from the side near its thick end
+        let wire = Curve::new(&[Vec3::zero(), Vec3::new(4.0, 0.0, 0.0)], CurveBasis::CatmullRom, 0.2, 0.0, Material::gray());
+        let hit = wire.intersect(&Ray::new(Vec3::new(0.1, 5.0, 0.0), -Vec3::unit_y()), Interval::FORWARD).unwrap();
+        assert!((hit.point.y - 0.1875).abs() < 1e-6); // The first segment is as thick as the wire at its middle
+        assert!((hit.normal - Vec3::unit_y()).length() < 1e-6);
+        assert!(wire.intersect(&Ray::new(Vec3::new(3.9, 5.0, 0.0), -Vec3::unit_y()), Interval::new(0.0, 4.9)).is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::math::{Vec3, Ray};
+use crate::math::{consts::PI, Float, Vec3, Ray, Interval, Aabb};
 use crate::material::Material;
 use super::{HitInfo, Intersectable, Transform};
 
@@ -6,15 +6,15 @@ use super::{HitInfo, Intersectable, Transform};
 #[derive(Debug, Clone)]
 pub struct Cylinder {
     pub center: Vec3,     // Center of the cylinder
-    pub radius: f64,      // Radius
-    pub height: f64,      // Height along Y axis
+    pub radius: Float,      // Radius
+    pub height: Float,      // Height along Y axis
     pub material: Material,
     pub transform: Transform,
 }
 
 impl Cylinder {
     /// Create a new cylinder
-    pub fn new(center: Vec3, radius: f64, height: f64, material: Material) -> Self {
+    pub fn new(center: Vec3, radius: Float, height: Float, material: Material) -> Self {
         Self {
             center,
             radius,
@@ -26,7 +26,7 @@ impl Cylinder {
 }
 
 impl Intersectable for Cylinder {
-    fn intersect(&self, ray: &Ray) -> Option<HitInfo> {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
         // Cylinder intersection (infinite cylinder + caps)
         // Cylinder equation: (x - cx)² + (z - cz)² = r²
         // Ray: P(t) = origin + t * direction
@@ -53,19 +53,19 @@ impl Intersectable for Cylinder {
         
         // Check both intersections with cylinder walls
         for &t in &[t1, t2] {
-            if t > 1e-4 {
+            if range.surrounds(t) {
                 let hit_point = ray.at(t);
                 let y = hit_point.y;
                 
                 // Check if hit is within cylinder height
-                if y >= self.center.y - self.height / 2.0 && y <= self.center.y + self.height / 2.0 {
-                    if closest_t.is_none() || t < closest_t.unwrap() {
-                        closest_t = Some(t);
-                        // Normal for cylinder wall
-                        let normal_x = (hit_point.x - self.center.x) / self.radius;
-                        let normal_z = (hit_point.z - self.center.z) / self.radius;
-                        closest_normal = Vec3::new(normal_x, 0.0, normal_z).normalize();
-                    }
+                if y >= self.center.y - self.height / 2.0 && y <= self.center.y + self.height / 2.0
+                    && (closest_t.is_none() || t < closest_t.unwrap())
+                {
+                    closest_t = Some(t);
+                    // Normal for cylinder wall
+                    let normal_x = (hit_point.x - self.center.x) / self.radius;
+                    let normal_z = (hit_point.z - self.center.z) / self.radius;
+                    closest_normal = Vec3::new(normal_x, 0.0, normal_z).normalize();
                 }
             }
         }
@@ -77,38 +77,45 @@ impl Intersectable for Cylinder {
         for &cap_y in &[cap_y_top, cap_y_bottom] {
             if ray.direction.y.abs() > 1e-6 {
                 let t = (cap_y - ray.origin.y) / ray.direction.y;
-                if t > 1e-4 {
+                if range.surrounds(t) {
                     let hit_point = ray.at(t);
                     let dx = hit_point.x - self.center.x;
                     let dz = hit_point.z - self.center.z;
                     
                     // Check if hit is within cap radius
-                    if dx * dx + dz * dz <= self.radius * self.radius {
-                        if closest_t.is_none() || t < closest_t.unwrap() {
-                            closest_t = Some(t);
-                            // Normal for cap
-                            closest_normal = if cap_y == cap_y_top {
-                                Vec3::unit_y()
-                            } else {
-                                -Vec3::unit_y()
-                            };
-                        }
+                    if dx * dx + dz * dz <= self.radius * self.radius
+                        && (closest_t.is_none() || t < closest_t.unwrap())
+                    {
+                        closest_t = Some(t);
+                        // Normal for cap
+                        closest_normal = if cap_y == cap_y_top {
+                            Vec3::unit_y()
+                        } else {
+                            -Vec3::unit_y()
+                        };
                     }
                 }
             }
         }
         
-        if let Some(t) = closest_t {
-            let hit_point = ray.at(t);
-            Some(HitInfo {
-                t,
-                point: hit_point,
-                normal: closest_normal,
-                material: self.material.clone(),
-            })
+        let t = closest_t?;
+        let offset = ray.at(t) - self.center;
+        let (uv, uv_scale, axes) = if closest_normal.y == 0.0 {
+            // Around the wall and up it
+            let u = (-offset.z).atan2(offset.x) / (2.0 * PI) + 0.5;
+            let around = Vec3::new(offset.z, 0.0, -offset.x).normalize();
+            ((u, offset.y / self.height + 0.5), (2.0 * PI * self.radius * self.height).sqrt(), (around, Vec3::unit_y()))
         } else {
-            None
-        }
+            // Caps are mapped like the square around them, seen from above
+            let diameter = 2.0 * self.radius;
+            ((offset.x / diameter + 0.5, 0.5 - offset.z / diameter), diameter, (Vec3::unit_x(), -Vec3::unit_z()))
+        };
+        Some(HitInfo::new(ray, t, closest_normal, self.material.clone()).with_uv(uv, uv_scale, axes))
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, self.height / 2.0, self.radius);
+        Some(Aabb::new(self.center - extent, self.center + extent))
     }
 }
 
@@ -127,7 +134,7 @@ mod tests {
         
         // Ray pointing at cylinder center
         let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
-        let hit = cylinder.intersect(&ray);
+        let hit = cylinder.intersect(&ray, Interval::FORWARD);
         
         assert!(hit.is_some());
         let hit = hit.unwrap();
@@ -0,0 +1,59 @@
+use std::collections::HashSet;
+
+use crate::math::{Ray, Interval, Aabb};
+use crate::scene::{Accelerator, AcceleratorOptions};
+use super::{HitInfo, Intersectable};
+
+/// Shapes intersected together through their own accelerator (a BVH by default), such as the triangles of a mesh
+/// Wrap a group in an `Arc` to place it many times with `Instance` without copying its shapes
+pub struct Group {
+    shapes: Vec<Box<dyn Intersectable>>,
+    index: Box<dyn Accelerator>,
+}
+
+impl Group {
+    /// Create a group, building a BVH over its shapes
+    pub fn new(shapes: Vec<Box<dyn Intersectable>>) -> Self {
+        Self::with_options(shapes, &AcceleratorOptions::default())
+    }
+    
+    /// Create a group, building the given kind of accelerator over its shapes
+    pub fn with_options(shapes: Vec<Box<dyn Intersectable>>, options: &AcceleratorOptions) -> Self {
+        let index = options.build(&shapes.iter().map(|shape| shape.bounds()).collect::<Vec<_>>());
+        Self { shapes, index }
+    }
+    
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+}
+
+impl Intersectable for Group {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
+        let mut closest = None;
+        self.index.traverse(ray, range.max, &mut |index, t_max| {
+            let hit = self.shapes[index].intersect(ray, Interval::new(range.min, t_max))?;
+            let t = hit.t;
+            closest = Some(HitInfo { primitive: hit.primitive * self.shapes.len() + index, ..hit }); // Unique across the shapes
+            Some(t)
+        });
+        closest
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        self.index.bounds()
+    }
+    
+    fn triangle_count(&self) -> usize {
+        self.shapes.iter().map(|shape| shape.triangle_count()).sum()
+    }
+    
+    fn memory(&self, shared: &mut HashSet<usize>) -> usize {
+        let shapes: usize = self.shapes.iter().map(|shape| shape.memory(shared)).sum();
+        size_of_val(self) + size_of_val(self.shapes.as_slice()) + shapes + self.index.memory()
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashSet;
+
+use crate::math::{Float, Vec3, Ray, Interval, Aabb};
+use crate::material::Material;
+use super::triangle::intersect_triangle;
+use super::{HitInfo, Intersectable};
+
+/// Terrain surface over a grid of heights, filling the box from `min` to `max`: a height of 0 lies on the
+/// bottom of the box and 1 on its top, with the grid's first row along its -z side and first column along -x
+/// Each grid cell is two triangles, smoothly shaded; rays find the cells they pass over through a quadtree
+/// of the lowest and highest point under each node
+#[derive(Debug, Clone)]
+pub struct Heightfield {
+    pub min: Vec3,
+    pub max: Vec3,
+    pub material: Material,
+    columns: usize,
+    rows: usize,
+    heights: Vec<Float>,              // World-space height of each grid point, row-major
+    normals: Vec<Vec3>,               // Unit normal at each grid point, for smooth shading
+    levels: Vec<Vec<(Float, Float)>>, // Lowest and highest height over each cell, then each 2 × 2 block of the level before
+}
+
+impl Heightfield {
+    /// Terrain from `columns` × `rows` heights between 0 and 1, row-major
+    pub fn new(columns: usize, rows: usize, heights: Vec<Float>, min: Vec3, max: Vec3, material: Material) -> Self {
+        assert!(columns >= 2 && rows >= 2, "height fields need at least 2 × 2 heights");
+        assert_eq!(heights.len(), columns * rows);
+        let heights: Vec<Float> = heights.into_iter().map(|height| min.y + height * (max.y - min.y)).collect();
+        
+        // Slopes by central differences (one-sided along the edges)
+        let spacing = ((max.x - min.x) / (columns - 1) as Float, (max.z - min.z) / (rows - 1) as Float);
+        let height = |column: usize, row: usize| heights[row * columns + column];
+        let normals = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let (left, right) = (column.saturating_sub(1), (column + 1).min(columns - 1));
+                let (back, front) = (row.saturating_sub(1), (row + 1).min(rows - 1));
+                let dx = (height(right, row) - height(left, row)) / ((right - left) as Float * spacing.0);
+                let dz = (height(column, front) - height(column, back)) / ((front - back) as Float * spacing.1);
+                Vec3::new(-dx, 1.0, -dz).normalize()
+            })
+            .collect();
+        
+        let cells: Vec<(Float, Float)> = (0..rows - 1)
+            .flat_map(|row| (0..columns - 1).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let corners = [height(column, row), height(column + 1, row), height(column, row + 1), height(column + 1, row + 1)];
+                corners.iter().fold((Float::INFINITY, Float::NEG_INFINITY), |(low, high), &h| (low.min(h), high.max(h)))
+            })
+            .collect();
+        let mut levels = vec![cells];
+        let mut size = (columns - 1, rows - 1);
+        while size != (1, 1) {
+            let next = (size.0.div_ceil(2), size.1.div_ceil(2));
+            let below = &levels[levels.len() - 1];
+            let level = (0..next.1)
+                .flat_map(|z| (0..next.0).map(move |x| (x, z)))
+                .map(|(x, z)| {
+                    let children = [(2 * x, 2 * z), (2 * x + 1, 2 * z), (2 * x, 2 * z + 1), (2 * x + 1, 2 * z + 1)];
+                    children
+                        .into_iter()
+                        .filter(|&(cx, cz)| cx < size.0 && cz < size.1)
+                        .map(|(cx, cz)| below[cz * size.0 + cx])
+                        .fold((Float::INFINITY, Float::NEG_INFINITY), |(low, high), (l, h)| (low.min(l), high.max(h)))
+                })
+                .collect();
+            levels.push(level);
+            size = next;
+        }
+        
+        Self { min, max, material, columns, rows, heights, normals, levels }
+    }
+    
+    /// Terrain sampling `height(x, z)` (both from 0 to 1 across the box, the result too) on a
+    /// `columns` × `rows` grid, for noise and other procedural landscapes
+    pub fn from_fn(columns: usize, rows: usize, height: impl Fn(Float, Float) -> Float, min: Vec3, max: Vec3, material: Material) -> Self {
+        let heights = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| height(column as Float / (columns - 1) as Float, row as Float / (rows - 1) as Float))
+            .collect();
+        Self::new(columns, rows, heights, min, max, material)
+    }
+    
+    /// Cells across x and z at a level of the quadtree (the cells themselves at level 0)
+    fn level_size(&self, level: usize) -> (usize, usize) {
+        ((self.columns - 1).div_ceil(1 << level), (self.rows - 1).div_ceil(1 << level))
+    }
+    
+    /// World-space position of a grid point
+    fn point(&self, column: usize, row: usize) -> Vec3 {
+        let x = self.min.x + (self.max.x - self.min.x) * column as Float / (self.columns - 1) as Float;
+        let z = self.min.z + (self.max.z - self.min.z) * row as Float / (self.rows - 1) as Float;
+        Vec3::new(x, self.heights[row * self.columns + column], z)
+    }
+    
+    /// Look for the closest hit within `range` under quadtree node (x, z) of `level`, narrowing `range` to it
+    fn visit(&self, level: usize, (x, z): (usize, usize), ray: &Ray, inverse_direction: Vec3, range: &mut Interval, closest: &mut Option<(Float, Vec3)>) {
+        let size = self.level_size(level);
+        let (low, high) = self.levels[level][z * size.0 + x];
+        let (cells_x, cells_z) = ((self.columns - 1) as Float, (self.rows - 1) as Float);
+        let span = (1 << level) as Float;
+        let corner = |x: Float, z: Float, y: Float| {
+            let x = self.min.x + (self.max.x - self.min.x) * (x * span).min(cells_x) / cells_x;
+            let z = self.min.z + (self.max.z - self.min.z) * (z * span).min(cells_z) / cells_z;
+            Vec3::new(x, y, z)
+        };
+        let bounds = Aabb::new(corner(x as Float, z as Float, low), corner((x + 1) as Float, (z + 1) as Float, high));
+        if bounds.hit_range(ray, inverse_direction, *range).is_none() {
+            return;
+        }
+        
+        if level == 0 {
+            let corners = [(x, z), (x + 1, z), (x, z + 1), (x + 1, z + 1)].map(|(column, row)| row * self.columns + column);
+            for triangle in [[corners[0], corners[3], corners[1]], [corners[0], corners[2], corners[3]]] {
+                let [a, b, c] = triangle.map(|index| self.point(index % self.columns, index / self.columns));
+                if let Some((t, u, v)) = intersect_triangle(ray, a, b, c, *range) {
+                    let [na, nb, nc] = triangle.map(|index| self.normals[index]);
+                    *closest = Some((t, (na * (1.0 - u - v) + nb * u + nc * v).normalize()));
+                    range.max = t;
+                }
+            }
+            return;
+        }
+        
+        // Children nearer the ray's origin first, so hits there cut the search short
+        let below = self.level_size(level - 1);
+        let xs = if ray.direction.x >= 0.0 { [2 * x, 2 * x + 1] } else { [2 * x + 1, 2 * x] };
+        let zs = if ray.direction.z >= 0.0 { [2 * z, 2 * z + 1] } else { [2 * z + 1, 2 * z] };
+        for child_z in zs.into_iter().filter(|&child_z| child_z < below.1) {
+            for child_x in xs.into_iter().filter(|&child_x| child_x < below.0) {
+                self.visit(level - 1, (child_x, child_z), ray, inverse_direction, range, closest);
+            }
+        }
+    }
+}
+
+impl Intersectable for Heightfield {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
+        let inverse_direction = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+        let mut range = range;
+        let mut closest = None;
+        self.visit(self.levels.len() - 1, (0, 0), ray, inverse_direction, &mut range, &mut closest);
+        let (t, normal) = closest?;
+        
+        // The image seen from above, -z up, like a plane facing up
+        let size = self.max - self.min;
+        let point = ray.at(t);
+        let uv = ((point.x - self.min.x) / size.x, (self.max.z - point.z) / size.z);
+        Some(HitInfo::new(ray, t, normal, self.material.clone()).with_uv(uv, (size.x * size.z).sqrt(), (Vec3::unit_x(), -Vec3::unit_z())))
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        let (low, high) = self.levels[self.levels.len() - 1][0];
+        Some(Aabb::new(Vec3::new(self.min.x, low, self.min.z), Vec3::new(self.max.x, high, self.max.z)))
+    }
+    
+    fn triangle_count(&self) -> usize {
+        2 * (self.columns - 1) * (self.rows - 1)
+    }
+    
+    fn memory(&self, _shared: &mut HashSet<usize>) -> usize {
+        let levels: usize = self.levels.iter().map(|level| size_of_val(level.as_slice())).sum();
+        size_of_val(self) + size_of_val(self.heights.as_slice()) + size_of_val(self.normals.as_slice()) + levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_heightfield_intersection() {
+        // A ramp rising along x from 0 to 1 over a 2 × 2 box
+        let ramp = Heightfield::from_fn(5, 3, |x, _| x, Vec3::new(-1.0, 0.0, -1.0), Vec3::new(1.0, 1.0, 1.0), Material::gray());
+        assert_eq!(ramp.levels.len(), 3);
+        let hit = ramp.intersect(&Ray::new(Vec3::new(0.5, 5.0, 0.3), -Vec3::unit_y()), Interval::FORWARD).unwrap();
+        assert!((hit.point.y - 0.75).abs() < 1e-5);
+        assert!((hit.normal - Vec3::new(-0.5, 1.0, 0.0).normalize()).length() < 1e-5);
+        
+        // Skimming over the low end, then running into the slope
+        let hit = ramp.intersect(&Ray::new(Vec3::new(-3.0, 0.5, 0.9), Vec3::unit_x()), Interval::FORWARD).unwrap();
+        assert!((hit.point.x - 0.0).abs() < 1e-5);
+        assert!(ramp.intersect(&Ray::new(Vec3::new(-3.0, 1.5, 0.0), Vec3::unit_x()), Interval::FORWARD).is_none());
+    }
+}
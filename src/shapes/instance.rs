@@ -0,0 +1,165 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::math::{Ray, Interval, Aabb};
+use crate::material::Material;
+use super::{HitInfo, Intersectable, Transform};
+
+/// Shared geometry placed in the scene with its own transform
+/// Many instances can reference one mesh (a forest of the same tree) while it is stored only once, each
+/// drawing it in materials of its own: all in one, some replaced by name (a car's paint), or tinted
+pub struct Instance {
+    pub geometry: Arc<dyn Intersectable>,
+    pub transform: Transform,
+    pub material: Option<Material>,         // Replaces the geometry's own materials when set
+    pub overrides: Vec<(String, Material)>, // Replace the geometry's materials of those names
+    pub tint: Option<Color>,                // Multiplies the albedo of whatever material is drawn
+}
+
+impl Instance {
+    /// Place shared geometry with a transform, keeping its materials
+    pub fn new(geometry: Arc<dyn Intersectable>, transform: Transform) -> Self {
+        Self { geometry, transform, material: None, overrides: Vec::new(), tint: None }
+    }
+    
+    /// Place shared geometry with a transform, drawing all of it in one material
+    pub fn with_material(geometry: Arc<dyn Intersectable>, transform: Transform, material: Material) -> Self {
+        Self { geometry, transform, material: Some(material), overrides: Vec::new(), tint: None }
+    }
+    
+    /// The same instance drawing the geometry's materials of the given names in others instead
+    pub fn with_overrides(self, overrides: Vec<(String, Material)>) -> Self {
+        Self { overrides, ..self }
+    }
+    
+    /// The same instance with the albedo of its materials multiplied by `tint`
+    pub fn with_tint(self, tint: Color) -> Self {
+        Self { tint: Some(tint), ..self }
+    }
+    
+    /// The material to draw a hit on the geometry in
+    fn material(&self, hit: Material) -> Material {
+        let mut material = match &self.material {
+            Some(material) => material.clone(),
+            None => match self.overrides.iter().find(|(name, _)| hit.name.as_deref() == Some(name.as_str())) {
+                Some((_, material)) => material.clone(),
+                None => hit,
+            },
+        };
+        if let Some(tint) = self.tint {
+            material.albedo = material.albedo * tint;
+        }
+        material
+    }
+}
+
+impl Intersectable for Instance {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
+        // The object-space ray keeps the same parameterization, so t carries over unchanged,
+        // and transforming the normal along with the ray leaves it facing the ray
+        let hit = self.geometry.intersect(&self.transform.inverse_transform_ray(ray), range)?;
+        Some(HitInfo {
+            t: hit.t,
+            point: ray.at(hit.t),
+            normal: self.transform.apply_to_normal(hit.normal),
+            geometric_normal: self.transform.apply_to_normal(hit.geometric_normal),
+            front_face: hit.front_face,
+            material: self.material(hit.material),
+            object: hit.object,
+            primitive: hit.primitive,
+            uv: hit.uv,
+            uv_scale: hit.uv_scale * self.transform.mean_scale(),
+            tangent: self.transform.apply_to_vector(hit.tangent).normalize(),
+            bitangent: self.transform.apply_to_vector(hit.bitangent).normalize(),
+        })
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        self.geometry.bounds().map(|bounds| bounds.transformed(&self.transform.matrix()))
+    }
+    
+    fn triangle_count(&self) -> usize {
+        self.geometry.triangle_count()
+    }
+    
+    fn memory(&self, shared: &mut HashSet<usize>) -> usize {
+        let geometry = if shared.insert(Arc::as_ptr(&self.geometry) as *const () as usize) { self.geometry.memory(shared) } else { 0 };
+        size_of_val(self) + size_of_val(self.overrides.as_slice()) + geometry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{consts::PI, Quaternion, Vec3};
+    use crate::shapes::{Cube, Group, Mesh, Sphere};
+    
+    #[test]
+    fn test_instance_matches_placed_geometry() {
+        let geometry: Arc<dyn Intersectable> = Arc::new(Group::new(vec![Box::new(Sphere::new(Vec3::zero(), 1.0, Material::red()))]));
+        let transform = Transform {
+            translation: Vec3::new(0.0, 0.0, -5.0),
+            rotation: Quaternion::identity(),
+            scale: Vec3::new(2.0, 2.0, 2.0),
+        };
+        let instance = Instance::with_material(geometry.clone(), transform, Material::blue());
+        let direct = Sphere::new(Vec3::new(0.0, 0.0, -5.0), 2.0, Material::red());
+        
+        let ray = Ray::new(Vec3::new(0.5, 0.3, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let (hit, expected) = (instance.intersect(&ray, Interval::FORWARD).unwrap(), direct.intersect(&ray, Interval::FORWARD).unwrap());
+        assert!((hit.t - expected.t).abs() < 1e-4);
+        assert!((hit.point - expected.point).length() < 1e-4);
+        assert!((hit.normal - expected.normal).length() < 1e-4);
+        assert_eq!(hit.material.albedo, Material::blue().albedo);
+        assert_eq!(instance.bounds(), direct.bounds());
+        
+        // Without an override the geometry keeps its own material
+        let plain = Instance::new(geometry, Transform::with_translation(Vec3::new(0.0, 0.0, -5.0)));
+        assert_eq!(plain.intersect(&ray, Interval::FORWARD).unwrap().material.albedo, Material::red().albedo);
+        
+        // Overrides replace only the materials of their names, and a tint colors whatever is drawn
+        let paint = Material { name: Some("paint".into()), ..Material::red() };
+        let parts: Vec<Box<dyn Intersectable>> = vec![Box::new(Sphere::new(Vec3::zero(), 1.0, paint)), Box::new(Sphere::new(Vec3::new(3.0, 0.0, 0.0), 1.0, Material::gray()))];
+        let tint = Color::new(1.0, 0.5, 1.0);
+        let car = Instance::new(Arc::new(Group::new(parts)), Transform::with_translation(Vec3::new(0.0, 0.0, -5.0)))
+            .with_overrides(vec![("paint".to_string(), Material::blue())])
+            .with_tint(tint);
+        assert_eq!(car.intersect(&ray, Interval::FORWARD).unwrap().material.albedo, Material::blue().albedo * tint);
+        let wheel = car.intersect(&Ray::new(Vec3::new(3.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)), Interval::FORWARD).unwrap();
+        assert_eq!(wheel.material.albedo, Material::gray().albedo * tint);
+    }
+    
+    #[test]
+    fn test_rotated_instance_normal() {
+        // A box rotated 90° about y: its -x face (at x = -1) now faces +z, at z = 1 before translation
+        let geometry: Arc<dyn Intersectable> = Arc::new(Cube::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(2.0, 1.0, 1.0), Material::gray()));
+        let transform = Transform {
+            translation: Vec3::new(0.0, 0.0, -10.0),
+            rotation: Quaternion::from_axis_angle(Vec3::unit_y(), PI / 2.0),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        };
+        let instance = Instance::new(geometry, transform.clone());
+        
+        let hit = instance.intersect(&Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0)), Interval::FORWARD).unwrap();
+        assert!((hit.t - 9.0).abs() < 1e-4, "{}", hit.t);
+        assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-4, "{:?}", hit.normal);
+        
+        let corner = Vec3::new(2.0, 1.0, 1.0);
+        assert!((transform.matrix().transform_point(corner) - transform.apply_to_point(corner)).length() < 1e-4);
+    }
+    
+    #[test]
+    fn test_shared_geometry_counted_once() {
+        // Each placement draws the mesh's triangles, but only the first pays for storing them
+        let positions = vec![Vec3::zero(), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)];
+        let mesh: Arc<dyn Intersectable> = Arc::new(Mesh::new(positions, vec![[0, 1, 2]], Material::gray()));
+        let place = |x| Instance::new(mesh.clone(), Transform::with_translation(Vec3::new(x, 0.0, 0.0)));
+        let (first, second) = (place(0.0), place(2.0));
+        assert_eq!(first.triangle_count() + second.triangle_count(), 2);
+        
+        let mut shared = HashSet::new();
+        let (first, second) = (first.memory(&mut shared), second.memory(&mut shared));
+        assert_eq!(first, second + mesh.memory(&mut HashSet::new()));
+    }
+}
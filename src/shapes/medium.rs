@@ -0,0 +1,131 @@
+use crate::math::{Float, Vec3, Ray, Interval};
+use super::Intersectable;
+
+/// Phase function describing how light scatters inside a medium
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PhaseFunction {
+    /// Scatters equally in all directions
+    Isotropic,
+    /// Henyey-Greenstein lobe with asymmetry g in (-1, 1); positive g favors forward scattering
+    HenyeyGreenstein(Float),
+}
+
+impl PhaseFunction {
+    /// Evaluate for the cosine of the angle between the light's travel direction and the scattered direction
+    /// Normalized so isotropic scattering evaluates to 1, matching the unnormalized Lambert term used for surfaces
+    pub fn evaluate(&self, cos_theta: Float) -> Float {
+        match *self {
+            PhaseFunction::Isotropic => 1.0,
+            PhaseFunction::HenyeyGreenstein(g) => {
+                let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+                (1.0 - g * g) / (denom * denom.sqrt())
+            }
+        }
+    }
+}
+
+/// Constant-density participating medium (smoke, mist, fog) filling a boundary shape
+pub struct ConstantMedium {
+    pub boundary: Box<dyn Intersectable>, // Closed shape enclosing the medium
+    pub density: Float,                     // Extinction coefficient per world unit
+    pub albedo: Vec3,                     // Fraction of extinguished light that is scattered
+    pub phase: PhaseFunction,
+}
+
+impl ConstantMedium {
+    /// Create an isotropically scattering medium
+    pub fn new(boundary: Box<dyn Intersectable>, density: Float, albedo: Vec3) -> Self {
+        Self::with_phase(boundary, density, albedo, PhaseFunction::Isotropic)
+    }
+    
+    /// Create a medium with a specific phase function
+    pub fn with_phase(boundary: Box<dyn Intersectable>, density: Float, albedo: Vec3, phase: PhaseFunction) -> Self {
+        Self {
+            boundary,
+            density,
+            albedo,
+            phase,
+        }
+    }
+    
+    /// Portion of the ray inside the boundary, clipped to [0, t_max]
+    /// Returns the (t_enter, t_exit) ray parameters, or None if the ray never passes through the medium
+    pub fn segment(&self, ray: &Ray, t_max: Float) -> Option<(Float, Float)> {
+        let first = self.boundary.intersect(ray, Interval::FORWARD)?;
+        
+        let (t_enter, t_exit) = if !first.front_face {
+            // Leaving through the first hit, so the ray started inside
+            (0.0, first.t)
+        } else {
+            // Entering at the first hit, find where the ray leaves again
+            let inner_ray = Ray::new(first.offset_origin(ray.direction), ray.direction);
+            let exit = self.boundary.intersect(&inner_ray, Interval::FORWARD)?;
+            (first.t, (exit.point - ray.origin).dot(&ray.direction) / ray.direction.length_squared())
+        };
+        
+        let t_exit = t_exit.min(t_max);
+        if t_enter < t_exit {
+            Some((t_enter, t_exit))
+        } else {
+            None
+        }
+    }
+    
+    /// Fraction of light surviving along the ray from its origin up to t_max (Beer-Lambert)
+    pub fn transmittance(&self, ray: &Ray, t_max: Float) -> Float {
+        match self.segment(ray, t_max) {
+            Some((t_enter, t_exit)) => {
+                let distance = (t_exit - t_enter) * ray.direction.length();
+                (-self.density * distance).exp()
+            }
+            None => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::shapes::Sphere;
+    
+    fn fog_ball() -> ConstantMedium {
+        ConstantMedium::new(
+            Box::new(Sphere::new(Vec3::new(0.0, 0.0, -3.0), 1.0, Material::white())),
+            0.5,
+            Vec3::new(1.0, 1.0, 1.0),
+        )
+    }
+    
+    #[test]
+    fn test_medium_segment() {
+        let medium = fog_ball();
+        
+        // Ray from outside passes through the full diameter
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let (t_enter, t_exit) = medium.segment(&ray, Float::INFINITY).unwrap();
+        assert!((t_enter - 2.0).abs() < 1e-10);
+        assert!((t_exit - 4.0).abs() < 1e-10);
+        assert!((medium.transmittance(&ray, Float::INFINITY) - (-1.0 as Float).exp()).abs() < 1e-10);
+        
+        // Ray starting at the center only travels the radius
+        let inside = Ray::new(Vec3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, -1.0));
+        let (t_enter, t_exit) = medium.segment(&inside, Float::INFINITY).unwrap();
+        assert_eq!(t_enter, 0.0);
+        assert!((t_exit - 1.0).abs() < 1e-10);
+        
+        // Segment is clipped by a closer surface
+        let (_, t_exit) = medium.segment(&ray, 3.0).unwrap();
+        assert_eq!(t_exit, 3.0);
+        assert!(medium.segment(&ray, 1.5).is_none());
+    }
+    
+    #[test]
+    fn test_phase_functions() {
+        assert_eq!(PhaseFunction::Isotropic.evaluate(0.3), 1.0);
+        assert!((PhaseFunction::HenyeyGreenstein(0.0).evaluate(-0.7) - 1.0).abs() < 1e-10);
+        
+        let forward = PhaseFunction::HenyeyGreenstein(0.6);
+        assert!(forward.evaluate(1.0) > forward.evaluate(-1.0));
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bvh::Bvh;
+use crate::color::Color;
+use crate::math::{Float, Vec3, Ray, Interval, Aabb};
+use crate::material::Material;
+use super::triangle::intersect_triangle;
+use super::{HitInfo, Intersectable};
+
+/// How a mesh's normals vary across its faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Shading {
+    Flat,   // Each face by its own plane, showing the facets
+    Smooth, // Vertex normals interpolated across the faces, hiding the facets of curved surfaces
+}
+
+/// Triangle mesh sharing its vertices between triangles, such as a model loaded from a file, with a BVH
+/// over its triangles; with vertex normals it is shaded smoothly, and flat from its faces without them
+/// Texture coordinates at its vertices are interpolated too; without them each triangle gets its barycentrics
+/// Colors at its vertices, as scans and painted models carry, are interpolated and multiply the material's albedo
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub material: Material,
+    positions: Vec<Vec3>,
+    normals: Option<Vec<Vec3>>, // Unit normal at each vertex, interpolated across the triangles
+    uvs: Option<Vec<(Float, Float)>>, // Texture coordinates at each vertex
+    colors: Option<Vec<Color>>, // Color at each vertex, tinting the albedo
+    triangles: Vec<[usize; 3]>, // Vertex indices of each triangle, counter-clockwise seen from the front
+    bvh: Bvh,
+}
+
+impl Mesh {
+    /// Create a mesh from shared vertex positions and triangles indexing them
+    pub fn new(positions: Vec<Vec3>, triangles: Vec<[usize; 3]>, material: Material) -> Self {
+        assert!(triangles.iter().flatten().all(|&index| index < positions.len()), "triangles refer past the mesh's vertices");
+        let bounds: Vec<Option<Aabb>> = triangles
+            .iter()
+            .map(|&[a, b, c]| Some(Aabb::new(positions[a], positions[a]).grow(positions[b]).grow(positions[c])))
+            .collect();
+        let bvh = Bvh::new(&bounds);
+        Self { material, positions, normals: None, uvs: None, colors: None, triangles, bvh }
+    }
+    
+    /// The same mesh shaded smoothly with a normal at each vertex (normalized here)
+    pub fn with_normals(self, normals: Vec<Vec3>) -> Self {
+        assert_eq!(normals.len(), self.positions.len(), "meshes need a normal per vertex");
+        Self { normals: Some(normals.into_iter().map(|normal| normal.normalize()).collect()), ..self }
+    }
+    
+    /// The same mesh shaded smoothly with normals computed from its faces: each vertex's is the average of the
+    /// faces around it, weighted by their areas so slivers don't pull it askew
+    pub fn with_smooth_normals(self) -> Self {
+        let mut normals = vec![Vec3::zero(); self.positions.len()];
+        for &[a, b, c] in &self.triangles {
+            let area_normal = (self.positions[b] - self.positions[a]).cross(&(self.positions[c] - self.positions[a]));
+            for vertex in [a, b, c] {
+                normals[vertex] += area_normal;
+            }
+        }
+        // Faces around a vertex can cancel out (a sheet folded flat onto itself); any direction does there
+        let normals = normals.into_iter().map(|normal| if normal.near_zero() { Vec3::unit_y() } else { normal }).collect();
+        self.with_normals(normals)
+    }
+    
+    /// The same mesh shaded flat, by its faces, dropping any vertex normals
+    pub fn flat(self) -> Self {
+        Self { normals: None, ..self }
+    }
+    
+    /// The same mesh with texture coordinates at each vertex
+    pub fn with_uvs(self, uvs: Vec<(Float, Float)>) -> Self {
+        assert_eq!(uvs.len(), self.positions.len(), "meshes need texture coordinates per vertex");
+        Self { uvs: Some(uvs), ..self }
+    }
+    
+    /// The same mesh with a color at each vertex, by which the material's albedo is multiplied
+    pub fn with_colors(self, colors: Vec<Color>) -> Self {
+        assert_eq!(colors.len(), self.positions.len(), "meshes need a color per vertex");
+        Self { colors: Some(colors), ..self }
+    }
+    
+    /// Number of triangles
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+}
+
+/// One level of Loop subdivision: every triangle split into four, and every vertex moved toward a weighted
+/// average of its neighbors, so repeated levels approach a smooth surface through the original's shape
+/// Open edges (and those shared by more than two triangles) stay creases, subdivided as curves of their own;
+/// other per-vertex values, such as colors, are subdivided along by passing them as `vertices` with the same triangles
+pub fn subdivide(vertices: &[Vec3], triangles: &[[usize; 3]]) -> (Vec<Vec3>, Vec<[usize; 3]>) {
+    // The corners opposite each edge, keyed by its ends in order
+    let mut opposite: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for &[a, b, c] in triangles {
+        for (start, end, across) in [(a, b, c), (b, c, a), (c, a, b)] {
+            opposite.entry((start.min(end), start.max(end))).or_default().push(across);
+        }
+    }
+    
+    // Each vertex's neighbors, and those along creases
+    let mut neighbors = vec![Vec::new(); vertices.len()];
+    let mut crease_neighbors = vec![Vec::new(); vertices.len()];
+    for (&(a, b), across) in &opposite {
+        neighbors[a].push(b);
+        neighbors[b].push(a);
+        if across.len() != 2 {
+            crease_neighbors[a].push(b);
+            crease_neighbors[b].push(a);
+        }
+    }
+    let mut new_vertices: Vec<Vec3> = (0..vertices.len())
+        .map(|index| {
+            let vertex = vertices[index];
+            match crease_neighbors[index][..] {
+                [] => {
+                    // Warren's weights: 3/16 for three neighbors, 3/(8n) for more
+                    let n = neighbors[index].len();
+                    let beta = if n > 3 { 3.0 / (8.0 * n as Float) } else { 3.0 / 16.0 };
+                    let sum = neighbors[index].iter().fold(Vec3::zero(), |sum, &neighbor| sum + vertices[neighbor]);
+                    vertex * (1.0 - n as Float * beta) + sum * beta
+                }
+                [a, b] => vertex * 0.75 + (vertices[a] + vertices[b]) * 0.125,
+                _ => vertex, // Where creases end or meet, a corner stays put
+            }
+        })
+        .collect();
+    
+    // A new vertex on each edge, in a fixed order so every attribute passed gets the same indices
+    let mut edges: Vec<(usize, usize)> = opposite.keys().copied().collect();
+    edges.sort_unstable();
+    let mut edge_vertex = HashMap::with_capacity(edges.len());
+    for (a, b) in edges {
+        edge_vertex.insert((a, b), new_vertices.len());
+        new_vertices.push(match opposite[&(a, b)][..] {
+            [c, d] => (vertices[a] + vertices[b]) * 0.375 + (vertices[c] + vertices[d]) * 0.125,
+            _ => (vertices[a] + vertices[b]) * 0.5,
+        });
+    }
+    let middle = |a: usize, b: usize| edge_vertex[&(a.min(b), a.max(b))];
+    let new_triangles = triangles
+        .iter()
+        .flat_map(|&[a, b, c]| {
+            let (ab, bc, ca) = (middle(a, b), middle(b, c), middle(c, a));
+            [[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+        })
+        .collect();
+    (new_vertices, new_triangles)
+}
+
+impl Intersectable for Mesh {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
+        let mut closest = None;
+        self.bvh.traverse(ray, range.max, |index, t_max| {
+            let [a, b, c] = self.triangles[index].map(|vertex| self.positions[vertex]);
+            let (t, u, v) = intersect_triangle(ray, a, b, c, Interval::new(range.min, t_max))?;
+            closest = Some((t, u, v, index));
+            Some(t)
+        });
+        let (t, u, v, index) = closest?;
+        
+        // Which side was hit goes by the face itself; the vertex normals only bend the shading
+        let corners = self.triangles[index];
+        let [a, b, c] = corners.map(|vertex| self.positions[vertex]);
+        let (edge1, edge2) = (b - a, c - a);
+        let face_normal = edge1.cross(&edge2).normalize();
+        let mut hit = HitInfo { primitive: index, ..HitInfo::new(ray, t, face_normal, self.material.clone()) };
+        if let Some(normals) = &self.normals {
+            let [na, nb, nc] = corners.map(|vertex| normals[vertex]);
+            let smooth = (na * (1.0 - u - v) + nb * u + nc * v).normalize();
+            hit.normal = if hit.front_face { smooth } else { -smooth };
+        }
+        if let Some(colors) = &self.colors {
+            let [ca, cb, cc] = corners.map(|vertex| colors[vertex]);
+            hit.material.albedo = hit.material.albedo * (ca * (1.0 - u - v) + cb * u + cc * v);
+        }
+        
+        // The vertices' texture coordinates, with the directions they increase in across the triangle;
+        // barycentric coordinates as on a lone triangle without them, or where they collapse
+        let area = edge1.cross(&edge2).length();
+        if let Some(uvs) = &self.uvs {
+            let [ta, tb, tc] = corners.map(|vertex| uvs[vertex]);
+            let (du1, dv1, du2, dv2) = (tb.0 - ta.0, tb.1 - ta.1, tc.0 - ta.0, tc.1 - ta.1);
+            let det = du1 * dv2 - du2 * dv1;
+            if det != 0.0 {
+                let uv = (ta.0 + du1 * u + du2 * v, ta.1 + dv1 * u + dv2 * v);
+                let tangent = (edge1 * dv2 - edge2 * dv1) / det;
+                let bitangent = (edge2 * du1 - edge1 * du2) / det;
+                return Some(hit.with_uv(uv, (area / det.abs()).sqrt(), (tangent.normalize(), bitangent.normalize())));
+            }
+        }
+        Some(hit.with_uv((u, v), area.sqrt(), (edge1.normalize(), edge2.normalize())))
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        self.bvh.bounds()
+    }
+    
+    fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+    
+    fn memory(&self, _shared: &mut HashSet<usize>) -> usize {
+        let normals = self.normals.as_deref().map_or(0, size_of_val);
+        let uvs = self.uvs.as_deref().map_or(0, size_of_val);
+        let colors = self.colors.as_deref().map_or(0, size_of_val);
+        size_of_val(self) + size_of_val(self.positions.as_slice()) + normals + uvs + colors + size_of_val(self.triangles.as_slice()) + self.bvh.memory()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_mesh_intersection() {
+        // A unit square facing up, split along its diagonal, its normals tilted outward at two corners
+        let positions = vec![Vec3::zero(), Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, -1.0), Vec3::new(0.0, 0.0, -1.0)];
+        let square = Mesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], Material::gray());
+        assert_eq!(square.len(), 2);
+        let hit = square.intersect(&Ray::new(Vec3::new(0.2, 1.0, -0.7), -Vec3::unit_y()), Interval::FORWARD).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-6 && hit.normal == Vec3::unit_y() && hit.front_face);
+        
+        let normals = vec![Vec3::new(-1.0, 1.0, 0.0), Vec3::unit_y(), Vec3::unit_y(), Vec3::new(-1.0, 1.0, 0.0)];
+        let square = square.with_normals(normals);
+        let hit = square.intersect(&Ray::new(Vec3::new(0.0, 1.0, -0.5), -Vec3::unit_y()), Interval::FORWARD).unwrap();
+        assert!((hit.normal - Vec3::new(-1.0, 1.0, 0.0).normalize()).length() < 1e-6);
+        let hit = square.intersect(&Ray::new(Vec3::new(0.0, -1.0, -0.5), Vec3::unit_y()), Interval::FORWARD).unwrap();
+        assert!(!hit.front_face && (hit.normal + Vec3::new(-1.0, 1.0, 0.0).normalize()).length() < 1e-6);
+        assert!(square.intersect(&Ray::new(Vec3::new(1.5, 1.0, -0.5), -Vec3::unit_y()), Interval::FORWARD).is_none());
+        
+        // Texture coordinates running across the square, twice over along v
+        let square = square.with_uvs(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 2.0), (0.0, 2.0)]);
+        let hit = square.intersect(&Ray::new(Vec3::new(0.25, 1.0, -0.5), -Vec3::unit_y()), Interval::FORWARD).unwrap();
+        assert!((hit.uv.0 - 0.25).abs() < 1e-6 && (hit.uv.1 - 1.0).abs() < 1e-6);
+        assert!((hit.tangent - Vec3::unit_x()).length() < 1e-6 && (hit.bitangent + Vec3::unit_z()).length() < 1e-6);
+        assert!((hit.uv_scale - Float::sqrt(0.5)).abs() < 1e-6);
+        
+        // Red along the left edge fading to blue along the right tints the albedo in between
+        let (red, blue) = (Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0));
+        let square = square.with_colors(vec![red, blue, blue, red]);
+        let hit = square.intersect(&Ray::new(Vec3::new(0.25, 1.0, -0.5), -Vec3::unit_y()), Interval::FORWARD).unwrap();
+        let albedo = Material::gray().albedo * Color::new(0.75, 0.0, 0.25);
+        assert!((hit.material.albedo.r - albedo.r).abs() < 1e-6 && (hit.material.albedo.b - albedo.b).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn test_subdivide() {
+        // A lone triangle is all crease: corners pulled in along its edges, new vertices at their midpoints
+        let corners = [Vec3::zero(), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)];
+        let (vertices, triangles) = subdivide(&corners, &[[0, 1, 2]]);
+        assert_eq!((vertices.len(), triangles.len()), (6, 4));
+        assert_eq!(vertices[0], Vec3::new(0.125, 0.125, 0.0));
+        assert!(vertices[3..].contains(&Vec3::new(0.5, 0.5, 0.0)));
+        
+        // A closed tetrahedron shrinks toward its center, staying symmetric
+        let corners = [Vec3::new(1.0, 1.0, 1.0), Vec3::new(1.0, -1.0, -1.0), Vec3::new(-1.0, 1.0, -1.0), Vec3::new(-1.0, -1.0, 1.0)];
+        let faces = [[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]];
+        let (vertices, triangles) = subdivide(&corners, &faces);
+        assert_eq!((vertices.len(), triangles.len()), (10, 16));
+        assert!((vertices[0] - Vec3::new(0.25, 0.25, 0.25)).length() < 1e-6);
+        let center = vertices.iter().fold(Vec3::zero(), |sum, &vertex| sum + vertex) / 10.0;
+        assert!(center.length() < 1e-6);
+    }
+    
+    #[test]
+    fn test_smooth_normals() {
+        // A ridge of two faces at right angles, the narrower one weighing half as much on the shared edge
+        let positions = vec![Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, -1.0), Vec3::new(-2.0, -1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)];
+        let ridge = Mesh::new(positions, vec![[0, 1, 2], [0, 3, 1]], Material::gray()).with_smooth_normals();
+        let hit = ridge.intersect(&Ray::new(Vec3::new(-1e-4, 2.0, -0.5), -Vec3::unit_y()), Interval::FORWARD).unwrap();
+        let expected = Vec3::new(-2.0, 2.0, 0.0) + Vec3::new(1.0, 1.0, 0.0);
+        assert!((hit.normal - expected.normalize()).length() < 1e-3);
+        
+        // Flat again, the face's own normal
+        let hit = ridge.flat().intersect(&Ray::new(Vec3::new(0.5, 2.0, -0.25), -Vec3::unit_y()), Interval::FORWARD).unwrap();
+        assert!((hit.normal - Vec3::new(1.0, 1.0, 0.0).normalize()).length() < 1e-6);
+    }
+}
@@ -1,31 +1,163 @@
 pub mod sphere;
+pub mod sphere_list;
 pub mod plane;
 pub mod cube;
 pub mod cylinder;
+pub mod capsule;
+pub mod heightfield;
+pub mod voxels;
+pub mod curve;
+pub mod points;
+pub mod triangle;
+pub mod mesh;
+pub mod medium;
+pub mod group;
+pub mod instance;
 
-use crate::math::{Vec3, Ray};
+use std::collections::HashSet;
+
+use crate::math::{Float, Vec3, Ray, Interval, Quaternion, Mat3, Mat4, Aabb};
 use crate::material::Material;
 
 /// Hit information for ray-object intersections
 #[derive(Debug, Clone)]
 pub struct HitInfo {
-    pub t: f64,           // Ray parameter at hit point
-    pub point: Vec3,      // Hit point in world space
-    pub normal: Vec3,     // Surface normal at hit point
-    pub material: Material, // Material at hit point
+    pub t: Float,               // Ray parameter at hit point
+    pub point: Vec3,            // Hit point in world space
+    pub normal: Vec3,           // Surface normal at hit point, facing the ray
+    pub geometric_normal: Vec3, // Normal of the surface itself, facing the ray, before vertex normals or bumps bend `normal`
+    pub front_face: bool,       // Whether the ray hit the outside of the surface
+    pub material: Material,     // Material at hit point
+    pub object: usize,          // Index of the scene object hit, filled in by the scene (0 from a lone shape)
+    pub primitive: usize,       // Part of the shape hit (a mesh's triangle, a voxel, a point), which wireframes outline
+    pub uv: (Float, Float),     // Texture coordinates at hit point
+    pub uv_scale: Float,        // World-space length one unit of uv spans around the hit, for texture filtering
+    pub tangent: Vec3,          // Unit direction in which u increases along the surface (zero without texture coordinates)
+    pub bitangent: Vec3,        // Unit direction in which v increases
+}
+
+/// How far `HitInfo::offset_origin` pushes a ray's origin off a surface, relative to the size of the numbers involved:
+/// a few hundred times the rounding error of each operation, enough to cover the error of every intersection routine
+const ORIGIN_OFFSET: Float = 256.0 * Float::EPSILON;
+
+impl HitInfo {
+    /// Hit at distance t along the ray, on a surface whose outward normal is flipped to face the ray if it hit the back
+    pub fn new(ray: &Ray, t: Float, outward_normal: Vec3, material: Material) -> Self {
+        let front_face = ray.direction.dot(&outward_normal) < 0.0;
+        Self {
+            t,
+            point: ray.at(t),
+            normal: if front_face { outward_normal } else { -outward_normal },
+            geometric_normal: if front_face { outward_normal } else { -outward_normal },
+            front_face,
+            material,
+            object: 0,
+            primitive: 0,
+            uv: (0.0, 0.0),
+            uv_scale: 1.0,
+            tangent: Vec3::zero(),
+            bitangent: Vec3::zero(),
+        }
+    }
+    
+    /// The same hit with texture coordinates, one unit of which spans `uv_scale` in world space
+    /// u increases along the first of `axes` and v along the second
+    pub fn with_uv(self, uv: (Float, Float), uv_scale: Float, (tangent, bitangent): (Vec3, Vec3)) -> Self {
+        Self { uv, uv_scale, tangent, bitangent, ..self }
+    }
+    
+    /// Whether the material's cutout map removes the surface at the hit
+    pub fn is_cut_out(&self) -> bool {
+        let material = &self.material;
+        material.opacity.as_ref().is_some_and(|opacity| opacity.sample_alpha(self.uv.0, self.uv.1) < material.alpha_cutoff)
+    }
+    
+    /// Bake the material's textures into its albedo, emission and normal, filtered for a ray footprint `width`
+    /// across (in world units) that arrived along `direction`; the footprint stretches as the surface turns away
+    pub fn apply_textures(&mut self, direction: Vec3, width: Float) {
+        let cos = self.normal.dot(&direction.normalize()).abs().max(1e-6);
+        let footprint = width / (cos * self.uv_scale);
+        let (u, v) = self.uv;
+        let material = &mut self.material;
+        if let Some(texture) = material.texture.take() {
+            material.albedo = material.albedo * texture.sample_lod(u, v, texture.lod(footprint));
+        }
+        if let Some(texture) = material.emission_texture.take() {
+            material.emission = material.emission * texture.sample_lod(u, v, texture.lod(footprint));
+        }
+        if let Some(bump) = material.bump.take() {
+            // Tilt the outward normal against the slope of the height field, measured in world units
+            let outward = if self.front_face { self.normal } else { -self.normal };
+            let (du, dv) = bump.gradient(u, v, bump.lod(footprint));
+            let along = |axis: Vec3| axis - outward * outward.dot(&axis); // In the surface, in case the axis isn't
+            let slope = (along(self.tangent) * du + along(self.bitangent) * dv) * (material.bump_height / self.uv_scale);
+            let bumped = (outward - slope).normalize();
+            self.normal = if self.front_face { bumped } else { -bumped };
+        }
+    }
+    
+    /// Where a ray leaving the hit in `direction` should start: the hit point pushed off the surface along its
+    /// geometric normal, to the side `direction` points to, so rounding errors can't make it hit the same surface again
+    /// (a bent shading normal could push it back under a surface the ray grazes)
+    /// The push grows with the point's coordinates and the hit distance, as those rounding errors do; a fixed
+    /// epsilon is too small for scenes kilometers across (shadow acne) and too big for millimeter ones (light leaks)
+    pub fn offset_origin(&self, direction: Vec3) -> Vec3 {
+        let offset = self.geometric_normal * (ORIGIN_OFFSET * (self.point.abs().max_component() + self.t));
+        if self.geometric_normal.dot(&direction) < 0.0 { self.point - offset } else { self.point + offset }
+    }
 }
 
 /// Trait for objects that can be intersected by rays
-pub trait Intersectable {
-    /// Test ray intersection, return closest hit if any
-    fn intersect(&self, ray: &Ray) -> Option<HitInfo>;
+/// Shapes are shared between render threads, so they must be Send + Sync
+pub trait Intersectable: Send + Sync {
+    /// Test ray intersection, return the closest hit whose distance `range` surrounds, if any
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo>;
+    
+    /// Like `intersect`, but passing through the back of single-sided materials and the holes of cutout
+    /// maps to whatever lies beyond
+    fn intersect_sided(&self, ray: &Ray, mut range: Interval) -> Option<HitInfo> {
+        loop {
+            let hit = self.intersect(ray, range)?;
+            if (hit.front_face || hit.material.two_sided) && !hit.is_cut_out() {
+                return Some(hit);
+            }
+            range.min = hit.t;
+        }
+    }
+    
+    /// World-space box around the shape, or None if it is unbounded (infinite planes)
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
+    
+    /// Triangles the shape is made of, shared geometry counted once for every placement
+    fn triangle_count(&self) -> usize {
+        0
+    }
+    
+    /// Rough bytes the shape takes: itself and what it owns, geometry it shares counted only the first time
+    /// its address turns up in `shared`
+    fn memory(&self, _shared: &mut HashSet<usize>) -> usize {
+        size_of_val(self)
+    }
+    
+    /// Center and radius if this is an untransformed two-sided sphere without cutouts, which the scene can gather into a `SphereList`
+    fn sphere(&self) -> Option<(Vec3, Float)> {
+        None
+    }
+    
+    /// Plain sphere or box geometry that the scene can intersect four at a time
+    #[cfg(feature = "simd")]
+    fn primitive(&self) -> Option<crate::simd::Primitive> {
+        None
+    }
 }
 
 /// Transform matrix for positioning/rotating/scaling objects
 #[derive(Debug, Clone)]
 pub struct Transform {
     pub translation: Vec3,
-    pub rotation: Vec3,    // Euler angles in radians
+    pub rotation: Quaternion,
     pub scale: Vec3,
 }
 
@@ -33,7 +165,7 @@ impl Transform {
     pub fn new() -> Self {
         Self {
             translation: Vec3::zero(),
-            rotation: Vec3::zero(),
+            rotation: Quaternion::identity(),
             scale: Vec3::new(1.0, 1.0, 1.0),
         }
     }
@@ -41,43 +173,85 @@ impl Transform {
     pub fn with_translation(translation: Vec3) -> Self {
         Self {
             translation,
-            rotation: Vec3::zero(),
+            rotation: Quaternion::identity(),
             scale: Vec3::new(1.0, 1.0, 1.0),
         }
     }
     
-    /// Apply transform to a point
+    /// The transform an affine matrix without shear performs, its scale taken from the lengths of its
+    /// columns (a mirroring matrix flips x)
+    pub fn from_matrix(matrix: &Mat4) -> Self {
+        let linear = matrix.linear();
+        let columns = linear.transpose().rows.map(|[x, y, z]| Vec3::new(x, y, z));
+        let mut scale = Vec3::new(columns[0].length(), columns[1].length(), columns[2].length());
+        if linear.determinant() < 0.0 {
+            scale.x = -scale.x;
+        }
+        let unscale = Mat3::from_scale(Vec3::new(1.0 / scale.x, 1.0 / scale.y, 1.0 / scale.z));
+        let [x, y, z] = [0, 1, 2].map(|row| matrix.rows[row][3]);
+        Self { translation: Vec3::new(x, y, z), rotation: Quaternion::from_matrix(&(linear * unscale)), scale }
+    }
+    
+    /// Whether the transform leaves everything where it is
+    pub fn is_identity(&self) -> bool {
+        self.translation == Vec3::zero() && self.rotation == Quaternion::identity() && self.scale == Vec3::new(1.0, 1.0, 1.0)
+    }
+    
+    /// The same transform as a matrix: scale, then rotate, then translate
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_translation(self.translation) * Mat4::from_rotation(self.rotation) * Mat4::from_scale(self.scale)
+    }
+    
+    /// Average factor the transform scales lengths by (the geometric mean of its axis scales)
+    pub fn mean_scale(&self) -> Float {
+        (self.scale.x * self.scale.y * self.scale.z).abs().cbrt()
+    }
+    
+    /// Apply transform to a point: scale, then rotate, then translate
     pub fn apply_to_point(&self, point: Vec3) -> Vec3 {
-        // For now, just apply translation and uniform scale
-        // TODO: Add proper rotation matrix support
-        Vec3::new(
-            point.x * self.scale.x + self.translation.x,
-            point.y * self.scale.y + self.translation.y,
-            point.z * self.scale.z + self.translation.z,
-        )
+        self.apply_to_vector(point) + self.translation
+    }
+    
+    /// Apply the scale and rotation to a direction or offset
+    pub fn apply_to_vector(&self, vector: Vec3) -> Vec3 {
+        self.rotation.rotate(vector * self.scale)
+    }
+    
+    /// Bring an object-space normal to world space (unit length, still perpendicular under non-uniform scale)
+    pub fn apply_to_normal(&self, normal: Vec3) -> Vec3 {
+        self.rotation.rotate(Vec3::new(normal.x / self.scale.x, normal.y / self.scale.y, normal.z / self.scale.z)).normalize()
     }
     
     /// Apply inverse transform to a ray (for object-space intersection)
+    /// The direction isn't renormalized, so hit distances `t` are the same in both spaces
     pub fn inverse_transform_ray(&self, ray: &Ray) -> Ray {
-        // For now, just handle translation and uniform scale
-        // TODO: Add proper inverse matrix support
-        let inv_scale = Vec3::new(1.0 / self.scale.x, 1.0 / self.scale.y, 1.0 / self.scale.z);
+        let unscale = |v: Vec3| Vec3::new(v.x / self.scale.x, v.y / self.scale.y, v.z / self.scale.z);
+        let unrotate = self.rotation.conjugate();
         Ray::new(
-            Vec3::new(
-                (ray.origin.x - self.translation.x) * inv_scale.x,
-                (ray.origin.y - self.translation.y) * inv_scale.y,
-                (ray.origin.z - self.translation.z) * inv_scale.z,
-            ),
-            Vec3::new(
-                ray.direction.x * inv_scale.x,
-                ray.direction.y * inv_scale.y,
-                ray.direction.z * inv_scale.z,
-            ),
+            unscale(unrotate.rotate(ray.origin - self.translation)),
+            unscale(unrotate.rotate(ray.direction)),
         )
     }
 }
 
+impl Default for Transform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub use sphere::Sphere;
+pub use sphere_list::SphereList;
 pub use plane::Plane;
 pub use cube::Cube;
 pub use cylinder::Cylinder;
+pub use capsule::Capsule;
+pub use heightfield::Heightfield;
+pub use voxels::VoxelGrid;
+pub use curve::{Curve, CurveBasis};
+pub use points::{PointCloud, Splat};
+pub use triangle::Triangle;
+pub use mesh::{Mesh, Shading};
+pub use medium::{ConstantMedium, PhaseFunction};
+pub use group::Group;
+pub use instance::Instance;
@@ -1,4 +1,4 @@
-use crate::math::{Vec3, Ray};
+use crate::math::{Float, Vec3, Ray, Interval, Onb};
 use crate::material::Material;
 use super::{HitInfo, Intersectable, Transform};
 
@@ -23,13 +23,13 @@ impl Plane {
     }
     
     /// Create a horizontal plane at given Y coordinate
-    pub fn horizontal(y: f64, material: Material) -> Self {
+    pub fn horizontal(y: Float, material: Material) -> Self {
         Self::new(Vec3::new(0.0, y, 0.0), Vec3::unit_y(), material)
     }
 }
 
 impl Intersectable for Plane {
-    fn intersect(&self, ray: &Ray) -> Option<HitInfo> {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
         // Ray-plane intersection
         // Plane equation: (P - point) · normal = 0
         // Ray: P(t) = origin + t * direction
@@ -45,25 +45,25 @@ impl Intersectable for Plane {
         
         let t = (self.point - ray.origin).dot(&self.normal) / denom;
         
-        // Check if intersection is behind ray origin
-        if t < 1e-4 {
+        // Check if intersection is behind ray origin or too far
+        if !range.surrounds(t) {
             return None;
         }
         
-        let hit_point = ray.at(t);
-        
-        Some(HitInfo {
-            t,
-            point: hit_point,
-            normal: self.normal,
-            material: self.material.clone(),
-        })
+        // World units along two axes of the plane
+        let axes = Onb::from_normal(self.normal);
+        let offset = ray.at(t) - self.point;
+        let uv = (offset.dot(&axes.u), offset.dot(&axes.v));
+        Some(HitInfo::new(ray, t, self.normal, self.material.clone()).with_uv(uv, 1.0, (axes.u, axes.v)))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
+    use crate::color::Color;
+    use crate::texture::{Filter, Texture, Wrap};
     
     #[test]
     fn test_plane_intersection() {
@@ -71,11 +71,48 @@ mod tests {
         
         // Ray pointing down at the plane
         let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, -1.0, 0.0));
-        let hit = plane.intersect(&ray);
+        let hit = plane.intersect(&ray, Interval::FORWARD);
         
         assert!(hit.is_some());
         let hit = hit.unwrap();
         assert!((hit.t - 1.0).abs() < 1e-10);
         assert_eq!(hit.point, Vec3::new(0.0, -1.0, 0.0));
     }
+    
+    #[test]
+    fn test_texture_footprint() {
+        // A 2 × 2 checkerboard, one world unit per texel, reads as one texel up close and gray far off
+        let checker = [0.0, 1.0, 1.0, 0.0].map(Color::gray).to_vec();
+        let mut material = Material::new(Color::white());
+        material.texture = Some(Arc::new(Texture::new(2, 2, checker, vec![1.0; 4]).with_scale(2.0)));
+        let plane = Plane::horizontal(0.0, material);
+        
+        let ray = Ray::new(Vec3::new(1.5, 1.0, -1.5), Vec3::new(0.0, -1.0, 0.0));
+        let albedo = |width: Float| {
+            let mut hit = plane.intersect(&ray, Interval::FORWARD).unwrap();
+            hit.apply_textures(ray.direction, width);
+            assert!(hit.material.texture.is_none());
+            hit.material.albedo.r
+        };
+        assert_eq!(albedo(0.01), 1.0);
+        assert_eq!(albedo(4.0), 0.5);
+    }
+    
+    #[test]
+    fn test_bump() {
+        // Ground rising a quarter unit per unit along +x faces back toward -x, seen from either side
+        let ramp = [0.0, 0.25, 0.5, 0.75].map(Color::gray).to_vec();
+        let mut material = Material::new(Color::white());
+        material.bump = Some(Arc::new(Texture::new(4, 1, ramp, vec![1.0; 4]).with_modes(Wrap::Clamp, Filter::Bilinear).with_scale(4.0)));
+        material.bump_height = 1.0;
+        let plane = Plane::horizontal(0.0, material);
+        
+        let expected = Vec3::new(-0.25, 1.0, 0.0).normalize();
+        for (origin, direction) in [(1.0, -1.0), (-1.0, 1.0)] {
+            let ray = Ray::new(Vec3::new(1.5, origin, -0.5), Vec3::new(0.0, direction, 0.0));
+            let mut hit = plane.intersect(&ray, Interval::FORWARD).unwrap();
+            hit.apply_textures(ray.direction, 0.01);
+            assert!((hit.normal - expected * -direction).length() < 1e-6, "{:?}", hit.normal);
+        }
+    }
 }
@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bvh::Bvh;
+use crate::color::Color;
+use crate::math::{Float, Vec3, Ray, Interval, Aabb};
+use crate::material::Material;
+use super::{HitInfo, Intersectable};
+
+/// How each point of a cloud is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Splat {
+    #[default]
+    Disk,   // Flat disk turned to face each ray, the cheapest and flattest look
+    Sphere, // Small sphere, shaded with depth and casting round shadows
+}
+
+/// Point cloud drawn as a splat of `radius` around each point, such as a 3D scan: the points are indexed
+/// by a BVH of their own, and each may carry its own color, replacing the material's albedo
+#[derive(Debug, Clone)]
+pub struct PointCloud {
+    pub radius: Float,
+    pub splat: Splat,
+    pub material: Material,
+    positions: Vec<Vec3>,
+    colors: Option<Vec<Color>>, // One per point
+    bvh: Bvh,
+}
+
+impl PointCloud {
+    /// Create a cloud of points, with a color for each or the material's albedo for all
+    pub fn new(positions: Vec<Vec3>, colors: Option<Vec<Color>>, radius: Float, splat: Splat, material: Material) -> Self {
+        assert!(colors.as_ref().is_none_or(|colors| colors.len() == positions.len()), "point clouds need a color per point");
+        let extent = Vec3::new(radius, radius, radius);
+        let bounds: Vec<Option<Aabb>> = positions.iter().map(|&point| Some(Aabb::new(point - extent, point + extent))).collect();
+        let bvh = Bvh::new(&bounds);
+        Self { radius, splat, material, positions, colors, bvh }
+    }
+    
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+    
+    /// Distance and outward normal where the ray meets the splat of point `index`
+    fn intersect_point(&self, index: usize, ray: &Ray, range: Interval) -> Option<(Float, Vec3)> {
+        let center = self.positions[index];
+        let r2 = self.radius * self.radius;
+        let a = ray.direction.length_squared();
+        match self.splat {
+            Splat::Disk => {
+                // Where the ray passes closest to the point, which lies on the disk facing it
+                let t = (center - ray.origin).dot(&ray.direction) / a;
+                let within = (ray.at(t) - center).length_squared() <= r2;
+                (within && range.surrounds(t)).then(|| (t, -ray.direction / a.sqrt()))
+            }
+            Splat::Sphere => {
+                let oc = ray.origin - center;
+                let half_b = oc.dot(&ray.direction);
+                let discriminant = half_b * half_b - a * (oc.length_squared() - r2);
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let sqrt_discriminant = discriminant.sqrt();
+                let t = [(-half_b - sqrt_discriminant) / a, (-half_b + sqrt_discriminant) / a].into_iter().find(|&t| range.surrounds(t))?;
+                Some((t, (ray.at(t) - center) / self.radius))
+            }
+        }
+    }
+}
+
+impl Intersectable for PointCloud {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
+        let mut closest = None;
+        self.bvh.traverse(ray, range.max, |index, t_max| {
+            let (t, normal) = self.intersect_point(index, ray, Interval::new(range.min, t_max))?;
+            closest = Some((t, normal, index));
+            Some(t)
+        });
+        let (t, normal, index) = closest?;
+        let material = match &self.colors {
+            Some(colors) => Material { albedo: colors[index], ..self.material.clone() },
+            None => self.material.clone(),
+        };
+        Some(HitInfo { primitive: index, ..HitInfo::new(ray, t, normal, material) })
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        self.bvh.bounds()
+    }
+    
+    fn memory(&self, _shared: &mut HashSet<usize>) -> usize {
+        let colors = self.colors.as_deref().map_or(0, size_of_val);
+        size_of_val(self) + size_of_val(self.positions.as_slice()) + colors + self.bvh.memory()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_point_cloud_intersection() {
+        let positions = vec![Vec3::zero(), Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, -2.0)];
+        let colors = vec![Color::new(1.0, 0.0, 0.0), Color::new(0.0, 1.0, 0.0), Color::new(0.0, 0.0, 1.0)];
+        let disks = PointCloud::new(positions.clone(), Some(colors), 0.1, Splat::Disk, Material::gray());
+        
+        // Face on to the nearer of two points in line, then just past the edge of a disk
+        let hit = disks.intersect(&Ray::new(Vec3::new(1.05, 0.0, 5.0), -Vec3::unit_z()), Interval::FORWARD).unwrap();
+        assert!((hit.t - 5.0).abs() < 1e-9 && hit.normal == Vec3::unit_z() && hit.front_face);
+        assert_eq!(hit.material.albedo, Color::new(0.0, 1.0, 0.0));
+        assert!(disks.intersect(&Ray::new(Vec3::new(0.11, 0.0, 5.0), -Vec3::unit_z()), Interval::FORWARD).is_none());
+        
+        // Spheres curve away from the ray; without colors the material shows
+        let spheres = PointCloud::new(positions, None, 0.1, Splat::Sphere, Material::gray());
+        let hit = spheres.intersect(&Ray::new(Vec3::new(0.06, 1.0, 0.0), -Vec3::unit_y()), Interval::FORWARD).unwrap();
+        assert!((hit.point.y - 0.08).abs() < 1e-5 && (hit.normal - Vec3::new(0.6, 0.8, 0.0)).length() < 1e-5);
+        assert_eq!(hit.material.albedo, Material::gray().albedo);
+    }
+}
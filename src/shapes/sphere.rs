@@ -1,4 +1,4 @@
-use crate::math::{Vec3, Ray};
+use crate::math::{consts::{PI, SQRT_2}, Float, Vec3, Ray, Interval, Aabb};
 use crate::material::Material;
 use super::{HitInfo, Intersectable, Transform};
 
@@ -6,14 +6,14 @@ use super::{HitInfo, Intersectable, Transform};
 #[derive(Debug, Clone)]
 pub struct Sphere {
     pub center: Vec3,
-    pub radius: f64,
+    pub radius: Float,
     pub material: Material,
     pub transform: Transform,
 }
 
 impl Sphere {
     /// Create a new sphere
-    pub fn new(center: Vec3, radius: f64, material: Material) -> Self {
+    pub fn new(center: Vec3, radius: Float, material: Material) -> Self {
         Self {
             center,
             radius,
@@ -23,7 +23,7 @@ impl Sphere {
     }
     
     /// Create a sphere with transform
-    pub fn with_transform(center: Vec3, radius: f64, material: Material, transform: Transform) -> Self {
+    pub fn with_transform(center: Vec3, radius: Float, material: Material, transform: Transform) -> Self {
         Self {
             center,
             radius,
@@ -34,9 +34,9 @@ impl Sphere {
 }
 
 impl Intersectable for Sphere {
-    fn intersect(&self, ray: &Ray) -> Option<HitInfo> {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
         // Transform ray to object space if needed
-        let local_ray = if self.transform.translation == Vec3::zero() && self.transform.scale == Vec3::new(1.0, 1.0, 1.0) {
+        let local_ray = if self.transform.is_identity() {
             ray.clone()
         } else {
             self.transform.inverse_transform_ray(ray)
@@ -62,31 +62,47 @@ impl Intersectable for Sphere {
         let t1 = (-b - sqrt_discriminant) / (2.0 * a);
         let t2 = (-b + sqrt_discriminant) / (2.0 * a);
         
-        // Choose the closest positive intersection
-        let t = if t1 > 1e-4 {
+        // Choose the closest intersection in range
+        let t = if range.surrounds(t1) {
             t1
-        } else if t2 > 1e-4 {
+        } else if range.surrounds(t2) {
             t2
         } else {
-            return None; // Both intersections behind ray origin
+            return None; // Both intersections behind ray origin or too far
         };
         
-        let hit_point = local_ray.at(t);
-        let normal = (hit_point - self.center).normalize();
+        let normal = (local_ray.at(t) - self.center).normalize();
         
-        // Transform back to world space if needed
-        let world_hit_point = if self.transform.translation == Vec3::zero() && self.transform.scale == Vec3::new(1.0, 1.0, 1.0) {
-            hit_point
+        // Transform the normal back to world space if needed; t is the same in both spaces
+        let world_normal = if self.transform.is_identity() {
+            normal
         } else {
-            self.transform.apply_to_point(hit_point)
+            self.transform.apply_to_normal(normal)
         };
         
-        Some(HitInfo {
-            t,
-            point: world_hit_point,
-            normal,
-            material: self.material.clone(),
-        })
+        // Longitude around the y axis and latitude from the bottom pole; u spans 2πr and v πr
+        let uv = ((-normal.z).atan2(normal.x) / (2.0 * PI) + 0.5, (-normal.y).acos() / PI);
+        let uv_scale = SQRT_2 * PI * self.radius * self.transform.mean_scale();
+        let east = Vec3::new(normal.z, 0.0, -normal.x); // Zero at the poles, where u has no direction
+        let north = normal.cross(&east);
+        let axes = (self.transform.apply_to_vector(east).normalize(), self.transform.apply_to_vector(north).normalize());
+        Some(HitInfo::new(ray, t, world_normal, self.material.clone()).with_uv(uv, uv_scale, axes))
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        let local = Aabb::new(self.center - extent, self.center + extent);
+        Some(if self.transform.is_identity() { local } else { local.transformed(&self.transform.matrix()) })
+    }
+    
+    fn sphere(&self) -> Option<(Vec3, Float)> {
+        let solid = self.material.two_sided && self.material.opacity.is_none();
+        (self.transform.is_identity() && solid).then_some((self.center, self.radius))
+    }
+    
+    #[cfg(feature = "simd")]
+    fn primitive(&self) -> Option<crate::simd::Primitive> {
+        self.transform.is_identity().then_some(crate::simd::Primitive::Sphere { center: self.center, radius: self.radius })
     }
 }
 
@@ -104,7 +120,7 @@ mod tests {
         
         // Ray pointing directly at sphere center
         let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
-        let hit = sphere.intersect(&ray);
+        let hit = sphere.intersect(&ray, Interval::FORWARD);
         
         assert!(hit.is_some());
         let hit = hit.unwrap();
@@ -122,8 +138,39 @@ mod tests {
         
         // Ray pointing away from sphere
         let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, 1.0));
-        let hit = sphere.intersect(&ray);
+        let hit = sphere.intersect(&ray, Interval::FORWARD);
         
         assert!(hit.is_none());
     }
+    
+    #[test]
+    fn test_sphere_from_inside() {
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, -1.0), 0.5, Material::red());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = sphere.intersect(&ray, Interval::FORWARD).unwrap();
+        
+        // The far side is hit from behind, and its normal is flipped to point back inside, toward the ray
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(sphere.intersect(&Ray::new(Vec3::zero(), ray.direction), Interval::FORWARD).unwrap().front_face);
+    }
+    
+    #[test]
+    fn test_offset_origin_at_any_scale() {
+        // Rays bounced off, or sent through, spheres a millimeter and a thousand kilometers across
+        // must not hit the surface they leave, however the rounding falls
+        for scale in [1e-3, 1.0, 1e6] {
+            let sphere = Sphere::new(Vec3::new(0.3, -0.2, -4.0) * scale, scale, Material::red());
+            for i in 0..50 {
+                let angle = i as Float * 0.03;
+                let ray = Ray::new(Vec3::zero(), Vec3::new(angle.sin() * 0.2, angle.cos() * 0.1 - 0.05, -1.0));
+                let Some(hit) = sphere.intersect(&ray, Interval::FORWARD) else { continue };
+                
+                let reflected = ray.direction.normalize().reflect(&hit.normal);
+                assert!(sphere.intersect(&Ray::new(hit.offset_origin(reflected), reflected), Interval::FORWARD).is_none());
+                let through = sphere.intersect(&Ray::new(hit.offset_origin(ray.direction), ray.direction), Interval::FORWARD).map(|exit| exit.t);
+                assert!(through.is_some_and(|t| t > 1e-3 * scale), "{} {:?}", scale, through);
+            }
+        }
+    }
 }
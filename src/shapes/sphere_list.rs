@@ -0,0 +1,105 @@
+use crate::math::{Float, Vec3, Ray, Interval, Aabb};
+
+/// Untransformed spheres with their centers and radii in flat arrays, intersected in one tight loop
+/// It only finds which sphere a ray hits first and where; the caller then intersects that sphere in full
+/// for its normal and material. The arithmetic is the same as `Sphere::intersect`, so the two always agree
+#[derive(Debug, Clone)]
+pub struct SphereList {
+    x: Vec<Float>,
+    y: Vec<Float>,
+    z: Vec<Float>,
+    radius_squared: Vec<Float>,
+    bounds: Aabb,
+}
+
+impl SphereList {
+    /// Create a list from (center, radius) pairs
+    pub fn new(spheres: &[(Vec3, Float)]) -> Self {
+        let mut list = Self { x: Vec::new(), y: Vec::new(), z: Vec::new(), radius_squared: Vec::new(), bounds: Aabb::empty() };
+        for &(center, radius) in spheres {
+            list.push(center, radius);
+        }
+        list
+    }
+    
+    pub fn push(&mut self, center: Vec3, radius: Float) {
+        self.x.push(center.x);
+        self.y.push(center.y);
+        self.z.push(center.z);
+        self.radius_squared.push(radius * radius);
+        let extent = Vec3::new(radius, radius, radius);
+        self.bounds = self.bounds.union(&Aabb::new(center - extent, center + extent));
+    }
+    
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
+    
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+    
+    /// Index and distance of the first sphere the ray hits in range
+    pub fn closest(&self, ray: &Ray, range: Interval) -> Option<(usize, Float)> {
+        let (o, d) = (ray.origin, ray.direction);
+        let a = d.dot(&d);
+        let mut closest = None;
+        let mut closest_t = range.max;
+        
+        let centers = self.x.iter().zip(&self.y).zip(&self.z);
+        for (i, (((&x, &y), &z), &radius_squared)) in centers.zip(&self.radius_squared).enumerate() {
+            let oc = Vec3::new(o.x - x, o.y - y, o.z - z);
+            let b = 2.0 * oc.dot(&d);
+            let c = oc.dot(&oc) - radius_squared;
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                continue; // Most spheres are missed, so this is the only test most of them see
+            }
+            
+            let sqrt_discriminant = discriminant.sqrt();
+            let t1 = (-b - sqrt_discriminant) / (2.0 * a);
+            let t2 = (-b + sqrt_discriminant) / (2.0 * a);
+            let t = if t1 > range.min { t1 } else { t2 };
+            if t > range.min && t < closest_t {
+                closest = Some(i);
+                closest_t = t;
+            }
+        }
+        closest.map(|i| (i, closest_t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::sampling::Rng;
+    use crate::shapes::{Intersectable, Sphere};
+    
+    #[test]
+    fn test_matches_spheres() {
+        // Overlapping spheres, with rays from outside and from inside some of them
+        let mut rng = Rng::new(3);
+        let mut random = |scale: Float| Vec3::new(rng.next_float(), rng.next_float(), rng.next_float()) * scale;
+        let spheres: Vec<Sphere> = (0..40)
+            .map(|_| Sphere::new(random(6.0) - Vec3::new(3.0, 3.0, 8.0), 0.2 + random(1.0).x, Material::red()))
+            .collect();
+        let list = SphereList::new(&spheres.iter().map(|s| (s.center, s.radius)).collect::<Vec<_>>());
+        assert_eq!(list.len(), spheres.len());
+        
+        for i in 0..400 {
+            let origin = if i % 2 == 0 { Vec3::zero() } else { spheres[i % spheres.len()].center };
+            let ray = Ray::new(origin, random(2.0) - Vec3::new(1.0, 1.0, 1.0));
+            let expected = spheres
+                .iter()
+                .enumerate()
+                .filter_map(|(index, sphere)| Some((index, sphere.intersect(&ray, Interval::FORWARD)?.t)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            assert_eq!(list.closest(&ray, Interval::FORWARD), expected, "{:?}", ray);
+        }
+    }
+}
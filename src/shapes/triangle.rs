@@ -0,0 +1,105 @@
+use crate::math::{Float, Vec3, Ray, Interval, Aabb};
+use crate::material::Material;
+use super::{HitInfo, Intersectable};
+
+/// Single triangle primitive; the normal follows counter-clockwise winding (a → b → c)
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub c: Vec3,
+    pub normal: Vec3, // Unit geometric normal
+    pub material: Material,
+}
+
+impl Triangle {
+    /// Create a triangle from three corners
+    pub fn new(a: Vec3, b: Vec3, c: Vec3, material: Material) -> Self {
+        let normal = (b - a).cross(&(c - a)).normalize();
+        Self { a, b, c, normal, material }
+    }
+}
+
+/// Möller–Trumbore: distance and barycentric coordinates (of b and c) where the ray crosses triangle abc
+pub(super) fn intersect_triangle(ray: &Ray, a: Vec3, b: Vec3, c: Vec3, range: Interval) -> Option<(Float, Float, Float)> {
+    let (edge1, edge2) = (b - a, c - a);
+    let p = ray.direction.cross(&edge2);
+    let det = edge1.dot(&p);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = ray.origin - a;
+    let u = s.dot(&p) * inv_det;
+    let q = s.cross(&edge1);
+    let v = ray.direction.dot(&q) * inv_det;
+    let t = edge2.dot(&q) * inv_det;
+    (u >= 0.0 && v >= 0.0 && u + v <= 1.0 && range.surrounds(t)).then_some((t, u, v))
+}
+
+impl Intersectable for Triangle {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
+        // Möller–Trumbore: solve origin + t * direction = a + u * edge1 + v * edge2
+        let edge1 = self.b - self.a;
+        let edge2 = self.c - self.a;
+        let p = ray.direction.cross(&edge2);
+        let det = edge1.dot(&p);
+        
+        // Ray is parallel to the triangle's plane
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        
+        let inv_det = 1.0 / det;
+        let s = ray.origin - self.a;
+        let u = s.dot(&p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        
+        let q = s.cross(&edge1);
+        let v = ray.direction.dot(&q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        
+        let t = edge2.dot(&q) * inv_det;
+        if !range.surrounds(t) {
+            return None;
+        }
+        
+        // Barycentric coordinates, spanning the parallelogram on the two edges
+        let uv_scale = edge1.cross(&edge2).length().sqrt();
+        Some(HitInfo::new(ray, t, self.normal, self.material.clone()).with_uv((u, v), uv_scale, (edge1.normalize(), edge2.normalize())))
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.a, self.a).grow(self.b).grow(self.c))
+    }
+    
+    fn triangle_count(&self) -> usize {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_triangle_intersection() {
+        let triangle = Triangle::new(
+            Vec3::new(-1.0, -1.0, -2.0),
+            Vec3::new(1.0, -1.0, -2.0),
+            Vec3::new(0.0, 1.0, -2.0),
+            Material::gray(),
+        );
+        assert_eq!(triangle.normal, Vec3::new(0.0, 0.0, 1.0));
+        
+        let hit = triangle.intersect(&Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0)), Interval::FORWARD).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-10);
+        
+        // Outside the edge a → c
+        assert!(triangle.intersect(&Ray::new(Vec3::new(-0.9, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0)), Interval::FORWARD).is_none());
+    }
+}
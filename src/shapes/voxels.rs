@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::math::{Float, Vec3, Ray, Interval, Aabb};
+use crate::material::Material;
+use crate::color::Color;
+use crate::vox::VoxModel;
+use super::{HitInfo, Intersectable};
+
+/// Grid of solid cubes (voxels) `voxel_size` across with its first corner at `min`, each with a material
+/// of its own; only filled voxels are stored, and rays step from voxel to voxel along their path (3D-DDA)
+/// until they cross between empty and filled ones
+#[derive(Debug, Clone)]
+pub struct VoxelGrid {
+    pub min: Vec3,
+    pub voxel_size: Float,
+    pub materials: Vec<Material>,       // Looked up by the voxels' material indices
+    size: [usize; 3],                   // Voxels along x, y and z, up to the last filled one
+    voxels: HashMap<[usize; 3], usize>, // Material index of each filled voxel
+}
+
+impl VoxelGrid {
+    /// Empty grid whose voxels will use `materials`
+    pub fn new(min: Vec3, voxel_size: Float, materials: Vec<Material>) -> Self {
+        Self { min, voxel_size, materials, size: [0; 3], voxels: HashMap::new() }
+    }
+    
+    /// Fill the voxel at `position` (counted from `min` along x, y and z) with material `material`
+    pub fn insert(&mut self, position: [usize; 3], material: usize) {
+        assert!(material < self.materials.len(), "no material {} in the grid", material);
+        self.size = [0, 1, 2].map(|axis| self.size[axis].max(position[axis] + 1));
+        self.voxels.insert(position, material);
+    }
+    
+    /// Number of filled voxels
+    pub fn len(&self) -> usize {
+        self.voxels.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.voxels.is_empty()
+    }
+    
+    /// Grid of a MagicaVoxel model, turned from its z-up axes to y up (the model's y axis pointing toward -z)
+    /// Each color of the palette in use becomes a copy of `base` with that albedo
+    pub fn from_vox(model: &VoxModel, min: Vec3, voxel_size: Float, base: &Material) -> Self {
+        let mut grid = Self::new(min, voxel_size, Vec::new());
+        let mut indices = HashMap::new(); // Material index of each color index
+        let depth = model.size[1] as usize;
+        for &([x, y, z], color) in &model.voxels {
+            let material = *indices.entry(color).or_insert_with(|| {
+                let [r, g, b, _] = model.palette[color as usize];
+                grid.materials.push(Material { albedo: Color::from_srgb8([r, g, b]), ..base.clone() });
+                grid.materials.len() - 1
+            });
+            grid.insert([x as usize, z as usize, depth - 1 - y as usize], material);
+        }
+        grid
+    }
+    
+    fn voxel(&self, cell: [usize; 3]) -> Option<usize> {
+        self.voxels.get(&cell).copied()
+    }
+    
+    /// The hit where a ray crosses into or out of a filled voxel at `t`, through a face whose normal
+    /// points out of that voxel
+    fn hit(&self, ray: &Ray, t: Float, cell: [usize; 3], outward_normal: Vec3, material: usize) -> HitInfo {
+        // Each face is mapped corner to corner like a cube's
+        let corner = self.min + Vec3::new(cell[0] as Float, cell[1] as Float, cell[2] as Float) * self.voxel_size;
+        let local = (ray.at(t) - corner) / self.voxel_size;
+        let (uv, axes) = if outward_normal.x != 0.0 {
+            ((local.z, local.y), (Vec3::unit_z(), Vec3::unit_y()))
+        } else if outward_normal.y != 0.0 {
+            ((local.x, local.z), (Vec3::unit_x(), Vec3::unit_z()))
+        } else {
+            ((local.x, local.y), (Vec3::unit_x(), Vec3::unit_y()))
+        };
+        let primitive = cell[0] + self.size[0] * (cell[1] + self.size[1] * cell[2]);
+        HitInfo { primitive, ..HitInfo::new(ray, t, outward_normal, self.materials[material].clone()) }.with_uv(uv, self.voxel_size, axes)
+    }
+}
+
+impl Intersectable for VoxelGrid {
+    fn intersect(&self, ray: &Ray, range: Interval) -> Option<HitInfo> {
+        let bounds = self.bounds()?;
+        let inverse_direction = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+        let ((t_near, near_normal), (t_far, _)) = bounds.slab_faces(ray, inverse_direction);
+        let mut t = t_near.max(range.min);
+        if t > t_far.min(range.max) {
+            return None;
+        }
+        
+        // The voxel the ray starts in, or enters the grid through
+        let local = (ray.at(t) - self.min) / self.voxel_size;
+        let mut cell = [0, 1, 2].map(|axis| (local[axis].floor().max(0.0) as usize).min(self.size[axis] - 1));
+        let mut previous = if t_near >= range.min { None } else { self.voxel(cell).map(|material| (material, cell)) };
+        let mut crossed = near_normal; // Outward normal of the face the ray last crossed, of the voxel it entered
+        
+        // Distance to the next voxel boundary along each axis, and between boundaries
+        let step = [0, 1, 2].map(|axis| if ray.direction[axis] >= 0.0 { 1.0 } else { -1.0 });
+        let delta = [0, 1, 2].map(|axis| (self.voxel_size * inverse_direction[axis]).abs());
+        let mut next = [0, 1, 2].map(|axis| {
+            if ray.direction[axis] == 0.0 {
+                return Float::INFINITY; // Never leaves this slab of voxels
+            }
+            let boundary = self.min[axis] + (cell[axis] as Float + if step[axis] > 0.0 { 1.0 } else { 0.0 }) * self.voxel_size;
+            (boundary - ray.origin[axis]) * inverse_direction[axis]
+        });
+        
+        loop {
+            let current = self.voxel(cell);
+            match (previous, current) {
+                (None, Some(material)) if range.surrounds(t) => return Some(self.hit(ray, t, cell, crossed, material)),
+                (Some((material, left)), None) if range.surrounds(t) => return Some(self.hit(ray, t, left, -crossed, material)),
+                _ => {}
+            }
+            previous = current.map(|material| (material, cell));
+            
+            // On to the neighbor across the nearest boundary; out of the grid, a filled voxel is left behind
+            let axis = (0..3).min_by(|&a, &b| next[a].total_cmp(&next[b])).unwrap_or(0);
+            t = next[axis];
+            crossed = Vec3::zero();
+            crossed[axis] = -step[axis];
+            let stepped = cell[axis] as Float + step[axis];
+            if stepped < 0.0 || stepped as usize >= self.size[axis] {
+                return current.filter(|_| range.surrounds(t)).map(|material| self.hit(ray, t, cell, -crossed, material));
+            }
+            if t >= range.max {
+                return None;
+            }
+            cell[axis] = stepped as usize;
+            next[axis] += delta[axis];
+        }
+    }
+    
+    fn bounds(&self) -> Option<Aabb> {
+        if self.voxels.is_empty() {
+            return None;
+        }
+        let extent = Vec3::new(self.size[0] as Float, self.size[1] as Float, self.size[2] as Float) * self.voxel_size;
+        Some(Aabb::new(self.min, self.min + extent))
+    }
+    
+    fn memory(&self, _shared: &mut HashSet<usize>) -> usize {
+        // A hash map entry, plus a control byte
+        let voxels = self.voxels.capacity() * (size_of::<([usize; 3], usize)>() + 1);
+        size_of_val(self) + size_of_val(self.materials.as_slice()) + voxels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_voxel_intersection() {
+        // An L of three unit voxels: two along x, one on top of the first
+        let mut grid = VoxelGrid::new(Vec3::zero(), 1.0, vec![Material::red(), Material::green()]);
+        grid.insert([0, 0, 0], 0);
+        grid.insert([1, 0, 0], 1);
+        grid.insert([0, 1, 0], 0);
+        assert_eq!(grid.bounds().unwrap().max, Vec3::new(2.0, 2.0, 1.0));
+        
+        // Down through the empty corner onto the second voxel's top
+        let hit = grid.intersect(&Ray::new(Vec3::new(1.5, 5.0, 0.5), -Vec3::unit_y()), Interval::FORWARD).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-9);
+        assert_eq!(hit.normal, Vec3::unit_y());
+        assert_eq!(hit.material.albedo, Material::green().albedo);
+        
+        // Across, into the side of the upper voxel
+        let hit = grid.intersect(&Ray::new(Vec3::new(5.0, 1.5, 0.5), -Vec3::unit_x()), Interval::FORWARD).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-9 && hit.normal == Vec3::unit_x());
+        
+        // From inside the bottom row, out through its far end
+        let hit = grid.intersect(&Ray::new(Vec3::new(0.5, 0.5, 0.5), Vec3::unit_x()), Interval::FORWARD).unwrap();
+        assert!((hit.t - 1.5).abs() < 1e-9 && !hit.front_face && hit.normal == -Vec3::unit_x());
+        assert!(grid.intersect(&Ray::new(Vec3::new(1.5, 1.5, 5.0), -Vec3::unit_z()), Interval::FORWARD).is_none());
+    }
+}
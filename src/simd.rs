@@ -0,0 +1,259 @@
+use wide::{CmpGt, CmpLt};
+
+use crate::math::{Float, Vec3, Ray};
+use crate::scene::{Accelerator, AcceleratorOptions, SceneObject};
+
+/// Four `Float` lanes
+#[cfg(not(feature = "f32"))]
+type Float4 = wide::f64x4;
+#[cfg(feature = "f32")]
+type Float4 = wide::f32x4;
+
+/// Geometry simple enough to intersect four at a time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Primitive {
+    Sphere { center: Vec3, radius: Float },
+    Box { min: Vec3, max: Vec3 },
+}
+
+/// Coordinates of four shapes, one per lane
+#[derive(Debug, Clone, Copy)]
+struct Lanes3 {
+    x: Float4,
+    y: Float4,
+    z: Float4,
+}
+
+impl Lanes3 {
+    fn new(points: [Vec3; 4]) -> Self {
+        Self {
+            x: Float4::new(points.map(|p| p.x)),
+            y: Float4::new(points.map(|p| p.y)),
+            z: Float4::new(points.map(|p| p.z)),
+        }
+    }
+}
+
+/// Groups of four spheres in structure-of-arrays layout
+/// Unused lanes have a NaN radius, which never hits
+#[derive(Debug, Default)]
+struct SphereGroups {
+    centers: Vec<Lanes3>,
+    radii: Vec<Float4>,
+}
+
+impl SphereGroups {
+    fn new(spheres: &[(Vec3, Float)]) -> Self {
+        let mut groups = Self::default();
+        for chunk in spheres.chunks(4) {
+            let mut centers = [Vec3::zero(); 4];
+            let mut radii = [Float::NAN; 4];
+            for (lane, &(center, radius)) in chunk.iter().enumerate() {
+                centers[lane] = center;
+                radii[lane] = radius;
+            }
+            groups.centers.push(Lanes3::new(centers));
+            groups.radii.push(Float4::new(radii));
+        }
+        groups
+    }
+    
+    /// Hit distances (infinity for misses), with the same arithmetic as `Sphere::intersect` so results match exactly
+    fn intersect(&self, ray: &Ray, out: &mut Vec<Float>) {
+        let o = ray.origin;
+        let d = ray.direction;
+        let a = d.dot(&d);
+        let inf = Float4::splat(Float::INFINITY);
+        
+        for (center, &radius) in self.centers.iter().zip(&self.radii) {
+            let ocx = Float4::splat(o.x) - center.x;
+            let ocy = Float4::splat(o.y) - center.y;
+            let ocz = Float4::splat(o.z) - center.z;
+            let b = Float4::splat(2.0) * (ocx * d.x + ocy * d.y + ocz * d.z);
+            let c = (ocx * ocx + ocy * ocy + ocz * ocz) - radius * radius;
+            
+            // Misses give a negative discriminant, whose square root is NaN and fails both tests below
+            let discriminant = b * b - Float4::splat(4.0 * a) * c;
+            if discriminant.cmp_lt(0.0).all() {
+                out.extend_from_slice(&[Float::INFINITY; 4]);
+                continue;
+            }
+            let sqrt_discriminant = discriminant.sqrt();
+            let t1 = (-b - sqrt_discriminant) / (2.0 * a);
+            let t2 = (-b + sqrt_discriminant) / (2.0 * a);
+            let t = t1.cmp_gt(0.0).blend(t1, t2.cmp_gt(0.0).blend(t2, inf));
+            out.extend_from_slice(&t.to_array());
+        }
+    }
+}
+
+/// Groups of four axis-aligned boxes in structure-of-arrays layout
+/// Unused lanes are inside out (min = +inf, max = -inf), which never hits
+#[derive(Debug, Default)]
+struct BoxGroups {
+    mins: Vec<Lanes3>,
+    maxs: Vec<Lanes3>,
+}
+
+impl BoxGroups {
+    fn new(boxes: &[(Vec3, Vec3)]) -> Self {
+        let mut groups = Self::default();
+        for chunk in boxes.chunks(4) {
+            let mut mins = [Vec3::new(Float::INFINITY, Float::INFINITY, Float::INFINITY); 4];
+            let mut maxs = [-mins[0]; 4];
+            for (lane, &(min, max)) in chunk.iter().enumerate() {
+                mins[lane] = min;
+                maxs[lane] = max;
+            }
+            groups.mins.push(Lanes3::new(mins));
+            groups.maxs.push(Lanes3::new(maxs));
+        }
+        groups
+    }
+    
+    /// Hit distances (infinity for misses) by the slab method, matching `Cube::intersect` (`Aabb::slab_faces`) exactly
+    fn intersect(&self, ray: &Ray, out: &mut Vec<Float>) {
+        let o = [ray.origin.x, ray.origin.y, ray.origin.z];
+        let d = [ray.direction.x, ray.direction.y, ray.direction.z];
+        let inverse = d.map(|d| 1.0 / d);
+        let inf = Float4::splat(Float::INFINITY);
+        
+        'groups: for (min, max) in self.mins.iter().zip(&self.maxs) {
+            let mut t_min = -inf;
+            let mut t_max = inf;
+            let mut missed = Float4::splat(0.0); // Lane mask: outside a slab the ray runs parallel to
+            
+            for (axis, (min, max)) in [(min.x, max.x), (min.y, max.y), (min.z, max.z)].into_iter().enumerate() {
+                if d[axis] == 0.0 {
+                    let origin = Float4::splat(o[axis]);
+                    missed = missed | origin.cmp_lt(min) | origin.cmp_gt(max);
+                } else {
+                    let t1 = (min - o[axis]) * inverse[axis];
+                    let t2 = (max - o[axis]) * inverse[axis];
+                    t_min = t_min.max(t1.min(t2));
+                    t_max = t_max.min(t1.max(t2));
+                }
+                
+                // Slabs only narrow the interval, so once every lane misses the group is done
+                if (missed | t_min.cmp_gt(t_max)).all() {
+                    out.extend_from_slice(&[Float::INFINITY; 4]);
+                    continue 'groups;
+                }
+            }
+            
+            let t = t_min.cmp_gt(0.0).blend(t_min, t_max.cmp_gt(0.0).blend(t_max, inf));
+            let t = (missed | t_min.cmp_gt(t_max)).blend(inf, t);
+            out.extend_from_slice(&t.to_array());
+        }
+    }
+}
+
+/// Which packed group lane, if any, holds each scene object
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Scalar,
+    Sphere(usize),
+    Box(usize),
+}
+
+/// A scene's spheres and boxes packed for four-wide intersection, and an accelerator over its other objects
+pub struct PackedScene {
+    spheres: SphereGroups,
+    boxes: BoxGroups,
+    slots: Vec<Slot>,
+    others: Vec<usize>,          // Objects that need a full scalar intersection test
+    index: Box<dyn Accelerator>, // Over `others`
+}
+
+thread_local! {
+    // Per-ray hit distances for the packed spheres and boxes, reused between rays
+    static DISTANCES: std::cell::RefCell<(Vec<Float>, Vec<Float>)> = const { std::cell::RefCell::new((Vec::new(), Vec::new())) };
+}
+
+impl PackedScene {
+    pub fn new(objects: &[SceneObject], options: &AcceleratorOptions) -> Self {
+        let mut spheres = Vec::new();
+        let mut boxes = Vec::new();
+        let slots = objects
+            .iter()
+            .map(|object| match object.shape.primitive() {
+                Some(Primitive::Sphere { center, radius }) => {
+                    spheres.push((center, radius));
+                    Slot::Sphere(spheres.len() - 1)
+                }
+                Some(Primitive::Box { min, max }) => {
+                    boxes.push((min, max));
+                    Slot::Box(boxes.len() - 1)
+                }
+                None => Slot::Scalar,
+            })
+            .collect::<Vec<_>>();
+        
+        let others: Vec<usize> = (0..objects.len()).filter(|&i| matches!(slots[i], Slot::Scalar)).collect();
+        let index = options.build(&others.iter().map(|&i| objects[i].shape.bounds()).collect::<Vec<_>>());
+        Self { spheres: SphereGroups::new(&spheres), boxes: BoxGroups::new(&boxes), slots, others, index }
+    }
+    
+    /// Call `visit` with the index of each packed sphere and box and its hit distance (infinity for a miss)
+    pub fn distances(&self, ray: &Ray, mut visit: impl FnMut(usize, Float)) {
+        DISTANCES.with(|distances| {
+            let (sphere_t, box_t) = &mut *distances.borrow_mut();
+            sphere_t.clear();
+            box_t.clear();
+            self.spheres.intersect(ray, sphere_t);
+            self.boxes.intersect(ray, box_t);
+            
+            for (i, slot) in self.slots.iter().enumerate() {
+                match *slot {
+                    Slot::Scalar => {}
+                    Slot::Sphere(lane) => visit(i, sphere_t[lane]),
+                    Slot::Box(lane) => visit(i, box_t[lane]),
+                }
+            }
+        });
+    }
+    
+    /// Offer the ray the objects that aren't packed, through their accelerator (see `Accelerator::traverse`)
+    pub fn traverse_others(&self, ray: &Ray, t_max: Float, mut visit: impl FnMut(usize, Float) -> Option<Float>) {
+        self.index.traverse(ray, t_max, &mut |i, t_max| visit(self.others[i], t_max));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Interval;
+    use crate::material::Material;
+    use crate::scene::Scene;
+    use crate::shapes::{Cube, Sphere};
+    
+    #[test]
+    fn test_matches_scalar_intersection() {
+        let mut scene = Scene::new();
+        for i in 0..7 {
+            let offset = i as Float * 0.7 - 2.0;
+            scene.add_object(Box::new(Sphere::new(Vec3::new(offset, 0.3, -4.0), 0.4 + 0.1 * i as Float, Material::red())));
+            scene.add_object(Box::new(Cube::new(
+                Vec3::new(offset, -1.0, -6.0),
+                Vec3::new(offset + 0.5, -0.2 + 0.1 * i as Float, -5.0),
+                Material::blue(),
+            )));
+        }
+        let packed = PackedScene::new(&scene.objects, &AcceleratorOptions::default());
+        
+        // Rays from outside and inside the shapes, including ones parallel to the box slabs
+        let origins = [Vec3::zero(), Vec3::new(-2.0, 0.3, -4.0), Vec3::new(0.1, -0.5, -5.5)];
+        for origin in origins {
+            for i in 0..400 {
+                let (u, v) = ((i % 20) as Float / 10.0 - 1.0, (i / 20) as Float / 10.0 - 1.0);
+                let direction = if i == 0 { Vec3::new(0.0, 0.0, -1.0) } else { Vec3::new(u, v, -1.0) };
+                let ray = Ray::new(origin, direction);
+                
+                packed.distances(&ray, |index, t| {
+                    let expected = scene.objects[index].shape.intersect(&ray, Interval::FORWARD).map_or(Float::INFINITY, |hit| hit.t);
+                    assert_eq!(t, expected, "object {} with {:?}", index, ray);
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::math::{consts::PI, Float, Vec3};
+use crate::color::Color;
+use crate::environment::Environment;
+use crate::scene::Light;
+
+/// Luminous illuminance of sunlight above the atmosphere, in lux
+const SOLAR_ILLUMINANCE: Float = 128000.0;
+
+/// Angular radius of the sun's disk, in degrees
+const SUN_RADIUS: Float = 0.2665;
+
+/// Distance of the sun light, far enough beyond any scene for its rays to arrive parallel
+const SUN_DISTANCE: Float = 10000.0;
+
+/// Size of the environment image a sky is baked into; the sky varies smoothly, and the sun is a light of its own
+const SKY_SIZE: (u32, u32) = (256, 128);
+
+/// Perez distribution coefficients (A to E) for luminance Y and chromaticities x and y, as (slope, offset) in turbidity
+const PEREZ: [[(Float, Float); 5]; 3] = [
+    [(0.1787, -1.4630), (-0.3554, 0.4275), (-0.0227, 5.3251), (0.1206, -2.5771), (-0.0670, 0.3703)],
+    [(-0.0193, -0.2592), (-0.0665, 0.0008), (-0.0004, 0.2125), (-0.0641, -0.8989), (-0.0033, 0.0452)],
+    [(-0.0167, -0.2608), (-0.0950, 0.0092), (-0.0079, 0.2102), (-0.0441, -1.6537), (-0.0109, 0.0529)],
+];
+
+/// Daylight sky after Preetham, Shirley and Smits, "A Practical Analytic Model for Daylight" (1999)
+/// Radiance comes out in cd/m² and the sun's irradiance in lux, for a camera `Exposure` to map to pixels
+/// North is -z and east +x; below the horizon the sky is replaced by a dim reflection of it
+#[derive(Debug, Clone)]
+pub struct Sky {
+    sun: Vec3,              // Unit direction toward the sun
+    turbidity: Float,       // Haze: 2 is a clear sky, 10 a hazy one
+    perez: [[Float; 5]; 3], // Coefficients for Y, x and y
+    zenith: [Float; 3],     // Y (cd/m²), x and y straight up
+}
+
+impl Sky {
+    /// Sky with the sun `elevation` degrees above the horizon and `azimuth` degrees from north toward east
+    /// The sky itself is modeled with the sun no lower than the horizon; below it, only the sun goes dark
+    pub fn new(elevation: Float, azimuth: Float, turbidity: Float) -> Self {
+        let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+        let sun = Vec3::new(azimuth.sin() * elevation.cos(), elevation.sin(), -azimuth.cos() * elevation.cos());
+        let t = turbidity.clamp(1.7, 10.0);
+        let perez = PEREZ.map(|channel| channel.map(|(slope, offset)| slope * t + offset));
+        
+        // Zenith values depend on the sun's zenith angle, in radians
+        let theta = (PI / 2.0 - elevation).clamp(0.0, PI / 2.0);
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta);
+        let luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192) * 1000.0;
+        let cubic = |[a, b, c, d]: [Float; 4]| ((a * theta + b) * theta + c) * theta + d;
+        let x = t * t * cubic([0.00166, -0.00375, 0.00209, 0.0]) + t * cubic([-0.02903, 0.06377, -0.03202, 0.00394])
+            + cubic([0.11693, -0.21196, 0.06052, 0.25886]);
+        let y = t * t * cubic([0.00275, -0.00610, 0.00317, 0.0]) + t * cubic([-0.04214, 0.08970, -0.04153, 0.00516])
+            + cubic([0.15346, -0.26756, 0.06670, 0.26688]);
+        
+        Self { sun, turbidity: t, perez, zenith: [luminance, x, y] }
+    }
+    
+    /// Sun (elevation, azimuth) in degrees at `latitude` degrees north, on `day` of the year, at `hour` of
+    /// local solar time (12 is noon)
+    pub fn sun_position(latitude: Float, day: Float, hour: Float) -> (Float, Float) {
+        let declination = (23.44 as Float).to_radians() * (2.0 * PI * (284.0 + day) / 365.0).sin();
+        let hour_angle = (15.0 * (hour - 12.0)).to_radians();
+        let latitude = latitude.to_radians();
+        
+        let sin_elevation = latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+        let elevation = sin_elevation.clamp(-1.0, 1.0).asin();
+        
+        // Components toward north and east, from which the azimuth follows in any quadrant
+        let north = declination.sin() * latitude.cos() - declination.cos() * latitude.sin() * hour_angle.cos();
+        let east = -declination.cos() * hour_angle.sin();
+        (elevation.to_degrees(), east.atan2(north).to_degrees().rem_euclid(360.0))
+    }
+    
+    /// Direction toward the sun
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun
+    }
+    
+    /// Radiance from `direction` in cd/m², linear RGB
+    pub fn radiance(&self, direction: Vec3) -> Vec3 {
+        let direction = direction.normalize();
+        if direction.y < 0.0 {
+            // Stand-in for the ground: the sky mirrored in a dark surface
+            return self.radiance(Vec3::new(direction.x, -direction.y, direction.z)) * 0.2;
+        }
+        let sun = Vec3::new(self.sun.x, self.sun.y.max(0.0), self.sun.z).normalize();
+        let cos_theta = direction.y.max(0.01);
+        let gamma = direction.dot(&sun).clamp(-1.0, 1.0).acos();
+        let sun_theta = sun.y.clamp(-1.0, 1.0).acos();
+        
+        let perez = |[a, b, c, d, e]: [Float; 5], cos_theta: Float, gamma: Float| {
+            (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+        };
+        let [luminance, x, y] = [0, 1, 2].map(|i| {
+            self.zenith[i] * perez(self.perez[i], cos_theta, gamma) / perez(self.perez[i], 1.0, sun_theta)
+        });
+        
+        // CIE xyY to XYZ to linear sRGB
+        let (cx, cz) = (x * luminance / y, (1.0 - x - y) * luminance / y);
+        Vec3::new(
+            (3.2406 * cx - 1.5372 * luminance - 0.4986 * cz).max(0.0),
+            (-0.9689 * cx + 1.8758 * luminance + 0.0415 * cz).max(0.0),
+            (0.0557 * cx - 0.2040 * luminance + 1.0570 * cz).max(0.0),
+        )
+    }
+    
+    /// Fraction of each channel of sunlight that makes it through the atmosphere: Rayleigh scattering
+    /// and aerosols (their optical depth rising with turbidity) along the air mass toward the sun
+    pub fn sun_transmittance(&self) -> Color {
+        let elevation = self.sun.y.clamp(-1.0, 1.0).asin().to_degrees();
+        if elevation <= 0.0 {
+            return Color::black();
+        }
+        // Kasten and Young's air mass
+        let air_mass = 1.0 / ((90.0 - elevation).to_radians().cos() + 0.50572 * (6.07995 + elevation).powf(-1.6364));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let depth = |rayleigh: Float, wavelength: Float| rayleigh + beta * wavelength.powf(-1.3);
+        let channel = |rayleigh: Float, wavelength: Float| (-air_mass * depth(rayleigh, wavelength)).exp();
+        Color::new(channel(0.036, 0.68), channel(0.097, 0.55), channel(0.24, 0.44))
+    }
+    
+    /// The sun as a distant sphere light of the sun's angular size, so it casts soft shadows in the path
+    /// tracer, scaled by `intensity` like the sky
+    pub fn sun(&self, intensity: Float) -> Light {
+        let radius = SUN_DISTANCE * SUN_RADIUS.to_radians().tan();
+        // Lux to light intensity, as `Light::from_lumens` does
+        Light::sphere(self.sun * SUN_DISTANCE, radius, SOLAR_ILLUMINANCE / PI * intensity, self.sun_transmittance())
+    }
+    
+    /// The sky baked into an environment, scaled by `intensity`
+    pub fn environment(&self, intensity: Float) -> Environment {
+        Environment::from_fn(SKY_SIZE.0, SKY_SIZE.1, intensity, |direction| self.radiance(direction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_sun_position() {
+        let close = |(elevation, azimuth): (Float, Float), expected: (Float, Float)| {
+            (elevation - expected.0).abs() < 0.5 && (azimuth - expected.1).abs() < 0.5
+        };
+        // Summer solstice noon at 45° north: the sun due south, 90 - 45 + 23.44 degrees up
+        assert!(close(Sky::sun_position(45.0, 172.0, 12.0), (68.44, 180.0)));
+        
+        // Equinox on the equator: rising in the east, setting in the west
+        let (elevation, azimuth) = Sky::sun_position(0.0, 80.0, 6.0);
+        assert!(elevation.abs() < 1.0 && (azimuth - 90.0).abs() < 1.0, "{} {}", elevation, azimuth);
+        assert!((Sky::sun_position(0.0, 80.0, 18.0).1 - 270.0).abs() < 1.0);
+    }
+    
+    #[test]
+    fn test_sky_radiance() {
+        let sky = Sky::new(30.0, 180.0, 3.0);
+        assert!((sky.sun_direction().y - 0.5).abs() < 1e-6 && sky.sun_direction().z > 0.0);
+        
+        // Blue straight up, brighter around the sun than away from it, darker below the horizon
+        let zenith = sky.radiance(Vec3::unit_y());
+        assert!(zenith.z > zenith.x && (1000.0..20000.0).contains(&zenith.y), "{:?}", zenith);
+        let (toward, away) = (sky.radiance(Vec3::new(0.0, 0.6, 1.0)), sky.radiance(Vec3::new(0.0, 0.6, -1.0)));
+        assert!(toward.y > 2.0 * away.y);
+        assert!(sky.radiance(Vec3::new(0.0, -0.6, -1.0)).y < away.y);
+        
+        // Low sunlight is redder and dimmer, and the sun goes out below the horizon
+        let (high, low) = (sky.sun_transmittance(), Sky::new(5.0, 180.0, 3.0).sun_transmittance());
+        assert!(low.g < high.g && low.b / low.r < high.b / high.r);
+        assert_eq!(Sky::new(-5.0, 180.0, 3.0).sun_transmittance(), Color::black());
+    }
+}
@@ -0,0 +1,93 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+/// Totals across all threads, updated when a thread calls `flush`
+static RAYS: AtomicU64 = AtomicU64::new(0);
+static INTERSECTION_TESTS: AtomicU64 = AtomicU64::new(0);
+static NODE_VISITS: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    // Per-thread counts, cheap to bump from the intersection loop
+    static LOCAL_RAYS: Cell<u64> = const { Cell::new(0) };
+    static LOCAL_TESTS: Cell<u64> = const { Cell::new(0) };
+    static LOCAL_NODES: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Ray and intersection counts for a stretch of rendering
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub rays: u64,               // Rays cast against the scene (camera, shadow, reflection, ...)
+    pub intersection_tests: u64, // Ray-object intersection tests
+    pub node_visits: u64,        // Accelerator nodes (BVH nodes, kd-tree cells) rays entered, in meshes' own BVHs too
+}
+
+/// Record one ray cast against the scene and the objects it was tested against
+pub fn count_ray(tests: u64) {
+    LOCAL_RAYS.with(|rays| rays.set(rays.get() + 1));
+    LOCAL_TESTS.with(|count| count.set(count.get() + tests));
+}
+
+/// Record the accelerator nodes one traversal entered
+pub fn count_nodes(nodes: u64) {
+    LOCAL_NODES.with(|count| count.set(count.get() + nodes));
+}
+
+/// Run `f`, returning its result with the rays, tests and node visits it made on this thread
+pub fn count<T>(f: impl FnOnce() -> T) -> (T, Counters) {
+    let local = || Counters {
+        rays: LOCAL_RAYS.with(Cell::get),
+        intersection_tests: LOCAL_TESTS.with(Cell::get),
+        node_visits: LOCAL_NODES.with(Cell::get),
+    };
+    let before = local();
+    let result = f();
+    let after = local();
+    let counters = Counters {
+        rays: after.rays - before.rays,
+        intersection_tests: after.intersection_tests - before.intersection_tests,
+        node_visits: after.node_visits - before.node_visits,
+    };
+    (result, counters)
+}
+
+/// Add this thread's counts to the totals (the renderer does this after each tile)
+pub fn flush() {
+    RAYS.fetch_add(LOCAL_RAYS.with(|rays| rays.replace(0)), Ordering::Relaxed);
+    INTERSECTION_TESTS.fetch_add(LOCAL_TESTS.with(|count| count.replace(0)), Ordering::Relaxed);
+    NODE_VISITS.fetch_add(LOCAL_NODES.with(|count| count.replace(0)), Ordering::Relaxed);
+}
+
+/// Wall-clock timer for log messages; wasm32 has no clock, so there it always reads zero
+pub struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: Instant,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: Instant::now(),
+        }
+    }
+    
+    pub fn elapsed(&self) -> Duration {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.start.elapsed();
+        #[cfg(target_arch = "wasm32")]
+        return Duration::ZERO;
+    }
+}
+
+/// Return the totals and reset them to zero
+pub fn take() -> Counters {
+    flush();
+    Counters {
+        rays: RAYS.swap(0, Ordering::Relaxed),
+        intersection_tests: INTERSECTION_TESTS.swap(0, Ordering::Relaxed),
+        node_visits: NODE_VISITS.swap(0, Ordering::Relaxed),
+    }
+}
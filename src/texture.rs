@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+
+use crate::math::Float;
+use crate::color::Color;
+use crate::image::RgbaImage;
+
+/// How texture coordinates outside [0, 1] are brought back onto the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Wrap {
+    #[default]
+    Repeat, // Tile the image
+    Clamp,  // Stretch the edge texels outward
+    Mirror, // Tile the image, flipping every other copy so edges meet seamlessly
+}
+
+/// How a texture is read between texel centers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Filter {
+    Nearest, // The texel the point falls in (blocky up close)
+    #[default]
+    Bilinear, // Blend of the four nearest texel centers
+}
+
+/// Image texture of linear RGB colors and alpha, looked up by (u, v): u runs across the image
+/// and v up it, both from 0 to `scale`
+/// The image is stored with its mipmaps, prefiltered copies each half the size of the one before, so
+/// distant or grazing surfaces can read one averaged texel instead of aliasing between many
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    pub width: u32,
+    pub height: u32,
+    levels: Vec<Level>, // The image, then its mipmaps down to 1 × 1
+    pub wrap: Wrap,
+    pub filter: Filter,
+    pub scale: Float, // Units of (u, v) one copy of the image covers
+}
+
+/// One resolution of a texture
+#[derive(Debug, Clone, PartialEq)]
+struct Level {
+    width: u32,
+    height: u32,
+    colors: Vec<Color>, // Row-major from the top-left texel
+    alphas: Vec<Float>,
+}
+
+impl Texture {
+    /// Texture of `width` × `height` texels, row-major from the top-left one, repeating and bilinearly filtered
+    pub fn new(width: u32, height: u32, colors: Vec<Color>, alphas: Vec<Float>) -> Self {
+        assert!(width > 0 && height > 0, "textures need at least one texel");
+        assert_eq!(colors.len(), (width * height) as usize);
+        assert_eq!(alphas.len(), colors.len());
+        let mut levels = vec![Level { width, height, colors, alphas }];
+        while let Some(next) = levels[levels.len() - 1].downsample() {
+            levels.push(next);
+        }
+        Self { width, height, levels, wrap: Wrap::default(), filter: Filter::default(), scale: 1.0 }
+    }
+    
+    /// Texture from an 8-bit image; `srgb` decodes colors with the sRGB curve, as color images are
+    /// stored, while data such as heights or opacity is read as is
+    pub fn from_image(image: &RgbaImage, srgb: bool) -> Self {
+        let colors = image
+            .pixels
+            .iter()
+            .map(|&[r, g, b, _]| {
+                if srgb {
+                    Color::from_srgb8([r, g, b])
+                } else {
+                    Color::new(r as Float, g as Float, b as Float) / 255.0
+                }
+            })
+            .collect();
+        let alphas = image.pixels.iter().map(|pixel| pixel[3] as Float / 255.0).collect();
+        Self::new(image.width, image.height, colors, alphas)
+    }
+    
+    /// Opacity map from an 8-bit image, held in the texture's alpha: the image's own alpha, or for an
+    /// image without any transparency its brightness, white opaque and black see-through
+    pub fn opacity_from_image(image: &RgbaImage) -> Self {
+        let texture = Self::from_image(image, false);
+        if image.pixels.iter().any(|pixel| pixel[3] < 255) {
+            return texture;
+        }
+        let alphas = image.pixels.iter().map(|&[r, g, b, _]| (r as Float + g as Float + b as Float) / 765.0).collect();
+        Self::new(image.width, image.height, texture.levels[0].colors.clone(), alphas)
+    }
+    
+    /// The same texture with other wrap and filter modes
+    pub fn with_modes(self, wrap: Wrap, filter: Filter) -> Self {
+        Self { wrap, filter, ..self }
+    }
+    
+    /// The same texture repeating every `scale` units of (u, v)
+    pub fn with_scale(self, scale: Float) -> Self {
+        Self { scale, ..self }
+    }
+    
+    /// Number of mipmap levels, the full-size image included
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+    
+    /// Mipmap level whose texels are `footprint` across in (u, v), the width of a ray's footprint there:
+    /// 0 while it covers at most a texel of the image, one level up each time it doubles
+    pub fn lod(&self, footprint: Float) -> Float {
+        let texels = footprint / self.scale * self.width.max(self.height) as Float;
+        if texels > 1.0 { texels.log2() } else { 0.0 }
+    }
+    
+    /// Color at (u, v) in the full-size image
+    pub fn sample(&self, u: Float, v: Float) -> Color {
+        self.sample_level(0, u, v)
+    }
+    
+    /// Color at (u, v) at mipmap level `lod`: bilinear filtering blends the two nearest levels (trilinear
+    /// filtering), nearest filtering reads the nearest one
+    pub fn sample_lod(&self, u: Float, v: Float, lod: Float) -> Color {
+        let lod = lod.clamp(0.0, (self.levels.len() - 1) as Float);
+        match self.filter {
+            Filter::Nearest => self.sample_level(lod.round() as usize, u, v),
+            Filter::Bilinear => {
+                let (lower, blend) = (lod.floor() as usize, lod.fract());
+                if blend == 0.0 {
+                    return self.sample_level(lower, u, v);
+                }
+                self.sample_level(lower, u, v) * (1.0 - blend) + self.sample_level(lower + 1, u, v) * blend
+            }
+        }
+    }
+    
+    /// Slope of the brightness at (u, v) at mipmap level `lod`, per unit of u and of v, taken across a texel
+    /// of that level on either side
+    pub fn gradient(&self, u: Float, v: Float, lod: Float) -> (Float, Float) {
+        let texel = self.scale * lod.max(0.0).exp2();
+        let (du, dv) = (texel / self.width as Float, texel / self.height as Float);
+        let height = |u: Float, v: Float| {
+            let color = self.sample_lod(u, v, lod);
+            (color.r + color.g + color.b) / 3.0
+        };
+        ((height(u + du, v) - height(u - du, v)) / (2.0 * du), (height(u, v + dv) - height(u, v - dv)) / (2.0 * dv))
+    }
+    
+    /// Alpha at (u, v) in the full-size image
+    pub fn sample_alpha(&self, u: Float, v: Float) -> Float {
+        let level = &self.levels[0];
+        self.taps(level, u, v).iter().map(|&(index, weight)| level.alphas[index] * weight).sum()
+    }
+    
+    fn sample_level(&self, level: usize, u: Float, v: Float) -> Color {
+        let level = &self.levels[level];
+        self.taps(level, u, v).iter().fold(Color::black(), |sum, &(index, weight)| sum + level.colors[index] * weight)
+    }
+    
+    /// Texels of `level` read at (u, v) and their weights, which sum to 1
+    fn taps(&self, level: &Level, u: Float, v: Float) -> [(usize, Float); 4] {
+        // Texel space: x across from the left edge, y down from the top, texel centers at half-integers
+        let (u, v) = (u / self.scale, v / self.scale);
+        let (x, y) = (u * level.width as Float, (1.0 - v) * level.height as Float);
+        let index = |x: Float, y: Float| {
+            let column = wrap(self.wrap, x as i64, level.width);
+            let row = wrap(self.wrap, y as i64, level.height);
+            (row * level.width + column) as usize
+        };
+        match self.filter {
+            Filter::Nearest => [(index(x.floor(), y.floor()), 1.0), (0, 0.0), (0, 0.0), (0, 0.0)],
+            Filter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                [
+                    (index(x0, y0), (1.0 - fx) * (1.0 - fy)),
+                    (index(x0 + 1.0, y0), fx * (1.0 - fy)),
+                    (index(x0, y0 + 1.0), (1.0 - fx) * fy),
+                    (index(x0 + 1.0, y0 + 1.0), fx * fy),
+                ]
+            }
+        }
+    }
+}
+
+impl Level {
+    /// The next mipmap level, half the size (rounded down) with each texel the average of the 2 × 2 it
+    /// covers; an odd last row or column is left out. None once a single texel remains
+    fn downsample(&self) -> Option<Self> {
+        if self.width == 1 && self.height == 1 {
+            return None;
+        }
+        let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let mut colors = Vec::with_capacity((width * height) as usize);
+        let mut alphas = Vec::with_capacity(colors.capacity());
+        for y in 0..height {
+            for x in 0..width {
+                // A side already one texel long is averaged with itself
+                let rows = [2 * y, (2 * y + 1).min(self.height - 1)];
+                let columns = [2 * x, (2 * x + 1).min(self.width - 1)];
+                let (mut color, mut alpha) = (Color::black(), 0.0);
+                for row in rows {
+                    for column in columns {
+                        let index = (row * self.width + column) as usize;
+                        color = color + self.colors[index];
+                        alpha += self.alphas[index];
+                    }
+                }
+                colors.push(color / 4.0);
+                alphas.push(alpha / 4.0);
+            }
+        }
+        Some(Self { width, height, colors, alphas })
+    }
+}
+
+/// Texel index `i` brought into 0..size by `mode`
+fn wrap(mode: Wrap, i: i64, size: u32) -> u32 {
+    let size = size as i64;
+    let wrapped = match mode {
+        Wrap::Repeat => i.rem_euclid(size),
+        Wrap::Clamp => i.clamp(0, size - 1),
+        Wrap::Mirror => {
+            let period = i.rem_euclid(2 * size);
+            if period < size { period } else { 2 * size - 1 - period }
+        }
+    };
+    wrapped as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// 2 × 2 grid of grays: 0 and 1 on the top row, 2 and 3 on the bottom one
+    fn grid() -> Texture {
+        Texture::new(2, 2, (0..4).map(|i| Color::gray(i as Float)).collect(), vec![1.0, 1.0, 0.0, 0.0])
+    }
+    
+    #[test]
+    fn test_bilinear() {
+        let texture = grid();
+        let at = |u: Float, v: Float| texture.sample(u, v).r;
+        
+        // Texel centers read back exactly; v = 1 is the top row
+        assert_eq!([at(0.25, 0.75), at(0.75, 0.75), at(0.25, 0.25), at(0.75, 0.25)], [0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(at(0.5, 0.5), 1.5);
+        assert_eq!(at(0.5, 0.75), 0.5);
+        assert_eq!(texture.sample_alpha(0.25, 0.5), 0.5);
+        
+        // Repeating, the left edge blends with the right-hand column
+        assert_eq!(at(0.0, 0.75), 0.5);
+        
+        let nearest = grid().with_modes(Wrap::Repeat, Filter::Nearest);
+        assert_eq!([nearest.sample(0.49, 0.51).r, nearest.sample(0.51, 0.49).r], [0.0, 3.0]);
+    }
+    
+    #[test]
+    fn test_wrap_modes() {
+        // One row of four texels, 0 to 3, read with nearest filtering past either end
+        let row = |wrap: Wrap| {
+            let texture = Texture::new(4, 1, (0..4).map(|i| Color::gray(i as Float)).collect(), vec![1.0; 4]).with_modes(wrap, Filter::Nearest);
+            (-4..8).map(|i| texture.sample((i as Float + 0.5) / 4.0, 0.5).r as i32).collect::<Vec<_>>()
+        };
+        assert_eq!(row(Wrap::Repeat), [0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3]);
+        assert_eq!(row(Wrap::Clamp), [0, 0, 0, 0, 0, 1, 2, 3, 3, 3, 3, 3]);
+        assert_eq!(row(Wrap::Mirror), [3, 2, 1, 0, 0, 1, 2, 3, 3, 2, 1, 0]);
+        
+        // Clamped, bilinear filtering holds the edge texel instead of blending across
+        let clamped = Texture::new(4, 1, (0..4).map(|i| Color::gray(i as Float)).collect(), vec![1.0; 4]).with_modes(Wrap::Clamp, Filter::Bilinear);
+        assert_eq!(clamped.sample(0.0, 0.5).r, 0.0);
+        assert_eq!(clamped.sample(1.0, 0.5).r, 3.0);
+    }
+    
+    #[test]
+    fn test_mipmaps() {
+        // A 4 × 4 checkerboard of single texels averages to flat gray one level up
+        let checker = (0..16).map(|i| Color::gray(((i % 4 + i / 4) % 2) as Float)).collect();
+        let texture = Texture::new(4, 4, checker, vec![1.0; 16]);
+        assert_eq!(texture.levels(), 3);
+        assert_eq!(texture.sample_lod(0.25, 0.375, 0.0).r, 0.5); // Between two texels
+        assert_eq!(texture.sample_lod(0.125, 0.125, 0.0).r, 1.0);
+        assert_eq!(texture.sample_lod(0.125, 0.125, 1.0).r, 0.5);
+        assert_eq!(texture.sample_lod(0.125, 0.125, 0.5).r, 0.75);
+        assert_eq!(texture.sample_lod(0.125, 0.125, 9.0).r, 0.5);
+        
+        // A footprint of a texel or less reads the image, and each doubling goes a level up
+        assert_eq!(texture.lod(0.1), 0.0);
+        assert_eq!(texture.lod(0.5), 1.0);
+        assert_eq!(texture.lod(1.0), 2.0);
+        
+        // Odd and one-texel-high sizes still shrink to a single texel
+        let strip = Texture::new(5, 1, vec![Color::white(); 5], vec![1.0; 5]);
+        assert_eq!(strip.levels(), 3);
+        assert_eq!(strip.sample_lod(0.5, 0.5, 2.0), Color::white());
+    }
+    
+    #[test]
+    fn test_from_image() {
+        let image = RgbaImage { width: 1, height: 1, pixels: vec![[255, 128, 0, 51]] };
+        let srgb = Texture::from_image(&image, true);
+        assert_eq!(srgb.sample(0.5, 0.5).r, 1.0);
+        assert!((srgb.sample(0.5, 0.5).g - 0.2158).abs() < 1e-3);
+        assert!((Texture::from_image(&image, false).sample(0.5, 0.5).g - 128.0 / 255.0).abs() < 1e-6);
+        assert!((srgb.sample_alpha(0.5, 0.5) - 0.2).abs() < 1e-6);
+        
+        // Opacity maps keep an image's alpha, or take the brightness of one that has none
+        assert!((Texture::opacity_from_image(&image).sample_alpha(0.5, 0.5) - 0.2).abs() < 1e-6);
+        let gray = RgbaImage { width: 2, height: 1, pixels: vec![[255, 255, 255, 255], [51, 51, 51, 255]] };
+        let opacity = Texture::opacity_from_image(&gray).with_modes(Wrap::Repeat, Filter::Nearest).with_scale(2.0);
+        assert_eq!([opacity.sample_alpha(0.5, 0.5), opacity.sample_alpha(1.5, 0.5)], [1.0, 0.2]);
+    }
+}
@@ -0,0 +1,490 @@
+use std::fmt;
+
+use crate::camera::Projection;
+use crate::math::{Float, Vec3};
+use crate::scene_file::{CameraDesc, SceneDesc, ObjectDesc, ShapeDesc, MaterialRef};
+use crate::shapes::CurveBasis;
+
+/// How far a plane normal's length may stray from 1 before it is reported
+const NORMAL_TOLERANCE: f64 = 1e-3;
+
+/// Most subdivision levels a mesh may ask for; each quadruples its triangles
+const MAX_SUBDIVISION: u32 = 8;
+
+/// Most copies an array may make
+const MAX_ARRAY_COPIES: u64 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,   // Would render garbage or fail to build
+    Warning, // Renders, but probably not as intended
+}
+
+/// A problem found in a scene description, located by its field path (e.g. `objects[2].radius`)
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", severity, self.field, self.message)
+    }
+}
+
+/// Collects diagnostics while walking a scene description
+#[derive(Default)]
+struct Checker {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn error(&mut self, field: String, message: String) {
+        self.diagnostics.push(Diagnostic { severity: Severity::Error, field, message });
+    }
+    
+    fn warning(&mut self, field: String, message: String) {
+        self.diagnostics.push(Diagnostic { severity: Severity::Warning, field, message });
+    }
+    
+    /// Report NaN or infinite values; returns whether all values were finite
+    fn finite(&mut self, field: String, values: &[f64]) -> bool {
+        let finite = values.iter().all(|v| v.is_finite());
+        if !finite {
+            self.error(field, format!("contains a non-finite value {:?}", values));
+        }
+        finite
+    }
+    
+    fn positive(&mut self, field: String, what: &str, value: f64) {
+        if self.finite(field.clone(), &[value]) && value <= 0.0 {
+            self.error(field, format!("{} must be positive, got {}", what, value));
+        }
+    }
+    
+    fn color(&mut self, field: String, color: [f64; 3]) {
+        if self.finite(field.clone(), &color) && color.iter().any(|&c| c < 0.0) {
+            self.warning(field, format!("negative color component in {:?}", color));
+        }
+    }
+}
+
+fn vec3(v: [f64; 3]) -> Vec3 {
+    Vec3::new(v[0] as Float, v[1] as Float, v[2] as Float)
+}
+
+/// Check a scene description (with includes resolved) for problems that would otherwise render garbage
+/// Diagnostics are returned in file order; errors and warnings are mixed
+pub fn validate(desc: &SceneDesc) -> Vec<Diagnostic> {
+    let mut checker = Checker::default();
+    
+    match &desc.camera {
+        Some(camera) => check_camera(&mut checker, "camera", camera),
+        None => checker.error("camera".to_string(), "missing (only included files may omit it)".to_string()),
+    }
+    for (name, camera) in &desc.cameras {
+        check_camera(&mut checker, &format!("cameras.{}", name), camera);
+    }
+    
+    if let Some(background) = desc.background {
+        checker.color("background".to_string(), background);
+    }
+    
+    if let Some(environment) = &desc.environment {
+        checker.finite("environment.rotation".to_string(), &[environment.rotation]);
+        if checker.finite("environment.intensity".to_string(), &[environment.intensity]) && environment.intensity < 0.0 {
+            checker.warning("environment.intensity".to_string(), format!("negative intensity {}", environment.intensity));
+        }
+    }
+    
+    if let Some(sky) = &desc.sky {
+        if desc.environment.is_some() {
+            checker.error("sky".to_string(), "can't be combined with an environment map".to_string());
+        }
+        if checker.finite("sky.turbidity".to_string(), &[sky.turbidity]) && !(2.0..=10.0).contains(&sky.turbidity) {
+            checker.warning("sky.turbidity".to_string(), format!("{} is outside the model's range of 2 to 10", sky.turbidity));
+        }
+        let fields = [("elevation", sky.elevation), ("azimuth", sky.azimuth), ("latitude", sky.latitude), ("day", sky.day), ("hour", sky.hour)];
+        for (field, value) in fields {
+            if let Some(value) = value {
+                checker.finite(format!("sky.{}", field), &[value]);
+            }
+        }
+        if sky.hour.is_some() && (sky.elevation.is_some() || sky.azimuth.is_some()) {
+            checker.warning("sky.hour".to_string(), "places the sun; elevation and azimuth are ignored".to_string());
+        }
+        if sky.sun_angles().0 < 0.0 {
+            checker.warning("sky".to_string(), "the sun is below the horizon".to_string());
+        }
+    }
+    
+    for name in desc.materials.keys() {
+        if let Err(err) = desc.resolve_material(&MaterialRef::Named(name.clone())) {
+            checker.error(format!("materials.{}", name), err.to_string());
+        }
+    }
+    
+    for (i, light) in desc.lights.iter().enumerate() {
+        checker.finite(format!("lights[{}].position", i), &light.position);
+        checker.color(format!("lights[{}].color", i), light.color);
+        if checker.finite(format!("lights[{}].intensity", i), &[light.intensity]) && light.intensity < 0.0 {
+            checker.warning(format!("lights[{}].intensity", i), format!("negative intensity {}", light.intensity));
+        }
+        if let Some(radius) = light.radius {
+            if checker.finite(format!("lights[{}].radius", i), &[radius]) && radius < 0.0 {
+                checker.error(format!("lights[{}].radius", i), format!("light radius must not be negative, got {}", radius));
+            }
+        }
+        for (field, power) in [("lumens", light.lumens), ("watts", light.watts)] {
+            if let Some(power) = power {
+                if checker.finite(format!("lights[{}].{}", i, field), &[power]) && power < 0.0 {
+                    checker.warning(format!("lights[{}].{}", i, field), format!("negative power {}", power));
+                }
+            }
+        }
+        if light.lumens.is_some() && light.watts.is_some() {
+            checker.warning(format!("lights[{}].watts", i), "ignored in favor of lumens".to_string());
+        }
+        if let Some(kelvin) = light.temperature {
+            if checker.finite(format!("lights[{}].temperature", i), &[kelvin]) && !(1000.0..=40000.0).contains(&kelvin) {
+                checker.warning(format!("lights[{}].temperature", i), format!("{} K is outside 1000–40000 K and will be clamped", kelvin));
+            }
+        }
+        for (field, names) in [("illuminates", &light.illuminates), ("ignores", &light.ignores)] {
+            for name in names.iter().filter(|name| desc.objects_named(name).is_err()) {
+                checker.error(format!("lights[{}].{}", i, field), format!("no object named '{}'", name));
+            }
+        }
+    }
+    if desc.lights.is_empty() && desc.environment.is_none() && desc.sky.is_none() {
+        checker.warning("lights".to_string(), "no lights; only ambient light will be visible".to_string());
+    }
+    
+    for (i, object) in desc.objects.iter().enumerate() {
+        check_object(&mut checker, desc, &format!("objects[{}]", i), object, false);
+    }
+    for (name, objects) in &desc.geometry {
+        for (i, object) in objects.iter().enumerate() {
+            check_object(&mut checker, desc, &format!("geometry.{}[{}]", name, i), object, true);
+        }
+    }
+    
+    checker.diagnostics
+}
+
+/// Check a camera's view, field of view, roll, shift, clip distances and exposure; `path` is where it is in the file
+fn check_camera(checker: &mut Checker, path: &str, camera: &CameraDesc) {
+    let field = |name: &str| format!("{}.{}", path, name);
+    
+    let finite = checker.finite(field("look_from"), &camera.look_from)
+        & checker.finite(field("look_at"), &camera.look_at)
+        & checker.finite(field("up"), &camera.up);
+    
+    let view = vec3(camera.look_at) - vec3(camera.look_from);
+    if finite && view.length() == 0.0 {
+        checker.error(field("look_at"), "same as look_from (zero-length view vector)".to_string());
+    } else if finite && vec3(camera.up).cross(&view).length() == 0.0 {
+        checker.error(field("up"), "zero or parallel to the view direction".to_string());
+    }
+    
+    // Only a perspective's field of view is held below 180° (a panorama sees everything whatever it is)
+    if let Some(fov) = camera.fov {
+        let max = if camera.projection == Some(Projection::Fisheye) { 360.0 } else { 180.0 };
+        let finite = checker.finite(field("fov"), &[fov]);
+        if finite && camera.projection != Some(Projection::Panorama) && (fov <= 0.0 || fov >= max) {
+            checker.error(field("fov"), format!("must be between 0 and {} degrees, got {}", max, fov));
+        }
+    }
+    if let Some(roll) = camera.roll {
+        checker.finite(field("roll"), &[roll]);
+    }
+    if let Some(shift) = camera.shift {
+        if checker.finite(field("shift"), &shift) && shift.iter().any(|offset| offset.abs() > 0.5) {
+            checker.warning(field("shift"), "more than half the image; look_at is out of view".to_string());
+        }
+    }
+    if let Some(near) = camera.near {
+        if checker.finite(field("near"), &[near]) && near < 0.0 {
+            checker.error(field("near"), format!("must not be negative, got {}", near));
+        }
+    }
+    if let Some(far) = camera.far {
+        let near = camera.near.unwrap_or(0.0);
+        if far <= near {
+            checker.error(field("far"), format!("must be beyond near ({}), got {}", near, far));
+        }
+    }
+    for (name, value) in [("iso", camera.iso), ("shutter", camera.shutter), ("aperture", camera.aperture)] {
+        if let Some(value) = value {
+            checker.positive(field(name), name, value);
+        }
+    }
+}
+
+/// Check one object, reporting problems under `path` (e.g. `objects[2]`)
+fn check_object(checker: &mut Checker, desc: &SceneDesc, path: &str, object: &ObjectDesc, in_geometry: bool) {
+    let field = |name: &str| format!("{}.{}", path, name);
+    
+    match object.shape {
+        ShapeDesc::Sphere { center, radius } => {
+            checker.finite(field("center"), &center);
+            checker.positive(field("radius"), "sphere radius", radius);
+        }
+        ShapeDesc::Cube { min, max } | ShapeDesc::Heightfield { min, max, .. } => {
+            if checker.finite(field("min"), &min) & checker.finite(field("max"), &max) {
+                for (axis, name) in ["x", "y", "z"].iter().enumerate() {
+                    if min[axis] >= max[axis] {
+                        let shape = object.shape.type_name();
+                        checker.error(
+                            field("max"),
+                            format!("{} max.{} ({}) must be greater than min.{} ({})", shape, name, max[axis], name, min[axis]),
+                        );
+                    }
+                }
+            }
+        }
+        ShapeDesc::Plane { point, normal } => {
+            checker.finite(field("point"), &point);
+            if checker.finite(field("normal"), &normal) {
+                let length = normal.iter().map(|c| c * c).sum::<f64>().sqrt();
+                if length == 0.0 {
+                    checker.error(field("normal"), "zero-length plane normal".to_string());
+                } else if (length - 1.0).abs() > NORMAL_TOLERANCE {
+                    checker.warning(field("normal"), format!("not normalized (length {:.3}); it will be normalized", length));
+                }
+            }
+        }
+        ShapeDesc::Cylinder { center, radius, height } => {
+            checker.finite(field("center"), &center);
+            checker.positive(field("radius"), "cylinder radius", radius);
+            checker.positive(field("height"), "cylinder height", height);
+        }
+        ShapeDesc::Voxels { min, voxel_size, .. } => {
+            checker.finite(field("min"), &min);
+            checker.positive(field("voxel_size"), "voxel size", voxel_size);
+        }
+        ShapeDesc::Points { radius, translate, scale, .. } => {
+            checker.positive(field("radius"), "point radius", radius);
+            checker.finite(field("translate"), &translate);
+            checker.positive(field("scale"), "point cloud scale", scale);
+        }
+        ShapeDesc::Mesh { translate, scale, subdivision, .. } => {
+            checker.finite(field("translate"), &translate);
+            checker.positive(field("scale"), "mesh scale", scale);
+            match subdivision {
+                Some(levels @ 5..=MAX_SUBDIVISION) => {
+                    checker.warning(field("subdivision"), format!("{} levels make {} times the triangles", levels, 4u64.pow(levels)));
+                }
+                Some(levels @ 5..) => checker.error(
+                    field("subdivision"),
+                    format!("{} levels would make {} times the triangles; the most is {}", levels, 4u64.saturating_pow(levels), MAX_SUBDIVISION),
+                ),
+                _ => {}
+            }
+        }
+        ShapeDesc::Gltf { translate, scale, .. } => {
+            checker.finite(field("translate"), &translate);
+            checker.positive(field("scale"), "glTF scale", scale);
+        }
+        ShapeDesc::Capsule { start, end, radius } => {
+            checker.finite(field("start"), &start);
+            checker.finite(field("end"), &end);
+            checker.positive(field("radius"), "capsule radius", radius);
+        }
+        ShapeDesc::Curve { ref points, radius, tip_radius, basis } => {
+            for (index, point) in points.iter().enumerate() {
+                checker.finite(field(&format!("points[{}]", index)), point);
+            }
+            if !basis.accepts(points.len()) {
+                let needed = match basis {
+                    CurveBasis::CatmullRom => "at least 2",
+                    CurveBasis::Bezier => "4, 7, 10 or another 3n + 1",
+                };
+                checker.error(field("points"), format!("{:?} curves need {} points, got {}", basis, needed, points.len()));
+            }
+            checker.positive(field("radius"), "curve radius", radius);
+            if let Some(tip_radius) = tip_radius.filter(|&r| !(r >= 0.0 && r.is_finite())) {
+                checker.error(field("tip_radius"), format!("curve tip radius must be zero or more, got {}", tip_radius));
+            }
+        }
+        ShapeDesc::Instance { ref geometry, translate, rotate, scale, ref overrides, tint } => {
+            if in_geometry {
+                checker.error(field("type"), "instances can't be nested inside geometry".to_string());
+            } else if !desc.geometry.contains_key(geometry) {
+                checker.error(field("geometry"), format!("unknown geometry '{}'", geometry));
+            }
+            checker.finite(field("translate"), &translate);
+            checker.finite(field("rotate"), &rotate);
+            if checker.finite(field("scale"), &scale) && scale.iter().any(|&s| s <= 0.0) {
+                checker.error(field("scale"), format!("instance scale must be positive, got {:?}", scale));
+            }
+            for (name, material) in overrides {
+                check_material(checker, desc, &field(&format!("overrides.{}", name)), material);
+            }
+            if !overrides.is_empty() && object.material.is_some() {
+                checker.warning(field("overrides"), "ignored, as the instance's material replaces all of its geometry's".to_string());
+            }
+            if let Some(tint) = tint {
+                if checker.finite(field("tint"), &tint) && tint.iter().any(|&c| c < 0.0) {
+                    checker.error(field("tint"), format!("tint must not be negative, got {:?}", tint));
+                }
+            }
+        }
+        ShapeDesc::Group { ref children, translate, rotate, scale } => {
+            checker.finite(field("translate"), &translate);
+            checker.finite(field("rotate"), &rotate);
+            if checker.finite(field("scale"), &scale) && scale.iter().any(|&s| s <= 0.0) {
+                checker.error(field("scale"), format!("group scale must be positive, got {:?}", scale));
+            }
+            if children.is_empty() {
+                checker.warning(field("children"), "empty group".to_string());
+            }
+            for (i, child) in children.iter().enumerate() {
+                check_part(checker, desc, &field(&format!("children[{}]", i)), child, in_geometry);
+            }
+        }
+        ShapeDesc::Array { ref object, count, spacing, translate, scale, turn, ref jitter, ref materials, .. } => {
+            let copies = count.iter().try_fold(1u64, |copies, &n| copies.checked_mul(n as u64));
+            if copies == Some(0) {
+                checker.error(field("count"), format!("array counts must be at least 1, got {:?}", count));
+            } else if copies.is_none_or(|copies| copies > MAX_ARRAY_COPIES) {
+                checker.error(field("count"), format!("{:?} makes more than the {} copies an array may have", count, MAX_ARRAY_COPIES));
+            }
+            checker.finite(field("spacing"), &spacing);
+            checker.finite(field("translate"), &translate);
+            checker.positive(field("scale"), "array scale", scale);
+            checker.finite(field("turn"), &turn);
+            if let Some(jitter) = jitter {
+                checker.finite(field("jitter.translate"), &jitter.translate);
+                checker.finite(field("jitter.rotate"), &jitter.rotate);
+                if !(0.0..1.0).contains(&jitter.scale) {
+                    checker.error(field("jitter.scale"), format!("scale jitter must be at least 0 and below 1, got {}", jitter.scale));
+                }
+            }
+            for (i, material) in materials.iter().enumerate() {
+                check_material(checker, desc, &field(&format!("materials[{}]", i)), material);
+            }
+            check_part(checker, desc, &field("object"), object, in_geometry);
+        }
+    }
+    
+    if let Some(material) = &object.material {
+        check_material(checker, desc, &field("material"), material);
+    }
+}
+
+/// Check an object placed by a group or array, whose flags its placer's override
+fn check_part(checker: &mut Checker, desc: &SceneDesc, path: &str, object: &ObjectDesc, in_geometry: bool) {
+    check_object(checker, desc, path, object, in_geometry);
+    let flags = [object.cast_shadows, object.visible_to_camera, object.visible_in_reflections];
+    if flags.iter().any(Option::is_some) || object.priority.is_some() {
+        checker.warning(path.to_string(), "shadow, visibility and priority settings apply to whole groups and arrays; set them there".to_string());
+    }
+}
+
+/// Check an object's material reference; named table materials were already reported above
+fn check_material(checker: &mut Checker, desc: &SceneDesc, path: &str, material: &MaterialRef) {
+    let reported = matches!(material, MaterialRef::Named(name) if desc.materials.contains_key(name));
+    if !reported {
+        if let Err(err) = desc.resolve_material(material) {
+            checker.error(path.to_string(), err.to_string());
+        }
+    }
+}
+
+/// Whether any diagnostic is an error
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_valid_scene() {
+        let desc = SceneDesc::from_json(include_str!("../scenes/showcase.json")).unwrap();
+        assert!(validate(&desc).is_empty());
+    }
+    
+    #[test]
+    fn test_diagnostics() {
+        let desc = SceneDesc::from_json(r#"{
+            "camera": { "look_from": [0, 0, 0], "look_at": [0, 0, 0], "shift": [0, 0.8] },
+            "cameras": { "wide": { "look_from": [0, 0, 0], "look_at": [0, 0, -1], "fov": 200, "far": 0 } },
+            "materials": { "loop": { "base": "loop2" }, "loop2": { "base": "loop" } },
+            "lights": [{ "position": [0, 5, 0], "ignores": ["ceiling"] }],
+            "objects": [
+                { "type": "sphere", "center": [0, 0, -3], "radius": 0 },
+                { "type": "cube", "min": [1, 1, 1], "max": [0, 2, 2], "material": "unobtainium" },
+                { "type": "plane", "point": [0, -1, 0], "normal": [0, 2, 0] },
+                { "type": "instance", "geometry": "tree", "scale": [1, 0, 1] },
+                { "type": "instance", "geometry": "shrub" },
+                { "type": "group", "children": [{ "type": "sphere", "center": [0, 0, 0], "radius": -1, "cast_shadows": false }] },
+                { "type": "array", "count": [4, 0, 1], "materials": ["unobtainium"], "object": { "type": "sphere", "center": [0, 0, 0], "radius": 1 } }
+            ],
+            "geometry": { "tree": [{ "type": "instance", "geometry": "tree" }] }
+        }"#).unwrap();
+        
+        let diagnostics = validate(&desc);
+        let fields: Vec<String> = diagnostics.iter().map(|d| format!("{:?} {}", d.severity, d.field)).collect();
+        assert_eq!(fields, vec![
+            "Error camera.look_at",
+            "Warning camera.shift",
+            "Error cameras.wide.fov",
+            "Error cameras.wide.far",
+            "Error materials.loop",
+            "Error materials.loop2",
+            "Error lights[0].ignores",
+            "Error objects[0].radius",
+            "Error objects[1].max",
+            "Error objects[1].material",
+            "Warning objects[2].normal",
+            "Error objects[3].scale",
+            "Error objects[4].geometry",
+            "Error objects[5].children[0].radius",
+            "Warning objects[5].children[0]",
+            "Error objects[6].count",
+            "Error objects[6].materials[0]",
+            "Error geometry.tree[0].type",
+        ]);
+        assert!(has_errors(&diagnostics));
+    }
+    
+    #[test]
+    fn test_array_copies() {
+        let copies = |count: &str| {
+            let json = format!(r#"{{
+                "camera": {{ "look_from": [0, 0, 0], "look_at": [0, 0, -1] }},
+                "lights": [{{ "position": [0, 5, 0] }}],
+                "objects": [{{ "type": "array", "count": {}, "object": {{ "type": "sphere", "center": [0, 0, 0], "radius": 1 }} }}]
+            }}"#, count);
+            validate(&SceneDesc::from_json(&json).unwrap()).into_iter().map(|d| d.severity).collect::<Vec<_>>()
+        };
+        assert_eq!(copies("[100, 100, 100]"), vec![]);
+        assert_eq!(copies("[100, 100, 101]"), vec![Severity::Error]);
+        assert_eq!(copies("[4294967295, 4294967295, 4294967295]"), vec![Severity::Error]); // Past where the product fits in a u64
+    }
+    
+    #[test]
+    fn test_subdivision_levels() {
+        let levels = |subdivision: u32| {
+            let json = format!(r#"{{
+                "camera": {{ "look_from": [0, 0, 0], "look_at": [0, 0, -1] }},
+                "lights": [{{ "position": [0, 5, 0] }}],
+                "objects": [{{ "type": "mesh", "path": "bunny.obj", "subdivision": {} }}]
+            }}"#, subdivision);
+            validate(&SceneDesc::from_json(&json).unwrap()).into_iter().map(|d| d.severity).collect::<Vec<_>>()
+        };
+        assert_eq!(levels(4), vec![]);
+        assert_eq!(levels(8), vec![Severity::Warning]);
+        assert_eq!(levels(9), vec![Severity::Error]);
+        assert_eq!(levels(40), vec![Severity::Error]); // Past where 4^levels fits in a u64
+    }
+}
@@ -0,0 +1,130 @@
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::io;
+
+#[derive(Debug, thiserror::Error)]
+pub enum VoxError {
+    #[error("could not read VOX file: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid VOX file: {0}")]
+    Invalid(String),
+}
+
+/// Voxel model from a MagicaVoxel `.vox` file: the first model in it, with the file's palette
+/// Coordinates are the file's own, z up
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxModel {
+    pub size: [u32; 3],             // Grid dimensions along x, y and z
+    pub voxels: Vec<([u8; 3], u8)>, // Position and color index (1 to 255) of each filled voxel
+    pub palette: [[u8; 4]; 256],    // sRGB colors with alpha, by color index (entry 0 is unused)
+}
+
+impl VoxModel {
+    /// Parse the bytes of a `.vox` file (RIFF-style chunks: MAIN holding SIZE, XYZI and RGBA)
+    pub fn parse(bytes: &[u8]) -> Result<Self, VoxError> {
+        let invalid = |reason: &str| VoxError::Invalid(reason.to_string());
+        if bytes.len() < 8 || &bytes[..4] != b"VOX " {
+            return Err(invalid("missing VOX header"));
+        }
+        let word = |bytes: &[u8], at: usize| -> Result<u32, VoxError> {
+            let word = bytes.get(at..at + 4).ok_or_else(|| invalid("truncated chunk"))?;
+            Ok(u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        };
+        
+        // Chunks are id, content size, children size, content; MAIN's children follow its (empty) content
+        let (mut size, mut voxels, mut palette) = (None, None, None);
+        let mut at = 8;
+        while at < bytes.len() {
+            let id = bytes.get(at..at + 4).ok_or_else(|| invalid("truncated chunk"))?;
+            let (content_size, children_size) = (word(bytes, at + 4)? as usize, word(bytes, at + 8)? as usize);
+            let content = bytes.get(at + 12..at + 12 + content_size).ok_or_else(|| invalid("truncated chunk"))?;
+            match id {
+                b"MAIN" => {
+                    at += 12 + content_size; // Step into the children
+                    continue;
+                }
+                b"SIZE" if size.is_none() => size = Some([word(content, 0)?, word(content, 4)?, word(content, 8)?]),
+                b"XYZI" if voxels.is_none() => {
+                    let count = word(content, 0)? as usize;
+                    let data = content.get(4..4 + 4 * count).ok_or_else(|| invalid("truncated voxel data"))?;
+                    voxels = Some(data.chunks_exact(4).map(|voxel| ([voxel[0], voxel[1], voxel[2]], voxel[3])).collect::<Vec<_>>());
+                }
+                b"RGBA" => {
+                    // Entry i of the chunk is color index i + 1
+                    let mut colors = [[0; 4]; 256];
+                    for (index, color) in content.chunks_exact(4).take(255).enumerate() {
+                        colors[index + 1] = [color[0], color[1], color[2], color[3]];
+                    }
+                    palette = Some(colors);
+                }
+                _ => {} // Further models, the scene graph, materials and layers
+            }
+            at += 12 + content_size + children_size;
+        }
+        
+        let size = size.ok_or_else(|| invalid("no SIZE chunk"))?;
+        let voxels = voxels.ok_or_else(|| invalid("no XYZI chunk"))?;
+        if let Some((position, _)) = voxels.iter().find(|(position, _)| (0..3).any(|axis| position[axis] as u32 >= size[axis])) {
+            return Err(VoxError::Invalid(format!("voxel at {:?} lies outside the {:?} model", position, size)));
+        }
+        Ok(Self { size, voxels, palette: palette.unwrap_or_else(default_palette) })
+    }
+    
+    /// Read and parse a `.vox` file
+    #[cfg(feature = "fs")]
+    pub fn load(path: &Path) -> Result<Self, VoxError> {
+        Self::parse(&std::fs::read(path)?)
+    }
+}
+
+/// MagicaVoxel's palette for files without an RGBA chunk: a 6 × 6 × 6 color cube from white down
+/// (black left out), then ramps of red, green, blue and gray
+fn default_palette() -> [[u8; 4]; 256] {
+    const CUBE: [u8; 6] = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    const RAMP: [u8; 10] = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+    let cube = CUBE.iter().flat_map(|&r| CUBE.iter().flat_map(move |&g| CUBE.iter().map(move |&b| [r, g, b, 0xff])));
+    let ramps = (0..4).flat_map(|ramp| {
+        RAMP.iter().map(move |&level| match ramp {
+            0 => [level, 0, 0, 0xff],
+            1 => [0, level, 0, 0xff],
+            2 => [0, 0, level, 0xff],
+            _ => [level, level, level, 0xff],
+        })
+    });
+    let mut palette = [[0; 4]; 256];
+    for (index, color) in cube.take(215).chain(ramps).enumerate() {
+        palette[index + 1] = color;
+    }
+    palette
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// A `.vox` file of the given chunks inside MAIN
+    fn vox_file(chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let children: Vec<u8> = chunks
+            .iter()
+            .flat_map(|(id, content)| [id.as_slice(), &(content.len() as u32).to_le_bytes(), &0u32.to_le_bytes(), content].concat())
+            .collect();
+        [b"VOX ".as_slice(), &150u32.to_le_bytes(), b"MAIN", &0u32.to_le_bytes(), &(children.len() as u32).to_le_bytes(), &children].concat()
+    }
+    
+    #[test]
+    fn test_parse() {
+        let size: Vec<u8> = [2u32, 3, 4].iter().flat_map(|n| n.to_le_bytes()).collect();
+        let voxels = [1u32.to_le_bytes(), [1, 2, 3, 7]].concat();
+        let model = VoxModel::parse(&vox_file(&[(b"SIZE", size.clone()), (b"XYZI", voxels.clone())])).unwrap();
+        assert_eq!(model.size, [2, 3, 4]);
+        assert_eq!(model.voxels, vec![([1, 2, 3], 7)]);
+        assert_eq!(model.palette[1], [0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(model.palette[255], [0x11, 0x11, 0x11, 0xff]);
+        
+        let mut rgba = vec![0; 1024];
+        rgba[24..28].copy_from_slice(&[10, 20, 30, 255]);
+        let model = VoxModel::parse(&vox_file(&[(b"SIZE", size), (b"XYZI", voxels), (b"RGBA", rgba)])).unwrap();
+        assert_eq!(model.palette[7], [10, 20, 30, 255]);
+        assert!(matches!(VoxModel::parse(b"VOX \x96\0\0\0"), Err(VoxError::Invalid(_))));
+    }
+}
@@ -0,0 +1,60 @@
+use crate::render::Renderer;
+use crate::scene_file::{SceneDesc, SceneError, DEFAULT_FOV};
+use crate::validate::{self, Severity};
+
+/// Render a JSON scene to 8-bit RGBA bytes (straight alpha, row-major from the top-left pixel)
+/// Nothing is read from disk, so scenes with `include`s must be flattened first with `rt export`; scenes
+/// `validate` finds errors in are refused
+pub fn render_to_rgba(scene_json: &str, width: u32, height: u32) -> Result<Vec<u8>, SceneError> {
+    let desc = SceneDesc::from_json(scene_json)?;
+    if !desc.include.is_empty() {
+        return Err(SceneError::IncludesUnavailable);
+    }
+    if let Some(error) = validate::validate(&desc).into_iter().find(|d| d.severity == Severity::Error) {
+        return Err(SceneError::Invalid(error));
+    }
+    if width == 0 || height == 0 {
+        return Ok(Vec::new());
+    }
+    
+    let (scene, camera) = desc.build(1.0, DEFAULT_FOV, width as f64 / height as f64)?;
+    Ok(Renderer::new().render_framebuffer(&scene, &camera, width, height).to_rgba8())
+}
+
+/// JavaScript exports, built with `--target wasm32-unknown-unknown`
+#[cfg(target_arch = "wasm32")]
+mod bindings {
+    use wasm_bindgen::prelude::*;
+    
+    /// `render_to_rgba` for the browser; scene errors are thrown as JavaScript errors
+    #[wasm_bindgen(js_name = render_to_rgba)]
+    pub fn render_to_rgba(scene_json: &str, width: u32, height: u32) -> Result<Vec<u8>, JsError> {
+        super::render_to_rgba(scene_json, width, height).map_err(|err| JsError::new(&err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_render_to_rgba() {
+        let json = r#"{
+            "camera": { "look_from": [0, 0, 0], "look_at": [0, 0, -1] },
+            "background": [0, 0, 1],
+            "lights": [{ "position": [0, 5, 0] }],
+            "objects": [{ "type": "sphere", "center": [0, 0, -3], "radius": 1, "material": "red" }]
+        }"#;
+        let pixels = render_to_rgba(json, 8, 6).unwrap();
+        assert_eq!(pixels.len(), 8 * 6 * 4);
+        assert_eq!(&pixels[0..4], &[0, 0, 255, 255]); // Corner sees the background
+        
+        let center = (3 * 8 + 4) * 4;
+        assert!(pixels[center] > pixels[center + 1] && pixels[center + 3] == 255); // Red sphere
+        
+        let include = r#"{ "include": [{ "path": "parts/pedestal.json" }] }"#;
+        assert!(matches!(render_to_rgba(include, 8, 6), Err(SceneError::IncludesUnavailable)));
+        let invalid = json.replace(r#""radius": 1"#, r#""radius": -1"#);
+        assert!(matches!(render_to_rgba(&invalid, 8, 6), Err(SceneError::Invalid(error)) if error.field == "objects[0].radius"));
+    }
+}